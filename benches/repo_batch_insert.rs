@@ -0,0 +1,125 @@
+//! Batch insert throughput: multi-row `VALUES` statements vs one statement per row.
+//!
+//! Both variants run inside a single transaction on a fresh database, so the difference is
+//! the per-statement overhead the batch methods used to pay.
+//!
+//! ```bash
+//! cargo bench --bench repo_batch_insert
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::Repository;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+fn make_fills(n: i64) -> Vec<Fill> {
+    let user = Address::new("0x1111111111111111111111111111111111111111".to_string());
+    let coin = Coin::new("BTC".to_string());
+    (1..=n)
+        .map(|i| {
+            Fill::new(
+                TimeMs::new(1_700_000_000_000 + i),
+                user.clone(),
+                coin.clone(),
+                if i % 2 == 0 { Side::Sell } else { Side::Buy },
+                Decimal::from_str("50000.5").unwrap(),
+                Decimal::from_str("0.01").unwrap(),
+                Decimal::from_str("0.25").unwrap(),
+                Decimal::from_str("0").unwrap(),
+                None,
+                Some(i),
+                Some(i),
+            )
+        })
+        .collect()
+}
+
+async fn fresh_db() -> (SqlitePool, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("bench.db");
+    let pool = init_db(path.to_str().unwrap()).await.unwrap();
+    (pool, temp_dir)
+}
+
+/// The previous `insert_fills_batch`: one `INSERT` per fill in a transaction.
+async fn insert_row_by_row(pool: &SqlitePool, fills: &[Fill]) -> usize {
+    let mut tx = pool.begin().await.unwrap();
+    let mut inserted = 0;
+    for fill in fills {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO raw_fills (
+                user, coin, time_ms, side, px, sz, fee, closed_pnl,
+                builder_fee, tid, oid, fill_key, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(fill_key) DO NOTHING
+            "#,
+        )
+        .bind(fill.user.as_str())
+        .bind(fill.coin.as_str())
+        .bind(fill.time_ms.as_i64())
+        .bind(fill.side.to_string())
+        .bind(fill.px.to_canonical_string())
+        .bind(fill.sz.to_canonical_string())
+        .bind(fill.fee.to_canonical_string())
+        .bind(fill.closed_pnl.to_canonical_string())
+        .bind(fill.builder_fee.map(|d| d.to_canonical_string()))
+        .bind(fill.tid)
+        .bind(fill.oid)
+        .bind(fill.fill_key.as_str())
+        .bind(0i64)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        inserted += result.rows_affected() as usize;
+    }
+    tx.commit().await.unwrap();
+    inserted
+}
+
+fn bench_insert_fills(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("insert_fills");
+    group.sample_size(10);
+
+    for n in [1_000i64, 10_000, 100_000] {
+        let fills = make_fills(n);
+        group.throughput(Throughput::Elements(n as u64));
+
+        group.bench_with_input(BenchmarkId::new("row_by_row", n), &fills, |b, fills| {
+            b.to_async(&rt).iter_custom(|iters| async move {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let (pool, _temp) = fresh_db().await;
+                    let start = Instant::now();
+                    assert_eq!(insert_row_by_row(&pool, fills).await, fills.len());
+                    total += start.elapsed();
+                }
+                total
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("multi_row", n), &fills, |b, fills| {
+            b.to_async(&rt).iter_custom(|iters| async move {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let (pool, _temp) = fresh_db().await;
+                    let repo = Repository::new(pool);
+                    let start = Instant::now();
+                    assert_eq!(repo.insert_fills_batch(fills).await.unwrap(), fills.len());
+                    total += start.elapsed();
+                }
+                total
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert_fills);
+criterion_main!(benches);
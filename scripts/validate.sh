@@ -55,7 +55,7 @@ show_help() {
     echo "  DATABASE_PATH     SQLite database path"
     echo "  HYPERLIQUID_API_URL  Hyperliquid API URL"
     echo "  TARGET_BUILDER    Builder address for attribution"
-    echo "  PNL_MODE          gross or net (default: gross)"
+    echo "  PNL_MODE          gross, netFees, or netAllFees (default: gross)"
 }
 
 while [[ $# -gt 0 ]]; do
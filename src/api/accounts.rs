@@ -0,0 +1,70 @@
+//! Named accounts: several wallet addresses reported as one.
+//!
+//! Accounts are managed under `/v1/admin/accounts`; per-user endpoints that accept
+//! `account` in place of `user` aggregate across every member address, and
+//! `includeSubAccounts=true` adds the sub-accounts the data source reports for each.
+
+use super::validate::parse_address;
+use super::AppState;
+use crate::domain::Address;
+use crate::error::AppError;
+
+/// Who a query is about.
+#[derive(Debug, Clone)]
+pub(crate) enum Subject {
+    User(Address),
+    /// Member addresses of a named account, sorted.
+    Account(Vec<Address>),
+}
+
+impl Subject {
+    pub(crate) fn members(&self) -> &[Address] {
+        match self {
+            Subject::User(user) => std::slice::from_ref(user),
+            Subject::Account(members) => members,
+        }
+    }
+}
+
+/// Resolve the `user` / `account` query parameters, exactly one of which must be given.
+///
+/// With `include_sub_accounts`, the sub-accounts of every resolved address join it, and the
+/// result is reported as an account so each row names the address it came from.
+pub(crate) async fn resolve_subject(
+    state: &AppState,
+    user: Option<&str>,
+    account: Option<&str>,
+    include_sub_accounts: bool,
+) -> Result<Subject, AppError> {
+    let subject = match (user, account) {
+        (Some(user), None) => Subject::User(parse_address("user", user)?),
+        (None, Some(account)) => {
+            let members = state.repo.query_account_group(account.trim()).await?;
+            if members.is_empty() {
+                return Err(AppError::NotFound(format!("Unknown account '{}'", account)));
+            }
+            Subject::Account(members)
+        }
+        _ => {
+            return Err(AppError::BadRequest(
+                "exactly one of user or account is required".to_string(),
+            ))
+        }
+    };
+    if !include_sub_accounts {
+        return Ok(subject);
+    }
+    if !state.config.load().sub_account_rollup {
+        return Err(AppError::BadRequest(
+            "includeSubAccounts requires SUB_ACCOUNT_ROLLUP to be enabled".to_string(),
+        ));
+    }
+
+    let mut members = subject.members().to_vec();
+    for parent in subject.members() {
+        members.extend(state.orchestrator.sub_accounts(parent).await?);
+    }
+    members.sort();
+    members.dedup();
+    Ok(Subject::Account(members))
+}
@@ -0,0 +1,98 @@
+//! `X-Address-Format` response option.
+//!
+//! Addresses are stored and returned lowercase (see [`Address`]). A client that sends
+//! `X-Address-Format: checksummed` gets every address in a JSON response in its EIP-55
+//! spelling instead. The rewrite happens after the handler and the response cache, so both
+//! spellings are served from the same cached response.
+
+use crate::domain::Address;
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderName, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+pub static ADDRESS_FORMAT_HEADER: HeaderName = HeaderName::from_static("x-address-format");
+
+/// Length of a JSON string holding an address, quotes included.
+const QUOTED_ADDRESS_LEN: usize = 44;
+
+pub async fn address_format(request: Request, next: Next) -> Response {
+    let checksummed = request
+        .headers()
+        .get(&ADDRESS_FORMAT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("checksummed"));
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !checksummed || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to read response body");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(checksum_json_addresses(&body)))
+}
+
+/// Replace every JSON string that is exactly a lowercase address with its EIP-55 spelling.
+///
+/// An opening quote is never escaped, so a quote preceded by a backslash is part of a
+/// longer string and is left alone.
+fn checksum_json_addresses(json: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(json.len());
+    let mut i = 0;
+    while i < json.len() {
+        let candidate = json.get(i..i + QUOTED_ADDRESS_LEN).filter(|s| {
+            s[0] == b'"'
+                && s[QUOTED_ADDRESS_LEN - 1] == b'"'
+                && s[1..3] == *b"0x"
+                && s[3..QUOTED_ADDRESS_LEN - 1]
+                    .iter()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                && (i == 0 || json[i - 1] != b'\\')
+        });
+        match candidate {
+            Some(quoted) => {
+                let address = Address::new(
+                    String::from_utf8_lossy(&quoted[1..QUOTED_ADDRESS_LEN - 1]).into_owned(),
+                );
+                out.push(b'"');
+                out.extend_from_slice(address.to_checksummed().as_bytes());
+                out.push(b'"');
+                i += QUOTED_ADDRESS_LEN;
+            }
+            None => {
+                out.push(json[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_json_addresses() {
+        let json = br#"{"user":"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed","txHash":"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00","note":"\"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\""}"#;
+        let out = checksum_json_addresses(json);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"user":"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed","txHash":"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed00","note":"\"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\""}"#
+        );
+    }
+}
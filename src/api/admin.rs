@@ -0,0 +1,1541 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use super::builder_logs::parse_day;
+use super::competitions::CompetitionDto;
+use super::leaderboard::METRIC_ERROR;
+use super::validate::{
+    parse_address, parse_coin, Pagination, TimeRange, ValidQuery, ValidatedAddress,
+};
+use super::AppState;
+use crate::client::LeaderboardMetric;
+use crate::compile::{Compiler, TableDiff};
+use crate::config::Config;
+use crate::datasource::{BuilderLogsDownloadStats, BuilderLogsFetcher};
+use crate::db::maintenance::{MaintenanceError, MaintenanceOutcome};
+use crate::db::pool::PoolStats;
+use crate::db::repo::Competition;
+use crate::db::{Maintenance, MaintenanceTask, COMPILE_SCHEMA_VERSION};
+use crate::domain::{Address, Coin, Decimal, TimeMs};
+use crate::engine::{CoinAdjustment, EffectType, LifecycleTag, SizeRebase, StrategyRule};
+use crate::error::AppError;
+use crate::orchestration::builder_logs_backfill::{
+    days_in_range, MAX_BACKFILL_BUILDERS, MAX_BACKFILL_DAYS,
+};
+use crate::telemetry::{LogFilterError, LogFilterHandle};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilterRequest {
+    /// Directives in `RUST_LOG` syntax, e.g. `info,hypesilico::compile=debug`.
+    pub filter: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilterResponse {
+    pub filter: String,
+}
+
+pub async fn get_log_filter(
+    State(state): State<AppState>,
+) -> Result<Json<LogFilterResponse>, AppError> {
+    let filter = log_filter(&state)?.current().map_err(map_error)?;
+    Ok(Json(LogFilterResponse { filter }))
+}
+
+/// Replace the tracing filter until the next change or restart.
+pub async fn put_log_filter(
+    State(state): State<AppState>,
+    body: Result<Json<LogFilterRequest>, JsonRejection>,
+) -> Result<Json<LogFilterResponse>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    let handle = log_filter(&state)?;
+    let previous = handle.current().map_err(map_error)?;
+    let filter = handle.set(&request.filter).map_err(map_error)?;
+    tracing::warn!("Log filter changed from '{}' to '{}'", previous, filter);
+    Ok(Json(LogFilterResponse { filter }))
+}
+
+/// Restore the filter the process started with.
+pub async fn reset_log_filter(
+    State(state): State<AppState>,
+) -> Result<Json<LogFilterResponse>, AppError> {
+    let filter = log_filter(&state)?.reset().map_err(map_error)?;
+    tracing::warn!("Log filter reset to '{}'", filter);
+    Ok(Json(LogFilterResponse { filter }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigReloadResponse {
+    /// Settings now running with their new value.
+    pub changed: Vec<&'static str>,
+    /// Changed settings left at their running value until the next restart.
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Re-read the config file and environment and apply the operational settings, as a
+/// SIGHUP does. An invalid config is rejected whole and the running one kept.
+pub async fn post_config_reload(
+    State(state): State<AppState>,
+) -> Result<Json<ConfigReloadResponse>, AppError> {
+    let next = Config::from_env().map_err(|e| AppError::Config(e.to_string()))?;
+    let report = state.reload_config(next);
+    Ok(Json(ConfigReloadResponse {
+        changed: report.changed,
+        restart_required: report.restart_required,
+    }))
+}
+
+fn log_filter(state: &AppState) -> Result<&LogFilterHandle, AppError> {
+    state
+        .log_filter
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("runtime log filter is not enabled".to_string()))
+}
+
+fn map_error(err: LogFilterError) -> AppError {
+    match err {
+        LogFilterError::Invalid(_) => AppError::BadRequest(err.to_string()),
+        LogFilterError::Reload(_) => AppError::Internal(err.to_string()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderDiagnosticsQuery {
+    /// Day whose log file is probed; defaults to yesterday (UTC), the latest complete day.
+    pub day: Option<String>,
+    /// Send a `HEAD` request to each builder's log URL (default true).
+    pub probe: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderDiagnosticsResponse {
+    pub day: String,
+    pub builders: Vec<BuilderDiagnosticDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderDiagnosticDto {
+    /// Where the address is configured, e.g. `TARGET_BUILDER`.
+    pub source: String,
+    /// The address exactly as configured.
+    pub configured: String,
+    /// Normalized address; omitted when the configured value is invalid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Whether the log file for `day` returned a 2xx status; omitted when not probed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reachable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe_error: Option<String>,
+}
+
+/// Validate configured builder addresses and check their builder log URLs.
+pub async fn get_builder_diagnostics(
+    ValidQuery(params): ValidQuery<BuilderDiagnosticsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<BuilderDiagnosticsResponse>, AppError> {
+    let day = match parse_day("day", params.day.as_deref())? {
+        Some(day) => day.to_string(),
+        None => (Utc::now() - Duration::days(1))
+            .format("%Y%m%d")
+            .to_string(),
+    };
+    let probe = params.probe.unwrap_or(true);
+    let config = state.config.load();
+    let fetcher = BuilderLogsFetcher::new(state.http_client.clone()).with_network(config.network);
+
+    let configured = [("TARGET_BUILDER", config.target_builder.as_str())];
+    let mut builders = Vec::with_capacity(configured.len());
+    for (source, value) in configured {
+        let mut dto = BuilderDiagnosticDto {
+            source: source.to_string(),
+            configured: value.to_string(),
+            address: None,
+            error: None,
+            url: None,
+            reachable: None,
+            http_status: None,
+            probe_error: None,
+        };
+        match Address::parse_normalized(value) {
+            Ok(address) => {
+                let url = BuilderLogsFetcher::builder_logs_url(config.network, &address, &day);
+                if probe {
+                    match fetcher.probe_url(&url).await {
+                        Ok(status) => {
+                            dto.reachable = Some((200..300).contains(&status));
+                            dto.http_status = Some(status);
+                        }
+                        Err(e) => {
+                            dto.reachable = Some(false);
+                            dto.probe_error = Some(e.to_string());
+                        }
+                    }
+                }
+                dto.address = Some(address.as_str().to_string());
+                dto.url = Some(url);
+            }
+            Err(e) => dto.error = Some(e.to_string()),
+        }
+        builders.push(dto);
+    }
+
+    Ok(Json(BuilderDiagnosticsResponse { day, builders }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsBackfillRequest {
+    /// First UTC day, `YYYYMMDD`.
+    pub from_day: String,
+    /// Last UTC day, `YYYYMMDD`, inclusive.
+    pub to_day: String,
+    /// Builder addresses; defaults to the target builder.
+    #[serde(default)]
+    pub builders: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsBackfillResponse {
+    pub from_day: String,
+    pub to_day: String,
+    pub builders: Vec<String>,
+    pub fills_stored: usize,
+    pub failed_days: usize,
+    pub fills_attributed: usize,
+    pub days: Vec<BuilderLogsBackfillDayDto>,
+    pub users: Vec<BuilderLogsBackfillUserDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsBackfillDayDto {
+    pub builder: String,
+    pub day: String,
+    pub fills: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsBackfillUserDto {
+    pub user: String,
+    pub attributed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Store builders' logs for a range of days and re-attribute the users found in them.
+pub async fn post_builder_logs_backfill(
+    State(state): State<AppState>,
+    body: Result<Json<BuilderLogsBackfillRequest>, JsonRejection>,
+) -> Result<Json<BuilderLogsBackfillResponse>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    parse_day("fromDay", Some(&request.from_day))?;
+    parse_day("toDay", Some(&request.to_day))?;
+    let days = days_in_range(&request.from_day, &request.to_day).ok_or_else(|| {
+        AppError::BadRequest("fromDay and toDay must be valid dates with fromDay <= toDay".into())
+    })?;
+    if days.len() > MAX_BACKFILL_DAYS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} days can be backfilled at once",
+            MAX_BACKFILL_DAYS
+        )));
+    }
+    let builders = if request.builders.is_empty() {
+        vec![parse_address("targetBuilder", &state.config.load().target_builder)?]
+    } else {
+        let mut builders = request
+            .builders
+            .iter()
+            .map(|b| parse_address("builders", b))
+            .collect::<Result<Vec<_>, _>>()?;
+        builders.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+        builders.dedup();
+        builders
+    };
+    if builders.len() > MAX_BACKFILL_BUILDERS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} builders can be backfilled at once",
+            MAX_BACKFILL_BUILDERS
+        )));
+    }
+
+    let report = state
+        .orchestrator
+        .backfill_builder_logs(&builders, &days)
+        .await?;
+
+    Ok(Json(BuilderLogsBackfillResponse {
+        from_day: request.from_day,
+        to_day: request.to_day,
+        builders: builders.iter().map(|b| b.as_str().to_string()).collect(),
+        fills_stored: report.fills_stored,
+        failed_days: report.failed_days(),
+        fills_attributed: report.fills_attributed(),
+        days: report
+            .days
+            .into_iter()
+            .map(|d| BuilderLogsBackfillDayDto {
+                builder: d.builder.as_str().to_string(),
+                day: d.yyyymmdd,
+                fills: d.fills,
+                error: d.error,
+            })
+            .collect(),
+        users: report
+            .users
+            .into_iter()
+            .map(|u| BuilderLogsBackfillUserDto {
+                user: u.user.as_str().to_string(),
+                attributed: u.attributed,
+                error: u.error,
+            })
+            .collect(),
+    }))
+}
+
+const DEFAULT_INVARIANT_LIMIT: i64 = 100;
+const MAX_INVARIANT_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantsQuery {
+    pub user: Option<String>,
+    pub coin: Option<String>,
+    /// Maximum violations listed, newest first (default 100, max 1000).
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantsResponse {
+    /// Violation counts per invariant across all recorded compiles.
+    pub counts: BTreeMap<String, i64>,
+    pub violations: Vec<InvariantViolationDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantViolationDto {
+    pub user: String,
+    pub coin: String,
+    pub invariant: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle_id: Option<i64>,
+    pub detail: String,
+    pub recorded_at_ms: i64,
+}
+
+/// Report compile invariant violations recorded by the compiler.
+pub async fn get_invariants(
+    ValidQuery(params): ValidQuery<InvariantsQuery>,
+    page: Pagination<MAX_INVARIANT_LIMIT>,
+    State(state): State<AppState>,
+) -> Result<Json<InvariantsResponse>, AppError> {
+    let user = params
+        .user
+        .as_deref()
+        .map(|user| parse_address("user", user))
+        .transpose()?;
+    let coin = params
+        .coin
+        .as_deref()
+        .map(Coin::from_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("Invalid coin".into()))?;
+    let limit = page.limit.unwrap_or(DEFAULT_INVARIANT_LIMIT);
+
+    let counts = state
+        .repo
+        .count_invariant_violations(user.as_ref(), coin.as_ref())
+        .await?;
+    let violations = state
+        .repo
+        .query_invariant_violations(user.as_ref(), coin.as_ref(), limit)
+        .await?
+        .into_iter()
+        .map(|row| InvariantViolationDto {
+            user: row.user.as_str().to_string(),
+            coin: row.coin.as_str().to_string(),
+            invariant: row.invariant,
+            fill_key: row.fill_key,
+            lifecycle_id: row.lifecycle_id,
+            detail: row.detail,
+            recorded_at_ms: row.recorded_at_ms,
+        })
+        .collect();
+
+    Ok(Json(InvariantsResponse { counts, violations }))
+}
+
+const DEFAULT_RECONCILIATION_LIMIT: i64 = 100;
+const MAX_RECONCILIATION_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationQuery {
+    pub user: Option<String>,
+    pub coin: Option<String>,
+    /// Maximum issues listed, newest first (default 100, max 1000).
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationResponse {
+    /// Issues matching the filters across all recorded compiles.
+    pub count: i64,
+    pub issues: Vec<ReconciliationIssueDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationIssueDto {
+    pub user: String,
+    pub coin: String,
+    pub lifecycle_id: i64,
+    pub start_time_ms: i64,
+    pub reported_pnl: String,
+    pub recomputed_pnl: String,
+    pub difference: String,
+    pub recorded_at_ms: i64,
+}
+
+/// Report lifecycles whose exchange-reported closed PnL disagrees with avg-entry PnL.
+pub async fn get_reconciliation(
+    ValidQuery(params): ValidQuery<ReconciliationQuery>,
+    page: Pagination<MAX_RECONCILIATION_LIMIT>,
+    State(state): State<AppState>,
+) -> Result<Json<ReconciliationResponse>, AppError> {
+    let user = params
+        .user
+        .as_deref()
+        .map(|user| parse_address("user", user))
+        .transpose()?;
+    let coin = params
+        .coin
+        .as_deref()
+        .map(Coin::from_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("Invalid coin".into()))?;
+    let limit = page.limit.unwrap_or(DEFAULT_RECONCILIATION_LIMIT);
+
+    let count = state
+        .repo
+        .count_reconciliation_issues(user.as_ref(), coin.as_ref())
+        .await?;
+    let issues = state
+        .repo
+        .query_reconciliation_issues(user.as_ref(), coin.as_ref(), limit)
+        .await?
+        .into_iter()
+        .map(|row| ReconciliationIssueDto {
+            user: row.user.as_str().to_string(),
+            coin: row.coin.as_str().to_string(),
+            lifecycle_id: row.lifecycle_id,
+            start_time_ms: row.start_time_ms,
+            reported_pnl: row.reported_pnl.to_canonical_string(),
+            recomputed_pnl: row.recomputed_pnl.to_canonical_string(),
+            difference: row.difference.to_canonical_string(),
+            recorded_at_ms: row.recorded_at_ms,
+        })
+        .collect();
+
+    Ok(Json(ReconciliationResponse { count, issues }))
+}
+
+const DEFAULT_QUARANTINE_LIMIT: i64 = 100;
+const MAX_QUARANTINE_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineQuery {
+    pub user: Option<String>,
+    /// Maximum rows listed, newest first (default 100, max 1000).
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineResponse {
+    /// Quarantined rows per source table matching the filter.
+    pub counts: BTreeMap<String, i64>,
+    pub rows: Vec<QuarantinedRowDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedRowDto {
+    pub id: i64,
+    pub table: String,
+    pub source_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coin: Option<String>,
+    pub column: String,
+    pub value: Option<String>,
+    pub reason: String,
+    /// The original row.
+    pub row: serde_json::Value,
+    pub quarantined_at_ms: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineSweepResponse {
+    /// Rows moved by this sweep per source table.
+    pub moved: BTreeMap<String, u64>,
+    /// `user:coin` pairs whose derived rows were reset for recompilation.
+    pub reset_pairs: Vec<String>,
+}
+
+/// Report rows moved out of their tables because a stored value did not decode.
+pub async fn get_quarantine(
+    ValidQuery(params): ValidQuery<QuarantineQuery>,
+    page: Pagination<MAX_QUARANTINE_LIMIT>,
+    State(state): State<AppState>,
+) -> Result<Json<QuarantineResponse>, AppError> {
+    let user = params
+        .user
+        .as_deref()
+        .map(|user| parse_address("user", user))
+        .transpose()?;
+    let limit = page.limit.unwrap_or(DEFAULT_QUARANTINE_LIMIT);
+
+    let counts = state.repo.count_quarantined_rows(user.as_ref()).await?;
+    let rows = state
+        .repo
+        .query_quarantined_rows(user.as_ref(), limit)
+        .await?
+        .into_iter()
+        .map(|row| QuarantinedRowDto {
+            id: row.id,
+            table: row.source_table,
+            source_id: row.source_id,
+            user: row.user.map(|u| u.as_str().to_string()),
+            coin: row.coin.map(|c| c.as_str().to_string()),
+            column: row.column,
+            value: row.value,
+            reason: row.reason,
+            row: serde_json::from_str(&row.row).unwrap_or(serde_json::Value::String(row.row)),
+            quarantined_at_ms: row.quarantined_at_ms,
+        })
+        .collect();
+
+    Ok(Json(QuarantineResponse { counts, rows }))
+}
+
+/// Sweep every table for undecodable values now instead of waiting for the next start.
+pub async fn post_quarantine(
+    State(state): State<AppState>,
+) -> Result<Json<QuarantineSweepResponse>, AppError> {
+    let report = state.repo.quarantine_corrupt_rows().await?;
+    if report.total() > 0 {
+        tracing::warn!(moved = ?report.moved, "Quarantined corrupt rows");
+    }
+    Ok(Json(QuarantineSweepResponse {
+        moved: report.moved,
+        reset_pairs: report
+            .reset_pairs
+            .iter()
+            .map(|(user, coin)| format!("{}:{}", user, coin))
+            .collect(),
+    }))
+}
+
+/// Forget a quarantined row, e.g. after the fill was re-ingested, so builder-only queries
+/// for its user are answered again.
+pub async fn delete_quarantined_row(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if !state.repo.delete_quarantined_row(id).await? {
+        return Err(AppError::NotFound(format!("Quarantined row {} not found", id)));
+    }
+    Ok(Json(serde_json::json!({ "deleted": id })))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileStateResponse {
+    pub user: String,
+    pub coins: Vec<CoinCompileStateDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinCompileStateDto {
+    pub coin: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_compiled_time_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_compiled_fill_key: Option<String>,
+    /// Number of compile runs recorded for the coin; absent if it was never compiled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compile_version: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compile_schema_version: Option<i64>,
+    pub raw_fills: i64,
+    pub compiled_fills: i64,
+    /// Raw fills without fill effects yet.
+    pub pending_fills: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_raw_time_ms: Option<i64>,
+    /// Derived tables are missing fills or were built by another compile schema version.
+    pub behind: bool,
+}
+
+/// Report per-coin compile watermarks against raw ingestion for one user.
+pub async fn get_compile_state(
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Json<CompileStateResponse>, AppError> {
+    let coins = state
+        .repo
+        .query_compile_progress(&user)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let pending_fills = row.raw_fills - row.compiled_fills;
+            let stale_schema = row
+                .compile_schema_version
+                .is_some_and(|version| version != COMPILE_SCHEMA_VERSION);
+            CoinCompileStateDto {
+                coin: row.coin.as_str().to_string(),
+                last_compiled_time_ms: row.last_compiled_time_ms,
+                last_compiled_fill_key: row.last_compiled_fill_key,
+                compile_version: row.compile_version,
+                compile_schema_version: row.compile_schema_version,
+                raw_fills: row.raw_fills,
+                compiled_fills: row.compiled_fills,
+                pending_fills,
+                latest_raw_time_ms: row.latest_raw_time_ms,
+                behind: pending_fills > 0 || stale_schema,
+            }
+        })
+        .collect();
+
+    Ok(Json(CompileStateResponse {
+        user: user.as_str().to_string(),
+        coins,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileDryRunQuery {
+    pub user: String,
+    pub coin: String,
+    /// Include the derived rows themselves, not just the diff (default false).
+    #[serde(default)]
+    pub include_rows: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileDryRunResponse {
+    pub user: String,
+    pub coin: String,
+    pub fills: usize,
+    pub lifecycles: usize,
+    pub snapshots: usize,
+    pub effects: usize,
+    /// Whether a rebuild would leave the stored derived tables unchanged.
+    pub matches_stored: bool,
+    pub diff: DerivedDiffDto,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<DryRunRowsDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedDiffDto {
+    pub lifecycles: TableDiffDto,
+    pub snapshots: TableDiffDto,
+    pub effects: TableDiffDto,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableDiffDto {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl From<TableDiff> for TableDiffDto {
+    fn from(diff: TableDiff) -> Self {
+        Self {
+            added: diff.added,
+            removed: diff.removed,
+            changed: diff.changed,
+            unchanged: diff.unchanged,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunRowsDto {
+    pub lifecycles: Vec<DryRunLifecycleDto>,
+    pub snapshots: Vec<DryRunSnapshotDto>,
+    pub effects: Vec<DryRunEffectDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunLifecycleDto {
+    pub id: i64,
+    pub start_time_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunSnapshotDto {
+    pub time_ms: i64,
+    pub seq: i32,
+    pub lifecycle_id: i64,
+    pub net_size: String,
+    pub avg_entry_px: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunEffectDto {
+    pub fill_key: String,
+    pub lifecycle_id: i64,
+    pub effect_type: String,
+    pub qty: String,
+    pub notional: String,
+    pub fee: String,
+    pub closed_pnl: String,
+}
+
+/// Compile one coin in memory and report how the result differs from the stored derived
+/// tables, without writing anything.
+pub async fn get_compile_dry_run(
+    ValidQuery(params): ValidQuery<CompileDryRunQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Json<CompileDryRunResponse>, AppError> {
+    if params.coin.is_empty() {
+        return Err(AppError::BadRequest("coin is required".into()));
+    }
+    let coin = parse_coin(&state.orchestrator.coins(), &params.coin)?;
+
+    let report = Compiler::compile_dry_run(&state.repo, &user, &coin).await?;
+    let rows = params.include_rows.then(|| DryRunRowsDto {
+        lifecycles: report
+            .lifecycles
+            .iter()
+            .map(|l| DryRunLifecycleDto {
+                id: l.id,
+                start_time_ms: l.start_time_ms.as_i64(),
+                end_time_ms: l.end_time_ms.map(|t| t.as_i64()),
+            })
+            .collect(),
+        snapshots: report
+            .snapshots
+            .iter()
+            .map(|s| DryRunSnapshotDto {
+                time_ms: s.time_ms.as_i64(),
+                seq: s.seq,
+                lifecycle_id: s.lifecycle_id,
+                net_size: s.net_size.to_canonical_string(),
+                avg_entry_px: s.avg_entry_px.to_canonical_string(),
+            })
+            .collect(),
+        effects: report
+            .effects
+            .iter()
+            .map(|e| DryRunEffectDto {
+                fill_key: e.fill_key.clone(),
+                lifecycle_id: e.lifecycle_id,
+                effect_type: match e.effect_type {
+                    EffectType::Open => "open",
+                    EffectType::Close => "close",
+                }
+                .to_string(),
+                qty: e.qty.to_canonical_string(),
+                notional: e.notional.to_canonical_string(),
+                fee: e.fee.to_canonical_string(),
+                closed_pnl: e.closed_pnl.to_canonical_string(),
+            })
+            .collect(),
+    });
+
+    Ok(Json(CompileDryRunResponse {
+        user: user.as_str().to_string(),
+        coin: coin.as_str().to_string(),
+        fills: report.fills,
+        lifecycles: report.lifecycles.len(),
+        snapshots: report.snapshots.len(),
+        effects: report.effects.len(),
+        matches_stored: report.diff.is_empty(),
+        diff: DerivedDiffDto {
+            lifecycles: report.diff.lifecycles.into(),
+            snapshots: report.diff.snapshots.into(),
+            effects: report.diff.effects.into(),
+        },
+        rows,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRequest {
+    pub user: String,
+    /// Write the corrections to `raw_fills` and rebuild affected coins (default false).
+    #[serde(default)]
+    pub apply: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResponse {
+    pub user: String,
+    pub payloads: usize,
+    pub replayed_fills: usize,
+    pub unchanged: usize,
+    pub missing: Vec<ReplayedFillDto>,
+    pub changed: Vec<FillCorrectionDto>,
+    pub applied: bool,
+    pub rebuilt_coins: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedFillDto {
+    pub fill_key: String,
+    pub coin: String,
+    pub time_ms: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillCorrectionDto {
+    pub fill_key: String,
+    pub coin: String,
+    pub changes: Vec<FieldChangeDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChangeDto {
+    pub field: String,
+    pub stored: Option<String>,
+    pub replayed: Option<String>,
+}
+
+/// Re-derive a user's fills from recorded payloads and diff them against `raw_fills`,
+/// optionally applying the corrections.
+pub async fn post_replay(
+    State(state): State<AppState>,
+    body: Result<Json<ReplayRequest>, JsonRejection>,
+) -> Result<Json<ReplayResponse>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    let user = parse_address("user", &request.user)?;
+
+    let report = state
+        .orchestrator
+        .replay_raw_payloads(&user, request.apply)
+        .await?;
+    if report.applied {
+        tracing::warn!(
+            user = %user,
+            missing = report.missing.len(),
+            changed = report.changed.len(),
+            "Applied fill corrections from recorded payloads"
+        );
+    }
+
+    Ok(Json(ReplayResponse {
+        user: user.as_str().to_string(),
+        payloads: report.payloads,
+        replayed_fills: report.replayed_fills,
+        unchanged: report.unchanged,
+        missing: report
+            .missing
+            .iter()
+            .map(|fill| ReplayedFillDto {
+                fill_key: fill.fill_key.clone(),
+                coin: fill.coin.as_str().to_string(),
+                time_ms: fill.time_ms.as_ms(),
+            })
+            .collect(),
+        changed: report
+            .changed
+            .into_iter()
+            .map(|correction| FillCorrectionDto {
+                fill_key: correction.replayed.fill_key,
+                coin: correction.replayed.coin.as_str().to_string(),
+                changes: correction
+                    .changes
+                    .into_iter()
+                    .map(|change| FieldChangeDto {
+                        field: change.field.to_string(),
+                        stored: change.stored,
+                        replayed: change.replayed,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        applied: report.applied,
+        rebuilt_coins: report
+            .rebuilt_coins
+            .iter()
+            .map(|coin| coin.as_str().to_string())
+            .collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceRequest {
+    /// Any of `checkpoint`, `analyze`, `backup`, `prune`, run in the order given.
+    pub tasks: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceResponse {
+    pub results: Vec<MaintenanceResultDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceResultDto {
+    pub task: String,
+    pub duration_ms: u64,
+    /// `checkpoint` only: a reader kept the WAL from being fully checkpointed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_frames: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpointed_frames: Option<i64>,
+    /// `backup` only: the snapshot written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// `backup` only: older snapshots deleted to stay within `DB_BACKUP_KEEP`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<Vec<String>>,
+    /// `prune` only: rows deleted per table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruned: Option<PrunedRowsDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedRowsDto {
+    pub position_snapshots: u64,
+    pub equity_snapshots: u64,
+    pub raw_payloads: u64,
+}
+
+/// Run database maintenance tasks now instead of waiting for their schedule.
+pub async fn post_maintenance(
+    State(state): State<AppState>,
+    body: Result<Json<MaintenanceRequest>, JsonRejection>,
+) -> Result<Json<MaintenanceResponse>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    if request.tasks.is_empty() {
+        return Err(AppError::BadRequest("tasks must not be empty".into()));
+    }
+    let tasks = request
+        .tasks
+        .iter()
+        .map(|t| {
+            MaintenanceTask::from_str(t).map_err(|_| {
+                AppError::BadRequest(format!(
+                    "Unknown task {}; must be one of: checkpoint, analyze, backup, prune",
+                    t
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let maintenance = Maintenance::new(state.repo.clone(), state.config.load().maintenance.clone());
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let started = std::time::Instant::now();
+        let outcome = maintenance.run(task).await.map_err(|e| match e {
+            MaintenanceError::BackupDisabled => AppError::BadRequest(e.to_string()),
+            _ => AppError::Internal(format!("{} failed: {}", task.as_str(), e)),
+        })?;
+        let mut result = MaintenanceResultDto {
+            task: task.as_str().to_string(),
+            duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            busy: None,
+            log_frames: None,
+            checkpointed_frames: None,
+            path: None,
+            bytes: None,
+            removed: None,
+            pruned: None,
+        };
+        match outcome {
+            MaintenanceOutcome::Checkpoint(c) => {
+                result.busy = Some(c.busy);
+                result.log_frames = Some(c.log_frames);
+                result.checkpointed_frames = Some(c.checkpointed_frames);
+            }
+            MaintenanceOutcome::Analyze => {}
+            MaintenanceOutcome::Backup(b) => {
+                tracing::info!(path = %b.path.display(), bytes = b.bytes, "Wrote database backup");
+                result.path = Some(b.path.display().to_string());
+                result.bytes = Some(b.bytes);
+                result.removed = Some(b.removed.iter().map(|p| p.display().to_string()).collect());
+            }
+            MaintenanceOutcome::Prune(p) => {
+                result.pruned = Some(PrunedRowsDto {
+                    position_snapshots: p.position_snapshots,
+                    equity_snapshots: p.equity_snapshots,
+                    raw_payloads: p.raw_payloads,
+                });
+            }
+        }
+        results.push(result);
+    }
+
+    Ok(Json(MaintenanceResponse { results }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupResponse {
+    pub path: String,
+    pub bytes: u64,
+    /// Older snapshots deleted to stay within `DB_BACKUP_KEEP`.
+    pub removed: Vec<String>,
+}
+
+/// Write a consistent snapshot of the live database to `DB_BACKUP_DIR`.
+pub async fn post_backup(State(state): State<AppState>) -> Result<Json<BackupResponse>, AppError> {
+    let maintenance = Maintenance::new(state.repo.clone(), state.config.load().maintenance.clone());
+    let backup = maintenance.backup().await.map_err(|e| match e {
+        MaintenanceError::BackupDisabled => AppError::BadRequest(e.to_string()),
+        _ => AppError::Internal(format!("Backup failed: {}", e)),
+    })?;
+    tracing::info!(path = %backup.path.display(), bytes = backup.bytes, "Wrote database backup");
+
+    Ok(Json(BackupResponse {
+        path: backup.path.display().to_string(),
+        bytes: backup.bytes,
+        removed: backup.removed.iter().map(|p| p.display().to_string()).collect(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsResponse {
+    pub db_pools: DbPoolsDto,
+    /// Rows in quarantine per source table; see `GET /v1/admin/quarantine`.
+    pub quarantined_rows: BTreeMap<String, i64>,
+    /// Builder log file downloads since startup; omitted without a builder logs source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub builder_logs: Option<BuilderLogsDownloadStats>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbPoolsDto {
+    pub write: PoolStatsDto,
+    /// Omitted when reads share the write pool (`DB_READ_POOL_SIZE=0`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read: Option<PoolStatsDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStatsDto {
+    pub max_connections: u32,
+    pub size: u32,
+    pub idle: usize,
+    pub acquired: u64,
+    pub failed: u64,
+    /// Time spent waiting for a connection, in microseconds.
+    pub wait_us_total: u64,
+    pub wait_us_avg: u64,
+    pub wait_us_max: u64,
+}
+
+impl From<PoolStats> for PoolStatsDto {
+    fn from(stats: PoolStats) -> Self {
+        Self {
+            max_connections: stats.max_connections,
+            size: stats.size,
+            idle: stats.idle,
+            acquired: stats.acquired,
+            failed: stats.failed,
+            wait_us_total: stats.wait_us_total,
+            wait_us_avg: stats.wait_us_avg(),
+            wait_us_max: stats.wait_us_max,
+        }
+    }
+}
+
+/// Process metrics: connection pool usage and wait times since startup, quarantined row
+/// counts, and builder log downloads.
+pub async fn get_metrics(State(state): State<AppState>) -> Result<Json<MetricsResponse>, AppError> {
+    let pools = state.repo.pool_stats();
+    let quarantined_rows = state.repo.count_quarantined_rows(None).await?;
+    Ok(Json(MetricsResponse {
+        db_pools: DbPoolsDto {
+            write: pools.write.into(),
+            read: pools.read.map(Into::into),
+        },
+        quarantined_rows,
+        builder_logs: state.orchestrator.builder_logs_download_stats(),
+    }))
+}
+
+/// Largest number of addresses one named account may group.
+pub const MAX_ACCOUNT_MEMBERS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountGroupRequest {
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountGroupsResponse {
+    pub accounts: Vec<AccountGroupDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountGroupDto {
+    pub name: String,
+    /// Sorted.
+    pub addresses: Vec<String>,
+}
+
+/// List named accounts and their member addresses.
+pub async fn get_account_groups(
+    State(state): State<AppState>,
+) -> Result<Json<AccountGroupsResponse>, AppError> {
+    let accounts = state
+        .repo
+        .query_account_groups()
+        .await?
+        .into_iter()
+        .map(|(name, members)| account_group_dto(name, &members))
+        .collect();
+    Ok(Json(AccountGroupsResponse { accounts }))
+}
+
+/// Create a named account or replace its member addresses.
+pub async fn put_account_group(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    body: Result<Json<AccountGroupRequest>, JsonRejection>,
+) -> Result<Json<AccountGroupDto>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    if !is_valid_name(&name) {
+        return Err(AppError::BadRequest(
+            "account name must be 1-64 characters of letters, digits, '-', '_' or '.'".to_string(),
+        ));
+    }
+
+    let mut members = request
+        .addresses
+        .iter()
+        .map(|a| parse_address("member", a.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    members.sort();
+    members.dedup();
+    if members.is_empty() {
+        return Err(AppError::BadRequest("addresses must not be empty".to_string()));
+    }
+    if members.len() > MAX_ACCOUNT_MEMBERS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} addresses per account",
+            MAX_ACCOUNT_MEMBERS
+        )));
+    }
+
+    state.repo.replace_account_group(&name, &members).await?;
+    tracing::info!("Account '{}' now groups {} addresses", name, members.len());
+    Ok(Json(account_group_dto(name, &members)))
+}
+
+/// Delete a named account; its addresses are unaffected.
+pub async fn delete_account_group(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<AccountGroupDto>, AppError> {
+    let members = state.repo.query_account_group(&name).await?;
+    if !state.repo.delete_account_group(&name).await? {
+        return Err(AppError::NotFound(format!("Unknown account '{}'", name)));
+    }
+    tracing::info!("Account '{}' deleted", name);
+    Ok(Json(account_group_dto(name, &members)))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn account_group_dto(name: String, members: &[Address]) -> AccountGroupDto {
+    AccountGroupDto {
+        name,
+        addresses: members.iter().map(|a| a.as_str().to_string()).collect(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyRuleRequest {
+    /// Rules are tried lowest first; ties go by name.
+    #[serde(default)]
+    pub priority: i64,
+    /// Empty matches any coin.
+    #[serde(default)]
+    pub coins: Vec<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyRulesResponse {
+    /// In the order they are tried.
+    pub strategies: Vec<StrategyRuleDto>,
+    /// Stored lifecycles are still being relabelled after a rule changed.
+    pub relabel_pending: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyRuleDto {
+    pub name: String,
+    pub priority: i64,
+    pub coins: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_ms: Option<i64>,
+    pub tags: Vec<String>,
+}
+
+impl From<&StrategyRule> for StrategyRuleDto {
+    fn from(rule: &StrategyRule) -> Self {
+        Self {
+            name: rule.name.clone(),
+            priority: rule.priority,
+            coins: rule.coins.iter().map(|c| c.as_str().to_string()).collect(),
+            from_ms: rule.from_ms.map(|t| t.as_ms()),
+            to_ms: rule.to_ms.map(|t| t.as_ms()),
+            tags: rule.tags.iter().map(|t| t.as_str().to_string()).collect(),
+        }
+    }
+}
+
+/// List strategy rules in the order they are tried.
+pub async fn get_strategy_rules(
+    State(state): State<AppState>,
+) -> Result<Json<StrategyRulesResponse>, AppError> {
+    let strategies = state
+        .repo
+        .query_strategy_rules()
+        .await?
+        .iter()
+        .map(StrategyRuleDto::from)
+        .collect();
+    Ok(Json(StrategyRulesResponse {
+        strategies,
+        relabel_pending: state.orchestrator.strategy_relabel_pending(),
+    }))
+}
+
+/// Create a strategy rule or replace it, then relabel every lifecycle in the background.
+pub async fn put_strategy_rule(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    body: Result<Json<StrategyRuleRequest>, JsonRejection>,
+) -> Result<Json<StrategyRuleDto>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    if !is_valid_name(&name) {
+        return Err(AppError::BadRequest(
+            "strategy name must be 1-64 characters of letters, digits, '-', '_' or '.'"
+                .to_string(),
+        ));
+    }
+    let TimeRange { from_ms, to_ms } = TimeRange::new(request.from_ms, request.to_ms)?;
+    let registry = state.orchestrator.coins();
+    let mut coins = request
+        .coins
+        .iter()
+        .map(|c| parse_coin(&registry, c))
+        .collect::<Result<Vec<_>, _>>()?;
+    coins.sort();
+    coins.dedup();
+    let mut tags = request
+        .tags
+        .iter()
+        .map(|t| LifecycleTag::from_str(t))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            let known: Vec<_> = LifecycleTag::ALL.iter().map(|t| t.as_str()).collect();
+            AppError::BadRequest(format!("tags must be among: {}", known.join(", ")))
+        })?;
+    tags.sort();
+    tags.dedup();
+
+    let rule = StrategyRule {
+        name,
+        priority: request.priority,
+        coins,
+        from_ms,
+        to_ms,
+        tags,
+    };
+    state.repo.upsert_strategy_rule(&rule).await?;
+    state.orchestrator.spawn_strategy_relabel();
+    tracing::info!("Strategy rule '{}' stored; relabelling lifecycles", rule.name);
+    Ok(Json(StrategyRuleDto::from(&rule)))
+}
+
+/// Delete a strategy rule, then relabel every lifecycle in the background.
+pub async fn delete_strategy_rule(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<StrategyRuleDto>, AppError> {
+    let rule = state
+        .repo
+        .query_strategy_rules()
+        .await?
+        .into_iter()
+        .find(|rule| rule.name == name)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown strategy '{}'", name)))?;
+    if !state.repo.delete_strategy_rule(&name).await? {
+        return Err(AppError::NotFound(format!("Unknown strategy '{}'", name)));
+    }
+    state.orchestrator.spawn_strategy_relabel();
+    tracing::info!("Strategy rule '{}' deleted; relabelling lifecycles", name);
+    Ok(Json(StrategyRuleDto::from(&rule)))
+}
+
+/// Largest number of users one competition may rank.
+pub const MAX_COMPETITION_USERS: usize = 1_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionRequest {
+    pub from_ms: i64,
+    pub to_ms: i64,
+    pub metric: String,
+    #[serde(default)]
+    pub builder_only: bool,
+    pub users: Vec<String>,
+    /// Prize descriptions by rank, first place first.
+    #[serde(default)]
+    pub prizes: Vec<String>,
+}
+
+/// Create a competition or replace its definition; frozen results cannot be changed.
+pub async fn put_competition(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    body: Result<Json<CompetitionRequest>, JsonRejection>,
+) -> Result<Json<CompetitionDto>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    if !is_valid_name(&name) {
+        return Err(AppError::BadRequest(
+            "competition name must be 1-64 characters of letters, digits, '-', '_' or '.'"
+                .to_string(),
+        ));
+    }
+    TimeRange::new(Some(request.from_ms), Some(request.to_ms))?;
+    let metric = LeaderboardMetric::from_str(&request.metric)
+        .map_err(|_| AppError::BadRequest(METRIC_ERROR.to_string()))?;
+
+    let mut users = request
+        .users
+        .iter()
+        .map(|a| parse_address("user", a.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    users.sort();
+    users.dedup();
+    if users.is_empty() {
+        return Err(AppError::BadRequest("users must not be empty".to_string()));
+    }
+    if users.len() > MAX_COMPETITION_USERS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} users per competition",
+            MAX_COMPETITION_USERS
+        )));
+    }
+
+    if !state.repo.query_competition_results(&name).await?.is_empty() {
+        return Err(AppError::Conflict(format!(
+            "Competition '{}' has ended and its results are frozen",
+            name
+        )));
+    }
+
+    let competition = Competition {
+        name,
+        from_ms: TimeMs::new(request.from_ms),
+        to_ms: TimeMs::new(request.to_ms),
+        metric: metric.as_str().to_string(),
+        builder_only: request.builder_only,
+        users,
+        prizes: request.prizes,
+    };
+    state.repo.upsert_competition(&competition).await?;
+    tracing::info!(
+        "Competition '{}' ranks {} users by {}",
+        competition.name,
+        competition.users.len(),
+        competition.metric
+    );
+    Ok(Json(CompetitionDto::from(&competition)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAdjustmentsQuery {
+    pub coin: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAdjustmentRequest {
+    pub coin: String,
+    /// Only `sizeRebase` so far.
+    pub kind: String,
+    pub effective_time_ms: i64,
+    /// Fills before `effectiveTimeMs` have their size multiplied and price divided by this.
+    pub factor: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAdjustmentsResponse {
+    pub coin: String,
+    /// Oldest effective time first, the order they are applied in.
+    pub adjustments: Vec<CoinAdjustmentDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinAdjustmentDto {
+    pub kind: String,
+    pub effective_time_ms: i64,
+    pub factor: String,
+}
+
+impl From<&CoinAdjustment> for CoinAdjustmentDto {
+    fn from(adjustment: &CoinAdjustment) -> Self {
+        match adjustment {
+            CoinAdjustment::SizeRebase(rebase) => Self {
+                kind: "sizeRebase".to_string(),
+                effective_time_ms: rebase.effective_time_ms.as_ms(),
+                factor: rebase.factor.to_canonical_string(),
+            },
+        }
+    }
+}
+
+/// List the compile adjustments of a coin.
+pub async fn get_coin_adjustments(
+    ValidQuery(params): ValidQuery<CoinAdjustmentsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<CoinAdjustmentsResponse>, AppError> {
+    let coin = parse_coin(&state.orchestrator.coins(), &params.coin)?;
+    let adjustments = state.repo.query_coin_adjustments(&coin).await?;
+    Ok(Json(CoinAdjustmentsResponse {
+        coin: coin.as_str().to_string(),
+        adjustments: adjustments.iter().map(CoinAdjustmentDto::from).collect(),
+    }))
+}
+
+/// Register a compile adjustment for a coin; its derived rows are rebuilt on next query.
+pub async fn post_coin_adjustment(
+    State(state): State<AppState>,
+    body: Result<Json<CoinAdjustmentRequest>, JsonRejection>,
+) -> Result<Json<CoinAdjustmentsResponse>, AppError> {
+    let Json(request) = body.map_err(AppError::from)?;
+    let coin = parse_coin(&state.orchestrator.coins(), &request.coin)?;
+    if request.kind != "sizeRebase" {
+        return Err(AppError::BadRequest("kind must be one of: sizeRebase".to_string()));
+    }
+    let factor = Decimal::from_str_canonical(&request.factor)
+        .map_err(|_| AppError::BadRequest("Invalid factor".to_string()))?;
+    let adjustment = SizeRebase::new(TimeMs::new(request.effective_time_ms), factor)
+        .map(CoinAdjustment::SizeRebase)
+        .ok_or_else(|| {
+            AppError::BadRequest(
+                "factor must be positive with an exact decimal inverse, such as 1000 or 0.5"
+                    .to_string(),
+            )
+        })?;
+
+    let users = state.repo.insert_coin_adjustment(&coin, &adjustment).await?;
+    state.orchestrator.derived_tables_changed(&users);
+    tracing::info!(
+        "Coin adjustment {} registered for {}; derived rows of {} users dropped",
+        adjustment.kind(),
+        coin,
+        users.len()
+    );
+    get_coin_adjustments(
+        ValidQuery(CoinAdjustmentsQuery {
+            coin: coin.as_str().to_string(),
+        }),
+        State(state),
+    )
+    .await
+}
+
+/// Delete a competition and any frozen results.
+pub async fn delete_competition(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<CompetitionDto>, AppError> {
+    let competition = state
+        .repo
+        .query_competition(&name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Unknown competition '{}'", name)))?;
+    state.repo.delete_competition(&name).await?;
+    tracing::info!("Competition '{}' deleted", name);
+    Ok(Json(CompetitionDto::from(&competition)))
+}
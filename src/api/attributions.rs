@@ -0,0 +1,323 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::config::BuilderAttributionMode;
+use crate::db::repo::AttributionAuditRow;
+use crate::domain::{
+    Address, Attribution, AttributionConfidence, AttributionMode, AttributionReason, Coin, TimeMs,
+    ToleranceTier,
+};
+use crate::error::AppError;
+use crate::orchestration::attribution::attribution_coverage;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AttributionsQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    /// Only return fills attributed with at least this score (0 to 100).
+    pub min_score: Option<u8>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionsResponse {
+    pub attributions: Vec<AttributionAuditDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionAuditDto {
+    pub fill_key: String,
+    pub time_ms: i64,
+    pub coin: String,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub builder_fee: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tid: Option<i64>,
+    /// Whether the fill counts as builder flow (included by `builderOnly=true`).
+    pub attributed: bool,
+    /// `heuristic` or `logs`; omitted if the fill has not been attributed yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<AttributionMode>,
+    /// `exact`, `fuzzy`, or `low`; omitted if the fill has not been attributed yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<AttributionConfidence>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub builder: Option<String>,
+    /// Tolerance tier that admitted a fuzzy log match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance_tier: Option<ToleranceTier>,
+    /// Key of the builder log row that matched this fill.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_log_key: Option<String>,
+    /// How likely the fill is to be builder flow, from 0 (not at all) to 100 (trade id
+    /// match); omitted if the fill has not been attributed yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u8>,
+    /// What the decision was based on, for filtering and audits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason_detail: Option<AttributionReason>,
+    /// Human-readable explanation of the decision.
+    pub reason: String,
+}
+
+/// Per-fill builder attribution decisions with their reasons.
+#[utoipa::path(
+    get,
+    path = "/v1/attributions",
+    tag = "attributions",
+    params(AttributionsQuery),
+    responses((status = 200, body = AttributionsResponse))
+)]
+pub async fn get_attributions(
+    ValidQuery(params): ValidQuery<AttributionsQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<AttributionsResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some("") | None => None,
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+    };
+    if params.min_score.is_some_and(|min| min > 100) {
+        return Err(AppError::BadRequest("minScore must be between 0 and 100".into()));
+    }
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    let rows = state
+        .repo
+        .query_attribution_audit(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    Ok(Json(AttributionsResponse {
+        attributions: rows
+            .into_iter()
+            .filter(|row| {
+                params.min_score.is_none_or(|min| {
+                    row.attribution
+                        .as_ref()
+                        .is_some_and(|a| a.attributed && a.score >= min)
+                })
+            })
+            .map(audit_dto)
+            .collect(),
+    }))
+}
+
+/// Builder-log coverage reported with `builderOnly` results, flattened into the response.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionStatus {
+    /// Whether some fills in the window were attributed without their day's builder logs,
+    /// so `builderOnly` results may still change. Omitted unless `builderOnly=true` and
+    /// attribution uses builder logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution_pending: Option<bool>,
+    /// Details of the pending window; present only while attribution is pending.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution_coverage: Option<AttributionCoverageDto>,
+}
+
+impl AttributionStatus {
+    /// Combined coverage of several users' windows: pending if any is, and final only up
+    /// to the earliest covered time.
+    pub(super) fn merge(self, other: Self) -> Self {
+        let attribution_pending = match (self.attribution_pending, other.attribution_pending) {
+            (Some(a), Some(b)) => Some(a || b),
+            (a, b) => a.or(b),
+        };
+        let attribution_coverage = match (self.attribution_coverage, other.attribution_coverage) {
+            (Some(a), Some(b)) => {
+                let mut pending_days = a.pending_days;
+                pending_days.extend(b.pending_days);
+                pending_days.sort();
+                pending_days.dedup();
+                Some(AttributionCoverageDto {
+                    covered_to_ms: a.covered_to_ms.zip(b.covered_to_ms).map(|(a, b)| a.min(b)),
+                    pending_days,
+                    backfill_queued: a.backfill_queued || b.backfill_queued,
+                })
+            }
+            (a, b) => a.or(b),
+        };
+        Self {
+            attribution_pending,
+            attribution_coverage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionCoverageDto {
+    /// Results up to this time are final; omitted when the first day with fills is pending.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub covered_to_ms: Option<i64>,
+    /// UTC days (`YYYYMMDD`) with fills whose builder logs are not stored yet.
+    pub pending_days: Vec<String>,
+    /// Whether a builder logs backfill is queued or running for the user.
+    pub backfill_queued: bool,
+}
+
+/// Check builder-log coverage of a `builderOnly` query window.
+///
+/// Starts a background logs backfill for the pending part when
+/// `BUILDER_LOGS_AUTO_BACKFILL` is enabled.
+pub(super) async fn attribution_status(
+    state: &AppState,
+    builder_only: bool,
+    user: &Address,
+    coin: Option<&Coin>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+) -> Result<AttributionStatus, AppError> {
+    ensure_not_quarantined(state, builder_only, std::slice::from_ref(user)).await?;
+    if !builder_only || state.config.load().builder_attribution_mode == BuilderAttributionMode::Heuristic
+    {
+        return Ok(AttributionStatus::default());
+    }
+
+    let target_builder = Address::new(state.config.load().target_builder.clone());
+    let coverage = attribution_coverage(&state.repo, &target_builder, user, coin, from_ms, to_ms)
+        .await
+        .map_err(|e| AppError::Internal(format!("Attribution coverage failed: {}", e)))?;
+    let Some(pending_from_ms) = coverage.pending_from_ms else {
+        return Ok(AttributionStatus {
+            attribution_pending: Some(false),
+            attribution_coverage: None,
+        });
+    };
+
+    let backfill_queued = state.config.load().builder_logs_auto_backfill
+        && state.orchestrator.spawn_attribution_backfill(
+            user,
+            pending_from_ms,
+            to_ms.unwrap_or_else(TimeMs::now),
+        );
+
+    Ok(AttributionStatus {
+        attribution_pending: Some(true),
+        attribution_coverage: Some(AttributionCoverageDto {
+            covered_to_ms: coverage.covered_to_ms.map(|t| t.as_ms()),
+            pending_days: coverage.pending_days,
+            backfill_queued,
+        }),
+    })
+}
+
+/// Refuse a `builderOnly` query over `users` when any of them has quarantined rows.
+///
+/// Attribution and taint are decided over the rows that remain, so a quarantined fill
+/// could make a mixed lifecycle look like builder flow; see
+/// [`Repository::quarantine_corrupt_rows`](crate::db::Repository::quarantine_corrupt_rows).
+pub(super) async fn ensure_not_quarantined(
+    state: &AppState,
+    builder_only: bool,
+    users: &[Address],
+) -> Result<(), AppError> {
+    if !builder_only {
+        return Ok(());
+    }
+    for user in users {
+        let count: i64 = state
+            .repo
+            .count_quarantined_rows(Some(user))
+            .await?
+            .values()
+            .sum();
+        if count > 0 {
+            return Err(AppError::DataIntegrity(format!(
+                "{} has {} quarantined rows; builder-only results would be incomplete",
+                user, count
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// [`attribution_status`] of each user, merged.
+pub(super) async fn attribution_status_for_users(
+    state: &AppState,
+    builder_only: bool,
+    users: &[Address],
+    coin: Option<&Coin>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+) -> Result<AttributionStatus, AppError> {
+    let mut merged = AttributionStatus::default();
+    for user in users {
+        let status = attribution_status(state, builder_only, user, coin, from_ms, to_ms).await?;
+        merged = merged.merge(status);
+    }
+    Ok(merged)
+}
+
+fn audit_dto(row: AttributionAuditRow) -> AttributionAuditDto {
+    let reason = reason(row.attribution.as_ref());
+    let attribution = row.attribution;
+
+    AttributionAuditDto {
+        fill_key: row.fill_key,
+        time_ms: row.time_ms.as_ms(),
+        coin: row.coin.as_str().to_string(),
+        side: row.side,
+        px: row.px,
+        sz: row.sz,
+        builder_fee: row.builder_fee,
+        tid: row.tid,
+        attributed: attribution.as_ref().is_some_and(|a| a.attributed),
+        mode: attribution.as_ref().map(|a| a.mode),
+        confidence: attribution.as_ref().map(|a| a.confidence),
+        builder: attribution
+            .as_ref()
+            .and_then(|a| a.builder.as_ref())
+            .map(|b| b.as_str().to_string()),
+        tolerance_tier: attribution.as_ref().and_then(|a| a.tolerance_tier),
+        score: attribution.as_ref().map(|a| a.score),
+        reason_detail: attribution.as_ref().map(|a| a.reason),
+        matched_log_key: attribution.and_then(|a| a.matched_log_key),
+        reason,
+    }
+}
+
+fn reason(attribution: Option<&Attribution>) -> String {
+    let Some(a) = attribution else {
+        return "not attributed yet".to_string();
+    };
+
+    match (a.reason, a.attributed) {
+        (AttributionReason::BuilderFee, true) => "builder fee > 0 (heuristic)".to_string(),
+        (AttributionReason::BuilderFee, false) => "no builder fee (heuristic)".to_string(),
+        (AttributionReason::NoLogMatch, _) => "no matching builder log row".to_string(),
+        (AttributionReason::Tid, _) => "matched builder log row by tid".to_string(),
+        (AttributionReason::Fields { time_delta_ms }, _) => {
+            let tolerances = match a.tolerance_tier {
+                Some(tier) => format!("{} tolerances", tier.as_str()),
+                None => "tolerances".to_string(),
+            };
+            match time_delta_ms {
+                Some(dt) => format!(
+                    "matched builder log row on time, price, and size within {} ms and {}",
+                    dt.abs(),
+                    tolerances
+                ),
+                None => format!("matched builder log row within {}", tolerances),
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Static API key authentication with per-key rate limits.
+//!
+//! Disabled when no keys are configured. Otherwise every request except the health probes
+//! and the API docs must present a key in `Authorization: Bearer <key>` or `X-Api-Key`. Read keys reach the
+//! query endpoints; admin keys also reach `/v1/admin/*` and `/v1/ingest/*`. Each key has
+//! its own token bucket, refilled at `requests_per_minute` and holding up to that many
+//! requests, so short bursts pass while sustained load is capped.
+
+use crate::error::AppError;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiScope {
+    /// Query endpoints.
+    Read,
+    /// Everything, including admin and ingest endpoints.
+    Admin,
+}
+
+impl FromStr for ApiScope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "read" => Ok(ApiScope::Read),
+            "admin" => Ok(ApiScope::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A configured API key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    pub scope: ApiScope,
+    pub requests_per_minute: u32,
+}
+
+impl fmt::Debug for ApiKeyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyConfig")
+            .field("key", &fingerprint(&digest(&self.key)))
+            .field("scope", &self.scope)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .finish()
+    }
+}
+
+/// Key table and rate limiter state shared by all requests.
+pub struct ApiAuth {
+    /// Keyed by SHA-256 of the key, so lookups do not compare secrets directly.
+    keys: HashMap<[u8; 32], KeyState>,
+}
+
+struct KeyState {
+    scope: ApiScope,
+    requests_per_minute: u32,
+    /// Short hash prefix that identifies the key in logs.
+    fingerprint: String,
+    /// Full hash that identifies the key to later middleware; see [`ApiCaller`].
+    caller: String,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl ApiAuth {
+    pub fn new(keys: &[ApiKeyConfig]) -> Self {
+        let now = Instant::now();
+        let keys = keys
+            .iter()
+            .map(|k| {
+                let digest = digest(&k.key);
+                let state = KeyState {
+                    scope: k.scope,
+                    requests_per_minute: k.requests_per_minute,
+                    fingerprint: fingerprint(&digest),
+                    caller: hex::encode(digest),
+                    bucket: Mutex::new(TokenBucket::new(k.requests_per_minute, now)),
+                };
+                (digest, state)
+            })
+            .collect();
+        Self { keys }
+    }
+
+    /// The key table for `keys`, carrying over the rate limit state of keys whose scope
+    /// and rate are unchanged so a reload does not hand every client a fresh burst.
+    pub fn reloaded(&self, keys: &[ApiKeyConfig]) -> Self {
+        let mut next = Self::new(keys);
+        for (digest, state) in next.keys.iter_mut() {
+            let Some(old) = self.keys.get(digest) else {
+                continue;
+            };
+            if old.scope == state.scope && old.requests_per_minute == state.requests_per_minute {
+                let bucket = old.bucket.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                state.bucket = Mutex::new(bucket);
+            }
+        }
+        next
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Admit one request for `path` presenting `key`, consuming a rate limit token.
+    fn admit(&self, key: Option<&str>, path: &str, now: Instant) -> Result<&KeyState, Rejection> {
+        let Some(key) = key else {
+            return Err(Rejection::Unauthorized("API key required"));
+        };
+        let state = self
+            .keys
+            .get(&digest(key))
+            .ok_or(Rejection::Unauthorized("Invalid API key"))?;
+
+        if state.scope < required_scope(path) {
+            return Err(Rejection::Forbidden);
+        }
+
+        state
+            .bucket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .try_take(1.0, now)
+            .map_err(Rejection::RateLimited)?;
+        Ok(state)
+    }
+
+    /// Take `tokens` more from the bucket of the key a request was admitted with, for
+    /// requests that do the work of several. At most the key's whole budget is taken, so a
+    /// request larger than it waits for a full bucket rather than being refused for good.
+    pub(crate) fn charge(
+        &self,
+        caller: &ApiCaller,
+        tokens: usize,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let digest: Option<[u8; 32]> = hex::decode(&caller.0)
+            .ok()
+            .and_then(|digest| digest.try_into().ok());
+        // A key removed by a reload since the request was admitted has no budget left to take.
+        let Some(state) = digest.and_then(|digest| self.keys.get(&digest)) else {
+            return Ok(());
+        };
+        state
+            .bucket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .try_take(tokens as f64, now)
+    }
+}
+
+/// Request extension naming the API key a request was admitted with, as the hex SHA-256 of
+/// the key. Absent when authentication is disabled or the path is public.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiCaller(pub String);
+
+enum Rejection {
+    Unauthorized(&'static str),
+    Forbidden,
+    RateLimited(Duration),
+}
+
+/// Middleware enforcing [`ApiAuth`]; a no-op when no keys are configured.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let auth = state.auth.load();
+    let path = request.uri().path();
+    if !auth.is_enabled() || is_public(path) {
+        return next.run(request).await;
+    }
+
+    match auth.admit(presented_key(request.headers()), path, Instant::now()) {
+        Ok(key) => {
+            tracing::Span::current().record("api_key", key.fingerprint.as_str());
+            request.extensions_mut().insert(ApiCaller(key.caller.clone()));
+            next.run(request).await
+        }
+        Err(Rejection::Unauthorized(msg)) => {
+            let mut response = AppError::Unauthorized(msg.to_string()).into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+        Err(Rejection::Forbidden) => {
+            AppError::Forbidden("API key is not allowed to use this endpoint".to_string())
+                .into_response()
+        }
+        Err(Rejection::RateLimited(retry_after)) => rate_limited(retry_after),
+    }
+}
+
+/// `429` telling the client to retry after `retry_after`.
+pub(crate) fn rate_limited(retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response =
+        AppError::RateLimited(format!("Rate limit exceeded, retry in {}s", secs)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+/// Probes used by load balancers and orchestrators stay reachable without a key, as does
+/// the API description.
+fn is_public(path: &str) -> bool {
+    matches!(path, "/health" | "/ready" | "/openapi.json")
+        || path == "/docs"
+        || path.starts_with("/docs/")
+}
+
+fn required_scope(path: &str) -> ApiScope {
+    if path.starts_with("/v1/admin/") || path.starts_with("/v1/ingest/") {
+        ApiScope::Admin
+    } else {
+        ApiScope::Read
+    }
+}
+
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn fingerprint(digest: &[u8; 32]) -> String {
+    hex::encode(&digest[..4])
+}
+
+#[derive(Clone)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Take `tokens`, capped at the capacity, or report how long until they are available.
+    fn try_take(&mut self, tokens: f64, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+
+        let tokens = tokens.min(self.capacity);
+        if self.tokens >= tokens {
+            self.tokens -= tokens;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((tokens - self.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str, scope: ApiScope, rpm: u32) -> ApiKeyConfig {
+        ApiKeyConfig {
+            key: key.to_string(),
+            scope,
+            requests_per_minute: rpm,
+        }
+    }
+
+    #[test]
+    fn test_scopes() {
+        let auth = ApiAuth::new(&[key("r", ApiScope::Read, 100), key("a", ApiScope::Admin, 100)]);
+        let now = Instant::now();
+
+        assert!(auth.admit(Some("r"), "/v1/pnl", now).is_ok());
+        assert!(matches!(
+            auth.admit(Some("r"), "/v1/admin/invariants", now),
+            Err(Rejection::Forbidden)
+        ));
+        assert!(auth.admit(Some("a"), "/v1/ingest/fills", now).is_ok());
+        assert!(matches!(
+            auth.admit(Some("x"), "/v1/pnl", now),
+            Err(Rejection::Unauthorized(_))
+        ));
+        assert!(matches!(
+            auth.admit(None, "/v1/pnl", now),
+            Err(Rejection::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(60, start);
+        for _ in 0..60 {
+            assert!(bucket.try_take(1.0, start).is_ok());
+        }
+        let retry_after = bucket.try_take(1.0, start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+
+        assert!(bucket.try_take(1.0, start + Duration::from_secs(1)).is_ok());
+        assert!(bucket.try_take(1.0, start + Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_reload_keeps_unchanged_buckets() {
+        let auth = ApiAuth::new(&[key("a", ApiScope::Read, 1), key("b", ApiScope::Read, 1)]);
+        let now = Instant::now();
+        assert!(auth.admit(Some("a"), "/v1/pnl", now).is_ok());
+        assert!(auth.admit(Some("b"), "/v1/pnl", now).is_ok());
+
+        let auth = auth.reloaded(&[key("a", ApiScope::Read, 1), key("b", ApiScope::Read, 2)]);
+        assert!(matches!(
+            auth.admit(Some("a"), "/v1/pnl", now),
+            Err(Rejection::RateLimited(_))
+        ));
+        assert!(auth.admit(Some("b"), "/v1/pnl", now).is_ok());
+    }
+
+    #[test]
+    fn test_charge_takes_extra_tokens() {
+        let auth = ApiAuth::new(&[key("a", ApiScope::Read, 60)]);
+        let now = Instant::now();
+        assert!(auth.admit(Some("a"), "/v1/batch/pnl", now).is_ok());
+        let caller = ApiCaller(hex::encode(digest("a")));
+
+        assert!(auth.charge(&caller, 49, now).is_ok());
+        assert_eq!(auth.charge(&caller, 20, now), Err(Duration::from_secs(10)));
+        // More than the whole budget waits for a full bucket.
+        let later = now + Duration::from_secs(60);
+        assert!(auth.charge(&caller, 500, later).is_ok());
+        assert!(matches!(
+            auth.admit(Some("a"), "/v1/pnl", later),
+            Err(Rejection::RateLimited(_))
+        ));
+    }
+
+    #[test]
+    fn test_debug_redacts_key() {
+        let debug = format!("{:?}", key("super-secret", ApiScope::Read, 1));
+        assert!(!debug.contains("super-secret"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Multi-user variants of per-user queries, so dashboards can fetch many users in one call.
+//!
+//! Each user is answered by the same code as the single-user endpoint, a few at a time.
+//! A failure for one user is reported under `errors` and does not fail the batch. Every
+//! distinct user costs the caller's API key one rate limit token.
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use super::pnl::{cached_pnl_response, PnlQuery, PnlResponse};
+use super::positions::{
+    open_positions_response, MarkPrices, OpenPositionsQuery, OpenPositionsResponse,
+};
+use super::auth::{rate_limited, ApiCaller};
+use super::AppState;
+use crate::error::{AppError, ErrorBody};
+
+/// Largest number of distinct users accepted by a single request.
+pub const MAX_BATCH_USERS: usize = 100;
+
+/// Users answered concurrently within one request.
+const BATCH_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPnlRequest {
+    pub users: Vec<String>,
+    pub coin: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    pub builder_only: Option<bool>,
+    pub taint_mode: Option<String>,
+    pub max_start_capital: Option<String>,
+    pub pnl_mode: Option<String>,
+    pub return_method: Option<String>,
+    pub group_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPnlResponse {
+    /// Keyed by user address as given.
+    pub results: BTreeMap<String, PnlResponse>,
+    /// Users whose query failed; omitted when none did.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, ErrorBody>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPositionsRequest {
+    pub users: Vec<String>,
+    pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
+    pub builder_only: Option<bool>,
+    pub include_unrealized: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPositionsResponse {
+    /// Keyed by user address as given.
+    pub results: BTreeMap<String, OpenPositionsResponse>,
+    /// Users whose query failed; omitted when none did.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, ErrorBody>,
+}
+
+/// Realized PnL for several users, with the options of `GET /v1/pnl`.
+#[utoipa::path(
+    post,
+    path = "/v1/batch/pnl",
+    tag = "pnl",
+    request_body = BatchPnlRequest,
+    responses((status = 200, body = BatchPnlResponse))
+)]
+pub async fn post_batch_pnl(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiCaller>>,
+    body: Result<Json<BatchPnlRequest>, JsonRejection>,
+) -> Result<Json<BatchPnlResponse>, Response> {
+    let Json(request) = body.map_err(|e| AppError::from(e).into_response())?;
+    let users = distinct_users(&request.users).map_err(IntoResponse::into_response)?;
+    charge_users(&state, caller, users.len()).map_err(rate_limited)?;
+
+    let (results, errors) = fan_out(users, |user| {
+        let params = PnlQuery {
+            user: Some(user),
+            account: None,
+            include_sub_accounts: None,
+            coin: request.coin.clone(),
+            from_ms: request.from_ms,
+            to_ms: request.to_ms,
+            builder_only: request.builder_only,
+            taint_mode: request.taint_mode.clone(),
+            max_start_capital: request.max_start_capital.clone(),
+            pnl_mode: request.pnl_mode.clone(),
+            return_method: request.return_method.clone(),
+            group_by: request.group_by.clone(),
+        };
+        let state = &state;
+        async move { cached_pnl_response(state, &params).await }
+    })
+    .await;
+
+    Ok(Json(BatchPnlResponse { results, errors }))
+}
+
+/// Open positions for several users, with the options of `GET /v1/positions/open`.
+///
+/// Mark prices are fetched at most once per request.
+#[utoipa::path(
+    post,
+    path = "/v1/batch/positions",
+    tag = "positions",
+    request_body = BatchPositionsRequest,
+    responses((status = 200, body = BatchPositionsResponse))
+)]
+pub async fn post_batch_positions(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiCaller>>,
+    body: Result<Json<BatchPositionsRequest>, JsonRejection>,
+) -> Result<Json<BatchPositionsResponse>, Response> {
+    let Json(request) = body.map_err(|e| AppError::from(e).into_response())?;
+    let users = distinct_users(&request.users).map_err(IntoResponse::into_response)?;
+    charge_users(&state, caller, users.len()).map_err(rate_limited)?;
+    let marks = MarkPrices::default();
+
+    let (results, errors) = fan_out(users, |user| {
+        let params = OpenPositionsQuery {
+            user,
+            coin: request.coin.clone(),
+            dex: request.dex.clone(),
+            builder_only: request.builder_only,
+            include_unrealized: request.include_unrealized,
+        };
+        let (state, marks) = (&state, &marks);
+        async move { open_positions_response(state, &params, marks).await }
+    })
+    .await;
+
+    Ok(Json(BatchPositionsResponse { results, errors }))
+}
+
+/// Trimmed, deduplicated users; addresses are validated per user by the query itself.
+fn distinct_users(users: &[String]) -> Result<BTreeSet<String>, AppError> {
+    let users: BTreeSet<String> = users.iter().map(|u| u.trim().to_string()).collect();
+    if users.is_empty() {
+        return Err(AppError::BadRequest("users must not be empty".to_string()));
+    }
+    if users.len() > MAX_BATCH_USERS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} users per request",
+            MAX_BATCH_USERS
+        )));
+    }
+    Ok(users)
+}
+
+/// Charge the caller one rate limit token per user beyond the one taken to admit the request.
+///
+/// Without authentication there is no caller and nothing to charge.
+fn charge_users(
+    state: &AppState,
+    caller: Option<Extension<ApiCaller>>,
+    users: usize,
+) -> Result<(), Duration> {
+    let Some(Extension(caller)) = caller else {
+        return Ok(());
+    };
+    state
+        .auth
+        .load()
+        .charge(&caller, users.saturating_sub(1), Instant::now())
+}
+
+/// Run `query` for each user, at most [`BATCH_CONCURRENCY`] at a time.
+async fn fan_out<T, F, Fut>(
+    users: BTreeSet<String>,
+    query: F,
+) -> (BTreeMap<String, T>, BTreeMap<String, ErrorBody>)
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let outcomes: Vec<(String, Result<T, AppError>)> = stream::iter(users)
+        .map(|user| {
+            let outcome = query(user.clone());
+            async move { (user, outcome.await) }
+        })
+        .buffer_unordered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut results = BTreeMap::new();
+    let mut errors = BTreeMap::new();
+    for (user, outcome) in outcomes {
+        match outcome {
+            Ok(result) => {
+                results.insert(user, result);
+            }
+            Err(e) => {
+                errors.insert(user, ErrorBody::from(e));
+            }
+        }
+    }
+    (results, errors)
+}
@@ -0,0 +1,33 @@
+//! Request body size limit (`MAX_REQUEST_BODY_BYTES`).
+//!
+//! The router wraps every request body in tower-http's `RequestBodyLimit`, so no handler or
+//! middleware can buffer more than the limit, however the body is sent. A body that declares
+//! its size up front is refused here instead, before anything reads it, so the caller gets
+//! the usual JSON error body rather than tower-http's plain-text one.
+
+use crate::error::AppError;
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// Middleware rejecting a request whose `Content-Length` exceeds `max_bytes` with `413`.
+pub async fn reject_oversized(
+    State(max_bytes): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    match declared {
+        Some(len) if len > max_bytes as u64 => AppError::PayloadTooLarge(format!(
+            "Request body of {} bytes exceeds the {} byte limit",
+            len, max_bytes
+        ))
+        .into_response(),
+        _ => next.run(request).await,
+    }
+}
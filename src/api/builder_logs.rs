@@ -0,0 +1,118 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::validate::{parse_address, ValidQuery};
+use super::AppState;
+use crate::db::repo::{BuilderLogDayStatus, BuilderLogRejectRow};
+use crate::domain::Address;
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct BuilderLogsStatusQuery {
+    /// Defaults to the configured target builder.
+    pub builder: Option<String>,
+    pub from_day: Option<String>,
+    pub to_day: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsStatusResponse {
+    pub builder: String,
+    pub days: Vec<BuilderLogDayDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogDayDto {
+    pub day: String,
+    pub cached: bool,
+    /// Rows parsed successfully; omitted for days that are not cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at_ms: Option<i64>,
+    pub rejected_count: usize,
+    pub rejects: Vec<BuilderLogRejectDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogRejectDto {
+    pub line: i64,
+    pub byte_offset: i64,
+    pub reason: String,
+    pub raw: String,
+}
+
+/// Which days of builder logs are cached, with rejected rows.
+#[utoipa::path(
+    get,
+    path = "/v1/builder-logs/status",
+    tag = "attributions",
+    params(BuilderLogsStatusQuery),
+    responses((status = 200, body = BuilderLogsStatusResponse))
+)]
+pub async fn get_builder_logs_status(
+    ValidQuery(params): ValidQuery<BuilderLogsStatusQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<BuilderLogsStatusResponse>, AppError> {
+    let builder = match params.builder.as_deref() {
+        Some(b) => parse_address("builder", b)?,
+        None => Address::new(state.config.load().target_builder.clone()),
+    };
+
+    let from_day = parse_day("fromDay", params.from_day.as_deref())?;
+    let to_day = parse_day("toDay", params.to_day.as_deref())?;
+    if let (Some(from), Some(to)) = (from_day, to_day) {
+        if from > to {
+            return Err(AppError::InvalidTimeRange("fromDay must be <= toDay".into()));
+        }
+    }
+
+    let days = state
+        .repo
+        .query_builder_log_status(&builder, from_day, to_day)
+        .await?;
+
+    Ok(Json(BuilderLogsStatusResponse {
+        builder: builder.to_string(),
+        days: days.into_iter().map(day_dto).collect(),
+    }))
+}
+
+pub(super) fn parse_day<'a>(name: &str, day: Option<&'a str>) -> Result<Option<&'a str>, AppError> {
+    match day {
+        Some(d) if d.len() != 8 || !d.bytes().all(|b| b.is_ascii_digit()) => Err(
+            AppError::BadRequest(format!("{} must be a YYYYMMDD date", name)),
+        ),
+        _ => Ok(day),
+    }
+}
+
+fn day_dto(day: BuilderLogDayStatus) -> BuilderLogDayDto {
+    BuilderLogDayDto {
+        day: day.yyyymmdd,
+        cached: day.cached,
+        row_count: day.row_count,
+        sha256: day.sha256,
+        fetched_at_ms: day.fetched_at_ms,
+        rejected_count: day.rejects.len(),
+        rejects: day.rejects.into_iter().map(reject_dto).collect(),
+    }
+}
+
+fn reject_dto(reject: BuilderLogRejectRow) -> BuilderLogRejectDto {
+    BuilderLogRejectDto {
+        line: reject.line,
+        byte_offset: reject.byte_offset,
+        reason: reject.reason,
+        raw: reject.raw,
+    }
+}
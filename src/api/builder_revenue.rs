@@ -0,0 +1,94 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::validate::{parse_address, TimeRange, ValidQuery};
+use super::AppState;
+use crate::client::BuilderRevenueRequest;
+use crate::domain::Decimal;
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct BuilderRevenueQuery {
+    pub builder: String,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderRevenueResponse {
+    /// Lowercased builder address.
+    pub builder: String,
+    pub fill_count: usize,
+    pub volume: String,
+    pub builder_fees: String,
+    /// UTC days with attributed fills, oldest first.
+    pub days: Vec<RevenueDayDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RevenueDayDto {
+    pub start_ms: i64,
+    pub fill_count: usize,
+    /// Distinct users with an attributed fill that day.
+    pub user_count: usize,
+    pub volume: String,
+    pub builder_fees: String,
+}
+
+/// Daily builder fees from tracked users' fills attributed to a builder.
+#[utoipa::path(
+    get,
+    path = "/v1/builder/revenue",
+    tag = "builder",
+    params(BuilderRevenueQuery),
+    responses((status = 200, body = BuilderRevenueResponse))
+)]
+pub async fn get_builder_revenue(
+    ValidQuery(params): ValidQuery<BuilderRevenueQuery>,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<BuilderRevenueResponse>, AppError> {
+    let builder = parse_address("builder", params.builder.trim())?;
+
+    let buckets = state
+        .ledger
+        .builder_revenue(&BuilderRevenueRequest {
+            builder: builder.clone(),
+            from_ms,
+            to_ms,
+        })
+        .await?;
+
+    let mut fill_count = 0;
+    let mut volume = Decimal::zero();
+    let mut builder_fees = Decimal::zero();
+    let days = buckets
+        .into_iter()
+        .map(|b| {
+            fill_count += b.fill_count;
+            volume = volume + b.volume;
+            builder_fees = builder_fees + b.builder_fees;
+            RevenueDayDto {
+                start_ms: b.start_ms.as_ms(),
+                fill_count: b.fill_count,
+                user_count: b.user_count,
+                volume: b.volume.to_canonical_string(),
+                builder_fees: b.builder_fees.to_canonical_string(),
+            }
+        })
+        .collect();
+
+    Ok(Json(BuilderRevenueResponse {
+        builder: builder.as_str().to_string(),
+        fill_count,
+        volume: volume.to_canonical_string(),
+        builder_fees: builder_fees.to_canonical_string(),
+        days,
+    }))
+}
@@ -0,0 +1,135 @@
+//! Shared TTL cache for computed responses.
+//!
+//! Entries are keyed by endpoint and the endpoint's parsed query parameters. Each entry
+//! also records the compile generation (see [`Orchestrator::compile_generation`]) of every
+//! user it was computed from, so compiling new fills for any of them makes it stale before
+//! its TTL runs out.
+
+use crate::api::AppState;
+use crate::domain::Address;
+use crate::error::AppError;
+use crate::orchestration::orchestrator::Orchestrator;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Entries kept before the oldest are evicted.
+const MAX_ENTRIES: usize = 10_000;
+
+type CacheKey = (&'static str, String);
+
+struct CacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    expires_at: Instant,
+    /// Users the value was computed from, with their compile generation at the time.
+    generations: Vec<(Address, u64)>,
+}
+
+#[derive(Default)]
+pub struct ResponseCache {
+    entries: RwLock<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached value for `key`, unless it expired or any of its users were recompiled.
+    async fn get<T: Clone + 'static>(
+        &self,
+        key: &CacheKey,
+        orchestrator: &Orchestrator,
+    ) -> Option<T> {
+        let entries = self.entries.read().await;
+        let entry = entries.get(key)?;
+        let current = entry.expires_at > Instant::now()
+            && entry
+                .generations
+                .iter()
+                .all(|(user, generation)| orchestrator.compile_generation(user) == *generation);
+        if !current {
+            return None;
+        }
+        entry.value.downcast_ref::<T>().cloned()
+    }
+
+    async fn insert<T: Send + Sync + 'static>(
+        &self,
+        key: CacheKey,
+        value: T,
+        ttl: Duration,
+        generations: Vec<(Address, u64)>,
+    ) {
+        let now = Instant::now();
+        let mut entries = self.entries.write().await;
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CacheEntry {
+                value: Arc::new(value),
+                expires_at: now + ttl,
+                generations,
+            },
+        );
+    }
+}
+
+/// Return the cached response for `(endpoint, key)` or compute and cache it.
+///
+/// `key` must be built from parsed values (validated addresses, defaults resolved) rather
+/// than the raw query, so that equivalent spellings of a request share an entry. `users`
+/// are the users whose derived tables the response reads. Errors are not cached, and
+/// nothing is cached when the TTL is zero.
+pub async fn cached<T, F, Fut>(
+    state: &AppState,
+    endpoint: &'static str,
+    key: &impl Serialize,
+    ttl: Duration,
+    users: &[Address],
+    compute: F,
+) -> Result<T, AppError>
+where
+    T: Clone + Send + Sync + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    if ttl.is_zero() {
+        return compute().await;
+    }
+
+    let key = serde_json::to_string(key)
+        .map_err(|e| AppError::Internal(format!("Cache key serialization failed: {}", e)))?;
+    let key = (endpoint, key);
+    if let Some(value) = state.response_cache.get(&key, &state.orchestrator).await {
+        return Ok(value);
+    }
+
+    // Read generations before computing: a compile that lands mid-computation leaves the
+    // entry stale rather than caching results that may predate it.
+    let generations = users
+        .iter()
+        .map(|user| (user.clone(), state.orchestrator.compile_generation(user)))
+        .collect();
+    let value = compute().await?;
+    state
+        .response_cache
+        .insert(key, value.clone(), ttl, generations)
+        .await;
+    Ok(value)
+}
@@ -0,0 +1,51 @@
+use axum::extract::State;
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::AppState;
+use crate::error::AppError;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinsResponse {
+    pub coins: Vec<CoinDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinDto {
+    pub coin: String,
+    /// Asset id of the coin in the perp universe.
+    pub asset_index: u32,
+    /// Decimal places sizes of this coin are rendered with.
+    pub sz_decimals: u32,
+    pub max_leverage: u32,
+    pub delisted: bool,
+}
+
+/// Perp assets known from Hyperliquid's `meta` endpoint, in asset id order.
+///
+/// Empty until the first metadata refresh.
+#[utoipa::path(
+    get,
+    path = "/v1/coins",
+    tag = "coins",
+    responses((status = 200, body = CoinsResponse))
+)]
+pub async fn get_coins(State(state): State<AppState>) -> Result<Json<CoinsResponse>, AppError> {
+    let coins = state.orchestrator.coins();
+    Ok(Json(CoinsResponse {
+        coins: coins
+            .coins()
+            .into_iter()
+            .map(|meta| CoinDto {
+                coin: meta.coin.as_str().to_string(),
+                asset_index: meta.asset_index,
+                sz_decimals: meta.sz_decimals,
+                max_leverage: meta.max_leverage,
+                delisted: meta.delisted,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,163 @@
+//! Competitions: a leaderboard over a fixed window and set of eligible users.
+//!
+//! Competitions are managed under `/v1/admin/competitions`. Standings are ranked live
+//! until `toMs` has passed; the first request after that stores them, and they are served
+//! from storage from then on.
+
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::cache::cached;
+use super::validate::ValidQuery;
+use super::AppState;
+use crate::db::repo::Competition;
+use crate::domain::TimeMs;
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionDto {
+    pub name: String,
+    pub from_ms: i64,
+    pub to_ms: i64,
+    pub metric: String,
+    pub builder_only: bool,
+    /// Eligible users, sorted.
+    pub users: Vec<String>,
+    /// Prize descriptions by rank, first place first.
+    pub prizes: Vec<String>,
+    /// `toMs` has passed.
+    pub ended: bool,
+}
+
+impl From<&Competition> for CompetitionDto {
+    fn from(competition: &Competition) -> Self {
+        Self {
+            name: competition.name.clone(),
+            from_ms: competition.from_ms.as_ms(),
+            to_ms: competition.to_ms.as_ms(),
+            metric: competition.metric.clone(),
+            builder_only: competition.builder_only,
+            users: competition.users.iter().map(|u| u.as_str().to_string()).collect(),
+            prizes: competition.prizes.clone(),
+            ended: TimeMs::now() > competition.to_ms,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionsResponse {
+    pub competitions: Vec<CompetitionDto>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct CompetitionStandingsQuery {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionStandingsResponse {
+    pub competition: CompetitionDto,
+    /// The standings are the stored final results.
+    pub frozen: bool,
+    pub standings: Vec<CompetitionStanding>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitionStanding {
+    pub rank: i64,
+    pub user: String,
+    pub metric_value: String,
+    pub trade_count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+    /// Prize for this rank, if one is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prize: Option<String>,
+}
+
+/// Every competition.
+#[utoipa::path(
+    get,
+    path = "/v1/competitions",
+    tag = "leaderboard",
+    responses((status = 200, body = CompetitionsResponse))
+)]
+pub async fn get_competitions(
+    State(state): State<AppState>,
+) -> Result<Json<CompetitionsResponse>, AppError> {
+    let competitions = state
+        .repo
+        .query_competitions()
+        .await?
+        .iter()
+        .map(CompetitionDto::from)
+        .collect();
+    Ok(Json(CompetitionsResponse { competitions }))
+}
+
+/// A competition's users ranked over its window, frozen once it has ended.
+#[utoipa::path(
+    get,
+    path = "/v1/competitions/standings",
+    tag = "leaderboard",
+    params(CompetitionStandingsQuery),
+    responses((status = 200, body = CompetitionStandingsResponse))
+)]
+pub async fn get_competition_standings(
+    ValidQuery(params): ValidQuery<CompetitionStandingsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<CompetitionStandingsResponse>, AppError> {
+    let competition = state
+        .repo
+        .query_competition(&params.name)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Unknown competition '{}'", params.name)))?;
+
+    // Keyed by the definition, so an edit is seen straight away.
+    let dto = CompetitionDto::from(&competition);
+    let response = cached(
+        &state,
+        "competition",
+        &dto,
+        state.config.load().response_cache_ttl(),
+        &competition.users,
+        || competition_standings(&state, &competition, dto.clone()),
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+async fn competition_standings(
+    state: &AppState,
+    competition: &Competition,
+    dto: CompetitionDto,
+) -> Result<CompetitionStandingsResponse, AppError> {
+    let standings = state.ledger.competition_standings(competition).await?;
+    Ok(CompetitionStandingsResponse {
+        competition: dto,
+        frozen: standings.frozen,
+        standings: standings
+            .rows
+            .into_iter()
+            .map(|row| CompetitionStanding {
+                prize: usize::try_from(row.rank - 1)
+                    .ok()
+                    .and_then(|i| competition.prizes.get(i))
+                    .cloned(),
+                rank: row.rank,
+                user: row.user.as_str().to_string(),
+                metric_value: row.metric_value.to_canonical_string(),
+                trade_count: row.trade_count,
+                tainted: row.tainted,
+            })
+            .collect(),
+    })
+}
@@ -1,21 +1,23 @@
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::api::validate::{TimeRange, ValidatedAddress};
 use crate::api::AppState;
-use crate::domain::{Address, Decimal, TimeMs};
+use crate::domain::{Decimal, TimeMs};
 use crate::error::AppError;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct DepositsQuery {
     pub user: String,
     pub from_ms: Option<i64>,
     pub to_ms: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DepositsResponse {
     pub total_deposits: String,
@@ -23,35 +25,36 @@ pub struct DepositsResponse {
     pub deposits: Vec<DepositDto>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DepositDto {
     pub time_ms: i64,
+    /// Signed: negative for withdrawals and outgoing transfers.
     pub amount: String,
+    /// `deposit`, `withdraw`, `internalTransfer`, `subAccountTransfer` or `spotTransfer`.
+    pub kind: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tx_hash: Option<String>,
 }
 
+/// Deposit history for a user.
+#[utoipa::path(
+    get,
+    path = "/v1/deposits",
+    tag = "deposits",
+    params(DepositsQuery),
+    responses((status = 200, body = DepositsResponse))
+)]
 pub async fn get_deposits(
-    Query(params): Query<DepositsQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
     State(state): State<AppState>,
 ) -> Result<Json<DepositsResponse>, AppError> {
-    let user = Address::from_str(&params.user)
-        .map_err(|_| AppError::BadRequest("Invalid user address".into()))?;
-
-    let from_ms = params.from_ms.map(TimeMs::new);
-    let to_ms = params.to_ms.map(TimeMs::new);
-    if let (Some(from_ms), Some(to_ms)) = (from_ms, to_ms) {
-        if from_ms > to_ms {
-            return Err(AppError::BadRequest("fromMs must be <= toMs".into()));
-        }
-    }
-
     state
         .orchestrator
         .ensure_deposits_ingested(&user, from_ms, to_ms)
         .await
-        .map_err(|e| AppError::Internal(format!("Deposit ingestion failed: {}", e)))?;
+        .map_err(|e| AppError::orchestration("Deposit ingestion failed", e))?;
 
     let deposits = state
         .repo
@@ -73,6 +76,7 @@ pub async fn get_deposits(
         .map(|d| DepositDto {
             time_ms: d.time_ms.as_ms(),
             amount: d.amount.to_canonical_string(),
+            kind: d.kind.as_str().to_string(),
             tx_hash: d.tx_hash,
         })
         .collect();
@@ -0,0 +1,93 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::positions::parse_max_points;
+use super::validate::{TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct EquityHistoryQuery {
+    pub user: String,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    /// Downsample to the last point in each of at most this many equal time buckets
+    /// (1-10000).
+    pub max_points: Option<i64>,
+    /// Value open positions at historical mark prices, adding a point at every hour in
+    /// which a position is open.
+    pub include_unrealized: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EquityHistoryResponse {
+    pub points: Vec<EquityPointDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EquityPointDto {
+    pub time_ms: i64,
+    /// Net ledger flows plus realized PnL up to and including `timeMs`.
+    pub equity: String,
+    /// PnL of the positions open at `timeMs` at that hour's mark prices; with
+    /// `includeUnrealized` only, and omitted when a held coin has no price for the hour.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<String>,
+    /// `equity` plus `unrealizedPnl`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marked_equity: Option<String>,
+}
+
+/// Account equity after every ledger flow and fill, optionally marked to market and
+/// downsampled.
+#[utoipa::path(
+    get,
+    path = "/v1/equity/history",
+    tag = "pnl",
+    params(EquityHistoryQuery),
+    responses((status = 200, body = EquityHistoryResponse))
+)]
+pub async fn get_equity_history(
+    ValidQuery(params): ValidQuery<EquityHistoryQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<EquityHistoryResponse>, AppError> {
+    let max_points = params.max_points.map(parse_max_points).transpose()?;
+
+    let points = if params.include_unrealized.unwrap_or(false) {
+        state
+            .ledger
+            .marked_equity_history(&user, from_ms, to_ms, max_points)
+            .await?
+            .into_iter()
+            .map(|p| EquityPointDto {
+                time_ms: p.time_ms.as_ms(),
+                equity: p.equity.to_canonical_string(),
+                unrealized_pnl: p.unrealized_pnl.map(|u| u.to_canonical_string()),
+                marked_equity: p.unrealized_pnl.map(|u| (p.equity + u).to_canonical_string()),
+            })
+            .collect()
+    } else {
+        state
+            .ledger
+            .equity_history(&user, from_ms, to_ms, max_points)
+            .await?
+            .into_iter()
+            .map(|(time_ms, equity)| EquityPointDto {
+                time_ms: time_ms.as_ms(),
+                equity: equity.to_canonical_string(),
+                unrealized_pnl: None,
+                marked_equity: None,
+            })
+            .collect()
+    };
+
+    Ok(Json(EquityHistoryResponse { points }))
+}
@@ -0,0 +1,158 @@
+use axum::extract::State;
+use axum::Json;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use utoipa::{IntoParams, ToSchema};
+
+use super::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::datasource::load_cached_day;
+use crate::domain::Address;
+use crate::engine::{execution_quality, BuilderLogsIndex, LogsMedianPrice};
+use crate::error::AppError;
+
+const DAY_MS: i64 = 86_400_000;
+const DEFAULT_WINDOW_MS: i64 = 60_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ExecutionQualityQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    /// Builder log rows within this many milliseconds of a fill make up its reference
+    /// price. Defaults to 60000, at most one day.
+    pub window_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionQualityResponse {
+    pub fills: Vec<FillSlippageDto>,
+    /// Fills with a reference price.
+    pub measured_fills: usize,
+    /// Notional-weighted average of `slippageBps`; omitted when no fill was measured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_slippage_bps: Option<String>,
+    /// `YYYYMMDD` days the reference needed whose builder logs are not stored yet.
+    pub pending_days: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FillSlippageDto {
+    pub fill_key: String,
+    pub time_ms: i64,
+    pub coin: String,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    /// Median builder log price around the fill; omitted when there was none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_px: Option<String>,
+    /// Builder log rows the median was taken over.
+    pub reference_count: usize,
+    /// Positive when the fill was worse than the reference.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<String>,
+}
+
+/// Per-fill slippage against the median price of the target builder's other fills.
+#[utoipa::path(
+    get,
+    path = "/v1/execution-quality",
+    tag = "trades",
+    params(ExecutionQualityQuery),
+    responses((status = 200, body = ExecutionQualityResponse))
+)]
+pub async fn get_execution_quality(
+    ValidQuery(params): ValidQuery<ExecutionQualityQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<ExecutionQualityResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let window_ms = params.window_ms.unwrap_or(DEFAULT_WINDOW_MS);
+    if !(0..=DAY_MS).contains(&window_ms) {
+        return Err(AppError::BadRequest(format!(
+            "windowMs must be between 0 and {}",
+            DAY_MS
+        )));
+    }
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    let fills = state
+        .repo
+        .query_fills(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    // A window reaching across midnight needs the neighbouring day's logs too.
+    let days: BTreeSet<i64> = fills
+        .iter()
+        .flat_map(|f| {
+            let t = f.time_ms.as_ms();
+            let first = t.saturating_sub(window_ms).div_euclid(DAY_MS);
+            let last = t.saturating_add(window_ms).div_euclid(DAY_MS);
+            first..=last
+        })
+        .collect();
+
+    let target_builder = Address::new(state.config.load().target_builder.clone());
+    let mut logs = Vec::new();
+    let mut pending_days = Vec::new();
+    for day in days {
+        let yyyymmdd = chrono::Utc
+            .timestamp_millis_opt(day * DAY_MS)
+            .single()
+            .ok_or_else(|| AppError::BadRequest("Fill time out of range".into()))?
+            .format("%Y%m%d")
+            .to_string();
+        match load_cached_day(&state.repo, &target_builder, &yyyymmdd)
+            .await
+            .map_err(|e| AppError::Internal(format!("Loading builder logs failed: {}", e)))?
+        {
+            Some(rows) => logs.extend(rows),
+            None => pending_days.push(yyyymmdd),
+        }
+    }
+
+    let index = BuilderLogsIndex::new(&logs);
+    let quality = execution_quality(
+        &fills,
+        &LogsMedianPrice {
+            index: &index,
+            window_ms,
+        },
+    );
+
+    Ok(Json(ExecutionQualityResponse {
+        fills: quality
+            .fills
+            .into_iter()
+            .map(|f| FillSlippageDto {
+                fill_key: f.fill_key,
+                time_ms: f.time_ms.as_ms(),
+                coin: f.coin.as_str().to_string(),
+                side: f.side.to_string(),
+                px: f.px.to_canonical_string(),
+                sz: f.sz.to_canonical_string(),
+                reference_px: f.reference_px.map(|d| d.to_canonical_string()),
+                reference_count: f.reference_count,
+                slippage_bps: f.slippage_bps.map(|d| d.to_canonical_string()),
+            })
+            .collect(),
+        measured_fills: quality.measured_fills,
+        avg_slippage_bps: quality.avg_slippage_bps.map(|d| d.to_canonical_string()),
+        pending_days,
+    }))
+}
@@ -1,11 +1,182 @@
+use axum::extract::State;
+use axum::http::StatusCode;
 use axum::Json;
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
 
+use super::AppState;
+use crate::db::SCHEMA_VERSION;
+
+/// How long the Hyperliquid reachability probe may take.
+const DATASOURCE_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Liveness probe.
+#[utoipa::path(get, path = "/health", tag = "health", responses((status = 200)))]
 pub async fn health() -> Json<serde_json::Value> {
     Json(serde_json::json!({"status": "ok"}))
 }
 
-pub async fn ready() -> Json<serde_json::Value> {
-    Json(serde_json::json!({"status": "ready"}))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadyStatus {
+    Ready,
+    NotReady,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Ok,
+    Fail,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyResponse {
+    /// `ready` once the database, migrations, and job scheduler checks pass.
+    pub status: ReadyStatus,
+    pub checks: ReadyChecks,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyChecks {
+    /// The database answers a query.
+    pub database: CheckResult,
+    /// The database layout matches this build's schema version.
+    pub migrations: CheckResult,
+    /// The job queue still accepts ingest and compile jobs.
+    pub scheduler: CheckResult,
+    /// Hyperliquid answers an HTTP request. Reported only: stored data can still be served
+    /// while it is unreachable, so it does not gate readiness.
+    pub datasource: CheckResult,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    fn ok(detail: Option<String>) -> Self {
+        Self {
+            status: CheckStatus::Ok,
+            detail,
+        }
+    }
+
+    fn fail(detail: String) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            detail: Some(detail),
+        }
+    }
+
+    fn passed(&self) -> bool {
+        self.status == CheckStatus::Ok
+    }
+}
+
+/// Readiness probe: `503` until the database is reachable and fully migrated.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, body = ReadyResponse),
+        (status = 503, description = "A readiness check failed", body = ReadyResponse)
+    )
+)]
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadyResponse>) {
+    let (database, migrations, datasource) = tokio::join!(
+        check_database(&state),
+        check_migrations(&state),
+        check_datasource(&state),
+    );
+    let scheduler = check_scheduler(&state);
+
+    let ready = database.passed() && migrations.passed() && scheduler.passed();
+    let (code, status) = if ready {
+        (StatusCode::OK, ReadyStatus::Ready)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, ReadyStatus::NotReady)
+    };
+
+    (
+        code,
+        Json(ReadyResponse {
+            status,
+            checks: ReadyChecks {
+                database,
+                migrations,
+                scheduler,
+                datasource,
+            },
+        }),
+    )
+}
+
+async fn check_database(state: &AppState) -> CheckResult {
+    match state.repo.ping().await {
+        Ok(()) => CheckResult::ok(None),
+        Err(e) => {
+            tracing::warn!("Readiness database check failed: {}", e);
+            CheckResult::fail("query failed".to_string())
+        }
+    }
+}
+
+async fn check_migrations(state: &AppState) -> CheckResult {
+    match state.repo.compat_manifest().await {
+        Ok(Some(manifest)) if manifest.schema_version == SCHEMA_VERSION => {
+            CheckResult::ok(Some(format!("schema version {}", SCHEMA_VERSION)))
+        }
+        Ok(Some(manifest)) => CheckResult::fail(format!(
+            "schema version {}, expected {}",
+            manifest.schema_version, SCHEMA_VERSION
+        )),
+        Ok(None) => CheckResult::fail("no schema version recorded".to_string()),
+        Err(e) => CheckResult::fail(e.to_string()),
+    }
+}
+
+fn check_scheduler(state: &AppState) -> CheckResult {
+    let queue = state.orchestrator.job_queue();
+    if queue.is_closed() {
+        return CheckResult::fail("job queue closed for shutdown".to_string());
+    }
+    let stats = queue.stats();
+    CheckResult::ok(Some(format!(
+        "{} running, {} waiting",
+        stats.running,
+        stats.waiting_interactive + stats.waiting_background
+    )))
+}
+
+/// Any HTTP response short of a server error counts as reachable; the probe does not need
+/// the root path to exist.
+async fn check_datasource(state: &AppState) -> CheckResult {
+    let response = state
+        .http_client
+        .head(&state.config.load().hyperliquid_api_url)
+        .timeout(DATASOURCE_CHECK_TIMEOUT)
+        .send()
+        .await;
+    match response {
+        Ok(response) if !response.status().is_server_error() => {
+            CheckResult::ok(Some(format!("HTTP {}", response.status().as_u16())))
+        }
+        Ok(response) => CheckResult::fail(format!("HTTP {}", response.status().as_u16())),
+        Err(e) if e.is_timeout() => CheckResult::fail("timed out".to_string()),
+        Err(e) => {
+            tracing::debug!("Readiness datasource check failed: {}", e);
+            CheckResult::fail("unreachable".to_string())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -18,9 +189,20 @@ mod tests {
         assert_eq!(body["status"], "ok");
     }
 
-    #[tokio::test]
-    async fn test_ready_returns_ready() {
-        let Json(body) = ready().await;
-        assert_eq!(body["status"], "ready");
+    #[test]
+    fn test_ready_response_serializes_checks() {
+        let response = ReadyResponse {
+            status: ReadyStatus::NotReady,
+            checks: ReadyChecks {
+                database: CheckResult::ok(None),
+                migrations: CheckResult::fail("no schema version recorded".to_string()),
+                scheduler: CheckResult::ok(Some("0 running, 0 waiting".to_string())),
+                datasource: CheckResult::fail("unreachable".to_string()),
+            },
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["status"], "notReady");
+        assert_eq!(json["checks"]["database"], serde_json::json!({"status": "ok"}));
+        assert_eq!(json["checks"]["migrations"]["status"], "fail");
     }
 }
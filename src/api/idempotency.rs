@@ -0,0 +1,236 @@
+//! `Idempotency-Key` support for the admin and ingest `POST` endpoints.
+//!
+//! A client that retries an ingest, replay, backup, or maintenance request after a timeout
+//! cannot tell whether the first attempt ran. Sending the same `Idempotency-Key` makes the
+//! retry safe: the first request claims the key in `idempotency_requests`, its response is
+//! stored there, and repeats within `IDEMPOTENCY_TTL_MS` get that response back (marked
+//! `Idempotent-Replayed: true`) instead of running the handler again.
+//!
+//! Keys are scoped to the API key and the path. A repeat whose query or body differs from
+//! the first request is rejected with `409`, as is one that arrives while the first is
+//! still running. Server errors are not stored, so a request that failed that way can be
+//! retried with its key; neither is anything from a request that never finished, whose
+//! claim is released when it is dropped or, after a restart, once `IN_FLIGHT_LEASE_MS`
+//! passes.
+
+use crate::api::auth::ApiCaller;
+use crate::api::AppState;
+use crate::db::{IdempotencyKey, Repository};
+use crate::domain::TimeMs;
+use crate::error::AppError;
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+pub static IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+pub static IDEMPOTENT_REPLAYED_HEADER: HeaderName =
+    HeaderName::from_static("idempotent-replayed");
+
+/// Longest accepted key.
+const MAX_KEY_LEN: usize = 255;
+
+/// A claim still without a response after this long is taken over by the next request
+/// with its key, so a key whose request died with the process does not stay locked for the
+/// whole TTL.
+const IN_FLIGHT_LEASE_MS: i64 = 15 * 60_000;
+
+/// Middleware applying [`Repository::claim_idempotency_key`] to admin and ingest `POST`s
+/// that carry an `Idempotency-Key`. Keys are not recorded while read-only.
+pub async fn idempotency(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let config = state.config.load();
+    let ttl_ms = config.idempotency_ttl_ms;
+    let applies = ttl_ms > 0
+        && !config.read_only
+        && request.method() == Method::POST
+        && is_covered(request.uri().path())
+        && request.headers().contains_key(&IDEMPOTENCY_KEY_HEADER);
+    if !applies {
+        return next.run(request).await;
+    }
+    match run_once(&state, ttl_ms, request, next).await {
+        Ok(response) => response,
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn run_once(
+    state: &AppState,
+    ttl_ms: u64,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = request
+        .headers()
+        .get(&IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LEN)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_KEY_LEN
+            ))
+        })?
+        .to_string();
+    let caller = request
+        .extensions()
+        .get::<ApiCaller>()
+        .map(|c| c.0.clone())
+        .unwrap_or_default();
+    let path = request.uri().path().to_string();
+
+    let (parts, body) = request.into_parts();
+    // Buffered to hash it; the router's body limit bounds its size.
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+    let mut hasher = Sha256::new();
+    hasher.update(parts.uri.query().unwrap_or("").as_bytes());
+    hasher.update([0]);
+    hasher.update(&body);
+    let request_hash = hex::encode(hasher.finalize());
+
+    let now_ms = TimeMs::now().as_ms();
+    let ttl_ms = i64::try_from(ttl_ms).unwrap_or(i64::MAX);
+    let expired_before_ms = now_ms.saturating_sub(ttl_ms);
+    let abandoned_before_ms = now_ms.saturating_sub(ttl_ms.min(IN_FLIGHT_LEASE_MS));
+    let id = IdempotencyKey {
+        caller: &caller,
+        key: &key,
+        path: &path,
+    };
+    let existing = state
+        .repo
+        .claim_idempotency_key(&id, &request_hash, now_ms, expired_before_ms, abandoned_before_ms)
+        .await?;
+    if let Some(record) = existing {
+        if record.request_hash != request_hash {
+            return Err(AppError::Conflict(
+                "Idempotency-Key was already used for a different request".to_string(),
+            ));
+        }
+        let Some(status) = record.status else {
+            return Err(AppError::Conflict(
+                "A request with this Idempotency-Key is still in progress".to_string(),
+            ));
+        };
+        tracing::debug!(key = %key, path = %path, status, "Replaying idempotent response");
+        let mut response = Response::new(Body::from(record.body));
+        *response.status_mut() = StatusCode::from_u16(status)
+            .map_err(|e| AppError::Internal(format!("Stored status {}: {}", status, e)))?;
+        if let Some(value) = record
+            .content_type
+            .and_then(|ct| HeaderValue::from_str(&ct).ok())
+        {
+            response.headers_mut().insert(header::CONTENT_TYPE, value);
+        }
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED_HEADER.clone(), HeaderValue::from_static("true"));
+        return Ok(response);
+    }
+
+    // Released on every path that does not store a response, including a dropped future.
+    let mut claim = Claim {
+        repo: state.repo.clone(),
+        caller: caller.clone(),
+        key: key.clone(),
+        path: path.clone(),
+        settled: false,
+    };
+    let response = next
+        .run(Request::from_parts(parts, Body::from(body)))
+        .await;
+    if response.status().is_server_error() {
+        claim.release().await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            claim.release().await;
+            return Err(AppError::Internal(format!("Failed to buffer response: {}", e)));
+        }
+    };
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    match state
+        .repo
+        .complete_idempotency_key(&id, parts.status.as_u16(), content_type, &body)
+        .await
+    {
+        Ok(()) => claim.settled = true,
+        Err(e) => {
+            // The handler already ran; answer it rather than fail, and let a retry run again.
+            tracing::warn!(
+                key = %key, path = %path, error = %e,
+                "Failed to store idempotent response"
+            );
+            claim.release().await;
+        }
+    }
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// A claimed key without a stored response. Dropping it unsettled, as happens when the
+/// client disconnects or the handler panics, releases the key in the background.
+struct Claim {
+    repo: Arc<Repository>,
+    caller: String,
+    key: String,
+    path: String,
+    settled: bool,
+}
+
+impl Claim {
+    fn id(&self) -> IdempotencyKey<'_> {
+        IdempotencyKey {
+            caller: &self.caller,
+            key: &self.key,
+            path: &self.path,
+        }
+    }
+
+    async fn release(&mut self) {
+        self.settled = true;
+        if let Err(e) = self.repo.release_idempotency_key(&self.id()).await {
+            tracing::warn!(
+                key = %self.key, path = %self.path, error = %e,
+                "Failed to release idempotency key"
+            );
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        // Without a runtime the lease frees the key instead.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let mut claim = Claim {
+            repo: self.repo.clone(),
+            caller: std::mem::take(&mut self.caller),
+            key: std::mem::take(&mut self.key),
+            path: std::mem::take(&mut self.path),
+            settled: false,
+        };
+        runtime.spawn(async move { claim.release().await });
+    }
+}
+
+/// Endpoints with side effects: ingestion and the admin actions.
+fn is_covered(path: &str) -> bool {
+    path.starts_with("/v1/ingest/") || path.starts_with("/v1/admin/")
+}
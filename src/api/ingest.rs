@@ -0,0 +1,207 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use super::validate::ValidQuery;
+use super::AppState;
+use crate::domain::{Address, Coin, CoinRegistry, Decimal, Fill, FillType, Side, TimeMs};
+use crate::error::AppError;
+use crate::orchestration::webhooks::WebhookEvent;
+
+/// Largest batch accepted by a single request.
+const MAX_FILLS_PER_REQUEST: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestFillsQuery {
+    /// Compile the affected (user, coin) pairs before responding.
+    pub compile: Option<bool>,
+}
+
+/// A pushed fill, in the same camelCase shape as `/v1/trades` plus the fields needed to
+/// compute its fill key.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestFillDto {
+    pub user: String,
+    pub time_ms: i64,
+    pub coin: String,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    pub fee: String,
+    pub closed_pnl: String,
+    pub builder_fee: Option<String>,
+    pub tid: Option<i64>,
+    pub oid: Option<i64>,
+    /// Whether the fill took liquidity; feeds the maker/taker split in `/v1/stats`.
+    pub crossed: Option<bool>,
+    /// `trade` (default), `liquidation` or `adl`.
+    pub fill_type: Option<String>,
+    /// TWAP order the fill belongs to; groups its slices in `/v1/orders`.
+    pub twap_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestFillsResponse {
+    pub received: usize,
+    pub inserted: usize,
+    /// Fills whose fill key was already stored.
+    pub duplicates: usize,
+    /// Fill keys in request order, so callers can reconcile against their own feed.
+    pub fill_keys: Vec<String>,
+    /// Number of (user, coin) pairs compiled; omitted unless `compile=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiled_pairs: Option<usize>,
+}
+
+pub async fn post_ingest_fills(
+    ValidQuery(params): ValidQuery<IngestFillsQuery>,
+    State(state): State<AppState>,
+    body: Result<Json<Vec<IngestFillDto>>, JsonRejection>,
+) -> Result<Json<IngestFillsResponse>, AppError> {
+    let Json(dtos) = body.map_err(AppError::from)?;
+    if dtos.len() > MAX_FILLS_PER_REQUEST {
+        return Err(AppError::BadRequest(format!(
+            "at most {} fills per request",
+            MAX_FILLS_PER_REQUEST
+        )));
+    }
+
+    let mut coins = state.orchestrator.coins();
+    if !coins.is_empty() && dtos.iter().any(|dto| coins.resolve(&dto.coin).is_none()) {
+        // A coin listed since the last refresh is unknown until the registry is refreshed.
+        if let Err(e) = state.orchestrator.refresh_coins().await {
+            tracing::warn!(error = %e, "Failed to refresh coin metadata for ingested fills");
+        }
+        coins = state.orchestrator.coins();
+    }
+    let fills = dtos
+        .into_iter()
+        .enumerate()
+        .map(|(i, dto)| {
+            parse_fill(dto, &coins)
+                .map_err(|msg| AppError::BadRequest(format!("fills[{}]: {}", i, msg)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+
+    // Insert per (user, coin) so only pairs that actually gained fills get compiled.
+    let mut pairs: BTreeMap<(String, String), Vec<Fill>> = BTreeMap::new();
+    for fill in fills {
+        pairs
+            .entry((
+                fill.user.as_str().to_string(),
+                fill.coin.as_str().to_string(),
+            ))
+            .or_default()
+            .push(fill);
+    }
+
+    let mut inserted = 0;
+    let mut changed = Vec::new();
+    for pair_fills in pairs.into_values() {
+        let n = state.repo.insert_fills_batch(&pair_fills).await?;
+        inserted += n;
+        if n > 0 {
+            let first = &pair_fills[0];
+            let from = pair_fills.iter().map(|f| f.time_ms).min().unwrap_or(first.time_ms);
+            let to = pair_fills.iter().map(|f| f.time_ms).max().unwrap_or(first.time_ms);
+            state.orchestrator.webhooks().emit(WebhookEvent::FillsIngested {
+                user: first.user.clone(),
+                fills_new: n,
+                from_ms: from.as_ms(),
+                to_ms: to.as_ms(),
+                source: "push",
+            });
+            changed.push(pair_fills);
+        }
+    }
+
+    let compiled_pairs = if params.compile.unwrap_or(false) {
+        for pair_fills in &changed {
+            let first = &pair_fills[0];
+            state
+                .orchestrator
+                .compile_pushed(&first.user, &first.coin, pair_fills)
+                .await?;
+        }
+        Some(changed.len())
+    } else {
+        None
+    };
+
+    Ok(Json(IngestFillsResponse {
+        received: fill_keys.len(),
+        inserted,
+        duplicates: fill_keys.len() - inserted,
+        fill_keys,
+        compiled_pairs,
+    }))
+}
+
+/// Build the fill `dto` describes, with its coin spelled the way `coins` spells it.
+fn parse_fill(dto: IngestFillDto, coins: &CoinRegistry) -> Result<Fill, String> {
+    let user = Address::parse_checksummed(&dto.user)
+        .map_err(|e| format!("invalid user address: {}", e))?;
+    let coin = Coin::from_str(&dto.coin).map_err(|e| e.to_string())?;
+    let coin = coins
+        .resolve(coin.as_str())
+        .ok_or_else(|| format!("unknown coin '{}'", coin))?;
+    if dto.time_ms < 0 {
+        return Err("timeMs must be >= 0".to_string());
+    }
+    let side = match dto.side.as_str() {
+        "buy" => Side::Buy,
+        "sell" => Side::Sell,
+        _ => return Err("side must be one of: buy, sell".to_string()),
+    };
+
+    let decimal = |name: &str, value: &str| {
+        Decimal::from_str_canonical(value).map_err(|_| format!("invalid {}", name))
+    };
+    let px = decimal("px", &dto.px)?;
+    let sz = decimal("sz", &dto.sz)?;
+    if !px.is_positive() || !sz.is_positive() {
+        return Err("px and sz must be > 0".to_string());
+    }
+    let fee = decimal("fee", &dto.fee)?;
+    let closed_pnl = decimal("closedPnl", &dto.closed_pnl)?;
+    let builder_fee = dto
+        .builder_fee
+        .as_deref()
+        .map(|v| decimal("builderFee", v))
+        .transpose()?;
+    let fill_type = match dto.fill_type.as_deref() {
+        None => FillType::Trade,
+        Some(value) => FillType::from_str(value)
+            .map_err(|_| "fillType must be one of: trade, liquidation, adl".to_string())?,
+    };
+
+    let fill = Fill::new(
+        TimeMs::new(dto.time_ms),
+        user,
+        coin,
+        side,
+        px,
+        sz,
+        fee,
+        closed_pnl,
+        builder_fee,
+        dto.tid,
+        dto.oid,
+    )
+    .with_fill_type(fill_type);
+    let fill = match dto.twap_id {
+        Some(twap_id) => fill.with_twap_id(twap_id),
+        None => fill,
+    };
+    Ok(match dto.crossed {
+        Some(crossed) => fill.with_crossed(crossed),
+        None => fill,
+    })
+}
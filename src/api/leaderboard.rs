@@ -1,48 +1,40 @@
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
-use futures::future::try_join_all;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use utoipa::{IntoParams, ToSchema};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+use crate::api::attributions::ensure_not_quarantined;
+use crate::api::cache::cached;
+use crate::api::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
 use crate::api::AppState;
+use crate::client::{LeaderboardMetric, LeaderboardRequest};
 use crate::config::PnlMode;
-use crate::db::repo::LeaderboardFillEffect;
 use crate::domain::{Address, Coin, Decimal, TimeMs};
+use crate::engine::TaintMode;
 use crate::error::AppError;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum LeaderboardMetric {
-    Volume,
-    Pnl,
-    ReturnPct,
-}
-
-impl FromStr for LeaderboardMetric {
-    type Err = ();
+pub(crate) const METRIC_ERROR: &str =
+    "metric must be one of: volume, pnl, returnPct, fees, feesPaid, builderFees, tradeCount";
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim().to_ascii_lowercase().as_str() {
-            "volume" => Ok(LeaderboardMetric::Volume),
-            "pnl" => Ok(LeaderboardMetric::Pnl),
-            "returnpct" => Ok(LeaderboardMetric::ReturnPct),
-            _ => Err(()),
-        }
-    }
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct LeaderboardQuery {
     pub coin: Option<String>,
     pub from_ms: Option<i64>,
     pub to_ms: Option<i64>,
+    /// One metric, or several separated by commas; rows are ordered by the first.
     pub metric: Option<String>,
     pub builder_only: Option<bool>,
+    pub taint_mode: Option<String>,
     pub max_start_capital: Option<String>,
+    /// `gross`, `netFees`, or `netAllFees`; defaults to `PNL_MODE`.
+    pub pnl_mode: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LeaderboardEntry {
     pub rank: i64,
@@ -51,253 +43,268 @@ pub struct LeaderboardEntry {
     pub trade_count: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tainted: Option<bool>,
+    /// Lifecycles only partly counted under `taintMode=fill`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_lifecycles: Option<usize>,
+    /// Every requested metric by name, present when more than one was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<BTreeMap<String, LeaderboardMetricScore>>,
 }
 
-struct UserMetric {
-    user: Address,
-    metric_value: Decimal,
-    metric_value_str: String,
-    trade_count: i64,
-    tainted: bool,
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardMetricScore {
+    pub value: String,
+    /// Rank under this metric alone.
+    pub rank: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardHistoryQuery {
+    pub user: String,
+    /// Only standings under this metric; every metric when absent.
+    pub metric: Option<String>,
+    /// Earliest snapshot time.
+    pub from_ms: Option<i64>,
+    /// Latest snapshot time.
+    pub to_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardHistoryResponse {
+    pub user: String,
+    pub standings: Vec<LeaderboardStanding>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardStanding {
+    /// The snapshot ranks fills before this time.
+    pub snapshot_ms: i64,
+    pub metric: String,
+    pub rank: i64,
+    pub metric_value: String,
+    pub trade_count: i64,
+    /// Users ranked in the snapshot.
+    pub entrants: i64,
 }
 
+/// Configured leaderboard users ranked by volume, PnL, return, fees, or trade count.
+#[utoipa::path(
+    get,
+    path = "/v1/leaderboard",
+    tag = "leaderboard",
+    params(LeaderboardQuery),
+    responses((status = 200, body = [LeaderboardEntry]))
+)]
 pub async fn get_leaderboard(
-    Query(params): Query<LeaderboardQuery>,
+    ValidQuery(params): ValidQuery<LeaderboardQuery>,
+    range: TimeRange,
     State(state): State<AppState>,
 ) -> Result<Json<Vec<LeaderboardEntry>>, AppError> {
+    let users = leaderboard_users(&state);
+    let request = leaderboard_request(&state, &params, range)?;
+    let config = state.config.load();
+    let mut extra_metrics: Vec<&str> = request.extra_metrics.iter().map(|m| m.as_str()).collect();
+    extra_metrics.sort_unstable();
+    let key = LeaderboardCacheKey {
+        users: &users,
+        metric: request.metric.as_str(),
+        extra_metrics,
+        coin: request.coin.as_ref(),
+        from_ms: request.from_ms,
+        to_ms: request.to_ms,
+        builder_only: request.builder_only,
+        taint_mode: request.taint_mode.as_str(),
+        max_start_capital: request.max_start_capital.map(|d| d.to_canonical_string()),
+        pnl_mode: request.pnl_mode.unwrap_or(config.pnl_mode),
+    };
+    let entries = cached(
+        &state,
+        "leaderboard",
+        &key,
+        config.response_cache_ttl(),
+        &users,
+        || leaderboard_entries(&state, &request),
+    )
+    .await?;
+    Ok(Json(entries))
+}
+
+/// Response cache key for a leaderboard query, built from its parsed parameters so that
+/// every spelling of the same query shares an entry.
+#[derive(Serialize)]
+struct LeaderboardCacheKey<'a> {
+    users: &'a [Address],
+    metric: &'static str,
+    extra_metrics: Vec<&'static str>,
+    coin: Option<&'a Coin>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+    builder_only: bool,
+    taint_mode: &'static str,
+    max_start_capital: Option<String>,
+    pnl_mode: PnlMode,
+}
+
+/// A user's rank in every stored leaderboard snapshot, oldest first.
+#[utoipa::path(
+    get,
+    path = "/v1/leaderboard/history",
+    tag = "leaderboard",
+    params(LeaderboardHistoryQuery),
+    responses((status = 200, body = LeaderboardHistoryResponse))
+)]
+pub async fn get_leaderboard_history(
+    ValidQuery(params): ValidQuery<LeaderboardHistoryQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<LeaderboardHistoryResponse>, AppError> {
+    let metric = params
+        .metric
+        .as_deref()
+        .map(LeaderboardMetric::from_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest(METRIC_ERROR.to_string()))?;
+
+    let standings = state
+        .ledger
+        .leaderboard_history(&user, metric, from_ms, to_ms)
+        .await?
+        .into_iter()
+        .map(|row| LeaderboardStanding {
+            snapshot_ms: row.snapshot_ms.as_ms(),
+            metric: row.metric,
+            rank: row.rank,
+            metric_value: row.value.to_canonical_string(),
+            trade_count: row.trade_count,
+            entrants: row.entrants,
+        })
+        .collect();
+
+    Ok(Json(LeaderboardHistoryResponse {
+        user: user.as_str().to_string(),
+        standings,
+    }))
+}
+
+/// The configured leaderboard users; invalid entries are skipped here and rejected by the
+/// ledger.
+fn leaderboard_users(state: &AppState) -> Vec<Address> {
+    state
+        .config
+        .load()
+        .leaderboard_users
+        .iter()
+        .filter_map(|u| Address::from_str(u.trim()).ok())
+        .collect()
+}
+
+fn leaderboard_request(
+    state: &AppState,
+    params: &LeaderboardQuery,
+    TimeRange { from_ms, to_ms }: TimeRange,
+) -> Result<LeaderboardRequest, AppError> {
     let metric = params
         .metric
         .as_deref()
         .ok_or_else(|| AppError::BadRequest("metric is required".to_string()))?;
-    let metric = LeaderboardMetric::from_str(metric).map_err(|_| {
-        AppError::BadRequest("metric must be one of: volume, pnl, returnPct".to_string())
-    })?;
+    let mut metrics: Vec<LeaderboardMetric> = Vec::new();
+    for name in metric.split(',') {
+        let metric = LeaderboardMetric::from_str(name)
+            .map_err(|_| AppError::BadRequest(METRIC_ERROR.to_string()))?;
+        if !metrics.contains(&metric) {
+            metrics.push(metric);
+        }
+    }
+    let metric = metrics.remove(0);
 
     let coin = params
         .coin
         .as_deref()
         .map(str::trim)
         .filter(|s| !s.is_empty())
-        .map(|s| Coin::new(s.to_string()));
-
-    let from_ms = params.from_ms.map(TimeMs::new);
-    let to_ms = params.to_ms.map(TimeMs::new);
-
-    if let (Some(from), Some(to)) = (from_ms, to_ms) {
-        if from > to {
-            return Err(AppError::BadRequest("fromMs must be <= toMs".to_string()));
-        }
-    }
+        .map(|s| parse_coin(&state.orchestrator.coins(), s))
+        .transpose()?;
 
     let builder_only = params.builder_only.unwrap_or(false);
+    let taint_mode = params
+        .taint_mode
+        .as_deref()
+        .map(TaintMode::from_str)
+        .transpose()
+        .map_err(|_| {
+            AppError::BadRequest("taintMode must be one of: lifecycle, fill".to_string())
+        })?
+        .unwrap_or_default();
     let max_start_capital = params
         .max_start_capital
         .as_deref()
         .map(Decimal::from_str_canonical)
         .transpose()
         .map_err(|_| AppError::BadRequest("Invalid maxStartCapital".to_string()))?;
-
-    let users = parse_leaderboard_users(&state.config.leaderboard_users)?;
-    if users.is_empty() {
-        return Ok(Json(Vec::new()));
-    }
-
-    // Process all users in parallel for better performance
-    let user_futures = users.into_iter().map(|user| {
-        let state = state.clone();
-        let coin = coin.clone();
-        async move {
-            state
-                .orchestrator
-                .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
-                .await
-                .map_err(|e| {
-                    tracing::error!(user=%user, error=%e, "Compilation failed");
-                    AppError::Internal(format!("Compilation failed: {}", e))
-                })?;
-
-            let effects = state
-                .repo
-                .query_fill_effects_for_leaderboard(&user, coin.as_ref(), from_ms, to_ms)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?;
-
-            let (effects, tainted) = if builder_only {
-                filter_effects_builder_only(&state, effects).await?
-            } else {
-                (effects, false)
-            };
-
-            compute_user_metric(
-                &state,
-                user,
-                effects,
-                tainted,
-                metric,
-                from_ms.unwrap_or(TimeMs::new(0)),
-                max_start_capital,
-            )
-            .await
-        }
-    });
-
-    let mut metrics: Vec<UserMetric> = try_join_all(user_futures).await?;
-
-    metrics.sort_by(|a, b| {
-        b.metric_value
-            .cmp(&a.metric_value)
-            .then_with(|| b.trade_count.cmp(&a.trade_count))
-            .then_with(|| a.user.as_str().cmp(b.user.as_str()))
-    });
-
-    let entries = metrics
-        .into_iter()
-        .enumerate()
-        .map(|(idx, m)| LeaderboardEntry {
-            rank: (idx + 1) as i64,
-            user: m.user.as_str().to_string(),
-            metric_value: m.metric_value_str,
-            trade_count: m.trade_count,
-            tainted: builder_only.then_some(m.tainted),
-        })
-        .collect();
-
-    Ok(Json(entries))
-}
-
-fn parse_leaderboard_users(users: &[String]) -> Result<Vec<Address>, AppError> {
-    let mut parsed: Vec<Address> = users
-        .iter()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(Address::from_str)
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|_| AppError::Internal("Invalid leaderboard user address in config".to_string()))?;
-
-    parsed.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
-    parsed.dedup();
-    Ok(parsed)
+    let pnl_mode = params
+        .pnl_mode
+        .as_deref()
+        .map(PnlMode::from_str)
+        .transpose()
+        .map_err(|_| {
+            AppError::BadRequest("pnlMode must be one of: gross, netFees, netAllFees".to_string())
+        })?;
+
+    Ok(LeaderboardRequest {
+        metric,
+        extra_metrics: metrics,
+        users: None,
+        coin,
+        from_ms,
+        to_ms,
+        builder_only,
+        taint_mode,
+        max_start_capital,
+        pnl_mode,
+    })
 }
 
-async fn filter_effects_builder_only(
+async fn leaderboard_entries(
     state: &AppState,
-    effects: Vec<LeaderboardFillEffect>,
-) -> Result<(Vec<LeaderboardFillEffect>, bool), AppError> {
-    if effects.is_empty() {
-        return Ok((effects, false));
-    }
-
-    let mut lifecycle_ids: Vec<i64> = effects.iter().map(|e| e.lifecycle_id).collect();
-    lifecycle_ids.sort_unstable();
-    lifecycle_ids.dedup();
+    request: &LeaderboardRequest,
+) -> Result<Vec<LeaderboardEntry>, AppError> {
+    // Checked before ranking, which compiles and scores every user.
+    ensure_not_quarantined(state, request.builder_only, &leaderboard_users(state)).await?;
 
-    let tainted_ids = state
-        .repo
-        .query_tainted_lifecycle_ids(&lifecycle_ids)
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
-
-    if tainted_ids.is_empty() {
-        return Ok((effects, false));
-    }
+    let multiple = !request.extra_metrics.is_empty();
+    let rows = state.ledger.leaderboard(request).await?;
 
-    let tainted_set: HashSet<i64> = tainted_ids.into_iter().collect();
-    let mut had_exclusions = false;
-
-    let included: Vec<_> = effects
+    let entries = rows
         .into_iter()
-        .filter(|e| {
-            let keep = !tainted_set.contains(&e.lifecycle_id);
-            had_exclusions |= !keep;
-            keep
+        .map(|row| LeaderboardEntry {
+            rank: row.rank,
+            user: row.user.as_str().to_string(),
+            metric_value: row.metric_value.to_canonical_string(),
+            trade_count: row.trade_count,
+            tainted: row.tainted,
+            partial_lifecycles: row.partial_lifecycles,
+            metrics: multiple.then(|| {
+                row.metrics
+                    .iter()
+                    .map(|score| {
+                        (
+                            score.metric.as_str().to_string(),
+                            LeaderboardMetricScore {
+                                value: score.value.to_canonical_string(),
+                                rank: score.rank,
+                            },
+                        )
+                    })
+                    .collect()
+            }),
         })
         .collect();
 
-    Ok((included, had_exclusions))
-}
-
-async fn compute_user_metric(
-    state: &AppState,
-    user: Address,
-    effects: Vec<LeaderboardFillEffect>,
-    tainted: bool,
-    metric: LeaderboardMetric,
-    equity_at_ms: TimeMs,
-    max_start_capital: Option<Decimal>,
-) -> Result<UserMetric, AppError> {
-    let mut volume = Decimal::zero();
-    let mut realized_pnl = Decimal::zero();
-    let mut fees_paid = Decimal::zero();
-    let mut fill_keys: HashSet<String> = HashSet::new();
-
-    for effect in &effects {
-        volume = volume + effect.notional;
-        realized_pnl = realized_pnl + effect.closed_pnl;
-        fees_paid = fees_paid + effect.fee;
-        fill_keys.insert(effect.fill_key.clone());
-    }
-
-    if state.config.pnl_mode == PnlMode::Net {
-        realized_pnl = realized_pnl - fees_paid;
-    }
-
-    let trade_count = fill_keys.len() as i64;
-
-    let metric_value = match metric {
-        LeaderboardMetric::Volume => volume,
-        LeaderboardMetric::Pnl => realized_pnl,
-        LeaderboardMetric::ReturnPct => {
-            let equity_at_start = state
-                .equity_resolver
-                .resolve_equity(&user, equity_at_ms)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?;
-
-            let effective_capital = match max_start_capital {
-                Some(max) if equity_at_start > max => max,
-                _ => equity_at_start,
-            };
-
-            if effective_capital.is_zero() {
-                Decimal::zero()
-            } else {
-                (realized_pnl / effective_capital) * Decimal::hundred()
-            }
-        }
-    };
-
-    Ok(UserMetric {
-        user,
-        metric_value,
-        metric_value_str: metric_value.to_canonical_string(),
-        trade_count,
-        tainted,
-    })
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn parse_metric_accepts_camel_case_return_pct() {
-        assert_eq!(
-            LeaderboardMetric::from_str("returnPct").unwrap(),
-            LeaderboardMetric::ReturnPct
-        );
-        assert!(LeaderboardMetric::from_str("nope").is_err());
-    }
-
-    #[test]
-    fn parse_leaderboard_users_sorts_and_dedups() {
-        let users = vec![
-            " 0x0000000000000000000000000000000000000002 ".to_string(),
-            "0x0000000000000000000000000000000000000001".to_string(),
-            "0x0000000000000000000000000000000000000002".to_string(),
-        ];
-        let parsed = parse_leaderboard_users(&users).unwrap();
-        assert_eq!(parsed.len(), 2);
-        assert_eq!(parsed[0].as_str(), "0x0000000000000000000000000000000000000001");
-        assert_eq!(parsed[1].as_str(), "0x0000000000000000000000000000000000000002");
-    }
+    Ok(entries)
 }
@@ -0,0 +1,192 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+
+use super::attributions::ensure_not_quarantined;
+use super::validate::{parse_coin, parse_group_by, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::domain::{Decimal, Side};
+use crate::engine::{lifecycle_reports, LifecycleReport};
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct LifecyclesQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    /// Drop lifecycles that include fills without builder attribution.
+    pub builder_only: Option<bool>,
+    /// `strategy` to also total the lifecycles by strategy in `groups`.
+    pub group_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecyclesResponse {
+    pub lifecycles: Vec<LifecycleDto>,
+    /// Whether any lifecycle was dropped by `builderOnly=true`; omitted otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+    /// With `groupBy=strategy`, totals of the lifecycles by strategy; omitted otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<LifecycleGroupDto>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleGroupDto {
+    /// Omitted for lifecycles no strategy rule matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    pub lifecycle_count: usize,
+    pub realized_pnl: String,
+    /// Net of rebates.
+    pub fees_paid: String,
+    pub trade_count: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleDto {
+    pub lifecycle_id: String,
+    pub coin: String,
+    /// `long` or `short`.
+    pub direction: String,
+    pub open_ms: i64,
+    /// Omitted while the position is open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub close_ms: Option<i64>,
+    /// Omitted while the position is open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    /// Whether a liquidation or auto-deleveraging fill closed the position.
+    pub liquidated: bool,
+    /// `liquidation` or `adl`; omitted unless `liquidated`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_by: Option<String>,
+    /// Name of the strategy rule the lifecycle matched; omitted when none did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Largest absolute size reached.
+    pub max_size: String,
+    /// Volume-weighted price of the fills that opened or added to the position.
+    pub entry_px: String,
+    /// Volume-weighted price of the fills that reduced or closed it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_px: Option<String>,
+    pub realized_pnl: String,
+    /// Net of rebates.
+    pub fees_paid: String,
+    pub trade_count: usize,
+    pub tainted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taint_reason: Option<String>,
+}
+
+/// Position lifecycles as closed (and open) trades, oldest first.
+#[utoipa::path(
+    get,
+    path = "/v1/lifecycles",
+    tag = "positions",
+    params(LifecyclesQuery),
+    responses((status = 200, body = LifecyclesResponse))
+)]
+pub async fn get_lifecycles(
+    ValidQuery(params): ValidQuery<LifecyclesQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Json<LifecyclesResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let builder_only = params.builder_only.unwrap_or(false);
+    let by_strategy = parse_group_by(params.group_by.as_deref())?;
+    ensure_not_quarantined(&state, builder_only, std::slice::from_ref(&user)).await?;
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), None, None)
+        .await?;
+
+    let rows = state.repo.query_lifecycle_effects(&user, coin.as_ref()).await?;
+    let reports = lifecycle_reports(&rows);
+
+    let (reports, tainted) = if builder_only {
+        let any_tainted = reports.iter().any(|r| r.tainted);
+        (
+            reports.into_iter().filter(|r| !r.tainted).collect(),
+            Some(any_tainted),
+        )
+    } else {
+        (reports, None)
+    };
+    let groups = by_strategy.then(|| strategy_groups(&reports));
+
+    let lifecycles = reports
+        .into_iter()
+        .map(|r| LifecycleDto {
+            lifecycle_id: r.lifecycle_id.to_string(),
+            coin: r.coin.as_str().to_string(),
+            direction: match r.side {
+                Side::Buy => "long",
+                Side::Sell => "short",
+            }
+            .to_string(),
+            open_ms: r.start_ms.as_ms(),
+            close_ms: r.end_ms.map(|t| t.as_ms()),
+            duration_ms: r.end_ms.map(|t| t.as_ms() - r.start_ms.as_ms()),
+            liquidated: r.closed_by.is_some(),
+            closed_by: r.closed_by.map(|t| t.as_str().to_string()),
+            strategy: r.strategy,
+            max_size: r.max_size.to_canonical_string(),
+            entry_px: r.entry_px.to_canonical_string(),
+            exit_px: r.exit_px.map(|d| d.to_canonical_string()),
+            realized_pnl: r.realized_pnl.to_canonical_string(),
+            fees_paid: r.fees.fees_paid.to_canonical_string(),
+            trade_count: r.trade_count,
+            tainted: r.tainted,
+            taint_reason: r.taint_reason,
+        })
+        .collect();
+
+    Ok(Json(LifecyclesResponse {
+        lifecycles,
+        tainted,
+        groups,
+    }))
+}
+
+#[derive(Default)]
+struct GroupTotals {
+    lifecycles: usize,
+    realized_pnl: Decimal,
+    fees_paid: Decimal,
+    trades: usize,
+}
+
+/// Totals of `reports` by strategy: named strategies by name, then unlabelled lifecycles.
+fn strategy_groups(reports: &[LifecycleReport]) -> Vec<LifecycleGroupDto> {
+    let mut groups: BTreeMap<(bool, Option<&str>), GroupTotals> = BTreeMap::new();
+    for r in reports {
+        let strategy = r.strategy.as_deref();
+        let totals = groups.entry((strategy.is_none(), strategy)).or_default();
+        totals.lifecycles += 1;
+        totals.realized_pnl = totals.realized_pnl + r.realized_pnl;
+        totals.fees_paid = totals.fees_paid + r.fees.fees_paid;
+        totals.trades += r.trade_count;
+    }
+    groups
+        .into_iter()
+        .map(|((_, strategy), totals)| LifecycleGroupDto {
+            strategy: strategy.map(str::to_string),
+            lifecycle_count: totals.lifecycles,
+            realized_pnl: totals.realized_pnl.to_canonical_string(),
+            fees_paid: totals.fees_paid.to_canonical_string(),
+            trade_count: totals.trades,
+        })
+        .collect()
+}
@@ -1,63 +1,303 @@
+pub mod accounts;
+pub mod address_format;
+pub mod admin;
+pub mod attributions;
+pub mod auth;
+pub mod batch;
+pub mod body_limit;
+pub mod cache;
+pub mod coins;
+pub mod builder_logs;
+pub mod builder_revenue;
+pub mod competitions;
 pub mod deposits;
+pub mod equity;
+pub mod execution;
 pub mod health;
+pub mod idempotency;
+pub mod ingest;
 pub mod leaderboard;
+pub mod lifecycles;
+pub mod openapi;
+pub mod orders;
+pub mod performance;
 pub mod pnl;
 pub mod positions;
+pub mod read_only;
+pub mod request_id;
 pub mod risk;
+pub mod stats;
+pub mod stream;
 pub mod trades;
+pub mod validate;
 
-use crate::config::Config;
+use crate::api::auth::ApiAuth;
+use crate::api::cache::ResponseCache;
+use crate::client::LedgerClient;
+use crate::config::{Config, ConfigHandle, ReloadReport};
 use crate::db::Repository;
 use crate::engine::EquityResolver;
 use crate::orchestration::orchestrator::Orchestrator;
-use axum::{routing::get, Router};
+use crate::telemetry::LogFilterHandle;
+use arc_swap::ArcSwap;
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+    Router,
+};
 use std::sync::Arc;
+use tokio::sync::watch;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 #[derive(Clone)]
 pub struct AppState {
     pub repo: Arc<Repository>,
-    pub config: Config,
+    /// Operational settings, replaced by [`AppState::reload_config`].
+    pub config: ConfigHandle,
     pub orchestrator: Arc<Orchestrator>,
     pub equity_resolver: Arc<EquityResolver>,
+    /// Query services the trades, PnL, and leaderboard handlers delegate to.
+    pub ledger: LedgerClient,
+    pub auth: Arc<ArcSwap<ApiAuth>>,
+    pub response_cache: Arc<ResponseCache>,
     pub http_client: reqwest::Client,
+    /// Runtime tracing filter; `None` when the process did not install a reloadable one.
+    pub log_filter: Option<LogFilterHandle>,
+    /// Set by [`AppState::close_streams`]; long-lived responses end when it is.
+    pub closing: Arc<watch::Sender<bool>>,
 }
 
 impl AppState {
     pub fn new(
         repo: Arc<Repository>,
-        config: Config,
+        config: impl Into<ConfigHandle>,
         orchestrator: Arc<Orchestrator>,
         equity_resolver: Arc<EquityResolver>,
     ) -> Self {
+        let config = config.into();
+        let ledger = LedgerClient::from_parts(
+            repo.clone(),
+            config.clone(),
+            orchestrator.clone(),
+            equity_resolver.clone(),
+        );
+        let auth = Arc::new(ArcSwap::from_pointee(ApiAuth::new(&config.load().api_keys)));
         Self {
             repo,
             config,
             orchestrator,
             equity_resolver,
+            ledger,
+            auth,
+            response_cache: Arc::new(ResponseCache::new()),
             http_client: reqwest::Client::new(),
+            log_filter: None,
+            closing: Arc::new(watch::channel(false).0),
         }
     }
+
+    /// End every `/v1/stream` response, so a graceful shutdown is not held open by them.
+    pub fn close_streams(&self) {
+        self.closing.send_replace(true);
+    }
+
+    pub fn with_log_filter(mut self, log_filter: LogFilterHandle) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+
+    /// Switch to `next`'s operational settings, as [`ConfigHandle::reload`] does, and
+    /// rebuild the API key table when the keys changed.
+    pub fn reload_config(&self, next: Config) -> ReloadReport {
+        let report = self.config.reload(next);
+        if report.changed.contains(&"api_keys") {
+            let config = self.config.load();
+            self.auth.rcu(|auth| auth.reloaded(&config.api_keys));
+        }
+        tracing::info!(changed = ?report.changed, "Reloaded configuration");
+        if !report.restart_required.is_empty() {
+            tracing::warn!(
+                settings = ?report.restart_required,
+                "Configuration changes that only take effect after a restart were ignored"
+            );
+        }
+        report
+    }
 }
 
+/// The public API, plus the admin endpoints unless `ADMIN_PORT` moves them to
+/// [`create_admin_router`].
 pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    Router::new()
+    let separate_admin = state.config.load().admin_port.is_some();
+    let router = Router::new()
         .route("/health", get(health::health))
         .route("/ready", get(health::ready))
         .route(
             "/v1/positions/history",
             get(positions::get_positions_history),
         )
+        .route("/v1/positions/open", get(positions::get_open_positions))
+        .route("/v1/positions/at", get(positions::get_positions_at))
+        .route("/v1/positions/aging", get(positions::get_positions_aging))
+        .route("/v1/positions/series", get(positions::get_positions_series))
+        .route("/v1/lifecycles", get(lifecycles::get_lifecycles))
         .route("/v1/trades", get(trades::get_trades))
+        .route("/v1/stream", get(stream::get_stream))
+        .route("/v1/orders", get(orders::get_orders))
+        .route(
+            "/v1/execution-quality",
+            get(execution::get_execution_quality),
+        )
         .route("/v1/pnl", get(pnl::get_pnl))
+        .route("/v1/performance", get(performance::get_performance))
+        .route("/v1/equity/history", get(equity::get_equity_history))
+        .route("/v1/batch/pnl", post(batch::post_batch_pnl))
+        .route("/v1/batch/positions", post(batch::post_batch_positions))
+        .route("/v1/ingest/fills", post(ingest::post_ingest_fills))
         .route("/v1/deposits", get(deposits::get_deposits))
         .route("/v1/leaderboard", get(leaderboard::get_leaderboard))
+        .route(
+            "/v1/leaderboard/history",
+            get(leaderboard::get_leaderboard_history),
+        )
+        .route("/v1/competitions", get(competitions::get_competitions))
+        .route(
+            "/v1/competitions/standings",
+            get(competitions::get_competition_standings),
+        )
         .route("/v1/risk", get(risk::get_risk))
+        .route("/v1/risk/history", get(risk::get_risk_history))
+        .route("/v1/stats", get(stats::get_stats))
+        .route("/v1/coins", get(coins::get_coins))
+        .route("/v1/attributions", get(attributions::get_attributions))
+        .route("/v1/builder/revenue", get(builder_revenue::get_builder_revenue))
+        .route(
+            "/v1/builder-logs/status",
+            get(builder_logs::get_builder_logs_status),
+        );
+    let router = if separate_admin {
+        router
+    } else {
+        router.merge(admin_routes())
+    };
+    let router =
+        router.merge(SwaggerUi::new("/docs").url("/openapi.json", openapi::ApiDoc::openapi()));
+    with_middleware(router, state)
+}
+
+/// The admin endpoints alone, with the health probes, for the listener on `ADMIN_PORT`.
+pub fn create_admin_router(state: AppState) -> Router {
+    let router = Router::new()
+        .route("/health", get(health::health))
+        .route("/ready", get(health::ready))
+        .merge(admin_routes());
+    with_middleware(router, state)
+}
+
+/// Every `/v1/admin/*` endpoint.
+fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/admin/accounts", get(admin::get_account_groups))
+        .route(
+            "/v1/admin/accounts/:name",
+            put(admin::put_account_group).delete(admin::delete_account_group),
+        )
+        .route("/v1/admin/competitions", get(competitions::get_competitions))
+        .route(
+            "/v1/admin/competitions/:name",
+            put(admin::put_competition).delete(admin::delete_competition),
+        )
+        .route(
+            "/v1/admin/coin-adjustments",
+            get(admin::get_coin_adjustments).post(admin::post_coin_adjustment),
+        )
+        .route("/v1/admin/strategies", get(admin::get_strategy_rules))
+        .route(
+            "/v1/admin/strategies/:name",
+            put(admin::put_strategy_rule).delete(admin::delete_strategy_rule),
+        )
+        .route("/v1/admin/builders", get(admin::get_builder_diagnostics))
+        .route(
+            "/v1/admin/builder-logs/backfill",
+            post(admin::post_builder_logs_backfill),
+        )
+        .route("/v1/admin/compile-state", get(admin::get_compile_state))
+        .route("/v1/admin/compile-dry-run", get(admin::get_compile_dry_run))
+        .route("/v1/admin/invariants", get(admin::get_invariants))
+        .route("/v1/admin/reconciliation", get(admin::get_reconciliation))
+        .route(
+            "/v1/admin/quarantine",
+            get(admin::get_quarantine).post(admin::post_quarantine),
+        )
+        .route(
+            "/v1/admin/quarantine/:id",
+            delete(admin::delete_quarantined_row),
+        )
+        .route("/v1/admin/replay", post(admin::post_replay))
+        .route("/v1/admin/maintenance", post(admin::post_maintenance))
+        .route("/v1/admin/backup", post(admin::post_backup))
+        .route("/v1/admin/metrics", get(admin::get_metrics))
+        .route("/v1/admin/config/reload", post(admin::post_config_reload))
+        .route(
+            "/v1/admin/log-filter",
+            get(admin::get_log_filter)
+                .put(admin::put_log_filter)
+                .delete(admin::reset_log_filter),
+        )
+}
+
+/// Authentication, read-only mode, idempotency, compression, body limits, CORS, and request
+/// IDs, shared by both listeners.
+fn with_middleware(router: Router<AppState>, state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers([request_id::REQUEST_ID_HEADER.clone()]);
+    let (response_compression, max_request_body_bytes, read_only) = {
+        let config = state.config.load();
+        (
+            config.response_compression,
+            config.max_request_body_bytes,
+            config.read_only,
+        )
+    };
+
+    let router = router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency::idempotency,
+        ))
+        .layer(middleware::from_fn_with_state(
+            read_only,
+            read_only::reject_writes,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
+        .layer(middleware::from_fn(address_format::address_format));
+    // Outside the address rewrite, which needs the uncompressed JSON.
+    let router = if response_compression {
+        router.layer(CompressionLayer::new())
+    } else {
+        router
+    };
+
+    router
+        // Replaces axum's fixed 2 MiB extractor limit with the configured one.
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_request_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            max_request_body_bytes,
+            body_limit::reject_oversized,
+        ))
         .layer(cors)
+        .layer(middleware::from_fn(request_id::request_id))
         .with_state(state)
 }
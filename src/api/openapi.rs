@@ -0,0 +1,99 @@
+//! OpenAPI document derived from the handler parameter and response types.
+//!
+//! Served at `/openapi.json`, with Swagger UI at `/docs`. Admin and ingest routes are
+//! operator tooling and are left out.
+
+use crate::error::{ErrorBody, ErrorCode};
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use super::{
+    attributions, batch, builder_logs, builder_revenue, coins, competitions, deposits, equity,
+    execution, health, leaderboard, lifecycles, orders, performance, pnl, positions, risk, stats,
+    stream, trades,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Hypesilico", description = "Hyperliquid trade ledger API."),
+    paths(
+        health::health,
+        health::ready,
+        trades::get_trades,
+        stream::get_stream,
+        orders::get_orders,
+        execution::get_execution_quality,
+        pnl::get_pnl,
+        batch::post_batch_pnl,
+        performance::get_performance,
+        equity::get_equity_history,
+        positions::get_positions_history,
+        positions::get_open_positions,
+        positions::get_positions_at,
+        positions::get_positions_aging,
+        positions::get_positions_series,
+        batch::post_batch_positions,
+        lifecycles::get_lifecycles,
+        stats::get_stats,
+        coins::get_coins,
+        leaderboard::get_leaderboard,
+        leaderboard::get_leaderboard_history,
+        competitions::get_competitions,
+        competitions::get_competition_standings,
+        risk::get_risk,
+        risk::get_risk_history,
+        deposits::get_deposits,
+        attributions::get_attributions,
+        builder_logs::get_builder_logs_status,
+        builder_revenue::get_builder_revenue,
+    ),
+    components(schemas(ErrorBody, ErrorCode)),
+    modifiers(&ErrorResponses, &ApiKeyAuth)
+)]
+pub struct ApiDoc;
+
+/// Documents the shared error body as the `default` response of every operation.
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let error = ResponseBuilder::new()
+            .description("Error; see `code` for the reason")
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ErrorBody")))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            let operations: [&mut Option<Operation>; 2] = [&mut item.get, &mut item.post];
+            for operation in operations.into_iter().flatten() {
+                operation
+                    .responses
+                    .responses
+                    .insert("default".to_string(), error.clone().into());
+            }
+        }
+    }
+}
+
+/// Declares the two ways of presenting an API key. Keys are only enforced when
+/// `API_KEYS` is set, so operations do not list them as required.
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "apiKey",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+        );
+    }
+}
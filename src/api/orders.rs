@@ -0,0 +1,112 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::engine::{order_reports, OrderKey};
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct OrdersQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrdersResponse {
+    pub orders: Vec<OrderDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderDto {
+    /// `twap` for TWAP slices, `order` for fills sharing an `oid`, `fill` for a fill with
+    /// neither.
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twap_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oid: Option<i64>,
+    /// Present for `fill` orders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_key: Option<String>,
+    pub coin: String,
+    pub side: String,
+    pub first_fill_ms: i64,
+    pub last_fill_ms: i64,
+    pub size: String,
+    pub notional: String,
+    /// Volume-weighted price of the fills.
+    pub avg_px: String,
+    /// Net of rebates.
+    pub fees_paid: String,
+    pub closed_pnl: String,
+    pub fill_count: usize,
+}
+
+/// Fills grouped into the orders that produced them, by first fill time.
+#[utoipa::path(
+    get,
+    path = "/v1/orders",
+    tag = "trades",
+    params(OrdersQuery),
+    responses((status = 200, body = OrdersResponse))
+)]
+pub async fn get_orders(
+    ValidQuery(params): ValidQuery<OrdersQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<OrdersResponse>, AppError> {
+    let coins = state.orchestrator.coins();
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&coins, c)?),
+        None => None,
+    };
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    let fills = state
+        .repo
+        .query_fills(&user, coin.as_ref(), from_ms, to_ms)
+        .await?;
+
+    let orders = order_reports(&fills)
+        .into_iter()
+        .map(|r| {
+            let (kind, twap_id, oid, fill_key) = match r.key {
+                OrderKey::Twap(id) => ("twap", Some(id), None, None),
+                OrderKey::Oid(oid) => ("order", None, Some(oid), None),
+                OrderKey::Fill(key) => ("fill", None, None, Some(key)),
+            };
+            OrderDto {
+                kind: kind.to_string(),
+                twap_id,
+                oid,
+                fill_key,
+                coin: r.coin.as_str().to_string(),
+                side: r.side.to_string(),
+                first_fill_ms: r.first_fill_ms.as_ms(),
+                last_fill_ms: r.last_fill_ms.as_ms(),
+                size: coins.format_sz(&r.coin, &r.size),
+                notional: r.notional.to_canonical_string(),
+                avg_px: r.avg_px.to_canonical_string(),
+                fees_paid: r.fees.fees_paid.to_canonical_string(),
+                closed_pnl: r.closed_pnl.to_canonical_string(),
+                fill_count: r.fill_count,
+            }
+        })
+        .collect();
+
+    Ok(Json(OrdersResponse { orders }))
+}
@@ -0,0 +1,127 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::attributions::ensure_not_quarantined;
+use super::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::domain::TimeMs;
+use crate::engine::{lifecycle_reports, ClosedTrade, PerformanceStats};
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct PerformanceQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    /// Only lifecycles closed at or after this time.
+    pub from_ms: Option<i64>,
+    /// Only lifecycles closed at or before this time.
+    pub to_ms: Option<i64>,
+    pub builder_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceResponse {
+    pub closed_count: usize,
+    pub win_count: usize,
+    pub loss_count: usize,
+    /// Omitted (like the other ratios) when there is nothing to divide by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub win_rate_pct: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_win: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_loss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_factor: Option<String>,
+    pub max_drawdown: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_drawdown_pct: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sharpe_ratio: Option<String>,
+    /// Whether any lifecycle was dropped by `builderOnly=true`; omitted otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+}
+
+/// Win rate, payoff, drawdown, and Sharpe-like ratio over closed lifecycles.
+#[utoipa::path(
+    get,
+    path = "/v1/performance",
+    tag = "pnl",
+    params(PerformanceQuery),
+    responses((status = 200, body = PerformanceResponse))
+)]
+pub async fn get_performance(
+    ValidQuery(params): ValidQuery<PerformanceQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    range: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<PerformanceResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let from_ms = range.from_ms.unwrap_or(TimeMs::new(0));
+    let to_ms = range.to_ms.unwrap_or(TimeMs::new(i64::MAX));
+    let builder_only = params.builder_only.unwrap_or(false);
+    ensure_not_quarantined(&state, builder_only, std::slice::from_ref(&user)).await?;
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), None, Some(to_ms))
+        .await?;
+
+    let rows = state.repo.query_lifecycle_effects(&user, coin.as_ref()).await?;
+    let mut closed: Vec<_> = lifecycle_reports(&rows)
+        .into_iter()
+        .filter(|r| r.end_ms.is_some_and(|end| end >= from_ms && end <= to_ms))
+        .collect();
+
+    let tainted = if builder_only {
+        let any_tainted = closed.iter().any(|r| r.tainted);
+        closed.retain(|r| !r.tainted);
+        Some(any_tainted)
+    } else {
+        None
+    };
+
+    closed.sort_by_key(|r| (r.end_ms, r.lifecycle_id));
+    let pnl_mode = state.config.load().pnl_mode;
+    let trades: Vec<ClosedTrade> = closed
+        .iter()
+        .map(|r| ClosedTrade {
+            close_ms: r.end_ms.expect("only closed lifecycles are kept"),
+            pnl: pnl_mode.apply(r.realized_pnl, r.fees.fees_paid, r.builder_fees),
+        })
+        .collect();
+
+    let starting_equity = state.equity_resolver.resolve_equity(&user, from_ms).await?;
+    let deposits: Vec<_> = state
+        .repo
+        .query_deposits(&user, from_ms.as_ms().saturating_add(1), to_ms.as_ms())
+        .await?
+        .into_iter()
+        .map(|d| (d.time_ms, d.amount))
+        .collect();
+
+    let stats = PerformanceStats::compute(&trades, starting_equity, &deposits);
+
+    Ok(Json(PerformanceResponse {
+        closed_count: stats.closed_count,
+        win_count: stats.win_count,
+        loss_count: stats.loss_count,
+        win_rate_pct: stats.win_rate_pct.map(|d| d.to_canonical_string()),
+        avg_win: stats.avg_win.map(|d| d.to_canonical_string()),
+        avg_loss: stats.avg_loss.map(|d| d.to_canonical_string()),
+        profit_factor: stats.profit_factor.map(|d| d.to_canonical_string()),
+        max_drawdown: stats.max_drawdown.to_canonical_string(),
+        max_drawdown_pct: stats.max_drawdown_pct.map(|d| d.to_canonical_string()),
+        sharpe_ratio: stats.sharpe_ratio.map(|d| d.to_canonical_string()),
+        tainted,
+    }))
+}
@@ -1,158 +1,265 @@
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use std::str::FromStr;
 
+use crate::api::accounts::{resolve_subject, Subject};
+use crate::api::attributions::{attribution_status_for_users, AttributionStatus};
+use crate::api::cache::cached;
+use crate::api::validate::{parse_coin, parse_group_by, TimeRange, ValidQuery};
 use crate::api::AppState;
+use crate::client::PnlRequest;
 use crate::config::PnlMode;
 use crate::domain::{Address, Coin, Decimal, TimeMs};
+use crate::engine::{ReturnMethod, TaintMode};
 use crate::error::AppError;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct PnlQuery {
-    pub user: String,
+    /// Required unless `account` is given.
+    pub user: Option<String>,
+    /// Named account to aggregate across its member addresses, instead of `user`.
+    pub account: Option<String>,
+    /// Also include the sub-accounts of each address (requires `SUB_ACCOUNT_ROLLUP`).
+    pub include_sub_accounts: Option<bool>,
     pub coin: Option<String>,
     pub from_ms: Option<i64>,
     pub to_ms: Option<i64>,
     pub builder_only: Option<bool>,
+    pub taint_mode: Option<String>,
     pub max_start_capital: Option<String>,
+    /// `gross`, `netFees`, or `netAllFees`; defaults to `PNL_MODE`.
+    pub pnl_mode: Option<String>,
+    /// `simple` (default), `twr` (time-weighted), or `irr` (money-weighted) for `returnPct`.
+    pub return_method: Option<String>,
+    /// `strategy` to also split the totals by lifecycle strategy in `groups`.
+    pub group_by: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PnlResponse {
     pub realized_pnl: String,
     pub return_pct: String,
+    /// Net of rebates; negative when rebates exceed fees charged.
     pub fees_paid: String,
+    /// Maker rebates (negative fees) included in `feesPaid`, as a positive amount.
+    pub rebates_received: String,
+    /// Builder fees allocated to the counted fill effects; only `netAllFees` subtracts them.
+    pub builder_fees_paid: String,
+    /// Same as `builderFeesPaid`; kept for existing clients.
+    pub builder_fees: String,
+    pub pnl_mode: PnlMode,
+    /// How `realizedPnl` was derived under `pnlMode`.
+    pub pnl_formula: String,
     pub trade_count: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tainted: Option<bool>,
+    /// Lifecycles only partly counted under `taintMode=fill`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_lifecycles: Option<usize>,
+    /// With `groupBy=strategy`, the totals split by strategy; omitted otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<StrategyPnlDto>>,
+    #[serde(flatten)]
+    pub attribution: AttributionStatus,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyPnlDto {
+    /// Omitted for lifecycles no strategy rule matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    pub realized_pnl: String,
+    pub fees_paid: String,
+    pub rebates_received: String,
+    pub builder_fees_paid: String,
+    pub trade_count: i64,
 }
 
+/// Realized PnL, return, and fees for a user or account over a window.
+#[utoipa::path(
+    get,
+    path = "/v1/pnl",
+    tag = "pnl",
+    params(PnlQuery),
+    responses((status = 200, body = PnlResponse))
+)]
 pub async fn get_pnl(
-    Query(params): Query<PnlQuery>,
+    ValidQuery(params): ValidQuery<PnlQuery>,
     State(state): State<AppState>,
 ) -> Result<Json<PnlResponse>, AppError> {
-    let user = Address::from_str(&params.user)
-        .map_err(|_| AppError::BadRequest("Invalid user address".into()))?;
+    Ok(Json(cached_pnl_response(&state, &params).await?))
+}
 
+/// PnL for one user or account, served from the response cache when possible.
+pub(crate) async fn cached_pnl_response(
+    state: &AppState,
+    params: &PnlQuery,
+) -> Result<PnlResponse, AppError> {
+    let subject = resolve_subject(
+        state,
+        params.user.as_deref(),
+        params.account.as_deref(),
+        params.include_sub_accounts.unwrap_or(false),
+    )
+    .await?;
+    let request = pnl_request(state, params, &subject)?;
+    let config = state.config.load();
+    // Members are part of the key so regrouping an account takes effect immediately.
+    let key = PnlCacheKey {
+        account: matches!(subject, Subject::Account(_)),
+        members: subject.members(),
+        coin: request.coin.as_ref(),
+        from_ms: request.from_ms,
+        to_ms: request.to_ms,
+        builder_only: request.builder_only,
+        taint_mode: request.taint_mode.as_str(),
+        max_start_capital: request.max_start_capital.map(|d| d.to_canonical_string()),
+        pnl_mode: request.pnl_mode.unwrap_or(config.pnl_mode),
+        return_method: request.return_method.as_str(),
+        by_strategy: request.by_strategy,
+    };
+    cached(
+        state,
+        "pnl",
+        &key,
+        config.response_cache_ttl(),
+        subject.members(),
+        || pnl_response(state, &request, &subject),
+    )
+    .await
+}
+
+/// Response cache key for a PnL query, built from its parsed parameters so that every
+/// spelling of the same query shares an entry.
+#[derive(Serialize)]
+struct PnlCacheKey<'a> {
+    account: bool,
+    members: &'a [Address],
+    coin: Option<&'a Coin>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+    builder_only: bool,
+    taint_mode: &'static str,
+    max_start_capital: Option<String>,
+    pnl_mode: PnlMode,
+    return_method: &'static str,
+    by_strategy: bool,
+}
+
+fn pnl_request(
+    state: &AppState,
+    params: &PnlQuery,
+    subject: &Subject,
+) -> Result<PnlRequest, AppError> {
     let coin = params
         .coin
         .as_deref()
         .map(str::trim)
         .filter(|s| !s.is_empty())
-        .map(|s| Coin::new(s.to_string()));
-
-    // If fromMs not provided, use earliest deposit time as default
-    // This ensures equity_at_start is calculated from when the user actually had capital
-    let from_ms = if let Some(from) = params.from_ms {
-        Some(TimeMs::new(from))
-    } else {
-        state
-            .repo
-            .get_earliest_deposit_timestamp(&user)
-            .await
-            .map_err(|e| AppError::Internal(e.to_string()))?
-            .map(TimeMs::new)
-    };
-    let to_ms = params.to_ms.map(TimeMs::new);
-
-    if let (Some(from), Some(to)) = (from_ms, to_ms) {
-        if from > to {
-            return Err(AppError::BadRequest("fromMs must be <= toMs".to_string()));
-        }
-    }
+        .map(|s| parse_coin(&state.orchestrator.coins(), s))
+        .transpose()?;
 
+    let TimeRange { from_ms, to_ms } = TimeRange::new(params.from_ms, params.to_ms)?;
     let builder_only = params.builder_only.unwrap_or(false);
+    let taint_mode = params
+        .taint_mode
+        .as_deref()
+        .map(TaintMode::from_str)
+        .transpose()
+        .map_err(|_| {
+            AppError::BadRequest("taintMode must be one of: lifecycle, fill".to_string())
+        })?
+        .unwrap_or_default();
     let max_start_capital = params
         .max_start_capital
         .as_deref()
         .map(Decimal::from_str_canonical)
         .transpose()
         .map_err(|_| AppError::BadRequest("Invalid maxStartCapital".to_string()))?;
-
-    state
-        .orchestrator
-        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
-        .await
-        .map_err(|e| {
-            tracing::error!(user=%user, error=%e, "Compilation failed");
-            AppError::Internal(format!("Compilation failed: {}", e))
+    let pnl_mode = params
+        .pnl_mode
+        .as_deref()
+        .map(PnlMode::from_str)
+        .transpose()
+        .map_err(|_| {
+            AppError::BadRequest("pnlMode must be one of: gross, netFees, netAllFees".to_string())
         })?;
 
-    let effects = state
-        .repo
-        .query_fill_effects_for_pnl(&user, coin.as_ref(), from_ms, to_ms)
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
-
-    let (filtered_effects, tainted) = if builder_only {
-        let mut lifecycle_ids: Vec<i64> = effects.iter().map(|e| e.lifecycle_id).collect();
-        lifecycle_ids.sort_unstable();
-        lifecycle_ids.dedup();
-
-        let tainted_ids = state
-            .repo
-            .query_tainted_lifecycle_ids(&lifecycle_ids)
-            .await
-            .map_err(|e| AppError::Internal(e.to_string()))?;
-
-        let mut tainted_set = std::collections::HashSet::new();
-        for id in tainted_ids {
-            tainted_set.insert(id);
-        }
-
-        let mut had_exclusions = false;
-        let included: Vec<_> = effects
-            .into_iter()
-            .filter(|e| {
-                let keep = !tainted_set.contains(&e.lifecycle_id);
-                had_exclusions |= !keep;
-                keep
-            })
-            .collect();
-
-        (included, Some(had_exclusions))
-    } else {
-        (effects, None)
-    };
-
-    let mut realized_pnl = Decimal::zero();
-    let mut fees_paid = Decimal::zero();
-
-    for effect in &filtered_effects {
-        realized_pnl = realized_pnl + effect.closed_pnl;
-        fees_paid = fees_paid + effect.fee;
-    }
-
-    if state.config.pnl_mode == PnlMode::Net {
-        realized_pnl = realized_pnl - fees_paid;
-    }
+    let return_method = params
+        .return_method
+        .as_deref()
+        .map(ReturnMethod::from_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("returnMethod must be one of: simple, twr, irr".to_string()))?
+        .unwrap_or_default();
+    let by_strategy = parse_group_by(params.group_by.as_deref())?;
 
-    let equity_at_start = state
-        .equity_resolver
-        .resolve_equity(&user, from_ms.unwrap_or(TimeMs::new(0)))
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(PnlRequest {
+        user: subject.members()[0].clone(),
+        coin,
+        from_ms,
+        to_ms,
+        builder_only,
+        taint_mode,
+        max_start_capital,
+        pnl_mode,
+        return_method,
+        by_strategy,
+    })
+}
 
-    let effective_capital = match max_start_capital {
-        Some(max) if equity_at_start > max => max,
-        _ => equity_at_start,
+async fn pnl_response(
+    state: &AppState,
+    request: &PnlRequest,
+    subject: &Subject,
+) -> Result<PnlResponse, AppError> {
+    let pnl = match subject {
+        Subject::User(_) => state.ledger.pnl(request).await?,
+        Subject::Account(members) => state.ledger.account_pnl(members, request).await?,
     };
 
-    let return_pct = if effective_capital.is_zero() {
-        Decimal::zero()
-    } else {
-        (realized_pnl / effective_capital) * Decimal::hundred()
-    };
+    let attribution = attribution_status_for_users(
+        state,
+        request.builder_only,
+        subject.members(),
+        request.coin.as_ref(),
+        pnl.from_ms,
+        request.to_ms,
+    )
+    .await?;
 
-    Ok(Json(PnlResponse {
-        realized_pnl: realized_pnl.to_canonical_string(),
-        return_pct: return_pct.to_canonical_string(),
-        fees_paid: fees_paid.to_canonical_string(),
-        trade_count: filtered_effects.len() as i64,
-        tainted,
-    }))
+    Ok(PnlResponse {
+        realized_pnl: pnl.realized_pnl.to_canonical_string(),
+        return_pct: pnl.return_pct.to_canonical_string(),
+        fees_paid: pnl.fees.fees_paid.to_canonical_string(),
+        rebates_received: pnl.fees.rebates_received.to_canonical_string(),
+        builder_fees_paid: pnl.builder_fees.to_canonical_string(),
+        builder_fees: pnl.builder_fees.to_canonical_string(),
+        pnl_mode: pnl.pnl_mode,
+        pnl_formula: pnl.pnl_mode.formula().to_string(),
+        trade_count: pnl.trade_count,
+        tainted: pnl.tainted,
+        partial_lifecycles: pnl.partial_lifecycles,
+        groups: pnl.by_strategy.map(|groups| {
+            groups
+                .into_iter()
+                .map(|g| StrategyPnlDto {
+                    strategy: g.strategy,
+                    realized_pnl: g.realized_pnl.to_canonical_string(),
+                    fees_paid: g.fees.fees_paid.to_canonical_string(),
+                    rebates_received: g.fees.rebates_received.to_canonical_string(),
+                    builder_fees_paid: g.builder_fees.to_canonical_string(),
+                    trade_count: g.trade_count,
+                })
+                .collect()
+        }),
+        attribution,
+    })
 }
 
@@ -1,88 +1,307 @@
+use crate::api::accounts::{resolve_subject, Subject};
+use crate::api::attributions::{attribution_status_for_users, AttributionStatus};
+use crate::api::cache::cached;
+use crate::api::validate::{
+    parse_address, parse_coin, parse_dex, Pagination, TimeRange, ValidQuery, ValidatedAddress,
+};
 use crate::api::AppState;
-use crate::domain::{Address, Coin, TimeMs};
+use crate::db::repo::{OpenPositionRow, SnapshotKey};
+use crate::domain::{Address, Coin, Decimal, TimeMs};
+use crate::engine::bucket_net_size;
 use crate::error::AppError;
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct PositionsHistoryQuery {
-    pub user: String,
+    /// Required unless `account` is given.
+    pub user: Option<String>,
+    /// Named account whose member addresses' snapshots are merged, instead of `user`.
+    pub account: Option<String>,
+    /// Also include the sub-accounts of each address (requires `SUB_ACCOUNT_ROLLUP`).
+    pub include_sub_accounts: Option<bool>,
     pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
     pub from_ms: Option<i64>,
     pub to_ms: Option<i64>,
     pub builder_only: Option<bool>,
+    /// Page size (1-10000); without it and `cursor` the whole history is returned.
+    pub limit: Option<i64>,
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    /// Value each snapshot at the mark price of its hour.
+    pub include_unrealized: Option<bool>,
+    /// Downsample to the last snapshot in each of at most this many equal time buckets
+    /// per coin (1-10000); cannot be combined with `limit` or `cursor`.
+    pub max_points: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+/// Page size used when `cursor` is given without `limit`.
+const DEFAULT_HISTORY_PAGE: i64 = 1000;
+const MAX_HISTORY_PAGE: i64 = 10_000;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionsHistoryResponse {
     pub snapshots: Vec<PositionSnapshotDto>,
+    /// Pass as `cursor` to fetch the next page; omitted on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tainted: Option<bool>,
+    #[serde(flatten)]
+    pub attribution: AttributionStatus,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionSnapshotDto {
+    /// Address the snapshot belongs to; present only for `account` and
+    /// `includeSubAccounts` queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
     pub time_ms: i64,
     pub coin: String,
     pub net_size: String,
     pub avg_entry_px: String,
     pub lifecycle_id: String,
+    /// Price at the start of the snapshot's hour; with `includeUnrealized` only, and
+    /// omitted when no price is known for that hour.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tainted: Option<bool>,
 }
 
+/// Position snapshots after every fill, for a user or every address of an account.
+#[utoipa::path(
+    get,
+    path = "/v1/positions/history",
+    tag = "positions",
+    params(PositionsHistoryQuery),
+    responses((status = 200, body = PositionsHistoryResponse))
+)]
 pub async fn get_positions_history(
-    Query(params): Query<PositionsHistoryQuery>,
+    ValidQuery(params): ValidQuery<PositionsHistoryQuery>,
+    range: TimeRange,
+    page: Pagination<MAX_HISTORY_PAGE>,
     State(state): State<AppState>,
 ) -> Result<Json<PositionsHistoryResponse>, AppError> {
-    let user = Address::from_str(&params.user)
-        .map_err(|_| AppError::BadRequest("Invalid user address".into()))?;
+    let subject = resolve_subject(
+        &state,
+        params.user.as_deref(),
+        params.account.as_deref(),
+        params.include_sub_accounts.unwrap_or(false),
+    )
+    .await?;
+    let request = history_request(&state, &params, range, &page)?;
+    let key = HistoryCacheKey {
+        account: matches!(subject, Subject::Account(_)),
+        members: subject.members(),
+        coin: request.coin.as_ref(),
+        dex: request.dex.as_deref(),
+        from_ms: request.from_ms,
+        to_ms: request.to_ms,
+        builder_only: request.builder_only,
+        limit: request.limit,
+        cursor: request.cursor.as_ref().map(HistoryCursor::encode),
+        max_points: request.max_points,
+        include_unrealized: request.include_unrealized,
+    };
+    let response = cached(
+        &state,
+        "positions/history",
+        &key,
+        state.config.load().response_cache_ttl(),
+        subject.members(),
+        || positions_history_response(&state, &request, &subject),
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+/// Parsed `/v1/positions/history` parameters.
+struct HistoryRequest {
+    coin: Option<Coin>,
+    dex: Option<String>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+    builder_only: bool,
+    limit: Option<i64>,
+    cursor: Option<HistoryCursor>,
+    max_points: Option<usize>,
+    include_unrealized: bool,
+}
+
+/// Response cache key for a positions history query, built from its parsed parameters so
+/// that every spelling of the same query shares an entry.
+#[derive(Serialize)]
+struct HistoryCacheKey<'a> {
+    account: bool,
+    members: &'a [Address],
+    coin: Option<&'a Coin>,
+    dex: Option<&'a str>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+    builder_only: bool,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    max_points: Option<usize>,
+    include_unrealized: bool,
+}
 
+fn history_request(
+    state: &AppState,
+    params: &PositionsHistoryQuery,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    page: &Pagination<MAX_HISTORY_PAGE>,
+) -> Result<HistoryRequest, AppError> {
     let coin = match params.coin.as_deref() {
-        Some(c) => Some(Coin::from_str(c).map_err(|_| AppError::BadRequest("Invalid coin".into()))?),
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
         None => None,
     };
+    let dex = parse_dex(params.dex.as_deref(), coin.as_ref())?;
 
-    let from_ms = params.from_ms.map(TimeMs::new);
-    let to_ms = params.to_ms.map(TimeMs::new);
-    if let (Some(from_ms), Some(to_ms)) = (from_ms, to_ms) {
-        if from_ms > to_ms {
-            return Err(AppError::BadRequest("fromMs must be <= toMs".into()));
-        }
-    }
     let builder_only = params.builder_only.unwrap_or(false);
+    let limit = match (page.limit, page.cursor.is_some()) {
+        (Some(limit), _) => Some(limit),
+        (None, true) => Some(DEFAULT_HISTORY_PAGE),
+        (None, false) => None,
+    };
+    let cursor = page
+        .cursor
+        .as_deref()
+        .map(|c| {
+            HistoryCursor::decode(c).ok_or_else(|| AppError::BadRequest("Invalid cursor".into()))
+        })
+        .transpose()?;
+    let max_points = match params.max_points {
+        Some(_) if limit.is_some() => {
+            return Err(AppError::BadRequest(
+                "maxPoints cannot be combined with limit or cursor".into(),
+            ))
+        }
+        Some(n) => Some(parse_max_points(n)?),
+        None => None,
+    };
 
-    state
-        .orchestrator
-        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
-        .await
-        .map_err(|e| AppError::Internal(format!("Compilation failed: {}", e)))?;
+    Ok(HistoryRequest {
+        coin,
+        dex,
+        from_ms,
+        to_ms,
+        builder_only,
+        limit,
+        cursor,
+        max_points,
+        include_unrealized: params.include_unrealized.unwrap_or(false),
+    })
+}
 
-    let mut snapshots = state
-        .repo
-        .query_position_snapshots(&user, coin.as_ref(), from_ms, to_ms)
-        .await
+async fn positions_history_response(
+    state: &AppState,
+    request: &HistoryRequest,
+    subject: &Subject,
+) -> Result<PositionsHistoryResponse, AppError> {
+    let &HistoryRequest {
+        ref coin,
+        ref dex,
+        from_ms,
+        to_ms,
+        builder_only,
+        limit,
+        ref cursor,
+        max_points,
+        include_unrealized,
+    } = request;
+
+    let mut snapshots = Vec::new();
+    for user in subject.members() {
+        state
+            .orchestrator
+            .ensure_compiled(user, coin.as_ref(), from_ms, to_ms)
+            .await?;
+
+        let (member_from_ms, after) = match &cursor {
+            Some(cursor) => cursor.resume(user, from_ms),
+            None => (from_ms, None),
+        };
+        let rows = match max_points {
+            Some(max_points) => {
+                state
+                    .repo
+                    .query_position_snapshots_downsampled(
+                        user,
+                        coin.as_ref(),
+                        dex.as_deref(),
+                        from_ms,
+                        to_ms,
+                        max_points,
+                        builder_only,
+                    )
+                    .await
+            }
+            // One row past the page tells whether another page follows.
+            None => {
+                state
+                    .repo
+                    .query_position_snapshots(
+                        user,
+                        coin.as_ref(),
+                        dex.as_deref(),
+                        member_from_ms,
+                        to_ms,
+                        after,
+                        limit.map(|l| l + 1),
+                    )
+                    .await
+            }
+        }
         .map_err(|e| AppError::Internal(format!("Snapshot query failed: {}", e)))?;
+        snapshots.extend(rows.into_iter().map(|row| (user, row)));
+    }
 
-    snapshots.sort_by(|a, b| {
+    // Members are sorted, so ties across addresses break by address.
+    snapshots.sort_by(|(user_a, a), (user_b, b)| {
         a.time_ms
             .cmp(&b.time_ms)
+            .then_with(|| user_a.as_str().cmp(user_b.as_str()))
             .then_with(|| a.seq.cmp(&b.seq))
             .then_with(|| a.coin.as_str().cmp(b.coin.as_str()))
             .then_with(|| a.lifecycle_id.cmp(&b.lifecycle_id))
     });
 
+    let label_users = matches!(subject, Subject::Account(_));
+    let next_cursor = match limit {
+        Some(limit) if snapshots.len() > limit as usize => {
+            snapshots.truncate(limit as usize);
+            snapshots.last().map(|(user, row)| {
+                HistoryCursor {
+                    user: label_users.then(|| (*user).clone()),
+                    key: row.key(),
+                }
+                .encode()
+            })
+        }
+        _ => None,
+    };
+
     let (filtered_snapshots, tainted) = if builder_only {
-        let any_tainted = snapshots.iter().any(|s| s.lifecycle_tainted);
+        let any_tainted = snapshots.iter().any(|(_, s)| s.lifecycle_tainted);
         (
             snapshots
                 .into_iter()
-                .filter(|s| !s.lifecycle_tainted)
+                .filter(|(_, s)| !s.lifecycle_tainted)
                 .collect::<Vec<_>>(),
             Some(any_tainted),
         )
@@ -90,21 +309,805 @@ pub async fn get_positions_history(
         (snapshots, None)
     };
 
+    // Snapshots are in time order.
+    let marks = match (filtered_snapshots.first(), filtered_snapshots.last()) {
+        (Some((_, first)), Some((_, last))) if include_unrealized => {
+            let coins: BTreeSet<&Coin> = filtered_snapshots.iter().map(|(_, s)| &s.coin).collect();
+            state
+                .orchestrator
+                .mark_prices(coins, first.time_ms, last.time_ms)
+                .await?
+        }
+        _ => HashMap::new(),
+    };
+
     let snapshot_dtos = filtered_snapshots
         .into_iter()
-        .map(|s| PositionSnapshotDto {
-            time_ms: s.time_ms.as_ms(),
-            coin: s.coin.as_str().to_string(),
-            net_size: s.net_size,
-            avg_entry_px: s.avg_entry_px,
-            lifecycle_id: s.lifecycle_id.to_string(),
-            tainted: if builder_only { Some(false) } else { None },
+        .map(|(user, s)| {
+            let mark_px = marks.get(&s.coin).and_then(|m| m.at(s.time_ms));
+            let unrealized_pnl = unrealized_pnl(&s.net_size, &s.avg_entry_px, mark_px);
+            PositionSnapshotDto {
+                user: label_users.then(|| user.as_str().to_string()),
+                time_ms: s.time_ms.as_ms(),
+                coin: s.coin.as_str().to_string(),
+                net_size: s.net_size,
+                avg_entry_px: s.avg_entry_px,
+                lifecycle_id: s.lifecycle_id.to_string(),
+                mark_px: mark_px.map(|px| px.to_canonical_string()),
+                unrealized_pnl: unrealized_pnl.map(|pnl| pnl.to_canonical_string()),
+                tainted: if builder_only { Some(false) } else { None },
+            }
         })
         .collect();
+    let attribution = attribution_status_for_users(
+        state,
+        builder_only,
+        subject.members(),
+        coin.as_ref(),
+        from_ms,
+        to_ms,
+    )
+    .await?;
 
-    Ok(Json(PositionsHistoryResponse {
+    Ok(PositionsHistoryResponse {
         snapshots: snapshot_dtos,
+        next_cursor,
+        tainted,
+        attribution,
+    })
+}
+
+/// Opaque `/v1/positions/history` cursor: the last snapshot of a page and, for merged
+/// histories, the address it belonged to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HistoryCursor {
+    user: Option<Address>,
+    key: SnapshotKey,
+}
+
+impl HistoryCursor {
+    /// Hex of `time:seq:lifecycle:user:coin`; the coin goes last as it may contain `:`.
+    fn encode(&self) -> String {
+        hex::encode(format!(
+            "{}:{}:{}:{}:{}",
+            self.key.time_ms.as_ms(),
+            self.key.seq,
+            self.key.lifecycle_id,
+            self.user.as_ref().map_or("", |u| u.as_str()),
+            self.key.coin.as_str()
+        ))
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let mut parts = raw.splitn(5, ':');
+        let time_ms = parts.next()?.parse().ok()?;
+        let seq = parts.next()?.parse().ok()?;
+        let lifecycle_id = parts.next()?.parse().ok()?;
+        let user = match parts.next()? {
+            "" => None,
+            user => Some(Address::from_str(user).ok()?),
+        };
+        let coin = Coin::new(parts.next()?.to_string());
+        Some(Self {
+            user,
+            key: SnapshotKey {
+                time_ms: TimeMs::new(time_ms),
+                seq,
+                coin,
+                lifecycle_id,
+            },
+        })
+    }
+
+    /// Where `member`'s rows resume after this cursor, given the merge order
+    /// `(time_ms, user, seq, coin, lifecycle_id)`: its own rows continue after the key, an
+    /// earlier address resumes after the cursor's time and a later one at it.
+    fn resume(
+        &self,
+        member: &Address,
+        from_ms: Option<TimeMs>,
+    ) -> (Option<TimeMs>, Option<&SnapshotKey>) {
+        let at_least = |t: TimeMs| Some(from_ms.map_or(t, |from| from.max(t)));
+        let time_ms = self.key.time_ms;
+        match self.user.as_ref().map(|user| member.cmp(user)) {
+            None | Some(Ordering::Equal) => (from_ms, Some(&self.key)),
+            Some(Ordering::Less) => (at_least(TimeMs::new(time_ms.as_ms().saturating_add(1))), None),
+            Some(Ordering::Greater) => (at_least(time_ms), None),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct OpenPositionsQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
+    pub builder_only: Option<bool>,
+    pub include_unrealized: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenPositionsResponse {
+    pub positions: Vec<OpenPositionDto>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenPositionDto {
+    pub coin: String,
+    pub net_size: String,
+    pub avg_entry_px: String,
+    pub lifecycle_id: String,
+    pub lifecycle_start_ms: i64,
+    pub last_update_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+}
+
+/// Currently open lifecycles, optionally marked to market.
+#[utoipa::path(
+    get,
+    path = "/v1/positions/open",
+    tag = "positions",
+    params(OpenPositionsQuery),
+    responses((status = 200, body = OpenPositionsResponse))
+)]
+pub async fn get_open_positions(
+    ValidQuery(params): ValidQuery<OpenPositionsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<OpenPositionsResponse>, AppError> {
+    let marks = MarkPrices::default();
+    Ok(Json(open_positions_response(&state, &params, &marks).await?))
+}
+
+/// Open positions for one user. Mark prices are fetched into `marks` per dex on first need, so
+/// callers answering several users share one upstream request.
+pub(crate) async fn open_positions_response(
+    state: &AppState,
+    params: &OpenPositionsQuery,
+    marks: &MarkPrices,
+) -> Result<OpenPositionsResponse, AppError> {
+    let user = parse_address("user", &params.user)?;
+
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let dex = parse_dex(params.dex.as_deref(), coin.as_ref())?;
+    let builder_only = params.builder_only.unwrap_or(false);
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), None, None)
+        .await?;
+
+    let mut positions = state
+        .repo
+        .query_open_positions(&user, coin.as_ref())
+        .await
+        .map_err(|e| AppError::Internal(format!("Open position query failed: {}", e)))?;
+    if let Some(dex) = &dex {
+        positions.retain(|p| p.coin.is_on_dex(dex));
+    }
+
+    let (positions, tainted) = if builder_only {
+        let any_tainted = positions.iter().any(|p| p.lifecycle_tainted);
+        (
+            positions
+                .into_iter()
+                .filter(|p| !p.lifecycle_tainted)
+                .collect::<Vec<_>>(),
+            Some(any_tainted),
+        )
+    } else {
+        (positions, None)
+    };
+
+    let marks = if params.include_unrealized.unwrap_or(false) && !positions.is_empty() {
+        marks.for_positions(state, &positions).await
+    } else {
+        HashMap::new()
+    };
+
+    let coins = state.orchestrator.coins();
+    let position_dtos = positions
+        .into_iter()
+        .map(|p| {
+            let mark_px = marks.get(p.coin.as_str()).copied();
+            let unrealized_pnl = unrealized_pnl(&p.net_size, &p.avg_entry_px, mark_px);
+            let net_size = match Decimal::from_str_canonical(&p.net_size) {
+                Ok(size) => coins.format_sz(&p.coin, &size),
+                Err(_) => p.net_size,
+            };
+            OpenPositionDto {
+                coin: p.coin.as_str().to_string(),
+                net_size,
+                avg_entry_px: p.avg_entry_px,
+                lifecycle_id: p.lifecycle_id.to_string(),
+                lifecycle_start_ms: p.start_time_ms.as_ms(),
+                last_update_ms: p.last_update_ms.as_ms(),
+                mark_px: mark_px.map(|m| m.to_canonical_string()),
+                unrealized_pnl: unrealized_pnl.map(|u| u.to_canonical_string()),
+                tainted: if builder_only { Some(false) } else { None },
+            }
+        })
+        .collect();
+
+    Ok(OpenPositionsResponse {
+        positions: position_dtos,
+        tainted,
+    })
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct PositionsAtQuery {
+    pub user: String,
+    pub at_ms: i64,
+    pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
+    pub builder_only: Option<bool>,
+    pub include_unrealized: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionsAtResponse {
+    pub at_ms: i64,
+    pub positions: Vec<OpenPositionDto>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+}
+
+/// Lifecycles open at `atMs`, each with its latest snapshot at or before then.
+#[utoipa::path(
+    get,
+    path = "/v1/positions/at",
+    tag = "positions",
+    params(PositionsAtQuery),
+    responses((status = 200, body = PositionsAtResponse))
+)]
+pub async fn get_positions_at(
+    ValidQuery(params): ValidQuery<PositionsAtQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Json<PositionsAtResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let request = PositionsAtRequest {
+        dex: parse_dex(params.dex.as_deref(), coin.as_ref())?,
+        coin,
+        at: TimeMs::new(params.at_ms),
+        builder_only: params.builder_only.unwrap_or(false),
+        include_unrealized: params.include_unrealized.unwrap_or(false),
+    };
+    let users = std::slice::from_ref(&user);
+    let ttl = state.config.load().response_cache_ttl();
+    let response = cached(&state, "positions/at", &(&user, &request), ttl, users, || {
+        positions_at_response(&state, &request, &user)
+    })
+    .await?;
+    Ok(Json(response))
+}
+
+/// Parsed `/v1/positions/at` parameters; with the user, also the response cache key, so
+/// every spelling of the same query shares an entry.
+#[derive(Serialize)]
+struct PositionsAtRequest {
+    coin: Option<Coin>,
+    dex: Option<String>,
+    at: TimeMs,
+    builder_only: bool,
+    include_unrealized: bool,
+}
+
+async fn positions_at_response(
+    state: &AppState,
+    request: &PositionsAtRequest,
+    user: &Address,
+) -> Result<PositionsAtResponse, AppError> {
+    let &PositionsAtRequest {
+        ref coin,
+        ref dex,
+        at,
+        builder_only,
+        include_unrealized,
+    } = request;
+
+    state
+        .orchestrator
+        .ensure_compiled(user, coin.as_ref(), None, Some(at))
+        .await?;
+
+    let mut positions = state
+        .repo
+        .query_positions_at(user, coin.as_ref(), at)
+        .await
+        .map_err(|e| AppError::Internal(format!("Position query failed: {}", e)))?;
+    if let Some(dex) = dex {
+        positions.retain(|p| p.coin.is_on_dex(dex));
+    }
+
+    let (positions, tainted) = if builder_only {
+        let any_tainted = positions.iter().any(|p| p.lifecycle_tainted);
+        (
+            positions
+                .into_iter()
+                .filter(|p| !p.lifecycle_tainted)
+                .collect::<Vec<_>>(),
+            Some(any_tainted),
+        )
+    } else {
+        (positions, None)
+    };
+
+    // Historical marks: the hourly mark of the bucket containing `atMs`.
+    let marks = if include_unrealized && !positions.is_empty() {
+        let coins: BTreeSet<&Coin> = positions.iter().map(|p| &p.coin).collect();
+        state.orchestrator.mark_prices(coins, at, at).await?
+    } else {
+        HashMap::new()
+    };
+
+    let coins = state.orchestrator.coins();
+    let position_dtos = positions
+        .into_iter()
+        .map(|p| {
+            let mark_px = marks.get(&p.coin).and_then(|m| m.at(at));
+            let unrealized_pnl = unrealized_pnl(&p.net_size, &p.avg_entry_px, mark_px);
+            let net_size = match Decimal::from_str_canonical(&p.net_size) {
+                Ok(size) => coins.format_sz(&p.coin, &size),
+                Err(_) => p.net_size,
+            };
+            OpenPositionDto {
+                coin: p.coin.as_str().to_string(),
+                net_size,
+                avg_entry_px: p.avg_entry_px,
+                lifecycle_id: p.lifecycle_id.to_string(),
+                lifecycle_start_ms: p.start_time_ms.as_ms(),
+                last_update_ms: p.last_update_ms.as_ms(),
+                mark_px: mark_px.map(|m| m.to_canonical_string()),
+                unrealized_pnl: unrealized_pnl.map(|u| u.to_canonical_string()),
+                tainted: if builder_only { Some(false) } else { None },
+            }
+        })
+        .collect();
+
+    Ok(PositionsAtResponse {
+        at_ms: at.as_ms(),
+        positions: position_dtos,
+        tainted,
+    })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct PositionsAgingQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
+    pub builder_only: Option<bool>,
+    pub include_unrealized: Option<bool>,
+    pub sort_by: Option<String>,
+    pub as_of_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AgingSort {
+    Age,
+    Size,
+}
+
+impl FromStr for AgingSort {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "age" => Ok(AgingSort::Age),
+            "size" => Ok(AgingSort::Size),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionsAgingResponse {
+    pub as_of_ms: i64,
+    pub positions: Vec<PositionAgingDto>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionAgingDto {
+    pub coin: String,
+    pub lifecycle_id: String,
+    pub lifecycle_start_ms: i64,
+    pub age_ms: i64,
+    pub net_size: String,
+    pub avg_entry_px: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<String>,
+}
+
+/// Open lifecycles ranked by age (oldest first) or absolute size (largest first).
+#[utoipa::path(
+    get,
+    path = "/v1/positions/aging",
+    tag = "positions",
+    params(PositionsAgingQuery),
+    responses((status = 200, body = PositionsAgingResponse))
+)]
+pub async fn get_positions_aging(
+    ValidQuery(params): ValidQuery<PositionsAgingQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Json<PositionsAgingResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => Some(parse_coin(&state.orchestrator.coins(), c)?),
+        None => None,
+    };
+    let sort = match params.sort_by.as_deref() {
+        Some(s) => AgingSort::from_str(s)
+            .map_err(|_| AppError::BadRequest("sortBy must be one of: age, size".into()))?,
+        None => AgingSort::Age,
+    };
+    let dex = parse_dex(params.dex.as_deref(), coin.as_ref())?;
+    let as_of = params.as_of_ms.map(TimeMs::new).unwrap_or_else(TimeMs::now);
+    let builder_only = params.builder_only.unwrap_or(false);
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, coin.as_ref(), None, None)
+        .await?;
+
+    let mut positions = state
+        .repo
+        .query_open_positions(&user, coin.as_ref())
+        .await
+        .map_err(|e| AppError::Internal(format!("Open position query failed: {}", e)))?;
+    if let Some(dex) = &dex {
+        positions.retain(|p| p.coin.is_on_dex(dex));
+    }
+
+    let (positions, tainted) = if builder_only {
+        let any_tainted = positions.iter().any(|p| p.lifecycle_tainted);
+        (
+            positions
+                .into_iter()
+                .filter(|p| !p.lifecycle_tainted)
+                .collect::<Vec<_>>(),
+            Some(any_tainted),
+        )
+    } else {
+        (positions, None)
+    };
+
+    let marks = if params.include_unrealized.unwrap_or(false) && !positions.is_empty() {
+        MarkPrices::default().for_positions(&state, &positions).await
+    } else {
+        HashMap::new()
+    };
+
+    let mut rows: Vec<(Decimal, i64, PositionAgingDto)> = positions
+        .into_iter()
+        .map(|p| {
+            let abs_size = Decimal::from_str_canonical(&p.net_size)
+                .map(|d| d.abs())
+                .unwrap_or_default();
+            let mark_px = marks.get(p.coin.as_str()).copied();
+            let unrealized_pnl = unrealized_pnl(&p.net_size, &p.avg_entry_px, mark_px);
+            let dto = PositionAgingDto {
+                coin: p.coin.as_str().to_string(),
+                lifecycle_id: p.lifecycle_id.to_string(),
+                lifecycle_start_ms: p.start_time_ms.as_ms(),
+                age_ms: (as_of.as_ms() - p.start_time_ms.as_ms()).max(0),
+                net_size: p.net_size,
+                avg_entry_px: p.avg_entry_px,
+                mark_px: mark_px.map(|m| m.to_canonical_string()),
+                unrealized_pnl: unrealized_pnl.map(|u| u.to_canonical_string()),
+            };
+            (abs_size, p.lifecycle_id, dto)
+        })
+        .collect();
+
+    rows.sort_by(|(a_size, a_id, a), (b_size, b_id, b)| {
+        let primary = match sort {
+            AgingSort::Age => b.age_ms.cmp(&a.age_ms),
+            AgingSort::Size => b_size.cmp(a_size),
+        };
+        primary.then_with(|| a.coin.cmp(&b.coin)).then_with(|| a_id.cmp(b_id))
+    });
+
+    Ok(Json(PositionsAgingResponse {
+        as_of_ms: as_of.as_ms(),
+        positions: rows.into_iter().map(|(_, _, dto)| dto).collect(),
         tainted,
     }))
 }
 
+/// Unrealized PnL of an open position at `mark_px`: `(mark - entry) * netSize`.
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct PositionsSeriesQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    pub interval_ms: Option<i64>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionsSeriesResponse {
+    pub coin: String,
+    pub interval_ms: i64,
+    /// Only buckets containing snapshots; the size is unchanged through omitted ones.
+    pub buckets: Vec<SizeBucketDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeBucketDto {
+    pub start_ms: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub snapshot_count: usize,
+}
+
+/// Net size of one coin as OHLC buckets.
+#[utoipa::path(
+    get,
+    path = "/v1/positions/series",
+    tag = "positions",
+    params(PositionsSeriesQuery),
+    responses((status = 200, body = PositionsSeriesResponse))
+)]
+pub async fn get_positions_series(
+    ValidQuery(params): ValidQuery<PositionsSeriesQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<PositionsSeriesResponse>, AppError> {
+    let coin = params
+        .coin
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("coin is required".into()))?;
+    let coin = parse_coin(&state.orchestrator.coins(), coin)?;
+    let interval_ms = match params.interval_ms {
+        Some(i) if i > 0 => i,
+        Some(_) => return Err(AppError::BadRequest("intervalMs must be > 0".into())),
+        None => return Err(AppError::BadRequest("intervalMs is required".into())),
+    };
+
+    state
+        .orchestrator
+        .ensure_compiled(&user, Some(&coin), from_ms, to_ms)
+        .await?;
+
+    let snapshots = state
+        .repo
+        .query_position_snapshots(&user, Some(&coin), None, from_ms, to_ms, None, None)
+        .await
+        .map_err(|e| AppError::Internal(format!("Snapshot query failed: {}", e)))?;
+
+    let carried_in = match from_ms {
+        Some(from_ms) => state
+            .repo
+            .query_net_size_before(&user, &coin, from_ms)
+            .await?
+            .map(|s| parse_net_size(&s))
+            .transpose()?
+            .unwrap_or_default(),
+        None => Decimal::zero(),
+    };
+    let points = snapshots
+        .iter()
+        .map(|s| Ok((s.time_ms, parse_net_size(&s.net_size)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let buckets = bucket_net_size(&points, interval_ms, carried_in)
+        .into_iter()
+        .map(|b| SizeBucketDto {
+            start_ms: b.start_ms.as_ms(),
+            open: b.open.to_canonical_string(),
+            high: b.high.to_canonical_string(),
+            low: b.low.to_canonical_string(),
+            close: b.close.to_canonical_string(),
+            snapshot_count: b.snapshot_count,
+        })
+        .collect();
+
+    Ok(Json(PositionsSeriesResponse {
+        coin: coin.as_str().to_string(),
+        interval_ms,
+        buckets,
+    }))
+}
+
+/// Validate a `maxPoints` parameter.
+pub(crate) fn parse_max_points(max_points: i64) -> Result<usize, AppError> {
+    if !(1..=MAX_HISTORY_PAGE).contains(&max_points) {
+        return Err(AppError::BadRequest(format!(
+            "maxPoints must be between 1 and {}",
+            MAX_HISTORY_PAGE
+        )));
+    }
+    Ok(max_points as usize)
+}
+
+fn parse_net_size(net_size: &str) -> Result<Decimal, AppError> {
+    Decimal::from_str_canonical(net_size)
+        .map_err(|_| AppError::Internal(format!("Invalid stored net size: {}", net_size)))
+}
+
+fn unrealized_pnl(net_size: &str, avg_entry_px: &str, mark_px: Option<Decimal>) -> Option<Decimal> {
+    let mark = mark_px?;
+    let net_size = Decimal::from_str_canonical(net_size).ok()?;
+    let avg_entry_px = Decimal::from_str_canonical(avg_entry_px).ok()?;
+    Some((mark - avg_entry_px) * net_size)
+}
+
+/// Mid prices from `allMids`, fetched once per dex and shared by the users one request
+/// answers.
+#[derive(Debug, Default)]
+pub(crate) struct MarkPrices {
+    /// Mids of each fetched dex, keyed by dex (`""` for the default one).
+    by_dex: tokio::sync::Mutex<HashMap<String, HashMap<String, Decimal>>>,
+}
+
+impl MarkPrices {
+    /// Mid prices of the coins of every dex `positions` trade on, by coin.
+    ///
+    /// Marks are best-effort: positions are still returned when the upstream is
+    /// unreachable, just without the unrealized PnL fields.
+    async fn for_positions(
+        &self,
+        state: &AppState,
+        positions: &[OpenPositionRow],
+    ) -> HashMap<String, Decimal> {
+        let dexes: BTreeSet<&str> = positions
+            .iter()
+            .map(|p| p.coin.dex().unwrap_or_default())
+            .collect();
+        let mut by_dex = self.by_dex.lock().await;
+        let mut marks = HashMap::new();
+        for dex in dexes {
+            if !by_dex.contains_key(dex) {
+                let base_url = &state.config.load().hyperliquid_api_url;
+                let mids = match fetch_mark_prices(&state.http_client, base_url, dex).await {
+                    Ok(mids) => mids,
+                    Err(e) => {
+                        tracing::warn!(dex, "Failed to fetch mark prices: {}", e);
+                        HashMap::new()
+                    }
+                };
+                by_dex.insert(dex.to_string(), mids);
+            }
+            marks.extend(by_dex[dex].iter().map(|(coin, px)| (coin.clone(), *px)));
+        }
+        marks
+    }
+}
+
+/// Fetch mid prices for all perp coins of `dex` from Hyperliquid (`allMids`); the empty
+/// string is the default dex.
+async fn fetch_mark_prices(
+    client: &reqwest::Client,
+    base_url: &str,
+    dex: &str,
+) -> Result<HashMap<String, Decimal>, String> {
+    let url = format!("{}/info", base_url);
+    let payload = if dex.is_empty() {
+        serde_json::json!({ "type": "allMids" })
+    } else {
+        serde_json::json!({ "type": "allMids", "dex": dex })
+    };
+
+    let response = client
+        .post(&url)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Parse error: {}", e))?;
+
+    let mids = parse_mark_prices(&json);
+    if dex.is_empty() {
+        return Ok(mids);
+    }
+    // Qualify bare names so they match the dex's stored coins.
+    Ok(mids
+        .into_iter()
+        .map(|(coin, px)| match coin.contains(Coin::DEX_SEPARATOR) {
+            true => (coin, px),
+            false => (format!("{}{}{}", dex, Coin::DEX_SEPARATOR, coin), px),
+        })
+        .collect())
+}
+
+fn parse_mark_prices(json: &serde_json::Value) -> HashMap<String, Decimal> {
+    json.as_object()
+        .map(|mids| {
+            mids.iter()
+                .filter_map(|(coin, px)| {
+                    let px = Decimal::from_str_canonical(px.as_str()?).ok()?;
+                    Some((coin.clone(), px))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mark_prices_skips_unparseable_entries() {
+        let json = serde_json::json!({
+            "BTC": "50000.5",
+            "ETH": "not-a-number",
+            "SOL": 123
+        });
+
+        let marks = parse_mark_prices(&json);
+
+        assert_eq!(marks.len(), 1);
+        assert_eq!(marks["BTC"].to_canonical_string(), "50000.5");
+    }
+
+    #[test]
+    fn test_parse_mark_prices_non_object_is_empty() {
+        assert!(parse_mark_prices(&serde_json::json!([])).is_empty());
+    }
+
+    #[test]
+    fn test_history_cursor_round_trips() {
+        let cursor = HistoryCursor {
+            user: Some(Address::new(format!("0x{}", "ab".repeat(20)))),
+            key: SnapshotKey {
+                time_ms: TimeMs::new(4000),
+                seq: 2,
+                coin: Coin::new("xyz:TSLA".to_string()),
+                lifecycle_id: -42,
+            },
+        };
+        assert_eq!(HistoryCursor::decode(&cursor.encode()), Some(cursor));
+
+        assert_eq!(HistoryCursor::decode("zz"), None);
+        assert_eq!(HistoryCursor::decode(&hex::encode("1:2")), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Read-only mode (`READ_ONLY`).
+//!
+//! The orchestrator already answers queries from what is stored without ingesting or
+//! compiling; this refuses the endpoints whose purpose is to write, so callers get a
+//! `READ_ONLY` error up front instead of a half-applied request. Everything not listed in
+//! [`writes`] as an exception is treated as a write, so new mutating routes are covered too.
+
+use crate::error::AppError;
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// Middleware rejecting writes with `403 READ_ONLY` when `read_only` is set.
+pub async fn reject_writes(State(read_only): State<bool>, request: Request, next: Next) -> Response {
+    if read_only && writes(request.method(), request.uri().path()) {
+        return AppError::ReadOnly(format!(
+            "{} {} is disabled while READ_ONLY is set",
+            request.method(),
+            request.uri().path()
+        ))
+        .into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether a request may write to the database.
+///
+/// Batch queries are `POST`s only to carry a body. Backups read the database into a separate
+/// file, the config and log filter endpoints change process state only, and a replay without
+/// `apply` only diffs; applying one is refused by the orchestrator.
+fn writes(method: &Method, path: &str) -> bool {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return false;
+    }
+    !(path.starts_with("/v1/batch/")
+        || matches!(
+            path,
+            "/v1/admin/backup"
+                | "/v1/admin/config/reload"
+                | "/v1/admin/log-filter"
+                | "/v1/admin/replay"
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes() {
+        assert!(!writes(&Method::GET, "/v1/pnl"));
+        assert!(!writes(&Method::POST, "/v1/batch/pnl"));
+        assert!(!writes(&Method::PUT, "/v1/admin/log-filter"));
+        assert!(writes(&Method::POST, "/v1/ingest/fills"));
+        assert!(writes(&Method::PUT, "/v1/admin/accounts/desk"));
+        assert!(writes(&Method::DELETE, "/v1/admin/quarantine/1"));
+        assert!(writes(&Method::POST, "/v1/admin/maintenance"));
+    }
+}
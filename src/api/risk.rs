@@ -1,82 +1,36 @@
-//! Risk fields endpoint - fetches real-time risk data from Hyperliquid.
+//! Risk fields endpoint - fetches real-time risk data from Hyperliquid, and historical
+//! exposure and drawdown from the derived tables.
 
+use crate::api::cache::cached;
+use crate::api::validate::{TimeRange, ValidQuery, ValidatedAddress};
 use crate::api::AppState;
 use crate::domain::Decimal;
+use crate::engine::RiskDay;
 use crate::error::AppError;
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::OnceLock;
-use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
-
-/// Cache entry with timestamp for TTL-based expiration.
-struct CacheEntry {
-    response: RiskResponse,
-    cached_at: Instant,
-}
-
-/// Simple in-memory cache for risk responses.
-/// Keyed by user address with configurable TTL.
-struct RiskCache {
-    entries: RwLock<HashMap<String, CacheEntry>>,
-    ttl: Duration,
-}
-
-impl RiskCache {
-    fn new(ttl_secs: u64) -> Self {
-        Self {
-            entries: RwLock::new(HashMap::new()),
-            ttl: Duration::from_secs(ttl_secs),
-        }
-    }
-
-    async fn get(&self, user: &str) -> Option<RiskResponse> {
-        let entries = self.entries.read().await;
-        if let Some(entry) = entries.get(user) {
-            if entry.cached_at.elapsed() < self.ttl {
-                return Some(entry.response.clone());
-            }
-        }
-        None
-    }
-
-    async fn set(&self, user: String, response: RiskResponse) {
-        let mut entries = self.entries.write().await;
-        entries.insert(
-            user,
-            CacheEntry {
-                response,
-                cached_at: Instant::now(),
-            },
-        );
-        // Cleanup old entries (simple eviction)
-        entries.retain(|_, entry| entry.cached_at.elapsed() < self.ttl * 2);
-    }
-}
+use utoipa::{IntoParams, ToSchema};
+use std::time::Duration;
 
-/// Global cache instance with 5-second TTL for rate limiting protection.
-static RISK_CACHE: OnceLock<RiskCache> = OnceLock::new();
+/// Live upstream data is reused this long to shield Hyperliquid from polling.
+const RISK_CACHE_TTL: Duration = Duration::from_secs(5);
 
-fn get_cache() -> &'static RiskCache {
-    RISK_CACHE.get_or_init(|| RiskCache::new(5))
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct RiskQuery {
     pub user: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RiskResponse {
     pub positions: Vec<PositionRisk>,
     pub cross_margin_summary: CrossMarginSummary,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionRisk {
     pub coin: String,
@@ -90,7 +44,7 @@ pub struct PositionRisk {
     pub max_leverage: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CrossMarginSummary {
     pub account_value: String,
@@ -100,35 +54,130 @@ pub struct CrossMarginSummary {
     pub withdrawable: String,
 }
 
+/// Live margin and liquidation data from Hyperliquid.
+#[utoipa::path(
+    get,
+    path = "/v1/risk",
+    tag = "risk",
+    params(RiskQuery),
+    responses((status = 200, body = RiskResponse))
+)]
 pub async fn get_risk(
-    Query(params): Query<RiskQuery>,
+    ValidQuery(_): ValidQuery<RiskQuery>,
+    ValidatedAddress(user): ValidatedAddress,
     State(state): State<AppState>,
 ) -> Result<Json<RiskResponse>, AppError> {
-    let user = &params.user;
+    // Risk reads no derived tables, so entries only expire.
+    let user_state = cached(&state, "risk", &user, RISK_CACHE_TTL, &[], || async {
+        let user = user.as_str();
+        fetch_user_state(&state.http_client, &state.config.load().hyperliquid_api_url, user)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to fetch user state for {}: {}", user, e);
+                AppError::UpstreamUnavailable("Failed to fetch risk data from upstream".into())
+            })
+    })
+    .await?;
 
-    // Validate address format
-    if !user.starts_with("0x") || user.len() != 42 {
-        return Err(AppError::BadRequest("Invalid user address".into()));
-    }
+    Ok(Json(user_state))
+}
 
-    // Check cache first for rate limiting protection
-    let cache = get_cache();
-    if let Some(cached) = cache.get(user).await {
-        return Ok(Json(cached));
-    }
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct RiskHistoryQuery {
+    pub user: String,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+}
 
-    // Fetch live user state from Hyperliquid
-    let user_state = fetch_user_state(&state.http_client, &state.config.hyperliquid_api_url, user)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to fetch user state for {}: {}", user, e);
-            AppError::Internal("Failed to fetch risk data from upstream".into())
-        })?;
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskHistoryResponse {
+    pub days: Vec<RiskDayDto>,
+    /// Highest gross exposure over the window.
+    pub peak_gross_exposure: String,
+    /// Largest fall of marked equity below its running high over the window.
+    pub max_drawdown: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_drawdown_pct: Option<String>,
+}
 
-    // Cache the response
-    cache.set(user.clone(), user_state.clone()).await;
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskDayDto {
+    /// UTC midnight starting the day.
+    pub day_start_ms: i64,
+    /// Sum of `|size * price|` over open positions at the end of the day.
+    pub gross_exposure: String,
+    /// Sum of `size * price`, shorts negative, at the end of the day.
+    pub net_exposure: String,
+    pub peak_gross_exposure: String,
+    /// Net flows plus realized and unrealized PnL at the end of the day.
+    pub equity: String,
+    pub max_drawdown: String,
+    /// `maxDrawdown` as a percentage of the high it fell from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_drawdown_pct: Option<String>,
+}
 
-    Ok(Json(user_state))
+/// Gross and net exposure, peak exposure and drawdown per UTC day, valued at historical
+/// mark prices.
+#[utoipa::path(
+    get,
+    path = "/v1/risk/history",
+    tag = "risk",
+    params(RiskHistoryQuery),
+    responses((status = 200, body = RiskHistoryResponse))
+)]
+pub async fn get_risk_history(
+    ValidQuery(_): ValidQuery<RiskHistoryQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<RiskHistoryResponse>, AppError> {
+    let users = std::slice::from_ref(&user);
+    let ttl = state.config.load().response_cache_ttl();
+    let response = cached(&state, "risk/history", &(&user, from_ms, to_ms), ttl, users, || async {
+        let days = state.ledger.risk_history(&user, from_ms, to_ms).await?;
+
+        let peak_gross_exposure = days
+            .iter()
+            .map(|d| d.peak_gross_exposure)
+            .fold(Decimal::zero(), |peak, g| if g > peak { g } else { peak });
+        let worst = days
+            .iter()
+            .filter(|d| !d.max_drawdown.is_zero())
+            .fold(None, |worst: Option<&RiskDay>, d| match worst {
+                Some(w) if w.max_drawdown >= d.max_drawdown => Some(w),
+                _ => Some(d),
+            });
+
+        Ok(RiskHistoryResponse {
+            peak_gross_exposure: peak_gross_exposure.to_canonical_string(),
+            max_drawdown: worst
+                .map_or(Decimal::zero(), |d| d.max_drawdown)
+                .to_canonical_string(),
+            max_drawdown_pct: worst
+                .and_then(|d| d.max_drawdown_pct)
+                .map(|pct| pct.to_canonical_string()),
+            days: days
+                .into_iter()
+                .map(|d| RiskDayDto {
+                    day_start_ms: d.start_ms.as_ms(),
+                    gross_exposure: d.gross_exposure.to_canonical_string(),
+                    net_exposure: d.net_exposure.to_canonical_string(),
+                    peak_gross_exposure: d.peak_gross_exposure.to_canonical_string(),
+                    equity: d.equity.to_canonical_string(),
+                    max_drawdown: d.max_drawdown.to_canonical_string(),
+                    max_drawdown_pct: d.max_drawdown_pct.map(|pct| pct.to_canonical_string()),
+                })
+                .collect(),
+        })
+    })
+    .await?;
+
+    Ok(Json(response))
 }
 
 async fn fetch_user_state(
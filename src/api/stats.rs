@@ -0,0 +1,141 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use std::str::FromStr;
+
+use super::attributions::{attribution_status, AttributionStatus};
+use super::validate::{parse_coin, TimeRange, ValidQuery, ValidatedAddress};
+use super::AppState;
+use crate::client::StatsRequest;
+use crate::engine::StatsInterval;
+use crate::error::AppError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    pub user: String,
+    pub coin: Option<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    pub builder_only: Option<bool>,
+    /// `day` or `week` (UTC, weeks start Monday) to also return per-bucket totals.
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    pub trade_count: usize,
+    pub volume: String,
+    pub fees_paid: String,
+    /// Maker rebates included in `feesPaid`, as a positive amount.
+    pub rebates_received: String,
+    /// Omitted when the window has no fills.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub median_trade_size: Option<String>,
+    /// Omitted when the window has no fills.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p95_notional: Option<String>,
+    /// Same semantics as `/v1/trades`: whether any fill was excluded for lacking attribution.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tainted: Option<bool>,
+    /// Present when `interval` is set. Only buckets containing fills, oldest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buckets: Option<Vec<VolumeBucketDto>>,
+    #[serde(flatten)]
+    pub attribution: AttributionStatus,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeBucketDto {
+    pub start_ms: i64,
+    pub trade_count: usize,
+    pub volume: String,
+    pub maker_volume: String,
+    /// Fills without maker/taker information count in `volume` only.
+    pub taker_volume: String,
+    pub fees_paid: String,
+    pub rebates_received: String,
+    pub builder_fees: String,
+}
+
+/// Fill count, volume, fees, and size distribution for a user, optionally per day or week.
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    tag = "stats",
+    params(StatsQuery),
+    responses((status = 200, body = StatsResponse))
+)]
+pub async fn get_stats(
+    ValidQuery(params): ValidQuery<StatsQuery>,
+    ValidatedAddress(user): ValidatedAddress,
+    TimeRange { from_ms, to_ms }: TimeRange,
+    State(state): State<AppState>,
+) -> Result<Json<StatsResponse>, AppError> {
+    let coin = match params.coin.as_deref() {
+        Some(c) => {
+            Some(parse_coin(&state.orchestrator.coins(), c)?)
+        }
+        None => None,
+    };
+    let builder_only = params.builder_only.unwrap_or(false);
+    let interval = params
+        .interval
+        .as_deref()
+        .map(StatsInterval::from_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("interval must be one of: day, week".to_string()))?;
+
+    let result = state
+        .ledger
+        .stats(&StatsRequest {
+            user: user.clone(),
+            coin: coin.clone(),
+            from_ms,
+            to_ms,
+            builder_only,
+            interval,
+        })
+        .await?;
+    let stats = result.stats;
+    let buckets = result.buckets.map(|buckets| {
+        buckets
+            .into_iter()
+            .map(|b| VolumeBucketDto {
+                start_ms: b.start_ms.as_ms(),
+                trade_count: b.trade_count,
+                volume: b.volume.to_canonical_string(),
+                maker_volume: b.maker_volume.to_canonical_string(),
+                taker_volume: b.taker_volume.to_canonical_string(),
+                fees_paid: b.fees.fees_paid.to_canonical_string(),
+                rebates_received: b.fees.rebates_received.to_canonical_string(),
+                builder_fees: b.builder_fees.to_canonical_string(),
+            })
+            .collect()
+    });
+    let attribution = attribution_status(
+        &state,
+        builder_only,
+        &user,
+        coin.as_ref(),
+        from_ms,
+        to_ms,
+    )
+    .await?;
+
+    Ok(Json(StatsResponse {
+        trade_count: stats.trade_count,
+        volume: stats.volume.to_canonical_string(),
+        fees_paid: stats.fees.fees_paid.to_canonical_string(),
+        rebates_received: stats.fees.rebates_received.to_canonical_string(),
+        median_trade_size: stats.median_trade_size.map(|d| d.to_canonical_string()),
+        p95_notional: stats.p95_notional.map(|d| d.to_canonical_string()),
+        tainted: result.tainted,
+        buckets,
+        attribution,
+    }))
+}
@@ -0,0 +1,300 @@
+//! `GET /v1/stream`: Server-Sent Events for dashboards that cannot hold a WebSocket.
+//!
+//! A stream follows the in-process event bus ([`Webhooks::subscribe`]) and reads what
+//! changed from storage, so it sees fills however they were ingested. It never fetches from
+//! Hyperliquid itself; something else (a query, the WebSocket feed, or a push) has to.
+//!
+//! [`Webhooks::subscribe`]: crate::orchestration::webhooks::Webhooks::subscribe
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+use utoipa::{IntoParams, ToSchema};
+
+use super::trades::TradeDto;
+use super::validate::ValidatedAddress;
+use super::AppState;
+use crate::domain::{Address, Coin, Decimal};
+use crate::error::AppError;
+use crate::orchestration::webhooks::WebhookEvent;
+
+/// New fills read from storage per query while catching up.
+const TRADES_PER_READ: i64 = 500;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    pub user: String,
+}
+
+/// `snapshot` event, sent first.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSnapshot {
+    pub positions: Vec<StreamPosition>,
+    pub realized_pnl: String,
+}
+
+/// `position` event: a coin's open position after a compile changed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamPosition {
+    pub coin: String,
+    /// `0` once the position is closed.
+    pub net_size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_entry_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_update_ms: Option<i64>,
+}
+
+/// `pnl` event: realized PnL over all fills under the configured `PNL_MODE`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamPnl {
+    pub realized_pnl: String,
+    /// Change since the previous `snapshot` or `pnl` event.
+    pub delta: String,
+}
+
+/// Live `snapshot`, `trade`, `position`, and `pnl` events for a user.
+#[utoipa::path(
+    get,
+    path = "/v1/stream",
+    tag = "trades",
+    params(StreamQuery),
+    responses((
+        status = 200,
+        content_type = "text/event-stream",
+        description = "`snapshot` (StreamSnapshot), then `trade` (TradeDto), `position` (StreamPosition), and `pnl` (StreamPnl) events"
+    ))
+)]
+pub async fn get_stream(
+    ValidatedAddress(user): ValidatedAddress,
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let follower = Follower::start(state, user).await?;
+    let events = stream::unfold(follower, |mut follower| async move {
+        follower.next().await.map(|event| (Ok(event), follower))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// One stream's view of a user: what was last sent, and the events still to send.
+struct Follower {
+    state: AppState,
+    user: Address,
+    events: broadcast::Receiver<Arc<WebhookEvent>>,
+    closing: watch::Receiver<bool>,
+    /// Row id of the last fill sent as a `trade`.
+    last_fill_id: i64,
+    /// Open positions by coin.
+    positions: BTreeMap<Coin, StreamPosition>,
+    /// Realized PnL by coin.
+    realized: BTreeMap<Coin, Decimal>,
+    pending: VecDeque<Event>,
+}
+
+impl Follower {
+    async fn start(state: AppState, user: Address) -> Result<Self, AppError> {
+        // Subscribe before reading, so nothing stored in between is missed.
+        let events = state.orchestrator.webhooks().subscribe();
+        let closing = state.closing.subscribe();
+        let last_fill_id = state.repo.last_fill_id(&user).await?;
+        let mut follower = Self {
+            state,
+            user,
+            events,
+            closing,
+            last_fill_id,
+            positions: BTreeMap::new(),
+            realized: BTreeMap::new(),
+            pending: VecDeque::new(),
+        };
+
+        let coins = follower.state.repo.query_distinct_coins(&follower.user, None, None).await?;
+        for coin in &coins {
+            follower.read_coin(coin).await?;
+        }
+        let snapshot = StreamSnapshot {
+            positions: follower.positions.values().cloned().collect(),
+            realized_pnl: follower.total_realized().to_canonical_string(),
+        };
+        follower.push("snapshot", &snapshot);
+        Ok(follower)
+    }
+
+    /// The next event to send, or `None` once the server is shutting down.
+    async fn next(&mut self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let received = tokio::select! {
+                received = self.events.recv() => received,
+                _ = self.closing.wait_for(|closing| *closing) => return None,
+            };
+            let result = match received {
+                Ok(event) => self.apply(&event).await,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::debug!(user = %self.user, missed, "Stream fell behind, rereading");
+                    self.resync().await
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            if let Err(e) = result {
+                tracing::warn!(user = %self.user, error = %e, "Failed to read stream update");
+            }
+        }
+    }
+
+    async fn apply(&mut self, event: &WebhookEvent) -> Result<(), sqlx::Error> {
+        match event {
+            WebhookEvent::FillsIngested { user, .. } if *user == self.user => {
+                self.read_trades().await
+            }
+            WebhookEvent::CompileCompleted { user, coins } if *user == self.user => {
+                self.refresh(coins).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Catch up on everything after missing events.
+    async fn resync(&mut self) -> Result<(), sqlx::Error> {
+        self.read_trades().await?;
+        let coins = self.state.repo.query_distinct_coins(&self.user, None, None).await?;
+        self.refresh(&coins).await
+    }
+
+    /// Queue a `trade` for every fill stored since the last one sent.
+    async fn read_trades(&mut self) -> Result<(), sqlx::Error> {
+        loop {
+            let fills = self
+                .state
+                .repo
+                .query_fills_after_id(&self.user, self.last_fill_id, TRADES_PER_READ)
+                .await?;
+            let done = (fills.len() as i64) < TRADES_PER_READ;
+            for (id, fill) in fills {
+                self.last_fill_id = id;
+                let trade = TradeDto {
+                    user: None,
+                    time_ms: fill.time_ms.as_ms(),
+                    coin: fill.coin.as_str().to_string(),
+                    side: fill.side.to_string(),
+                    px: fill.px.to_canonical_string(),
+                    sz: fill.sz.to_canonical_string(),
+                    fee: fill.fee.to_canonical_string(),
+                    closed_pnl: fill.closed_pnl.to_canonical_string(),
+                    builder: None,
+                };
+                self.push("trade", &trade);
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Queue `position` events for `coins` whose position changed, then a `pnl` event if
+    /// realized PnL did.
+    async fn refresh(&mut self, coins: &[Coin]) -> Result<(), sqlx::Error> {
+        let before = self.total_realized();
+        for coin in coins {
+            let previous = self.positions.get(coin).cloned();
+            self.read_coin(coin).await?;
+            let current = self.positions.get(coin).cloned();
+            match (previous, current) {
+                (previous, Some(current)) if previous.as_ref() != Some(&current) => {
+                    self.push("position", &current);
+                }
+                (Some(_), None) => {
+                    let closed = StreamPosition {
+                        coin: coin.as_str().to_string(),
+                        net_size: "0".to_string(),
+                        avg_entry_px: None,
+                        lifecycle_id: None,
+                        last_update_ms: None,
+                    };
+                    self.push("position", &closed);
+                }
+                _ => {}
+            }
+        }
+
+        let after = self.total_realized();
+        if after != before {
+            let pnl = StreamPnl {
+                realized_pnl: after.to_canonical_string(),
+                delta: (after - before).to_canonical_string(),
+            };
+            self.push("pnl", &pnl);
+        }
+        Ok(())
+    }
+
+    /// Read `coin`'s open position and realized PnL from storage.
+    async fn read_coin(&mut self, coin: &Coin) -> Result<(), sqlx::Error> {
+        let repo = &self.state.repo;
+        let position = repo
+            .query_open_positions(&self.user, Some(coin))
+            .await?
+            .into_iter()
+            .next()
+            .map(|p| StreamPosition {
+                coin: p.coin.as_str().to_string(),
+                net_size: p.net_size,
+                avg_entry_px: Some(p.avg_entry_px),
+                lifecycle_id: Some(p.lifecycle_id.to_string()),
+                last_update_ms: Some(p.last_update_ms.as_ms()),
+            });
+        match position {
+            Some(position) => self.positions.insert(coin.clone(), position),
+            None => self.positions.remove(coin),
+        };
+
+        let effects = repo
+            .query_fill_effects_for_pnl(&self.user, Some(coin), None, None)
+            .await?;
+        let (closed_pnl, fees, builder_fees) = effects.iter().fold(
+            (Decimal::zero(), Decimal::zero(), Decimal::zero()),
+            |(closed_pnl, fees, builder_fees), e| {
+                (
+                    closed_pnl + e.closed_pnl,
+                    fees + e.fee,
+                    builder_fees + e.builder_fee,
+                )
+            },
+        );
+        let realized = self
+            .state
+            .config
+            .load()
+            .pnl_mode
+            .apply(closed_pnl, fees, builder_fees);
+        self.realized.insert(coin.clone(), realized);
+        Ok(())
+    }
+
+    fn total_realized(&self) -> Decimal {
+        self.realized
+            .values()
+            .fold(Decimal::zero(), |total, pnl| total + *pnl)
+    }
+
+    fn push(&mut self, name: &str, data: &impl Serialize) {
+        match Event::default().event(name).json_data(data) {
+            Ok(event) => self.pending.push_back(event),
+            Err(e) => tracing::warn!(error = %e, "Failed to serialize stream event"),
+        }
+    }
+}
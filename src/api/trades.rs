@@ -1,24 +1,31 @@
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::Json;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::domain::ordering::sort_fills_deterministic;
-use crate::domain::{Address, AttributionMode, Coin, TimeMs};
+use crate::client::TradesRequest;
 use crate::error::AppError;
+use super::accounts::{resolve_subject, Subject};
+use super::attributions::{attribution_status_for_users, AttributionStatus};
+use super::validate::{parse_coin, parse_dex, TimeRange, ValidQuery};
 use super::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct TradesQuery {
     pub user: String,
     pub coin: Option<String>,
+    /// Only coins of this builder-deployed perp dex; empty for the default dex.
+    pub dex: Option<String>,
     pub from_ms: Option<i64>,
     pub to_ms: Option<i64>,
     pub builder_only: Option<bool>,
+    /// Also include fills of the user's sub-accounts (requires `SUB_ACCOUNT_ROLLUP`).
+    pub include_sub_accounts: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TradesResponse {
     pub trades: Vec<TradeDto>,
@@ -31,11 +38,16 @@ pub struct TradesResponse {
     /// Note: This is a per-fill exclusion flag, not a lifecycle-level taint indicator.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tainted: Option<bool>,
+    #[serde(flatten)]
+    pub attribution: AttributionStatus,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TradeDto {
+    /// Address the fill was made by; present only with `includeSubAccounts=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
     pub time_ms: i64,
     pub coin: String,
     pub side: String,
@@ -47,79 +59,84 @@ pub struct TradeDto {
     pub builder: Option<String>,
 }
 
+/// Fills for a user, optionally with their sub-accounts, within a time window.
+#[utoipa::path(
+    get,
+    path = "/v1/trades",
+    tag = "trades",
+    params(TradesQuery),
+    responses((status = 200, body = TradesResponse))
+)]
 pub async fn get_trades(
-    Query(params): Query<TradesQuery>,
+    ValidQuery(params): ValidQuery<TradesQuery>,
+    TimeRange { from_ms, to_ms }: TimeRange,
     State(state): State<AppState>,
 ) -> Result<Json<TradesResponse>, AppError> {
-    let user = Address::from_str(&params.user)
-        .map_err(|_| AppError::BadRequest("Invalid user address".into()))?;
-
+    let subject = resolve_subject(
+        &state,
+        Some(&params.user),
+        None,
+        params.include_sub_accounts.unwrap_or(false),
+    )
+    .await?;
+
+    let coins = state.orchestrator.coins();
     let coin = match params.coin.as_deref() {
         Some("") | None => None,
-        Some(c) => Some(Coin::new(c.to_string())),
+        Some(c) => Some(parse_coin(&coins, c)?),
     };
-    let from_ms = params.from_ms.map(TimeMs::new);
-    let to_ms = params.to_ms.map(TimeMs::new);
+    let dex = parse_dex(params.dex.as_deref(), coin.as_ref())?;
     let builder_only = params.builder_only.unwrap_or(false);
 
-    state
-        .orchestrator
-        .ensure_compiled(&user, coin.as_ref(), from_ms, to_ms)
-        .await
-        .map_err(|e| AppError::Internal(format!("Compilation failed: {}", e)))?;
-
-    let mut fills = state
-        .repo
-        .query_fills(&user, coin.as_ref(), from_ms, to_ms)
-        .await?;
-
-    sort_fills_deterministic(&mut fills);
-
-    let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
-    let attributions = state.repo.query_attributions_full(&fill_keys).await?;
-
-    let (fills, tainted) = if builder_only {
-        let mut included = Vec::with_capacity(fills.len());
-        let mut excluded_any = false;
-
-        for fill in fills {
-            let attributed = attributions
-                .get(fill.fill_key())
-                .map(|a| a.attributed)
-                .unwrap_or(false);
-            if attributed {
-                included.push(fill);
-            } else {
-                excluded_any = true;
-            }
-        }
-
-        (included, Some(excluded_any))
-    } else {
-        (fills, None)
-    };
-
-    let trades = fills
-        .into_iter()
-        .map(|f| {
-            let builder = attributions
-                .get(f.fill_key())
-                .filter(|a| a.attributed && a.mode == AttributionMode::Logs)
-                .and_then(|a| a.builder.as_ref())
-                .map(|b| b.as_str().to_string());
-
-            TradeDto {
-                time_ms: f.time_ms.as_ms(),
-                coin: f.coin.as_str().to_string(),
-                side: f.side.to_string(),
-                px: f.px.to_canonical_string(),
-                sz: f.sz.to_canonical_string(),
-                fee: f.fee.to_canonical_string(),
-                closed_pnl: f.closed_pnl.to_canonical_string(),
-                builder,
-            }
-        })
-        .collect();
-
-    Ok(Json(TradesResponse { trades, tainted }))
+    let label_users = matches!(subject, Subject::Account(_));
+    let mut trades = Vec::new();
+    let mut tainted = None;
+    for user in subject.members() {
+        let result = state
+            .ledger
+            .trades(&TradesRequest {
+                user: user.clone(),
+                coin: coin.clone(),
+                dex: dex.clone(),
+                from_ms,
+                to_ms,
+                builder_only,
+            })
+            .await?;
+        tainted = match (tainted, result.tainted) {
+            (Some(a), Some(b)) => Some(a || b),
+            (a, b) => a.or(b),
+        };
+
+        trades.extend(result.trades.into_iter().map(|t| TradeDto {
+            user: label_users.then(|| user.as_str().to_string()),
+            time_ms: t.time_ms.as_ms(),
+            coin: t.coin.as_str().to_string(),
+            side: t.side.to_string(),
+            px: t.px.to_canonical_string(),
+            sz: coins.format_sz(&t.coin, &t.sz),
+            fee: t.fee.to_canonical_string(),
+            closed_pnl: t.closed_pnl.to_canonical_string(),
+            builder: t.builder.map(|b| b.as_str().to_string()),
+        }));
+    }
+    // Members are sorted and each member's fills are already in fill order, so a stable
+    // sort on time keeps the merge deterministic.
+    trades.sort_by_key(|t| t.time_ms);
+
+    let attribution = attribution_status_for_users(
+        &state,
+        builder_only,
+        subject.members(),
+        coin.as_ref(),
+        from_ms,
+        to_ms,
+    )
+    .await?;
+
+    Ok(Json(TradesResponse {
+        trades,
+        tainted,
+        attribution,
+    }))
 }
@@ -0,0 +1,238 @@
+//! Shared validation of query parameters.
+//!
+//! Handlers take their endpoint's query struct through [`ValidQuery`] and the common
+//! parameters through the typed extractors here, so a malformed address, an inverted time
+//! window or an out-of-range page size is rejected the same way on every endpoint, with the
+//! usual `{code, error}` body. The query structs keep every parameter: they document the
+//! endpoint in the OpenAPI spec and key the response cache.
+
+use crate::domain::{Address, AddressParseError, Coin, CoinRegistry, TimeMs};
+use crate::error::AppError;
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// [`Query`] whose rejection (a missing parameter, a number that does not parse) is an
+/// [`AppError::BadRequest`] rather than axum's plain-text response.
+#[derive(Debug, Clone)]
+pub struct ValidQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Query::try_from_uri(&parts.uri)
+            .map(|Query(value)| ValidQuery(value))
+            .map_err(|rejection| AppError::BadRequest(rejection.body_text()))
+    }
+}
+
+/// Parse the address given as parameter `field`; see [`Address::parse_checksummed`].
+pub fn parse_address(field: &str, value: &str) -> Result<Address, AppError> {
+    Address::parse_checksummed(value).map_err(|e| match e {
+        AddressParseError::ChecksumMismatch => {
+            AppError::InvalidAddress(format!("Invalid {} address: {}", field, e))
+        }
+        _ => AppError::InvalidAddress(format!("Invalid {} address", field)),
+    })
+}
+
+/// Parse a coin parameter, spelled the way `coins` spells it; see [`CoinRegistry::resolve`].
+///
+/// A symbol the registry does not list is rejected, so a typo is an error rather than an
+/// empty result.
+pub fn parse_coin(coins: &CoinRegistry, value: &str) -> Result<Coin, AppError> {
+    if value.trim().is_empty() {
+        return Err(AppError::BadRequest("Invalid coin".into()));
+    }
+    coins
+        .resolve(value)
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown coin '{}'", value.trim())))
+}
+
+/// Parse a `dex` parameter: the name of a builder-deployed perp dex, or empty for the
+/// default dex. With a `coin` too, the coin must be on that dex.
+pub fn parse_dex(value: Option<&str>, coin: Option<&Coin>) -> Result<Option<String>, AppError> {
+    let Some(dex) = value.map(|v| v.trim().to_lowercase()) else {
+        return Ok(None);
+    };
+    if dex.contains(Coin::DEX_SEPARATOR) {
+        return Err(AppError::BadRequest("Invalid dex".into()));
+    }
+    if let Some(coin) = coin.filter(|coin| !coin.is_on_dex(&dex)) {
+        return Err(AppError::BadRequest(format!(
+            "coin '{}' is not on dex '{}'",
+            coin, dex
+        )));
+    }
+    Ok(Some(dex))
+}
+
+/// Parse a `groupBy` parameter. Returns whether results are broken down by strategy.
+pub fn parse_group_by(value: Option<&str>) -> Result<bool, AppError> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(false),
+        Some(v) if v.eq_ignore_ascii_case("strategy") => Ok(true),
+        Some(_) => Err(AppError::BadRequest("groupBy must be one of: strategy".into())),
+    }
+}
+
+/// The required `user` parameter, checksum-verified and lowercased.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedAddress(pub Address);
+
+#[derive(Deserialize)]
+struct UserParam {
+    user: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ValidatedAddress {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ValidQuery(UserParam { user }) = ValidQuery::from_request_parts(parts, state).await?;
+        let user = user.ok_or_else(|| AppError::BadRequest("user is required".into()))?;
+        parse_address("user", &user).map(ValidatedAddress)
+    }
+}
+
+/// The optional `fromMs` / `toMs` window, both inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeRange {
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+}
+
+impl TimeRange {
+    /// Reject a window that ends before it starts.
+    pub fn new(from_ms: Option<i64>, to_ms: Option<i64>) -> Result<Self, AppError> {
+        if let (Some(from_ms), Some(to_ms)) = (from_ms, to_ms) {
+            if from_ms > to_ms {
+                return Err(AppError::InvalidTimeRange("fromMs must be <= toMs".into()));
+            }
+        }
+        Ok(Self {
+            from_ms: from_ms.map(TimeMs::new),
+            to_ms: to_ms.map(TimeMs::new),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeRangeParams {
+    from_ms: Option<i64>,
+    to_ms: Option<i64>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for TimeRange {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ValidQuery(TimeRangeParams { from_ms, to_ms }) =
+            ValidQuery::from_request_parts(parts, state).await?;
+        TimeRange::new(from_ms, to_ms)
+    }
+}
+
+/// The optional `limit` (between 1 and `MAX`) and `cursor` parameters; what an absent
+/// `limit` means is up to the endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pagination<const MAX: i64> {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+impl<const MAX: i64> Pagination<MAX> {
+    pub fn new(limit: Option<i64>, cursor: Option<String>) -> Result<Self, AppError> {
+        if let Some(limit) = limit {
+            if !(1..=MAX).contains(&limit) {
+                return Err(AppError::BadRequest(format!(
+                    "limit must be between 1 and {}",
+                    MAX
+                )));
+            }
+        }
+        Ok(Self { limit, cursor })
+    }
+}
+
+#[derive(Deserialize)]
+struct PaginationParams {
+    limit: Option<i64>,
+    cursor: Option<String>,
+}
+
+#[async_trait]
+impl<const MAX: i64, S: Send + Sync> FromRequestParts<S> for Pagination<MAX> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ValidQuery(PaginationParams { limit, cursor }) =
+            ValidQuery::from_request_parts(parts, state).await?;
+        Pagination::new(limit, cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn extract<T: FromRequestParts<()>>(uri: &str) -> Result<T, T::Rejection> {
+        let (mut parts, _) = Request::builder().uri(uri).body(()).unwrap().into_parts();
+        T::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_validated_address() {
+        let user = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let ValidatedAddress(address) = extract(&format!("/v1/pnl?user={}", user)).await.unwrap();
+        assert_eq!(address.as_str(), user.to_ascii_lowercase());
+
+        let err = extract::<ValidatedAddress>("/v1/pnl?user=0x123").await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid address: Invalid user address");
+        let typo = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        let err = extract::<ValidatedAddress>(&format!("/v1/pnl?user={}", typo))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("EIP-55"), "{}", err);
+        let err = extract::<ValidatedAddress>("/v1/pnl").await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_time_range() {
+        let range: TimeRange = extract("/v1/pnl?fromMs=5&toMs=5").await.unwrap();
+        assert_eq!(range.from_ms, Some(TimeMs::new(5)));
+        assert_eq!(range.to_ms, Some(TimeMs::new(5)));
+
+        let err = extract::<TimeRange>("/v1/pnl?fromMs=6&toMs=5").await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidTimeRange(_)));
+        let err = extract::<TimeRange>("/v1/pnl?fromMs=soon").await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pagination_bounds_limit() {
+        let page: Pagination<10> = extract("/v1/x?limit=10&cursor=abc").await.unwrap();
+        assert_eq!(page.limit, Some(10));
+        assert_eq!(page.cursor.as_deref(), Some("abc"));
+
+        for limit in ["0", "11", "-1"] {
+            let err = extract::<Pagination<10>>(&format!("/v1/x?limit={}", limit))
+                .await
+                .unwrap_err();
+            assert_eq!(err.to_string(), "Bad request: limit must be between 1 and 10");
+        }
+    }
+}
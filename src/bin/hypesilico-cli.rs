@@ -0,0 +1,323 @@
+//! Offline operations on the ledger database, without the HTTP server.
+//!
+//! Reads the same configuration as the server (the config file and environment). Logs go
+//! to stderr so `export` can write CSV to stdout.
+
+use clap::{Args, Parser, Subcommand};
+use futures::TryStreamExt;
+use hypesilico::client::{LedgerClient, LedgerError};
+use hypesilico::compile::{Compiler, TableDiff};
+use hypesilico::config::Config;
+use hypesilico::db::compat::check_database_file;
+use hypesilico::domain::{Address, Coin, TimeMs};
+use hypesilico::orchestration::builder_logs_backfill::{days_in_range, MAX_BACKFILL_DAYS};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing_subscriber::util::SubscriberInitExt;
+
+#[derive(Parser)]
+#[command(name = "hypesilico-cli", version, about = "Offline operations on the hypesilico ledger")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch users' fills and deposits into the database without compiling them.
+    Backfill(Window),
+    /// Fetch users' fills and bring their derived tables up to date.
+    Compile(Window),
+    /// Write users' stored fills as CSV; reads the database only.
+    Export {
+        #[command(flatten)]
+        window: Window,
+        /// Write to this file instead of stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Check the database file and, for the given users, recompile every coin in memory
+    /// and compare it with the stored derived tables.
+    Verify {
+        /// User address; repeat for several.
+        #[arg(long = "user")]
+        users: Vec<Address>,
+        #[arg(long)]
+        coin: Option<Coin>,
+    },
+    /// Builder attribution.
+    #[command(subcommand)]
+    Attribution(AttributionCommand),
+}
+
+#[derive(Subcommand)]
+enum AttributionCommand {
+    /// Re-attribute users' fills against builder logs and rebuild the coins traded.
+    Run(Window),
+    /// Store builders' logs for a range of UTC days, then re-attribute every stored user
+    /// who appears in them.
+    BackfillLogs {
+        /// First day, `YYYYMMDD`.
+        #[arg(long)]
+        from_day: String,
+        /// Last day, `YYYYMMDD`, inclusive.
+        #[arg(long)]
+        to_day: String,
+        /// Builder address; repeat for several. Defaults to `TARGET_BUILDER`.
+        #[arg(long = "builder")]
+        builders: Vec<Address>,
+    },
+}
+
+#[derive(Args)]
+struct Window {
+    /// User address; repeat for several.
+    #[arg(long = "user", required = true)]
+    users: Vec<Address>,
+    #[arg(long)]
+    coin: Option<Coin>,
+    #[arg(long)]
+    from_ms: Option<i64>,
+    #[arg(long)]
+    to_ms: Option<i64>,
+}
+
+impl Window {
+    fn start(&self) -> Option<TimeMs> {
+        self.from_ms.map(TimeMs::new)
+    }
+
+    fn end(&self) -> Option<TimeMs> {
+        self.to_ms.map(TimeMs::new)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("{0}")]
+    Config(#[from] hypesilico::config::ConfigError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+    #[error("{0}")]
+    Other(String),
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(hypesilico::telemetry::default_filter())
+        .with_writer(std::io::stderr)
+        .finish();
+    if let Err(e) = subscriber.try_init() {
+        eprintln!("Failed to install tracing subscriber: {}", e);
+    }
+
+    match run(cli.command).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(2),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run `command`; `Ok(false)` means it ran but found problems.
+async fn run(command: Command) -> Result<bool, CliError> {
+    let config = Config::from_env()?;
+    if let Command::Verify { users, coin } = &command {
+        return verify(config, users, coin.as_ref()).await;
+    }
+
+    let client = LedgerClient::open(config).await?;
+    match command {
+        Command::Backfill(window) => {
+            for user in &window.users {
+                let result = client
+                    .ingest(user, window.coin.as_ref(), window.start(), window.end())
+                    .await?;
+                client
+                    .orchestrator()
+                    .ensure_deposits_ingested(user, window.start(), window.end())
+                    .await
+                    .map_err(LedgerError::Deposits)?;
+                println!(
+                    "{}: {} fills fetched, {} new, in {} chunks",
+                    user, result.fills_fetched, result.fills_new, result.chunks
+                );
+            }
+        }
+        Command::Compile(window) => {
+            for user in &window.users {
+                client
+                    .compile(user, window.coin.as_ref(), window.start(), window.end())
+                    .await?;
+                println!("{}: compiled", user);
+            }
+        }
+        Command::Export { window, output } => {
+            let out: Box<dyn Write> = match &output {
+                Some(path) => Box::new(std::fs::File::create(path).map_err(|e| {
+                    CliError::Other(format!("Failed to create {}: {}", path.display(), e))
+                })?),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            export(&client, &window, out).await?;
+        }
+        Command::Attribution(AttributionCommand::Run(window)) => {
+            let from_ms = window.start().unwrap_or(TimeMs::new(0));
+            let to_ms = window.end().unwrap_or_else(TimeMs::now);
+            for user in &window.users {
+                let attributed = client
+                    .orchestrator()
+                    .backfill_attributions(user, from_ms, to_ms)
+                    .await
+                    .map_err(LedgerError::from)?;
+                println!("{}: {} fills attributed", user, attributed);
+            }
+        }
+        Command::Attribution(AttributionCommand::BackfillLogs {
+            from_day,
+            to_day,
+            mut builders,
+        }) => {
+            let days = days_in_range(&from_day, &to_day).ok_or_else(|| {
+                CliError::Other("--from-day and --to-day must be YYYYMMDD dates in order".into())
+            })?;
+            if days.len() > MAX_BACKFILL_DAYS {
+                return Err(CliError::Other(format!(
+                    "at most {} days can be backfilled at once",
+                    MAX_BACKFILL_DAYS
+                )));
+            }
+            if builders.is_empty() {
+                builders.push(Address::new(client.config().target_builder.clone()));
+            }
+            let report = client
+                .orchestrator()
+                .backfill_builder_logs(&builders, &days)
+                .await
+                .map_err(LedgerError::from)?;
+            for day in &report.days {
+                match &day.error {
+                    Some(error) => println!("{} {}: {}", day.builder, day.yyyymmdd, error),
+                    None => println!("{} {}: {} fills", day.builder, day.yyyymmdd, day.fills),
+                }
+            }
+            for user in &report.users {
+                match &user.error {
+                    Some(error) => println!("{}: {}", user.user, error),
+                    None => println!("{}: {} fills attributed", user.user, user.attributed),
+                }
+            }
+            return Ok(report.failed_days() == 0 && report.users.iter().all(|u| u.error.is_none()));
+        }
+        Command::Verify { .. } => unreachable!("handled before opening the client"),
+    }
+    Ok(true)
+}
+
+/// One CSV row per stored fill, in fill order, without fetching anything.
+async fn export(client: &LedgerClient, window: &Window, out: Box<dyn Write>) -> Result<(), CliError> {
+    let csv_error = |e: csv::Error| CliError::Other(format!("Failed to write CSV: {}", e));
+    let mut writer = csv::Writer::from_writer(out);
+    writer
+        .write_record([
+            "fill_key", "user", "time_ms", "coin", "side", "px", "sz", "fee", "closed_pnl",
+            "builder_fee", "tid", "oid",
+        ])
+        .map_err(csv_error)?;
+    for user in &window.users {
+        let mut fills = client.repo().stream_fills(
+            user,
+            window.coin.as_ref(),
+            window.start(),
+            window.end(),
+        );
+        while let Some(f) = fills.try_next().await? {
+            writer
+                .write_record([
+                    f.fill_key.clone(),
+                    f.user.as_str().to_string(),
+                    f.time_ms.as_ms().to_string(),
+                    f.coin.as_str().to_string(),
+                    f.side.to_string(),
+                    f.px.to_canonical_string(),
+                    f.sz.to_canonical_string(),
+                    f.fee.to_canonical_string(),
+                    f.closed_pnl.to_canonical_string(),
+                    f.builder_fee.map(|d| d.to_canonical_string()).unwrap_or_default(),
+                    f.tid.map(|t| t.to_string()).unwrap_or_default(),
+                    f.oid.map(|o| o.to_string()).unwrap_or_default(),
+                ])
+                .map_err(csv_error)?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| CliError::Other(format!("Failed to write CSV: {}", e)))
+}
+
+/// Report problems rather than stop at the first one; returns whether everything passed.
+async fn verify(config: Config, users: &[Address], coin: Option<&Coin>) -> Result<bool, CliError> {
+    let mut ok = true;
+    match check_database_file(&config.database_path).await {
+        Ok(report) => {
+            println!(
+                "{}: compatible (written by hypesilico {})",
+                config.database_path, report.source.crate_version
+            );
+            if report.recompile_required {
+                println!("{}: derived tables will be rebuilt on next start", config.database_path);
+            }
+        }
+        Err(e) => {
+            println!("{}: {}", config.database_path, e);
+            return Ok(false);
+        }
+    }
+
+    let client = LedgerClient::open(config).await?;
+    let repo = client.repo();
+    for user in users {
+        let coins = match coin {
+            Some(coin) => vec![coin.clone()],
+            None => repo.query_distinct_coins(user, None, None).await?,
+        };
+        for coin in &coins {
+            let report = Compiler::compile_dry_run(repo, user, coin).await?;
+            let diff = &report.diff;
+            if diff.is_empty() {
+                println!("{} {}: derived tables match {} fills", user, coin, report.fills);
+            } else {
+                ok = false;
+                println!(
+                    "{} {}: lifecycles {}, snapshots {}, effects {}",
+                    user,
+                    coin,
+                    describe(&diff.lifecycles),
+                    describe(&diff.snapshots),
+                    describe(&diff.effects)
+                );
+            }
+        }
+        for (invariant, count) in repo.count_invariant_violations(Some(user), coin).await? {
+            ok = false;
+            println!("{}: {} {} violations", user, count, invariant);
+        }
+    }
+    Ok(ok)
+}
+
+fn describe(diff: &TableDiff) -> String {
+    format!(
+        "+{} -{} ~{}",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )
+}
@@ -0,0 +1,1461 @@
+//! Programmatic access to the ledger without the HTTP server.
+//!
+//! [`LedgerClient`] is the supported embedding surface: it ingests and compiles fills on
+//! demand and answers the same trades, PnL, stats, equity, and leaderboard queries as the
+//! HTTP API, which is a thin layer over it. Inputs and outputs use domain types rather than wire DTOs.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), hypesilico::client::LedgerError> {
+//! use hypesilico::client::{LedgerClient, PnlRequest};
+//! use hypesilico::{Address, Config};
+//! use std::str::FromStr;
+//!
+//! let client = LedgerClient::open(Config::from_env().expect("config")).await?;
+//! let user = Address::from_str("0x0000000000000000000000000000000000000001").unwrap();
+//! let pnl = client.pnl(&PnlRequest::new(user)).await?;
+//! println!("realized {}", pnl.realized_pnl);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::{Config, ConfigHandle, PnlMode};
+use crate::datasource::{BuilderLogsFetcher, CachedBuilderLogsFetcher, HyperliquidDataSource};
+use crate::db::repo::{
+    last_in_bucket, Competition, CompetitionResultRow, LeaderboardFillEffect,
+    LeaderboardSnapshotRow,
+};
+use crate::db::{init_db, Repository};
+use crate::domain::{Address, AttributionMode, Coin, Decimal, MarkSeries, Side, TimeMs};
+use crate::engine::{
+    filter_attributed_fills, mark_to_market, revenue_buckets, risk_by_day, volume_buckets,
+    EquityCurve, EquityResolver, FeeTotals, FillStats, HeldPosition, MarkedEquityPoint,
+    RevenueBucket, RiskDay, StatsInterval, ReturnMethod, TaintMode, VolumeBucket,
+};
+use crate::orchestration::ensure::{Ingestor, IngestionResult};
+use crate::orchestration::orchestrator::{OrchestrationError, Orchestrator};
+use futures::future::try_join_all;
+use futures::TryStreamExt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+/// Fills converted per attribution lookup when listing trades.
+const TRADES_CHUNK_SIZE: usize = 5_000;
+
+/// How soon a failed leaderboard snapshot is retried.
+const SNAPSHOT_RETRY_MS: u64 = 60_000;
+
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    #[error("fromMs must be <= toMs")]
+    InvalidTimeRange,
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    #[error(transparent)]
+    Orchestration(#[from] OrchestrationError),
+    /// Deposits and withdrawals could not be fetched, so equity would be wrong.
+    #[error("Deposit ingestion failed: {0}")]
+    Deposits(OrchestrationError),
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+/// A marked equity curve and the position snapshots and marks it was valued with.
+type MarkedCurve = (Vec<MarkedEquityPoint>, Vec<HeldPosition>, HashMap<Coin, MarkSeries>);
+
+/// A user's fills, optionally limited to builder-attributed ones.
+#[derive(Debug, Clone)]
+pub struct TradesRequest {
+    pub user: Address,
+    pub coin: Option<Coin>,
+    /// Only coins of this perp dex; `""` is the default dex.
+    pub dex: Option<String>,
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+    pub builder_only: bool,
+}
+
+impl TradesRequest {
+    pub fn new(user: Address) -> Self {
+        Self {
+            user,
+            coin: None,
+            dex: None,
+            from_ms: None,
+            to_ms: None,
+            builder_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trade {
+    pub time_ms: TimeMs,
+    pub coin: Coin,
+    pub side: Side,
+    pub px: Decimal,
+    pub sz: Decimal,
+    pub fee: Decimal,
+    pub closed_pnl: Decimal,
+    /// Builder matched from builder logs; heuristic attributions carry no builder.
+    pub builder: Option<Address>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trades {
+    /// In deterministic fill order.
+    pub trades: Vec<Trade>,
+    /// With `builder_only`, whether any fill was excluded for lacking attribution.
+    pub tainted: Option<bool>,
+}
+
+/// Realized PnL over a window.
+#[derive(Debug, Clone)]
+pub struct PnlRequest {
+    pub user: Address,
+    pub coin: Option<Coin>,
+    /// Defaults to the user's first deposit.
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+    pub builder_only: bool,
+    pub taint_mode: TaintMode,
+    /// Caps the starting equity used for the return; only [`ReturnMethod::Simple`] uses it.
+    pub max_start_capital: Option<Decimal>,
+    /// Defaults to the configured `PNL_MODE`.
+    pub pnl_mode: Option<PnlMode>,
+    pub return_method: ReturnMethod,
+    /// Also break the totals down by the strategy of each fill's lifecycle.
+    pub by_strategy: bool,
+}
+
+impl PnlRequest {
+    pub fn new(user: Address) -> Self {
+        Self {
+            user,
+            coin: None,
+            from_ms: None,
+            to_ms: None,
+            builder_only: false,
+            taint_mode: TaintMode::default(),
+            max_start_capital: None,
+            pnl_mode: None,
+            return_method: ReturnMethod::default(),
+            by_strategy: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pnl {
+    /// Net of the fees `pnl_mode` selects.
+    pub realized_pnl: Decimal,
+    pub return_pct: Decimal,
+    pub fees: FeeTotals,
+    /// Builder fees of the counted fills.
+    pub builder_fees: Decimal,
+    /// Mode `realized_pnl` was computed under.
+    pub pnl_mode: PnlMode,
+    pub trade_count: i64,
+    /// With `builder_only`, whether anything was excluded.
+    pub tainted: Option<bool>,
+    /// Lifecycles only partly counted under [`TaintMode::Fill`].
+    pub partial_lifecycles: Option<usize>,
+    /// Start of the window actually used, after defaulting to the first deposit.
+    pub from_ms: Option<TimeMs>,
+    /// With `by_strategy`, the totals split by lifecycle strategy.
+    pub by_strategy: Option<Vec<StrategyPnl>>,
+}
+
+/// Share of a [`Pnl`] from the lifecycles labelled with one strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyPnl {
+    /// `None` for lifecycles no strategy rule matched.
+    pub strategy: Option<String>,
+    pub realized_pnl: Decimal,
+    pub fees: FeeTotals,
+    pub builder_fees: Decimal,
+    pub trade_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeaderboardMetric {
+    Volume,
+    /// Realized PnL under the request's PnL mode.
+    Pnl,
+    ReturnPct,
+    /// Exchange fees net of rebates plus builder fees.
+    Fees,
+    /// Exchange fees net of rebates.
+    FeesPaid,
+    BuilderFees,
+    TradeCount,
+}
+
+impl LeaderboardMetric {
+    pub const ALL: [LeaderboardMetric; 7] = [
+        LeaderboardMetric::Pnl,
+        LeaderboardMetric::Volume,
+        LeaderboardMetric::ReturnPct,
+        LeaderboardMetric::Fees,
+        LeaderboardMetric::FeesPaid,
+        LeaderboardMetric::BuilderFees,
+        LeaderboardMetric::TradeCount,
+    ];
+
+    /// Name used in requests and responses.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LeaderboardMetric::Volume => "volume",
+            LeaderboardMetric::Pnl => "pnl",
+            LeaderboardMetric::ReturnPct => "returnPct",
+            LeaderboardMetric::Fees => "fees",
+            LeaderboardMetric::FeesPaid => "feesPaid",
+            LeaderboardMetric::BuilderFees => "builderFees",
+            LeaderboardMetric::TradeCount => "tradeCount",
+        }
+    }
+}
+
+impl FromStr for LeaderboardMetric {
+    type Err = ();
+
+    /// `realizedPnl` is accepted for `pnl`, after the `/v1/pnl` field.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "volume" => Ok(LeaderboardMetric::Volume),
+            "pnl" | "realizedpnl" => Ok(LeaderboardMetric::Pnl),
+            "returnpct" => Ok(LeaderboardMetric::ReturnPct),
+            "fees" => Ok(LeaderboardMetric::Fees),
+            "feespaid" => Ok(LeaderboardMetric::FeesPaid),
+            "builderfees" => Ok(LeaderboardMetric::BuilderFees),
+            "tradecount" => Ok(LeaderboardMetric::TradeCount),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Ranks the configured leaderboard users by a metric.
+#[derive(Debug, Clone)]
+pub struct LeaderboardRequest {
+    /// Metric the rows are ordered by.
+    pub metric: LeaderboardMetric,
+    /// Further metrics scored and ranked alongside `metric`.
+    pub extra_metrics: Vec<LeaderboardMetric>,
+    /// Users ranked instead of the configured leaderboard users.
+    pub users: Option<Vec<Address>>,
+    pub coin: Option<Coin>,
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+    pub builder_only: bool,
+    pub taint_mode: TaintMode,
+    pub max_start_capital: Option<Decimal>,
+    /// Defaults to the configured `PNL_MODE`.
+    pub pnl_mode: Option<PnlMode>,
+}
+
+impl LeaderboardRequest {
+    pub fn new(metric: LeaderboardMetric) -> Self {
+        Self {
+            metric,
+            extra_metrics: Vec::new(),
+            users: None,
+            coin: None,
+            from_ms: None,
+            to_ms: None,
+            builder_only: false,
+            taint_mode: TaintMode::default(),
+            max_start_capital: None,
+            pnl_mode: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardRow {
+    /// 1-based.
+    pub rank: i64,
+    pub user: Address,
+    pub metric_value: Decimal,
+    pub trade_count: i64,
+    pub tainted: Option<bool>,
+    pub partial_lifecycles: Option<usize>,
+    /// `metric` followed by the extra metrics, each with the user's rank under it.
+    pub metrics: Vec<MetricScore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricScore {
+    pub metric: LeaderboardMetric,
+    pub value: Decimal,
+    /// 1-based, with the same tie-breaks as the row rank.
+    pub rank: i64,
+}
+
+/// Standings of a competition; see [`LedgerClient::competition_standings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompetitionStandings {
+    pub rows: Vec<LeaderboardRow>,
+    /// The rows are the stored final results rather than a live ranking.
+    pub frozen: bool,
+}
+
+/// Fill count, volume, fees, and size distribution over a window.
+#[derive(Debug, Clone)]
+pub struct StatsRequest {
+    pub user: Address,
+    pub coin: Option<Coin>,
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+    pub builder_only: bool,
+    /// Also total the fills per day or week.
+    pub interval: Option<StatsInterval>,
+}
+
+impl StatsRequest {
+    pub fn new(user: Address) -> Self {
+        Self {
+            user,
+            coin: None,
+            from_ms: None,
+            to_ms: None,
+            builder_only: false,
+            interval: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub stats: FillStats,
+    /// With `builder_only`, whether any fill was excluded for lacking attribution.
+    pub tainted: Option<bool>,
+    /// With `interval`, the buckets containing fills, oldest first.
+    pub buckets: Option<Vec<VolumeBucket>>,
+}
+
+/// Builder fee revenue across the configured leaderboard users.
+#[derive(Debug, Clone)]
+pub struct BuilderRevenueRequest {
+    pub builder: Address,
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+}
+
+/// Embeddable ledger: repository, ingestion, compilation, and the query services.
+///
+/// Cheap to clone; clones share the database pool and job queue.
+#[derive(Clone)]
+pub struct LedgerClient {
+    repo: Arc<Repository>,
+    config: ConfigHandle,
+    orchestrator: Arc<Orchestrator>,
+    equity_resolver: Arc<EquityResolver>,
+}
+
+impl LedgerClient {
+    /// Open the database at `config.database_path` and fetch from Hyperliquid, as the
+    /// server does.
+    pub async fn open(config: Config) -> Result<Self, LedgerError> {
+        let repo = Arc::new(Repository::new(init_db(&config.database_path).await?));
+        repo.claim_network(config.network)
+            .await
+            .map_err(|e| LedgerError::Config(e.to_string()))?;
+        let datasource = Arc::new(HyperliquidDataSource::new(config.hyperliquid_api_url.clone()));
+        let config = ConfigHandle::new(config);
+        let ingestor = Ingestor::new(datasource, repo.clone(), config.clone()).with_builder_logs(
+            Arc::new(CachedBuilderLogsFetcher::new(
+                BuilderLogsFetcher::default()
+                    .with_network(config.load().network)
+                    .with_max_concurrent_downloads(config.load().builder_logs_max_concurrent_downloads),
+                repo.clone(),
+            )),
+        );
+        let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+        let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+        Ok(Self::from_parts(repo, config, orchestrator, equity_resolver))
+    }
+
+    /// Assemble a client from existing components, e.g. with a custom data source.
+    pub fn from_parts(
+        repo: Arc<Repository>,
+        config: impl Into<ConfigHandle>,
+        orchestrator: Arc<Orchestrator>,
+        equity_resolver: Arc<EquityResolver>,
+    ) -> Self {
+        Self {
+            repo,
+            config: config.into(),
+            orchestrator,
+            equity_resolver,
+        }
+    }
+
+    pub fn repo(&self) -> &Arc<Repository> {
+        &self.repo
+    }
+
+    /// The configuration in effect now.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load()
+    }
+
+    pub fn orchestrator(&self) -> &Arc<Orchestrator> {
+        &self.orchestrator
+    }
+
+    /// Fetch and store a user's fills for a window without compiling them.
+    pub async fn ingest(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<IngestionResult, LedgerError> {
+        check_window(from_ms, to_ms)?;
+        Ok(self
+            .orchestrator
+            .ensure_ingested(user, coin, from_ms, to_ms)
+            .await?)
+    }
+
+    /// Ingest a window and bring the user's derived tables up to date.
+    pub async fn compile(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<(), LedgerError> {
+        check_window(from_ms, to_ms)?;
+        self.orchestrator
+            .ensure_compiled(user, coin, from_ms, to_ms)
+            .await
+            .map_err(|e| {
+                tracing::error!(user=%user, error=%e, "Compilation failed");
+                LedgerError::from(e)
+            })
+    }
+
+    pub async fn trades(&self, request: &TradesRequest) -> Result<Trades, LedgerError> {
+        let TradesRequest {
+            user,
+            coin,
+            dex,
+            from_ms,
+            to_ms,
+            builder_only,
+        } = request;
+        self.compile(user, coin.as_ref(), *from_ms, *to_ms).await?;
+
+        // Fills arrive in deterministic order (time_ms, tid, oid, fill_key) and are converted
+        // chunk by chunk, so only the output is held for the whole window.
+        let mut chunks = self
+            .repo
+            .stream_fills(user, coin.as_ref(), *from_ms, *to_ms)
+            .try_chunks(TRADES_CHUNK_SIZE);
+
+        let mut trades = Vec::new();
+        let mut excluded_any = false;
+        while let Some(fills) = chunks.try_next().await.map_err(|e| e.1)? {
+            let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+            let attributions = self.repo.query_attributions_full(&fill_keys).await?;
+
+            for f in fills {
+                if dex.as_deref().is_some_and(|dex| !f.coin.is_on_dex(dex)) {
+                    continue;
+                }
+                let attribution = attributions.get(f.fill_key());
+                if *builder_only && !attribution.map(|a| a.attributed).unwrap_or(false) {
+                    excluded_any = true;
+                    continue;
+                }
+
+                let builder = attribution
+                    .filter(|a| a.attributed && a.mode == AttributionMode::Logs)
+                    .and_then(|a| a.builder.clone());
+
+                trades.push(Trade {
+                    time_ms: f.time_ms,
+                    coin: f.coin,
+                    side: f.side,
+                    px: f.px,
+                    sz: f.sz,
+                    fee: f.fee,
+                    closed_pnl: f.closed_pnl,
+                    builder,
+                });
+            }
+        }
+
+        Ok(Trades {
+            trades,
+            tainted: builder_only.then_some(excluded_any),
+        })
+    }
+
+    pub async fn pnl(&self, request: &PnlRequest) -> Result<Pnl, LedgerError> {
+        Ok(self.pnl_with_curve(request).await?.0)
+    }
+
+    pub async fn stats(&self, request: &StatsRequest) -> Result<Stats, LedgerError> {
+        let user = &request.user;
+        let coin = request.coin.as_ref();
+        let (from_ms, to_ms) = (request.from_ms, request.to_ms);
+        self.compile(user, coin, from_ms, to_ms).await?;
+
+        let fills = self.repo.query_fills(user, coin, from_ms, to_ms).await?;
+        let (fills, tainted) = if request.builder_only {
+            let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+            let attributions = self.repo.query_attributions_full(&fill_keys).await?;
+            let before = fills.len();
+            let included: Vec<_> = fills
+                .into_iter()
+                .filter(|f| {
+                    attributions
+                        .get(f.fill_key())
+                        .map(|a| a.attributed)
+                        .unwrap_or(false)
+                })
+                .collect();
+            let excluded_any = included.len() != before;
+            (included, Some(excluded_any))
+        } else {
+            (fills, None)
+        };
+
+        let buckets = match request.interval {
+            Some(interval) => {
+                let mut effects = self
+                    .repo
+                    .query_volume_effects(
+                        user,
+                        coin,
+                        from_ms,
+                        to_ms,
+                        interval.width_ms(),
+                        interval.origin_ms(),
+                    )
+                    .await?;
+                if request.builder_only {
+                    let included: HashSet<&str> = fills.iter().map(|f| f.fill_key()).collect();
+                    effects.retain(|e| included.contains(e.fill_key.as_str()));
+                }
+                Some(volume_buckets(&effects))
+            }
+            None => None,
+        };
+
+        Ok(Stats {
+            stats: FillStats::from_fills(&fills),
+            tainted,
+            buckets,
+        })
+    }
+
+    /// Account equity after every ledger flow and fill up to `to_ms`, optionally
+    /// downsampled to the last point in each of at most `max_points` equal time buckets.
+    pub async fn equity_history(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        max_points: Option<usize>,
+    ) -> Result<Vec<(TimeMs, Decimal)>, LedgerError> {
+        check_window(from_ms, to_ms)?;
+        // Equity at `from_ms` depends on everything before it.
+        self.orchestrator
+            .ensure_deposits_ingested(user, None, to_ms)
+            .await
+            .map_err(LedgerError::Deposits)?;
+        self.compile(user, None, None, to_ms).await?;
+        Ok(self
+            .repo
+            .query_equity_history(user, from_ms, to_ms, max_points)
+            .await?)
+    }
+
+    /// [`LedgerClient::equity_history`] with open positions valued at historical mark
+    /// prices, and a point at every mark bucket in which a position is open; see
+    /// [`mark_to_market`].
+    ///
+    /// Downsampling keeps the last point of each bucket, as for the realized curve.
+    pub async fn marked_equity_history(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        max_points: Option<usize>,
+    ) -> Result<Vec<MarkedEquityPoint>, LedgerError> {
+        let (marked, _, _) = self.marked_curve(user, from_ms, to_ms).await?;
+        Ok(match max_points {
+            Some(max_points) => last_in_bucket(
+                marked.into_iter().map(|p| (p.time_ms, p)).collect(),
+                max_points,
+            )
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect(),
+            None => marked,
+        })
+    }
+
+    /// Gross and net exposure and drawdown of `user` per UTC day, from the marked equity
+    /// curve; see [`risk_by_day`].
+    pub async fn risk_history(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<RiskDay>, LedgerError> {
+        let (marked, positions, marks) = self.marked_curve(user, from_ms, to_ms).await?;
+        Ok(risk_by_day(&marked, &positions, &marks))
+    }
+
+    /// The marked equity curve, with the position snapshots and marks it was valued with.
+    async fn marked_curve(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<MarkedCurve, LedgerError> {
+        let points = self.equity_history(user, from_ms, to_ms, None).await?;
+        let Some(&(start_ms, _)) = points.first() else {
+            return Ok((Vec::new(), Vec::new(), HashMap::new()));
+        };
+        let end_ms = to_ms.unwrap_or_else(TimeMs::now);
+        let positions = self.repo.query_held_positions(user, end_ms).await?;
+
+        // Coins held at some point of the window: open at its start or traded in it.
+        let mut held_at_start = HashMap::new();
+        let mut coins = HashSet::new();
+        for p in &positions {
+            if p.time_ms <= start_ms {
+                held_at_start.insert(&p.coin, !p.net_size.is_zero());
+            } else {
+                coins.insert(&p.coin);
+            }
+        }
+        coins.extend(held_at_start.into_iter().filter(|(_, open)| *open).map(|(c, _)| c));
+
+        let marks = self
+            .orchestrator
+            .mark_prices(coins, start_ms, end_ms)
+            .await?;
+        let marked = mark_to_market(&points, &positions, &marks, end_ms);
+        Ok((marked, positions, marks))
+    }
+
+    /// [`LedgerClient::pnl`] and the equity curve its return was taken from.
+    async fn pnl_with_curve(&self, request: &PnlRequest) -> Result<(Pnl, EquityCurve), LedgerError> {
+        let user = &request.user;
+        let coin = request.coin.as_ref();
+
+        // Without fromMs, start at the first deposit so equity_at_start reflects when the
+        // user actually had capital.
+        let from_ms = match request.from_ms {
+            Some(from) => Some(from),
+            None => self
+                .repo
+                .get_earliest_deposit_timestamp(user)
+                .await?
+                .map(TimeMs::new),
+        };
+        let to_ms = request.to_ms;
+        self.compile(user, coin, from_ms, to_ms).await?;
+
+        let effects = self
+            .repo
+            .query_fill_effects_for_pnl(user, coin, from_ms, to_ms)
+            .await?;
+
+        let (filtered_effects, tainted, partial_lifecycles) = if !request.builder_only {
+            (effects, None, None)
+        } else if request.taint_mode == TaintMode::Fill {
+            let fill_keys: Vec<String> = effects.iter().map(|e| e.fill_key.clone()).collect();
+            let attributions = self.repo.query_attributions_full(&fill_keys).await?;
+
+            let filtered = filter_attributed_fills(effects, &attributions, |e| {
+                (e.fill_key.as_str(), e.lifecycle_id)
+            });
+            (
+                filtered.included,
+                Some(filtered.had_exclusions),
+                Some(filtered.partial_lifecycles),
+            )
+        } else {
+            let tainted_set = self
+                .tainted_lifecycles(effects.iter().map(|e| e.lifecycle_id))
+                .await?;
+
+            let mut had_exclusions = false;
+            let included: Vec<_> = effects
+                .into_iter()
+                .filter(|e| {
+                    let keep = !tainted_set.contains(&e.lifecycle_id);
+                    had_exclusions |= !keep;
+                    keep
+                })
+                .collect();
+
+            (included, Some(had_exclusions), None)
+        };
+
+        let mut closed_pnl = Decimal::zero();
+        let mut fees = FeeTotals::default();
+        let mut builder_fees = Decimal::zero();
+
+        for effect in &filtered_effects {
+            closed_pnl = closed_pnl + effect.closed_pnl;
+            fees.add(effect.fee);
+            builder_fees = builder_fees + effect.builder_fee;
+        }
+
+        // Rebates are negative fees, so net modes add them back to PnL.
+        let pnl_mode = request.pnl_mode.unwrap_or(self.config.load().pnl_mode);
+        let realized_pnl = pnl_mode.apply(closed_pnl, fees.fees_paid, builder_fees);
+
+        let by_strategy = if request.by_strategy {
+            let mut ids: Vec<i64> = filtered_effects.iter().map(|e| e.lifecycle_id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            let strategies = self.repo.query_lifecycle_strategies(&ids).await?;
+            Some(sum_by_strategy(filtered_effects.iter().map(|e| StrategyPnl {
+                strategy: strategies.get(&e.lifecycle_id).cloned(),
+                realized_pnl: pnl_mode.apply(e.closed_pnl, e.fee, e.builder_fee),
+                fees: FeeTotals::from_fees([e.fee]),
+                builder_fees: e.builder_fee,
+                trade_count: 1,
+            })))
+        } else {
+            None
+        };
+
+        let mut curve = self
+            .equity_resolver
+            .equity_curve(user, from_ms.unwrap_or(TimeMs::new(0)), to_ms)
+            .await?;
+        if request.return_method != ReturnMethod::Simple {
+            curve.pnl = filtered_effects
+                .iter()
+                .map(|e| (e.time_ms, pnl_mode.apply(e.closed_pnl, e.fee, e.builder_fee)))
+                .collect();
+            curve.pnl.sort_by_key(|(t, _)| *t);
+        }
+        let return_pct = return_on_curve(
+            &curve,
+            realized_pnl,
+            to_ms,
+            request.return_method,
+            request.max_start_capital,
+        );
+
+        let pnl = Pnl {
+            realized_pnl,
+            return_pct,
+            fees,
+            builder_fees,
+            pnl_mode,
+            trade_count: filtered_effects.len() as i64,
+            tainted,
+            partial_lifecycles,
+            from_ms,
+            by_strategy,
+        };
+        Ok((pnl, curve))
+    }
+
+    /// Realized PnL of several addresses taken as one account.
+    ///
+    /// Each member is computed as by [`LedgerClient::pnl`] with `request.user` replaced, and
+    /// the totals are summed. The return is on the members' combined starting equity, each
+    /// taken at the start of its own window, with `max_start_capital` capping the sum.
+    pub async fn account_pnl(
+        &self,
+        members: &[Address],
+        request: &PnlRequest,
+    ) -> Result<Pnl, LedgerError> {
+        let requests: Vec<PnlRequest> = members
+            .iter()
+            .map(|member| PnlRequest {
+                user: member.clone(),
+                max_start_capital: None,
+                ..request.clone()
+            })
+            .collect();
+        let pnls = try_join_all(requests.iter().map(|r| self.pnl_with_curve(r))).await?;
+
+        let mut total = Pnl {
+            realized_pnl: Decimal::zero(),
+            return_pct: Decimal::zero(),
+            fees: FeeTotals::default(),
+            builder_fees: Decimal::zero(),
+            pnl_mode: request.pnl_mode.unwrap_or(self.config.load().pnl_mode),
+            trade_count: 0,
+            tainted: None,
+            partial_lifecycles: None,
+            from_ms: None,
+            by_strategy: None,
+        };
+        let mut combined: Option<EquityCurve> = None;
+        let mut by_strategy = Vec::new();
+        for (pnl, curve) in pnls {
+            match combined.as_mut() {
+                Some(combined) => combined.merge(curve),
+                None => combined = Some(curve),
+            }
+            total.realized_pnl = total.realized_pnl + pnl.realized_pnl;
+            total.fees.fees_paid = total.fees.fees_paid + pnl.fees.fees_paid;
+            total.fees.rebates_received = total.fees.rebates_received + pnl.fees.rebates_received;
+            total.builder_fees = total.builder_fees + pnl.builder_fees;
+            total.trade_count += pnl.trade_count;
+            total.tainted = match (total.tainted, pnl.tainted) {
+                (Some(a), Some(b)) => Some(a || b),
+                (a, b) => a.or(b),
+            };
+            total.partial_lifecycles = match (total.partial_lifecycles, pnl.partial_lifecycles) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            };
+            total.from_ms = match (total.from_ms, pnl.from_ms) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            by_strategy.extend(pnl.by_strategy.into_iter().flatten());
+        }
+        if request.by_strategy {
+            total.by_strategy = Some(sum_by_strategy(by_strategy));
+        }
+        if let Some(curve) = combined {
+            total.return_pct = return_on_curve(
+                &curve,
+                total.realized_pnl,
+                request.to_ms,
+                request.return_method,
+                request.max_start_capital,
+            );
+        }
+
+        Ok(total)
+    }
+
+    /// Per-day builder fees from the configured leaderboard users' fills attributed to
+    /// `request.builder`.
+    ///
+    /// Heuristic attributions name no builder; they count when `request.builder` is the
+    /// configured `TARGET_BUILDER`, the only builder they can stand for.
+    pub async fn builder_revenue(
+        &self,
+        request: &BuilderRevenueRequest,
+    ) -> Result<Vec<RevenueBucket>, LedgerError> {
+        check_window(request.from_ms, request.to_ms)?;
+        let users = parse_leaderboard_users(&self.config.load().leaderboard_users)?;
+        try_join_all(
+            users
+                .iter()
+                .map(|user| self.compile(user, None, request.from_ms, request.to_ms)),
+        )
+        .await?;
+
+        let include_unnamed = Address::parse_normalized(&self.config.load().target_builder)
+            .is_ok_and(|target| target == request.builder);
+        let day = StatsInterval::Day;
+        let fills = self
+            .repo
+            .query_builder_fee_fills(
+                &users,
+                &request.builder,
+                include_unnamed,
+                request.from_ms,
+                request.to_ms,
+                day.width_ms(),
+                day.origin_ms(),
+            )
+            .await?;
+        Ok(revenue_buckets(&fills))
+    }
+
+    /// Rank the configured leaderboard users, best first.
+    ///
+    /// Ties are broken by trade count, then by address. Each extra metric is ranked the
+    /// same way, independently of the row order.
+    pub async fn leaderboard(
+        &self,
+        request: &LeaderboardRequest,
+    ) -> Result<Vec<LeaderboardRow>, LedgerError> {
+        check_window(request.from_ms, request.to_ms)?;
+        let users = match &request.users {
+            Some(users) => {
+                let mut users = users.clone();
+                users.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+                users.dedup();
+                users
+            }
+            None => parse_leaderboard_users(&self.config.load().leaderboard_users)?,
+        };
+        if users.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Users are compiled and scored concurrently.
+        let mut rows = try_join_all(users.into_iter().map(|user| self.leaderboard_row(user, request)))
+            .await?;
+
+        for i in 1..rows.first().map_or(0, |row| row.metrics.len()) {
+            sort_leaderboard(&mut rows, |row| &row.metrics[i].value);
+            for (idx, row) in rows.iter_mut().enumerate() {
+                row.metrics[i].rank = (idx + 1) as i64;
+            }
+        }
+        sort_leaderboard(&mut rows, |row| &row.metric_value);
+        for (idx, row) in rows.iter_mut().enumerate() {
+            row.rank = (idx + 1) as i64;
+            row.metrics[0].rank = row.rank;
+        }
+
+        Ok(rows)
+    }
+
+    /// Store the standings under every metric over all fills before `snapshot_ms`.
+    ///
+    /// Returns the rows written: `0` when a snapshot at `snapshot_ms` already exists, since
+    /// stored snapshots are never recomputed, or when there are no leaderboard users.
+    pub async fn snapshot_leaderboard(&self, snapshot_ms: TimeMs) -> Result<usize, LedgerError> {
+        if self.repo.has_leaderboard_snapshot(snapshot_ms).await? {
+            return Ok(0);
+        }
+
+        let mut request = LeaderboardRequest::new(LeaderboardMetric::ALL[0]);
+        request.extra_metrics = LeaderboardMetric::ALL[1..].to_vec();
+        request.to_ms = Some(TimeMs::new(snapshot_ms.as_ms() - 1));
+        let rows = self.leaderboard(&request).await?;
+
+        let entrants = rows.len() as i64;
+        let standings: Vec<LeaderboardSnapshotRow> = rows
+            .iter()
+            .flat_map(|row| {
+                row.metrics.iter().map(move |score| LeaderboardSnapshotRow {
+                    snapshot_ms,
+                    metric: score.metric.as_str().to_string(),
+                    user: row.user.clone(),
+                    rank: score.rank,
+                    value: score.value,
+                    trade_count: row.trade_count,
+                    entrants,
+                })
+            })
+            .collect();
+        Ok(self
+            .repo
+            .insert_leaderboard_snapshot(snapshot_ms, &standings)
+            .await?)
+    }
+
+    /// A user's standings in the stored leaderboard snapshots, oldest first.
+    pub async fn leaderboard_history(
+        &self,
+        user: &Address,
+        metric: Option<LeaderboardMetric>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<LeaderboardSnapshotRow>, LedgerError> {
+        check_window(from_ms, to_ms)?;
+        Ok(self
+            .repo
+            .query_leaderboard_history(user, metric.map(LeaderboardMetric::as_str), from_ms, to_ms)
+            .await?)
+    }
+
+    /// Start the background loop that stores a leaderboard snapshot at every multiple of
+    /// `LEADERBOARD_SNAPSHOT_INTERVAL_MS` unless read-only; abort the handle to stop.
+    ///
+    /// The latest boundary is snapshotted at startup if it is missing, so a restart does
+    /// not lose the one it slept through. A reload that changes the interval applies
+    /// straight away.
+    pub fn spawn_leaderboard_snapshots(&self) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut reloads = client.config.subscribe();
+            loop {
+                let config = client.config.load();
+                let interval = config.leaderboard_snapshot_interval_ms as i64;
+                if interval <= 0 || config.read_only {
+                    // `config` keeps the sender alive, so this only resolves on reloads.
+                    let _ = reloads.changed().await;
+                    continue;
+                }
+
+                let now = TimeMs::now().as_ms();
+                let snapshot_ms = TimeMs::new(now - now.rem_euclid(interval));
+                let mut wait = (snapshot_ms.as_ms() + interval - now).max(0) as u64;
+                match client.snapshot_leaderboard(snapshot_ms).await {
+                    Ok(0) => {}
+                    Ok(rows) => tracing::info!(
+                        snapshot_ms = snapshot_ms.as_ms(),
+                        rows,
+                        "Stored leaderboard snapshot"
+                    ),
+                    Err(e) => {
+                        tracing::warn!(
+                            snapshot_ms = snapshot_ms.as_ms(),
+                            "Leaderboard snapshot failed: {}",
+                            e
+                        );
+                        wait = wait.min(SNAPSHOT_RETRY_MS);
+                    }
+                }
+                let wait = wait.saturating_sub((TimeMs::now().as_ms() - now).max(0) as u64);
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(wait)) => {}
+                    _ = reloads.changed() => {}
+                }
+            }
+        })
+    }
+
+    /// Fetch each leaderboard user's live account value and store it as an equity snapshot,
+    /// giving [`EquityResolver`] a recent anchor for `returnPct`. Returns how many were stored.
+    pub async fn sample_equity(&self) -> Result<usize, LedgerError> {
+        let users = parse_leaderboard_users(&self.config.load().leaderboard_users)?;
+        let mut stored = 0;
+        for user in &users {
+            match self.orchestrator.sample_equity(user).await {
+                Ok(Some(_)) => stored += 1,
+                Ok(None) => {}
+                Err(e) => tracing::warn!(user = %user, "Equity sample failed: {}", e),
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Start the background loop that samples leaderboard equity every
+    /// `EQUITY_SAMPLE_INTERVAL_MS`, starting now, unless read-only; abort the handle to stop.
+    pub fn spawn_equity_sampler(&self) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut reloads = client.config.subscribe();
+            loop {
+                let config = client.config.load();
+                let interval = config.equity_sample_interval_ms;
+                if interval == 0 || config.read_only {
+                    let _ = reloads.changed().await;
+                    continue;
+                }
+
+                match client.sample_equity().await {
+                    Ok(0) => {}
+                    Ok(users) => tracing::info!(users, "Sampled live equity"),
+                    Err(e) => tracing::warn!("Equity sampling failed: {}", e),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(interval)) => {}
+                    _ = reloads.changed() => {}
+                }
+            }
+        })
+    }
+
+    /// Start the background loop that refreshes the perp asset metadata every
+    /// `COIN_META_REFRESH_INTERVAL_MS`, starting now, unless read-only; abort the handle to
+    /// stop.
+    pub fn spawn_coin_refresh(&self) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut reloads = client.config.subscribe();
+            loop {
+                let config = client.config.load();
+                let interval = config.coin_meta_refresh_interval_ms;
+                if interval == 0 || config.read_only {
+                    let _ = reloads.changed().await;
+                    continue;
+                }
+
+                match client.orchestrator.refresh_coins().await {
+                    Ok(coins) => tracing::debug!(coins, "Refreshed coin metadata"),
+                    Err(e) => tracing::warn!("Coin metadata refresh failed: {}", e),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(interval)) => {}
+                    _ = reloads.changed() => {}
+                }
+            }
+        })
+    }
+
+    /// Rank a competition's users over its window.
+    ///
+    /// Once `to_ms` has passed, the first call stores the ranking and every later call
+    /// returns the stored rows, so the final results do not move when fills arrive late.
+    /// A read-only client ranks without storing.
+    pub async fn competition_standings(
+        &self,
+        competition: &Competition,
+    ) -> Result<CompetitionStandings, LedgerError> {
+        let metric = LeaderboardMetric::from_str(&competition.metric).map_err(|_| {
+            LedgerError::Config(format!(
+                "Competition '{}' has unknown metric '{}'",
+                competition.name, competition.metric
+            ))
+        })?;
+
+        let stored = self.repo.query_competition_results(&competition.name).await?;
+        if !stored.is_empty() {
+            let rows = stored
+                .into_iter()
+                .map(|result| LeaderboardRow {
+                    rank: result.rank,
+                    user: result.user,
+                    metric_value: result.value,
+                    trade_count: result.trade_count,
+                    tainted: result.tainted,
+                    partial_lifecycles: None,
+                    metrics: vec![MetricScore {
+                        metric,
+                        value: result.value,
+                        rank: result.rank,
+                    }],
+                })
+                .collect();
+            return Ok(CompetitionStandings { rows, frozen: true });
+        }
+
+        let mut request = LeaderboardRequest::new(metric);
+        request.users = Some(competition.users.clone());
+        request.from_ms = Some(competition.from_ms);
+        request.to_ms = Some(competition.to_ms);
+        request.builder_only = competition.builder_only;
+        let rows = self.leaderboard(&request).await?;
+
+        let frozen = TimeMs::now() > competition.to_ms
+            && !self.config.load().read_only
+            && self
+                .repo
+                .insert_competition_results(
+                    &competition.name,
+                    &rows
+                        .iter()
+                        .map(|row| CompetitionResultRow {
+                            user: row.user.clone(),
+                            rank: row.rank,
+                            value: row.metric_value,
+                            trade_count: row.trade_count,
+                            tainted: row.tainted,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .await?;
+        Ok(CompetitionStandings { rows, frozen })
+    }
+
+    /// Score one user; the rank is assigned by the caller.
+    async fn leaderboard_row(
+        &self,
+        user: Address,
+        request: &LeaderboardRequest,
+    ) -> Result<LeaderboardRow, LedgerError> {
+        let coin = request.coin.as_ref();
+        self.compile(&user, coin, request.from_ms, request.to_ms)
+            .await?;
+
+        let effects = self
+            .repo
+            .query_fill_effects_for_leaderboard(&user, coin, request.from_ms, request.to_ms)
+            .await?;
+
+        let (effects, tainted, partial_lifecycles) = match (request.builder_only, request.taint_mode) {
+            (false, _) => (effects, false, None),
+            (true, TaintMode::Lifecycle) => {
+                let (effects, tainted) = self.filter_tainted_lifecycles(effects).await?;
+                (effects, tainted, None)
+            }
+            (true, TaintMode::Fill) => {
+                let fill_keys: Vec<String> = effects.iter().map(|e| e.fill_key.clone()).collect();
+                let attributions = self.repo.query_attributions_full(&fill_keys).await?;
+                let filtered = filter_attributed_fills(effects, &attributions, |e| {
+                    (e.fill_key.as_str(), e.lifecycle_id)
+                });
+                (
+                    filtered.included,
+                    filtered.had_exclusions,
+                    Some(filtered.partial_lifecycles),
+                )
+            }
+        };
+
+        let mut volume = Decimal::zero();
+        let mut closed_pnl = Decimal::zero();
+        let mut fees = FeeTotals::default();
+        let mut builder_fees = Decimal::zero();
+        let mut fill_keys: HashSet<&str> = HashSet::new();
+
+        for effect in &effects {
+            volume = volume + effect.notional;
+            closed_pnl = closed_pnl + effect.closed_pnl;
+            fees.add(effect.fee);
+            builder_fees = builder_fees + effect.builder_fee;
+            fill_keys.insert(effect.fill_key.as_str());
+        }
+
+        let realized_pnl = request
+            .pnl_mode
+            .unwrap_or(self.config.load().pnl_mode)
+            .apply(closed_pnl, fees.fees_paid, builder_fees);
+
+        let trade_count = fill_keys.len() as i64;
+        let metrics: Vec<LeaderboardMetric> = std::iter::once(request.metric)
+            .chain(request.extra_metrics.iter().copied())
+            .collect();
+
+        // The capital base is the only metric input that needs another query.
+        let return_pct = if metrics.contains(&LeaderboardMetric::ReturnPct) {
+            self.return_pct(
+                &user,
+                realized_pnl,
+                request.from_ms.unwrap_or(TimeMs::new(0)),
+                request.to_ms,
+                request.max_start_capital,
+            )
+            .await?
+        } else {
+            Decimal::zero()
+        };
+
+        let metrics: Vec<MetricScore> = metrics
+            .into_iter()
+            .map(|metric| MetricScore {
+                metric,
+                value: match metric {
+                    LeaderboardMetric::Volume => volume,
+                    LeaderboardMetric::Pnl => realized_pnl,
+                    LeaderboardMetric::ReturnPct => return_pct,
+                    LeaderboardMetric::Fees => fees.fees_paid + builder_fees,
+                    LeaderboardMetric::FeesPaid => fees.fees_paid,
+                    LeaderboardMetric::BuilderFees => builder_fees,
+                    LeaderboardMetric::TradeCount => Decimal::new(rust_decimal::Decimal::from(trade_count)),
+                },
+                rank: 0,
+            })
+            .collect();
+
+        Ok(LeaderboardRow {
+            rank: 0,
+            user,
+            metric_value: metrics[0].value,
+            trade_count,
+            tainted: request.builder_only.then_some(tainted),
+            partial_lifecycles,
+            metrics,
+        })
+    }
+
+    /// Drop effects of tainted lifecycles; also reports whether any were dropped.
+    async fn filter_tainted_lifecycles(
+        &self,
+        effects: Vec<LeaderboardFillEffect>,
+    ) -> Result<(Vec<LeaderboardFillEffect>, bool), LedgerError> {
+        if effects.is_empty() {
+            return Ok((effects, false));
+        }
+
+        let tainted_set = self
+            .tainted_lifecycles(effects.iter().map(|e| e.lifecycle_id))
+            .await?;
+        if tainted_set.is_empty() {
+            return Ok((effects, false));
+        }
+
+        let mut had_exclusions = false;
+        let included: Vec<_> = effects
+            .into_iter()
+            .filter(|e| {
+                let keep = !tainted_set.contains(&e.lifecycle_id);
+                had_exclusions |= !keep;
+                keep
+            })
+            .collect();
+
+        Ok((included, had_exclusions))
+    }
+
+    async fn tainted_lifecycles(
+        &self,
+        lifecycle_ids: impl Iterator<Item = i64>,
+    ) -> Result<HashSet<i64>, LedgerError> {
+        let mut lifecycle_ids: Vec<i64> = lifecycle_ids.collect();
+        lifecycle_ids.sort_unstable();
+        lifecycle_ids.dedup();
+
+        Ok(self
+            .repo
+            .query_tainted_lifecycle_ids(&lifecycle_ids)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Realized PnL as a percentage of the capital in use from `from_ms` to `to_ms` (see
+    /// [`EquityResolver::capital_base`]), capped by `max_start_capital`; zero when there is
+    /// no capital.
+    async fn return_pct(
+        &self,
+        user: &Address,
+        realized_pnl: Decimal,
+        from_ms: TimeMs,
+        to_ms: Option<TimeMs>,
+        max_start_capital: Option<Decimal>,
+    ) -> Result<Decimal, LedgerError> {
+        let capital = self
+            .equity_resolver
+            .capital_base(user, from_ms, to_ms)
+            .await?;
+
+        Ok(return_on(realized_pnl, capital, max_start_capital))
+    }
+}
+
+/// Order rows best first by `value`, then by trade count, then by address.
+fn sort_leaderboard(rows: &mut [LeaderboardRow], value: impl Fn(&LeaderboardRow) -> &Decimal) {
+    rows.sort_by(|a, b| {
+        value(b)
+            .cmp(value(a))
+            .then_with(|| b.trade_count.cmp(&a.trade_count))
+            .then_with(|| a.user.as_str().cmp(b.user.as_str()))
+    });
+}
+
+/// Sum `parts` by strategy: named strategies by name, then unlabelled lifecycles.
+fn sum_by_strategy(parts: impl IntoIterator<Item = StrategyPnl>) -> Vec<StrategyPnl> {
+    let mut sums: BTreeMap<(bool, Option<String>), StrategyPnl> = BTreeMap::new();
+    for part in parts {
+        let sum = sums
+            .entry((part.strategy.is_none(), part.strategy.clone()))
+            .or_insert_with(|| StrategyPnl {
+                strategy: part.strategy.clone(),
+                realized_pnl: Decimal::zero(),
+                fees: FeeTotals::default(),
+                builder_fees: Decimal::zero(),
+                trade_count: 0,
+            });
+        sum.realized_pnl = sum.realized_pnl + part.realized_pnl;
+        sum.fees.fees_paid = sum.fees.fees_paid + part.fees.fees_paid;
+        sum.fees.rebates_received = sum.fees.rebates_received + part.fees.rebates_received;
+        sum.builder_fees = sum.builder_fees + part.builder_fees;
+        sum.trade_count += part.trade_count;
+    }
+    sums.into_values().collect()
+}
+
+/// Percentage return of `realized_pnl` on `capital` capped at `max_start_capital`; zero
+/// without capital.
+fn return_on(
+    realized_pnl: Decimal,
+    capital: Decimal,
+    max_start_capital: Option<Decimal>,
+) -> Decimal {
+    let effective_capital = match max_start_capital {
+        Some(max) if capital > max => max,
+        _ => capital,
+    };
+
+    if effective_capital.is_zero() {
+        Decimal::zero()
+    } else {
+        (realized_pnl / effective_capital) * Decimal::hundred()
+    }
+}
+
+/// Return in percent on `curve` by `method`; zero when it cannot be computed.
+///
+/// The money-weighted return runs to `to_ms`, or to the last flow or PnL without one.
+fn return_on_curve(
+    curve: &EquityCurve,
+    realized_pnl: Decimal,
+    to_ms: Option<TimeMs>,
+    method: ReturnMethod,
+    max_start_capital: Option<Decimal>,
+) -> Decimal {
+    let computed = match method {
+        ReturnMethod::Simple => {
+            return return_on(realized_pnl, curve.capital(), max_start_capital);
+        }
+        ReturnMethod::Twr => curve.time_weighted_return(),
+        ReturnMethod::Irr => {
+            let end_ms = to_ms.unwrap_or_else(|| {
+                curve
+                    .flows
+                    .iter()
+                    .chain(&curve.pnl)
+                    .map(|(t, _)| *t)
+                    .max()
+                    .unwrap_or(curve.start_ms)
+            });
+            curve.money_weighted_return(end_ms)
+        }
+    };
+    computed.unwrap_or_else(Decimal::zero)
+}
+
+fn check_window(from_ms: Option<TimeMs>, to_ms: Option<TimeMs>) -> Result<(), LedgerError> {
+    match (from_ms, to_ms) {
+        (Some(from), Some(to)) if from > to => Err(LedgerError::InvalidTimeRange),
+        _ => Ok(()),
+    }
+}
+
+fn parse_leaderboard_users(users: &[String]) -> Result<Vec<Address>, LedgerError> {
+    let mut parsed: Vec<Address> = users
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(Address::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| LedgerError::Config("Invalid leaderboard user address in config".to_string()))?;
+
+    parsed.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+    parsed.dedup();
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metric_accepts_camel_case_return_pct() {
+        assert_eq!(
+            LeaderboardMetric::from_str("returnPct").unwrap(),
+            LeaderboardMetric::ReturnPct
+        );
+        assert_eq!(
+            LeaderboardMetric::from_str("fees").unwrap(),
+            LeaderboardMetric::Fees
+        );
+        assert!(LeaderboardMetric::from_str("nope").is_err());
+    }
+
+    #[test]
+    fn parse_metric_round_trips_names() {
+        for metric in LeaderboardMetric::ALL {
+            assert_eq!(LeaderboardMetric::from_str(metric.as_str()).unwrap(), metric);
+        }
+        assert_eq!(
+            LeaderboardMetric::from_str("realizedPnl").unwrap(),
+            LeaderboardMetric::Pnl
+        );
+    }
+
+    #[test]
+    fn parse_leaderboard_users_sorts_and_dedups() {
+        let users = vec![
+            " 0x0000000000000000000000000000000000000002 ".to_string(),
+            "0x0000000000000000000000000000000000000001".to_string(),
+            "0x0000000000000000000000000000000000000002".to_string(),
+        ];
+        let parsed = parse_leaderboard_users(&users).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].as_str(), "0x0000000000000000000000000000000000000001");
+        assert_eq!(parsed[1].as_str(), "0x0000000000000000000000000000000000000002");
+    }
+}
@@ -0,0 +1,288 @@
+//! Compiling a coin in memory to preview the derived rows without writing them.
+//!
+//! A dry run replays every stored fill of a (user, coin) through the current engine, as a
+//! reset followed by a compile would, and diffs the result against the stored
+//! `position_lifecycles`, `position_snapshots` and `fill_effects`. Nothing is written, not
+//! even the heuristic attributions a real compile inserts, and taint is not computed.
+
+use super::Compiler;
+use crate::db::Repository;
+use crate::domain::{Address, Coin};
+use crate::engine::{CompileHookRegistry, Effect, EffectType, Lifecycle, PositionTracker, Snapshot};
+use futures::TryStreamExt;
+use std::collections::BTreeMap;
+
+/// Derived rows a compile would produce, and how they differ from the stored ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DryRunReport {
+    pub fills: usize,
+    pub lifecycles: Vec<Lifecycle>,
+    pub snapshots: Vec<Snapshot>,
+    pub effects: Vec<Effect>,
+    pub diff: DerivedDiff,
+}
+
+/// Per-table differences between the dry run and the stored derived rows.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivedDiff {
+    pub lifecycles: TableDiff,
+    pub snapshots: TableDiff,
+    pub effects: TableDiff,
+}
+
+impl DerivedDiff {
+    pub fn is_empty(&self) -> bool {
+        self.lifecycles.is_empty() && self.snapshots.is_empty() && self.effects.is_empty()
+    }
+}
+
+/// Row keys that a compile would add, remove or rewrite in one table.
+///
+/// Keys are the lifecycle id for lifecycles, `time_ms:seq` for snapshots and
+/// `fill_key:lifecycle_id:effect_type` for effects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Compare rows keyed by identity, with each value rendering the row's remaining columns.
+    fn between(stored: BTreeMap<String, String>, compiled: BTreeMap<String, String>) -> Self {
+        let mut diff = Self::default();
+        for (key, value) in &compiled {
+            match stored.get(key) {
+                None => diff.added.push(key.clone()),
+                Some(old) if old != value => diff.changed.push(key.clone()),
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        diff.removed = stored
+            .into_keys()
+            .filter(|key| !compiled.contains_key(key))
+            .collect();
+        diff
+    }
+}
+
+impl Compiler {
+    /// Compile all fills of a user and coin in memory and diff the output against the
+    /// stored derived tables, without writing anything.
+    ///
+    /// # Errors
+    /// Returns an error if database reads fail
+    #[tracing::instrument(level = "debug", skip_all, fields(user = %user, coin = %coin))]
+    pub async fn compile_dry_run(
+        repo: &Repository,
+        user: &Address,
+        coin: &Coin,
+    ) -> Result<DryRunReport, sqlx::Error> {
+        let adjustments = repo.query_coin_adjustments(coin).await?;
+        let registry = (!adjustments.is_empty()).then(|| {
+            CompileHookRegistry::from_adjustments(
+                adjustments.into_iter().map(|a| (coin.clone(), a)).collect(),
+            )
+        });
+
+        let mut fills: Vec<_> = repo
+            .stream_fills_after_watermark(user, coin, None)
+            .try_collect()
+            .await?;
+        let mut tracker = PositionTracker::new();
+        for fill in &mut fills {
+            let processed = match &registry {
+                Some(registry) => registry.apply(coin, fill),
+                None => Ok(()),
+            }
+            .and_then(|()| tracker.try_process_fill(fill));
+            // A compile would fail or skip the fill; either way it adds no rows.
+            if let Err(e) = processed {
+                tracing::warn!(fill_key = %fill.fill_key, error = %e, "Skipping fill");
+            }
+        }
+        let lifecycles = tracker.get_lifecycles().to_vec();
+        let snapshots = tracker.get_snapshots().to_vec();
+        let effects = tracker.get_effects().to_vec();
+
+        let stored_lifecycles = repo
+            .query_lifecycles(user, coin)
+            .await?
+            .into_iter()
+            .map(|l| {
+                (
+                    l.id.to_string(),
+                    format!("{}:{:?}", l.start_time_ms.as_i64(), l.end_time_ms.map(|t| t.as_i64())),
+                )
+            })
+            .collect();
+        let stored_snapshots = repo
+            .query_snapshots(user, coin)
+            .await?
+            .into_iter()
+            .map(|s| {
+                (
+                    format!("{}:{}", s.time_ms.as_i64(), s.seq),
+                    format!(
+                        "{}:{}:{}",
+                        s.lifecycle_id,
+                        s.net_size.to_canonical_string(),
+                        s.avg_entry_px.to_canonical_string()
+                    ),
+                )
+            })
+            .collect();
+        let stored_effects = repo
+            .query_effects(user, coin)
+            .await?
+            .into_iter()
+            .map(|e| {
+                (
+                    format!("{}:{}:{}", e.fill_key, e.lifecycle_id, effect_type_str(e.effect_type)),
+                    format!(
+                        "{}:{}:{}:{}",
+                        e.qty.to_canonical_string(),
+                        e.notional.to_canonical_string(),
+                        e.fee.to_canonical_string(),
+                        e.closed_pnl.to_canonical_string()
+                    ),
+                )
+            })
+            .collect();
+
+        let diff = DerivedDiff {
+            lifecycles: TableDiff::between(
+                stored_lifecycles,
+                lifecycles
+                    .iter()
+                    .map(|l| {
+                        (
+                            l.id.to_string(),
+                            format!(
+                                "{}:{:?}",
+                                l.start_time_ms.as_i64(),
+                                l.end_time_ms.map(|t| t.as_i64())
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            snapshots: TableDiff::between(
+                stored_snapshots,
+                snapshots
+                    .iter()
+                    .map(|s| {
+                        (
+                            format!("{}:{}", s.time_ms.as_i64(), s.seq),
+                            format!(
+                                "{}:{}:{}",
+                                s.lifecycle_id,
+                                s.net_size.to_canonical_string(),
+                                s.avg_entry_px.to_canonical_string()
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+            effects: TableDiff::between(
+                stored_effects,
+                effects
+                    .iter()
+                    .map(|e| {
+                        (
+                            format!("{}:{}:{}", e.fill_key, e.lifecycle_id, effect_type_str(e.effect_type)),
+                            format!(
+                                "{}:{}:{}:{}",
+                                e.qty.to_canonical_string(),
+                                e.notional.to_canonical_string(),
+                                e.fee.to_canonical_string(),
+                                e.closed_pnl.to_canonical_string()
+                            ),
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+
+        Ok(DryRunReport {
+            fills: fills.len(),
+            lifecycles,
+            snapshots,
+            effects,
+            diff,
+        })
+    }
+}
+
+/// The `fill_effects.effect_type` column value.
+fn effect_type_str(effect_type: EffectType) -> &'static str {
+    match effect_type {
+        EffectType::Open => "open",
+        EffectType::Close => "close",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::init_db;
+    use crate::domain::{Decimal, Fill, Side, TimeMs};
+    use std::str::FromStr;
+    use tempfile::TempDir;
+
+    fn fill(time_ms: i64, side: Side, sz: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            side,
+            Decimal::from_str("100").unwrap(),
+            Decimal::from_str(sz).unwrap(),
+            Decimal::zero(),
+            Decimal::zero(),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_compile_dry_run_diffs_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db").to_string_lossy().to_string();
+        let repo = Repository::new(init_db(&db_path).await.unwrap());
+        let user = Address::new("0xabc".to_string());
+        let coin = Coin::new("BTC".to_string());
+
+        repo.insert_fills_batch(&[fill(1000, Side::Buy, "1", 1), fill(2000, Side::Sell, "1", 2)])
+            .await
+            .unwrap();
+
+        // Nothing compiled yet: every row would be added, and none is written.
+        let report = Compiler::compile_dry_run(&repo, &user, &coin).await.unwrap();
+        assert_eq!(report.fills, 2);
+        assert_eq!(report.lifecycles.len(), 1);
+        assert_eq!(report.diff.lifecycles.added.len(), 1);
+        assert_eq!(report.diff.effects.added.len(), 2);
+        assert!(repo.query_lifecycles(&user, &coin).await.unwrap().is_empty());
+
+        // Once compiled, the same fills reproduce the stored rows.
+        Compiler::compile_incremental(&repo, &user, &coin).await.unwrap();
+        let report = Compiler::compile_dry_run(&repo, &user, &coin).await.unwrap();
+        assert!(report.diff.is_empty(), "{:?}", report.diff);
+        assert_eq!(report.diff.snapshots.unchanged, report.snapshots.len());
+
+        // A fill reopening the position adds a lifecycle, a snapshot and an effect.
+        repo.insert_fills_batch(&[fill(3000, Side::Buy, "2", 3)]).await.unwrap();
+        let report = Compiler::compile_dry_run(&repo, &user, &coin).await.unwrap();
+        assert_eq!(report.diff.lifecycles.added.len(), 1);
+        assert_eq!(report.diff.snapshots.added.len(), 1);
+        assert_eq!(report.diff.effects.added.len(), 1);
+        assert!(report.diff.effects.removed.is_empty());
+    }
+}
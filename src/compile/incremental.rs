@@ -1,9 +1,90 @@
 //! Incremental compilation logic for processing fills and generating derived tables.
 
 use crate::db::Repository;
-use crate::domain::{Address, Attribution, AttributionConfidence, AttributionMode, Coin};
-use crate::engine::{PositionTracker, TaintComputer};
-use std::collections::{HashMap, HashSet};
+use crate::domain::{
+    Address, Attribution, AttributionConfidence, AttributionMode, AttributionReason, Coin,
+    DecimalError, FillType, TimeMs,
+};
+use crate::engine::{
+    CompileHookRegistry, HeuristicAttributor, InvariantChecker, PnlReconciler, PositionTracker,
+    TaintComputer,
+};
+use futures::TryStreamExt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Fills read from the database per compile step.
+const COMPILE_CHUNK_SIZE: usize = 10_000;
+
+/// What a compile does with a fill whose derived values would overflow a [`Decimal`].
+///
+/// [`Decimal`]: crate::domain::Decimal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail the compile, leaving the pair's derived tables and watermark as they were.
+    #[default]
+    Fail,
+    /// Skip the fill and taint the lifecycle it would have touched.
+    Taint,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OverflowPolicy::Fail => "fail",
+            OverflowPolicy::Taint => "taint",
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Settings a compile runs with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileOptions {
+    pub overflow: OverflowPolicy,
+    /// Attributes fills that have no attribution yet.
+    pub heuristic: HeuristicAttributor,
+}
+
+impl From<OverflowPolicy> for CompileOptions {
+    fn from(overflow: OverflowPolicy) -> Self {
+        Self {
+            overflow,
+            ..Self::default()
+        }
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fail" => Ok(OverflowPolicy::Fail),
+            "taint" => Ok(OverflowPolicy::Taint),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CompileError {
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+    #[error("fill {fill_key} of {coin}: {source}")]
+    Arithmetic {
+        coin: Coin,
+        fill_key: String,
+        source: DecimalError,
+    },
+}
 
 /// Compiler for incremental fill processing.
 pub struct Compiler;
@@ -11,6 +92,18 @@ pub struct Compiler;
 impl Compiler {
     /// Compile fills for a user and coin, processing only new fills since last watermark.
     ///
+    /// Fills that would overflow fail the compile; see [`Compiler::compile_incremental_with`].
+    pub async fn compile_incremental(
+        repo: &Repository,
+        user: &Address,
+        coin: &Coin,
+    ) -> Result<usize, CompileError> {
+        Self::compile_incremental_with(repo, user, coin, OverflowPolicy::Fail).await
+    }
+
+    /// Compile fills for a user and coin, processing only new fills since last watermark,
+    /// handling arithmetic overflow and attributing new fills according to `options`.
+    ///
     /// # Arguments
     /// * `repo` - Database repository
     /// * `user` - User address
@@ -20,64 +113,141 @@ impl Compiler {
     /// Number of fills processed
     ///
     /// # Errors
-    /// Returns an error if database operations fail
-    pub async fn compile_incremental(
+    /// Returns an error if database operations fail, or if a fill overflows under
+    /// [`OverflowPolicy::Fail`]
+    #[tracing::instrument(level = "debug", skip_all, fields(user = %user, coin = %coin))]
+    pub async fn compile_incremental_with(
         repo: &Repository,
         user: &Address,
         coin: &Coin,
-    ) -> Result<usize, sqlx::Error> {
+        options: impl Into<CompileOptions>,
+    ) -> Result<usize, CompileError> {
+        let CompileOptions {
+            overflow,
+            heuristic,
+        } = options.into();
         // Get current watermark
         let watermark = repo.get_compile_state(user, coin).await?;
         let last_fill_key = watermark.as_ref().and_then(|(_, key)| key.clone());
 
-        // Query uncompiled fills
-        let fills = repo
-            .query_fills_after_watermark(user, coin, last_fill_key.as_deref())
-            .await?;
+        // Restate fills of coins with registered adjustments (e.g. size rebases) in a
+        // single unit system before tracking. Fill keys are left untouched.
+        let adjustments = repo.query_coin_adjustments(coin).await?;
+        let registry = (!adjustments.is_empty()).then(|| {
+            CompileHookRegistry::from_adjustments(
+                adjustments.into_iter().map(|a| (coin.clone(), a)).collect(),
+            )
+        });
 
-        if fills.is_empty() {
-            return Ok(0);
-        }
+        // Stream uncompiled fills in chunks so a user with millions of fills never has them
+        // all in memory at once; only tracker outputs and attributions accumulate.
+        let mut chunks = repo
+            .stream_fills_after_watermark(user, coin, last_fill_key.as_deref())
+            .try_chunks(COMPILE_CHUNK_SIZE);
+
+        let mut tracker = PositionTracker::new();
+        let mut invariants = InvariantChecker::new();
+        let mut reconciler = PnlReconciler::new();
+        let mut all_attributions: Vec<(String, bool, String, String, Option<String>)> = Vec::new();
+        let mut fill_count = 0usize;
+        let mut last_fill: Option<(TimeMs, String)> = None;
+        let mut forced_fills: Vec<(String, FillType)> = Vec::new();
+        // Lifecycles tainted by a skipped fill, with the first such fill.
+        let mut overflowed: BTreeMap<i64, String> = BTreeMap::new();
+
+        while let Some(mut fills) = chunks.try_next().await.map_err(|e| e.1)? {
+            // A fill that cannot be restated is handled like one that would overflow tracking.
+            let mut unadjusted = HashMap::new();
+            if let Some(registry) = &registry {
+                for fill in fills.iter_mut() {
+                    if let Err(source) = registry.apply(coin, fill) {
+                        unadjusted.insert(fill.fill_key.clone(), source);
+                    }
+                }
+            }
 
-        // Ensure attributions exist for these fills so builder-only tainting can be computed.
-        //
-        // Important: Do not overwrite existing attributions (e.g., from builder logs). We only
-        // populate missing rows with a heuristic default (builder_fee > 0).
-        let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
-        let existing_attributions = repo.query_attributions(&fill_keys).await?;
-        let existing_keys: HashSet<String> = existing_attributions
-            .iter()
-            .map(|(fill_key, _attributed, _mode, _confidence, _builder)| fill_key.clone())
-            .collect();
-
-        // Build new heuristic attributions for fills that don't have one yet
-        let attributions_to_insert: Vec<(String, bool, String, String, Option<String>)> = fills
-            .iter()
-            .filter(|f| !existing_keys.contains(&f.fill_key))
-            .map(|f| {
-                let attr = Attribution::from_heuristic(f.builder_fee.as_ref());
-                (
-                    f.fill_key.clone(),
-                    attr.attributed,
-                    "heuristic".to_string(),
-                    "low".to_string(),
-                    attr.builder.map(|b| b.as_str().to_string()),
-                )
-            })
-            .collect();
-        repo.insert_attributions(&attributions_to_insert).await?;
-
-        // Combine existing attributions with newly inserted ones (avoid duplicate DB query)
-        let all_attributions: Vec<(String, bool, String, String, Option<String>)> =
-            existing_attributions
-                .into_iter()
-                .chain(attributions_to_insert)
+            // Ensure attributions exist for these fills so builder-only tainting can be computed.
+            //
+            // Important: Do not overwrite existing attributions (e.g., from builder logs). We only
+            // populate missing rows with the heuristic attribution by builder fee.
+            let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+            let existing_attributions = repo.query_attributions(&fill_keys).await?;
+            let existing_keys: HashSet<String> = existing_attributions
+                .iter()
+                .map(|(fill_key, _attributed, _mode, _confidence, _builder)| fill_key.clone())
                 .collect();
 
-        // Process fills through position tracker
-        let mut tracker = PositionTracker::new();
-        for fill in &fills {
-            tracker.process_fill(fill);
+            // Build new heuristic attributions for fills that don't have one yet
+            let attributions_to_insert: Vec<(String, bool, String, String, Option<String>)> = fills
+                .iter()
+                .filter(|f| !existing_keys.contains(&f.fill_key))
+                .map(|f| {
+                    let attr = heuristic.attribute(f);
+                    (
+                        f.fill_key.clone(),
+                        attr.attributed,
+                        "heuristic".to_string(),
+                        "low".to_string(),
+                        attr.builder.map(|b| b.as_str().to_string()),
+                    )
+                })
+                .collect();
+            repo.insert_attributions(&attributions_to_insert).await?;
+
+            // Combine existing attributions with newly inserted ones (avoid duplicate DB query)
+            all_attributions.extend(existing_attributions);
+            all_attributions.extend(attributions_to_insert);
+
+            // Process fills through position tracker
+            let effects_before = tracker.get_effects().len();
+            for fill in &fills {
+                let checked = match unadjusted.get(&fill.fill_key) {
+                    Some(&source) => Err(source),
+                    None => tracker.check_fill(fill),
+                };
+                if let Err(source) = checked {
+                    if overflow == OverflowPolicy::Fail {
+                        return Err(CompileError::Arithmetic {
+                            coin: coin.clone(),
+                            fill_key: fill.fill_key.clone(),
+                            source,
+                        });
+                    }
+                    tracing::warn!(fill_key = %fill.fill_key, error = %source, "Skipping fill");
+                    if let Some(lifecycle_id) = tracker.state.lifecycle_id {
+                        overflowed
+                            .entry(lifecycle_id)
+                            .or_insert_with(|| fill.fill_key.clone());
+                    }
+                    continue;
+                }
+                reconciler.record_fill(&tracker.state, fill);
+                tracker.process_fill(fill);
+            }
+
+            // Effects are appended per fill, so this chunk's effects are the new tail.
+            let chunk_effects = &tracker.get_effects()[effects_before..];
+            invariants.check_fills(&fills, chunk_effects);
+            reconciler.record_effects(chunk_effects);
+
+            forced_fills.extend(
+                fills
+                    .iter()
+                    .filter(|f| !f.fill_type.is_trade())
+                    .map(|f| (f.fill_key.clone(), f.fill_type)),
+            );
+            fill_count += fills.len();
+            last_fill = fills.last().map(|f| (f.time_ms, f.fill_key.clone()));
+        }
+        drop(chunks);
+
+        tracing::debug!(
+            watermark = ?last_fill_key,
+            fills = fill_count,
+            "Compiled fills after watermark"
+        );
+        if fill_count == 0 {
+            return Ok(0);
         }
 
         // Get outputs from tracker
@@ -85,14 +255,6 @@ impl Compiler {
         let snapshots = tracker.get_snapshots();
         let effects = tracker.get_effects();
 
-        // Get the last fill key for watermark update
-        let last_fill_key = fills.last().map(|f| f.fill_key.clone());
-        let last_time_ms = fills.last().map(|f| f.time_ms);
-
-        // Insert all derived tables atomically in a single transaction
-        repo.insert_derived_tables_atomic(user, coin, lifecycles, snapshots, effects)
-            .await?;
-
         // Build fill_key -> lifecycle_id mapping from effects
         // Effects already track which fill belongs to which lifecycle
         let mut fill_to_lifecycle: HashMap<String, Vec<i64>> = HashMap::new();
@@ -106,22 +268,19 @@ impl Compiler {
         // Build attribution map from combined data (no duplicate DB query)
         let mut attribution_map = HashMap::new();
         for (fill_key, attributed, mode_str, confidence_str, builder_str) in all_attributions {
-            let mode = match mode_str.as_str() {
-                "heuristic" => AttributionMode::Heuristic,
-                "logs" => AttributionMode::Logs,
-                _ => AttributionMode::Heuristic,
-            };
-            let confidence = match confidence_str.as_str() {
-                "exact" => AttributionConfidence::Exact,
-                "fuzzy" => AttributionConfidence::Fuzzy,
-                "low" => AttributionConfidence::Low,
-                _ => AttributionConfidence::Low,
-            };
+            let mode = AttributionMode::parse(&mode_str).unwrap_or(AttributionMode::Heuristic);
+            let confidence =
+                AttributionConfidence::parse(&confidence_str).unwrap_or(AttributionConfidence::Low);
+            let reason = AttributionReason::infer(mode, confidence, attributed);
             let attribution = Attribution {
                 attributed,
                 mode,
                 confidence,
                 builder: builder_str.map(Address::new),
+                tolerance_tier: None,
+                matched_log_key: None,
+                score: reason.score(attributed, None, 0),
+                reason,
             };
             attribution_map.insert(fill_key, attribution);
         }
@@ -139,27 +298,66 @@ impl Compiler {
         for (fill_key, attribution) in attribution_map {
             taint_computer.set_attribution(fill_key, attribution);
         }
+        for (fill_key, fill_type) in forced_fills {
+            taint_computer.set_fill_type(fill_key, fill_type);
+        }
 
         let taint_infos = taint_computer.compute_all_taints();
 
         // Prepare taint updates
         let mut taint_updates = Vec::new();
         for lifecycle in lifecycles {
+            if let Some(fill_key) = overflowed.get(&lifecycle.id) {
+                let reason = format!("skipped fill {} after decimal overflow", fill_key);
+                taint_updates.push((lifecycle.id, true, Some(reason)));
+                continue;
+            }
             let taint_info = taint_infos.get(&lifecycle.id);
             let is_tainted = taint_info.map(|t| t.is_tainted).unwrap_or(false);
             let taint_reason = taint_info.and_then(|t| t.reason.clone());
             taint_updates.push((lifecycle.id, is_tainted, taint_reason));
         }
 
-        // Update taint flags
-        repo.update_lifecycle_taints(&taint_updates).await?;
+        // Record engine invariant violations instead of failing the compile, so regressions
+        // surface in the admin report without blocking queries.
+        let violations = invariants.finish(lifecycles);
+        if !violations.is_empty() {
+            tracing::error!(
+                violations = violations.len(),
+                first = ?violations[0],
+                "Compile invariants violated"
+            );
+        }
+
+        // Store derived tables, taint flags, violations and the watermark in one transaction,
+        // so a crash can never leave the watermark ahead of (or behind) the data it describes.
+        if let Some((time_ms, key)) = &last_fill {
+            repo.commit_compile(
+                user,
+                coin,
+                lifecycles,
+                snapshots,
+                effects,
+                &taint_updates,
+                &violations,
+                (time_ms.as_i64(), key),
+            )
+            .await?;
+        }
 
-        // Update watermark atomically
-        if let (Some(time_ms), Some(key)) = (last_time_ms, last_fill_key) {
-            repo.store_compile_state(user, coin, Some(time_ms.as_i64()), Some(&key))
+        // Disagreement with the exchange's closed PnL is expected for a window that starts
+        // mid-position, so it is only a warning.
+        let issues = reconciler.finish(lifecycles);
+        if !issues.is_empty() {
+            tracing::warn!(
+                issues = issues.len(),
+                first = ?issues[0],
+                "Reported closed PnL disagrees with recomputed PnL"
+            );
+            repo.insert_reconciliation_issues(user, coin, &issues, TimeMs::now().as_ms())
                 .await?;
         }
 
-        Ok(fills.len())
+        Ok(fill_count)
     }
 }
@@ -5,13 +5,16 @@
 //! - Position lifecycle tracking and snapshots
 //! - Fill effect decomposition (flip handling)
 //! - Taint flag computation for builder-only filtering
+//! - Dry runs previewing derived rows without writing them
 
 use crate::domain::{Address, Coin, TimeMs};
 use serde::{Deserialize, Serialize};
 
+pub mod dry_run;
 pub mod incremental;
 
-pub use incremental::Compiler;
+pub use dry_run::{DerivedDiff, DryRunReport, TableDiff};
+pub use incremental::{CompileError, CompileOptions, Compiler, OverflowPolicy};
 
 /// Compile state tracking for watermark-based incremental processing.
 ///
@@ -1,16 +1,158 @@
+use crate::api::auth::{ApiKeyConfig, ApiScope};
+use crate::compile::OverflowPolicy;
+use crate::db::maintenance::{MaintenanceConfig, RetentionPolicy};
+use crate::db::{DbPoolConfig, IntegrityCheck};
+use crate::domain::{Address, Decimal};
+use crate::engine::{HeuristicAttributor, MatchTolerances};
+use crate::orchestration::jobs::JobLimits;
+use crate::orchestration::webhooks::{WebhookConfig, WebhookEventKind};
+use arc_swap::ArcSwap;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::watch;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone)]
+/// Requests per minute allowed per API key unless the key sets its own rate.
+const DEFAULT_API_RATE_LIMIT_PER_MINUTE: usize = 600;
+
+/// Config file read from the working directory when `CONFIG_FILE` is not set.
+pub const DEFAULT_CONFIG_FILE: &str = "hypesilico.toml";
+
+/// Every setting, by environment variable name. A config file may set any of them.
+const SETTINGS: &[&str] = &[
+    "ADMIN_BIND_ADDR",
+    "ADMIN_PORT",
+    "API_KEYS",
+    "API_RATE_LIMIT_PER_MINUTE",
+    "BIND_ADDR",
+    "BUILDER_ATTRIBUTION_MODE",
+    "BUILDER_LOGS_AUTO_BACKFILL",
+    "BUILDER_LOGS_MAX_CONCURRENT_DOWNLOADS",
+    "COIN_META_REFRESH_INTERVAL_MS",
+    "COMPILE_PARALLELISM",
+    "DATABASE_PATH",
+    "DB_ANALYZE_INTERVAL_MS",
+    "DB_BACKUP_DIR",
+    "DB_BACKUP_INTERVAL_MS",
+    "DB_BACKUP_KEEP",
+    "DB_CHECKPOINT_INTERVAL_MS",
+    "DB_PRUNE_INTERVAL_MS",
+    "DB_READ_POOL_SIZE",
+    "DB_RESTORE_FROM",
+    "DB_WRITE_POOL_SIZE",
+    "DECIMAL_OVERFLOW_POLICY",
+    "EQUITY_SAMPLE_INTERVAL_MS",
+    "HEURISTIC_BUILDER_FEE_BPS",
+    "HYPERLIQUID_API_URL",
+    "HYPERLIQUID_NETWORK",
+    "HYPERLIQUID_WS_URL",
+    "IDEMPOTENCY_TTL_MS",
+    "INGEST_CHUNK_MS",
+    "INGEST_MAX_CONCURRENT_CHUNKS",
+    "JOB_MAX_PER_USER",
+    "JOB_WORKERS",
+    "LEADERBOARD_SNAPSHOT_INTERVAL_MS",
+    "LEADERBOARD_USERS",
+    "LEADERBOARD_USERS_FILE",
+    "LOOKBACK_MS",
+    "MAX_REQUEST_BODY_BYTES",
+    "MATCH_PX_TOLERANCE_ABS",
+    "MATCH_PX_TOLERANCE_BPS",
+    "MATCH_SZ_TOLERANCE_ABS",
+    "MATCH_TIME_TOLERANCE_MS",
+    "PNL_MODE",
+    "PORT",
+    "READ_ONLY",
+    "RECORD_RAW_PAYLOADS",
+    "RESPONSE_CACHE_TTL_MS",
+    "RESPONSE_COMPRESSION",
+    "RETAIN_EQUITY_SNAPSHOTS_DAYS",
+    "RETAIN_POSITION_SNAPSHOTS_DAYS",
+    "RETAIN_RAW_PAYLOADS_DAYS",
+    "RETENTION_ARCHIVE_PATH",
+    "STARTUP_INTEGRITY_CHECK",
+    "SUB_ACCOUNT_ROLLUP",
+    "TARGET_BUILDER",
+    "WEBHOOK_EVENTS",
+    "WEBHOOK_SECRET",
+    "WEBHOOK_URLS",
+];
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub port: u16,
+    /// Interface the public API listens on.
+    pub bind_addr: IpAddr,
+    /// Serves the `/v1/admin/*` endpoints on their own listener when set, and no longer on
+    /// `port`.
+    pub admin_port: Option<u16>,
+    /// Interface the admin listener binds to.
+    pub admin_bind_addr: IpAddr,
     pub database_path: String,
+    /// Which Hyperliquid environment the data comes from; a database holds one only.
+    pub network: Network,
     pub hyperliquid_api_url: String,
+    /// Enables real-time fill streaming when set.
+    pub hyperliquid_ws_url: Option<String>,
     pub target_builder: String,
     pub builder_attribution_mode: BuilderAttributionMode,
     pub pnl_mode: PnlMode,
     pub lookback_ms: i64,
     pub leaderboard_users: Vec<String>,
+    pub match_tolerances: MatchTolerances,
+    /// How fills are attributed by builder fee when no builder log decides them.
+    pub heuristic_attribution: HeuristicAttributor,
+    pub job_limits: JobLimits,
+    /// Fetch missing builder logs in the background when a `builderOnly` query finds them.
+    pub builder_logs_auto_backfill: bool,
+    /// Daily builder log files downloaded at once, shared by queries and backfills.
+    pub builder_logs_max_concurrent_downloads: usize,
+    /// Static API keys; authentication is disabled when empty.
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// How long computed PnL, leaderboard, and position history responses are reused;
+    /// `0` disables caching.
+    pub response_cache_ttl_ms: u64,
+    /// Allow `includeSubAccounts=true` to roll a user's sub-accounts up into their results.
+    pub sub_account_rollup: bool,
+    /// Longest window fetched from the data source in one request; longer ingestion windows
+    /// are split into chunks of this size, each stored as soon as it arrives.
+    pub ingest_chunk_ms: i64,
+    /// How many chunks of one ingestion window are fetched at once.
+    pub ingest_max_concurrent_chunks: usize,
+    /// Keep every Hyperliquid Info API response in `raw_payloads` for replay.
+    pub record_raw_payloads: bool,
+    /// WAL checkpoint, `ANALYZE`, and backup scheduling, and the backup to restore at startup.
+    pub maintenance: MaintenanceConfig,
+    /// Connections in the write pool and the separate read-only pool.
+    pub db_pool: DbPoolConfig,
+    /// How long an `Idempotency-Key` on an admin or ingest `POST` is remembered; `0`
+    /// ignores the header.
+    pub idempotency_ttl_ms: u64,
+    /// Milliseconds between stored leaderboard snapshots, taken at multiples of the interval
+    /// since the epoch; `0` disables.
+    pub leaderboard_snapshot_interval_ms: u64,
+    /// Milliseconds between live equity samples of the leaderboard users; `0` disables.
+    pub equity_sample_interval_ms: u64,
+    /// Milliseconds between refreshes of the perp asset metadata; `0` disables.
+    pub coin_meta_refresh_interval_ms: u64,
+    /// What a compile does with a fill whose derived values overflow a decimal.
+    pub overflow_policy: OverflowPolicy,
+    /// Gzip or deflate responses for clients that accept it.
+    pub response_compression: bool,
+    /// Largest request body accepted; bigger ones are rejected with `413`.
+    pub max_request_body_bytes: usize,
+    /// Which page check the startup self-check runs before serving.
+    pub startup_integrity_check: IntegrityCheck,
+    /// Serve stored data only: no ingestion, compiles, or writes through the API.
+    pub read_only: bool,
+    /// URLs notified of ingestion, compile, taint, and backfill events.
+    pub webhooks: WebhookConfig,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,10 +162,101 @@ pub enum BuilderAttributionMode {
     Logs,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Which fees realized PnL is reported net of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub enum PnlMode {
+    /// Closed PnL only; fees are reported alongside.
     Gross,
-    Net,
+    /// Closed PnL minus exchange fees, which already include maker rebates.
+    NetFees,
+    /// Closed PnL minus exchange fees and builder fees.
+    NetAllFees,
+}
+
+impl PnlMode {
+    /// Realized PnL under this mode.
+    pub fn apply(self, closed_pnl: Decimal, fees_paid: Decimal, builder_fees: Decimal) -> Decimal {
+        match self {
+            PnlMode::Gross => closed_pnl,
+            PnlMode::NetFees => closed_pnl - fees_paid,
+            PnlMode::NetAllFees => closed_pnl - fees_paid - builder_fees,
+        }
+    }
+
+    /// The formula [`PnlMode::apply`] evaluates, in response field names.
+    pub fn formula(self) -> &'static str {
+        match self {
+            PnlMode::Gross => "realizedPnl = sum(closedPnl)",
+            PnlMode::NetFees => "realizedPnl = sum(closedPnl) - feesPaid",
+            PnlMode::NetAllFees => "realizedPnl = sum(closedPnl) - feesPaid - builderFeesPaid",
+        }
+    }
+}
+
+/// A Hyperliquid environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+        }
+    }
+
+    /// Info API base URL used when `HYPERLIQUID_API_URL` is not set.
+    pub fn default_api_url(self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://api.hyperliquid.xyz",
+            Network::Testnet => "https://api.hyperliquid-testnet.xyz",
+        }
+    }
+
+    /// Path segment of the builder fills logs on the stats host.
+    pub fn stats_prefix(self) -> &'static str {
+        match self {
+            Network::Mainnet => "Mainnet",
+            Network::Testnet => "Testnet",
+        }
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Network {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for PnlMode {
+    type Err = ();
+
+    /// `net` is accepted as the original name of `netFees`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gross" => Ok(PnlMode::Gross),
+            "net" | "netfees" => Ok(PnlMode::NetFees),
+            "netallfees" => Ok(PnlMode::NetAllFees),
+            _ => Err(()),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -32,15 +265,83 @@ pub enum ConfigError {
     MissingEnv(String),
     #[error("Invalid value for {0}: {1}")]
     InvalidValue(String, String),
+    #[error("Config file {0}: {1}")]
+    File(String, String),
+    /// More than one setting is wrong; each is listed.
+    #[error("{} configuration errors:\n{}", .0.len(), list_errors(.0))]
+    Multiple(Vec<ConfigError>),
+}
+
+fn list_errors(errors: &[ConfigError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("  - {}", e))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Errors found while loading, so every bad setting is reported in one go rather than
+/// one per restart.
+#[derive(Default)]
+struct Problems(Vec<ConfigError>);
+
+impl Problems {
+    /// The value of `result`, or `fallback` after recording its error. Fallbacks never reach
+    /// a [`Config`]: loading fails once anything was recorded.
+    fn or<T>(&mut self, result: Result<T, ConfigError>, fallback: T) -> T {
+        result.unwrap_or_else(|e| {
+            self.0.push(e);
+            fallback
+        })
+    }
+
+    fn finish(mut self) -> Result<(), ConfigError> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.remove(0)),
+            _ => Err(ConfigError::Multiple(self.0)),
+        }
+    }
 }
 
 impl Config {
+    /// Load from the environment, layered over a TOML config file when there is one.
+    ///
+    /// The file is `CONFIG_FILE` if set (it must exist), else `hypesilico.toml` in the
+    /// working directory if present. Environment variables override the file.
     pub fn from_env() -> Result<Self, ConfigError> {
-        Self::from_env_map(std::env::vars().collect())
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let path = match env.get("CONFIG_FILE") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => {
+                let default = Path::new(DEFAULT_CONFIG_FILE);
+                default.exists().then(|| default.to_path_buf())
+            }
+        };
+
+        let mut values = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(&path).map_err(|e| {
+                    ConfigError::File(path.display().to_string(), e.to_string())
+                })?;
+                config_file_values(&path.display().to_string(), &content)?
+            }
+            None => HashMap::new(),
+        };
+        values.extend(env);
+        Self::from_env_map(values)
     }
 
     #[cfg_attr(not(test), allow(dead_code))]
     pub fn from_env_map(env_map: HashMap<String, String>) -> Result<Self, ConfigError> {
+        let mut problems = Problems::default();
+        let required = |key: &str| {
+            env_map
+                .get(key)
+                .cloned()
+                .ok_or_else(|| ConfigError::MissingEnv(key.to_string()))
+        };
+
         let port = env_map
             .get("PORT")
             .map(|s| s.as_str())
@@ -48,53 +349,109 @@ impl Config {
             .parse::<u16>()
             .map_err(|_| {
                 ConfigError::InvalidValue("PORT".to_string(), "must be a valid u16".to_string())
-            })?;
-
-        let database_path = env_map
-            .get("DATABASE_PATH")
-            .cloned()
-            .ok_or_else(|| ConfigError::MissingEnv("DATABASE_PATH".to_string()))?;
+            });
+        let port = problems.or(port, 0);
+        let bind_addr = problems.or(
+            parse_ip_addr(&env_map, "BIND_ADDR"),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        );
+        let admin_port = env_map
+            .get("ADMIN_PORT")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<u16>()
+                    .ok()
+                    .filter(|admin_port| *admin_port != port)
+                    .ok_or_else(|| {
+                        ConfigError::InvalidValue(
+                            "ADMIN_PORT".to_string(),
+                            "must be a valid u16 other than PORT".to_string(),
+                        )
+                    })
+            })
+            .transpose();
+        let admin_port = problems.or(admin_port, None);
+        let admin_bind_addr = problems.or(
+            parse_ip_addr(&env_map, "ADMIN_BIND_ADDR"),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        );
 
+        let database_path = problems.or(required("DATABASE_PATH"), String::new());
+        let network = env_map
+            .get("HYPERLIQUID_NETWORK")
+            .map(|s| s.as_str())
+            .unwrap_or("mainnet");
+        let network = Network::from_str(network).map_err(|_| {
+            ConfigError::InvalidValue(
+                "HYPERLIQUID_NETWORK".to_string(),
+                format!("must be mainnet or testnet, got {}", network),
+            )
+        });
+        let network = problems.or(network, Network::Mainnet);
         let hyperliquid_api_url = env_map
             .get("HYPERLIQUID_API_URL")
-            .cloned()
-            .ok_or_else(|| ConfigError::MissingEnv("HYPERLIQUID_API_URL".to_string()))?;
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| network.default_api_url().to_string());
+
+        let hyperliquid_ws_url = env_map
+            .get("HYPERLIQUID_WS_URL")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
 
-        let target_builder = env_map
-            .get("TARGET_BUILDER")
-            .cloned()
-            .ok_or_else(|| ConfigError::MissingEnv("TARGET_BUILDER".to_string()))?;
+        // An invalid builder address makes attribution silently match nothing, so refuse it.
+        let target_builder = required("TARGET_BUILDER").and_then(|target_builder| {
+            Address::parse_normalized(&target_builder)
+                .map(|a| a.as_str().to_string())
+                .map_err(|e| {
+                    ConfigError::InvalidValue(
+                        "TARGET_BUILDER".to_string(),
+                        format!("{} (got {:?})", e, target_builder),
+                    )
+                })
+        });
+        let target_builder = problems.or(target_builder, String::new());
 
         let builder_attribution_mode = match env_map
             .get("BUILDER_ATTRIBUTION_MODE")
             .map(|s| s.as_str())
             .unwrap_or("auto")
         {
-            "auto" => BuilderAttributionMode::Auto,
-            "heuristic" => BuilderAttributionMode::Heuristic,
-            "logs" => BuilderAttributionMode::Logs,
-            other => {
-                return Err(ConfigError::InvalidValue(
-                    "BUILDER_ATTRIBUTION_MODE".to_string(),
-                    format!("must be auto, heuristic, or logs, got {}", other),
-                ))
-            }
+            "auto" => Ok(BuilderAttributionMode::Auto),
+            "heuristic" => Ok(BuilderAttributionMode::Heuristic),
+            "logs" => Ok(BuilderAttributionMode::Logs),
+            other => Err(ConfigError::InvalidValue(
+                "BUILDER_ATTRIBUTION_MODE".to_string(),
+                format!("must be auto, heuristic, or logs, got {}", other),
+            )),
         };
+        let builder_attribution_mode =
+            problems.or(builder_attribution_mode, BuilderAttributionMode::Auto);
 
-        let pnl_mode = match env_map
+        let pnl_mode = env_map
             .get("PNL_MODE")
             .map(|s| s.as_str())
-            .unwrap_or("gross")
-        {
-            "gross" => PnlMode::Gross,
-            "net" => PnlMode::Net,
-            other => {
-                return Err(ConfigError::InvalidValue(
-                    "PNL_MODE".to_string(),
-                    format!("must be gross or net, got {}", other),
-                ))
-            }
-        };
+            .unwrap_or("gross");
+        let pnl_mode = PnlMode::from_str(pnl_mode).map_err(|_| {
+            ConfigError::InvalidValue(
+                "PNL_MODE".to_string(),
+                format!("must be gross, netFees, or netAllFees, got {}", pnl_mode),
+            )
+        });
+        let pnl_mode = problems.or(pnl_mode, PnlMode::Gross);
+
+        let overflow_policy = env_map
+            .get("DECIMAL_OVERFLOW_POLICY")
+            .map(|s| s.as_str())
+            .unwrap_or("fail");
+        let overflow_policy = OverflowPolicy::from_str(overflow_policy).map_err(|_| {
+            ConfigError::InvalidValue(
+                "DECIMAL_OVERFLOW_POLICY".to_string(),
+                format!("must be fail or taint, got {}", overflow_policy),
+            )
+        });
+        let overflow_policy = problems.or(overflow_policy, OverflowPolicy::Fail);
 
         let lookback_ms = env_map
             .get("LOOKBACK_MS")
@@ -106,21 +463,363 @@ impl Config {
                     "LOOKBACK_MS".to_string(),
                     "must be a valid i64".to_string(),
                 )
-            })?;
+            });
+        let lookback_ms = problems.or(lookback_ms, 0);
 
-        let leaderboard_users = parse_leaderboard_users_from_map(&env_map)?;
+        let leaderboard_users = problems.or(parse_leaderboard_users_from_map(&env_map), Vec::new());
+        let match_tolerances = parse_match_tolerances_from_map(&env_map, &mut problems);
+        let heuristic_attribution = problems.or(
+            parse_heuristic_from_map(&env_map),
+            HeuristicAttributor::default(),
+        );
+        let job_limits = parse_job_limits_from_map(&env_map, &mut problems);
 
+        let builder_logs_auto_backfill = problems.or(
+            parse_bool(&env_map, "BUILDER_LOGS_AUTO_BACKFILL", false),
+            false,
+        );
+        let builder_logs_max_concurrent_downloads = problems.or(
+            parse_positive_usize(&env_map, "BUILDER_LOGS_MAX_CONCURRENT_DOWNLOADS", 4),
+            1,
+        );
+
+        let api_keys = problems.or(parse_api_keys_from_map(&env_map), Vec::new());
+
+        let response_cache_ttl_ms = env_map
+            .get("RESPONSE_CACHE_TTL_MS")
+            .map(|s| s.trim())
+            .unwrap_or("5000")
+            .parse::<u64>()
+            .map_err(|_| {
+                ConfigError::InvalidValue(
+                    "RESPONSE_CACHE_TTL_MS".to_string(),
+                    "must be a non-negative integer".to_string(),
+                )
+            });
+        let response_cache_ttl_ms = problems.or(response_cache_ttl_ms, 0);
+
+        let sub_account_rollup =
+            problems.or(parse_bool(&env_map, "SUB_ACCOUNT_ROLLUP", false), false);
+
+        let ingest_chunk_ms = env_map
+            .get("INGEST_CHUNK_MS")
+            .map(|s| s.trim())
+            .unwrap_or("86400000")
+            .parse::<i64>()
+            .ok()
+            .filter(|ms| *ms > 0)
+            .ok_or_else(|| {
+                ConfigError::InvalidValue(
+                    "INGEST_CHUNK_MS".to_string(),
+                    "must be a positive integer".to_string(),
+                )
+            });
+        let ingest_chunk_ms = problems.or(ingest_chunk_ms, 1);
+        let ingest_max_concurrent_chunks = problems.or(
+            parse_positive_usize(&env_map, "INGEST_MAX_CONCURRENT_CHUNKS", 4),
+            1,
+        );
+
+        let record_raw_payloads =
+            problems.or(parse_bool(&env_map, "RECORD_RAW_PAYLOADS", false), false);
+
+        let maintenance = parse_maintenance_from_map(&env_map, &mut problems);
+        let db_pool = parse_db_pool_from_map(&env_map, &mut problems);
+        let idempotency_ttl_ms =
+            problems.or(parse_interval_ms(&env_map, "IDEMPOTENCY_TTL_MS", 86_400_000), 0);
+        let leaderboard_snapshot_interval_ms = problems.or(
+            parse_interval_ms(&env_map, "LEADERBOARD_SNAPSHOT_INTERVAL_MS", 86_400_000),
+            0,
+        );
+        let equity_sample_interval_ms = problems.or(
+            parse_interval_ms(&env_map, "EQUITY_SAMPLE_INTERVAL_MS", 3_600_000),
+            0,
+        );
+        let coin_meta_refresh_interval_ms = problems.or(
+            parse_interval_ms(&env_map, "COIN_META_REFRESH_INTERVAL_MS", 3_600_000),
+            0,
+        );
+        let webhooks = problems.or(parse_webhooks_from_map(&env_map), WebhookConfig::default());
+        let response_compression =
+            problems.or(parse_bool(&env_map, "RESPONSE_COMPRESSION", true), true);
+        let max_request_body_bytes = problems.or(
+            parse_positive_usize(&env_map, "MAX_REQUEST_BODY_BYTES", 2 * 1024 * 1024),
+            1,
+        );
+
+        let startup_integrity_check = env_map
+            .get("STARTUP_INTEGRITY_CHECK")
+            .map(|s| s.as_str())
+            .unwrap_or("quick");
+        let startup_integrity_check =
+            IntegrityCheck::from_str(startup_integrity_check).map_err(|_| {
+                ConfigError::InvalidValue(
+                    "STARTUP_INTEGRITY_CHECK".to_string(),
+                    format!("must be full, quick, or off, got {}", startup_integrity_check),
+                )
+            });
+        let startup_integrity_check = problems.or(startup_integrity_check, IntegrityCheck::Quick);
+        let read_only = problems.or(parse_bool(&env_map, "READ_ONLY", false), false);
+
+        problems.finish()?;
         Ok(Config {
             port,
+            bind_addr,
+            admin_port,
+            admin_bind_addr,
             database_path,
+            network,
             hyperliquid_api_url,
+            hyperliquid_ws_url,
             target_builder,
             builder_attribution_mode,
             pnl_mode,
             lookback_ms,
             leaderboard_users,
+            match_tolerances,
+            heuristic_attribution,
+            job_limits,
+            builder_logs_auto_backfill,
+            builder_logs_max_concurrent_downloads,
+            api_keys,
+            response_cache_ttl_ms,
+            sub_account_rollup,
+            ingest_chunk_ms,
+            ingest_max_concurrent_chunks,
+            record_raw_payloads,
+            maintenance,
+            db_pool,
+            idempotency_ttl_ms,
+            leaderboard_snapshot_interval_ms,
+            equity_sample_interval_ms,
+            coin_meta_refresh_interval_ms,
+            overflow_policy,
+            webhooks,
+            response_compression,
+            max_request_body_bytes,
+            startup_integrity_check,
+            read_only,
         })
     }
+
+    pub fn response_cache_ttl(&self) -> Duration {
+        Duration::from_millis(self.response_cache_ttl_ms)
+    }
+
+    /// This config with `next`'s operational settings, keeping the ones fixed at startup.
+    fn merge_reload(&self, mut next: Config) -> (Config, ReloadReport) {
+        let mut report = ReloadReport::default();
+        macro_rules! keep {
+            ($($field:ident),*) => {$(
+                if self.$field != next.$field {
+                    report.restart_required.push(stringify!($field));
+                    next.$field = self.$field.clone();
+                }
+            )*};
+        }
+        macro_rules! apply {
+            ($($field:ident),*) => {$(
+                if self.$field != next.$field {
+                    report.changed.push(stringify!($field));
+                }
+            )*};
+        }
+
+        // The listener, router, pools, and data sources are built once, and changing what gets
+        // attributed would leave the stored attributions stale.
+        keep!(
+            port,
+            bind_addr,
+            admin_port,
+            admin_bind_addr,
+            database_path,
+            network,
+            hyperliquid_api_url,
+            hyperliquid_ws_url,
+            target_builder,
+            builder_attribution_mode,
+            job_limits,
+            builder_logs_max_concurrent_downloads,
+            record_raw_payloads,
+            db_pool,
+            response_compression,
+            max_request_body_bytes,
+            startup_integrity_check,
+            read_only
+        );
+        if self.maintenance.restore_from != next.maintenance.restore_from {
+            report.restart_required.push("maintenance.restore_from");
+            next.maintenance.restore_from = self.maintenance.restore_from.clone();
+        }
+        apply!(
+            pnl_mode,
+            lookback_ms,
+            leaderboard_users,
+            match_tolerances,
+            heuristic_attribution,
+            builder_logs_auto_backfill,
+            api_keys,
+            response_cache_ttl_ms,
+            sub_account_rollup,
+            ingest_chunk_ms,
+            ingest_max_concurrent_chunks,
+            maintenance,
+            idempotency_ttl_ms,
+            leaderboard_snapshot_interval_ms,
+            equity_sample_interval_ms,
+            coin_meta_refresh_interval_ms,
+            overflow_policy,
+            webhooks
+        );
+        (next, report)
+    }
+}
+
+/// What a [`ConfigHandle::reload`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    /// Settings now running with their new value.
+    pub changed: Vec<&'static str>,
+    /// Settings whose new value was ignored because it only takes effect on restart.
+    pub restart_required: Vec<&'static str>,
+}
+
+/// The running [`Config`], replaceable without a restart.
+///
+/// Clones share one config. Readers take a snapshot with [`ConfigHandle::load`] each time
+/// they need a setting, so a reload reaches them on their next use; background loops that
+/// wait on a setting can [`subscribe`](ConfigHandle::subscribe) to hear about it sooner.
+#[derive(Clone)]
+pub struct ConfigHandle {
+    current: Arc<ArcSwap<Config>>,
+    /// Counts reloads that changed something.
+    generation: Arc<watch::Sender<u64>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::from_pointee(config)),
+            generation: Arc::new(watch::channel(0).0),
+        }
+    }
+
+    /// The config in effect now.
+    pub fn load(&self) -> Arc<Config> {
+        self.current.load_full()
+    }
+
+    /// Notified after every reload that changed a setting.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    /// Switch to `next`'s operational settings.
+    ///
+    /// Structural settings (the port, database, data source URLs, pools, job limits, and
+    /// attribution target) keep their running values and are listed in
+    /// [`ReloadReport::restart_required`] when `next` differs.
+    pub fn reload(&self, next: Config) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        // The sender's lock serializes reloads, so none merges against a replaced config.
+        self.generation.send_if_modified(|generation| {
+            let (merged, merge_report) = self.current.load().merge_reload(next);
+            report = merge_report;
+            if report.changed.is_empty() {
+                return false;
+            }
+            self.current.store(Arc::new(merged));
+            *generation += 1;
+            true
+        });
+        report
+    }
+}
+
+impl From<Config> for ConfigHandle {
+    fn from(config: Config) -> Self {
+        Self::new(config)
+    }
+}
+
+impl std::fmt::Debug for ConfigHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.load().fmt(f)
+    }
+}
+
+/// Settings of a TOML config file, keyed by environment variable name.
+///
+/// A key names the variable in lowercase, and tables prefix their keys, so
+/// `[retain] raw_payloads_days = 30` and `retain_raw_payloads_days = 30` both set
+/// `RETAIN_RAW_PAYLOADS_DAYS`. Arrays are joined with commas, as list variables are.
+/// Unknown keys and unsupported values are all reported together.
+pub fn config_file_values(
+    path: &str,
+    content: &str,
+) -> Result<HashMap<String, String>, ConfigError> {
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| ConfigError::File(path.to_string(), e.message().to_string()))?;
+
+    let mut values = HashMap::new();
+    let mut problems = Problems::default();
+    flatten_table(path, &table, "", &mut values, &mut problems);
+    problems.finish()?;
+    Ok(values)
+}
+
+fn flatten_table(
+    path: &str,
+    table: &toml::Table,
+    prefix: &str,
+    values: &mut HashMap<String, String>,
+    problems: &mut Problems,
+) {
+    for (key, value) in table {
+        let dotted = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if let toml::Value::Table(inner) = value {
+            flatten_table(path, inner, &dotted, values, problems);
+            continue;
+        }
+
+        let name = dotted.replace('.', "_").to_ascii_uppercase();
+        let invalid = |msg: &str| ConfigError::File(path.to_string(), format!("`{}` {}", dotted, msg));
+        if !SETTINGS.contains(&name.as_str()) {
+            problems.0.push(invalid("is not a known setting"));
+            continue;
+        }
+        let value = match value {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(scalar_value)
+                .collect::<Option<Vec<_>>>()
+                .map(|items| items.join(",")),
+            other => scalar_value(other),
+        };
+        match value {
+            Some(value) => {
+                values.insert(name, value);
+            }
+            None => problems
+                .0
+                .push(invalid("must be a string, number, boolean, or array of those")),
+        }
+    }
+}
+
+fn scalar_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
 }
 
 #[cfg_attr(not(test), allow(dead_code))]
@@ -150,6 +849,350 @@ fn parse_leaderboard_users_from_map(
     }
 }
 
+/// Builder-logs match tolerances; unset or invalid variables keep the [`MatchTolerances`]
+/// defaults, the latter recorded in `problems`.
+fn parse_match_tolerances_from_map(
+    env_map: &HashMap<String, String>,
+    problems: &mut Problems,
+) -> MatchTolerances {
+    let defaults = MatchTolerances::default();
+
+    let time_ms = match env_map.get("MATCH_TIME_TOLERANCE_MS") {
+        Some(v) => v.parse::<i64>().ok().filter(|ms| *ms >= 0).ok_or_else(|| {
+            ConfigError::InvalidValue(
+                "MATCH_TIME_TOLERANCE_MS".to_string(),
+                "must be a non-negative integer".to_string(),
+            )
+        }),
+        None => Ok(defaults.time_ms),
+    };
+
+    MatchTolerances {
+        time_ms: problems.or(time_ms, defaults.time_ms),
+        px_abs: problems.or(
+            parse_tolerance_decimal(env_map, "MATCH_PX_TOLERANCE_ABS", defaults.px_abs),
+            defaults.px_abs,
+        ),
+        px_bps: problems.or(
+            parse_tolerance_decimal(env_map, "MATCH_PX_TOLERANCE_BPS", defaults.px_bps),
+            defaults.px_bps,
+        ),
+        sz_abs: problems.or(
+            parse_tolerance_decimal(env_map, "MATCH_SZ_TOLERANCE_ABS", defaults.sz_abs),
+            defaults.sz_abs,
+        ),
+    }
+}
+
+/// Builder fee rates that count for heuristic attribution, as comma-separated basis points.
+fn parse_heuristic_from_map(
+    env_map: &HashMap<String, String>,
+) -> Result<HeuristicAttributor, ConfigError> {
+    let Some(raw) = env_map.get("HEURISTIC_BUILDER_FEE_BPS") else {
+        return Ok(HeuristicAttributor::default());
+    };
+    let tiers = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            Decimal::from_str(s)
+                .ok()
+                .filter(|d| !d.is_negative() && !d.is_zero())
+                .ok_or_else(|| {
+                    ConfigError::InvalidValue(
+                        "HEURISTIC_BUILDER_FEE_BPS".to_string(),
+                        format!("{:?} is not a positive number of basis points", s),
+                    )
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(HeuristicAttributor::new(tiers))
+}
+
+/// Job queue limits; unset or invalid variables keep the [`JobLimits`] defaults.
+fn parse_job_limits_from_map(env_map: &HashMap<String, String>, problems: &mut Problems) -> JobLimits {
+    let defaults = JobLimits::default();
+    JobLimits {
+        max_workers: problems.or(
+            parse_positive_usize(env_map, "JOB_WORKERS", defaults.max_workers),
+            defaults.max_workers,
+        ),
+        max_per_user: problems.or(
+            parse_positive_usize(env_map, "JOB_MAX_PER_USER", defaults.max_per_user),
+            defaults.max_per_user,
+        ),
+        coin_parallelism: problems.or(
+            parse_positive_usize(env_map, "COMPILE_PARALLELISM", defaults.coin_parallelism),
+            defaults.coin_parallelism,
+        ),
+    }
+}
+
+/// Database pool sizes; unset or invalid variables keep the [`DbPoolConfig`] defaults.
+fn parse_db_pool_from_map(
+    env_map: &HashMap<String, String>,
+    problems: &mut Problems,
+) -> DbPoolConfig {
+    let defaults = DbPoolConfig::default();
+    let write_connections = problems.or(
+        parse_positive_usize(
+            env_map,
+            "DB_WRITE_POOL_SIZE",
+            defaults.write_connections as usize,
+        ),
+        defaults.write_connections as usize,
+    );
+    let read_connections = match env_map.get("DB_READ_POOL_SIZE") {
+        Some(v) => v.trim().parse::<u32>().map_err(|_| {
+            ConfigError::InvalidValue(
+                "DB_READ_POOL_SIZE".to_string(),
+                "must be a non-negative integer".to_string(),
+            )
+        }),
+        None => Ok(defaults.read_connections),
+    };
+    DbPoolConfig {
+        write_connections: u32::try_from(write_connections).unwrap_or(u32::MAX),
+        read_connections: problems.or(read_connections, defaults.read_connections),
+    }
+}
+
+/// Database maintenance schedule; unset or invalid variables keep the
+/// [`MaintenanceConfig`] defaults.
+fn parse_maintenance_from_map(
+    env_map: &HashMap<String, String>,
+    problems: &mut Problems,
+) -> MaintenanceConfig {
+    let defaults = MaintenanceConfig::default();
+    let mut interval = |key: &str, default: u64| {
+        problems.or(parse_interval_ms(env_map, key, default), default)
+    };
+    let checkpoint_interval_ms =
+        interval("DB_CHECKPOINT_INTERVAL_MS", defaults.checkpoint_interval_ms);
+    let analyze_interval_ms = interval("DB_ANALYZE_INTERVAL_MS", defaults.analyze_interval_ms);
+    let backup_interval_ms = interval("DB_BACKUP_INTERVAL_MS", defaults.backup_interval_ms);
+    let prune_interval_ms = interval("DB_PRUNE_INTERVAL_MS", defaults.prune_interval_ms);
+    let path = |key: &str| {
+        env_map
+            .get(key)
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+    };
+
+    MaintenanceConfig {
+        checkpoint_interval_ms,
+        analyze_interval_ms,
+        backup_dir: path("DB_BACKUP_DIR"),
+        backup_interval_ms,
+        backup_keep: problems.or(
+            parse_positive_usize(env_map, "DB_BACKUP_KEEP", defaults.backup_keep),
+            defaults.backup_keep,
+        ),
+        restore_from: path("DB_RESTORE_FROM"),
+        prune_interval_ms,
+        retention: RetentionPolicy {
+            position_snapshots_days: problems.or(
+                parse_retention_days(env_map, "RETAIN_POSITION_SNAPSHOTS_DAYS"),
+                None,
+            ),
+            equity_snapshots_days: problems
+                .or(parse_retention_days(env_map, "RETAIN_EQUITY_SNAPSHOTS_DAYS"), None),
+            raw_payloads_days: problems
+                .or(parse_retention_days(env_map, "RETAIN_RAW_PAYLOADS_DAYS"), None),
+            archive_path: path("RETENTION_ARCHIVE_PATH"),
+        },
+    }
+}
+
+/// Parse `API_KEYS` as comma-separated `key:scope[:requests_per_minute]` entries.
+///
+/// Entries without a rate use `API_RATE_LIMIT_PER_MINUTE`.
+fn parse_api_keys_from_map(
+    env_map: &HashMap<String, String>,
+) -> Result<Vec<ApiKeyConfig>, ConfigError> {
+    let default_rpm = parse_positive_usize(
+        env_map,
+        "API_RATE_LIMIT_PER_MINUTE",
+        DEFAULT_API_RATE_LIMIT_PER_MINUTE,
+    )?;
+    let invalid = |msg: String| ConfigError::InvalidValue("API_KEYS".to_string(), msg);
+
+    let mut keys: Vec<ApiKeyConfig> = Vec::new();
+    let entries = env_map.get("API_KEYS").map(String::as_str).unwrap_or("");
+    for entry in entries.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.split(':');
+        let key = parts.next().unwrap_or("").trim();
+        let scope = parts.next().ok_or_else(|| {
+            invalid("each entry must be key:scope[:requests_per_minute]".to_string())
+        })?;
+        let scope = ApiScope::from_str(scope)
+            .map_err(|_| invalid(format!("scope must be read or admin, got {}", scope)))?;
+        let requests_per_minute = match parts.next() {
+            Some(rpm) => rpm.trim().parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                invalid(format!("requests per minute must be a positive integer, got {}", rpm))
+            })?,
+            None => default_rpm,
+        };
+        if parts.next().is_some() || key.is_empty() || key.chars().any(char::is_whitespace) {
+            return Err(invalid(
+                "each entry must be key:scope[:requests_per_minute] with a non-empty key"
+                    .to_string(),
+            ));
+        }
+        if keys.iter().any(|k| k.key == key) {
+            return Err(invalid("duplicate key".to_string()));
+        }
+        keys.push(ApiKeyConfig {
+            key: key.to_string(),
+            scope,
+            requests_per_minute: u32::try_from(requests_per_minute).unwrap_or(u32::MAX),
+        });
+    }
+    Ok(keys)
+}
+
+fn parse_webhooks_from_map(
+    env_map: &HashMap<String, String>,
+) -> Result<WebhookConfig, ConfigError> {
+    let list = |key: &str| {
+        env_map
+            .get(key)
+            .map(String::as_str)
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let urls = list("WEBHOOK_URLS");
+    if let Some(url) = urls
+        .iter()
+        .find(|url| !(url.starts_with("http://") || url.starts_with("https://")))
+    {
+        return Err(ConfigError::InvalidValue(
+            "WEBHOOK_URLS".to_string(),
+            format!("must be http(s) URLs, got {}", url),
+        ));
+    }
+    let events = list("WEBHOOK_EVENTS")
+        .iter()
+        .map(|event| {
+            WebhookEventKind::from_str(event).map_err(|_| {
+                let known: Vec<_> = WebhookEventKind::ALL.iter().map(|k| k.as_str()).collect();
+                ConfigError::InvalidValue(
+                    "WEBHOOK_EVENTS".to_string(),
+                    format!("must be among {}, got {}", known.join(", "), event),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let secret = env_map
+        .get("WEBHOOK_SECRET")
+        .filter(|s| !s.is_empty())
+        .cloned();
+
+    Ok(WebhookConfig {
+        urls,
+        secret,
+        events,
+    })
+}
+
+fn parse_bool(env_map: &HashMap<String, String>, key: &str, default: bool) -> Result<bool, ConfigError> {
+    match env_map.get(key).map(|s| s.trim()) {
+        None => Ok(default),
+        Some("true" | "1") => Ok(true),
+        Some("false" | "0") => Ok(false),
+        Some(other) => Err(ConfigError::InvalidValue(
+            key.to_string(),
+            format!("must be true or false, got {}", other),
+        )),
+    }
+}
+
+fn parse_positive_usize(
+    env_map: &HashMap<String, String>,
+    key: &str,
+    default: usize,
+) -> Result<usize, ConfigError> {
+    match env_map.get(key) {
+        Some(v) => v
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                ConfigError::InvalidValue(key.to_string(), "must be a positive integer".to_string())
+            }),
+        None => Ok(default),
+    }
+}
+
+/// An interface address to listen on; loopback when unset.
+fn parse_ip_addr(env_map: &HashMap<String, String>, key: &str) -> Result<IpAddr, ConfigError> {
+    match env_map.get(key) {
+        Some(v) => v.trim().parse::<IpAddr>().map_err(|_| {
+            ConfigError::InvalidValue(
+                key.to_string(),
+                format!("must be an IP address, got {}", v.trim()),
+            )
+        }),
+        None => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    }
+}
+
+/// A non-negative millisecond interval where `0` means disabled.
+fn parse_interval_ms(
+    env_map: &HashMap<String, String>,
+    key: &str,
+    default: u64,
+) -> Result<u64, ConfigError> {
+    match env_map.get(key) {
+        Some(v) => v.trim().parse::<u64>().map_err(|_| {
+            ConfigError::InvalidValue(key.to_string(), "must be a non-negative integer".to_string())
+        }),
+        None => Ok(default),
+    }
+}
+
+/// A retention period in days; unset keeps rows forever.
+fn parse_retention_days(
+    env_map: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<u32>, ConfigError> {
+    env_map
+        .get(key)
+        .map(|v| {
+            v.trim().parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                ConfigError::InvalidValue(key.to_string(), "must be a positive integer".to_string())
+            })
+        })
+        .transpose()
+}
+
+fn parse_tolerance_decimal(
+    env_map: &HashMap<String, String>,
+    key: &str,
+    default: Decimal,
+) -> Result<Decimal, ConfigError> {
+    match env_map.get(key) {
+        Some(v) => Decimal::from_str(v.trim())
+            .ok()
+            .filter(|d| !d.is_negative())
+            .ok_or_else(|| {
+                ConfigError::InvalidValue(
+                    key.to_string(),
+                    "must be a non-negative decimal".to_string(),
+                )
+            }),
+        None => Ok(default),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,7 +1204,10 @@ mod tests {
             "HYPERLIQUID_API_URL".to_string(),
             "https://api.hyperliquid.xyz".to_string(),
         );
-        map.insert("TARGET_BUILDER".to_string(), "0x123".to_string());
+        map.insert(
+            "TARGET_BUILDER".to_string(),
+            "0x2868fc0d9786a740b491577a43502259efa78a39".to_string(),
+        );
         map
     }
 
@@ -177,13 +1223,27 @@ mod tests {
     }
 
     #[test]
-    fn test_missing_hyperliquid_api_url() {
+    fn test_hyperliquid_api_url_defaults_to_network() {
         let mut env_map = setup_required_env();
         env_map.remove("HYPERLIQUID_API_URL");
-        let result = Config::from_env_map(env_map);
-        match result {
-            Err(ConfigError::MissingEnv(s)) => assert_eq!(s, "HYPERLIQUID_API_URL"),
-            _ => panic!("Expected MissingEnv error"),
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.network, Network::Mainnet);
+        assert_eq!(config.hyperliquid_api_url, "https://api.hyperliquid.xyz");
+
+        env_map.insert("HYPERLIQUID_NETWORK".to_string(), "Testnet".to_string());
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.network, Network::Testnet);
+        assert_eq!(config.hyperliquid_api_url, "https://api.hyperliquid-testnet.xyz");
+
+        // An explicit URL still wins, e.g. for a proxy.
+        env_map.insert("HYPERLIQUID_API_URL".to_string(), "http://proxy.local".to_string());
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.hyperliquid_api_url, "http://proxy.local");
+
+        env_map.insert("HYPERLIQUID_NETWORK".to_string(), "devnet".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(key, _)) => assert_eq!(key, "HYPERLIQUID_NETWORK"),
+            other => panic!("Expected InvalidValue, got {:?}", other),
         }
     }
 
@@ -198,6 +1258,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_invalid_target_builder() {
+        for value in ["0x123", "2868fc0d9786a740b491577a43502259efa78a39ab", "0xbuilder"] {
+            let mut env_map = setup_required_env();
+            env_map.insert("TARGET_BUILDER".to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(key, msg)) => {
+                    assert_eq!(key, "TARGET_BUILDER");
+                    assert!(msg.contains(value), "{}", msg);
+                }
+                other => panic!("Expected InvalidValue for {}, got {:?}", value, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_target_builder_is_lowercased() {
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "TARGET_BUILDER".to_string(),
+            " 0x2868FC0D9786A740B491577A43502259EFA78A39 ".to_string(),
+        );
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(
+            config.target_builder,
+            "0x2868fc0d9786a740b491577a43502259efa78a39"
+        );
+    }
+
     #[test]
     fn test_invalid_port() {
         let mut env_map = setup_required_env();
@@ -233,4 +1322,644 @@ mod tests {
             _ => panic!("Expected InvalidValue error"),
         }
     }
+
+    #[test]
+    fn test_pnl_mode_names() {
+        let mut env_map = setup_required_env();
+        env_map.insert("PNL_MODE".to_string(), "netAllFees".to_string());
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.pnl_mode, PnlMode::NetAllFees);
+
+        env_map.insert("PNL_MODE".to_string(), "net".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(config.pnl_mode, PnlMode::NetFees);
+    }
+
+    #[test]
+    fn test_startup_integrity_check_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.startup_integrity_check, IntegrityCheck::Quick);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("STARTUP_INTEGRITY_CHECK".to_string(), "Full".to_string());
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.startup_integrity_check, IntegrityCheck::Full);
+
+        env_map.insert("STARTUP_INTEGRITY_CHECK".to_string(), "none".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "STARTUP_INTEGRITY_CHECK"),
+            _ => panic!("Expected InvalidValue error for STARTUP_INTEGRITY_CHECK"),
+        }
+    }
+
+    #[test]
+    fn test_overflow_policy_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.overflow_policy, OverflowPolicy::Fail);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("DECIMAL_OVERFLOW_POLICY".to_string(), "Taint".to_string());
+        let config = Config::from_env_map(env_map.clone()).unwrap();
+        assert_eq!(config.overflow_policy, OverflowPolicy::Taint);
+
+        env_map.insert("DECIMAL_OVERFLOW_POLICY".to_string(), "saturate".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "DECIMAL_OVERFLOW_POLICY"),
+            _ => panic!("Expected InvalidValue error for DECIMAL_OVERFLOW_POLICY"),
+        }
+    }
+
+    #[test]
+    fn test_hyperliquid_ws_url_is_optional() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.hyperliquid_ws_url, None);
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "HYPERLIQUID_WS_URL".to_string(),
+            "wss://api.hyperliquid.xyz/ws".to_string(),
+        );
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(
+            config.hyperliquid_ws_url.as_deref(),
+            Some("wss://api.hyperliquid.xyz/ws")
+        );
+    }
+
+    #[test]
+    fn test_match_tolerances_default_when_unset() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.match_tolerances, MatchTolerances::default());
+    }
+
+    #[test]
+    fn test_match_tolerances_from_env() {
+        let mut env_map = setup_required_env();
+        env_map.insert("MATCH_TIME_TOLERANCE_MS".to_string(), "250".to_string());
+        env_map.insert("MATCH_PX_TOLERANCE_BPS".to_string(), "2.5".to_string());
+        env_map.insert("MATCH_SZ_TOLERANCE_ABS".to_string(), "0.01".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+
+        assert_eq!(config.match_tolerances.time_ms, 250);
+        assert_eq!(
+            config.match_tolerances.px_bps,
+            Decimal::from_str("2.5").unwrap()
+        );
+        assert_eq!(
+            config.match_tolerances.sz_abs,
+            Decimal::from_str("0.01").unwrap()
+        );
+        assert_eq!(
+            config.match_tolerances.px_abs,
+            MatchTolerances::default().px_abs
+        );
+    }
+
+    #[test]
+    fn test_heuristic_builder_fee_tiers() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert!(config.heuristic_attribution.fee_tiers_bps.is_empty());
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "HEURISTIC_BUILDER_FEE_BPS".to_string(),
+            "1, 2.5".to_string(),
+        );
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(
+            config.heuristic_attribution.fee_tiers_bps,
+            vec![Decimal::from_str("1").unwrap(), Decimal::from_str("2.5").unwrap()]
+        );
+
+        for bad in ["0", "-1", "1,abc"] {
+            let mut env_map = setup_required_env();
+            env_map.insert("HEURISTIC_BUILDER_FEE_BPS".to_string(), bad.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(key, _)) => {
+                    assert_eq!(key, "HEURISTIC_BUILDER_FEE_BPS")
+                }
+                other => panic!("expected InvalidValue for {:?}, got {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_match_tolerances() {
+        for (key, value) in [
+            ("MATCH_TIME_TOLERANCE_MS", "-1"),
+            ("MATCH_PX_TOLERANCE_BPS", "abc"),
+            ("MATCH_SZ_TOLERANCE_ABS", "-0.1"),
+        ] {
+            let mut env_map = setup_required_env();
+            env_map.insert(key.to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, key),
+                _ => panic!("Expected InvalidValue error for {}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_job_limits_from_env() {
+        let mut env_map = setup_required_env();
+        env_map.insert("JOB_WORKERS".to_string(), "16".to_string());
+        env_map.insert("COMPILE_PARALLELISM".to_string(), "2".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+
+        assert_eq!(config.job_limits.max_workers, 16);
+        assert_eq!(config.job_limits.coin_parallelism, 2);
+        assert_eq!(
+            config.job_limits.max_per_user,
+            JobLimits::default().max_per_user
+        );
+
+        let mut env_map = setup_required_env();
+        env_map.insert("COMPILE_PARALLELISM".to_string(), "0".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "COMPILE_PARALLELISM"),
+            _ => panic!("Expected InvalidValue error for COMPILE_PARALLELISM"),
+        }
+    }
+
+    #[test]
+    fn test_builder_logs_max_concurrent_downloads() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.builder_logs_max_concurrent_downloads, 4);
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "BUILDER_LOGS_MAX_CONCURRENT_DOWNLOADS".to_string(),
+            "16".to_string(),
+        );
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(config.builder_logs_max_concurrent_downloads, 16);
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "BUILDER_LOGS_MAX_CONCURRENT_DOWNLOADS".to_string(),
+            "0".to_string(),
+        );
+        assert!(Config::from_env_map(env_map).is_err());
+    }
+
+    #[test]
+    fn test_builder_logs_auto_backfill() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert!(!config.builder_logs_auto_backfill);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("BUILDER_LOGS_AUTO_BACKFILL".to_string(), "true".to_string());
+        assert!(Config::from_env_map(env_map).unwrap().builder_logs_auto_backfill);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("BUILDER_LOGS_AUTO_BACKFILL".to_string(), "yes".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "BUILDER_LOGS_AUTO_BACKFILL"),
+            _ => panic!("Expected InvalidValue error for BUILDER_LOGS_AUTO_BACKFILL"),
+        }
+    }
+
+    #[test]
+    fn test_api_keys_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert!(config.api_keys.is_empty());
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "API_KEYS".to_string(),
+            "reader:read, ops:admin:30".to_string(),
+        );
+        env_map.insert("API_RATE_LIMIT_PER_MINUTE".to_string(), "120".to_string());
+        let keys = Config::from_env_map(env_map).unwrap().api_keys;
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key, "reader");
+        assert_eq!(keys[0].scope, ApiScope::Read);
+        assert_eq!(keys[0].requests_per_minute, 120);
+        assert_eq!(keys[1].scope, ApiScope::Admin);
+        assert_eq!(keys[1].requests_per_minute, 30);
+
+        for bad in ["nokey", "k:owner", "k:read:0", ":read", "k:read,k:admin"] {
+            let mut env_map = setup_required_env();
+            env_map.insert("API_KEYS".to_string(), bad.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "API_KEYS", "{}", bad),
+                _ => panic!("Expected InvalidValue error for API_KEYS={}", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn test_response_cache_ttl() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.response_cache_ttl(), Duration::from_secs(5));
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RESPONSE_CACHE_TTL_MS".to_string(), "0".to_string());
+        assert!(Config::from_env_map(env_map).unwrap().response_cache_ttl().is_zero());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RESPONSE_CACHE_TTL_MS".to_string(), "-1".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "RESPONSE_CACHE_TTL_MS"),
+            _ => panic!("Expected InvalidValue error for RESPONSE_CACHE_TTL_MS"),
+        }
+    }
+
+    #[test]
+    fn test_sub_account_rollup_from_env() {
+        assert!(!Config::from_env_map(setup_required_env()).unwrap().sub_account_rollup);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("SUB_ACCOUNT_ROLLUP".to_string(), "1".to_string());
+        assert!(Config::from_env_map(env_map).unwrap().sub_account_rollup);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("SUB_ACCOUNT_ROLLUP".to_string(), "maybe".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "SUB_ACCOUNT_ROLLUP"),
+            _ => panic!("Expected InvalidValue error for SUB_ACCOUNT_ROLLUP"),
+        }
+    }
+
+    #[test]
+    fn test_ingest_chunking_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.ingest_chunk_ms, 86_400_000);
+        assert_eq!(config.ingest_max_concurrent_chunks, 4);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("INGEST_CHUNK_MS".to_string(), "3600000".to_string());
+        env_map.insert("INGEST_MAX_CONCURRENT_CHUNKS".to_string(), "2".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(config.ingest_chunk_ms, 3_600_000);
+        assert_eq!(config.ingest_max_concurrent_chunks, 2);
+
+        for (key, value) in [("INGEST_CHUNK_MS", "0"), ("INGEST_MAX_CONCURRENT_CHUNKS", "0")] {
+            let mut env_map = setup_required_env();
+            env_map.insert(key.to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, key),
+                _ => panic!("Expected InvalidValue error for {}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reports_every_invalid_setting() {
+        let mut env_map = setup_required_env();
+        env_map.remove("DATABASE_PATH");
+        env_map.insert("PORT".to_string(), "http".to_string());
+        env_map.insert("MATCH_PX_TOLERANCE_BPS".to_string(), "-1".to_string());
+        env_map.insert("DB_BACKUP_KEEP".to_string(), "0".to_string());
+
+        let err = Config::from_env_map(env_map).unwrap_err();
+        let ConfigError::Multiple(errors) = &err else {
+            panic!("Expected Multiple, got {:?}", err);
+        };
+        let keys: Vec<&str> = errors
+            .iter()
+            .map(|e| match e {
+                ConfigError::MissingEnv(k) | ConfigError::InvalidValue(k, _) => k.as_str(),
+                other => panic!("Unexpected error {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            ["PORT", "DATABASE_PATH", "MATCH_PX_TOLERANCE_BPS", "DB_BACKUP_KEEP"]
+        );
+        assert!(err.to_string().starts_with("4 configuration errors:\n  - Invalid value for PORT"));
+    }
+
+    #[test]
+    fn test_config_file_values_layer_under_env() {
+        let file = r#"
+            database_path = "/data/file.db"
+            port = 9000
+            leaderboard_users = ["0xa", "0xb"]
+            record_raw_payloads = true
+
+            [match]
+            px_tolerance_bps = "2.5"
+
+            [retain]
+            raw_payloads_days = 30
+        "#;
+        let mut values = config_file_values("hypesilico.toml", file).unwrap();
+        assert_eq!(values["MATCH_PX_TOLERANCE_BPS"], "2.5");
+        assert_eq!(values["LEADERBOARD_USERS"], "0xa,0xb");
+
+        let mut env_map = setup_required_env();
+        env_map.insert("PORT".to_string(), "9100".to_string());
+        values.extend(env_map);
+        let config = Config::from_env_map(values).unwrap();
+
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.database_path, "/tmp/test.db");
+        assert_eq!(config.leaderboard_users, vec!["0xa", "0xb"]);
+        assert!(config.record_raw_payloads);
+        assert_eq!(config.match_tolerances.px_bps, Decimal::from_str("2.5").unwrap());
+        assert_eq!(config.maintenance.retention.raw_payloads_days, Some(30));
+    }
+
+    #[test]
+    fn test_config_file_rejects_unknown_keys_and_values() {
+        let file = r#"
+            prot = 9000
+            [db]
+            backup_dir = 1979-05-27T07:32:00Z
+        "#;
+        let err = config_file_values("hypesilico.toml", file).unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(err, ConfigError::Multiple(ref errors) if errors.len() == 2));
+        assert!(message.contains("`prot` is not a known setting"));
+        assert!(message.contains("`db.backup_dir` must be a string, number, boolean"));
+
+        match config_file_values("hypesilico.toml", "port = ") {
+            Err(ConfigError::File(path, _)) => assert_eq!(path, "hypesilico.toml"),
+            other => panic!("Expected File error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_ttl_from_env() {
+        assert_eq!(
+            Config::from_env_map(setup_required_env()).unwrap().idempotency_ttl_ms,
+            86_400_000
+        );
+
+        let mut env_map = setup_required_env();
+        env_map.insert("IDEMPOTENCY_TTL_MS".to_string(), "0".to_string());
+        assert_eq!(Config::from_env_map(env_map).unwrap().idempotency_ttl_ms, 0);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("IDEMPOTENCY_TTL_MS".to_string(), "soon".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "IDEMPOTENCY_TTL_MS"),
+            _ => panic!("Expected InvalidValue error for IDEMPOTENCY_TTL_MS"),
+        }
+    }
+
+    #[test]
+    fn test_leaderboard_snapshot_interval_from_env() {
+        assert_eq!(
+            Config::from_env_map(setup_required_env())
+                .unwrap()
+                .leaderboard_snapshot_interval_ms,
+            86_400_000
+        );
+
+        let mut env_map = setup_required_env();
+        env_map.insert("LEADERBOARD_SNAPSHOT_INTERVAL_MS".to_string(), "3600000".to_string());
+        assert_eq!(
+            Config::from_env_map(env_map).unwrap().leaderboard_snapshot_interval_ms,
+            3_600_000
+        );
+    }
+
+    #[test]
+    fn test_equity_sample_interval_from_env() {
+        assert_eq!(
+            Config::from_env_map(setup_required_env())
+                .unwrap()
+                .equity_sample_interval_ms,
+            3_600_000
+        );
+
+        let mut env_map = setup_required_env();
+        env_map.insert("EQUITY_SAMPLE_INTERVAL_MS".to_string(), "0".to_string());
+        assert_eq!(Config::from_env_map(env_map).unwrap().equity_sample_interval_ms, 0);
+    }
+
+    #[test]
+    fn test_coin_meta_refresh_interval_from_env() {
+        assert_eq!(
+            Config::from_env_map(setup_required_env())
+                .unwrap()
+                .coin_meta_refresh_interval_ms,
+            3_600_000
+        );
+
+        let mut env_map = setup_required_env();
+        env_map.insert("COIN_META_REFRESH_INTERVAL_MS".to_string(), "0".to_string());
+        assert_eq!(Config::from_env_map(env_map).unwrap().coin_meta_refresh_interval_ms, 0);
+    }
+
+    #[test]
+    fn test_webhooks_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.webhooks, WebhookConfig::default());
+
+        let mut env_map = setup_required_env();
+        env_map.insert(
+            "WEBHOOK_URLS".to_string(),
+            "https://a.example/hook, http://b.example".to_string(),
+        );
+        env_map.insert("WEBHOOK_SECRET".to_string(), "s3cret".to_string());
+        env_map.insert("WEBHOOK_EVENTS".to_string(), "taint.changed".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(
+            config.webhooks,
+            WebhookConfig {
+                urls: vec![
+                    "https://a.example/hook".to_string(),
+                    "http://b.example".to_string()
+                ],
+                secret: Some("s3cret".to_string()),
+                events: vec![WebhookEventKind::TaintChanged],
+            }
+        );
+        assert!(!format!("{:?}", config).contains("s3cret"));
+
+        for (key, value) in [("WEBHOOK_URLS", "a.example"), ("WEBHOOK_EVENTS", "fills")] {
+            let mut env_map = setup_required_env();
+            env_map.insert(key.to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, key),
+                _ => panic!("Expected InvalidValue error for {}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_db_pool_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.db_pool, DbPoolConfig::default());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("DB_WRITE_POOL_SIZE".to_string(), "2".to_string());
+        env_map.insert("DB_READ_POOL_SIZE".to_string(), "0".to_string());
+        assert_eq!(
+            Config::from_env_map(env_map).unwrap().db_pool,
+            DbPoolConfig {
+                write_connections: 2,
+                read_connections: 0,
+            }
+        );
+
+        for (key, value) in [("DB_WRITE_POOL_SIZE", "0"), ("DB_READ_POOL_SIZE", "-1")] {
+            let mut env_map = setup_required_env();
+            env_map.insert(key.to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, key),
+                _ => panic!("Expected InvalidValue error for {}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_maintenance_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.maintenance, MaintenanceConfig::default());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("DB_CHECKPOINT_INTERVAL_MS".to_string(), "0".to_string());
+        env_map.insert("DB_BACKUP_DIR".to_string(), " /var/backups/hypesilico ".to_string());
+        env_map.insert("DB_BACKUP_KEEP".to_string(), "3".to_string());
+        let maintenance = Config::from_env_map(env_map).unwrap().maintenance;
+        assert_eq!(maintenance.checkpoint_interval_ms, 0);
+        assert_eq!(
+            maintenance.backup_dir,
+            Some(std::path::PathBuf::from("/var/backups/hypesilico"))
+        );
+        assert_eq!(maintenance.backup_keep, 3);
+        assert_eq!(maintenance.restore_from, None);
+        assert!(!maintenance.retention.is_enabled());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RETAIN_POSITION_SNAPSHOTS_DAYS".to_string(), "90".to_string());
+        env_map.insert("RETENTION_ARCHIVE_PATH".to_string(), "archive.db".to_string());
+        let retention = Config::from_env_map(env_map).unwrap().maintenance.retention;
+        assert_eq!(retention.position_snapshots_days, Some(90));
+        assert_eq!(retention.equity_snapshots_days, None);
+        assert_eq!(
+            retention.archive_path,
+            Some(std::path::PathBuf::from("archive.db"))
+        );
+
+        for (key, value) in [
+            ("DB_ANALYZE_INTERVAL_MS", "-1"),
+            ("DB_BACKUP_KEEP", "0"),
+            ("RETAIN_RAW_PAYLOADS_DAYS", "0"),
+        ] {
+            let mut env_map = setup_required_env();
+            env_map.insert(key.to_string(), value.to_string());
+            match Config::from_env_map(env_map) {
+                Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, key),
+                _ => panic!("Expected InvalidValue error for {}", key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_record_raw_payloads_from_env() {
+        assert!(!Config::from_env_map(setup_required_env()).unwrap().record_raw_payloads);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RECORD_RAW_PAYLOADS".to_string(), "true".to_string());
+        assert!(Config::from_env_map(env_map).unwrap().record_raw_payloads);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RECORD_RAW_PAYLOADS".to_string(), "yes".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "RECORD_RAW_PAYLOADS"),
+            _ => panic!("Expected InvalidValue error for RECORD_RAW_PAYLOADS"),
+        }
+    }
+
+    #[test]
+    fn test_listen_addresses_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert_eq!(config.bind_addr, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(config.admin_port, None);
+        assert_eq!(config.admin_bind_addr, IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let mut env_map = setup_required_env();
+        env_map.insert("BIND_ADDR".to_string(), "0.0.0.0".to_string());
+        env_map.insert("ADMIN_PORT".to_string(), "9090".to_string());
+        env_map.insert("ADMIN_BIND_ADDR".to_string(), "::1".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+        assert_eq!(config.bind_addr, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(config.admin_port, Some(9090));
+        assert_eq!(config.admin_bind_addr, "::1".parse::<IpAddr>().unwrap());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("ADMIN_PORT".to_string(), "8080".to_string());
+        env_map.insert("BIND_ADDR".to_string(), "localhost".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::Multiple(errors)) => {
+                let keys: Vec<_> = errors
+                    .iter()
+                    .map(|e| match e {
+                        ConfigError::InvalidValue(k, _) => k.as_str(),
+                        _ => panic!("Expected InvalidValue, got {:?}", e),
+                    })
+                    .collect();
+                assert_eq!(keys, vec!["BIND_ADDR", "ADMIN_PORT"]);
+            }
+            other => panic!("Expected both errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_limits_from_env() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert!(config.response_compression);
+        assert_eq!(config.max_request_body_bytes, 2 * 1024 * 1024);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("RESPONSE_COMPRESSION".to_string(), "false".to_string());
+        env_map.insert("MAX_REQUEST_BODY_BYTES".to_string(), "1024".to_string());
+        let config = Config::from_env_map(env_map).unwrap();
+        assert!(!config.response_compression);
+        assert_eq!(config.max_request_body_bytes, 1024);
+
+        let mut env_map = setup_required_env();
+        env_map.insert("MAX_REQUEST_BODY_BYTES".to_string(), "0".to_string());
+        match Config::from_env_map(env_map) {
+            Err(ConfigError::InvalidValue(k, _)) => assert_eq!(k, "MAX_REQUEST_BODY_BYTES"),
+            _ => panic!("Expected InvalidValue error for MAX_REQUEST_BODY_BYTES"),
+        }
+    }
+
+    #[test]
+    fn test_read_only_needs_a_restart() {
+        let config = Config::from_env_map(setup_required_env()).unwrap();
+        assert!(!config.read_only);
+
+        let handle = ConfigHandle::new(config);
+        let mut env_map = setup_required_env();
+        env_map.insert("READ_ONLY".to_string(), "true".to_string());
+        let next = Config::from_env_map(env_map).unwrap();
+        assert!(next.read_only);
+        let report = handle.reload(next);
+        assert_eq!(report.restart_required, vec!["read_only"]);
+        assert!(!handle.load().read_only);
+    }
+
+    #[test]
+    fn test_reload_keeps_structural_settings() {
+        let handle = ConfigHandle::new(Config::from_env_map(setup_required_env()).unwrap());
+        let reloads = handle.subscribe();
+
+        let unchanged = handle.reload(Config::from_env_map(setup_required_env()).unwrap());
+        assert_eq!(unchanged, ReloadReport::default());
+        assert!(!reloads.has_changed().unwrap());
+
+        let mut env_map = setup_required_env();
+        env_map.insert("PORT".to_string(), "9090".to_string());
+        env_map.insert(
+            "LEADERBOARD_USERS".to_string(),
+            "0x0000000000000000000000000000000000000001".to_string(),
+        );
+        env_map.insert("MATCH_TIME_TOLERANCE_MS".to_string(), "250".to_string());
+        let report = handle.reload(Config::from_env_map(env_map).unwrap());
+        assert_eq!(report.changed, vec!["leaderboard_users", "match_tolerances"]);
+        assert_eq!(report.restart_required, vec!["port"]);
+        assert!(reloads.has_changed().unwrap());
+
+        let config = handle.load();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.leaderboard_users.len(), 1);
+        assert_eq!(config.match_tolerances.time_ms, 250);
+    }
 }
@@ -1,9 +1,22 @@
 //! Fetching and parsing Hyperliquid builder fills logs.
 
-use crate::domain::{Address, BuilderLogFill, Coin, Decimal, Side, TimeMs};
+use super::normalize::{BuilderLogsCsvAdapter, FillAdapter, NormalizeError, Normalized};
+use crate::config::Network;
+use crate::db::repo::{BuilderLogFileRow, BuilderLogRejectRow};
+use crate::db::Repository;
+use crate::domain::{Address, BuilderLogFill, TimeMs};
 use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
+use tokio::sync::Semaphore;
+
+/// Daily files downloaded at once unless configured otherwise.
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 4;
 
 #[derive(Debug, Error)]
 pub enum BuilderLogsError {
@@ -15,11 +28,58 @@ pub enum BuilderLogsError {
     Lz4(String),
     #[error("csv parse error: {0}")]
     Csv(String),
+    #[error("cache error: {0}")]
+    Cache(String),
+}
+
+/// A parsed daily builder log file.
+///
+/// Malformed rows do not fail the whole day: they are skipped and reported in `rejects`
+/// so they can be recorded in the `builder_log_rejects` dead-letter table.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedBuilderLogs {
+    pub fills: Vec<BuilderLogFill>,
+    pub rejects: Vec<BuilderLogRejectRow>,
+}
+
+/// Downloads by a [`BuilderLogsFetcher`] and its clones since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderLogsDownloadStats {
+    /// Files downloaded in full.
+    pub files: u64,
+    /// Downloads that failed, including `403`/`404` for days without a file.
+    pub failures: u64,
+    /// Compressed bytes received.
+    pub bytes: u64,
+    /// Time spent on successful downloads, summed over files.
+    pub download_ms: u64,
+    /// Downloads running now.
+    pub in_flight: u64,
+    /// Downloads allowed to run at once.
+    pub max_concurrent: usize,
 }
 
+#[derive(Debug, Default)]
+struct DownloadCounters {
+    files: AtomicU64,
+    failures: AtomicU64,
+    bytes: AtomicU64,
+    download_ms: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+/// Downloads daily builder fills logs.
+///
+/// Clones share one download limit, so every caller together runs at most
+/// `max_concurrent_downloads` requests.
 #[derive(Debug, Clone)]
 pub struct BuilderLogsFetcher {
     client: reqwest::Client,
+    network: Network,
+    downloads: Arc<Semaphore>,
+    max_concurrent_downloads: usize,
+    counters: Arc<DownloadCounters>,
 }
 
 #[async_trait]
@@ -29,28 +89,137 @@ pub trait BuilderLogsSource: Send + Sync {
         builder: &Address,
         yyyymmdd: &str,
     ) -> Result<Vec<BuilderLogFill>, BuilderLogsError>;
+
+    /// Download counters, for sources that download.
+    fn download_stats(&self) -> Option<BuilderLogsDownloadStats> {
+        None
+    }
 }
 
 impl BuilderLogsFetcher {
+    /// A fetcher for Mainnet logs.
     pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            network: Network::Mainnet,
+            downloads: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS)),
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            counters: Arc::new(DownloadCounters::default()),
+        }
+    }
+
+    /// Fetch the logs published for `network` instead.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Allow `max` downloads at once (at least one).
+    pub fn with_max_concurrent_downloads(mut self, max: usize) -> Self {
+        let max = max.max(1);
+        self.downloads = Arc::new(Semaphore::new(max));
+        self.max_concurrent_downloads = max;
+        self
     }
 
-    pub fn builder_logs_url(builder: &Address, yyyymmdd: &str) -> String {
+    pub fn stats(&self) -> BuilderLogsDownloadStats {
+        BuilderLogsDownloadStats {
+            files: self.counters.files.load(Ordering::Relaxed),
+            failures: self.counters.failures.load(Ordering::Relaxed),
+            bytes: self.counters.bytes.load(Ordering::Relaxed),
+            download_ms: self.counters.download_ms.load(Ordering::Relaxed),
+            in_flight: self.counters.in_flight.load(Ordering::Relaxed),
+            max_concurrent: self.max_concurrent_downloads,
+        }
+    }
+
+    pub fn builder_logs_url(network: Network, builder: &Address, yyyymmdd: &str) -> String {
         format!(
-            "https://stats-data.hyperliquid.xyz/Mainnet/builder_fills/{}/{}.csv.lz4",
+            "https://stats-data.hyperliquid.xyz/{}/builder_fills/{}/{}.csv.lz4",
+            network.stats_prefix(),
             builder.as_str(),
             yyyymmdd
         )
     }
 
+    /// Send a `HEAD` request to a builder log URL and return the HTTP status.
+    ///
+    /// Used by diagnostics to tell an unreachable host apart from a builder or day that has
+    /// no log file (typically `403`/`404`).
+    pub async fn probe_url(&self, url: &str) -> Result<u16, BuilderLogsError> {
+        self.client
+            .head(url)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .map(|resp| resp.status().as_u16())
+            .map_err(|e| BuilderLogsError::Http(e.to_string()))
+    }
+
     pub async fn fetch_lz4_bytes(
         &self,
         builder: &Address,
         yyyymmdd: &str,
     ) -> Result<Vec<u8>, BuilderLogsError> {
-        let url = Self::builder_logs_url(builder, yyyymmdd);
-        let resp = self
+        self.fetch_lz4_file(builder, yyyymmdd)
+            .await
+            .map(|(bytes, _etag)| bytes)
+    }
+
+    /// Download a daily file, returning its bytes and the `ETag` header if the server sent one.
+    ///
+    /// Waits for a download slot first. The compressed body is kept, since the cache stores
+    /// it as served; decompression streams from it in [`Self::parse_lz4`].
+    async fn fetch_lz4_file(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<(Vec<u8>, Option<String>), BuilderLogsError> {
+        let _slot = self
+            .downloads
+            .acquire()
+            .await
+            .map_err(|e| BuilderLogsError::Http(e.to_string()))?;
+        self.counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        let result = self.download(builder, yyyymmdd).await;
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        match &result {
+            Ok((bytes, _)) => {
+                self.counters.files.fetch_add(1, Ordering::Relaxed);
+                self.counters.bytes.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                self.counters.download_ms.fetch_add(elapsed_ms, Ordering::Relaxed);
+                tracing::info!(
+                    builder=%builder,
+                    yyyymmdd=%yyyymmdd,
+                    bytes = bytes.len(),
+                    elapsed_ms,
+                    "Downloaded builder log file"
+                );
+            }
+            Err(e) => {
+                self.counters.failures.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(
+                    builder=%builder,
+                    yyyymmdd=%yyyymmdd,
+                    elapsed_ms,
+                    error=%e,
+                    "Builder log file download failed"
+                );
+            }
+        }
+        result
+    }
+
+    async fn download(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<(Vec<u8>, Option<String>), BuilderLogsError> {
+        let url = Self::builder_logs_url(self.network, builder, yyyymmdd);
+        let mut resp = self
             .client
             .get(url)
             .send()
@@ -62,10 +231,21 @@ impl BuilderLogsFetcher {
             return Err(BuilderLogsError::HttpStatus(status.as_u16()));
         }
 
-        resp.bytes()
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
+        let mut bytes = Vec::with_capacity(resp.content_length().unwrap_or(0) as usize);
+        while let Some(chunk) = resp
+            .chunk()
             .await
-            .map(|b| b.to_vec())
-            .map_err(|e| BuilderLogsError::Http(e.to_string()))
+            .map_err(|e| BuilderLogsError::Http(e.to_string()))?
+        {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok((bytes, etag))
     }
 
     pub fn decompress_lz4_frame(lz4_bytes: &[u8]) -> Result<Vec<u8>, BuilderLogsError> {
@@ -77,84 +257,20 @@ impl BuilderLogsFetcher {
         Ok(out)
     }
 
-    pub fn parse_csv(csv_bytes: &[u8]) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
-        // Actual API schema:
-        // time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee
-        #[derive(Debug, serde::Deserialize)]
-        #[allow(dead_code)]
-        struct Row {
-            time: String, // ISO8601 format: "2024-12-15T10:30:45.123Z"
-            user: String,
-            coin: String,
-            side: String,
-            px: String,
-            sz: String,
-            // Additional columns from actual API (we ignore them but need to handle them)
-            #[serde(default)]
-            crossed: Option<String>,
-            #[serde(default)]
-            special_trade_type: Option<String>,
-            #[serde(default)]
-            tif: Option<String>,
-            #[serde(default)]
-            is_trigger: Option<String>,
-            #[serde(default)]
-            counterparty: Option<String>,
-            #[serde(default)]
-            closed_pnl: Option<String>,
-            #[serde(default)]
-            twap_id: Option<String>,
-            #[serde(default)]
-            builder_fee: Option<String>,
-        }
-
-        fn parse_side(s: &str) -> Option<Side> {
-            match s.trim().to_ascii_lowercase().as_str() {
-                "a" | "buy" | "bid" => Some(Side::Buy),
-                "b" | "sell" | "ask" => Some(Side::Sell),
-                _ => None,
-            }
-        }
-
-        /// Parse ISO8601 time string to milliseconds since epoch.
-        /// Expects format: "2024-12-15T10:30:45.123Z" or similar.
-        fn parse_time_to_ms(time_str: &str) -> Result<i64, BuilderLogsError> {
-            use chrono::{DateTime, Utc};
-            let dt: DateTime<Utc> = time_str
-                .parse()
-                .map_err(|e| BuilderLogsError::Csv(format!("invalid time '{}': {}", time_str, e)))?;
-            Ok(dt.timestamp_millis())
-        }
-
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(true)
-            .flexible(true)
-            .from_reader(csv_bytes);
-
-        let mut fills = Vec::new();
-        for record in reader.deserialize::<Row>() {
-            let row = record.map_err(|e| BuilderLogsError::Csv(e.to_string()))?;
-            let time_ms = parse_time_to_ms(&row.time)?;
-            let side = parse_side(&row.side)
-                .ok_or_else(|| BuilderLogsError::Csv(format!("invalid side: {}", row.side)))?;
-            let px = Decimal::from_str_canonical(&row.px)
-                .map_err(|e| BuilderLogsError::Csv(format!("invalid px: {}", e)))?;
-            let sz = Decimal::from_str_canonical(&row.sz)
-                .map_err(|e| BuilderLogsError::Csv(format!("invalid sz: {}", e)))?;
-
-            fills.push(BuilderLogFill {
-                time_ms: TimeMs::new(time_ms),
-                user: Address::new(row.user),
-                coin: Coin::new(row.coin),
-                side,
-                px,
-                sz,
-                tid: None, // Not available in actual API
-                oid: None, // Not available in actual API
-            });
-        }
+    pub fn parse_csv(csv_bytes: &[u8]) -> Result<ParsedBuilderLogs, BuilderLogsError> {
+        BuilderLogsCsvAdapter
+            .normalize(csv_bytes)
+            .map(parsed_logs)
+            .map_err(normalize_error)
+    }
 
-        Ok(fills)
+    /// Decompress and parse a daily file in one pass, without holding the decompressed
+    /// CSV in memory.
+    pub fn parse_lz4(lz4_bytes: &[u8]) -> Result<ParsedBuilderLogs, BuilderLogsError> {
+        BuilderLogsCsvAdapter
+            .normalize_reader(lz4_flex::frame::FrameDecoder::new(lz4_bytes))
+            .map(parsed_logs)
+            .map_err(normalize_error)
     }
 
     async fn fetch_and_parse_day_impl(
@@ -163,8 +279,9 @@ impl BuilderLogsFetcher {
         yyyymmdd: &str,
     ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
         let lz4 = self.fetch_lz4_bytes(builder, yyyymmdd).await?;
-        let csv = Self::decompress_lz4_frame(&lz4)?;
-        Self::parse_csv(&csv)
+        let parsed = Self::parse_lz4(&lz4)?;
+        warn_on_rejects(builder, yyyymmdd, &parsed);
+        Ok(parsed.fills)
     }
 }
 
@@ -183,11 +300,181 @@ impl BuilderLogsSource for BuilderLogsFetcher {
     ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
         self.fetch_and_parse_day_impl(builder, yyyymmdd).await
     }
+
+    fn download_stats(&self) -> Option<BuilderLogsDownloadStats> {
+        Some(self.stats())
+    }
+}
+
+/// Builder logs source that keeps downloaded daily files in the `builder_log_files` table
+/// and their parsed rows in `builder_log_fills`.
+///
+/// Completed UTC days never change upstream, so once a day is cached it is served from the
+/// database without decompressing or parsing it again. The current day is still being appended to and is always fetched fresh.
+/// Cached content is checked against its stored SHA-256 and refetched if it does not match.
+#[derive(Clone)]
+pub struct CachedBuilderLogsFetcher {
+    fetcher: BuilderLogsFetcher,
+    repo: Arc<Repository>,
+}
+
+impl CachedBuilderLogsFetcher {
+    pub fn new(fetcher: BuilderLogsFetcher, repo: Arc<Repository>) -> Self {
+        Self { fetcher, repo }
+    }
+}
+
+/// A builder's log rows for a day from the database cache, without fetching.
+///
+/// Rows already in `builder_log_fills` are read directly; otherwise the cached file is
+/// parsed and its rows stored there for next time. Returns `None` when the day is not
+/// cached or the cached content fails its hash check.
+pub async fn load_cached_day(
+    repo: &Repository,
+    builder: &Address,
+    yyyymmdd: &str,
+) -> Result<Option<Vec<BuilderLogFill>>, BuilderLogsError> {
+    match repo.query_cached_builder_log_fills(builder, yyyymmdd).await {
+        Ok(Some(fills)) => return Ok(Some(fills)),
+        Ok(None) => {}
+        // The file is still cached, so reparse it and replace the bad rows.
+        Err(e) => tracing::warn!(
+            builder=%builder,
+            yyyymmdd=%yyyymmdd,
+            error=%e,
+            "Stored builder log rows could not be read, reparsing the cached file"
+        ),
+    }
+
+    let Some(file) = repo
+        .get_builder_log_file(builder, yyyymmdd)
+        .await
+        .map_err(|e| BuilderLogsError::Cache(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+
+    if sha256_hex(&file.content) != file.sha256 {
+        tracing::warn!(
+            builder=%builder,
+            yyyymmdd=%yyyymmdd,
+            "Cached builder log file failed hash check, ignoring it"
+        );
+        return Ok(None);
+    }
+
+    // Rejected rows were recorded when the file was first downloaded.
+    let fills = BuilderLogsFetcher::parse_lz4(&file.content)?.fills;
+    repo.replace_builder_log_fills(builder, yyyymmdd, &fills)
+        .await
+        .map_err(|e| BuilderLogsError::Cache(e.to_string()))?;
+    Ok(Some(fills))
+}
+
+#[async_trait]
+impl BuilderLogsSource for CachedBuilderLogsFetcher {
+    async fn fetch_and_parse_day(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
+        if let Some(fills) = load_cached_day(&self.repo, builder, yyyymmdd).await? {
+            return Ok(fills);
+        }
+
+        let (lz4, etag) = self.fetcher.fetch_lz4_file(builder, yyyymmdd).await?;
+        let parsed = BuilderLogsFetcher::parse_lz4(&lz4)?;
+        warn_on_rejects(builder, yyyymmdd, &parsed);
+
+        let now = TimeMs::now();
+        self.repo
+            .replace_builder_log_rejects(builder, yyyymmdd, &parsed.rejects, now.as_ms())
+            .await
+            .map_err(|e| BuilderLogsError::Cache(e.to_string()))?;
+        let fills = parsed.fills;
+
+        if is_completed_day(yyyymmdd, now) {
+            let file = BuilderLogFileRow {
+                builder: builder.as_str().to_string(),
+                yyyymmdd: yyyymmdd.to_string(),
+                sha256: sha256_hex(&lz4),
+                etag,
+                row_count: fills.len() as i64,
+                fetched_at_ms: now.as_ms(),
+                content: lz4,
+            };
+            self.repo
+                .upsert_builder_log_file(&file)
+                .await
+                .map_err(|e| BuilderLogsError::Cache(e.to_string()))?;
+            self.repo
+                .replace_builder_log_fills(builder, yyyymmdd, &fills)
+                .await
+                .map_err(|e| BuilderLogsError::Cache(e.to_string()))?;
+        }
+
+        Ok(fills)
+    }
+
+    fn download_stats(&self) -> Option<BuilderLogsDownloadStats> {
+        Some(self.fetcher.stats())
+    }
+}
+
+fn parsed_logs(normalized: Normalized<BuilderLogFill>) -> ParsedBuilderLogs {
+    ParsedBuilderLogs {
+        fills: normalized.records,
+        rejects: normalized
+            .rejects
+            .into_iter()
+            .map(|reject| BuilderLogRejectRow {
+                line: reject.position.map_or(0, |p| p.line as i64),
+                byte_offset: reject.position.map_or(0, |p| p.byte_offset as i64),
+                reason: reject.reason,
+                raw: reject.raw,
+            })
+            .collect(),
+    }
+}
+
+fn normalize_error(e: NormalizeError) -> BuilderLogsError {
+    match e {
+        NormalizeError::Csv(msg) | NormalizeError::Malformed(msg) => BuilderLogsError::Csv(msg),
+        NormalizeError::Read(msg) => BuilderLogsError::Lz4(msg),
+    }
+}
+
+fn warn_on_rejects(builder: &Address, yyyymmdd: &str, parsed: &ParsedBuilderLogs) {
+    if let Some(first) = parsed.rejects.first() {
+        tracing::warn!(
+            builder=%builder,
+            yyyymmdd=%yyyymmdd,
+            rejected = parsed.rejects.len(),
+            first_line = first.line,
+            first_reason = %first.reason,
+            "Skipped malformed builder log rows"
+        );
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Whether `yyyymmdd` is a UTC day that ended before `now`.
+fn is_completed_day(yyyymmdd: &str, now: TimeMs) -> bool {
+    use chrono::TimeZone;
+    chrono::Utc
+        .timestamp_millis_opt(now.as_ms())
+        .single()
+        .map(|dt| yyyymmdd < dt.format("%Y%m%d").to_string().as_str())
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::Side;
     use std::io::Write;
 
     fn compress_lz4_frame(input: &[u8]) -> Vec<u8> {
@@ -206,12 +493,36 @@ mod tests {
         assert_eq!(out, csv);
     }
 
+    #[test]
+    fn lz4_file_is_parsed_while_decompressing() {
+        let mut csv = b"time,user,coin,side,px,sz\n".to_vec();
+        for i in 0..1000 {
+            csv.extend_from_slice(
+                format!("2023-11-14T16:53:20.000Z,0xabc,BTC,buy,{},1\n", 100 + i).as_bytes(),
+            );
+        }
+        let lz4 = compress_lz4_frame(&csv);
+
+        let streamed = BuilderLogsFetcher::parse_lz4(&lz4).unwrap();
+        let buffered =
+            BuilderLogsFetcher::parse_csv(&BuilderLogsFetcher::decompress_lz4_frame(&lz4).unwrap())
+                .unwrap();
+        assert_eq!(streamed.fills.len(), 1000);
+        assert_eq!(streamed.fills, buffered.fills);
+
+        let truncated = &lz4[..lz4.len() / 2];
+        assert!(matches!(
+            BuilderLogsFetcher::parse_lz4(truncated),
+            Err(BuilderLogsError::Lz4(_))
+        ));
+    }
+
     #[test]
     fn csv_parsing_valid_row() {
         // Schema: time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee
         let csv = b"time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee\n\
             2023-11-14T16:53:20.000Z,0xabc,BTC,A,100,1,false,,Gtc,false,0xdef,0,,0.01\n";
-        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap();
+        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap().fills;
         assert_eq!(fills.len(), 1);
         // 2023-11-14T16:53:20.000Z = 1699980800000 ms
         assert_eq!(fills[0].time_ms, TimeMs::new(1699980800000));
@@ -226,11 +537,13 @@ mod tests {
     }
 
     #[test]
-    fn csv_parsing_invalid_side_errors() {
+    fn csv_parsing_invalid_side_is_rejected() {
         let csv = b"time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee\n\
             2023-11-14T16:53:20.000Z,0xabc,BTC,wat,100,1,false,,Gtc,false,0xdef,0,,0.01\n";
-        let err = BuilderLogsFetcher::parse_csv(csv).unwrap_err();
-        assert!(matches!(err, BuilderLogsError::Csv(_)));
+        let parsed = BuilderLogsFetcher::parse_csv(csv).unwrap();
+        assert!(parsed.fills.is_empty());
+        assert_eq!(parsed.rejects.len(), 1);
+        assert_eq!(parsed.rejects[0].reason, "invalid side: wat");
     }
 
     #[test]
@@ -239,7 +552,7 @@ mod tests {
         let csv = b"time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee\n\
             2023-11-14T16:53:20.000Z,0xabc,BTC,A,100,1,false,,Gtc,false,0xdef,0,,0.01\n\
             2023-11-14T16:53:21.000Z,0xdef,ETH,B,200,2,false,,Gtc,false,0xabc,0,,0.02\n";
-        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap();
+        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap().fills;
         assert_eq!(fills.len(), 2);
         assert_eq!(fills[0].side, Side::Buy, "uppercase 'A' should be Buy");
         assert_eq!(fills[1].side, Side::Sell, "uppercase 'B' should be Sell");
@@ -250,17 +563,171 @@ mod tests {
         // Test parsing with only the required columns (extra columns marked as optional)
         let csv = b"time,user,coin,side,px,sz\n\
             2023-11-14T16:53:20.000Z,0xabc,BTC,buy,100.5,1.25\n";
-        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap();
+        let fills = BuilderLogsFetcher::parse_csv(csv).unwrap().fills;
         assert_eq!(fills.len(), 1);
         assert_eq!(fills[0].px.to_canonical_string(), "100.5");
         assert_eq!(fills[0].sz.to_canonical_string(), "1.25");
     }
 
     #[test]
-    fn csv_parsing_invalid_time_format_errors() {
+    fn csv_parsing_invalid_time_format_is_rejected() {
         let csv = b"time,user,coin,side,px,sz\n\
             not-a-valid-time,0xabc,BTC,buy,100,1\n";
-        let err = BuilderLogsFetcher::parse_csv(csv).unwrap_err();
-        assert!(matches!(err, BuilderLogsError::Csv(_)));
+        let parsed = BuilderLogsFetcher::parse_csv(csv).unwrap();
+        assert!(parsed.fills.is_empty());
+        assert!(parsed.rejects[0]
+            .reason
+            .starts_with("invalid time 'not-a-valid-time'"));
+    }
+
+    #[test]
+    fn csv_parsing_skips_bad_rows_and_keeps_going() {
+        let csv = b"time,user,coin,side,px,sz\n\
+            2023-11-14T16:53:20.000Z,0xabc,BTC,buy,100,1\n\
+            2023-11-14T16:53:21.000Z,0xabc,BTC,buy,1e,1\n\
+            2023-11-14T16:53:22.000Z,0xabc\n\
+            2023-11-14T16:53:23.000Z,0xabc,ETH,sell,200,2\n";
+        let parsed = BuilderLogsFetcher::parse_csv(csv).unwrap();
+
+        assert_eq!(parsed.fills.len(), 2);
+        assert_eq!(parsed.fills[1].coin.as_str(), "ETH");
+
+        assert_eq!(parsed.rejects.len(), 2);
+        let header_len = "time,user,coin,side,px,sz\n".len() as i64;
+        let row_len = "2023-11-14T16:53:20.000Z,0xabc,BTC,buy,100,1\n".len() as i64;
+        assert_eq!(parsed.rejects[0].line, 3);
+        assert_eq!(parsed.rejects[0].byte_offset, header_len + row_len);
+        assert!(parsed.rejects[0].reason.starts_with("invalid px"));
+        assert_eq!(
+            parsed.rejects[0].raw,
+            "2023-11-14T16:53:21.000Z,0xabc,BTC,buy,1e,1"
+        );
+        assert_eq!(parsed.rejects[1].line, 4);
+        assert_eq!(parsed.rejects[1].raw, "2023-11-14T16:53:22.000Z,0xabc");
+    }
+
+    #[test]
+    fn builder_logs_url_uses_network_prefix() {
+        let builder = Address::new("0xabc".to_string());
+        assert_eq!(
+            BuilderLogsFetcher::builder_logs_url(Network::Testnet, &builder, "20231114"),
+            "https://stats-data.hyperliquid.xyz/Testnet/builder_fills/0xabc/20231114.csv.lz4"
+        );
+    }
+
+    #[test]
+    fn completed_day_excludes_today() {
+        // 2023-11-14T16:53:20Z
+        let now = TimeMs::new(1699980800000);
+        assert!(is_completed_day("20231113", now));
+        assert!(!is_completed_day("20231114", now));
+        assert!(!is_completed_day("20231115", now));
+    }
+
+    #[tokio::test]
+    async fn probe_url_reports_status_and_connection_errors() {
+        let app = axum::Router::new().route(
+            "/builder_fills/0xabc/20231114.csv.lz4",
+            axum::routing::get(|| async { "lz4" }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let fetcher = BuilderLogsFetcher::new(reqwest::Client::builder().no_proxy().build().unwrap());
+        let base = format!("http://{}/builder_fills/0xabc", addr);
+        assert_eq!(
+            fetcher.probe_url(&format!("{}/20231114.csv.lz4", base)).await.unwrap(),
+            200
+        );
+        assert_eq!(
+            fetcher.probe_url(&format!("{}/20231115.csv.lz4", base)).await.unwrap(),
+            404
+        );
+
+        let err = fetcher.probe_url("http://127.0.0.1:1/x").await.unwrap_err();
+        assert!(matches!(err, BuilderLogsError::Http(_)));
+    }
+
+    async fn setup_cache() -> (CachedBuilderLogsFetcher, Arc<Repository>, tempfile::TempDir) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+        let pool = crate::db::init_db(&db_path).await.unwrap();
+        let repo = Arc::new(Repository::new(pool));
+        // Unroutable client: any cache miss fails instead of touching the network.
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::all("http://127.0.0.1:1").unwrap())
+            .build()
+            .unwrap();
+        let cache = CachedBuilderLogsFetcher::new(BuilderLogsFetcher::new(client), repo.clone());
+        (cache, repo, temp_dir)
+    }
+
+    fn cached_file(builder: &str, content: Vec<u8>, sha256: String) -> BuilderLogFileRow {
+        BuilderLogFileRow {
+            builder: builder.to_string(),
+            yyyymmdd: "20231114".to_string(),
+            sha256,
+            etag: None,
+            row_count: 1,
+            fetched_at_ms: 0,
+            content,
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_day_is_served_without_fetching() {
+        let (cache, repo, _temp) = setup_cache().await;
+        let builder = Address::new("0xbuilder".to_string());
+        let csv = b"time,user,coin,side,px,sz\n\
+            2023-11-14T16:53:20.000Z,0xabc,BTC,buy,100,1\n";
+        let lz4 = compress_lz4_frame(csv);
+        repo.upsert_builder_log_file(&cached_file("0xbuilder", lz4.clone(), sha256_hex(&lz4)))
+            .await
+            .unwrap();
+
+        let fills = cache.fetch_and_parse_day(&builder, "20231114").await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].coin.as_str(), "BTC");
+    }
+
+    #[tokio::test]
+    async fn parsed_cached_day_is_served_from_stored_rows() {
+        let (cache, repo, _temp) = setup_cache().await;
+        let builder = Address::new("0xbuilder".to_string());
+        let csv = b"time,user,coin,side,px,sz\n\
+            2023-11-14T16:53:20.000Z,0xabc,BTC,buy,100,1\n";
+        let lz4 = compress_lz4_frame(csv);
+        repo.upsert_builder_log_file(&cached_file("0xbuilder", lz4.clone(), sha256_hex(&lz4)))
+            .await
+            .unwrap();
+        let parsed = cache.fetch_and_parse_day(&builder, "20231114").await.unwrap();
+        assert_eq!(repo.query_builder_log_fills(&builder, "20231114").await.unwrap(), parsed);
+
+        // Content that no longer decompresses is never read once the rows are stored.
+        let garbage = b"not lz4".to_vec();
+        repo.upsert_builder_log_file(&cached_file("0xbuilder", garbage.clone(), sha256_hex(&garbage)))
+            .await
+            .unwrap();
+        let fills = cache.fetch_and_parse_day(&builder, "20231114").await.unwrap();
+        assert_eq!(fills, parsed);
+    }
+
+    #[tokio::test]
+    async fn corrupted_cache_entry_is_refetched() {
+        let (cache, repo, _temp) = setup_cache().await;
+        let builder = Address::new("0xbuilder".to_string());
+        let lz4 = compress_lz4_frame(b"time,user,coin,side,px,sz\n");
+        repo.upsert_builder_log_file(&cached_file("0xbuilder", lz4, "not-the-hash".to_string()))
+            .await
+            .unwrap();
+
+        // The hash mismatch forces a download, which the unroutable client cannot do.
+        let err = cache.fetch_and_parse_day(&builder, "20231114").await.unwrap_err();
+        assert!(matches!(err, BuilderLogsError::Http(_)));
     }
 }
@@ -1,19 +1,100 @@
 //! Hyperliquid API client implementation.
 
-use super::{DataSource, DataSourceError};
-use crate::domain::{Address, Coin, Decimal, Deposit, Fill, Side, TimeMs};
+use super::normalize::{FillAdapter, RestFillsAdapter};
+use super::{DataSource, DataSourceError, FillsPage, PageToken};
+use crate::db::Repository;
+use crate::domain::coin_meta::{BUILDER_DEX_ASSET_OFFSET, BUILDER_DEX_ASSET_STRIDE};
+use crate::domain::{
+    Address, Coin, CoinMeta, Decimal, Deposit, Fill, LedgerKind, MarkPrice, TimeMs,
+    MARK_PRICE_BUCKET_MS,
+};
 use async_trait::async_trait;
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
 use reqwest::Client;
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, warn};
 
+/// Most fills `userFillsByTime` returns for one request.
+const MAX_FILLS_PER_REQUEST: usize = 2000;
+
+/// Most candles `candleSnapshot` returns for one request.
+const MAX_CANDLES_PER_REQUEST: i64 = 5000;
+
+/// `candleSnapshot` interval whose candles are one mark bucket wide.
+const MARK_PRICE_CANDLE_INTERVAL: &str = "1h";
+
+/// Where the next `userFillsByTime` page starts.
+///
+/// The API returns fills oldest first and pages by start time only, so a page that ends
+/// partway through a millisecond is resumed at that millisecond, skipping the `seen` fills already returned for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FillsCursor {
+    start_ms: i64,
+    seen: usize,
+}
+
+impl FillsCursor {
+    fn encode(self) -> PageToken {
+        PageToken(format!("{}:{}", self.start_ms, self.seen))
+    }
+
+    fn decode(token: &PageToken) -> Result<Self, DataSourceError> {
+        let invalid = || DataSourceError::Other(format!("Invalid fills page token '{}'", token.0));
+        let (start_ms, seen) = token.0.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            start_ms: start_ms.parse().map_err(|_| invalid())?,
+            seen: seen.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    /// The cursor after a page of `returned` rows whose parsed fills are `fills`, or `None`
+    /// if the page was not full and so ends the window.
+    fn next(self, fills: &[Fill], returned: usize, to_ms: i64) -> Option<Self> {
+        if returned < MAX_FILLS_PER_REQUEST {
+            return None;
+        }
+        let last_ms = fills.iter().map(|f| f.time_ms.as_ms()).max()?;
+        let seen = fills.iter().filter(|f| f.time_ms.as_ms() == last_ms).count();
+        let next = if last_ms == self.start_ms && seen <= self.seen {
+            // A whole page within one millisecond and nothing new: the API cannot page
+            // further into it, so move on rather than request the same page forever.
+            warn!(
+                "More than {} fills at {}; fills beyond them are not retrievable",
+                MAX_FILLS_PER_REQUEST, last_ms
+            );
+            Self {
+                start_ms: last_ms + 1,
+                seen: 0,
+            }
+        } else {
+            Self {
+                start_ms: last_ms,
+                seen,
+            }
+        };
+        (next.start_ms <= to_ms).then_some(next)
+    }
+}
+
 /// Hyperliquid data source using the public Info API.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct HyperliquidDataSource {
     client: Client,
     base_url: String,
+    /// Where response bodies are recorded, when enabled.
+    payloads: Option<Arc<Repository>>,
+}
+
+impl fmt::Debug for HyperliquidDataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HyperliquidDataSource")
+            .field("base_url", &self.base_url)
+            .field("record_payloads", &self.payloads.is_some())
+            .finish()
+    }
 }
 
 impl HyperliquidDataSource {
@@ -22,9 +103,17 @@ impl HyperliquidDataSource {
         Self {
             client: Client::new(),
             base_url,
+            payloads: None,
         }
     }
 
+    /// Record every Info API response body in `raw_payloads`, so fills can be re-derived
+    /// from what Hyperliquid actually returned.
+    pub fn with_payload_recording(mut self, repo: Arc<Repository>) -> Self {
+        self.payloads = Some(repo);
+        self
+    }
+
     /// Create with default Hyperliquid API URL.
     pub fn default_url() -> Self {
         Self::new("https://api.hyperliquid.xyz".to_string())
@@ -40,7 +129,7 @@ impl HyperliquidDataSource {
             ..Default::default()
         };
 
-        retry(backoff, || async {
+        let body = retry(backoff, || async {
             let response = self
                 .client
                 .post(&url)
@@ -69,11 +158,35 @@ impl HyperliquidDataSource {
             }
 
             response
-                .json::<serde_json::Value>()
+                .bytes()
                 .await
-                .map_err(|e| backoff::Error::permanent(DataSourceError::ParseError(e.to_string())))
+                .map_err(|e| backoff::Error::transient(DataSourceError::NetworkError(e.to_string())))
         })
-        .await
+        .await?;
+
+        self.record_payload(&payload, &body).await;
+        serde_json::from_slice(&body).map_err(|e| DataSourceError::ParseError(e.to_string()))
+    }
+
+    /// Store a response body; a failure is logged rather than failing the request.
+    async fn record_payload(&self, payload: &serde_json::Value, body: &[u8]) {
+        let Some(repo) = self.payloads.as_ref() else {
+            return;
+        };
+        let request_type = payload["type"].as_str().unwrap_or_default();
+        let user = payload["user"].as_str();
+        if let Err(e) = repo
+            .insert_raw_payload(
+                request_type,
+                user,
+                &payload.to_string(),
+                body,
+                TimeMs::now().as_ms(),
+            )
+            .await
+        {
+            warn!("Failed to record {} payload: {}", request_type, e);
+        }
     }
 }
 
@@ -86,38 +199,65 @@ impl DataSource for HyperliquidDataSource {
         from_ms: i64,
         to_ms: i64,
     ) -> Result<Vec<Fill>, DataSourceError> {
+        let mut fills = Vec::new();
+        let mut page = None;
+        loop {
+            let FillsPage { fills: batch, next } = self
+                .fetch_fills_page(user, coin, from_ms, to_ms, page.as_ref())
+                .await?;
+            fills.extend(batch);
+            match next {
+                Some(token) => page = Some(token),
+                None => return Ok(fills),
+            }
+        }
+    }
+
+    async fn fetch_fills_page(
+        &self,
+        user: &str,
+        coin: &str,
+        from_ms: i64,
+        to_ms: i64,
+        page: Option<&PageToken>,
+    ) -> Result<FillsPage, DataSourceError> {
+        let cursor = match page {
+            Some(token) => FillsCursor::decode(token)?,
+            None => FillsCursor {
+                start_ms: from_ms,
+                seen: 0,
+            },
+        };
         debug!(
-            "Fetching fills for user={}, coin={}, from_ms={}, to_ms={}",
-            user, coin, from_ms, to_ms
+            "Fetching fills for user={}, coin={}, from_ms={}, to_ms={}, seen={}",
+            user, coin, cursor.start_ms, to_ms, cursor.seen
         );
 
         let payload = serde_json::json!({
             "type": "userFillsByTime",
             "user": user,
             "coin": coin,
-            "startTime": from_ms,
+            "startTime": cursor.start_ms,
             "endTime": to_ms,
             "aggregateByTime": false
         });
 
         let response = self.post_info(payload).await?;
+        let (fills, returned) = parse_fills_response(&response, user, coin)?;
 
-        // Parse response into fills
-        let fills_json = response
-            .as_array()
-            .ok_or_else(|| DataSourceError::ParseError("Expected array response".to_string()))?;
-
-        let mut fills = Vec::new();
-        for fill_json in fills_json {
-            match parse_fill(fill_json, user, coin) {
-                Ok(fill) => fills.push(fill),
-                Err(e) => {
-                    warn!("Failed to parse fill: {}", e);
-                }
-            }
-        }
+        let next = cursor.next(&fills, returned, to_ms);
+        // Fills at the start time that the previous page already returned.
+        let fills = fills
+            .into_iter()
+            .enumerate()
+            .filter(|(i, fill)| *i >= cursor.seen || fill.time_ms.as_ms() != cursor.start_ms)
+            .map(|(_, fill)| fill)
+            .collect();
 
-        Ok(fills)
+        Ok(FillsPage {
+            fills,
+            next: next.map(|cursor| cursor.encode()),
+        })
     }
 
     async fn fetch_deposits(
@@ -147,7 +287,8 @@ impl DataSource for HyperliquidDataSource {
         let mut deposits = Vec::new();
         for deposit_json in deposits_json {
             match parse_deposit(deposit_json, user) {
-                Ok(deposit) => deposits.push(deposit),
+                Ok(Some(deposit)) => deposits.push(deposit),
+                Ok(None) => {}
                 Err(e) => {
                     warn!("Failed to parse deposit: {}", e);
                 }
@@ -165,97 +306,271 @@ impl DataSource for HyperliquidDataSource {
         debug!("Fetching equity for user={}, at_ms={}", user, at_ms);
 
         let payload = serde_json::json!({
-            "type": "userState",
+            "type": "clearinghouseState",
             "user": user
         });
 
         let response = self.post_info(payload).await?;
+        parse_account_value(&response)
+    }
 
-        // Try to extract equity from response
-        if let Some(equity_str) = response.get("equity").and_then(|v| v.as_str()) {
-            Decimal::from_str_canonical(equity_str)
-                .map(Some)
-                .map_err(|e| DataSourceError::ParseError(format!("Invalid equity: {}", e)))
-        } else {
-            Ok(None)
+    async fn fetch_sub_accounts(&self, user: &str) -> Result<Vec<Address>, DataSourceError> {
+        debug!("Fetching sub-accounts for user={}", user);
+
+        let payload = serde_json::json!({
+            "type": "subAccounts",
+            "user": user
+        });
+
+        let response = self.post_info(payload).await?;
+        parse_sub_accounts(&response)
+    }
+
+    /// Lists the default dex, then every builder-deployed perp dex from `perpDexs`.
+    async fn fetch_coin_meta(&self) -> Result<Vec<CoinMeta>, DataSourceError> {
+        debug!("Fetching perp asset metadata");
+
+        let payload = serde_json::json!({ "type": "meta" });
+        let response = self.post_info(payload).await?;
+        let mut coins = parse_meta(&response, None, 0)?;
+
+        let payload = serde_json::json!({ "type": "perpDexs" });
+        let dexes = match self.post_info(payload).await.and_then(|r| parse_perp_dexs(&r)) {
+            Ok(dexes) => dexes,
+            Err(e) => {
+                warn!("Failed to list perp dexes, keeping the default dex only: {}", e);
+                return Ok(coins);
+            }
+        };
+        for (position, dex) in dexes {
+            let payload = serde_json::json!({ "type": "meta", "dex": dex });
+            let response = self.post_info(payload).await?;
+            let first_asset = BUILDER_DEX_ASSET_OFFSET + position * BUILDER_DEX_ASSET_STRIDE;
+            coins.extend(parse_meta(&response, Some(&dex), first_asset)?);
+        }
+        Ok(coins)
+    }
+
+    /// Takes the open of each hourly candle from `candleSnapshot`, a page of at most
+    /// [`MAX_CANDLES_PER_REQUEST`] candles at a time.
+    async fn fetch_mark_prices(
+        &self,
+        coin: &str,
+        from_ms: i64,
+        to_ms: i64,
+    ) -> Result<Vec<MarkPrice>, DataSourceError> {
+        debug!("Fetching mark prices for {} from {} to {}", coin, from_ms, to_ms);
+
+        let mut marks = Vec::new();
+        let mut start = from_ms;
+        while start <= to_ms {
+            let end = start
+                .saturating_add(MAX_CANDLES_PER_REQUEST * MARK_PRICE_BUCKET_MS - 1)
+                .min(to_ms);
+            let payload = serde_json::json!({
+                "type": "candleSnapshot",
+                "req": {
+                    "coin": coin,
+                    "interval": MARK_PRICE_CANDLE_INTERVAL,
+                    "startTime": start,
+                    "endTime": end,
+                }
+            });
+            let response = self.post_info(payload).await?;
+            marks.extend(
+                parse_candles(&response, coin)?
+                    .into_iter()
+                    .filter(|m| m.time_ms.as_ms() >= start && m.time_ms.as_ms() <= end),
+            );
+            start = end.saturating_add(1);
+            if end == i64::MAX {
+                break;
+            }
         }
+        Ok(marks)
     }
 }
 
-fn parse_fill(
-    fill_json: &serde_json::Value,
-    user: &str,
+/// Parse a `candleSnapshot` response into the open price of each candle:
+///
+/// ```json
+/// [{"t": 1700000000000, "T": 1700003599999, "s": "BTC", "i": "1h", "o": "37000.0", ...}]
+/// ```
+fn parse_candles(
+    response: &serde_json::Value,
     coin: &str,
-) -> Result<Fill, DataSourceError> {
-    let time_ms = fill_json
-        .get("time")
-        .and_then(|v| v.as_i64())
-        .ok_or_else(|| DataSourceError::ParseError("Missing time field".to_string()))?;
+) -> Result<Vec<MarkPrice>, DataSourceError> {
+    let candles = response
+        .as_array()
+        .ok_or_else(|| DataSourceError::ParseError("Expected array response".to_string()))?;
+    candles
+        .iter()
+        .map(|candle| {
+            let time_ms = candle.get("t").and_then(|v| v.as_i64()).ok_or_else(|| {
+                DataSourceError::ParseError(format!("Missing t in candle: {}", candle))
+            })?;
+            let px = candle
+                .get("o")
+                .and_then(|v| v.as_str())
+                .and_then(|o| Decimal::from_str_canonical(o).ok())
+                .ok_or_else(|| {
+                    DataSourceError::ParseError(format!("Invalid o in candle: {}", candle))
+                })?;
+            Ok(MarkPrice {
+                coin: Coin::new(coin.to_string()),
+                time_ms: TimeMs::new(time_ms),
+                px,
+            })
+        })
+        .collect()
+}
 
-    let side_str = fill_json
-        .get("side")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| DataSourceError::ParseError("Missing side field".to_string()))?;
-
-    let side = match side_str {
-        "A" => Side::Buy,
-        "B" => Side::Sell,
-        _ => {
-            return Err(DataSourceError::ParseError(format!(
-                "Invalid side: {}",
-                side_str
-            )))
-        }
-    };
+/// Parse a `perpDexs` response into each builder-deployed dex's name and position. The
+/// default dex comes first, as `null`.
+fn parse_perp_dexs(response: &serde_json::Value) -> Result<Vec<(u32, String)>, DataSourceError> {
+    let dexes = response
+        .as_array()
+        .ok_or_else(|| DataSourceError::ParseError("Expected array response".to_string()))?;
+    dexes
+        .iter()
+        .enumerate()
+        .filter(|(_, dex)| !dex.is_null())
+        .map(|(position, dex)| {
+            let name = dex.get("name").and_then(|v| v.as_str()).ok_or_else(|| {
+                DataSourceError::ParseError(format!("Missing name in perp dex: {}", dex))
+            })?;
+            Ok((position as u32, name.to_string()))
+        })
+        .collect()
+}
 
-    let px_str = fill_json
-        .get("px")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| DataSourceError::ParseError("Missing px field".to_string()))?;
-    let px = Decimal::from_str_canonical(px_str)
-        .map_err(|e| DataSourceError::ParseError(format!("Invalid px: {}", e)))?;
+/// Parse a `meta` response, whose `universe` lists a dex's perp assets in asset id order:
+///
+/// ```json
+/// {"universe": [{"name": "BTC", "szDecimals": 5, "maxLeverage": 40}, ...]}
+/// ```
+///
+/// Assets of a builder-deployed `dex` are qualified with it, as in `xyz:TSLA`, and their
+/// asset ids start at `first_asset`.
+fn parse_meta(
+    response: &serde_json::Value,
+    dex: Option<&str>,
+    first_asset: u32,
+) -> Result<Vec<CoinMeta>, DataSourceError> {
+    let universe = response
+        .get("universe")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| DataSourceError::ParseError("Missing universe in meta".to_string()))?;
 
-    let sz_str = fill_json
-        .get("sz")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| DataSourceError::ParseError("Missing sz field".to_string()))?;
-    let sz = Decimal::from_str_canonical(sz_str)
-        .map_err(|e| DataSourceError::ParseError(format!("Invalid sz: {}", e)))?;
+    universe
+        .iter()
+        .enumerate()
+        .map(|(index, asset)| {
+            let name = asset.get("name").and_then(|v| v.as_str()).ok_or_else(|| {
+                DataSourceError::ParseError(format!("Missing name in meta asset: {}", asset))
+            })?;
+            let number = |field: &str| {
+                asset
+                    .get(field)
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u32::try_from(v).ok())
+                    .ok_or_else(|| {
+                        DataSourceError::ParseError(format!("Invalid {} for {}", field, name))
+                    })
+            };
+            let coin = match dex {
+                Some(dex) if !name.contains(Coin::DEX_SEPARATOR) => {
+                    format!("{}{}{}", dex, Coin::DEX_SEPARATOR, name)
+                }
+                _ => name.to_string(),
+            };
+            Ok(CoinMeta {
+                coin: Coin::new(coin),
+                asset_index: first_asset + index as u32,
+                sz_decimals: number("szDecimals")?,
+                max_leverage: number("maxLeverage")?,
+                delisted: asset
+                    .get("isDelisted")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            })
+        })
+        .collect()
+}
 
-    let fee_str = fill_json
-        .get("fee")
+/// Account value from a `clearinghouseState` response, or `None` if it has none.
+fn parse_account_value(response: &serde_json::Value) -> Result<Option<Decimal>, DataSourceError> {
+    response
+        .get("marginSummary")
+        .and_then(|summary| summary.get("accountValue"))
         .and_then(|v| v.as_str())
-        .ok_or_else(|| DataSourceError::ParseError("Missing fee field".to_string()))?;
-    let fee = Decimal::from_str_canonical(fee_str)
-        .map_err(|e| DataSourceError::ParseError(format!("Invalid fee: {}", e)))?;
+        .map(|value| {
+            Decimal::from_str_canonical(value)
+                .map_err(|e| DataSourceError::ParseError(format!("Invalid accountValue: {}", e)))
+        })
+        .transpose()
+}
 
-    let closed_pnl_str = fill_json
-        .get("closedPnl")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| DataSourceError::ParseError("Missing closedPnl field".to_string()))?;
-    let closed_pnl = Decimal::from_str_canonical(closed_pnl_str)
-        .map_err(|e| DataSourceError::ParseError(format!("Invalid closedPnl: {}", e)))?;
-
-    let tid = fill_json.get("tid").and_then(|v| v.as_i64());
-    let oid = fill_json.get("oid").and_then(|v| v.as_i64());
-    let builder_fee = fill_json
-        .get("builderFee")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Decimal::from_str_canonical(s).ok());
-
-    Ok(Fill::new(
-        TimeMs::new(time_ms),
-        Address::new(user.to_string()),
-        Coin::new(coin.to_string()),
-        side,
-        px,
-        sz,
-        fee,
-        closed_pnl,
-        builder_fee,
-        tid,
-        oid,
-    ))
+/// Parse a `userFillsByTime` response, returning the fills and how many rows it held.
+///
+/// Rows that fail to parse are logged and skipped.
+fn parse_fills_response(
+    response: &serde_json::Value,
+    user: &str,
+    coin: &str,
+) -> Result<(Vec<Fill>, usize), DataSourceError> {
+    let normalized = RestFillsAdapter::new(user, coin)
+        .normalize(response)
+        .map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    for reject in &normalized.rejects {
+        warn!("Failed to parse fill: {}", reject.reason);
+    }
+    let returned = normalized.total();
+    Ok((normalized.records, returned))
+}
+
+/// Re-derive fills from a recorded `userFillsByTime` response (see
+/// [`HyperliquidDataSource::with_payload_recording`]), without contacting the API.
+///
+/// `request` is the recorded request body and `content` the response body.
+pub fn replay_fills(request: &str, content: &[u8]) -> Result<Vec<Fill>, DataSourceError> {
+    let request: serde_json::Value =
+        serde_json::from_str(request).map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    if request["type"] != "userFillsByTime" {
+        return Err(DataSourceError::Other(format!(
+            "Cannot replay fills from a {} payload",
+            request["type"]
+        )));
+    }
+    let user = request["user"].as_str().unwrap_or_default();
+    let coin = request["coin"].as_str().unwrap_or_default();
+    let response: serde_json::Value =
+        serde_json::from_slice(content).map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    Ok(parse_fills_response(&response, user, coin)?.0)
+}
+
+/// Parse a `subAccounts` response: `null` when the user has none, otherwise an array of
+/// objects carrying the sub-account address in `subAccountUser`.
+fn parse_sub_accounts(response: &serde_json::Value) -> Result<Vec<Address>, DataSourceError> {
+    if response.is_null() {
+        return Ok(Vec::new());
+    }
+    let entries = response
+        .as_array()
+        .ok_or_else(|| DataSourceError::ParseError("Expected array response".to_string()))?;
+
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .get("subAccountUser")
+                .and_then(|v| v.as_str())
+                .map(|s| Address::new(s.to_string()))
+                .ok_or_else(|| {
+                    DataSourceError::ParseError("Missing subAccountUser field".to_string())
+                })
+        })
+        .collect()
 }
 
 /// Parse a non-funding ledger update from Hyperliquid API.
@@ -266,107 +581,241 @@ fn parse_fill(
 ///   "time": 1234567890,
 ///   "hash": "0x...",
 ///   "delta": {
-///     "type": "deposit" | "withdraw" | "transfer" | ...,
+///     "type": "deposit" | "withdraw" | "internalTransfer" | ...,
 ///     "usdc": "1000"
 ///   }
 /// }
 /// ```
 ///
-/// We filter for "deposit" and "withdraw" types only.
-fn parse_deposit(deposit_json: &serde_json::Value, user: &str) -> Result<Deposit, DataSourceError> {
+/// Deposits, withdrawals, internal and sub-account transfers and spot transfers become
+/// signed [`Deposit`]s: positive when funds arrive, negative (including any fee) when they
+/// leave. Other types, such as vault and spot/perp class transfers, return `Ok(None)`.
+fn parse_deposit(
+    deposit_json: &serde_json::Value,
+    user: &str,
+) -> Result<Option<Deposit>, DataSourceError> {
     let time_ms = deposit_json
         .get("time")
         .and_then(|v| v.as_i64())
         .ok_or_else(|| DataSourceError::ParseError("Missing time field".to_string()))?;
 
-    // Get the delta object
-    let delta = deposit_json.get("delta");
-
-    // Handle nested delta structure from Hyperliquid API
-    let (amount_str, event_type) = if let Some(delta_obj) = delta {
-        if delta_obj.is_object() {
-            // Nested format: { "type": "deposit", "usdc": "1000" }
-            let event_type = delta_obj.get("type").and_then(|v| v.as_str());
-            let usdc_amount = delta_obj.get("usdc").and_then(|v| v.as_str());
-            (usdc_amount, event_type)
-        } else if delta_obj.is_string() {
-            // Flat format: "delta": "1000"
-            (delta_obj.as_str(), None)
-        } else {
-            (None, None)
-        }
-    } else {
-        // Try legacy "amount" field
-        (deposit_json.get("amount").and_then(|v| v.as_str()), None)
-    };
-
-    // Filter: only accept deposits and withdrawals
-    if let Some(evt_type) = event_type {
-        match evt_type {
-            "deposit" | "withdraw" => {}
-            other => {
-                debug!("Skipping non-funding ledger event type: {}", other);
-                return Err(DataSourceError::ParseError(format!(
-                    "Skipping event type: {}",
-                    other
-                )));
-            }
-        }
-    }
-
-    let amount_str = amount_str.ok_or_else(|| {
-        DataSourceError::ParseError(format!(
-            "Missing delta/amount field in: {}",
-            deposit_json
-        ))
-    })?;
-
-    let amount = Decimal::from_str_canonical(amount_str)
-        .map_err(|e| DataSourceError::ParseError(format!("Invalid amount: {}", e)))?;
-
     let tx_hash = deposit_json
         .get("hash")
         .and_then(|v| v.as_str())
         .or_else(|| deposit_json.get("txHash").and_then(|v| v.as_str()))
         .map(|s| s.to_string());
 
+    let (amount, kind) = match deposit_json.get("delta") {
+        Some(delta) if delta.is_object() => match ledger_delta(delta, user)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        },
+        // Flat format: "delta": "1000", or the legacy "amount" field
+        delta => {
+            let amount_str = delta
+                .and_then(|v| v.as_str())
+                .or_else(|| deposit_json.get("amount").and_then(|v| v.as_str()))
+                .ok_or_else(|| {
+                    DataSourceError::ParseError(format!(
+                        "Missing delta/amount field in: {}",
+                        deposit_json
+                    ))
+                })?;
+            let amount = ledger_decimal(amount_str)?;
+            (amount, LedgerKind::from_sign(&amount))
+        }
+    };
+
     debug!(
-        "Parsed deposit: time={}, amount={}, hash={:?}, type={:?}",
-        time_ms, amount, tx_hash, event_type
+        "Parsed ledger update: time={}, amount={}, hash={:?}, kind={}",
+        time_ms, amount, tx_hash, kind
     );
 
-    Ok(Deposit::new(
-        Address::new(user.to_string()),
-        TimeMs::new(time_ms),
-        amount,
-        tx_hash,
+    Ok(Some(
+        Deposit::new(
+            Address::new(user.to_string()),
+            TimeMs::new(time_ms),
+            amount,
+            tx_hash,
+        )
+        .with_kind(kind),
     ))
 }
 
+/// Signed USDC amount and kind of a nested `delta`, or `None` for types that are not
+/// deposits, withdrawals or transfers.
+fn ledger_delta(
+    delta: &serde_json::Value,
+    user: &str,
+) -> Result<Option<(Decimal, LedgerKind)>, DataSourceError> {
+    let field = |name: &str| -> Result<Option<Decimal>, DataSourceError> {
+        delta
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(ledger_decimal)
+            .transpose()
+    };
+    let required = |name: &str| -> Result<Decimal, DataSourceError> {
+        field(name)?.ok_or_else(|| {
+            DataSourceError::ParseError(format!("Missing {} in ledger update: {}", name, delta))
+        })
+    };
+    let fee = field("fee")?.unwrap_or_else(Decimal::zero).abs();
+
+    let event_type = delta.get("type").and_then(|v| v.as_str()).unwrap_or_default();
+    let (value, kind) = match event_type {
+        "deposit" => return Ok(Some((required("usdc")?, LedgerKind::Deposit))),
+        "withdraw" => {
+            let usdc = required("usdc")?.abs();
+            return Ok(Some((-(usdc + fee), LedgerKind::Withdraw)));
+        }
+        "internalTransfer" => (required("usdc")?, LedgerKind::InternalTransfer),
+        "subAccountTransfer" => (required("usdc")?, LedgerKind::SubAccountTransfer),
+        "spotTransfer" => {
+            let value = match field("usdcValue")? {
+                Some(value) => value,
+                None if delta.get("token").and_then(|v| v.as_str()) == Some("USDC") => {
+                    required("amount")?
+                }
+                None => required("usdcValue")?,
+            };
+            (value, LedgerKind::SpotTransfer)
+        }
+        other => {
+            debug!("Skipping non-funding ledger event type: {}", other);
+            return Ok(None);
+        }
+    };
+
+    // Transfers name both ends; the sender's side is an outflow.
+    let is = |name: &str| {
+        delta
+            .get(name)
+            .and_then(|v| v.as_str())
+            .is_some_and(|address| address.eq_ignore_ascii_case(user))
+    };
+    let value = value.abs();
+    if is("user") {
+        Ok(Some((-(value + fee), kind)))
+    } else if is("destination") {
+        Ok(Some((value, kind)))
+    } else {
+        Err(DataSourceError::ParseError(format!(
+            "{} does not involve {}: {}",
+            event_type, user, delta
+        )))
+    }
+}
+
+fn ledger_decimal(s: &str) -> Result<Decimal, DataSourceError> {
+    Decimal::from_str_canonical(s)
+        .map_err(|e| DataSourceError::ParseError(format!("Invalid amount: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::datasource::normalize::rest::parse_fill;
 
     #[test]
-    fn test_parse_fill_valid() {
-        let fill_json = serde_json::json!({
-            "time": 1000,
-            "side": "A",
-            "px": "50000",
-            "sz": "1",
-            "fee": "10",
-            "closedPnl": "0",
-            "tid": 123,
-            "oid": 456
+    fn test_parse_sub_accounts() {
+        assert!(parse_sub_accounts(&serde_json::Value::Null).unwrap().is_empty());
+
+        let response = serde_json::json!([
+            { "name": "hedge", "subAccountUser": "0xabc", "master": "0x123" },
+            { "name": "vault-mm", "subAccountUser": "0xdef", "master": "0x123" }
+        ]);
+        assert_eq!(
+            parse_sub_accounts(&response).unwrap(),
+            vec![
+                Address::new("0xabc".to_string()),
+                Address::new("0xdef".to_string())
+            ]
+        );
+
+        let response = serde_json::json!([{ "name": "broken" }]);
+        assert!(parse_sub_accounts(&response).is_err());
+    }
+
+    #[test]
+    fn test_parse_candles() {
+        let response = serde_json::json!([
+            {"t": 3_600_000, "T": 7_199_999, "s": "BTC", "i": "1h", "o": "37000.5", "c": "37100"},
+            {"t": 7_200_000, "T": 10_799_999, "s": "BTC", "i": "1h", "o": "37100", "c": "36900"}
+        ]);
+        let marks = parse_candles(&response, "BTC").unwrap();
+        assert_eq!(marks.len(), 2);
+        assert_eq!(marks[0].coin.as_str(), "BTC");
+        assert_eq!(marks[0].time_ms, TimeMs::new(3_600_000));
+        assert_eq!(marks[0].px.to_canonical_string(), "37000.5");
+        assert_eq!(marks[1].px.to_canonical_string(), "37100");
+
+        let bad = serde_json::json!([{"t": 3_600_000, "o": 1}]);
+        assert!(parse_candles(&bad, "BTC").is_err());
+    }
+
+    #[test]
+    fn test_parse_meta() {
+        let response = serde_json::json!({
+            "universe": [
+                { "name": "BTC", "szDecimals": 5, "maxLeverage": 40 },
+                { "name": "kPEPE", "szDecimals": 0, "maxLeverage": 10, "onlyIsolated": false },
+                { "name": "MATIC", "szDecimals": 1, "maxLeverage": 20, "isDelisted": true }
+            ],
+            "marginTables": []
+        });
+        let coins = parse_meta(&response, None, 0).unwrap();
+        assert_eq!(coins.len(), 3);
+        assert_eq!(coins[1].coin.as_str(), "kPEPE");
+        assert_eq!(coins[1].asset_index, 1);
+        assert_eq!(coins[1].sz_decimals, 0);
+        assert_eq!(coins[0].max_leverage, 40);
+        assert!(!coins[0].delisted);
+        assert!(coins[2].delisted);
+
+        let response = serde_json::json!({ "universe": [{ "name": "BTC" }] });
+        assert!(parse_meta(&response, None, 0).is_err());
+        assert!(parse_meta(&serde_json::Value::Null, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_builder_dex_meta() {
+        let dexes = serde_json::json!([
+            null,
+            { "name": "xyz", "fullName": "XYZ", "deployer": "0x1" },
+            { "name": "flx", "fullName": "Felix", "deployer": "0x2" }
+        ]);
+        assert_eq!(
+            parse_perp_dexs(&dexes).unwrap(),
+            vec![(1, "xyz".to_string()), (2, "flx".to_string())]
+        );
+
+        let response = serde_json::json!({
+            "universe": [
+                { "name": "xyz:XYZ100", "szDecimals": 4, "maxLeverage": 20 },
+                { "name": "TSLA", "szDecimals": 3, "maxLeverage": 10 }
+            ]
         });
+        let coins = parse_meta(&response, Some("xyz"), 110_000).unwrap();
+        assert_eq!(coins[0].coin.as_str(), "xyz:XYZ100");
+        assert_eq!(coins[0].asset_index, 110_000);
+        assert_eq!(coins[1].coin.as_str(), "xyz:TSLA");
+        assert_eq!(coins[1].asset_index, 110_001);
+    }
+
+    #[test]
+    fn test_parse_account_value() {
+        let response = serde_json::json!({
+            "marginSummary": { "accountValue": "10432.5", "totalMarginUsed": "120" }
+        });
+        assert_eq!(
+            parse_account_value(&response).unwrap(),
+            Some(Decimal::from_str_canonical("10432.5").unwrap())
+        );
+        assert_eq!(parse_account_value(&serde_json::Value::Null).unwrap(), None);
 
-        let fill = parse_fill(&fill_json, "0x123", "BTC").unwrap();
-        assert_eq!(fill.user, Address::new("0x123".to_string()));
-        assert_eq!(fill.coin, Coin::new("BTC".to_string()));
-        assert_eq!(fill.time_ms, TimeMs::new(1000));
-        assert_eq!(fill.side, Side::Buy);
-        assert_eq!(fill.tid, Some(123));
-        assert_eq!(fill.oid, Some(456));
+        let response = serde_json::json!({ "marginSummary": { "accountValue": "lots" } });
+        assert!(parse_account_value(&response).is_err());
     }
 
     #[test]
@@ -378,7 +827,7 @@ mod tests {
             "hash": "0xdeadbeef"
         });
 
-        let deposit = parse_deposit(&deposit_json, "0x123").unwrap();
+        let deposit = parse_deposit(&deposit_json, "0x123").unwrap().unwrap();
         assert_eq!(deposit.user, Address::new("0x123".to_string()));
         assert_eq!(deposit.time_ms, TimeMs::new(1000));
         assert_eq!(deposit.amount.to_canonical_string(), "1000");
@@ -398,7 +847,7 @@ mod tests {
             "hash": "0xabcdef"
         });
 
-        let deposit = parse_deposit(&deposit_json, "0x789").unwrap();
+        let deposit = parse_deposit(&deposit_json, "0x789").unwrap().unwrap();
         assert_eq!(deposit.user, Address::new("0x789".to_string()));
         assert_eq!(deposit.time_ms, TimeMs::new(1500));
         assert_eq!(deposit.amount.to_canonical_string(), "2500.5");
@@ -417,24 +866,90 @@ mod tests {
             "hash": "0xwithdraw"
         });
 
-        let deposit = parse_deposit(&deposit_json, "0xabc").unwrap();
+        let deposit = parse_deposit(&deposit_json, "0xabc").unwrap().unwrap();
         assert_eq!(deposit.amount.to_canonical_string(), "-500");
     }
 
     #[test]
-    fn test_parse_deposit_skip_transfer() {
-        // Transfer type should be skipped
+    fn test_parse_deposit_skips_other_types() {
         let deposit_json = serde_json::json!({
             "time": 1700,
             "delta": {
-                "type": "transfer",
+                "type": "vaultDeposit",
+                "vault": "0xvault",
                 "usdc": "100"
             },
-            "hash": "0xtransfer"
+            "hash": "0xvault"
+        });
+
+        assert_eq!(parse_deposit(&deposit_json, "0xskip").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_deposit_withdraw_includes_fee() {
+        let deposit_json = serde_json::json!({
+            "time": 1650,
+            "delta": { "type": "withdraw", "usdc": "99", "nonce": 7, "fee": "1" },
+            "hash": "0xfee"
+        });
+
+        let deposit = parse_deposit(&deposit_json, "0xabc").unwrap().unwrap();
+        assert_eq!(deposit.amount.to_canonical_string(), "-100");
+        assert_eq!(deposit.kind, LedgerKind::Withdraw);
+        assert_eq!(deposit.event_key, "0xfee");
+    }
+
+    #[test]
+    fn test_parse_deposit_transfers_are_signed_by_direction() {
+        let transfer = |delta: serde_json::Value, user: &str| {
+            let deposit_json = serde_json::json!({ "time": 1800, "delta": delta, "hash": "0xT" });
+            parse_deposit(&deposit_json, user).unwrap().unwrap()
+        };
+        let internal = serde_json::json!({
+            "type": "internalTransfer",
+            "usdc": "50",
+            "user": "0xAAA",
+            "destination": "0xbbb",
+            "fee": "1"
         });
 
-        let result = parse_deposit(&deposit_json, "0xskip");
-        assert!(result.is_err());
+        let sent = transfer(internal.clone(), "0xaaa");
+        assert_eq!(sent.amount.to_canonical_string(), "-51");
+        assert_eq!(sent.kind, LedgerKind::InternalTransfer);
+        let received = transfer(internal.clone(), "0xbbb");
+        assert_eq!(received.amount.to_canonical_string(), "50");
+        // Both sides share the hash but keep distinct keys.
+        assert_ne!(sent.event_key, received.event_key);
+
+        let sub_account = transfer(
+            serde_json::json!({
+                "type": "subAccountTransfer",
+                "usdc": "20",
+                "user": "0xaaa",
+                "destination": "0xccc"
+            }),
+            "0xccc",
+        );
+        assert_eq!(sub_account.amount.to_canonical_string(), "20");
+        assert_eq!(sub_account.kind, LedgerKind::SubAccountTransfer);
+
+        let spot = transfer(
+            serde_json::json!({
+                "type": "spotTransfer",
+                "token": "PURR",
+                "amount": "1000",
+                "usdcValue": "12.5",
+                "user": "0xaaa",
+                "destination": "0xddd",
+                "fee": "0"
+            }),
+            "0xaaa",
+        );
+        assert_eq!(spot.amount.to_canonical_string(), "-12.5");
+        assert_eq!(spot.kind, LedgerKind::SpotTransfer);
+
+        let unrelated = serde_json::json!({ "time": 1800, "delta": internal, "hash": "0xT" });
+        assert!(parse_deposit(&unrelated, "0xeee").is_err());
     }
 
     #[test]
@@ -446,10 +961,141 @@ mod tests {
             "txHash": "0xcafe"
         });
 
-        let deposit = parse_deposit(&deposit_json, "0x456").unwrap();
+        let deposit = parse_deposit(&deposit_json, "0x456").unwrap().unwrap();
         assert_eq!(deposit.user, Address::new("0x456".to_string()));
         assert_eq!(deposit.time_ms, TimeMs::new(2000));
         assert_eq!(deposit.amount.to_canonical_string(), "500.5");
         assert_eq!(deposit.tx_hash.as_deref(), Some("0xcafe"));
     }
+
+    fn fills_at(times: &[i64]) -> Vec<Fill> {
+        times
+            .iter()
+            .enumerate()
+            .map(|(i, time)| {
+                let fill_json = serde_json::json!({
+                    "time": time,
+                    "side": "B",
+                    "px": "1",
+                    "sz": "1",
+                    "fee": "0",
+                    "closedPnl": "0",
+                    "tid": i
+                });
+                parse_fill(&fill_json, "0x123", "BTC").unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fills_cursor_token_roundtrip() {
+        let cursor = FillsCursor {
+            start_ms: 1000,
+            seen: 3,
+        };
+        assert_eq!(FillsCursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(FillsCursor::decode(&PageToken("1000".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_fills_cursor_stops_on_short_page() {
+        let cursor = FillsCursor {
+            start_ms: 0,
+            seen: 0,
+        };
+        let fills = fills_at(&[1, 2, 3]);
+        assert_eq!(cursor.next(&fills, fills.len(), 10_000), None);
+    }
+
+    #[test]
+    fn test_fills_cursor_resumes_within_last_millisecond() {
+        let cursor = FillsCursor {
+            start_ms: 0,
+            seen: 0,
+        };
+        let mut times = vec![5; MAX_FILLS_PER_REQUEST - 2];
+        times.extend([7, 7]);
+        let fills = fills_at(&times);
+        assert_eq!(
+            cursor.next(&fills, fills.len(), 10_000),
+            Some(FillsCursor {
+                start_ms: 7,
+                seen: 2,
+            })
+        );
+        // The window ends before the next page would start.
+        assert_eq!(cursor.next(&fills, fills.len(), 6), None);
+    }
+
+    #[test]
+    fn test_fills_cursor_skips_millisecond_it_cannot_page_through() {
+        let fills = fills_at(&vec![7; MAX_FILLS_PER_REQUEST]);
+        let first = FillsCursor {
+            start_ms: 0,
+            seen: 0,
+        }
+        .next(&fills, fills.len(), 10_000)
+        .unwrap();
+        assert_eq!(
+            first,
+            FillsCursor {
+                start_ms: 7,
+                seen: MAX_FILLS_PER_REQUEST,
+            }
+        );
+        assert_eq!(
+            first.next(&fills, fills.len(), 10_000),
+            Some(FillsCursor {
+                start_ms: 8,
+                seen: 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorded_payload_replays_to_same_fills() {
+        let body = serde_json::json!([
+            {"coin": "BTC", "time": 1000, "side": "B", "px": "50000", "sz": "1", "fee": "1", "closedPnl": "0", "tid": 1},
+            {"coin": "BTC", "time": 2000, "side": "A", "px": "51000", "sz": "1", "fee": "1", "closedPnl": "1000", "tid": 2}
+        ]);
+        let app = axum::Router::new().route(
+            "/info",
+            axum::routing::post(move || {
+                let body = body.clone();
+                async move { axum::Json(body) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db").to_string_lossy().to_string();
+        let repo = Arc::new(Repository::new(crate::db::init_db(&db_path).await.unwrap()));
+        let datasource = HyperliquidDataSource {
+            client: Client::builder().no_proxy().build().unwrap(),
+            base_url: format!("http://{}", addr),
+            payloads: None,
+        }
+        .with_payload_recording(repo.clone());
+
+        let fills = datasource.fetch_fills("0xabc", "BTC", 0, 10_000).await.unwrap();
+        assert_eq!(fills.len(), 2);
+        // An identical response is stored once.
+        datasource.fetch_fills("0xabc", "BTC", 0, 10_000).await.unwrap();
+
+        let user = Address::new("0xabc".to_string());
+        let recorded = repo
+            .query_raw_payloads("userFillsByTime", Some(&user))
+            .await
+            .unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(replay_fills(&recorded[0].request, &recorded[0].content).unwrap(), fills);
+    }
+
+    #[test]
+    fn test_replay_fills_rejects_other_payloads() {
+        let request = serde_json::json!({"type": "userState", "user": "0xabc"}).to_string();
+        assert!(replay_fills(&request, b"{}").is_err());
+    }
 }
@@ -0,0 +1,452 @@
+//! Hyperliquid WebSocket client streaming user fills as they happen.
+//!
+//! The pull path (`ensure_ingested`) only sees fills when a request asks for them, which
+//! leaves a multi-minute gap for busy accounts. This source keeps one connection open,
+//! subscribes to the `userFills` channel for every tracked user, and hands each batch to the
+//! [`Ingestor`]. Whenever a (re)connection is established the REST API is queried from the
+//! user's latest stored fill, so nothing sent while the socket was down is lost. Fill keys
+//! make the overlap between the two paths harmless.
+
+use super::normalize::{FillAdapter, WsFillsAdapter};
+use super::DataSourceError;
+use crate::domain::{Address, Fill};
+use crate::orchestration::ensure::{IngestionError, Ingestor};
+use crate::orchestration::jobs::{JobPriority, JobQueue};
+use futures::{SinkExt, StreamExt};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+/// Streams `userFills` for a fixed set of users into an [`Ingestor`].
+#[derive(Debug, Clone)]
+pub struct HyperliquidWsDataSource {
+    ws_url: String,
+    users: Vec<Address>,
+    ping_interval: Duration,
+    initial_reconnect_delay: Duration,
+    max_reconnect_delay: Duration,
+    jobs: Option<JobQueue>,
+}
+
+impl HyperliquidWsDataSource {
+    /// Create a stream for `users` against `ws_url` (e.g. `wss://api.hyperliquid.xyz/ws`).
+    pub fn new(ws_url: String, users: Vec<Address>) -> Self {
+        Self {
+            ws_url,
+            users,
+            // The server drops connections that stay silent for 60 seconds.
+            ping_interval: Duration::from_secs(50),
+            initial_reconnect_delay: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(60),
+            jobs: None,
+        }
+    }
+
+    /// Run reconnect gap fills as background jobs on `jobs`, so they wait behind API requests.
+    pub fn with_job_queue(mut self, jobs: JobQueue) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Override the reconnect backoff bounds.
+    pub fn with_reconnect_delays(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_reconnect_delay = initial;
+        self.max_reconnect_delay = max;
+        self
+    }
+
+    /// Override how often an application-level ping is sent.
+    ///
+    /// A connection that receives nothing for two intervals is treated as dead.
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    /// Stream fills, reconnecting with exponential backoff, until the job queue is closed.
+    pub async fn run(&self, ingestor: Ingestor) {
+        let mut backoff =
+            ReconnectBackoff::new(self.initial_reconnect_delay, self.max_reconnect_delay);
+
+        loop {
+            let result = self.run_session(&ingestor, &mut backoff).await;
+            if self.jobs.as_ref().is_some_and(JobQueue::is_closed) {
+                info!("Job queue closed, stopping Hyperliquid WebSocket stream");
+                return;
+            }
+            match result {
+                Ok(()) => info!("Hyperliquid WebSocket closed by server, reconnecting"),
+                Err(e) => warn!("Hyperliquid WebSocket session failed: {}", e),
+            }
+            let delay = backoff.next_delay();
+            debug!("Reconnecting to {} in {:?}", self.ws_url, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// One connection: subscribe, gap-fill over REST, then stream until the socket ends.
+    async fn run_session(
+        &self,
+        ingestor: &Ingestor,
+        backoff: &mut ReconnectBackoff,
+    ) -> Result<(), IngestionError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.ws_url.as_str())
+            .await
+            .map_err(network_error)?;
+        info!(
+            "Connected to {}, subscribing to fills for {} users",
+            self.ws_url,
+            self.users.len()
+        );
+
+        // Subscribe before gap filling so fills made during the REST query arrive on the socket.
+        for user in &self.users {
+            socket
+                .send(Message::Text(subscribe_message(user)))
+                .await
+                .map_err(network_error)?;
+        }
+        for user in &self.users {
+            let _permit = match &self.jobs {
+                Some(jobs) => match jobs.acquire(user, JobPriority::Background).await {
+                    Ok(permit) => Some(permit),
+                    // Shutting down; `run` stops once the session ends.
+                    Err(_) => return Ok(()),
+                },
+                None => None,
+            };
+            let since = ingestor.repo().get_latest_fill_time(user).await?;
+            let result = ingestor.ensure_ingested(user, None, since, None).await?;
+            if result.fills_new > 0 {
+                info!("Gap fill stored {} fills for {}", result.fills_new, user);
+            }
+        }
+        backoff.reset();
+
+        let mut ping = tokio::time::interval(self.ping_interval);
+        ping.tick().await;
+        let mut last_message = Instant::now();
+
+        loop {
+            tokio::select! {
+                _ = ping.tick() => {
+                    if last_message.elapsed() > self.ping_interval * 2 {
+                        return Err(network_error("no messages received, connection is stale").into());
+                    }
+                    socket
+                        .send(Message::Text(PING_MESSAGE.to_string()))
+                        .await
+                        .map_err(network_error)?;
+                }
+                message = socket.next() => {
+                    let Some(message) = message else {
+                        return Ok(());
+                    };
+                    last_message = Instant::now();
+                    match message.map_err(network_error)? {
+                        Message::Text(text) => match parse_message(&text) {
+                            Ok(Some(update)) => {
+                                let new = ingestor
+                                    .ingest_streamed_fills(&update.user, &update.fills)
+                                    .await?;
+                                debug!(
+                                    "Streamed {} fills for {} ({} new, snapshot={})",
+                                    update.fills.len(),
+                                    update.user,
+                                    new,
+                                    update.is_snapshot
+                                );
+                            }
+                            Ok(None) => {}
+                            Err(e) => warn!("Ignoring malformed WebSocket message: {}", e),
+                        },
+                        Message::Close(_) => return Ok(()),
+                        // tungstenite answers protocol pings itself.
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+const PING_MESSAGE: &str = r#"{"method":"ping"}"#;
+
+fn subscribe_message(user: &Address) -> String {
+    serde_json::json!({
+        "method": "subscribe",
+        "subscription": { "type": "userFills", "user": user.as_str() }
+    })
+    .to_string()
+}
+
+fn network_error(e: impl std::fmt::Display) -> DataSourceError {
+    DataSourceError::NetworkError(e.to_string())
+}
+
+/// A `userFills` channel message.
+#[derive(Debug)]
+struct UserFillsUpdate {
+    user: Address,
+    /// The first message after subscribing replays recent fills.
+    is_snapshot: bool,
+    fills: Vec<Fill>,
+}
+
+/// Parse a text frame; `None` for pongs, subscription acks and other channels.
+fn parse_message(text: &str) -> Result<Option<UserFillsUpdate>, DataSourceError> {
+    let value: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    if value.get("channel").and_then(|v| v.as_str()) != Some("userFills") {
+        return Ok(None);
+    }
+
+    let data = value
+        .get("data")
+        .ok_or_else(|| DataSourceError::ParseError("Missing data field".to_string()))?;
+    let user = WsFillsAdapter::user(data).map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    let is_snapshot = data
+        .get("isSnapshot")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let normalized = WsFillsAdapter
+        .normalize(data)
+        .map_err(|e| DataSourceError::ParseError(e.to_string()))?;
+    for reject in &normalized.rejects {
+        warn!("Failed to parse streamed fill: {}", reject.reason);
+    }
+    let fills = normalized.records;
+
+    Ok(Some(UserFillsUpdate {
+        user: Address::new(user.to_string()),
+        is_snapshot,
+        fills,
+    }))
+}
+
+/// Exponential reconnect delay, reset once a session is established.
+#[derive(Debug)]
+struct ReconnectBackoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl ReconnectBackoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BuilderAttributionMode, Config, Network, PnlMode};
+    use crate::datasource::MockDataSource;
+    use crate::db::migrations::init_db;
+    use crate::db::Repository;
+    use crate::domain::{Coin, Decimal, Side, TimeMs};
+    use crate::engine::{HeuristicAttributor, MatchTolerances};
+    use crate::orchestration::jobs::JobLimits;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    const USER: &str = "0x1111111111111111111111111111111111111111";
+
+    fn fill_json(time_ms: i64, tid: i64) -> serde_json::Value {
+        serde_json::json!({
+            "coin": "BTC",
+            "px": "50000",
+            "sz": "0.1",
+            "side": "A",
+            "time": time_ms,
+            "fee": "1",
+            "closedPnl": "0",
+            "tid": tid,
+            "oid": tid
+        })
+    }
+
+    fn fills_message(is_snapshot: bool, fills: Vec<serde_json::Value>) -> String {
+        serde_json::json!({
+            "channel": "userFills",
+            "data": { "isSnapshot": is_snapshot, "user": USER, "fills": fills }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_user_fills_message() {
+        let update = parse_message(&fills_message(true, vec![fill_json(1000, 7)]))
+            .unwrap()
+            .unwrap();
+
+        assert!(update.is_snapshot);
+        assert_eq!(update.user, Address::new(USER.to_string()));
+        assert_eq!(update.fills.len(), 1);
+        assert_eq!(update.fills[0].coin, Coin::new("BTC".to_string()));
+        assert_eq!(update.fills[0].tid, Some(7));
+    }
+
+    #[test]
+    fn test_parse_ignores_other_channels() {
+        assert!(parse_message(r#"{"channel":"pong"}"#).unwrap().is_none());
+        assert!(parse_message(
+            r#"{"channel":"subscriptionResponse","data":{"method":"subscribe"}}"#
+        )
+        .unwrap()
+        .is_none());
+        assert!(parse_message("not json").is_err());
+    }
+
+    #[test]
+    fn test_subscribe_message() {
+        let msg: serde_json::Value =
+            serde_json::from_str(&subscribe_message(&Address::new(USER.to_string()))).unwrap();
+        assert_eq!(msg["method"], "subscribe");
+        assert_eq!(msg["subscription"]["type"], "userFills");
+        assert_eq!(msg["subscription"]["user"], USER);
+    }
+
+    #[test]
+    fn test_reconnect_backoff_doubles_and_resets() {
+        let mut backoff = ReconnectBackoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_streams_fills_resubscribes_and_gap_fills() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let pool = init_db(db_path.to_str().unwrap()).await.unwrap();
+        let repo = Arc::new(Repository::new(pool));
+
+        // Only reachable through REST: made while the socket was down.
+        let missed = Fill::new(
+            TimeMs::new(2000),
+            Address::new(USER.to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Sell,
+            Decimal::from_str_canonical("50100").unwrap(),
+            Decimal::from_str_canonical("0.1").unwrap(),
+            Decimal::from_str_canonical("1").unwrap(),
+            Decimal::from_str_canonical("10").unwrap(),
+            None,
+            Some(2),
+            Some(2),
+        );
+        let config = Config {
+            port: 0,
+            bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+            admin_port: None,
+            admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+            database_path: db_path.to_string_lossy().to_string(),
+            network: Network::Mainnet,
+            hyperliquid_api_url: "http://example.invalid".to_string(),
+            hyperliquid_ws_url: None,
+            target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+            builder_attribution_mode: BuilderAttributionMode::Heuristic,
+            pnl_mode: PnlMode::Gross,
+            lookback_ms: 0,
+            leaderboard_users: vec![],
+            match_tolerances: MatchTolerances::default(),
+            heuristic_attribution: HeuristicAttributor::default(),
+            job_limits: JobLimits::default(),
+            builder_logs_auto_backfill: false,
+            builder_logs_max_concurrent_downloads: 4,
+            api_keys: Vec::new(),
+            response_cache_ttl_ms: 0,
+            sub_account_rollup: false,
+            ingest_chunk_ms: 86_400_000,
+            ingest_max_concurrent_chunks: 4,
+            record_raw_payloads: false,
+            maintenance: Default::default(),
+            db_pool: Default::default(),
+            idempotency_ttl_ms: 0,
+            leaderboard_snapshot_interval_ms: 0,
+            equity_sample_interval_ms: 0,
+            response_compression: true,
+            max_request_body_bytes: 2 * 1024 * 1024,
+            startup_integrity_check: Default::default(),
+            read_only: false,
+            coin_meta_refresh_interval_ms: 0,
+            overflow_policy: Default::default(),
+            webhooks: Default::default(),
+        };
+        let ingestor = Ingestor::new(
+            Arc::new(MockDataSource::new().with_fill(missed)),
+            repo.clone(),
+            config,
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut subscriptions = Vec::new();
+            for (time_ms, tid) in [(1000, 1), (3000, 3)] {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                let Some(Ok(Message::Text(sub))) = ws.next().await else {
+                    panic!("expected a subscribe message");
+                };
+                subscriptions.push(sub);
+                ws.send(Message::Text(fills_message(
+                    tid == 1,
+                    vec![fill_json(time_ms, tid)],
+                )))
+                .await
+                .unwrap();
+                // Drop the connection to force a reconnect.
+                ws.close(None).await.unwrap();
+            }
+            subscriptions
+        });
+
+        let source = HyperliquidWsDataSource::new(url, vec![Address::new(USER.to_string())])
+            .with_reconnect_delays(Duration::from_millis(10), Duration::from_millis(50));
+        let client = tokio::spawn(async move { source.run(ingestor).await });
+
+        let user = Address::new(USER.to_string());
+        let coin = Coin::new("BTC".to_string());
+        let mut stored = Vec::new();
+        for _ in 0..200 {
+            stored = repo
+                .query_fills(&user, Some(&coin), None, None)
+                .await
+                .unwrap();
+            if stored.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        client.abort();
+
+        let subscriptions = server.await.unwrap();
+        assert_eq!(subscriptions.len(), 2, "client should resubscribe");
+        assert!(subscriptions.iter().all(|s| s == &subscribe_message(&user)));
+
+        let tids: Vec<Option<i64>> = stored.iter().map(|f| f.tid).collect();
+        assert_eq!(tids, vec![Some(1), Some(2), Some(3)]);
+    }
+}
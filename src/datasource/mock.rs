@@ -1,8 +1,10 @@
 //! Mock data source for testing without network calls.
 
-use super::{DataSource, DataSourceError};
-use crate::domain::{Address, Decimal, Deposit, Fill, TimeMs};
+use super::{DataSource, DataSourceError, FillsPage, PageToken};
+use crate::domain::{Address, CoinMeta, Decimal, Deposit, Fill, MarkPrice, TimeMs};
 use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Mock data source that returns predefined test data.
 #[derive(Debug, Clone)]
@@ -10,6 +12,16 @@ pub struct MockDataSource {
     fills: Vec<Fill>,
     deposits: Vec<Deposit>,
     equity: Option<Decimal>,
+    /// `(parent, sub-account)` pairs.
+    sub_accounts: Vec<(Address, Address)>,
+    /// Fills per `fetch_fills_page` page; unlimited when `None`.
+    page_size: Option<usize>,
+    coin_meta: Vec<CoinMeta>,
+    mark_prices: Vec<MarkPrice>,
+    /// Number of `fetch_mark_prices` calls served.
+    mark_price_fetches: Arc<AtomicUsize>,
+    /// Number of `fetch_sub_accounts` calls served.
+    sub_account_fetches: Arc<AtomicUsize>,
 }
 
 impl MockDataSource {
@@ -19,6 +31,12 @@ impl MockDataSource {
             fills: Vec::new(),
             deposits: Vec::new(),
             equity: None,
+            sub_accounts: Vec::new(),
+            page_size: None,
+            coin_meta: Vec::new(),
+            mark_prices: Vec::new(),
+            mark_price_fetches: Arc::new(AtomicUsize::new(0)),
+            sub_account_fetches: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -51,6 +69,40 @@ impl MockDataSource {
         self.equity = Some(equity);
         self
     }
+
+    /// Register `sub_account` as a sub-account of `parent`.
+    pub fn with_sub_account(mut self, parent: Address, sub_account: Address) -> Self {
+        self.sub_accounts.push((parent, sub_account));
+        self
+    }
+
+    /// Set the asset metadata returned by fetch_coin_meta.
+    pub fn with_coin_meta(mut self, coin_meta: Vec<CoinMeta>) -> Self {
+        self.coin_meta = coin_meta;
+        self
+    }
+
+    /// Add price history returned by fetch_mark_prices.
+    pub fn with_mark_prices(mut self, mark_prices: Vec<MarkPrice>) -> Self {
+        self.mark_prices.extend(mark_prices);
+        self
+    }
+
+    /// How many times fetch_mark_prices has been called.
+    pub fn mark_price_fetches(&self) -> usize {
+        self.mark_price_fetches.load(Ordering::Relaxed)
+    }
+
+    /// How many times fetch_sub_accounts has been called.
+    pub fn sub_account_fetches(&self) -> usize {
+        self.sub_account_fetches.load(Ordering::Relaxed)
+    }
+
+    /// Serve `fetch_fills_page` in pages of at most `page_size` fills.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
 }
 
 impl Default for MockDataSource {
@@ -85,6 +137,34 @@ impl DataSource for MockDataSource {
             .collect())
     }
 
+    /// Pages through the matching fills in insertion order; the token is the offset of
+    /// the next page.
+    async fn fetch_fills_page(
+        &self,
+        user: &str,
+        coin: &str,
+        from_ms: i64,
+        to_ms: i64,
+        page: Option<&PageToken>,
+    ) -> Result<FillsPage, DataSourceError> {
+        let fills = self.fetch_fills(user, coin, from_ms, to_ms).await?;
+        let offset = match page {
+            Some(token) => token.0.parse::<usize>().map_err(|_| {
+                DataSourceError::Other(format!("Invalid fills page token '{}'", token.0))
+            })?,
+            None => 0,
+        };
+        let end = match self.page_size {
+            Some(size) => (offset + size).min(fills.len()),
+            None => fills.len(),
+        };
+
+        Ok(FillsPage {
+            fills: fills.get(offset..end).unwrap_or_default().to_vec(),
+            next: (end < fills.len()).then(|| PageToken(end.to_string())),
+        })
+    }
+
     async fn fetch_deposits(
         &self,
         user: &str,
@@ -110,6 +190,41 @@ impl DataSource for MockDataSource {
     ) -> Result<Option<Decimal>, DataSourceError> {
         Ok(self.equity)
     }
+
+    async fn fetch_sub_accounts(&self, user: &str) -> Result<Vec<Address>, DataSourceError> {
+        self.sub_account_fetches.fetch_add(1, Ordering::Relaxed);
+        Ok(self
+            .sub_accounts
+            .iter()
+            .filter(|(parent, _)| parent.as_str() == user)
+            .map(|(_, sub_account)| sub_account.clone())
+            .collect())
+    }
+
+    async fn fetch_coin_meta(&self) -> Result<Vec<CoinMeta>, DataSourceError> {
+        Ok(self.coin_meta.clone())
+    }
+
+    async fn fetch_mark_prices(
+        &self,
+        coin: &str,
+        from_ms: i64,
+        to_ms: i64,
+    ) -> Result<Vec<MarkPrice>, DataSourceError> {
+        self.mark_price_fetches.fetch_add(1, Ordering::Relaxed);
+        let mut marks: Vec<MarkPrice> = self
+            .mark_prices
+            .iter()
+            .filter(|m| {
+                m.coin.as_str() == coin
+                    && m.time_ms >= TimeMs::new(from_ms)
+                    && m.time_ms <= TimeMs::new(to_ms)
+            })
+            .cloned()
+            .collect();
+        marks.sort_by_key(|m| m.time_ms);
+        Ok(marks)
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +287,36 @@ mod tests {
         let result = mock.fetch_equity("0x123", 1000).await.unwrap();
         assert_eq!(result, Some(equity));
     }
+
+    #[tokio::test]
+    async fn test_mock_datasource_fetch_fills_page() {
+        let fills: Vec<Fill> = (0..5)
+            .map(|i| {
+                let mut fill = make_test_fill();
+                fill.tid = Some(i);
+                fill
+            })
+            .collect();
+        let mock = MockDataSource::new()
+            .with_fills(fills.clone())
+            .with_page_size(2);
+
+        let mut fetched = Vec::new();
+        let mut page = None;
+        let mut pages = 0;
+        loop {
+            let FillsPage { fills, next } = mock
+                .fetch_fills_page("0x123", "BTC", 0, 2000, page.as_ref())
+                .await
+                .unwrap();
+            fetched.extend(fills);
+            pages += 1;
+            match next {
+                Some(token) => page = Some(token),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(fetched, fills);
+    }
 }
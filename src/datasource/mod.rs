@@ -1,16 +1,22 @@
 //! Data source abstraction for fetching fills, deposits, and equity from external sources.
 
-use crate::domain::{Decimal, Deposit, Fill};
+use crate::domain::{Address, CoinMeta, Decimal, Deposit, Fill, MarkPrice};
 use async_trait::async_trait;
 use std::fmt;
 
 pub mod hyperliquid;
+pub mod hyperliquid_ws;
 pub mod mock;
+pub mod normalize;
 pub mod builder_logs;
 
 pub use hyperliquid::HyperliquidDataSource;
+pub use hyperliquid_ws::HyperliquidWsDataSource;
 pub use mock::MockDataSource;
-pub use builder_logs::{BuilderLogsError, BuilderLogsFetcher, BuilderLogsSource};
+pub use builder_logs::{
+    load_cached_day, BuilderLogsDownloadStats, BuilderLogsError, BuilderLogsFetcher,
+    BuilderLogsSource, CachedBuilderLogsFetcher,
+};
 
 /// Data source trait for fetching fills, deposits, and equity information.
 ///
@@ -35,6 +41,26 @@ pub trait DataSource: Send + Sync + fmt::Debug {
         to_ms: i64,
     ) -> Result<Vec<Fill>, DataSourceError>;
 
+    /// Fetch one page of fills for a user and coin within a time range.
+    ///
+    /// Pass `None` for the first page, then the previous page's [`FillsPage::next`] until it
+    /// is `None`. Sources whose upstream API caps rows per call return a continuation token
+    /// rather than truncating; the default returns everything from
+    /// [`DataSource::fetch_fills`] as a single page.
+    async fn fetch_fills_page(
+        &self,
+        user: &str,
+        coin: &str,
+        from_ms: i64,
+        to_ms: i64,
+        _page: Option<&PageToken>,
+    ) -> Result<FillsPage, DataSourceError> {
+        Ok(FillsPage {
+            fills: self.fetch_fills(user, coin, from_ms, to_ms).await?,
+            next: None,
+        })
+    }
+
     /// Fetch deposits/withdrawals for a user within a time range.
     ///
     /// # Arguments
@@ -53,6 +79,9 @@ pub trait DataSource: Send + Sync + fmt::Debug {
 
     /// Fetch user's equity at a specific point in time (best-effort).
     ///
+    /// Sources that only know the live account value, like Hyperliquid, return it whatever
+    /// `at_ms` is.
+    ///
     /// # Arguments
     /// * `user` - User address
     /// * `at_ms` - Time in milliseconds
@@ -64,6 +93,51 @@ pub trait DataSource: Send + Sync + fmt::Debug {
         user: &str,
         at_ms: i64,
     ) -> Result<Option<Decimal>, DataSourceError>;
+
+    /// Fetch the addresses of a user's sub-accounts.
+    ///
+    /// Sub-accounts trade under their own addresses; their fills are fetched with
+    /// [`DataSource::fetch_fills`] like any other user's. Sources without sub-accounts
+    /// return none.
+    async fn fetch_sub_accounts(&self, _user: &str) -> Result<Vec<Address>, DataSourceError> {
+        Ok(Vec::new())
+    }
+
+    /// Fetch the metadata of every perp asset the exchange lists.
+    ///
+    /// Sources without asset metadata return none, which leaves the stored registry as is.
+    async fn fetch_coin_meta(&self) -> Result<Vec<CoinMeta>, DataSourceError> {
+        Ok(Vec::new())
+    }
+
+    /// Fetch the mark price of `coin` at the start of every
+    /// [`MARK_PRICE_BUCKET_MS`](crate::domain::MARK_PRICE_BUCKET_MS) bucket starting within
+    /// a time range (both inclusive), in time order.
+    ///
+    /// Buckets the source has no price for, e.g. before the coin listed, are left out.
+    /// Sources without price history return none.
+    async fn fetch_mark_prices(
+        &self,
+        _coin: &str,
+        _from_ms: i64,
+        _to_ms: i64,
+    ) -> Result<Vec<MarkPrice>, DataSourceError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Continuation token for [`DataSource::fetch_fills_page`].
+///
+/// Opaque to callers: only the source that issued it can interpret it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageToken(pub String);
+
+/// One page of fills and the token for the page after it.
+#[derive(Debug, Clone, Default)]
+pub struct FillsPage {
+    pub fills: Vec<Fill>,
+    /// `None` once the requested window is exhausted.
+    pub next: Option<PageToken>,
 }
 
 /// Error type for data source operations.
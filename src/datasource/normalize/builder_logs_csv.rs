@@ -0,0 +1,159 @@
+//! Daily builder fills logs: decompressed CSV with a header row.
+
+use super::{FillAdapter, NormalizeError, Normalized, Reject, TextPosition};
+use crate::domain::{Address, BuilderLogFill, Coin, Decimal, Side, TimeMs};
+
+/// Reads a decompressed builder fills CSV.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuilderLogsCsvAdapter;
+
+// Actual API schema:
+// time,user,coin,side,px,sz,crossed,special_trade_type,tif,is_trigger,counterparty,closed_pnl,twap_id,builder_fee
+#[derive(Debug, serde::Deserialize)]
+#[allow(dead_code)]
+struct Row {
+    time: String, // ISO8601 format: "2024-12-15T10:30:45.123Z"
+    user: String,
+    coin: String,
+    side: String,
+    px: String,
+    sz: String,
+    // Additional columns from actual API (we ignore them but need to handle them)
+    #[serde(default)]
+    crossed: Option<String>,
+    #[serde(default)]
+    special_trade_type: Option<String>,
+    #[serde(default)]
+    tif: Option<String>,
+    #[serde(default)]
+    is_trigger: Option<String>,
+    #[serde(default)]
+    counterparty: Option<String>,
+    #[serde(default)]
+    closed_pnl: Option<String>,
+    #[serde(default)]
+    twap_id: Option<String>,
+    #[serde(default)]
+    builder_fee: Option<String>,
+}
+
+fn parse_side(s: &str) -> Option<Side> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "a" | "buy" | "bid" => Some(Side::Buy),
+        "b" | "sell" | "ask" => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+/// Parse ISO8601 time string to milliseconds since epoch.
+/// Expects format: "2024-12-15T10:30:45.123Z" or similar.
+fn parse_time_to_ms(time_str: &str) -> Result<i64, String> {
+    use chrono::{DateTime, Utc};
+    let dt: DateTime<Utc> = time_str
+        .parse()
+        .map_err(|e| format!("invalid time '{}': {}", time_str, e))?;
+    Ok(dt.timestamp_millis())
+}
+
+fn parse_row(record: &csv::ByteRecord, headers: &csv::ByteRecord) -> Result<BuilderLogFill, String> {
+    let row: Row = record
+        .deserialize(Some(headers))
+        .map_err(|e| e.to_string())?;
+    let time_ms = parse_time_to_ms(&row.time)?;
+    let side = parse_side(&row.side).ok_or_else(|| format!("invalid side: {}", row.side))?;
+    let px = Decimal::from_str_canonical(&row.px).map_err(|e| format!("invalid px: {}", e))?;
+    let sz = Decimal::from_str_canonical(&row.sz).map_err(|e| format!("invalid sz: {}", e))?;
+
+    Ok(BuilderLogFill {
+        time_ms: TimeMs::new(time_ms),
+        user: Address::new(row.user),
+        coin: Coin::new(row.coin),
+        side,
+        px,
+        sz,
+        tid: None, // Not available in actual API
+        oid: None, // Not available in actual API
+    })
+}
+
+impl BuilderLogsCsvAdapter {
+    /// Read the CSV as it streams from `input`, e.g. straight out of an LZ4 decoder, without
+    /// holding the whole file in memory.
+    pub fn normalize_reader<R: std::io::Read>(
+        &self,
+        input: R,
+    ) -> Result<Normalized<BuilderLogFill>, NormalizeError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(input);
+
+        let headers = reader.byte_headers().map_err(csv_error)?.clone();
+
+        let mut normalized = Normalized::default();
+        let mut record = csv::ByteRecord::new();
+        let mut index = 0;
+        while reader.read_byte_record(&mut record).map_err(csv_error)? {
+            match parse_row(&record, &headers) {
+                Ok(fill) => normalized.records.push(fill),
+                Err(reason) => normalized.rejects.push(Reject {
+                    index,
+                    position: record.position().map(|p| TextPosition {
+                        line: p.line(),
+                        byte_offset: p.byte(),
+                    }),
+                    reason,
+                    raw: record
+                        .iter()
+                        .map(String::from_utf8_lossy)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                }),
+            }
+            index += 1;
+        }
+
+        Ok(normalized)
+    }
+}
+
+fn csv_error(e: csv::Error) -> NormalizeError {
+    match e.kind() {
+        csv::ErrorKind::Io(io) => NormalizeError::Read(io.to_string()),
+        _ => NormalizeError::Csv(e.to_string()),
+    }
+}
+
+impl FillAdapter for BuilderLogsCsvAdapter {
+    type Input = [u8];
+    type Output = BuilderLogFill;
+
+    fn normalize(&self, csv_bytes: &[u8]) -> Result<Normalized<BuilderLogFill>, NormalizeError> {
+        self.normalize_reader(csv_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../../../tests/fixtures/normalize/builder_fills.csv");
+
+    #[test]
+    fn test_builder_logs_csv_fixture() {
+        let normalized = BuilderLogsCsvAdapter.normalize(FIXTURE).unwrap();
+
+        assert_eq!(normalized.records.len(), 2);
+        assert_eq!(normalized.records[0].time_ms, TimeMs::new(1_700_000_000_000));
+        assert_eq!(normalized.records[0].side, Side::Buy);
+        assert_eq!(normalized.records[1].side, Side::Sell);
+        assert_eq!(normalized.records[1].px.to_canonical_string(), "101");
+
+        assert_eq!(normalized.rejects.len(), 1);
+        let reject = &normalized.rejects[0];
+        assert_eq!(reject.index, 2);
+        assert_eq!(reject.position.map(|p| p.line), Some(4));
+        assert_eq!(reject.reason, "invalid side: wat");
+        assert!(reject.raw.starts_with("2023-11-14T22:13:22.000Z,0xabc,BTC,wat"));
+    }
+}
@@ -0,0 +1,82 @@
+//! Converting upstream fill payloads into domain records.
+//!
+//! Each wire format gets its own [`FillAdapter`]: the `userFillsByTime` REST response
+//! ([`rest`]), the `userFills` WebSocket channel ([`ws`]) and the daily builder fills CSV
+//! ([`builder_logs_csv`]). Adapters never fail a whole payload over one bad record; they
+//! return what they could read together with a [`Reject`] per record they could not, and
+//! only a payload whose overall shape is wrong is an error.
+
+pub mod builder_logs_csv;
+pub mod rest;
+pub mod ws;
+
+pub use builder_logs_csv::BuilderLogsCsvAdapter;
+pub use rest::RestFillsAdapter;
+pub use ws::WsFillsAdapter;
+
+use thiserror::Error;
+
+/// Reads one upstream payload format into domain records.
+pub trait FillAdapter {
+    /// The payload as received, e.g. a parsed JSON document or raw CSV bytes.
+    type Input: ?Sized;
+    /// The record produced per accepted row.
+    type Output;
+
+    fn normalize(&self, input: &Self::Input) -> Result<Normalized<Self::Output>, NormalizeError>;
+}
+
+/// Records read from a payload, plus those that were skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized<T> {
+    pub records: Vec<T>,
+    pub rejects: Vec<Reject>,
+}
+
+impl<T> Normalized<T> {
+    /// Number of records in the payload, accepted or not.
+    pub fn total(&self) -> usize {
+        self.records.len() + self.rejects.len()
+    }
+}
+
+impl<T> Default for Normalized<T> {
+    fn default() -> Self {
+        Self {
+            records: Vec::new(),
+            rejects: Vec::new(),
+        }
+    }
+}
+
+/// A record an adapter could not convert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reject {
+    /// Zero-based index of the record within the payload.
+    pub index: usize,
+    /// Where the record starts, for line-oriented formats.
+    pub position: Option<TextPosition>,
+    pub reason: String,
+    /// The record as read (JSON text, or the CSV row lossily decoded as UTF-8).
+    pub raw: String,
+}
+
+/// Location of a record in a text payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextPosition {
+    /// 1-based line number.
+    pub line: u64,
+    pub byte_offset: u64,
+}
+
+/// The payload as a whole could not be read.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NormalizeError {
+    #[error("{0}")]
+    Malformed(String),
+    #[error("csv parse error: {0}")]
+    Csv(String),
+    /// The reader the payload was streamed from failed, e.g. a corrupt compressed frame.
+    #[error("read error: {0}")]
+    Read(String),
+}
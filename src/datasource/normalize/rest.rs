@@ -0,0 +1,250 @@
+//! `userFillsByTime` responses: a JSON array of fill objects.
+
+use super::{FillAdapter, NormalizeError, Normalized, Reject};
+use crate::domain::{Address, Coin, Decimal, Fill, FillType, Side, TimeMs};
+
+/// Reads a `userFillsByTime` response for one user.
+#[derive(Debug, Clone)]
+pub struct RestFillsAdapter<'a> {
+    pub user: &'a str,
+    /// Fallback for fills that omit `coin`; empty for requests covering all coins.
+    pub coin: &'a str,
+}
+
+impl<'a> RestFillsAdapter<'a> {
+    pub fn new(user: &'a str, coin: &'a str) -> Self {
+        Self { user, coin }
+    }
+}
+
+impl FillAdapter for RestFillsAdapter<'_> {
+    type Input = serde_json::Value;
+    type Output = Fill;
+
+    fn normalize(&self, input: &serde_json::Value) -> Result<Normalized<Fill>, NormalizeError> {
+        let rows = input
+            .as_array()
+            .ok_or_else(|| NormalizeError::Malformed("Expected array response".to_string()))?;
+        Ok(normalize_fill_objects(rows, self.user, self.coin))
+    }
+}
+
+/// Convert a list of fill objects, collecting the ones that fail as rejects.
+pub(crate) fn normalize_fill_objects(
+    rows: &[serde_json::Value],
+    user: &str,
+    coin: &str,
+) -> Normalized<Fill> {
+    let mut normalized = Normalized::default();
+    for (index, row) in rows.iter().enumerate() {
+        match parse_fill(row, user, coin) {
+            Ok(fill) => normalized.records.push(fill),
+            Err(reason) => normalized.rejects.push(Reject {
+                index,
+                position: None,
+                reason,
+                raw: row.to_string(),
+            }),
+        }
+    }
+    normalized
+}
+
+/// Parse a fill object as sent by `userFillsByTime` and the `userFills` WebSocket channel.
+///
+/// The fill's own `coin` field wins over `coin`, which only serves as a fallback for
+/// payloads that omit it (requests for all coins pass an empty string).
+pub(crate) fn parse_fill(
+    fill_json: &serde_json::Value,
+    user: &str,
+    coin: &str,
+) -> Result<Fill, String> {
+    let time_ms = fill_json
+        .get("time")
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| "Missing time field".to_string())?;
+
+    let side_str = fill_json
+        .get("side")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "Missing side field".to_string())?;
+
+    let side = match side_str {
+        "A" => Side::Buy,
+        "B" => Side::Sell,
+        _ => return Err(format!("Invalid side: {}", side_str)),
+    };
+
+    let px = decimal_field(fill_json, "px")?;
+    let sz = decimal_field(fill_json, "sz")?;
+    let fee = decimal_field(fill_json, "fee")?;
+    let closed_pnl = decimal_field(fill_json, "closedPnl")?;
+
+    let tid = fill_json.get("tid").and_then(|v| v.as_i64());
+    let oid = fill_json.get("oid").and_then(|v| v.as_i64());
+    let builder_fee = fill_json
+        .get("builderFee")
+        .and_then(|v| v.as_str())
+        .and_then(|s| Decimal::from_str_canonical(s).ok());
+
+    let crossed = fill_json.get("crossed").and_then(|v| v.as_bool());
+
+    let coin = fill_json
+        .get("coin")
+        .and_then(|v| v.as_str())
+        .unwrap_or(coin);
+
+    let fill = Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        px,
+        sz,
+        fee,
+        closed_pnl,
+        builder_fee,
+        tid,
+        oid,
+    );
+    let fill = fill.with_fill_type(fill_type(fill_json, user));
+    // `twapId` is null for fills of regular orders.
+    let fill = match fill_json.get("twapId").and_then(|v| v.as_i64()) {
+        Some(twap_id) => fill.with_twap_id(twap_id),
+        None => fill,
+    };
+    Ok(match crossed {
+        Some(crossed) => fill.with_crossed(crossed),
+        None => fill,
+    })
+}
+
+/// Auto-deleveraging fills are marked by their `dir`. Liquidation fills carry a
+/// `liquidation` object, which the liquidator's side of the trade also gets, so it only
+/// counts when it names `user` as the liquidated account.
+fn fill_type(fill_json: &serde_json::Value, user: &str) -> FillType {
+    if fill_json.get("dir").and_then(|v| v.as_str()) == Some("Auto-Deleveraging") {
+        return FillType::Adl;
+    }
+    match fill_json.get("liquidation") {
+        Some(liquidation) if liquidation.is_object() => {
+            let liquidated = liquidation.get("liquidatedUser").and_then(|v| v.as_str());
+            if liquidated.is_none_or(|liquidated| liquidated.eq_ignore_ascii_case(user)) {
+                FillType::Liquidation
+            } else {
+                FillType::Trade
+            }
+        }
+        _ => FillType::Trade,
+    }
+}
+
+/// A required decimal sent as a string.
+fn decimal_field(fill_json: &serde_json::Value, field: &str) -> Result<Decimal, String> {
+    let s = fill_json
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("Missing {} field", field))?;
+    Decimal::from_str_canonical(s).map_err(|e| format!("Invalid {}: {}", field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/normalize/rest_user_fills.json");
+
+    #[test]
+    fn test_parse_fill_valid() {
+        let fill_json = serde_json::json!({
+            "time": 1000,
+            "side": "A",
+            "px": "50000",
+            "sz": "1",
+            "fee": "10",
+            "closedPnl": "0",
+            "tid": 123,
+            "oid": 456,
+            "crossed": true,
+            "twapId": 77
+        });
+
+        let fill = parse_fill(&fill_json, "0x123", "BTC").unwrap();
+        assert_eq!(fill.user, Address::new("0x123".to_string()));
+        assert_eq!(fill.coin, Coin::new("BTC".to_string()));
+        assert_eq!(fill.time_ms, TimeMs::new(1000));
+        assert_eq!(fill.side, Side::Buy);
+        assert_eq!(fill.tid, Some(123));
+        assert_eq!(fill.oid, Some(456));
+        assert_eq!(fill.crossed, Some(true));
+        assert_eq!(fill.twap_id, Some(77));
+    }
+
+    #[test]
+    fn test_rest_fixture() {
+        let payload: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        let normalized = RestFillsAdapter::new("0xabc", "").normalize(&payload).unwrap();
+        assert_eq!(normalized.total(), 3);
+
+        let [open, close] = normalized.records.as_slice() else {
+            panic!("expected two fills, got {:?}", normalized.records);
+        };
+        assert_eq!(open.coin, Coin::new("BTC".to_string()));
+        assert_eq!(open.side, Side::Buy);
+        assert_eq!(open.px.to_canonical_string(), "50000");
+        assert_eq!(open.fee.to_canonical_string(), "7.5");
+        assert_eq!(open.builder_fee.map(|d| d.to_canonical_string()), Some("0.5".to_string()));
+        assert_eq!(open.tid, Some(101));
+        assert_eq!(open.oid, Some(9001));
+        assert_eq!(open.crossed, Some(true));
+
+        assert_eq!(close.side, Side::Sell);
+        assert_eq!(close.closed_pnl.to_canonical_string(), "500");
+        assert_eq!(close.fee.to_canonical_string(), "-0.5");
+        assert_eq!(close.builder_fee, None);
+        assert_eq!(close.crossed, Some(false));
+
+        assert_eq!(normalized.rejects.len(), 1);
+        assert_eq!(normalized.rejects[0].index, 2);
+        assert_eq!(normalized.rejects[0].position, None);
+        assert!(normalized.rejects[0].reason.starts_with("Invalid px"));
+    }
+
+    #[test]
+    fn test_parse_fill_type() {
+        let fill_json = |extra: serde_json::Value| {
+            let mut fill = serde_json::json!({
+                "time": 1000, "side": "B", "px": "100", "sz": "1", "fee": "0", "closedPnl": "-5"
+            });
+            fill.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            parse_fill(&fill, "0xAbC", "BTC").unwrap().fill_type
+        };
+
+        assert_eq!(fill_json(serde_json::json!({ "dir": "Close Long" })), FillType::Trade);
+        assert_eq!(
+            fill_json(serde_json::json!({
+                "dir": "Close Long",
+                "liquidation": { "liquidatedUser": "0xabc", "markPx": "99", "method": "market" }
+            })),
+            FillType::Liquidation
+        );
+        // The liquidator's side of the same trade.
+        assert_eq!(
+            fill_json(serde_json::json!({
+                "liquidation": { "liquidatedUser": "0xdef", "markPx": "99", "method": "market" }
+            })),
+            FillType::Trade
+        );
+        assert_eq!(fill_json(serde_json::json!({ "dir": "Auto-Deleveraging" })), FillType::Adl);
+    }
+
+    #[test]
+    fn test_rest_rejects_non_array_payload() {
+        let err = RestFillsAdapter::new("0xabc", "BTC")
+            .normalize(&serde_json::json!({ "error": "rate limited" }))
+            .unwrap_err();
+        assert_eq!(err, NormalizeError::Malformed("Expected array response".to_string()));
+    }
+}
@@ -0,0 +1,74 @@
+//! `userFills` WebSocket messages.
+//!
+//! The channel's `data` object names the user once and carries fill objects in the same
+//! shape as the REST response.
+
+use super::rest::normalize_fill_objects;
+use super::{FillAdapter, NormalizeError, Normalized};
+use crate::domain::Fill;
+
+/// Reads the `data` object of a `userFills` channel message.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WsFillsAdapter;
+
+impl WsFillsAdapter {
+    /// The user a `userFills` message is for.
+    pub fn user(data: &serde_json::Value) -> Result<&str, NormalizeError> {
+        data.get("user")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| NormalizeError::Malformed("Missing user field".to_string()))
+    }
+}
+
+impl FillAdapter for WsFillsAdapter {
+    type Input = serde_json::Value;
+    type Output = Fill;
+
+    fn normalize(&self, data: &serde_json::Value) -> Result<Normalized<Fill>, NormalizeError> {
+        let user = Self::user(data)?;
+        let rows = data
+            .get("fills")
+            .and_then(|v| v.as_array())
+            .map(|a| a.as_slice())
+            .unwrap_or_default();
+        Ok(normalize_fill_objects(rows, user, ""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Coin, Side};
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/normalize/ws_user_fills.json");
+
+    #[test]
+    fn test_ws_fixture() {
+        let message: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        let normalized = WsFillsAdapter.normalize(&message["data"]).unwrap();
+
+        assert_eq!(normalized.records.len(), 1);
+        let fill = &normalized.records[0];
+        assert_eq!(
+            fill.user,
+            Address::new("0x0000000000000000000000000000000000000a11".to_string())
+        );
+        assert_eq!(fill.coin, Coin::new("ETH".to_string()));
+        assert_eq!(fill.side, Side::Sell);
+        assert_eq!(fill.sz.to_canonical_string(), "2");
+        assert_eq!(fill.tid, Some(7));
+        assert_eq!(fill.oid, Some(42));
+
+        assert_eq!(normalized.rejects.len(), 1);
+        assert_eq!(normalized.rejects[0].index, 1);
+        assert_eq!(normalized.rejects[0].reason, "Missing sz field");
+    }
+
+    #[test]
+    fn test_ws_requires_user() {
+        let err = WsFillsAdapter
+            .normalize(&serde_json::json!({ "fills": [] }))
+            .unwrap_err();
+        assert_eq!(err, NormalizeError::Malformed("Missing user field".to_string()));
+    }
+}
@@ -0,0 +1,430 @@
+//! Compatibility checks for databases written by other hypesilico versions.
+//!
+//! Every migrated database records the versions of the build that last opened it in
+//! `instance_metadata`. Before a backup is restored or a user archive is imported, the
+//! source is checked against the running build so that data with different fill keys or
+//! decimal encodings is refused up front instead of being merged and silently diverging.
+
+use crate::config::Network;
+use crate::db::migrations::{COMPILE_SCHEMA_VERSION, SCHEMA_VERSION};
+use crate::domain::{Decimal, DECIMAL_FORMAT_VERSION, FILL_KEY_VERSION};
+use futures::TryStreamExt;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Decimal TEXT columns whose stored form must match our canonical encoding.
+const DECIMAL_COLUMNS: &[(&str, &str)] = &[
+    ("raw_fills", "px"),
+    ("raw_fills", "sz"),
+    ("raw_fills", "fee"),
+    ("raw_fills", "closed_pnl"),
+    ("raw_fills", "builder_fee"),
+    ("deposits", "amount"),
+];
+
+#[derive(Debug, Error)]
+pub enum CompatError {
+    #[error(
+        "source database has no instance_metadata; it predates version tracking, so re-ingest \
+         its users from the API instead of importing it"
+    )]
+    MissingManifest,
+    #[error("source instance_metadata is missing or has an invalid '{0}' entry")]
+    InvalidManifest(String),
+    #[error(
+        "source schema version {source_version} is newer than this build supports \
+         ({current}); upgrade hypesilico to {crate_version} or later before importing"
+    )]
+    SchemaTooNew {
+        source_version: i64,
+        current: i64,
+        crate_version: String,
+    },
+    #[error(
+        "source fill keys use version {source_version} but this build uses {current}; \
+         import with a build on the same fill key version or re-ingest fills from the API"
+    )]
+    FillKeyVersion { source_version: i64, current: i64 },
+    #[error(
+        "source decimals use encoding version {source_version} but this build uses {current}; \
+         import with a build on the same encoding or re-ingest from the API"
+    )]
+    DecimalFormatVersion { source_version: i64, current: i64 },
+    #[error(
+        "source {table}.{column} holds non-canonical decimal '{value}' (expected '{expected}'); \
+         the source was not written by a compatible build"
+    )]
+    NonCanonicalDecimal {
+        table: String,
+        column: String,
+        value: String,
+        expected: String,
+    },
+    #[error(
+        "database holds {recorded} data but HYPERLIQUID_NETWORK is {configured}; point \
+         DATABASE_PATH at a {configured} database instead of mixing the two"
+    )]
+    NetworkMismatch {
+        recorded: Network,
+        configured: Network,
+    },
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+/// Versions recorded by the build that last migrated a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatManifest {
+    pub crate_version: String,
+    pub schema_version: i64,
+    pub fill_key_version: i64,
+    /// `COMPILE_SCHEMA_VERSION` of the compiler that produced the derived tables.
+    pub engine_version: i64,
+    pub decimal_format_version: i64,
+}
+
+/// Outcome of a successful compatibility check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatReport {
+    pub source: CompatManifest,
+    /// Derived tables came from a different compiler and will be rebuilt on next startup.
+    pub recompile_required: bool,
+}
+
+impl CompatManifest {
+    /// Manifest for the running build.
+    pub fn current() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: SCHEMA_VERSION,
+            fill_key_version: FILL_KEY_VERSION,
+            engine_version: COMPILE_SCHEMA_VERSION,
+            decimal_format_version: DECIMAL_FORMAT_VERSION,
+        }
+    }
+
+    /// Read the manifest from a database, or `None` if it was never recorded.
+    pub async fn read(pool: &SqlitePool) -> Result<Option<Self>, CompatError> {
+        let has_table: Option<(String,)> = sqlx::query_as(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'instance_metadata'",
+        )
+        .fetch_optional(pool)
+        .await?;
+        if has_table.is_none() {
+            return Ok(None);
+        }
+
+        let rows = sqlx::query("SELECT key, value FROM instance_metadata")
+            .fetch_all(pool)
+            .await?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let get = |key: &str| -> Result<String, CompatError> {
+            rows.iter()
+                .find(|row| row.get::<String, _>("key") == key)
+                .map(|row| row.get::<String, _>("value"))
+                .ok_or_else(|| CompatError::InvalidManifest(key.to_string()))
+        };
+        let get_i64 = |key: &str| -> Result<i64, CompatError> {
+            get(key)?
+                .parse::<i64>()
+                .map_err(|_| CompatError::InvalidManifest(key.to_string()))
+        };
+
+        Ok(Some(Self {
+            crate_version: get("crate_version")?,
+            schema_version: get_i64("schema_version")?,
+            fill_key_version: get_i64("fill_key_version")?,
+            engine_version: get_i64("engine_version")?,
+            decimal_format_version: get_i64("decimal_format_version")?,
+        }))
+    }
+
+    /// Record this manifest in `instance_metadata`, replacing any previous values.
+    pub async fn write(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let entries = [
+            ("crate_version", self.crate_version.clone()),
+            ("schema_version", self.schema_version.to_string()),
+            ("fill_key_version", self.fill_key_version.to_string()),
+            ("engine_version", self.engine_version.to_string()),
+            (
+                "decimal_format_version",
+                self.decimal_format_version.to_string(),
+            ),
+        ];
+
+        let mut tx = pool.begin().await?;
+        for (key, value) in entries {
+            sqlx::query("INSERT OR REPLACE INTO instance_metadata (key, value) VALUES (?, ?)")
+                .bind(key)
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Check whether data described by `self` can be imported into the running build.
+    ///
+    /// Older schemas are accepted because migrations upgrade them in place. Fill key and
+    /// decimal encoding versions must match exactly. A different engine version is accepted
+    /// and reported, since stale derived tables are recompiled on startup.
+    pub fn check_against_current(&self) -> Result<CompatReport, CompatError> {
+        let current = Self::current();
+
+        if self.schema_version > current.schema_version {
+            return Err(CompatError::SchemaTooNew {
+                source_version: self.schema_version,
+                current: current.schema_version,
+                crate_version: self.crate_version.clone(),
+            });
+        }
+        if self.fill_key_version != current.fill_key_version {
+            return Err(CompatError::FillKeyVersion {
+                source_version: self.fill_key_version,
+                current: current.fill_key_version,
+            });
+        }
+        if self.decimal_format_version != current.decimal_format_version {
+            return Err(CompatError::DecimalFormatVersion {
+                source_version: self.decimal_format_version,
+                current: current.decimal_format_version,
+            });
+        }
+
+        Ok(CompatReport {
+            source: self.clone(),
+            recompile_required: self.engine_version != current.engine_version,
+        })
+    }
+}
+
+/// The network whose data the database holds, or `None` before one has been claimed.
+pub async fn read_network(pool: &SqlitePool) -> Result<Option<Network>, CompatError> {
+    let value: Option<(String,)> =
+        sqlx::query_as("SELECT value FROM instance_metadata WHERE key = 'network'")
+            .fetch_optional(pool)
+            .await?;
+    value
+        .map(|(value,)| {
+            Network::from_str(&value).map_err(|_| CompatError::InvalidManifest("network".into()))
+        })
+        .transpose()
+}
+
+/// Tag the database with `network`, or refuse if it already holds another network's data.
+///
+/// Fills, builder logs, and prices from Mainnet and Testnet share addresses and coins, so
+/// a mixed database would silently merge two unrelated ledgers. An untagged database is
+/// claimed by the first network that opens it.
+pub async fn claim_network(pool: &SqlitePool, network: Network) -> Result<(), CompatError> {
+    match read_network(pool).await? {
+        Some(recorded) if recorded != network => Err(CompatError::NetworkMismatch {
+            recorded,
+            configured: network,
+        }),
+        Some(_) => Ok(()),
+        None => {
+            sqlx::query("INSERT OR IGNORE INTO instance_metadata (key, value) VALUES ('network', ?)")
+                .bind(network.as_str())
+                .execute(pool)
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Validate a source database before restoring or importing it.
+///
+/// Checks the recorded manifest, then verifies that every stored decimal is in canonical
+/// form, since fill keys hash the canonical strings.
+pub async fn check_source_db(pool: &SqlitePool) -> Result<CompatReport, CompatError> {
+    let manifest = CompatManifest::read(pool)
+        .await?
+        .ok_or(CompatError::MissingManifest)?;
+    let report = manifest.check_against_current()?;
+
+    for (table, column) in DECIMAL_COLUMNS {
+        let sql = format!(
+            "SELECT {column} FROM {table} WHERE {column} IS NOT NULL",
+            column = column,
+            table = table
+        );
+        // Streamed, since a source can hold millions of fills.
+        let mut values = sqlx::query_as::<_, (String,)>(&sql).fetch(pool);
+        while let Some((value,)) = values.try_next().await? {
+            let expected = Decimal::from_str_canonical(&value)
+                .map(|d| d.to_canonical_string())
+                .unwrap_or_default();
+            if expected != value {
+                return Err(CompatError::NonCanonicalDecimal {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    value,
+                    expected,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Open a database file read-only and run [`check_source_db`] against it.
+pub async fn check_database_file(path: &str) -> Result<CompatReport, CompatError> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=ro", path))
+        .await?;
+    let result = check_source_db(&pool).await;
+    pool.close().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::init_db;
+    use tempfile::TempDir;
+
+    async fn setup_db(temp_dir: &TempDir, name: &str) -> (SqlitePool, String) {
+        let db_path = temp_dir.path().join(name).to_string_lossy().to_string();
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        (pool, db_path)
+    }
+
+    #[tokio::test]
+    async fn test_init_db_records_current_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir, "test.db").await;
+
+        let manifest = CompatManifest::read(&pool).await.unwrap();
+        assert_eq!(manifest, Some(CompatManifest::current()));
+    }
+
+    #[tokio::test]
+    async fn test_current_database_file_is_compatible() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, db_path) = setup_db(&temp_dir, "source.db").await;
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key) \
+             VALUES ('0xabc', 1, '100.5', NULL, 'k1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+
+        let report = check_database_file(&db_path).await.unwrap();
+        assert_eq!(report.source, CompatManifest::current());
+        assert!(!report.recompile_required);
+    }
+
+    #[tokio::test]
+    async fn test_missing_manifest_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir, "test.db").await;
+        sqlx::query("DELETE FROM instance_metadata")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = check_source_db(&pool).await.unwrap_err();
+        assert!(matches!(err, CompatError::MissingManifest));
+    }
+
+    #[tokio::test]
+    async fn test_non_canonical_decimal_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir, "test.db").await;
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key) \
+             VALUES ('0xabc', 1, '100.50', NULL, 'k1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        match check_source_db(&pool).await.unwrap_err() {
+            CompatError::NonCanonicalDecimal {
+                table,
+                column,
+                value,
+                expected,
+            } => {
+                assert_eq!((table.as_str(), column.as_str()), ("deposits", "amount"));
+                assert_eq!(value, "100.50");
+                assert_eq!(expected, "100.5");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_network_refuses_other_network() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir, "test.db").await;
+        assert_eq!(read_network(&pool).await.unwrap(), None);
+
+        claim_network(&pool, Network::Testnet).await.unwrap();
+        claim_network(&pool, Network::Testnet).await.unwrap();
+        assert_eq!(read_network(&pool).await.unwrap(), Some(Network::Testnet));
+
+        match claim_network(&pool, Network::Mainnet).await.unwrap_err() {
+            CompatError::NetworkMismatch {
+                recorded,
+                configured,
+            } => assert_eq!((recorded, configured), (Network::Testnet, Network::Mainnet)),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn test_version_rules() {
+        let current = CompatManifest::current();
+
+        let older_schema = CompatManifest {
+            schema_version: current.schema_version - 1,
+            ..current.clone()
+        };
+        assert!(older_schema.check_against_current().is_ok());
+
+        let newer_schema = CompatManifest {
+            schema_version: current.schema_version + 1,
+            ..current.clone()
+        };
+        assert!(matches!(
+            newer_schema.check_against_current(),
+            Err(CompatError::SchemaTooNew { .. })
+        ));
+
+        let other_keys = CompatManifest {
+            fill_key_version: current.fill_key_version + 1,
+            ..current.clone()
+        };
+        assert!(matches!(
+            other_keys.check_against_current(),
+            Err(CompatError::FillKeyVersion { .. })
+        ));
+
+        let other_decimals = CompatManifest {
+            decimal_format_version: current.decimal_format_version + 1,
+            ..current.clone()
+        };
+        assert!(matches!(
+            other_decimals.check_against_current(),
+            Err(CompatError::DecimalFormatVersion { .. })
+        ));
+
+        let other_engine = CompatManifest {
+            engine_version: current.engine_version + 1,
+            ..current.clone()
+        };
+        assert!(other_engine.check_against_current().unwrap().recompile_required);
+    }
+}
@@ -0,0 +1,603 @@
+//! Scheduled SQLite upkeep: WAL checkpoints, `ANALYZE`, and `VACUUM INTO` backups.
+//!
+//! SQLite's automatic checkpoints are PASSIVE and never shrink the `-wal` file, so a
+//! long-running server keeps a WAL as large as its busiest burst until it shuts down.
+//! [`Maintenance`] truncates it on a timer, refreshes planner statistics, optionally
+//! writes snapshot backups, and prunes rows past their [`RetentionPolicy`]. The same tasks
+//! can be triggered from the admin API.
+//!
+//! Snapshots are written with `VACUUM INTO`, which reads one consistent transaction, so
+//! ingestion keeps running while they are taken. [`restore_backup`] puts one back in place
+//! before [`init_db`](super::init_db) opens it.
+
+use super::compat::{check_database_file, CompatError, CompatReport};
+use super::repo::{Repository, WalCheckpoint};
+use crate::config::ConfigHandle;
+use crate::domain::TimeMs;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// File name prefix of backup snapshots; only files with it are pruned.
+const BACKUP_PREFIX: &str = "hypesilico-";
+const BACKUP_SUFFIX: &str = ".db";
+const DAY_MS: i64 = 86_400_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceConfig {
+    /// Milliseconds between `wal_checkpoint(TRUNCATE)` runs; `0` disables.
+    pub checkpoint_interval_ms: u64,
+    /// Milliseconds between `ANALYZE` runs; `0` disables.
+    pub analyze_interval_ms: u64,
+    /// Directory `VACUUM INTO` snapshots are written to; backups are off when unset.
+    pub backup_dir: Option<PathBuf>,
+    /// Milliseconds between scheduled backups; `0` leaves them to the admin endpoint.
+    pub backup_interval_ms: u64,
+    /// Snapshots kept in `backup_dir`; older ones are deleted after each backup.
+    pub backup_keep: usize,
+    /// Snapshot copied into place at startup, before the database is opened.
+    pub restore_from: Option<PathBuf>,
+    /// Milliseconds between pruning runs; `0` disables. Nothing is pruned without a
+    /// retention period.
+    pub prune_interval_ms: u64,
+    pub retention: RetentionPolicy,
+}
+
+/// How long prunable tables keep their rows; `None` keeps them forever.
+///
+/// Raw fills, fill effects, lifecycles, and deposits are never pruned: PnL and equity are
+/// summed from them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// The latest snapshot of each user and coin before the cutoff is always kept.
+    pub position_snapshots_days: Option<u32>,
+    pub equity_snapshots_days: Option<u32>,
+    pub raw_payloads_days: Option<u32>,
+    /// SQLite file pruned rows are copied to before they are deleted.
+    pub archive_path: Option<PathBuf>,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.position_snapshots_days.is_some()
+            || self.equity_snapshots_days.is_some()
+            || self.raw_payloads_days.is_some()
+    }
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            checkpoint_interval_ms: 300_000,
+            analyze_interval_ms: 86_400_000,
+            backup_dir: None,
+            backup_interval_ms: 86_400_000,
+            backup_keep: 7,
+            restore_from: None,
+            prune_interval_ms: 86_400_000,
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    /// How often `task` runs on its own, or `None` when it is not scheduled.
+    pub fn interval(&self, task: MaintenanceTask) -> Option<Duration> {
+        let ms = match task {
+            MaintenanceTask::Checkpoint => self.checkpoint_interval_ms,
+            MaintenanceTask::Analyze => self.analyze_interval_ms,
+            MaintenanceTask::Backup if self.backup_dir.is_some() => self.backup_interval_ms,
+            MaintenanceTask::Backup => 0,
+            MaintenanceTask::Prune if self.retention.is_enabled() => self.prune_interval_ms,
+            MaintenanceTask::Prune => 0,
+        };
+        (ms > 0).then(|| Duration::from_millis(ms))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaintenanceTask {
+    Checkpoint,
+    Analyze,
+    Backup,
+    Prune,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 4] = [
+        MaintenanceTask::Checkpoint,
+        MaintenanceTask::Analyze,
+        MaintenanceTask::Backup,
+        MaintenanceTask::Prune,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceTask::Checkpoint => "checkpoint",
+            MaintenanceTask::Analyze => "analyze",
+            MaintenanceTask::Backup => "backup",
+            MaintenanceTask::Prune => "prune",
+        }
+    }
+}
+
+impl FromStr for MaintenanceTask {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "checkpoint" => Ok(MaintenanceTask::Checkpoint),
+            "analyze" => Ok(MaintenanceTask::Analyze),
+            "backup" => Ok(MaintenanceTask::Backup),
+            "prune" => Ok(MaintenanceTask::Prune),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MaintenanceError {
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("backup I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("backups are disabled; set DB_BACKUP_DIR")]
+    BackupDisabled,
+}
+
+#[derive(Debug, Error)]
+pub enum RestoreError {
+    #[error(
+        "{0} already exists; move the database and its -wal file aside before restoring, \
+         or unset DB_RESTORE_FROM"
+    )]
+    TargetExists(String),
+    #[error("backup failed its integrity check: {0}")]
+    Corrupt(String),
+    #[error(transparent)]
+    Compat(#[from] CompatError),
+    #[error("restore I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A snapshot written by [`Maintenance::backup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Older snapshots deleted to stay within `backup_keep`.
+    pub removed: Vec<PathBuf>,
+}
+
+/// Rows deleted by [`Maintenance::prune`], per table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub position_snapshots: u64,
+    pub equity_snapshots: u64,
+    pub raw_payloads: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceOutcome {
+    Checkpoint(WalCheckpoint),
+    Analyze,
+    Backup(BackupReport),
+    Prune(PruneReport),
+}
+
+/// Runs maintenance tasks against one database, on demand or on a schedule.
+#[derive(Clone)]
+pub struct Maintenance {
+    repo: Arc<Repository>,
+    config: MaintenanceConfig,
+}
+
+impl Maintenance {
+    pub fn new(repo: Arc<Repository>, config: MaintenanceConfig) -> Self {
+        Self { repo, config }
+    }
+
+    pub async fn run(&self, task: MaintenanceTask) -> Result<MaintenanceOutcome, MaintenanceError> {
+        Ok(match task {
+            MaintenanceTask::Checkpoint => MaintenanceOutcome::Checkpoint(self.repo.wal_checkpoint().await?),
+            MaintenanceTask::Analyze => {
+                self.repo.analyze().await?;
+                MaintenanceOutcome::Analyze
+            }
+            MaintenanceTask::Backup => MaintenanceOutcome::Backup(self.backup().await?),
+            MaintenanceTask::Prune => MaintenanceOutcome::Prune(self.prune_at(TimeMs::now()).await?),
+        })
+    }
+
+    /// Delete rows older than their retention period as of `now`, archiving them first
+    /// when an archive path is configured.
+    pub async fn prune_at(&self, now: TimeMs) -> Result<PruneReport, MaintenanceError> {
+        let retention = &self.config.retention;
+        let archive = retention
+            .archive_path
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned());
+        let archive = archive.as_deref();
+        let cutoff =
+            |days: u32| TimeMs::new(now.as_ms().saturating_sub(i64::from(days) * DAY_MS));
+
+        let mut report = PruneReport::default();
+        if let Some(days) = retention.position_snapshots_days {
+            report.position_snapshots =
+                self.repo.prune_position_snapshots(cutoff(days), archive).await?;
+        }
+        if let Some(days) = retention.equity_snapshots_days {
+            report.equity_snapshots =
+                self.repo.prune_equity_snapshots(cutoff(days), archive).await?;
+        }
+        if let Some(days) = retention.raw_payloads_days {
+            report.raw_payloads = self.repo.prune_raw_payloads(cutoff(days), archive).await?;
+        }
+        Ok(report)
+    }
+
+    /// Snapshot the database into `backup_dir`, then prune all but the newest
+    /// `backup_keep` snapshots.
+    ///
+    /// # Errors
+    /// Returns [`MaintenanceError::BackupDisabled`] when no backup directory is configured.
+    pub async fn backup(&self) -> Result<BackupReport, MaintenanceError> {
+        let dir = self
+            .config
+            .backup_dir
+            .as_ref()
+            .ok_or(MaintenanceError::BackupDisabled)?;
+        tokio::fs::create_dir_all(dir).await?;
+
+        // Millisecond timestamps sort in creation order and keep names unique.
+        let name = format!(
+            "{}{}{}",
+            BACKUP_PREFIX,
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%3fZ"),
+            BACKUP_SUFFIX
+        );
+        let path = dir.join(name);
+        self.repo.vacuum_into(&path.to_string_lossy()).await?;
+        let bytes = tokio::fs::metadata(&path).await?.len();
+
+        let mut snapshots = Vec::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX) {
+                snapshots.push(entry.path());
+            }
+        }
+        snapshots.sort();
+        let excess = snapshots.len().saturating_sub(self.config.backup_keep.max(1));
+        let mut removed = Vec::new();
+        for old in snapshots.into_iter().take(excess) {
+            tokio::fs::remove_file(&old).await?;
+            removed.push(old);
+        }
+
+        Ok(BackupReport {
+            path,
+            bytes,
+            removed,
+        })
+    }
+
+    /// Start a background loop for every task; abort the handles to stop.
+    ///
+    /// Each loop runs its task at the interval in the current `config` and idles while it
+    /// has none. The first run waits one full interval, and a reload that changes the
+    /// interval starts the wait over; other settings are read afresh for every run. Nothing
+    /// is started when read-only.
+    pub fn spawn_scheduled(repo: Arc<Repository>, config: ConfigHandle) -> Vec<JoinHandle<()>> {
+        if config.load().read_only {
+            return Vec::new();
+        }
+        MaintenanceTask::ALL
+            .into_iter()
+            .map(|task| {
+                let repo = repo.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    let mut reloads = config.subscribe();
+                    let mut period = config.load().maintenance.interval(task);
+                    let mut next_run = period.map(|p| Instant::now() + p);
+                    loop {
+                        let due = async {
+                            match next_run {
+                                Some(at) => tokio::time::sleep_until(at).await,
+                                None => std::future::pending().await,
+                            }
+                        };
+                        tokio::select! {
+                            _ = due => {
+                                let this = Maintenance::new(repo.clone(), config.load().maintenance.clone());
+                                log_outcome(task, this.run(task).await);
+                                next_run = period.map(|p| Instant::now() + p);
+                            }
+                            // `config` keeps the sender alive, so this only resolves on reloads.
+                            _ = reloads.changed() => {
+                                let interval = config.load().maintenance.interval(task);
+                                if interval != period {
+                                    period = interval;
+                                    next_run = period.map(|p| Instant::now() + p);
+                                }
+                            }
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+fn log_outcome(task: MaintenanceTask, result: Result<MaintenanceOutcome, MaintenanceError>) {
+    match result {
+        Ok(MaintenanceOutcome::Checkpoint(c)) if c.busy => tracing::warn!(
+            log_frames = c.log_frames,
+            checkpointed_frames = c.checkpointed_frames,
+            "WAL checkpoint could not finish; a reader is holding an old snapshot"
+        ),
+        Ok(MaintenanceOutcome::Backup(b)) => tracing::info!(
+            path = %b.path.display(),
+            bytes = b.bytes,
+            removed = b.removed.len(),
+            "Wrote database backup"
+        ),
+        Ok(MaintenanceOutcome::Prune(p)) => tracing::info!(
+            position_snapshots = p.position_snapshots,
+            equity_snapshots = p.equity_snapshots,
+            raw_payloads = p.raw_payloads,
+            "Pruned rows past their retention"
+        ),
+        Ok(_) => tracing::debug!(task = task.as_str(), "Database maintenance done"),
+        Err(e) => tracing::warn!(
+            task = task.as_str(),
+            "Database maintenance failed: {}",
+            e
+        ),
+    }
+}
+
+/// Copy the snapshot at `backup_path` into place as the database at `db_path`.
+///
+/// The snapshot must pass `PRAGMA quick_check` and the [compatibility
+/// check](super::compat::check_source_db); an older schema is then migrated by
+/// [`init_db`](super::init_db) as usual. The target must not exist, and neither may its
+/// `-wal` file, which SQLite would otherwise replay over the restored pages. The copy is
+/// written next to the target and renamed, so a failed restore leaves no partial database.
+///
+/// # Errors
+/// Returns an error if the snapshot is unreadable, corrupt, or incompatible, or if the target
+/// already exists.
+pub async fn restore_backup(backup_path: &str, db_path: &str) -> Result<CompatReport, RestoreError> {
+    for existing in [db_path.to_string(), format!("{}-wal", db_path)] {
+        if tokio::fs::try_exists(&existing).await? {
+            return Err(RestoreError::TargetExists(existing));
+        }
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=ro", backup_path))
+        .await
+        .map_err(CompatError::from)?;
+    let check: Result<Vec<(String,)>, sqlx::Error> =
+        sqlx::query_as("PRAGMA quick_check").fetch_all(&pool).await;
+    pool.close().await;
+    let problems: Vec<String> = check
+        .map_err(CompatError::from)?
+        .into_iter()
+        .map(|(line,)| line)
+        .filter(|line| line != "ok")
+        .collect();
+    if !problems.is_empty() {
+        return Err(RestoreError::Corrupt(problems.join("; ")));
+    }
+    let report = check_database_file(backup_path).await?;
+
+    if let Some(parent) = Path::new(db_path).parent() {
+        if !parent.as_os_str().is_empty() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+    }
+    let staging = format!("{}.restoring", db_path);
+    tokio::fs::copy(backup_path, &staging).await?;
+    tokio::fs::File::open(&staging).await?.sync_all().await?;
+    tokio::fs::rename(&staging, db_path).await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use tempfile::TempDir;
+
+    async fn setup(temp_dir: &TempDir, config: MaintenanceConfig) -> Maintenance {
+        let db_path = temp_dir.path().join("test.db").to_string_lossy().to_string();
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        Maintenance::new(Arc::new(Repository::new(pool)), config)
+    }
+
+    #[test]
+    fn test_task_parsing_and_intervals() {
+        assert_eq!(MaintenanceTask::from_str(" Backup "), Ok(MaintenanceTask::Backup));
+        assert!(MaintenanceTask::from_str("vacuum").is_err());
+        // Pruning needs a retention period even with an interval.
+        assert_eq!(MaintenanceConfig::default().interval(MaintenanceTask::Prune), None);
+
+        let config = MaintenanceConfig {
+            analyze_interval_ms: 0,
+            ..MaintenanceConfig::default()
+        };
+        assert_eq!(
+            config.interval(MaintenanceTask::Checkpoint),
+            Some(Duration::from_millis(300_000))
+        );
+        assert_eq!(config.interval(MaintenanceTask::Analyze), None);
+        // Backups need a directory even with an interval.
+        assert_eq!(config.interval(MaintenanceTask::Backup), None);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_analyze_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let maintenance = setup(&temp_dir, MaintenanceConfig::default()).await;
+
+        match maintenance.run(MaintenanceTask::Checkpoint).await.unwrap() {
+            MaintenanceOutcome::Checkpoint(c) => assert!(!c.busy),
+            other => panic!("unexpected outcome {:?}", other),
+        }
+        assert_eq!(
+            maintenance.run(MaintenanceTask::Analyze).await.unwrap(),
+            MaintenanceOutcome::Analyze
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backup_writes_snapshot_and_prunes_old_ones() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("backups");
+        let maintenance = setup(
+            &temp_dir,
+            MaintenanceConfig {
+                backup_dir: Some(backup_dir.clone()),
+                backup_keep: 2,
+                ..MaintenanceConfig::default()
+            },
+        )
+        .await;
+
+        std::fs::create_dir_all(&backup_dir).unwrap();
+        std::fs::write(backup_dir.join("hypesilico-19700101T000000000Z.db"), b"old").unwrap();
+        std::fs::write(backup_dir.join("hypesilico-19700102T000000000Z.db"), b"old").unwrap();
+        std::fs::write(backup_dir.join("notes.txt"), b"keep me").unwrap();
+
+        let report = maintenance.backup().await.unwrap();
+        assert!(report.bytes > 0);
+        assert_eq!(
+            report.removed,
+            vec![backup_dir.join("hypesilico-19700101T000000000Z.db")]
+        );
+
+        // The snapshot is a usable database.
+        let copy = init_db(&report.path.to_string_lossy()).await.unwrap();
+        sqlx::query("SELECT COUNT(*) FROM deposits").fetch_one(&copy).await.unwrap();
+        assert!(backup_dir.join("notes.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_backup_without_dir_is_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let maintenance = setup(&temp_dir, MaintenanceConfig::default()).await;
+        assert!(matches!(
+            maintenance.backup().await,
+            Err(MaintenanceError::BackupDisabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prune_applies_each_retention_period() {
+        let temp_dir = TempDir::new().unwrap();
+        let maintenance = setup(
+            &temp_dir,
+            MaintenanceConfig {
+                retention: RetentionPolicy {
+                    equity_snapshots_days: Some(1),
+                    raw_payloads_days: Some(30),
+                    ..RetentionPolicy::default()
+                },
+                ..MaintenanceConfig::default()
+            },
+        )
+        .await;
+        assert!(maintenance.config.interval(MaintenanceTask::Prune).is_some());
+
+        let now = TimeMs::new(100 * DAY_MS);
+        let user = crate::domain::Address::new("0xabc".to_string());
+        let repo = &maintenance.repo;
+        for days_ago in [40, 2, 0] {
+            let time_ms = now.as_ms() - days_ago * DAY_MS;
+            repo.upsert_equity_snapshot(&user, TimeMs::new(time_ms), crate::domain::Decimal::zero())
+                .await
+                .unwrap();
+            repo.insert_raw_payload("userFills", Some("0xabc"), &days_ago.to_string(), b"[]", time_ms)
+                .await
+                .unwrap();
+        }
+
+        let report = maintenance.prune_at(now).await.unwrap();
+        assert_eq!(
+            report,
+            PruneReport {
+                position_snapshots: 0,
+                equity_snapshots: 2,
+                raw_payloads: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let maintenance = setup(
+            &temp_dir,
+            MaintenanceConfig {
+                backup_dir: Some(temp_dir.path().join("backups")),
+                ..MaintenanceConfig::default()
+            },
+        )
+        .await;
+        let live = init_db(&temp_dir.path().join("test.db").to_string_lossy())
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key) \
+             VALUES ('0xabc', 1, '100.5', NULL, 'k1')",
+        )
+        .execute(&live)
+        .await
+        .unwrap();
+        let snapshot = maintenance.backup().await.unwrap().path;
+        let snapshot = snapshot.to_string_lossy();
+
+        let target = temp_dir.path().join("restored").join("test.db");
+        let target = target.to_string_lossy();
+        let report = restore_backup(&snapshot, &target).await.unwrap();
+        assert!(!report.recompile_required);
+
+        let pool = init_db(&target).await.unwrap();
+        let (amount,): (String,) = sqlx::query_as("SELECT amount FROM deposits")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(amount, "100.5");
+        pool.close().await;
+
+        // A second restore over the now-live database is refused.
+        assert!(matches!(
+            restore_backup(&snapshot, &target).await,
+            Err(RestoreError::TargetExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup_rejects_non_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let bogus = temp_dir.path().join("bogus.db");
+        std::fs::write(&bogus, vec![7u8; 8192]).unwrap();
+        let target = temp_dir.path().join("test.db");
+
+        assert!(restore_backup(&bogus.to_string_lossy(), &target.to_string_lossy())
+            .await
+            .is_err());
+        assert!(!target.exists());
+    }
+}
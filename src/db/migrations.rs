@@ -1,11 +1,129 @@
 //! Database migrations and initialization.
 
-use sqlx::sqlite::{SqliteConnection, SqlitePool, SqlitePoolOptions};
+use crate::db::compat::CompatManifest;
+use crate::db::pool::DbPoolConfig;
+use crate::db::repo::fill_from_row;
+use crate::domain::{
+    Address, AttributionConfidence, AttributionMode, AttributionReason, Decimal, Deposit, Fill,
+    FillOrderingKey, LedgerKind, TimeMs, ToleranceTier,
+};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions};
 use std::path::Path;
+use std::str::FromStr;
 use tracing::info;
 
+/// Version of the compiler semantics that produced the derived tables.
+///
+/// Bump this whenever lifecycle, snapshot, or effect semantics change. On startup the
+/// orchestrator recompiles every (user, coin) pair whose `compile_state` was written by a
+/// different version, so rows from incompatible compilers are never mixed.
+pub const COMPILE_SCHEMA_VERSION: i64 = 4;
+
+/// Version of the database layout (tables and columns).
+///
+/// Bump whenever schema.sql or `ADDED_COLUMNS` changes. Recorded in `instance_metadata` so
+/// restores and imports can refuse databases written by a newer layout.
+pub const SCHEMA_VERSION: i64 = 30;
+
+/// First `SCHEMA_VERSION` that stores every address lowercase; older databases are
+/// rewritten by [`normalize_address_case`].
+const LOWERCASE_ADDRESSES_VERSION: i64 = 16;
+
+/// First `SCHEMA_VERSION` that stores `raw_fills.ordering_key`; older databases are
+/// backfilled by [`backfill_ordering_keys`].
+const ORDERING_KEY_VERSION: i64 = 22;
+
+/// First `SCHEMA_VERSION` with unique keys on `builder_log_fills`; rows stored before them
+/// are deduplicated by [`dedupe_builder_log_fills`].
+const BUILDER_LOG_FILL_KEYS_VERSION: i64 = 24;
+
+/// First `SCHEMA_VERSION` that scores attributions; older rows are scored by
+/// [`backfill_attribution_scores`].
+const ATTRIBUTION_SCORE_VERSION: i64 = 25;
+
+/// First `SCHEMA_VERSION` that indexes snapshots by `(user, coin, time_ms, seq)`; older
+/// databases drop the superseded `idx_snapshots_user_coin_time`.
+const SNAPSHOT_SEQ_INDEX_VERSION: i64 = 28;
+
+/// First `SCHEMA_VERSION` that scopes idempotency keys to the caller in
+/// `idempotency_requests`; older databases drop the unscoped `idempotency_keys`.
+const IDEMPOTENCY_CALLER_VERSION: i64 = 30;
+
+/// Address columns lowercased by [`normalize_address_case`]: `(table, column)`.
+///
+/// `raw_fills` and `deposits` are not listed because their keys embed the user and are
+/// recomputed row by row.
+const ADDRESS_COLUMNS: &[(&str, &str)] = &[
+    ("fill_attributions", "builder"),
+    ("equity_snapshots", "user"),
+    ("builder_logs_cache", "builder"),
+    ("builder_log_files", "builder"),
+    ("builder_log_rejects", "builder"),
+    ("invariant_violations", "user"),
+    ("reconciliation_issues", "user"),
+    ("account_groups", "address"),
+    ("raw_payloads", "user"),
+];
+
+/// Columns added after a table was first released.
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves existing tables untouched, so these are applied with
+/// `ALTER TABLE ... ADD COLUMN` when missing. Fresh databases already get them from schema.sql.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    (
+        "compile_state",
+        "compile_schema_version",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("fill_attributions", "tolerance_tier", "TEXT"),
+    ("fill_attributions", "matched_log_key", "TEXT"),
+    ("deposits", "amount_e8", "INTEGER"),
+    ("fill_effects", "closed_pnl_e8", "INTEGER"),
+    ("raw_fills", "crossed", "INTEGER"),
+    ("raw_fills", "fill_type", "TEXT NOT NULL DEFAULT 'trade'"),
+    ("position_lifecycles", "closed_by", "TEXT"),
+    ("raw_fills", "twap_id", "INTEGER"),
+    ("deposits", "kind", "TEXT"),
+    ("fill_effects", "builder_fee", "TEXT NOT NULL DEFAULT '0'"),
+    ("raw_fills", "ordering_key", "TEXT"),
+    ("fill_attributions", "score", "INTEGER"),
+    ("fill_attributions", "reason", "TEXT"),
+    ("fill_attributions", "match_time_delta_ms", "INTEGER"),
+    ("position_lifecycles", "strategy", "TEXT"),
+];
+
+/// Scaled integer mirrors of decimal TEXT columns: `(table, decimal column, scaled column)`.
+///
+/// When a scaled column is added to an existing database, it is backfilled from the decimal
+/// column so SQL sums can use it right away.
+const SCALED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("deposits", "amount", "amount_e8"),
+    ("fill_effects", "closed_pnl", "closed_pnl_e8"),
+];
+
 /// Initialize the SQLite database with schema and pragmas.
+///
+/// To start from a backup, put it in place with
+/// [`restore_backup`](super::maintenance::restore_backup) first; its schema is migrated here
+/// like any other database.
 pub async fn init_db(db_path: &str) -> Result<SqlitePool, sqlx::Error> {
+    let (pool, _) = init_db_with_pools(
+        db_path,
+        &DbPoolConfig {
+            read_connections: 0,
+            ..DbPoolConfig::default()
+        },
+    )
+    .await?;
+    Ok(pool)
+}
+
+/// Like [`init_db`] with `pools.write_connections` connections, also opening a read-only
+/// pool of `pools.read_connections` once the schema is migrated (none when that is `0`).
+pub async fn init_db_with_pools(
+    db_path: &str,
+    pools: &DbPoolConfig,
+) -> Result<(SqlitePool, Option<SqlitePool>), sqlx::Error> {
     if let Some(parent) = Path::new(db_path).parent() {
         if !parent.as_os_str().is_empty() {
             std::fs::create_dir_all(parent).ok();
@@ -13,21 +131,57 @@ pub async fn init_db(db_path: &str) -> Result<SqlitePool, sqlx::Error> {
     }
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
+        .max_connections(pools.write_connections)
         .after_connect(|conn, _meta| Box::pin(async move { configure_pragmas_conn(conn).await }))
         .connect(&format!("sqlite:{}?mode=rwc", db_path))
         .await?;
 
     run_migrations(&pool).await?;
 
+    let read_pool = match pools.read_connections {
+        0 => None,
+        n => Some(open_read_pool(db_path, n).await?),
+    };
+
     info!("Database initialized successfully at {}", db_path);
-    Ok(pool)
+    Ok((pool, read_pool))
+}
+
+/// Open an existing database without migrating it, for `READ_ONLY`: both pools, sized as
+/// in [`init_db_with_pools`], hold `SQLITE_OPEN_READONLY` connections, so nothing written
+/// through either reaches the file.
+pub async fn open_db_read_only(
+    db_path: &str,
+    pools: &DbPoolConfig,
+) -> Result<(SqlitePool, Option<SqlitePool>), sqlx::Error> {
+    let pool = open_read_pool(db_path, pools.write_connections).await?;
+    let read_pool = match pools.read_connections {
+        0 => None,
+        n => Some(open_read_pool(db_path, n).await?),
+    };
+    info!("Database opened read-only at {}", db_path);
+    Ok((pool, read_pool))
+}
+
+/// Open a pool of `SQLITE_OPEN_READONLY` connections to an existing, migrated database.
+///
+/// In WAL mode these read alongside the writer without blocking it, and see every
+/// transaction committed before their own read began.
+pub async fn open_read_pool(db_path: &str, max_connections: u32) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", db_path))?
+        .read_only(true)
+        .busy_timeout(std::time::Duration::from_secs(5));
+    SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
+        .await
 }
 
 /// Run all database migrations.
-async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+pub(crate) async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     info!("Running database migrations...");
     let schema_sql = include_str!("schema.sql");
+    let previous_version = recorded_schema_version(pool).await?;
 
     for statement in schema_sql.split(';') {
         let trimmed = statement.trim();
@@ -36,10 +190,438 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
         }
     }
 
+    for (table, column, definition) in ADDED_COLUMNS {
+        let added = add_column_if_missing(pool, table, column, definition).await?;
+        let scaled = SCALED_COLUMNS
+            .iter()
+            .find(|(t, _, scaled)| t == table && scaled == column);
+        if let (true, Some((_, decimal_column, _))) = (added, scaled) {
+            backfill_scaled_column(pool, table, decimal_column, column).await?;
+        }
+    }
+
+    if !matches!(previous_version, Some(v) if v >= LOWERCASE_ADDRESSES_VERSION) {
+        normalize_address_case(pool).await?;
+    }
+    if !matches!(previous_version, Some(v) if v >= ORDERING_KEY_VERSION) {
+        backfill_ordering_keys(pool).await?;
+    }
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_raw_fills_user_coin_ordering \
+         ON raw_fills(user, coin, ordering_key)",
+    )
+    .execute(pool)
+    .await?;
+    if !matches!(previous_version, Some(v) if v >= BUILDER_LOG_FILL_KEYS_VERSION) {
+        dedupe_builder_log_fills(pool).await?;
+    }
+    // Rows with a trade id are keyed by it; the rest by every matched field, as in
+    // `BuilderLogFill::log_key`.
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_builder_log_fills_tid \
+         ON builder_log_fills(builder, yyyymmdd, tid) WHERE tid IS NOT NULL",
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_builder_log_fills_fields \
+         ON builder_log_fills(builder, yyyymmdd, time_ms, user, coin, side, px, sz) \
+         WHERE tid IS NULL",
+    )
+    .execute(pool)
+    .await?;
+
+    if !matches!(previous_version, Some(v) if v >= ATTRIBUTION_SCORE_VERSION) {
+        backfill_attribution_scores(pool).await?;
+    }
+    if !matches!(previous_version, Some(v) if v >= IDEMPOTENCY_CALLER_VERSION) {
+        // Remembered responses only matter for a day; retries after the upgrade run again.
+        sqlx::query("DROP TABLE IF EXISTS idempotency_keys")
+            .execute(pool)
+            .await?;
+    }
+    if !matches!(previous_version, Some(v) if v >= SNAPSHOT_SEQ_INDEX_VERSION) {
+        // Superseded by idx_snapshots_user_coin_time_seq, which also orders by seq.
+        sqlx::query("DROP INDEX IF EXISTS idx_snapshots_user_coin_time")
+            .execute(pool)
+            .await?;
+    }
+
+    CompatManifest::current().write(pool).await?;
+
     info!("Migrations completed successfully");
     Ok(())
 }
 
+/// Add a column to an existing table unless it is already present.
+///
+/// Returns whether the column was added.
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool, sqlx::Error> {
+    let exists: Option<(String,)> =
+        sqlx::query_as(&format!("SELECT name FROM pragma_table_info('{}') WHERE name = ?", table))
+            .bind(column)
+            .fetch_optional(pool)
+            .await?;
+
+    if exists.is_none() {
+        info!("Adding column {}.{}", table, column);
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(exists.is_none())
+}
+
+/// Fill `scaled_column` from `decimal_column` for rows written before it existed.
+///
+/// Values that are not representable in scaled units stay NULL, which makes SQL sums over
+/// them fall back to decimal summation.
+async fn backfill_scaled_column(
+    pool: &SqlitePool,
+    table: &str,
+    decimal_column: &str,
+    scaled_column: &str,
+) -> Result<(), sqlx::Error> {
+    use sqlx::Row;
+
+    let rows = sqlx::query(&format!(
+        "SELECT id, {} FROM {} WHERE {} IS NULL",
+        decimal_column, table, scaled_column
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    let update = format!("UPDATE {} SET {} = ? WHERE id = ?", table, scaled_column);
+    let mut filled = 0usize;
+    for row in rows {
+        let id: i64 = row.get(0);
+        let value: String = row.get(1);
+        let Some(units) = Decimal::from_str_canonical(&value)
+            .ok()
+            .and_then(|d| d.to_scaled_units())
+        else {
+            continue;
+        };
+        sqlx::query(&update)
+            .bind(units)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        filled += 1;
+    }
+    tx.commit().await?;
+
+    info!("Backfilled {} rows of {}.{}", filled, table, scaled_column);
+    Ok(())
+}
+
+/// Compute `raw_fills.ordering_key` for every stored fill.
+///
+/// Runs once when upgrading from a database that ordered compiles by `fill_key` alone.
+/// `COMPILE_SCHEMA_VERSION` was bumped with it, so every pair's watermark is discarded by
+/// `Orchestrator::recompile_stale` rather than resumed in the new order.
+async fn backfill_ordering_keys(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    use sqlx::Row;
+
+    let rows = sqlx::query("SELECT id, time_ms, tid, oid, fill_key FROM raw_fills")
+        .fetch_all(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    for row in &rows {
+        let key = FillOrderingKey {
+            time_ms: row.get("time_ms"),
+            tid: row.get("tid"),
+            oid: row.get("oid"),
+            fill_key: row.get("fill_key"),
+        };
+        sqlx::query("UPDATE raw_fills SET ordering_key = ? WHERE id = ?")
+            .bind(key.to_sortable_string())
+            .bind(row.get::<i64, _>("id"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    info!("Backfilled ordering keys of {} fills", rows.len());
+    Ok(())
+}
+
+/// Keep the first of each set of `builder_log_fills` rows that the unique keys would merge.
+async fn dedupe_builder_log_fills(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM builder_log_fills
+        WHERE id NOT IN (
+            SELECT MIN(id) FROM builder_log_fills WHERE tid IS NOT NULL
+            GROUP BY builder, yyyymmdd, tid
+            UNION ALL
+            SELECT MIN(id) FROM builder_log_fills WHERE tid IS NULL
+            GROUP BY builder, yyyymmdd, time_ms, user, coin, side, px, sz
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        info!("Removed {} duplicate builder log rows", result.rows_affected());
+    }
+    Ok(())
+}
+
+/// Give attributions stored before scoring a reason and score from their mode and confidence.
+///
+/// The time difference of their log matches was not kept, so field matches score as the
+/// worst match their tier allows.
+async fn backfill_attribution_scores(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let groups: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(
+        "SELECT DISTINCT mode, confidence, attributed, tolerance_tier \
+         FROM fill_attributions WHERE reason IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut scored = 0;
+    for (mode, confidence, attributed, tier) in groups {
+        let reason = AttributionReason::infer(
+            AttributionMode::parse(&mode).unwrap_or(AttributionMode::Heuristic),
+            AttributionConfidence::parse(&confidence).unwrap_or(AttributionConfidence::Low),
+            attributed != 0,
+        );
+        let score = reason.score(
+            attributed != 0,
+            tier.as_deref().and_then(ToleranceTier::parse),
+            0,
+        );
+        let result = sqlx::query(
+            "UPDATE fill_attributions SET reason = ?, score = ? \
+             WHERE reason IS NULL AND mode = ? AND confidence = ? AND attributed = ? \
+             AND tolerance_tier IS ?",
+        )
+        .bind(reason.as_str())
+        .bind(i64::from(score))
+        .bind(&mode)
+        .bind(&confidence)
+        .bind(attributed)
+        .bind(&tier)
+        .execute(pool)
+        .await?;
+        scored += result.rows_affected();
+    }
+
+    if scored > 0 {
+        info!("Scored {} attributions stored before scoring", scored);
+    }
+    Ok(())
+}
+
+/// The `SCHEMA_VERSION` recorded by the build that last migrated the database, if any.
+async fn recorded_schema_version(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
+    let has_table: Option<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'instance_metadata'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if has_table.is_none() {
+        return Ok(None);
+    }
+
+    let value: Option<(String,)> =
+        sqlx::query_as("SELECT value FROM instance_metadata WHERE key = 'schema_version'")
+            .fetch_optional(pool)
+            .await?;
+    Ok(value.and_then(|(v,)| v.parse().ok()))
+}
+
+/// Lowercase every stored address, merging the duplicate identities that mixed-case input
+/// created before [`Address`] normalized it.
+///
+/// Fill and deposit keys embed the user, so mixed-case rows are rekeyed; a row whose new
+/// key already exists is a duplicate and is dropped. Derived tables of the affected
+/// (user, coin) pairs are cleared and the pairs are marked stale, so
+/// `Orchestrator::recompile_stale` rebuilds them on startup.
+async fn normalize_address_case(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    use sqlx::Row;
+
+    let mut tx = pool.begin().await?;
+    // Fill keys are rewritten in both raw_fills and fill_attributions.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await?;
+
+    let pairs = sqlx::query(
+        r#"
+        SELECT user, coin FROM raw_fills WHERE user != lower(user)
+        UNION
+        SELECT user, coin FROM compile_state WHERE user != lower(user)
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in &pairs {
+        let user: String = row.get("user");
+        let coin: String = row.get("coin");
+        sqlx::query(
+            r#"
+            DELETE FROM fill_effects WHERE lifecycle_id IN (
+                SELECT id FROM position_lifecycles WHERE user = ? AND coin = ?
+            )
+            "#,
+        )
+        .bind(&user)
+        .bind(&coin)
+        .execute(&mut *tx)
+        .await?;
+        for sql in [
+            "DELETE FROM position_snapshots WHERE user = ? AND coin = ?",
+            "DELETE FROM position_lifecycles WHERE user = ? AND coin = ?",
+            "DELETE FROM compile_state WHERE user = ? AND coin = ?",
+        ] {
+            sqlx::query(sql)
+                .bind(&user)
+                .bind(&coin)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO compile_state (user, coin, compile_schema_version) VALUES (?, ?, 0)
+            ON CONFLICT(user, coin) DO UPDATE SET compile_schema_version = 0
+            "#,
+        )
+        .bind(user.to_ascii_lowercase())
+        .bind(&coin)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let fills = sqlx::query("SELECT * FROM raw_fills WHERE user != lower(user)")
+        .fetch_all(&mut *tx)
+        .await?;
+    for row in &fills {
+        let id: i64 = row.get("id");
+        let Ok(fill) = fill_from_row(row) else {
+            // As for deposits below: its fill key cannot be recomputed, so it keeps the old
+            // one and is left for the quarantine sweep.
+            sqlx::query("UPDATE raw_fills SET user = lower(user) WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            continue;
+        };
+        let fill_key = Fill::compute_fill_key(
+            &fill.user,
+            &fill.coin,
+            fill.time_ms,
+            fill.side,
+            &fill.px,
+            &fill.sz,
+            &fill.fee,
+            &fill.closed_pnl,
+            fill.builder_fee.as_ref(),
+            fill.tid,
+            fill.oid,
+        );
+        sqlx::query("UPDATE OR IGNORE fill_attributions SET fill_key = ? WHERE fill_key = ?")
+            .bind(&fill_key)
+            .bind(&fill.fill_key)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE OR IGNORE raw_fills SET user = ?, fill_key = ? WHERE id = ?")
+            .bind(fill.user.as_str())
+            .bind(&fill_key)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let deposits = sqlx::query(
+        "SELECT id, user, time_ms, amount, tx_hash, kind FROM deposits WHERE user != lower(user)",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in &deposits {
+        let id: i64 = row.get("id");
+        let Ok(amount) = Decimal::from_str(&row.get::<String, _>("amount")) else {
+            // Its event key cannot be recomputed, so it keeps the old one rather than being
+            // merged away below; the quarantine sweep then sets it aside.
+            sqlx::query("UPDATE deposits SET user = lower(user) WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            continue;
+        };
+        let mut deposit = Deposit::new(
+            Address::new(row.get("user")),
+            TimeMs::new(row.get("time_ms")),
+            amount,
+            row.get("tx_hash"),
+        );
+        if let Some(kind) = row
+            .get::<Option<String>, _>("kind")
+            .and_then(|k| k.parse::<LedgerKind>().ok())
+        {
+            deposit = deposit.with_kind(kind);
+        }
+        sqlx::query("UPDATE OR IGNORE deposits SET user = ?, event_key = ? WHERE id = ?")
+            .bind(deposit.user.as_str())
+            .bind(&deposit.event_key)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Fills and deposits still mixed-case duplicate a rekeyed row, as do attributions
+    // still under an old fill key.
+    let mut merged = 0u64;
+    for sql in [
+        "DELETE FROM fill_attributions WHERE fill_key IN (
+            SELECT fill_key FROM raw_fills WHERE user != lower(user)
+        )",
+        "DELETE FROM raw_fills WHERE user != lower(user)",
+        "DELETE FROM deposits WHERE user != lower(user)",
+    ] {
+        merged += sqlx::query(sql).execute(&mut *tx).await?.rows_affected();
+    }
+    for (table, column) in ADDRESS_COLUMNS {
+        sqlx::query(&format!(
+            "UPDATE OR IGNORE {table} SET {column} = lower({column}) WHERE {column} != lower({column})"
+        ))
+        .execute(&mut *tx)
+        .await?;
+        merged += sqlx::query(&format!(
+            "DELETE FROM {table} WHERE {column} != lower({column})"
+        ))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+
+    tx.commit().await?;
+
+    if !pairs.is_empty() || merged > 0 {
+        info!(
+            pairs = pairs.len(),
+            fills = fills.len(),
+            deposits = deposits.len(),
+            merged,
+            "Normalized mixed-case addresses"
+        );
+    }
+    Ok(())
+}
+
 /// Configure SQLite pragmas for optimal performance and reliability.
 async fn configure_pragmas_conn(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
     use sqlx::Row;
@@ -91,6 +673,37 @@ mod tests {
         assert_eq!(result.0, 1);
     }
 
+    #[tokio::test]
+    async fn test_read_pool_sees_commits_and_rejects_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+        let (pool, read_pool) = init_db_with_pools(&db_path, &DbPoolConfig::default())
+            .await
+            .expect("init_db_with_pools failed");
+        let read_pool = read_pool.expect("read pool");
+        assert_eq!(read_pool.options().get_max_connections(), 8);
+
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key) \
+             VALUES ('0xabc', 1, '100.5', NULL, 'k1')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposits")
+            .fetch_one(&read_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let write = sqlx::query("DELETE FROM deposits").execute(&read_pool).await;
+        assert!(write.is_err());
+    }
+
     #[tokio::test]
     async fn test_migrations_create_tables() {
         let temp_dir = TempDir::new().unwrap();
@@ -132,6 +745,390 @@ mod tests {
         assert!(result.0 > 0);
     }
 
+    #[tokio::test]
+    async fn test_migrations_add_compile_schema_version_to_existing_table() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Simulate a database created before compile_schema_version existed.
+        let legacy = SqlitePoolOptions::new()
+            .connect(&format!("sqlite:{}?mode=rwc", db_path))
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE compile_state (user TEXT NOT NULL, coin TEXT NOT NULL, \
+             last_compiled_time_ms INTEGER, last_compiled_fill_key TEXT, \
+             compile_version INTEGER NOT NULL DEFAULT 1, PRIMARY KEY(user, coin))",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO compile_state (user, coin) VALUES ('0xabc', 'BTC')")
+            .execute(&legacy)
+            .await
+            .unwrap();
+        legacy.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        let result: (i64,) = sqlx::query_as("SELECT compile_schema_version FROM compile_state")
+            .fetch_one(&pool)
+            .await
+            .expect("query failed");
+        assert_eq!(result.0, 0, "legacy rows must be marked stale");
+    }
+
+    #[tokio::test]
+    async fn test_migrations_backfill_scaled_deposit_amounts() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Simulate a deposits table from before amount_e8 existed.
+        let legacy = SqlitePoolOptions::new()
+            .connect(&format!("sqlite:{}?mode=rwc", db_path))
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE deposits (id INTEGER PRIMARY KEY AUTOINCREMENT, user TEXT NOT NULL, \
+             time_ms INTEGER NOT NULL, amount TEXT NOT NULL, tx_hash TEXT, \
+             event_key TEXT NOT NULL UNIQUE)",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, event_key) \
+             VALUES ('0xabc', 1, '100.5', 'k1'), ('0xabc', 2, '0.000000001', 'k2')",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        legacy.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        let rows: Vec<(String, Option<i64>)> =
+            sqlx::query_as("SELECT event_key, amount_e8 FROM deposits ORDER BY event_key")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("k1".to_string(), Some(10_050_000_000)),
+                // Too precise for 1e-8 units; sums fall back to decimals.
+                ("k2".to_string(), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrations_merge_mixed_case_addresses() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+        let mixed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lower = mixed.to_ascii_lowercase();
+
+        // Rows written by a build that kept the address as given.
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        for (user, tid) in [(lower.as_str(), 1), (mixed, 1), (mixed, 2)] {
+            sqlx::query(
+                "INSERT INTO raw_fills (user, coin, time_ms, side, px, sz, fee, closed_pnl, tid, \
+                 fill_key, created_at) VALUES (?, 'BTC', ?, 'buy', '1', '1', '0', '0', ?, ?, 0)",
+            )
+            .bind(user)
+            .bind(tid)
+            .bind(tid)
+            .bind(format!("{}:BTC:tid:{}", user, tid))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO fill_attributions (fill_key, attributed, mode, confidence) \
+             VALUES (?, 1, 'heuristic', 'exact')",
+        )
+        .bind(format!("{}:BTC:tid:2", mixed))
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO compile_state (user, coin, compile_schema_version) VALUES (?, 'BTC', ?)")
+            .bind(mixed)
+            .bind(COMPILE_SCHEMA_VERSION)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO account_groups (name, address) VALUES ('desk', ?), ('desk', ?)")
+            .bind(mixed)
+            .bind(&lower)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO deposits (user, time_ms, amount, event_key) \
+             VALUES (?, 1000, 'garbage', 'corrupt')",
+        )
+        .bind(mixed)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO raw_fills (user, coin, time_ms, side, px, sz, fee, closed_pnl, tid, \
+             fill_key, created_at) VALUES (?, 'BTC', 1, 'buy', 'garbage', '1', '0', '0', 3, \
+             'corrupt', 0)",
+        )
+        .bind(mixed)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE instance_metadata SET value = '15' WHERE key = 'schema_version'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        // Rows whose keys cannot be recomputed are lowercased in place, not lost.
+        let deposits: Vec<(String, String)> =
+            sqlx::query_as("SELECT user, event_key FROM deposits")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(deposits, vec![(lower.clone(), "corrupt".to_string())]);
+
+        let fills: Vec<(String, String)> =
+            sqlx::query_as("SELECT user, fill_key FROM raw_fills ORDER BY fill_key")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            fills,
+            vec![
+                (lower.clone(), format!("{}:BTC:tid:1", lower)),
+                (lower.clone(), format!("{}:BTC:tid:2", lower)),
+                (lower.clone(), "corrupt".to_string()),
+            ]
+        );
+        let (attributed,): (String,) = sqlx::query_as("SELECT fill_key FROM fill_attributions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(attributed, format!("{}:BTC:tid:2", lower));
+        let stale: Vec<(String, i64)> =
+            sqlx::query_as("SELECT user, compile_schema_version FROM compile_state")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stale, vec![(lower.clone(), 0)]);
+        let members: Vec<(String,)> = sqlx::query_as("SELECT address FROM account_groups")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(members, vec![(lower.clone(),)]);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_drop_superseded_snapshot_index_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+        let create = "CREATE INDEX idx_snapshots_user_coin_time \
+                      ON position_snapshots(user, coin, time_ms)";
+        let index_exists = |pool: SqlitePool| async move {
+            sqlx::query_as::<_, (String,)>(
+                "SELECT name FROM sqlite_master WHERE name = 'idx_snapshots_user_coin_time'",
+            )
+            .fetch_optional(&pool)
+            .await
+            .unwrap()
+            .is_some()
+        };
+
+        // A database from before the seq index still has the old one.
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        sqlx::query(create).execute(&pool).await.unwrap();
+        sqlx::query("UPDATE instance_metadata SET value = '27' WHERE key = 'schema_version'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        assert!(!index_exists(pool.clone()).await);
+        pool.close().await;
+
+        // Later startups leave an index of that name alone.
+        let pool = SqlitePool::connect(&format!("sqlite:{}", db_path)).await.unwrap();
+        sqlx::query(create).execute(&pool).await.unwrap();
+        pool.close().await;
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        assert!(index_exists(pool).await);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_backfill_fill_ordering_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Fills written before ordering keys were stored.
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        for (time_ms, tid) in [(2000, 10), (1000, 9)] {
+            sqlx::query(
+                "INSERT INTO raw_fills (user, coin, time_ms, side, px, sz, fee, closed_pnl, tid, \
+                 fill_key, created_at) VALUES ('0xabc', 'BTC', ?, 'buy', '1', '1', '0', '0', ?, ?, 0)",
+            )
+            .bind(time_ms)
+            .bind(tid)
+            .bind(format!("0xabc:BTC:tid:{}", tid))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE instance_metadata SET value = '21' WHERE key = 'schema_version'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        // `tid:10` sorts before `tid:9` as a fill key but after it in compile order.
+        let ordered: Vec<(i64,)> =
+            sqlx::query_as("SELECT tid FROM raw_fills ORDER BY ordering_key ASC")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(ordered, vec![(9,), (10,)]);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_dedupe_builder_log_fills() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Rows stored twice before the unique keys existed.
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        for index in ["idx_builder_log_fills_tid", "idx_builder_log_fills_fields"] {
+            sqlx::query(&format!("DROP INDEX {}", index))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for tid in [Some(7), Some(7), None, None] {
+            sqlx::query(
+                "INSERT INTO builder_log_fills (builder, yyyymmdd, time_ms, user, coin, side, \
+                 px, sz, tid, oid) VALUES ('0xb', '20240101', 1000, '0xabc', 'BTC', 'buy', '1', \
+                 '1', ?, NULL)",
+            )
+            .bind(tid)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE instance_metadata SET value = '23' WHERE key = 'schema_version'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        let tids: Vec<(Option<i64>,)> =
+            sqlx::query_as("SELECT tid FROM builder_log_fills ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tids, vec![(Some(7),), (None,)]);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_score_existing_attributions() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir
+            .path()
+            .join("test.db")
+            .to_string_lossy()
+            .to_string();
+
+        // Attributions stored before they were scored.
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        let rows = [
+            ("k1", 1, "logs", "exact", None),
+            ("k2", 1, "logs", "fuzzy", Some("absolute")),
+            ("k3", 0, "logs", "exact", None),
+            ("k4", 1, "heuristic", "low", None),
+        ];
+        for (key, attributed, mode, confidence, tier) in rows {
+            sqlx::query(
+                "INSERT INTO raw_fills (user, coin, time_ms, side, px, sz, fee, closed_pnl, \
+                 fill_key, created_at) VALUES ('0xabc', 'BTC', 1000, 'buy', '1', '1', '0', '0', ?, 0)",
+            )
+            .bind(key)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO fill_attributions (fill_key, attributed, mode, confidence, \
+                 tolerance_tier) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(key)
+            .bind(attributed)
+            .bind(mode)
+            .bind(confidence)
+            .bind(tier)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE instance_metadata SET value = '24' WHERE key = 'schema_version'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let pool = init_db(&db_path).await.expect("init_db failed");
+
+        let scored: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT fill_key, reason, score FROM fill_attributions ORDER BY fill_key",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            scored,
+            vec![
+                ("k1".to_string(), "tid".to_string(), 100),
+                ("k2".to_string(), "fields".to_string(), 70),
+                ("k3".to_string(), "no_log_match".to_string(), 0),
+                ("k4".to_string(), "builder_fee".to_string(), 30),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_pragmas_configured() {
         let temp_dir = TempDir::new().unwrap();
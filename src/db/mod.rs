@@ -3,10 +3,28 @@
 //! This module provides:
 //! - Database initialization and migrations
 //! - SQLite pragma configuration
-//! - Repository layer for database operations
+//! - Repository layer for database operations, with an optional read-only pool
+//! - Scheduled maintenance: WAL checkpoints, `ANALYZE`, and snapshot backups
+//! - Compatibility checks for databases written by other versions
+//! - A startup self-check of the file's integrity, schema, and versions
 
+pub mod compat;
+pub mod maintenance;
 pub mod migrations;
+pub mod pool;
 pub mod repo;
+pub mod self_check;
 
-pub use migrations::init_db;
-pub use repo::Repository;
+pub use compat::{CompatError, CompatManifest, CompatReport};
+pub use maintenance::{
+    restore_backup, Maintenance, MaintenanceConfig, MaintenanceTask, RestoreError,
+};
+pub use migrations::{
+    init_db, init_db_with_pools, open_db_read_only, open_read_pool, COMPILE_SCHEMA_VERSION,
+    SCHEMA_VERSION,
+};
+pub use pool::DbPoolConfig;
+pub use repo::{IdempotencyKey, IdempotencyRecord, Repository, RepositoryPoolStats, WalCheckpoint};
+pub use self_check::{
+    check_manifest, check_read_only_manifest, verify_database, IntegrityCheck, SelfCheckError,
+};
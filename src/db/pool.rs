@@ -0,0 +1,158 @@
+//! Connection pools that record how long callers wait for a connection.
+//!
+//! SQLite serializes writers, so ingestion holding the write pool can stall every query
+//! sharing it. [`Repository`](super::Repository) routes reads through a separate pool of
+//! read-only connections when one is configured, and both pools are wrapped in
+//! [`MeteredPool`] so their contention shows up in `/v1/admin/metrics`.
+
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{Sqlite, SqlitePool};
+use sqlx::Transaction;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Connection counts of the write and read pools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbPoolConfig {
+    /// Read-write connections; SQLite runs one write at a time, so a few suffice.
+    pub write_connections: u32,
+    /// Read-only connections for queries; `0` sends reads through the write pool.
+    pub read_connections: u32,
+}
+
+impl Default for DbPoolConfig {
+    fn default() -> Self {
+        Self {
+            write_connections: 5,
+            read_connections: 8,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PoolMetrics {
+    acquired: AtomicU64,
+    failed: AtomicU64,
+    wait_us_total: AtomicU64,
+    wait_us_max: AtomicU64,
+}
+
+/// Point-in-time view of a [`MeteredPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    pub max_connections: u32,
+    /// Open connections, idle or in use.
+    pub size: u32,
+    pub idle: usize,
+    /// Successful acquisitions since startup.
+    pub acquired: u64,
+    /// Acquisitions that failed, e.g. timed out.
+    pub failed: u64,
+    pub wait_us_total: u64,
+    pub wait_us_max: u64,
+}
+
+impl PoolStats {
+    /// Mean wait per successful acquisition in microseconds.
+    pub fn wait_us_avg(&self) -> u64 {
+        self.wait_us_total.checked_div(self.acquired).unwrap_or(0)
+    }
+}
+
+/// A [`SqlitePool`] whose acquisitions are timed. Clones share the pool and its counters.
+#[derive(Debug, Clone)]
+pub struct MeteredPool {
+    pool: SqlitePool,
+    metrics: Arc<PoolMetrics>,
+}
+
+impl MeteredPool {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// The underlying pool, for callers that cannot hold a single connection, such as
+    /// streams. Acquisitions through it are not timed.
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub async fn acquire(&self) -> Result<PoolConnection<Sqlite>, sqlx::Error> {
+        let started = Instant::now();
+        let conn = self.pool.acquire().await;
+        let waited = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+        match &conn {
+            Ok(_) => {
+                self.metrics.acquired.fetch_add(1, Ordering::Relaxed);
+                self.metrics.wait_us_total.fetch_add(waited, Ordering::Relaxed);
+                self.metrics.wait_us_max.fetch_max(waited, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.metrics.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        conn
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static, Sqlite>, sqlx::Error> {
+        Transaction::begin(self.acquire().await?).await
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            max_connections: self.pool.options().get_max_connections(),
+            size: self.pool.size(),
+            idle: self.pool.num_idle(),
+            acquired: self.metrics.acquired.load(Ordering::Relaxed),
+            failed: self.metrics.failed.load(Ordering::Relaxed),
+            wait_us_total: self.metrics.wait_us_total.load(Ordering::Relaxed),
+            wait_us_max: self.metrics.wait_us_max.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether both handles wrap the same pool.
+    pub fn shares_pool_with(&self, other: &MeteredPool) -> bool {
+        Arc::ptr_eq(&self.metrics, &other.metrics)
+    }
+
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_acquire_records_waits() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let metered = MeteredPool::new(pool);
+
+        let held = metered.acquire().await.unwrap();
+        let waiter = {
+            let metered = metered.clone();
+            tokio::spawn(async move { metered.acquire().await.map(drop) })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        waiter.await.unwrap().unwrap();
+
+        let stats = metered.stats();
+        assert_eq!(stats.acquired, 2);
+        assert_eq!(stats.failed, 0);
+        assert_eq!(stats.max_connections, 1);
+        assert!(stats.wait_us_max >= 15_000);
+        assert!(stats.wait_us_avg() >= stats.wait_us_max / 2);
+    }
+}
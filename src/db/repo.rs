@@ -1,12 +1,23 @@
 //! Repository layer for database operations.
 
-use crate::domain::{Address, Attribution, AttributionConfidence, AttributionMode, Coin, Decimal, Deposit, Fill, Side, TimeMs};
-use crate::engine::{Effect, EffectType, Lifecycle, Snapshot};
-use sqlx::sqlite::SqlitePool;
-use sqlx::Row;
-use std::collections::HashMap;
+use crate::config::Network;
+use crate::db::compat::{CompatError, CompatManifest};
+use crate::db::pool::{MeteredPool, PoolStats};
+use crate::db::migrations::COMPILE_SCHEMA_VERSION;
+use crate::domain::{Address, Attribution, AttributionConfidence, AttributionMode, AttributionReason, BuilderLogFill, Coin, CoinMeta, Decimal, Deposit, Fill, FillOrderingKey, FillType, LedgerKind, MarkPrice, MarkSeries, Side, TimeMs, ToleranceTier};
+use crate::engine::{
+    assign_strategy, CoinAdjustment, Effect, EffectType, HeldPosition, InvariantViolation,
+    Lifecycle, LifecycleFacts, ReconciliationIssue, Snapshot, StrategyRule,
+};
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use sqlx::sqlite::{Sqlite, SqliteConnection, SqlitePool, SqliteRow};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, QueryBuilder, Row};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::str::FromStr;
-use tracing::warn;
+use thiserror::Error;
+use tracing::debug;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PositionSnapshotRow {
@@ -19,11 +30,276 @@ pub struct PositionSnapshotRow {
     pub lifecycle_tainted: bool,
 }
 
+impl PositionSnapshotRow {
+    pub fn key(&self) -> SnapshotKey {
+        SnapshotKey {
+            time_ms: self.time_ms,
+            seq: self.seq,
+            coin: self.coin.clone(),
+            lifecycle_id: self.lifecycle_id,
+        }
+    }
+}
+
+/// Sort key of a user's position snapshots: `(time_ms, seq, coin, lifecycle_id)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotKey {
+    pub time_ms: TimeMs,
+    pub seq: i32,
+    pub coin: Coin,
+    pub lifecycle_id: i64,
+}
+
+/// Latest snapshot of a lifecycle that has not been closed yet, now or at a queried time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPositionRow {
+    pub coin: Coin,
+    pub lifecycle_id: i64,
+    pub start_time_ms: TimeMs,
+    pub last_update_ms: TimeMs,
+    pub net_size: String,
+    pub avg_entry_px: String,
+    pub lifecycle_tainted: bool,
+}
+
+/// A fill in a window together with its stored attribution, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributionAuditRow {
+    pub fill_key: String,
+    pub time_ms: TimeMs,
+    pub coin: Coin,
+    pub side: String,
+    pub px: String,
+    pub sz: String,
+    pub builder_fee: Option<String>,
+    pub tid: Option<i64>,
+    pub attribution: Option<Attribution>,
+}
+
+/// Cached daily builder log file as downloaded (LZ4-compressed CSV).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderLogFileRow {
+    pub builder: String,
+    pub yyyymmdd: String,
+    pub sha256: String,
+    pub etag: Option<String>,
+    pub row_count: i64,
+    pub fetched_at_ms: i64,
+    pub content: Vec<u8>,
+}
+
+/// A builder log CSV row that failed to parse and was skipped (dead letter).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderLogRejectRow {
+    /// 1-based line number in the decompressed CSV.
+    pub line: i64,
+    /// Byte offset of the start of the row in the decompressed CSV.
+    pub byte_offset: i64,
+    pub reason: String,
+    /// The row as read, lossily decoded as UTF-8.
+    pub raw: String,
+}
+
+/// Per-day builder log ingest status, combining the file cache and dead letters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderLogDayStatus {
+    pub yyyymmdd: String,
+    /// Whether the day's file is in `builder_log_files` (completed days only).
+    pub cached: bool,
+    pub row_count: Option<i64>,
+    pub sha256: Option<String>,
+    pub fetched_at_ms: Option<i64>,
+    pub rejects: Vec<BuilderLogRejectRow>,
+}
+
+/// A recorded compile invariant violation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolationRow {
+    pub id: i64,
+    pub user: Address,
+    pub coin: Coin,
+    pub invariant: String,
+    pub fill_key: Option<String>,
+    pub lifecycle_id: Option<i64>,
+    pub detail: String,
+    pub recorded_at_ms: i64,
+}
+
+/// A recorded PnL reconciliation issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciliationIssueRow {
+    pub id: i64,
+    pub user: Address,
+    pub coin: Coin,
+    pub lifecycle_id: i64,
+    pub start_time_ms: i64,
+    pub reported_pnl: Decimal,
+    pub recomputed_pnl: Decimal,
+    pub difference: Decimal,
+    pub recorded_at_ms: i64,
+}
+
+/// A row moved to `quarantined_rows` by [`Repository::quarantine_corrupt_rows`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedRow {
+    pub id: i64,
+    pub source_table: String,
+    /// `id` of the row in `source_table`.
+    pub source_id: i64,
+    pub user: Option<Address>,
+    pub coin: Option<Coin>,
+    /// First column that did not decode, and its stored value.
+    pub column: String,
+    pub value: Option<String>,
+    pub reason: String,
+    /// The original row as a JSON object.
+    pub row: String,
+    pub quarantined_at_ms: i64,
+}
+
+/// Rows moved by one [`Repository::quarantine_corrupt_rows`] sweep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuarantineReport {
+    /// Rows moved per source table; tables without corrupt rows are omitted.
+    pub moved: BTreeMap<String, u64>,
+    /// Pairs whose derived rows were reset so the next compile rebuilds them.
+    pub reset_pairs: Vec<(Address, Coin)>,
+}
+
+impl QuarantineReport {
+    pub fn total(&self) -> u64 {
+        self.moved.values().sum()
+    }
+}
+
+/// Tables swept by [`Repository::quarantine_corrupt_rows`]: `(table, decimal columns,
+/// user expression, coin expression)`, the expressions evaluated against the row as `t`.
+///
+/// Only decimal TEXT columns are checked; the other columns are typed by SQLite. Derived
+/// tables are listed so a corrupt snapshot or effect is recorded before its pair is reset.
+const QUARANTINE_TABLES: &[(&str, &[&str], &str, &str)] = &[
+    (
+        "raw_fills",
+        &["px", "sz", "fee", "closed_pnl", "builder_fee"],
+        "t.user",
+        "t.coin",
+    ),
+    ("deposits", &["amount"], "t.user", "NULL"),
+    ("equity_snapshots", &["equity"], "t.user", "NULL"),
+    (
+        "position_snapshots",
+        &["net_size", "avg_entry_px"],
+        "t.user",
+        "t.coin",
+    ),
+    (
+        "fill_effects",
+        &["qty", "notional", "fee", "builder_fee", "closed_pnl"],
+        "(SELECT user FROM position_lifecycles WHERE id = t.lifecycle_id)",
+        "(SELECT coin FROM position_lifecycles WHERE id = t.lifecycle_id)",
+    ),
+];
+
+/// Compile watermark and raw-vs-compiled fill counts for one coin of a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileProgressRow {
+    pub coin: Coin,
+    /// `None` when the coin has raw fills but was never compiled.
+    pub last_compiled_time_ms: Option<i64>,
+    pub last_compiled_fill_key: Option<String>,
+    pub compile_version: Option<i64>,
+    pub compile_schema_version: Option<i64>,
+    pub raw_fills: i64,
+    /// Raw fills with at least one row in `fill_effects`.
+    pub compiled_fills: i64,
+    pub latest_raw_time_ms: Option<i64>,
+}
+
+/// A recorded upstream response; `content` is the decompressed body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPayloadRow {
+    pub id: i64,
+    pub request_type: String,
+    pub user: Option<String>,
+    /// The request body that produced the response.
+    pub request: String,
+    /// SHA-256 of the uncompressed content.
+    pub sha256: String,
+    pub fetched_at_ms: i64,
+    pub content: Vec<u8>,
+}
+
+/// Where an `Idempotency-Key` applies: keys are scoped to the caller and the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdempotencyKey<'a> {
+    /// Hex SHA-256 of the API key; empty when authentication is disabled.
+    pub caller: &'a str,
+    pub key: &'a str,
+    pub path: &'a str,
+}
+
+/// A remembered `Idempotency-Key`; see [`Repository::claim_idempotency_key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyRecord {
+    /// SHA-256 of the query string and body of the first request.
+    pub request_hash: String,
+    /// `None` while the first request is still running.
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// One user's standing under one metric in a stored leaderboard snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardSnapshotRow {
+    /// The window the standings cover ends here.
+    pub snapshot_ms: TimeMs,
+    pub metric: String,
+    pub user: Address,
+    /// 1-based.
+    pub rank: i64,
+    pub value: Decimal,
+    pub trade_count: i64,
+    /// Users ranked in the snapshot.
+    pub entrants: i64,
+}
+
+/// A stored competition: a leaderboard over a fixed window and set of users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Competition {
+    pub name: String,
+    pub from_ms: TimeMs,
+    pub to_ms: TimeMs,
+    /// Leaderboard metric name, as accepted by `metric=`.
+    pub metric: String,
+    pub builder_only: bool,
+    /// Eligible users, sorted.
+    pub users: Vec<Address>,
+    /// Prize descriptions by rank, first place first.
+    pub prizes: Vec<String>,
+}
+
+/// One user's final standing in an ended competition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompetitionResultRow {
+    pub user: Address,
+    /// 1-based.
+    pub rank: i64,
+    pub value: Decimal,
+    pub trade_count: i64,
+    pub tainted: Option<bool>,
+}
+
 /// Minimal fill effect row for PnL aggregation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PnlFillEffect {
+    pub fill_key: String,
     pub lifecycle_id: i64,
+    /// Time of the fill.
+    pub time_ms: TimeMs,
     pub fee: Decimal,
+    /// Share of the fill's builder fee allocated to this effect.
+    pub builder_fee: Decimal,
     pub closed_pnl: Decimal,
 }
 
@@ -34,18 +310,539 @@ pub struct LeaderboardFillEffect {
     pub lifecycle_id: i64,
     pub notional: Decimal,
     pub fee: Decimal,
+    /// Share of the fill's builder fee allocated to this effect.
+    pub builder_fee: Decimal,
+    pub closed_pnl: Decimal,
+}
+
+/// Fill effect row tagged with its time bucket, for volume and fee analytics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeFillEffect {
+    pub bucket_start_ms: TimeMs,
+    pub fill_key: String,
+    pub notional: Decimal,
+    pub fee: Decimal,
+    /// Share of the fill's builder fee allocated to this effect.
+    pub builder_fee: Decimal,
+    pub crossed: Option<bool>,
+}
+
+/// Builder-attributed fill tagged with its time bucket, for builder revenue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderFeeFill {
+    pub bucket_start_ms: TimeMs,
+    pub user: Address,
+    pub fill_key: String,
+    pub notional: Decimal,
+    pub builder_fee: Option<Decimal>,
+}
+
+/// Fill effect row joined with its lifecycle, for per-lifecycle trade reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleEffectRow {
+    pub lifecycle_id: i64,
+    pub coin: Coin,
+    pub start_time_ms: TimeMs,
+    pub end_time_ms: Option<TimeMs>,
+    pub is_tainted: bool,
+    pub taint_reason: Option<String>,
+    /// Set when a liquidation or ADL fill closed the lifecycle.
+    pub closed_by: Option<FillType>,
+    /// Name of the strategy rule the lifecycle matched.
+    pub strategy: Option<String>,
+    pub fill_key: String,
+    pub side: Side,
+    pub effect_type: EffectType,
+    pub qty: Decimal,
+    pub notional: Decimal,
+    pub fee: Decimal,
+    /// Share of the fill's builder fee allocated to this effect.
+    pub builder_fee: Decimal,
+    pub closed_pnl: Decimal,
+}
+
+/// Stored position lifecycle of one (user, coin).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleRow {
+    pub id: i64,
+    pub user: Address,
+    pub coin: Coin,
+    pub start_time_ms: TimeMs,
+    pub end_time_ms: Option<TimeMs>,
+    pub is_tainted: bool,
+    pub taint_reason: Option<String>,
+    /// Set when a liquidation or ADL fill closed the lifecycle.
+    pub closed_by: Option<FillType>,
+}
+
+/// Stored position snapshot with its decimals parsed, as written by a compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotRowFull {
+    pub id: i64,
+    pub time_ms: TimeMs,
+    pub seq: i32,
+    pub lifecycle_id: i64,
+    pub net_size: Decimal,
+    pub avg_entry_px: Decimal,
+    pub is_tainted: bool,
+}
+
+/// Stored fill effect with its decimals parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectRow {
+    pub id: i64,
+    pub fill_key: String,
+    pub lifecycle_id: i64,
+    pub effect_type: EffectType,
+    pub qty: Decimal,
+    pub notional: Decimal,
+    pub fee: Decimal,
     pub closed_pnl: Decimal,
 }
 
+/// Upper bound on bind parameters in one statement (`SQLITE_MAX_VARIABLE_NUMBER` in the
+/// bundled SQLite).
+const SQLITE_MAX_BIND_PARAMS: usize = 32_766;
+
+/// Columns bound per row by [`Repository::insert_fills_batch`].
+const RAW_FILL_INSERT_COLUMNS: usize = 17;
+
+/// Rows per multi-row `INSERT` for a table with `columns` bound columns.
+const fn rows_per_insert(columns: usize) -> usize {
+    SQLITE_MAX_BIND_PARAMS / columns
+}
+
+/// Result of a `PRAGMA wal_checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalCheckpoint {
+    /// A reader or writer kept the checkpoint from finishing.
+    pub busy: bool,
+    /// Frames in the WAL when the checkpoint ran; `-1` when not in WAL mode.
+    pub log_frames: i64,
+    /// Frames copied back into the database file.
+    pub checkpointed_frames: i64,
+}
+
 /// Repository for database operations.
 pub struct Repository {
-    pool: SqlitePool,
+    writer: MeteredPool,
+    /// Serves `query_*`, `get_*`, `sum_*`, and `count_*` reads; the writer unless a
+    /// read-only pool was attached with [`Self::with_read_pool`].
+    reader: MeteredPool,
+}
+
+/// Connection stats of a repository's pools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepositoryPoolStats {
+    pub write: PoolStats,
+    /// `None` when reads share the write pool.
+    pub read: Option<PoolStats>,
 }
 
 impl Repository {
     /// Create a new repository with the given connection pool.
     pub fn new(pool: SqlitePool) -> Self {
-        Repository { pool }
+        let writer = MeteredPool::new(pool);
+        Repository {
+            reader: writer.clone(),
+            writer,
+        }
+    }
+
+    /// Send reads through `pool`, typically opened with
+    /// [`open_read_pool`](super::migrations::open_read_pool), so they do not queue behind
+    /// writes for a connection.
+    pub fn with_read_pool(mut self, pool: SqlitePool) -> Self {
+        self.reader = MeteredPool::new(pool);
+        self
+    }
+
+    pub fn pool_stats(&self) -> RepositoryPoolStats {
+        RepositoryPoolStats {
+            write: self.writer.stats(),
+            read: (!self.reader.shares_pool_with(&self.writer)).then(|| self.reader.stats()),
+        }
+    }
+
+    /// Fold the WAL back into the database file and close every pooled connection.
+    ///
+    /// Waits for checked-out connections to be returned; later queries fail with
+    /// `PoolClosed`.
+    ///
+    /// # Errors
+    /// Returns an error if the checkpoint fails; the pools are closed either way.
+    pub async fn close(&self) -> Result<(), sqlx::Error> {
+        // Open read transactions would keep the checkpoint from truncating the WAL.
+        if !self.reader.shares_pool_with(&self.writer) {
+            self.reader.close().await;
+        }
+        let checkpoint = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(self.writer.pool())
+            .await;
+        self.writer.close().await;
+        checkpoint.map(|_| ())
+    }
+
+    /// Run `PRAGMA wal_checkpoint(TRUNCATE)`, copying the WAL into the database file and
+    /// truncating it to zero bytes.
+    ///
+    /// The checkpoint is partial when a reader still holds an older snapshot; the result
+    /// reports `busy` and the WAL is left for the next attempt.
+    ///
+    /// # Errors
+    /// Returns an error if the pragma fails.
+    pub async fn wal_checkpoint(&self) -> Result<WalCheckpoint, sqlx::Error> {
+        let row = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(&mut *self.writer.acquire().await?)
+            .await?;
+        Ok(WalCheckpoint {
+            busy: row.try_get::<i64, _>(0)? != 0,
+            log_frames: row.try_get(1)?,
+            checkpointed_frames: row.try_get(2)?,
+        })
+    }
+
+    /// Refresh the query planner's table and index statistics.
+    ///
+    /// # Errors
+    /// Returns an error if `ANALYZE` fails.
+    pub async fn analyze(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("ANALYZE").execute(&mut *self.writer.acquire().await?).await?;
+        Ok(())
+    }
+
+    /// Write a compacted, consistent copy of the database to `path` with `VACUUM INTO`.
+    ///
+    /// Readers and writers keep going while the copy is taken. `path` must not exist yet.
+    ///
+    /// # Errors
+    /// Returns an error if SQLite cannot write the copy.
+    pub async fn vacuum_into(&self, path: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path)
+            .execute(&mut *self.writer.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete position snapshots older than `before`, keeping the latest one of each
+    /// `(user, coin)` so positions at `before` and later still resolve.
+    ///
+    /// With `archive`, the deleted rows are first copied to `position_snapshots` in that
+    /// SQLite file, once per `(user, coin, time_ms, seq)`: a rebuilt pair's snapshots are
+    /// pruned again under new ids. Returns the number of rows deleted.
+    ///
+    /// # Errors
+    /// Returns an error if the archive cannot be written or the delete fails.
+    pub async fn prune_position_snapshots(
+        &self,
+        before: TimeMs,
+        archive: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        self.prune_table(
+            "position_snapshots",
+            "user, coin, time_ms, seq",
+            "time_ms < ?1 AND id NOT IN ( \
+                SELECT id FROM ( \
+                    SELECT id, ROW_NUMBER() OVER ( \
+                        PARTITION BY user, coin ORDER BY time_ms DESC, seq DESC \
+                    ) AS rn \
+                    FROM main.position_snapshots WHERE time_ms < ?1 \
+                ) WHERE rn = 1 \
+             )",
+            before,
+            archive,
+        )
+        .await
+    }
+
+    /// Delete cached equity snapshots older than `before`; equity is rederived on demand.
+    ///
+    /// # Errors
+    /// Returns an error if the archive cannot be written or the delete fails.
+    pub async fn prune_equity_snapshots(
+        &self,
+        before: TimeMs,
+        archive: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        self.prune_table("equity_snapshots", "user, time_ms", "time_ms < ?1", before, archive)
+            .await
+    }
+
+    /// Delete recorded Info API responses fetched before `before`.
+    ///
+    /// # Errors
+    /// Returns an error if the archive cannot be written or the delete fails.
+    pub async fn prune_raw_payloads(
+        &self,
+        before: TimeMs,
+        archive: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        self.prune_table(
+            "raw_payloads",
+            "request, sha256",
+            "fetched_at_ms < ?1",
+            before,
+            archive,
+        )
+        .await
+    }
+
+    /// Delete the rows of `table` matching `condition`, where `?1` is `before`, copying them
+    /// to the same table in the `archive` file first.
+    ///
+    /// Copy and delete share one transaction, so a row is never lost between them. The
+    /// archive table is created from the current schema on first use, with a unique index on
+    /// the `identity` columns, and a row whose identity is already archived is not copied
+    /// again.
+    async fn prune_table(
+        &self,
+        table: &str,
+        identity: &str,
+        condition: &str,
+        before: TimeMs,
+        archive: Option<&str>,
+    ) -> Result<u64, sqlx::Error> {
+        // ATTACH is per connection and cannot run inside a transaction.
+        let mut conn = self.writer.acquire().await?;
+        if let Some(path) = archive {
+            sqlx::query("ATTACH DATABASE ? AS archive")
+                .bind(path)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        let result = async {
+            let mut tx = conn.begin().await?;
+            if archive.is_some() {
+                sqlx::query(&format!(
+                    "CREATE TABLE IF NOT EXISTS archive.{table} AS SELECT * FROM main.{table} WHERE 0",
+                    table = table
+                ))
+                .execute(&mut *tx)
+                .await?;
+                let index = format!("{}_identity", table);
+                let indexed: Option<i64> = sqlx::query_scalar(
+                    "SELECT 1 FROM archive.sqlite_master WHERE type = 'index' AND name = ?",
+                )
+                .bind(&index)
+                .fetch_optional(&mut *tx)
+                .await?;
+                if indexed.is_none() {
+                    // Archives written before the index existed may hold duplicates.
+                    sqlx::query(&format!(
+                        "DELETE FROM archive.{table} WHERE rowid NOT IN ( \
+                            SELECT MIN(rowid) FROM archive.{table} GROUP BY {identity} \
+                         )",
+                        table = table,
+                        identity = identity
+                    ))
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query(&format!(
+                        "CREATE UNIQUE INDEX archive.{index} ON {table}({identity})",
+                        index = index,
+                        table = table,
+                        identity = identity
+                    ))
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                sqlx::query(&format!(
+                    "INSERT OR IGNORE INTO archive.{table} \
+                     SELECT * FROM main.{table} WHERE {condition}",
+                    table = table,
+                    condition = condition
+                ))
+                .bind(before.as_ms())
+                .execute(&mut *tx)
+                .await?;
+            }
+            let deleted = sqlx::query(&format!(
+                "DELETE FROM main.{table} WHERE {condition}",
+                table = table,
+                condition = condition
+            ))
+            .bind(before.as_ms())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(deleted)
+        }
+        .await;
+
+        if archive.is_some() {
+            let detached = sqlx::query("DETACH DATABASE archive")
+                .execute(&mut *conn)
+                .await;
+            if detached.is_err() {
+                // Never hand a connection with the archive still attached back to the pool.
+                conn.detach();
+            }
+        }
+        result
+    }
+
+    /// Claim `id` for a request whose body hashes to `request_hash`, first forgetting every
+    /// key created before `expired_before_ms` and every claim still without a response that
+    /// was made before `abandoned_before_ms`.
+    ///
+    /// Returns `None` when the key is new and now belongs to the caller, who must then
+    /// [complete](Self::complete_idempotency_key) or [release](Self::release_idempotency_key)
+    /// it. Otherwise returns the existing record, which has no status yet while its
+    /// request is still running.
+    ///
+    /// # Errors
+    /// Returns an error if the database operation fails.
+    pub async fn claim_idempotency_key(
+        &self,
+        id: &IdempotencyKey<'_>,
+        request_hash: &str,
+        now_ms: i64,
+        expired_before_ms: i64,
+        abandoned_before_ms: i64,
+    ) -> Result<Option<IdempotencyRecord>, sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_requests
+            WHERE created_at_ms < ? OR (status IS NULL AND created_at_ms < ?)
+            "#,
+        )
+        .bind(expired_before_ms)
+        .bind(abandoned_before_ms)
+        .execute(&mut *tx)
+        .await?;
+        let claimed = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO idempotency_requests
+                (caller, key, path, request_hash, created_at_ms)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(id.caller)
+        .bind(id.key)
+        .bind(id.path)
+        .bind(request_hash)
+        .bind(now_ms)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        let existing = if claimed {
+            None
+        } else {
+            let row = sqlx::query(
+                r#"
+                SELECT request_hash, status, content_type, body
+                FROM idempotency_requests
+                WHERE caller = ? AND key = ? AND path = ?
+                "#,
+            )
+            .bind(id.caller)
+            .bind(id.key)
+            .bind(id.path)
+            .fetch_one(&mut *tx)
+            .await?;
+            Some(IdempotencyRecord {
+                request_hash: row.get("request_hash"),
+                status: row
+                    .get::<Option<i64>, _>("status")
+                    .and_then(|s| u16::try_from(s).ok()),
+                content_type: row.get("content_type"),
+                body: row.get::<Option<Vec<u8>>, _>("body").unwrap_or_default(),
+            })
+        };
+        tx.commit().await?;
+        Ok(existing)
+    }
+
+    /// Store the response of a request that claimed `id`, so repeats replay it.
+    ///
+    /// # Errors
+    /// Returns an error if the update fails.
+    pub async fn complete_idempotency_key(
+        &self,
+        id: &IdempotencyKey<'_>,
+        status: u16,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_requests
+            SET status = ?, content_type = ?, body = ?
+            WHERE caller = ? AND key = ? AND path = ?
+            "#,
+        )
+        .bind(i64::from(status))
+        .bind(content_type)
+        .bind(body)
+        .bind(id.caller)
+        .bind(id.key)
+        .bind(id.path)
+        .execute(&mut *self.writer.acquire().await?)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget a claimed `id` without a response, so the request can be retried.
+    ///
+    /// # Errors
+    /// Returns an error if the delete fails.
+    pub async fn release_idempotency_key(
+        &self,
+        id: &IdempotencyKey<'_>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "DELETE FROM idempotency_requests WHERE caller = ? AND key = ? AND path = ? \
+             AND status IS NULL",
+        )
+        .bind(id.caller)
+        .bind(id.key)
+        .bind(id.path)
+        .execute(&mut *self.writer.acquire().await?)
+        .await?;
+        Ok(())
+    }
+
+    /// Run a trivial query to check the database answers.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(&mut *self.writer.acquire().await?).await.map(|_| ())
+    }
+
+    /// Versions recorded by the build that last migrated this database.
+    ///
+    /// # Errors
+    /// Returns an error if the metadata cannot be read or is malformed.
+    pub async fn compat_manifest(&self) -> Result<Option<CompatManifest>, CompatError> {
+        CompatManifest::read(self.reader.pool()).await
+    }
+
+    /// Tag this database with `network`; see [`claim_network`](super::compat::claim_network).
+    ///
+    /// # Errors
+    /// Returns an error if the database already holds another network's data.
+    pub async fn claim_network(&self, network: Network) -> Result<(), CompatError> {
+        super::compat::claim_network(self.writer.pool(), network).await
+    }
+
+    /// Like [`Self::claim_network`] without tagging an untagged database, for read-only use.
+    ///
+    /// # Errors
+    /// Returns an error if the database already holds another network's data.
+    pub async fn check_network(&self, network: Network) -> Result<(), CompatError> {
+        match super::compat::read_network(self.reader.pool()).await? {
+            Some(recorded) if recorded != network => Err(CompatError::NetworkMismatch {
+                recorded,
+                configured: network,
+            }),
+            _ => Ok(()),
+        }
     }
 
     /// Insert a fill into the database idempotently.
@@ -57,8 +854,9 @@ impl Repository {
             r#"
             INSERT INTO raw_fills (
                 user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                builder_fee, tid, oid, fill_key, created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key, created_at,
+                ordering_key
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(fill_key) DO NOTHING
             "#,
         )
@@ -73,42 +871,39 @@ impl Repository {
         .bind(fill.builder_fee.map(|d| d.to_canonical_string()))
         .bind(fill.tid)
         .bind(fill.oid)
+        .bind(fill.crossed)
+        .bind(fill.fill_type.as_str())
+        .bind(fill.twap_id)
         .bind(fill.fill_key.as_str())
         .bind(chrono::Utc::now().timestamp_millis())
-        .execute(&self.pool)
+        .bind(ordering_key(fill))
+        .execute(&mut *self.writer.acquire().await?)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    /// Insert multiple fills in a single transaction for better performance.
+    /// Overwrite the stored fields of existing fills, matched by `fill_key`.
     ///
-    /// Returns the number of newly inserted fills (excludes duplicates).
+    /// Derived tables are not touched; rebuild the affected coins afterwards. Returns the
+    /// number of fills updated.
     ///
     /// # Errors
-    /// Returns an error if the transaction fails.
-    pub async fn insert_fills_batch(&self, fills: &[Fill]) -> Result<usize, sqlx::Error> {
-        if fills.is_empty() {
-            return Ok(0);
-        }
-
-        let created_at = chrono::Utc::now().timestamp_millis();
-        let mut total_inserted = 0usize;
-
-        // Use a transaction for atomicity and better performance
-        let mut tx = self.pool.begin().await?;
+    /// Returns an error if any update fails; the transaction is rolled back.
+    pub async fn update_fills(&self, fills: &[Fill]) -> Result<usize, sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        let mut updated = 0usize;
 
         for fill in fills {
             let result = sqlx::query(
                 r#"
-                INSERT INTO raw_fills (
-                    user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                    builder_fee, tid, oid, fill_key, created_at
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                ON CONFLICT(fill_key) DO NOTHING
+                UPDATE raw_fills SET
+                    coin = ?, time_ms = ?, side = ?, px = ?, sz = ?, fee = ?, closed_pnl = ?,
+                    builder_fee = ?, tid = ?, oid = ?, crossed = ?, fill_type = ?, twap_id = ?,
+                    ordering_key = ?
+                WHERE fill_key = ?
                 "#,
             )
-            .bind(fill.user.as_str())
             .bind(fill.coin.as_str())
             .bind(fill.time_ms.as_i64())
             .bind(fill.side.to_string())
@@ -119,14 +914,67 @@ impl Repository {
             .bind(fill.builder_fee.map(|d| d.to_canonical_string()))
             .bind(fill.tid)
             .bind(fill.oid)
+            .bind(fill.crossed)
+            .bind(fill.fill_type.as_str())
+            .bind(fill.twap_id)
+            .bind(ordering_key(fill))
             .bind(fill.fill_key.as_str())
-            .bind(created_at)
             .execute(&mut *tx)
             .await?;
+            updated += result.rows_affected() as usize;
+        }
 
-            if result.rows_affected() > 0 {
-                total_inserted += 1;
-            }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Insert multiple fills in a single transaction for better performance.
+    ///
+    /// Returns the number of newly inserted fills (excludes duplicates).
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn insert_fills_batch(&self, fills: &[Fill]) -> Result<usize, sqlx::Error> {
+        if fills.is_empty() {
+            return Ok(0);
+        }
+
+        let created_at = chrono::Utc::now().timestamp_millis();
+        let mut total_inserted = 0usize;
+
+        // One multi-row statement per chunk; a transaction keeps the batch atomic
+        let mut tx = self.writer.begin().await?;
+
+        for chunk in fills.chunks(rows_per_insert(RAW_FILL_INSERT_COLUMNS)) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO raw_fills (user, coin, time_ms, side, px, sz, fee, closed_pnl, \
+                 builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key, created_at, \
+                 ordering_key) ",
+            );
+            query.push_values(chunk, |mut row, fill| {
+                row.push_bind(fill.user.as_str())
+                    .push_bind(fill.coin.as_str())
+                    .push_bind(fill.time_ms.as_i64())
+                    .push_bind(fill.side.to_string())
+                    .push_bind(fill.px.to_canonical_string())
+                    .push_bind(fill.sz.to_canonical_string())
+                    .push_bind(fill.fee.to_canonical_string())
+                    .push_bind(fill.closed_pnl.to_canonical_string())
+                    .push_bind(fill.builder_fee.map(|d| d.to_canonical_string()))
+                    .push_bind(fill.tid)
+                    .push_bind(fill.oid)
+                    .push_bind(fill.crossed)
+                    .push_bind(fill.fill_type.as_str())
+                    .push_bind(fill.twap_id)
+                    .push_bind(fill.fill_key.as_str())
+                    .push_bind(created_at)
+                    .push_bind(ordering_key(fill));
+            });
+            query.push(" ON CONFLICT(fill_key) DO NOTHING");
+
+            // Duplicates are skipped, so the change count is the number of new fills
+            let result = query.build().execute(&mut *tx).await?;
+            total_inserted += result.rows_affected() as usize;
         }
 
         tx.commit().await?;
@@ -140,429 +988,2303 @@ impl Repository {
     pub async fn insert_deposit(&self, deposit: &Deposit) -> Result<bool, sqlx::Error> {
         let result = sqlx::query(
             r#"
-            INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO deposits (user, time_ms, amount, amount_e8, tx_hash, kind, event_key)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(event_key) DO NOTHING
             "#,
         )
         .bind(deposit.user.as_str())
         .bind(deposit.time_ms.as_i64())
         .bind(deposit.amount.to_canonical_string())
+        .bind(deposit.amount.to_scaled_units())
         .bind(deposit.tx_hash.as_deref())
+        .bind(deposit.kind.as_str())
         .bind(deposit.event_key.as_str())
-        .execute(&self.pool)
+        .execute(&mut *self.writer.acquire().await?)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    /// Insert multiple deposits in a single transaction for better performance.
+    /// Insert multiple deposits in a single transaction for better performance.
+    ///
+    /// Returns the number of newly inserted deposits (excludes duplicates).
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn insert_deposits_batch(&self, deposits: &[Deposit]) -> Result<usize, sqlx::Error> {
+        if deposits.is_empty() {
+            return Ok(0);
+        }
+
+        let mut total_inserted = 0usize;
+        let mut tx = self.writer.begin().await?;
+
+        for chunk in deposits.chunks(rows_per_insert(7)) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO deposits (user, time_ms, amount, amount_e8, tx_hash, kind, event_key) ",
+            );
+            query.push_values(chunk, |mut row, deposit| {
+                row.push_bind(deposit.user.as_str())
+                    .push_bind(deposit.time_ms.as_i64())
+                    .push_bind(deposit.amount.to_canonical_string())
+                    .push_bind(deposit.amount.to_scaled_units())
+                    .push_bind(deposit.tx_hash.as_deref())
+                    .push_bind(deposit.kind.as_str())
+                    .push_bind(deposit.event_key.as_str());
+            });
+            query.push(" ON CONFLICT(event_key) DO NOTHING");
+
+            let result = query.build().execute(&mut *tx).await?;
+            total_inserted += result.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+        Ok(total_inserted)
+    }
+
+    /// Query deposits for a user within a time range.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_deposits(
+        &self,
+        user: &Address,
+        from_ms: i64,
+        to_ms: i64,
+    ) -> Result<Vec<Deposit>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user, time_ms, amount, tx_hash, kind, event_key
+            FROM deposits
+            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY time_ms ASC, event_key ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(from_ms)
+        .bind(to_ms)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        let deposits = rows
+            .iter()
+            .map(|row| {
+                let user: String = row.get("user");
+                let time_ms: i64 = row.get("time_ms");
+                let tx_hash: Option<String> = row.get("tx_hash");
+                let event_key: String = row.get("event_key");
+
+                let amount = decimal_column(row, "amount")?;
+
+                let kind = row
+                    .get::<Option<String>, _>("kind")
+                    .and_then(|k| k.parse().ok())
+                    .unwrap_or_else(|| LedgerKind::from_sign(&amount));
+
+                Ok(Deposit {
+                    event_key,
+                    user: Address::new(user),
+                    time_ms: TimeMs::new(time_ms),
+                    amount,
+                    tx_hash,
+                    kind,
+                })
+            })
+            .collect::<Result<_, sqlx::Error>>()?;
+
+        Ok(deposits)
+    }
+
+    /// Query raw fills for a user and coin within a time range.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_raw_fills(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        from_ms: i64,
+        to_ms: i64,
+    ) -> Result<Vec<Fill>, sqlx::Error> {
+        self.query_fills(
+            user,
+            Some(coin),
+            Some(TimeMs::new(from_ms)),
+            Some(TimeMs::new(to_ms)),
+        )
+        .await
+    }
+
+    /// Query fills for a user with optional coin and time window.
+    ///
+    /// Loads every matching fill; prefer [`Self::stream_fills`] for unbounded windows.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_fills(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<Fill>, sqlx::Error> {
+        self.stream_fills(user, coin, from_ms, to_ms)
+            .try_collect()
+            .await
+    }
+
+    /// Stream fills for a user with optional coin and time window, in the order of
+    /// [`Self::query_fills`].
+    ///
+    /// Rows are decoded as SQLite yields them instead of being buffered, so memory use does
+    /// not grow with the number of matching fills.
+    pub fn stream_fills<'a>(
+        &'a self,
+        user: &'a Address,
+        coin: Option<&'a Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> BoxStream<'a, Result<Fill, sqlx::Error>> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let sql = if coin.is_some() {
+            r#"
+            SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
+            FROM raw_fills
+            WHERE user = ? AND coin = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY time_ms ASC, tid ASC, oid ASC, fill_key ASC
+            "#
+        } else {
+            r#"
+            SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
+            FROM raw_fills
+            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY time_ms ASC, tid ASC, oid ASC, fill_key ASC
+            "#
+        };
+
+        let mut query = sqlx::query(sql).bind(user.as_str());
+        if let Some(coin) = coin {
+            query = query.bind(coin.as_str());
+        }
+        query
+            .bind(from_ms)
+            .bind(to_ms)
+            .fetch(self.reader.pool())
+            .and_then(|row| async move { fill_from_row(&row) })
+            .boxed()
+    }
+
+    /// Row id of the last fill stored for `user`, or `0` without any.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn last_fill_id(&self, user: &Address) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query("SELECT COALESCE(MAX(id), 0) AS id FROM raw_fills WHERE user = ?")
+            .bind(user.as_str())
+            .fetch_one(&mut *self.reader.acquire().await?)
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    /// Up to `limit` fills of `user` stored after row `after_id`, in the order they were
+    /// stored, with their row ids.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_fills_after_id(
+        &self,
+        user: &Address,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, Fill)>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user, coin, time_ms, side, px, sz, fee, closed_pnl,
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
+            FROM raw_fills
+            WHERE user = ? AND id > ?
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("id")?, fill_from_row(row)?)))
+            .collect()
+    }
+
+    /// Query distinct coins for a user within an optional time range.
+    ///
+    /// Used to determine which per-coin compile pipelines should run.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_distinct_coins(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<Coin>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT coin
+            FROM raw_fills
+            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY coin ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(from_ms)
+        .bind(to_ms)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Coin::new(row.get::<String, _>("coin")))
+            .collect())
+    }
+
+    /// UTC days (days since the Unix epoch) on which a user has fills, ascending.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_fill_days(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<i64>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let sql = if coin.is_some() {
+            r#"
+            SELECT DISTINCT time_ms / 86400000 AS day
+            FROM raw_fills
+            WHERE user = ? AND coin = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY day ASC
+            "#
+        } else {
+            r#"
+            SELECT DISTINCT time_ms / 86400000 AS day
+            FROM raw_fills
+            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY day ASC
+            "#
+        };
+
+        let mut query = sqlx::query(sql).bind(user.as_str());
+        if let Some(coin) = coin {
+            query = query.bind(coin.as_str());
+        }
+        let rows = query
+            .bind(from_ms)
+            .bind(to_ms)
+            .fetch_all(&mut *self.reader.acquire().await?)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<i64, _>("day")).collect())
+    }
+
+    /// Query position snapshots for a user with optional coin and time window.
+    ///
+    /// Joins lifecycles to expose lifecycle-level taint flags for builder-only filtering.
+    /// Rows come in [`SnapshotKey`] order; `after` and `limit` page through them by keyset,
+    /// so a page boundary never depends on rows inserted elsewhere in the history.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_position_snapshots(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        dex: Option<&str>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        after: Option<&SnapshotKey>,
+        limit: Option<i64>,
+    ) -> Result<Vec<PositionSnapshotRow>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT ps.time_ms, ps.seq, ps.coin, ps.net_size, ps.avg_entry_px, ps.lifecycle_id, \
+             pl.is_tainted \
+             FROM position_snapshots ps \
+             JOIN position_lifecycles pl ON ps.lifecycle_id = pl.id \
+             WHERE ps.user = ",
+        );
+        query.push_bind(user.as_str());
+        if let Some(coin) = coin {
+            query.push(" AND ps.coin = ").push_bind(coin.as_str());
+        }
+        if let Some(dex) = dex {
+            push_dex_filter(&mut query, dex);
+        }
+        query
+            .push(" AND ps.time_ms >= ")
+            .push_bind(from_ms)
+            .push(" AND ps.time_ms <= ")
+            .push_bind(to_ms);
+        if let Some(after) = after {
+            query
+                .push(" AND (ps.time_ms, ps.seq, ps.coin, ps.lifecycle_id) > (")
+                .push_bind(after.time_ms.as_ms())
+                .push(", ")
+                .push_bind(after.seq)
+                .push(", ")
+                .push_bind(after.coin.as_str())
+                .push(", ")
+                .push_bind(after.lifecycle_id)
+                .push(")");
+        }
+        query.push(" ORDER BY ps.time_ms ASC, ps.seq ASC, ps.coin ASC, ps.lifecycle_id ASC");
+        if let Some(limit) = limit {
+            query.push(" LIMIT ").push_bind(limit);
+        }
+
+        let rows = query.build().fetch_all(&mut *self.reader.acquire().await?).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| PositionSnapshotRow {
+                time_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
+                seq: row.get::<i32, _>("seq"),
+                coin: Coin::new(row.get::<String, _>("coin")),
+                net_size: row.get::<String, _>("net_size"),
+                avg_entry_px: row.get::<String, _>("avg_entry_px"),
+                lifecycle_id: row.get::<i64, _>("lifecycle_id"),
+                lifecycle_tainted: row.get::<i32, _>("is_tainted") != 0,
+            })
+            .collect())
+    }
+
+    /// Like [`Self::query_position_snapshots`], but only the last snapshot of each coin in
+    /// each of at most `max_points` equal time buckets spanning the matching rows.
+    ///
+    /// With `split_tainted`, tainted and clean lifecycles are bucketed apart, so dropping
+    /// the tainted rows afterwards still leaves the last clean snapshot of each bucket.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_position_snapshots_downsampled(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        dex: Option<&str>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        max_points: usize,
+        split_tainted: bool,
+    ) -> Result<Vec<PositionSnapshotRow>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+        let push_filter = |query: &mut QueryBuilder<'_, Sqlite>| {
+            query.push(" WHERE ps.user = ").push_bind(user.as_str().to_string());
+            if let Some(coin) = coin {
+                query.push(" AND ps.coin = ").push_bind(coin.as_str().to_string());
+            }
+            if let Some(dex) = dex {
+                push_dex_filter(query, dex);
+            }
+            query
+                .push(" AND ps.time_ms >= ")
+                .push_bind(from_ms)
+                .push(" AND ps.time_ms <= ")
+                .push_bind(to_ms);
+        };
+
+        let mut span = QueryBuilder::<Sqlite>::new(
+            "SELECT MIN(ps.time_ms) AS first_ms, MAX(ps.time_ms) AS last_ms FROM position_snapshots ps",
+        );
+        push_filter(&mut span);
+        let row = span.build().fetch_one(&mut *self.reader.acquire().await?).await?;
+        let (Some(first_ms), Some(last_ms)) = (
+            row.get::<Option<i64>, _>("first_ms"),
+            row.get::<Option<i64>, _>("last_ms"),
+        ) else {
+            return Ok(Vec::new());
+        };
+        let width_ms = bucket_width_ms(first_ms, last_ms, max_points);
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT time_ms, seq, coin, net_size, avg_entry_px, lifecycle_id, is_tainted FROM (\
+             SELECT ps.time_ms, ps.seq, ps.coin, ps.net_size, ps.avg_entry_px, ps.lifecycle_id, \
+             pl.is_tainted, ROW_NUMBER() OVER (PARTITION BY ps.coin, (ps.time_ms - ",
+        );
+        query
+            .push_bind(first_ms)
+            .push(") / ")
+            .push_bind(width_ms)
+            .push(", CASE WHEN ")
+            .push_bind(split_tainted)
+            .push(
+                " THEN pl.is_tainted ELSE 0 END \
+                 ORDER BY ps.time_ms DESC, ps.seq DESC, ps.lifecycle_id DESC) AS bucket_rank \
+                 FROM position_snapshots ps \
+                 JOIN position_lifecycles pl ON ps.lifecycle_id = pl.id",
+            );
+        push_filter(&mut query);
+        query.push(
+            ") WHERE bucket_rank = 1 \
+             ORDER BY time_ms ASC, seq ASC, coin ASC, lifecycle_id ASC",
+        );
+
+        let rows = query.build().fetch_all(&mut *self.reader.acquire().await?).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| PositionSnapshotRow {
+                time_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
+                seq: row.get::<i32, _>("seq"),
+                coin: Coin::new(row.get::<String, _>("coin")),
+                net_size: row.get::<String, _>("net_size"),
+                avg_entry_px: row.get::<String, _>("avg_entry_px"),
+                lifecycle_id: row.get::<i64, _>("lifecycle_id"),
+                lifecycle_tainted: row.get::<i32, _>("is_tainted") != 0,
+            })
+            .collect())
+    }
+
+    /// Equity after each millisecond with a ledger flow or realized PnL in the window.
+    ///
+    /// Equity follows [`crate::engine::EquityResolver`]: flows and gross `closed_pnl` only,
+    /// flows before fills in the same millisecond. With `from_ms`, the history starts with
+    /// a point for the equity at `from_ms`. With `max_points`, only the last point of each
+    /// of at most that many equal time buckets is kept.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_equity_history(
+        &self,
+        user: &Address,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        max_points: Option<usize>,
+    ) -> Result<Vec<(TimeMs, Decimal)>, sqlx::Error> {
+        let mut points = Vec::new();
+        let mut equity = Decimal::zero();
+        if let Some(from_ms) = from_ms {
+            equity = self.sum_deposits_up_to(user, from_ms).await?
+                + self.sum_realized_pnl_before(user, from_ms).await?;
+            points.push((from_ms, equity));
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT time_ms, 0 AS source, id, amount AS delta
+            FROM deposits
+            WHERE user = ? AND time_ms > ? AND time_ms <= ?
+            UNION ALL
+            SELECT rf.time_ms, 1 AS source, fe.id, fe.closed_pnl AS delta
+            FROM fill_effects fe
+            JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+            JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
+            WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
+            ORDER BY time_ms ASC, source ASC, id ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(from_ms.map_or(i64::MIN, |t| t.as_ms()))
+        .bind(to_ms.map_or(i64::MAX, |t| t.as_ms()))
+        .bind(user.as_str())
+        .bind(from_ms.map_or(i64::MIN, |t| t.as_ms()))
+        .bind(to_ms.map_or(i64::MAX, |t| t.as_ms()))
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        for row in rows {
+            let time_ms = TimeMs::new(row.get("time_ms"));
+            let delta = decimal_column(&row, "delta")?;
+            equity = equity + delta;
+            match points.last_mut() {
+                Some((last_ms, last)) if *last_ms == time_ms => *last = equity,
+                _ => points.push((time_ms, equity)),
+            }
+        }
+
+        Ok(match max_points {
+            Some(max_points) => last_in_bucket(points, max_points),
+            None => points,
+        })
+    }
+
+    /// Every position snapshot of `user` up to `to_ms`, over all coins, in snapshot order.
+    pub async fn query_held_positions(
+        &self,
+        user: &Address,
+        to_ms: TimeMs,
+    ) -> Result<Vec<HeldPosition>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT time_ms, coin, net_size, avg_entry_px
+            FROM position_snapshots
+            WHERE user = ? AND time_ms <= ?
+            ORDER BY time_ms ASC, seq ASC, coin ASC, lifecycle_id ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(to_ms.as_ms())
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(HeldPosition {
+                    time_ms: TimeMs::new(row.get("time_ms")),
+                    coin: Coin::new(row.get("coin")),
+                    net_size: decimal_column(row, "net_size")?,
+                    avg_entry_px: decimal_column(row, "avg_entry_px")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Net size of the last snapshot for (user, coin) strictly before `time_ms`.
+    ///
+    /// Returns None if the user had no snapshots for the coin before then.
+    pub async fn query_net_size_before(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        time_ms: TimeMs,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT net_size
+            FROM position_snapshots
+            WHERE user = ? AND coin = ? AND time_ms < ?
+            ORDER BY time_ms DESC, seq DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(coin.as_str())
+        .bind(time_ms.as_ms())
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(row.map(|r| r.get::<String, _>("net_size")))
+    }
+
+    /// Get a cached builder log file for a builder and UTC day.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn get_builder_log_file(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<Option<BuilderLogFileRow>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT builder, yyyymmdd, sha256, etag, row_count, fetched_at_ms, content
+            FROM builder_log_files
+            WHERE builder = ? AND yyyymmdd = ?
+            "#,
+        )
+        .bind(builder.as_str())
+        .bind(yyyymmdd)
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(row.map(|r| BuilderLogFileRow {
+            builder: r.get("builder"),
+            yyyymmdd: r.get("yyyymmdd"),
+            sha256: r.get("sha256"),
+            etag: r.get("etag"),
+            row_count: r.get("row_count"),
+            fetched_at_ms: r.get("fetched_at_ms"),
+            content: r.get("content"),
+        }))
+    }
+
+    /// Insert or replace a cached builder log file.
+    ///
+    /// # Errors
+    /// Returns an error if the upsert fails.
+    pub async fn upsert_builder_log_file(&self, file: &BuilderLogFileRow) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO builder_log_files
+            (builder, yyyymmdd, sha256, etag, row_count, fetched_at_ms, content)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(builder, yyyymmdd) DO UPDATE SET
+                sha256 = excluded.sha256,
+                etag = excluded.etag,
+                row_count = excluded.row_count,
+                fetched_at_ms = excluded.fetched_at_ms,
+                content = excluded.content
+            "#,
+        )
+        .bind(file.builder.to_ascii_lowercase())
+        .bind(&file.yyyymmdd)
+        .bind(&file.sha256)
+        .bind(&file.etag)
+        .bind(file.row_count)
+        .bind(file.fetched_at_ms)
+        .bind(&file.content)
+        .execute(&mut *self.writer.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record an upstream response body, compressed. A response identical to one already
+    /// recorded for the same request is not stored again.
+    ///
+    /// Returns whether a new row was stored.
+    ///
+    /// # Errors
+    /// Returns an error if the insert fails.
+    pub async fn insert_raw_payload(
+        &self,
+        request_type: &str,
+        user: Option<&str>,
+        request: &str,
+        content: &[u8],
+        fetched_at_ms: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let sha256 = hex::encode(Sha256::digest(content));
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO raw_payloads
+            (request_type, user, request, sha256, fetched_at_ms, content)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(request_type)
+        .bind(user)
+        .bind(request)
+        .bind(sha256)
+        .bind(fetched_at_ms)
+        .bind(lz4_flex::compress_prepend_size(content))
+        .execute(&mut *self.writer.acquire().await?)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Recorded responses of one request type, optionally for one user, oldest first.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails or a stored body does not decompress.
+    pub async fn query_raw_payloads(
+        &self,
+        request_type: &str,
+        user: Option<&Address>,
+    ) -> Result<Vec<RawPayloadRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, request_type, user, request, sha256, fetched_at_ms, content
+            FROM raw_payloads
+            WHERE request_type = ? AND (? IS NULL OR user = ?)
+            ORDER BY fetched_at_ms ASC, id ASC
+            "#,
+        )
+        .bind(request_type)
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let compressed: Vec<u8> = row.get("content");
+                let content = lz4_flex::decompress_size_prepended(&compressed)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                Ok(RawPayloadRow {
+                    id: row.get("id"),
+                    request_type: row.get("request_type"),
+                    user: row.get("user"),
+                    request: row.get("request"),
+                    sha256: row.get("sha256"),
+                    fetched_at_ms: row.get("fetched_at_ms"),
+                    content,
+                })
+            })
+            .collect()
+    }
+
+    /// Replace the recorded dead-letter rows for a builder and UTC day.
+    ///
+    /// Every parse of a day yields the full set of bad rows, so previous entries are dropped
+    /// first; an empty `rejects` clears the day.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn replace_builder_log_rejects(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+        rejects: &[BuilderLogRejectRow],
+        recorded_at_ms: i64,
+    ) -> Result<(), sqlx::Error> {
+        let builder = builder.as_str();
+        let mut tx = self.writer.begin().await?;
+
+        sqlx::query("DELETE FROM builder_log_rejects WHERE builder = ? AND yyyymmdd = ?")
+            .bind(builder)
+            .bind(yyyymmdd)
+            .execute(&mut *tx)
+            .await?;
+
+        for reject in rejects {
+            sqlx::query(
+                r#"
+                INSERT INTO builder_log_rejects
+                (builder, yyyymmdd, line, byte_offset, reason, raw, recorded_at_ms)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(builder)
+            .bind(yyyymmdd)
+            .bind(reject.line)
+            .bind(reject.byte_offset)
+            .bind(&reject.reason)
+            .bind(&reject.raw)
+            .bind(recorded_at_ms)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace the stored builder log rows for a builder and UTC day.
+    ///
+    /// A day's file is the complete set of its rows, so previous rows are dropped first;
+    /// backfilling the same day twice leaves one copy. Returns the number of rows stored.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn replace_builder_log_fills(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+        fills: &[BuilderLogFill],
+    ) -> Result<usize, sqlx::Error> {
+        let builder = builder.as_str();
+        let mut tx = self.writer.begin().await?;
+
+        sqlx::query("DELETE FROM builder_log_fills WHERE builder = ? AND yyyymmdd = ?")
+            .bind(builder)
+            .bind(yyyymmdd)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut stored = 0usize;
+        for chunk in fills.chunks(rows_per_insert(10)) {
+            // A row repeated within the file is stored once.
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT OR IGNORE INTO builder_log_fills \
+                 (builder, yyyymmdd, time_ms, user, coin, side, px, sz, tid, oid) ",
+            );
+            query.push_values(chunk, |mut row, fill| {
+                row.push_bind(builder)
+                    .push_bind(yyyymmdd)
+                    .push_bind(fill.time_ms.as_i64())
+                    .push_bind(fill.user.as_str())
+                    .push_bind(fill.coin.as_str())
+                    .push_bind(fill.side.to_string())
+                    .push_bind(fill.px.to_canonical_string())
+                    .push_bind(fill.sz.to_canonical_string())
+                    .push_bind(fill.tid)
+                    .push_bind(fill.oid);
+            });
+            stored += query.build().execute(&mut *tx).await?.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    /// The stored builder log rows for a builder and UTC day, in file order.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails or a stored value cannot be decoded.
+    pub async fn query_builder_log_fills(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<Vec<BuilderLogFill>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT time_ms, user, coin, side, px, sz, tid, oid
+            FROM builder_log_fills
+            WHERE builder = ? AND yyyymmdd = ?
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(builder.as_str())
+        .bind(yyyymmdd)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(BuilderLogFill {
+                    time_ms: TimeMs::new(row.try_get("time_ms")?),
+                    user: Address::new(row.try_get("user")?),
+                    coin: Coin::new(row.try_get("coin")?),
+                    side: side_column(row, "side")?,
+                    px: decimal_column(row, "px")?,
+                    sz: decimal_column(row, "sz")?,
+                    tid: row.try_get("tid")?,
+                    oid: row.try_get("oid")?,
+                })
+            })
+            .collect()
+    }
+
+    /// The stored rows of a cached completed day, or `None` unless the day's file is in
+    /// `builder_log_files` and all of its rows are in `builder_log_fills`.
+    ///
+    /// Lets attribution build its index from the database instead of decompressing and
+    /// parsing the cached file again.
+    ///
+    /// # Errors
+    /// Returns an error if a query fails or a stored value cannot be decoded.
+    pub async fn query_cached_builder_log_fills(
+        &self,
+        builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<Option<Vec<BuilderLogFill>>, sqlx::Error> {
+        let row_count: Option<(i64,)> = sqlx::query_as(
+            "SELECT row_count FROM builder_log_files WHERE builder = ? AND yyyymmdd = ?",
+        )
+        .bind(builder.as_str())
+        .bind(yyyymmdd)
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+        let Some((row_count,)) = row_count else {
+            return Ok(None);
+        };
+
+        let fills = self.query_builder_log_fills(builder, yyyymmdd).await?;
+        Ok((fills.len() as i64 == row_count).then_some(fills))
+    }
+
+    /// Users appearing in the stored builder logs of `builders` over an inclusive `YYYYMMDD`
+    /// range who also have stored fills in `[from_ms, to_ms]`, ordered by address.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_builder_log_users_with_fills(
+        &self,
+        builders: &[Address],
+        from_day: &str,
+        to_day: &str,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> Result<Vec<Address>, sqlx::Error> {
+        if builders.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT DISTINCT b.user FROM builder_log_fills b WHERE b.yyyymmdd >= ",
+        );
+        query
+            .push_bind(from_day)
+            .push(" AND b.yyyymmdd <= ")
+            .push_bind(to_day)
+            .push(" AND b.builder IN (");
+        let mut separated = query.separated(", ");
+        for builder in builders {
+            separated.push_bind(builder.as_str());
+        }
+        query
+            .push(") AND EXISTS (SELECT 1 FROM raw_fills f WHERE f.user = b.user AND f.time_ms >= ")
+            .push_bind(from_ms.as_i64())
+            .push(" AND f.time_ms <= ")
+            .push_bind(to_ms.as_i64())
+            .push(") ORDER BY b.user");
+
+        let rows = query
+            .build()
+            .fetch_all(&mut *self.reader.acquire().await?)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Address::new(row.get::<String, _>("user")))
+            .collect())
+    }
+
+    /// Per-day builder log status for a builder, ordered by day.
+    ///
+    /// Covers every day that is either cached or has dead-letter rows, optionally limited to
+    /// an inclusive `YYYYMMDD` range.
+    ///
+    /// # Errors
+    /// Returns an error if a query fails.
+    pub async fn query_builder_log_status(
+        &self,
+        builder: &Address,
+        from_day: Option<&str>,
+        to_day: Option<&str>,
+    ) -> Result<Vec<BuilderLogDayStatus>, sqlx::Error> {
+        let builder = builder.as_str();
+        let from_day = from_day.unwrap_or("00000000");
+        let to_day = to_day.unwrap_or("99999999");
+        let mut days: BTreeMap<String, BuilderLogDayStatus> = BTreeMap::new();
+
+        let files = sqlx::query(
+            r#"
+            SELECT yyyymmdd, sha256, row_count, fetched_at_ms
+            FROM builder_log_files
+            WHERE builder = ? AND yyyymmdd >= ? AND yyyymmdd <= ?
+            "#,
+        )
+        .bind(builder)
+        .bind(from_day)
+        .bind(to_day)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        for row in files {
+            let yyyymmdd: String = row.get("yyyymmdd");
+            days.insert(
+                yyyymmdd.clone(),
+                BuilderLogDayStatus {
+                    yyyymmdd,
+                    cached: true,
+                    row_count: Some(row.get("row_count")),
+                    sha256: Some(row.get("sha256")),
+                    fetched_at_ms: Some(row.get("fetched_at_ms")),
+                    rejects: Vec::new(),
+                },
+            );
+        }
+
+        let rejects = sqlx::query(
+            r#"
+            SELECT yyyymmdd, line, byte_offset, reason, raw
+            FROM builder_log_rejects
+            WHERE builder = ? AND yyyymmdd >= ? AND yyyymmdd <= ?
+            ORDER BY yyyymmdd ASC, line ASC
+            "#,
+        )
+        .bind(builder)
+        .bind(from_day)
+        .bind(to_day)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        for row in rejects {
+            let yyyymmdd: String = row.get("yyyymmdd");
+            days.entry(yyyymmdd.clone())
+                .or_insert_with(|| BuilderLogDayStatus {
+                    yyyymmdd,
+                    cached: false,
+                    row_count: None,
+                    sha256: None,
+                    fetched_at_ms: None,
+                    rejects: Vec::new(),
+                })
+                .rejects
+                .push(BuilderLogRejectRow {
+                    line: row.get("line"),
+                    byte_offset: row.get("byte_offset"),
+                    reason: row.get("reason"),
+                    raw: row.get("raw"),
+                });
+        }
+
+        Ok(days.into_values().collect())
+    }
+
+    /// `YYYYMMDD` days in an inclusive range whose builder log file is cached.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_cached_builder_log_days(
+        &self,
+        builder: &Address,
+        from_day: &str,
+        to_day: &str,
+    ) -> Result<BTreeSet<String>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT yyyymmdd
+            FROM builder_log_files
+            WHERE builder = ? AND yyyymmdd >= ? AND yyyymmdd <= ?
+            "#,
+        )
+        .bind(builder.as_str())
+        .bind(from_day)
+        .bind(to_day)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("yyyymmdd")).collect())
+    }
+
+    /// Register a compile adjustment for a coin.
+    ///
+    /// Derived rows for the coin are dropped for every user in the same transaction, so the
+    /// next compile rebuilds them with the adjustment applied from the first fill. Returns
+    /// the users whose rows were dropped.
+    ///
+    /// # Errors
+    /// Returns an error if any statement fails; the transaction is rolled back.
+    pub async fn insert_coin_adjustment(
+        &self,
+        coin: &Coin,
+        adjustment: &CoinAdjustment,
+    ) -> Result<Vec<Address>, sqlx::Error> {
+        let factor = match adjustment {
+            CoinAdjustment::SizeRebase(rebase) => rebase.factor,
+        };
+
+        let mut tx = self.writer.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO coin_adjustments (coin, kind, effective_time_ms, factor)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(coin.as_str())
+        .bind(adjustment.kind())
+        .bind(adjustment.effective_time_ms().as_i64())
+        .bind(factor.to_canonical_string())
+        .execute(&mut *tx)
+        .await?;
+
+        let users: Vec<(String,)> =
+            sqlx::query_as("SELECT DISTINCT user FROM compile_state WHERE coin = ? ORDER BY user")
+                .bind(coin.as_str())
+                .fetch_all(&mut *tx)
+                .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM fill_effects WHERE lifecycle_id IN (
+                SELECT id FROM position_lifecycles WHERE coin = ?
+            )
+            "#,
+        )
+        .bind(coin.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        for sql in [
+            "DELETE FROM position_snapshots WHERE coin = ?",
+            "DELETE FROM position_lifecycles WHERE coin = ?",
+            "DELETE FROM compile_state WHERE coin = ?",
+            "DELETE FROM reconciliation_issues WHERE coin = ?",
+        ] {
+            sqlx::query(sql).bind(coin.as_str()).execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(users.into_iter().map(|(user,)| Address::new(user)).collect())
+    }
+
+    /// Query the compile adjustments registered for a coin, oldest effective time first.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails or a stored row cannot be decoded.
+    pub async fn query_coin_adjustments(
+        &self,
+        coin: &Coin,
+    ) -> Result<Vec<CoinAdjustment>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT kind, effective_time_ms, factor
+            FROM coin_adjustments
+            WHERE coin = ?
+            ORDER BY effective_time_ms ASC, id ASC
+            "#,
+        )
+        .bind(coin.as_str())
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let kind: String = row.get("kind");
+                let factor: String = row.get("factor");
+                Decimal::from_str(&factor)
+                    .ok()
+                    .and_then(|factor| {
+                        CoinAdjustment::from_parts(
+                            &kind,
+                            TimeMs::new(row.get("effective_time_ms")),
+                            factor,
+                        )
+                    })
+                    .ok_or_else(|| {
+                        sqlx::Error::Decode(
+                            format!("invalid coin adjustment: {} factor={}", kind, factor).into(),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Query the latest snapshot of every open lifecycle for a user.
+    ///
+    /// A lifecycle is open while its `end_time_ms` is NULL. The latest snapshot is chosen by
+    /// `(time_ms, seq)` so that flips within the same millisecond resolve deterministically.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_open_positions(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+    ) -> Result<Vec<OpenPositionRow>, sqlx::Error> {
+        let (sql, binds_coin) = if coin.is_some() {
+            (
+                r#"
+                SELECT pl.id, pl.coin, pl.start_time_ms, pl.is_tainted,
+                       ps.time_ms, ps.net_size, ps.avg_entry_px
+                FROM position_lifecycles pl
+                JOIN position_snapshots ps ON ps.id = (
+                    SELECT ps2.id FROM position_snapshots ps2
+                    WHERE ps2.lifecycle_id = pl.id
+                    ORDER BY ps2.time_ms DESC, ps2.seq DESC
+                    LIMIT 1
+                )
+                WHERE pl.user = ? AND pl.coin = ? AND pl.end_time_ms IS NULL
+                ORDER BY pl.coin ASC, pl.id ASC
+                "#,
+                true,
+            )
+        } else {
+            (
+                r#"
+                SELECT pl.id, pl.coin, pl.start_time_ms, pl.is_tainted,
+                       ps.time_ms, ps.net_size, ps.avg_entry_px
+                FROM position_lifecycles pl
+                JOIN position_snapshots ps ON ps.id = (
+                    SELECT ps2.id FROM position_snapshots ps2
+                    WHERE ps2.lifecycle_id = pl.id
+                    ORDER BY ps2.time_ms DESC, ps2.seq DESC
+                    LIMIT 1
+                )
+                WHERE pl.user = ? AND pl.end_time_ms IS NULL
+                ORDER BY pl.coin ASC, pl.id ASC
+                "#,
+                false,
+            )
+        };
+
+        let mut query = sqlx::query(sql).bind(user.as_str());
+        if binds_coin {
+            query = query.bind(coin.expect("binds_coin implies coin is Some").as_str());
+        }
+
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| OpenPositionRow {
+                coin: Coin::new(row.get::<String, _>("coin")),
+                lifecycle_id: row.get::<i64, _>("id"),
+                start_time_ms: TimeMs::new(row.get::<i64, _>("start_time_ms")),
+                last_update_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
+                net_size: row.get::<String, _>("net_size"),
+                avg_entry_px: row.get::<String, _>("avg_entry_px"),
+                lifecycle_tainted: row.get::<i32, _>("is_tainted") != 0,
+            })
+            .collect())
+    }
+
+    /// Reconstruct the positions of a user as of `at_ms`: for each coin, the latest snapshot at
+    /// or before `at_ms`, kept only while its lifecycle was still open then.
+    ///
+    /// The user's coins come from `idx_lifecycles_user_coin`, and each costs one seek on
+    /// `idx_snapshots_user_coin_time_seq` rather than a scan of its history. A lifecycle
+    /// closed exactly at `at_ms` is no longer open.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_positions_at(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        at_ms: TimeMs,
+    ) -> Result<Vec<OpenPositionRow>, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT pl.id, pl.coin, pl.start_time_ms, pl.is_tainted, \
+             ps.time_ms, ps.net_size, ps.avg_entry_px \
+             FROM (",
+        );
+        match coin {
+            Some(coin) => query.push("SELECT ").push_bind(coin.as_str()).push(" AS coin"),
+            None => query
+                .push("SELECT DISTINCT coin FROM position_lifecycles WHERE user = ")
+                .push_bind(user.as_str()),
+        };
+        query
+            .push(
+                ") c \
+                 JOIN position_snapshots ps ON ps.id = ( \
+                 SELECT ps2.id FROM position_snapshots ps2 \
+                 WHERE ps2.user = ",
+            )
+            .push_bind(user.as_str())
+            .push(" AND ps2.coin = c.coin AND ps2.time_ms <= ")
+            .push_bind(at_ms.as_ms())
+            .push(
+                " ORDER BY ps2.time_ms DESC, ps2.seq DESC \
+                 LIMIT 1) \
+                 JOIN position_lifecycles pl ON pl.id = ps.lifecycle_id \
+                 WHERE pl.end_time_ms IS NULL OR pl.end_time_ms > ",
+            )
+            .push_bind(at_ms.as_ms())
+            .push(" ORDER BY pl.coin ASC, pl.id ASC");
+
+        let rows = query.build().fetch_all(&mut *self.reader.acquire().await?).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| OpenPositionRow {
+                coin: Coin::new(row.get::<String, _>("coin")),
+                lifecycle_id: row.get::<i64, _>("id"),
+                start_time_ms: TimeMs::new(row.get::<i64, _>("start_time_ms")),
+                last_update_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
+                net_size: row.get::<String, _>("net_size"),
+                avg_entry_px: row.get::<String, _>("avg_entry_px"),
+                lifecycle_tainted: row.get::<i32, _>("is_tainted") != 0,
+            })
+            .collect())
+    }
+
+    /// Get a raw fill by its fill_key.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn get_raw_fill_by_key(&self, fill_key: &str) -> Result<Option<Fill>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
+            FROM raw_fills
+            WHERE fill_key = ?
+            "#,
+        )
+        .bind(fill_key)
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+
+        row.as_ref().map(fill_from_row).transpose()
+    }
+
+    /// Store compile state for a user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the insert fails.
+    pub async fn store_compile_state(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        last_compiled_time_ms: Option<i64>,
+        last_compiled_fill_key: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        write_compile_state(
+            &mut *self.writer.acquire().await?,
+            user,
+            coin,
+            last_compiled_time_ms,
+            last_compiled_fill_key,
+        )
+        .await
+    }
+
+    /// Get compile state for a user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn get_compile_state(
+        &self,
+        user: &Address,
+        coin: &Coin,
+    ) -> Result<Option<(Option<i64>, Option<String>)>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT last_compiled_time_ms, last_compiled_fill_key FROM compile_state WHERE user = ? AND coin = ?",
+        )
+        .bind(user.as_str())
+        .bind(coin.as_str())
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(row.map(|r| {
+            (
+                r.get("last_compiled_time_ms"),
+                r.get("last_compiled_fill_key"),
+            )
+        }))
+    }
+
+    /// List (user, coin) pairs whose derived tables were compiled by a different
+    /// `COMPILE_SCHEMA_VERSION`.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_stale_compile_states(&self) -> Result<Vec<(Address, Coin)>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user, coin FROM compile_state
+            WHERE compile_schema_version != ?
+            ORDER BY user ASC, coin ASC
+            "#,
+        )
+        .bind(COMPILE_SCHEMA_VERSION)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    Address::new(row.get::<String, _>("user")),
+                    Coin::new(row.get::<String, _>("coin")),
+                )
+            })
+            .collect())
+    }
+
+    /// Compile progress per coin for a user: the stored watermark next to counts of raw
+    /// fills and of raw fills that have fill effects. Coins appear if they have raw fills
+    /// or a compile state.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_compile_progress(
+        &self,
+        user: &Address,
+    ) -> Result<Vec<CompileProgressRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            WITH raw AS (
+                SELECT
+                    r.coin,
+                    COUNT(*) AS raw_fills,
+                    SUM(EXISTS (SELECT 1 FROM fill_effects e WHERE e.fill_key = r.fill_key))
+                        AS compiled_fills,
+                    MAX(r.time_ms) AS latest_raw_time_ms
+                FROM raw_fills r
+                WHERE r.user = ?1
+                GROUP BY r.coin
+            ),
+            coins AS (
+                SELECT coin FROM raw
+                UNION
+                SELECT coin FROM compile_state WHERE user = ?1
+            )
+            SELECT
+                c.coin,
+                cs.last_compiled_time_ms,
+                cs.last_compiled_fill_key,
+                cs.compile_version,
+                cs.compile_schema_version,
+                COALESCE(raw.raw_fills, 0) AS raw_fills,
+                COALESCE(raw.compiled_fills, 0) AS compiled_fills,
+                raw.latest_raw_time_ms
+            FROM coins c
+            LEFT JOIN raw ON raw.coin = c.coin
+            LEFT JOIN compile_state cs ON cs.user = ?1 AND cs.coin = c.coin
+            ORDER BY c.coin ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| CompileProgressRow {
+                coin: Coin::new(row.get::<String, _>("coin")),
+                last_compiled_time_ms: row.get("last_compiled_time_ms"),
+                last_compiled_fill_key: row.get("last_compiled_fill_key"),
+                compile_version: row.get("compile_version"),
+                compile_schema_version: row.get("compile_schema_version"),
+                raw_fills: row.get("raw_fills"),
+                compiled_fills: row.get("compiled_fills"),
+                latest_raw_time_ms: row.get("latest_raw_time_ms"),
+            })
+            .collect())
+    }
+
+    /// Delete all derived rows and the compile watermark for a user and coin.
+    ///
+    /// Raw fills and attributions are kept, so the next compile rebuilds the pair from scratch.
+    ///
+    /// # Errors
+    /// Returns an error if any delete fails; the transaction is rolled back.
+    pub async fn reset_derived_state(&self, user: &Address, coin: &Coin) -> Result<(), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        reset_derived_rows(&mut tx, user, coin).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Append invariant violations found while compiling a user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn insert_invariant_violations(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        violations: &[InvariantViolation],
+        recorded_at_ms: i64,
+    ) -> Result<(), sqlx::Error> {
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.writer.begin().await?;
+        write_invariant_violations(&mut tx, user, coin, violations, recorded_at_ms).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Most recent invariant violations, newest first, optionally for one user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_invariant_violations(
+        &self,
+        user: Option<&Address>,
+        coin: Option<&Coin>,
+        limit: i64,
+    ) -> Result<Vec<InvariantViolationRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user, coin, invariant, fill_key, lifecycle_id, detail, recorded_at_ms
+            FROM invariant_violations
+            WHERE (? IS NULL OR user = ?) AND (? IS NULL OR coin = ?)
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(limit)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| InvariantViolationRow {
+                id: row.get("id"),
+                user: Address::new(row.get("user")),
+                coin: Coin::new(row.get("coin")),
+                invariant: row.get("invariant"),
+                fill_key: row.get("fill_key"),
+                lifecycle_id: row.get("lifecycle_id"),
+                detail: row.get("detail"),
+                recorded_at_ms: row.get("recorded_at_ms"),
+            })
+            .collect())
+    }
+
+    /// Count invariant violations per invariant, optionally for one user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn count_invariant_violations(
+        &self,
+        user: Option<&Address>,
+        coin: Option<&Coin>,
+    ) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT invariant, COUNT(*) AS count
+            FROM invariant_violations
+            WHERE (? IS NULL OR user = ?) AND (? IS NULL OR coin = ?)
+            GROUP BY invariant
+            "#,
+        )
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("invariant"), row.get("count")))
+            .collect())
+    }
+
+    /// Append the PnL reconciliation issues found while compiling a user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction fails.
+    pub async fn insert_reconciliation_issues(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        issues: &[ReconciliationIssue],
+        recorded_at_ms: i64,
+    ) -> Result<(), sqlx::Error> {
+        if issues.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.writer.begin().await?;
+
+        for issue in issues {
+            sqlx::query(
+                r#"
+                INSERT INTO reconciliation_issues
+                (user, coin, lifecycle_id, start_time_ms, reported_pnl, recomputed_pnl, difference,
+                 recorded_at_ms)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(user.as_str())
+            .bind(coin.as_str())
+            .bind(issue.lifecycle_id)
+            .bind(issue.start_time_ms.as_i64())
+            .bind(issue.reported_pnl.to_canonical_string())
+            .bind(issue.recomputed_pnl.to_canonical_string())
+            .bind(issue.difference.to_canonical_string())
+            .bind(recorded_at_ms)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Most recent PnL reconciliation issues, newest first, optionally for one user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_reconciliation_issues(
+        &self,
+        user: Option<&Address>,
+        coin: Option<&Coin>,
+        limit: i64,
+    ) -> Result<Vec<ReconciliationIssueRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user, coin, lifecycle_id, start_time_ms, reported_pnl, recomputed_pnl,
+                   difference, recorded_at_ms
+            FROM reconciliation_issues
+            WHERE (? IS NULL OR user = ?) AND (? IS NULL OR coin = ?)
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(limit)
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows
+            .into_iter()
+            .map(|row| {
+                let lifecycle_id: i64 = row.get("lifecycle_id");
+                Ok(ReconciliationIssueRow {
+                    id: row.get("id"),
+                    user: Address::new(row.get("user")),
+                    coin: Coin::new(row.get("coin")),
+                    lifecycle_id,
+                    start_time_ms: row.get("start_time_ms"),
+                    reported_pnl: decimal_column(&row, "reported_pnl")?,
+                    recomputed_pnl: decimal_column(&row, "recomputed_pnl")?,
+                    difference: decimal_column(&row, "difference")?,
+                    recorded_at_ms: row.get("recorded_at_ms"),
+                })
+            })
+            .collect()
+    }
+
+    /// Count PnL reconciliation issues, optionally for one user and coin.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn count_reconciliation_issues(
+        &self,
+        user: Option<&Address>,
+        coin: Option<&Coin>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM reconciliation_issues
+            WHERE (? IS NULL OR user = ?) AND (? IS NULL OR coin = ?)
+            "#,
+        )
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .bind(coin.map(|c| c.as_str()))
+        .fetch_one(&mut *self.reader.acquire().await?)
+        .await
+    }
+
+    /// Move rows whose decimal columns do not decode into `quarantined_rows`.
     ///
-    /// Returns the number of newly inserted deposits (excludes duplicates).
+    /// Readers fail with [`is_data_corruption`] errors on such rows, so one damaged value
+    /// would otherwise break every query touching it. Each table in [`QUARANTINE_TABLES`]
+    /// is scanned; a corrupt row is copied to `quarantined_rows` as JSON and deleted. A
+    /// quarantined raw fill takes its attribution with it, and any pair that lost a raw
+    /// fill, snapshot, or effect has its derived rows reset so the next compile rebuilds
+    /// it without the row. The running crate version is recorded as having swept; see
+    /// [`Self::quarantine_swept_version`].
     ///
     /// # Errors
-    /// Returns an error if the transaction fails.
-    pub async fn insert_deposits_batch(&self, deposits: &[Deposit]) -> Result<usize, sqlx::Error> {
-        if deposits.is_empty() {
-            return Ok(0);
+    /// Returns an error if a scan or the move fails; the move is one transaction.
+    pub async fn quarantine_corrupt_rows(&self) -> Result<QuarantineReport, sqlx::Error> {
+        struct Corrupt {
+            table: &'static str,
+            id: i64,
+            user: Option<String>,
+            coin: Option<String>,
+            column: &'static str,
+            value: Option<String>,
+            reason: String,
         }
 
-        let mut total_inserted = 0usize;
-        let mut tx = self.pool.begin().await?;
+        let mut corrupt = Vec::new();
+        for &(table, columns, user_expr, coin_expr) in QUARANTINE_TABLES {
+            let sql = format!(
+                "SELECT t.id AS id, {user} AS user, {coin} AS coin, {columns} FROM {table} t",
+                user = user_expr,
+                coin = coin_expr,
+                columns = columns
+                    .iter()
+                    .map(|c| format!("t.{}", c))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                table = table,
+            );
+            // Streamed: the tables can be far larger than memory, the corrupt rows are few.
+            let mut rows = sqlx::query(&sql).fetch(self.reader.pool());
+            while let Some(row) = rows.try_next().await? {
+                for &column in columns {
+                    let value: Option<String> = row.try_get(column)?;
+                    let Some(error) = value
+                        .as_deref()
+                        .and_then(|v| Decimal::from_str(v).err())
+                    else {
+                        continue;
+                    };
+                    corrupt.push(Corrupt {
+                        table,
+                        id: row.try_get("id")?,
+                        user: row.try_get("user")?,
+                        coin: row.try_get("coin")?,
+                        column,
+                        value,
+                        reason: error.to_string(),
+                    });
+                    break;
+                }
+            }
+        }
 
-        for deposit in deposits {
-            let result = sqlx::query(
+        let mut report = QuarantineReport::default();
+        let mut tx = self.writer.begin().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO instance_metadata (key, value) \
+             VALUES ('quarantine_swept_version', ?)",
+        )
+        .bind(env!("CARGO_PKG_VERSION"))
+        .execute(&mut *tx)
+        .await?;
+        if corrupt.is_empty() {
+            tx.commit().await?;
+            return Ok(report);
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut pairs = BTreeSet::new();
+        for row in &corrupt {
+            let columns: Vec<String> =
+                sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+                    .bind(row.table)
+                    .fetch_all(&mut *tx)
+                    .await?;
+            let json = columns
+                .iter()
+                .map(|c| format!("'{c}', t.{c}", c = c))
+                .collect::<Vec<_>>()
+                .join(", ");
+            sqlx::query(&format!(
                 r#"
-                INSERT INTO deposits (user, time_ms, amount, tx_hash, event_key)
-                VALUES (?, ?, ?, ?, ?)
-                ON CONFLICT(event_key) DO NOTHING
+                INSERT INTO quarantined_rows (
+                    source_table, source_id, user, coin, column_name, value, reason, row_json,
+                    quarantined_at_ms
+                )
+                SELECT ?, t.id, ?, ?, ?, ?, ?, json_object({json}), ?
+                FROM {table} t WHERE t.id = ?
                 "#,
-            )
-            .bind(deposit.user.as_str())
-            .bind(deposit.time_ms.as_i64())
-            .bind(deposit.amount.to_canonical_string())
-            .bind(deposit.tx_hash.as_deref())
-            .bind(deposit.event_key.as_str())
+                json = json,
+                table = row.table,
+            ))
+            .bind(row.table)
+            .bind(&row.user)
+            .bind(&row.coin)
+            .bind(row.column)
+            .bind(&row.value)
+            .bind(&row.reason)
+            .bind(now)
+            .bind(row.id)
             .execute(&mut *tx)
             .await?;
 
-            if result.rows_affected() > 0 {
-                total_inserted += 1;
+            if row.table == "raw_fills" {
+                sqlx::query(
+                    "DELETE FROM fill_attributions WHERE fill_key = \
+                     (SELECT fill_key FROM raw_fills WHERE id = ?)",
+                )
+                .bind(row.id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            if let (Some(user), Some(coin)) = (&row.user, &row.coin) {
+                pairs.insert((user.clone(), coin.clone()));
             }
+            *report.moved.entry(row.table.to_string()).or_default() += 1;
         }
 
+        // Effects reference raw fills, so derived rows go before the corrupt rows do.
+        for (user, coin) in &pairs {
+            reset_derived_rows(
+                &mut tx,
+                &Address::new(user.clone()),
+                &Coin::new(coin.clone()),
+            )
+            .await?;
+        }
+        for row in &corrupt {
+            sqlx::query(&format!("DELETE FROM {} WHERE id = ?", row.table))
+                .bind(row.id)
+                .execute(&mut *tx)
+                .await?;
+        }
         tx.commit().await?;
-        Ok(total_inserted)
+
+        report.reset_pairs = pairs
+            .into_iter()
+            .map(|(user, coin)| (Address::new(user), Coin::new(coin)))
+            .collect();
+        Ok(report)
     }
 
-    /// Query deposits for a user within a time range.
+    /// Crate version of the build that last completed [`Self::quarantine_corrupt_rows`] on
+    /// this database, or `None` if none has.
     ///
     /// # Errors
     /// Returns an error if the query fails.
-    pub async fn query_deposits(
+    pub async fn quarantine_swept_version(&self) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT value FROM instance_metadata WHERE key = 'quarantine_swept_version'",
+        )
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await
+    }
+
+    /// Most recently quarantined rows, newest first, optionally for one user.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_quarantined_rows(
         &self,
-        user: &Address,
-        from_ms: i64,
-        to_ms: i64,
-    ) -> Result<Vec<Deposit>, sqlx::Error> {
+        user: Option<&Address>,
+        limit: i64,
+    ) -> Result<Vec<QuarantinedRow>, sqlx::Error> {
         let rows = sqlx::query(
             r#"
-            SELECT user, time_ms, amount, tx_hash, event_key
-            FROM deposits
-            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
-            ORDER BY time_ms ASC, event_key ASC
+            SELECT id, source_table, source_id, user, coin, column_name, value, reason, row_json,
+                   quarantined_at_ms
+            FROM quarantined_rows
+            WHERE (? IS NULL OR user = ?)
+            ORDER BY id DESC
+            LIMIT ?
             "#,
         )
-        .bind(user.as_str())
-        .bind(from_ms)
-        .bind(to_ms)
-        .fetch_all(&self.pool)
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .bind(limit)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        let deposits = rows
-            .iter()
-            .map(|row| {
-                let user: String = row.get("user");
-                let time_ms: i64 = row.get("time_ms");
-                let amount_str: String = row.get("amount");
-                let tx_hash: Option<String> = row.get("tx_hash");
-                let event_key: String = row.get("event_key");
-
-                let amount = Decimal::from_str(&amount_str).unwrap_or_else(|e| {
-                    warn!(
-                        event_key = %event_key,
-                        amount = %amount_str,
-                        error = %e,
-                        "Failed to parse deposit amount decimal, using default"
-                    );
-                    Decimal::default()
-                });
-
-                Deposit {
-                    event_key,
-                    user: Address::new(user),
-                    time_ms: TimeMs::new(time_ms),
-                    amount,
-                    tx_hash,
-                }
+        Ok(rows
+            .into_iter()
+            .map(|row| QuarantinedRow {
+                id: row.get("id"),
+                source_table: row.get("source_table"),
+                source_id: row.get("source_id"),
+                user: row.get::<Option<String>, _>("user").map(Address::new),
+                coin: row.get::<Option<String>, _>("coin").map(Coin::new),
+                column: row.get("column_name"),
+                value: row.get("value"),
+                reason: row.get("reason"),
+                row: row.get("row_json"),
+                quarantined_at_ms: row.get("quarantined_at_ms"),
             })
-            .collect();
-
-        Ok(deposits)
+            .collect())
     }
 
-    /// Query raw fills for a user and coin within a time range.
+    /// Count quarantined rows per source table, optionally for one user.
     ///
     /// # Errors
     /// Returns an error if the query fails.
-    pub async fn query_raw_fills(
+    pub async fn count_quarantined_rows(
         &self,
-        user: &Address,
-        coin: &Coin,
-        from_ms: i64,
-        to_ms: i64,
-    ) -> Result<Vec<Fill>, sqlx::Error> {
-        self.query_fills(
-            user,
-            Some(coin),
-            Some(TimeMs::new(from_ms)),
-            Some(TimeMs::new(to_ms)),
+        user: Option<&Address>,
+    ) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT source_table, COUNT(*) AS count
+            FROM quarantined_rows
+            WHERE (? IS NULL OR user = ?)
+            GROUP BY source_table
+            "#,
         )
-        .await
+        .bind(user.map(|u| u.as_str()))
+        .bind(user.map(|u| u.as_str()))
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("source_table"), row.get("count")))
+            .collect())
     }
 
-    /// Query fills for a user with optional coin and time window.
+    /// Forget a quarantined row once it has been repaired or re-ingested.
     ///
     /// # Errors
-    /// Returns an error if the query fails.
-    pub async fn query_fills(
+    /// Returns an error if the delete fails.
+    pub async fn delete_quarantined_row(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM quarantined_rows WHERE id = ?")
+            .bind(id)
+            .execute(&mut *self.writer.acquire().await?)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the member addresses of a named account, creating it if needed.
+    ///
+    /// # Errors
+    /// Returns an error if any statement fails; the transaction is rolled back.
+    pub async fn replace_account_group(
         &self,
-        user: &Address,
-        coin: Option<&Coin>,
-        from_ms: Option<TimeMs>,
-        to_ms: Option<TimeMs>,
-    ) -> Result<Vec<Fill>, sqlx::Error> {
-        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
-        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+        name: &str,
+        members: &[Address],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
 
-        let (sql, binds_coin) = if coin.is_some() {
-            (
-                r#"
-                SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                       builder_fee, tid, oid, fill_key
-                FROM raw_fills
-                WHERE user = ? AND coin = ? AND time_ms >= ? AND time_ms <= ?
-                ORDER BY time_ms ASC, tid ASC, oid ASC, fill_key ASC
-                "#,
-                true,
-            )
-        } else {
-            (
-                r#"
-                SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                       builder_fee, tid, oid, fill_key
-                FROM raw_fills
-                WHERE user = ? AND time_ms >= ? AND time_ms <= ?
-                ORDER BY time_ms ASC, tid ASC, oid ASC, fill_key ASC
-                "#,
-                false,
-            )
-        };
+        sqlx::query("DELETE FROM account_groups WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
 
-        let mut query = sqlx::query(sql).bind(user.as_str());
-        if binds_coin {
-            query = query.bind(coin.expect("binds_coin implies coin is Some").as_str());
+        for member in members {
+            sqlx::query("INSERT OR IGNORE INTO account_groups (name, address) VALUES (?, ?)")
+                .bind(name)
+                .bind(member.as_str())
+                .execute(&mut *tx)
+                .await?;
         }
-        query = query.bind(from_ms).bind(to_ms);
 
-        let rows = query.fetch_all(&self.pool).await?;
-
-        let fills = rows
-            .iter()
-            .map(|row| {
-                let side_str: String = row.get("side");
-                let side = match side_str.as_str() {
-                    "buy" => Side::Buy,
-                    "sell" => Side::Sell,
-                    _ => Side::Buy, // Default fallback
-                };
+        tx.commit().await
+    }
 
-                let px_str: String = row.get("px");
-                let sz_str: String = row.get("sz");
-                let fee_str: String = row.get("fee");
-                let closed_pnl_str: String = row.get("closed_pnl");
-                let builder_fee_opt: Option<String> = row.get("builder_fee");
-                let fill_key: String = row.get("fill_key");
+    /// Delete a named account. Returns whether it existed.
+    ///
+    /// # Errors
+    /// Returns an error if the delete fails.
+    pub async fn delete_account_group(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM account_groups WHERE name = ?")
+            .bind(name)
+            .execute(&mut *self.writer.acquire().await?)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-                let mut fill = Fill::new(
-                    TimeMs::new(row.get("time_ms")),
-                    Address::new(row.get("user")),
-                    Coin::new(row.get("coin")),
-                    side,
-                    Decimal::from_str(&px_str).unwrap_or_default(),
-                    Decimal::from_str(&sz_str).unwrap_or_default(),
-                    Decimal::from_str(&fee_str).unwrap_or_default(),
-                    Decimal::from_str(&closed_pnl_str).unwrap_or_default(),
-                    builder_fee_opt.and_then(|s| Decimal::from_str(&s).ok()),
-                    row.get("tid"),
-                    row.get("oid"),
-                );
-                fill.fill_key = fill_key;
-                fill
-            })
-            .collect();
+    /// Member addresses of a named account, sorted; empty when the account does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_account_group(&self, name: &str) -> Result<Vec<Address>, sqlx::Error> {
+        let rows = sqlx::query("SELECT address FROM account_groups WHERE name = ? ORDER BY address")
+            .bind(name)
+            .fetch_all(&mut *self.reader.acquire().await?)
+            .await?;
 
-        Ok(fills)
+        Ok(rows
+            .into_iter()
+            .map(|row| Address::new(row.get("address")))
+            .collect())
     }
 
-    /// Query distinct coins for a user within an optional time range.
-    ///
-    /// Used to determine which per-coin compile pipelines should run.
+    /// Every named account with its sorted member addresses.
     ///
     /// # Errors
     /// Returns an error if the query fails.
-    pub async fn query_distinct_coins(
+    pub async fn query_account_groups(
         &self,
-        user: &Address,
-        from_ms: Option<TimeMs>,
-        to_ms: Option<TimeMs>,
-    ) -> Result<Vec<Coin>, sqlx::Error> {
-        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
-        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+    ) -> Result<BTreeMap<String, Vec<Address>>, sqlx::Error> {
+        let rows = sqlx::query("SELECT name, address FROM account_groups ORDER BY name, address")
+            .fetch_all(&mut *self.reader.acquire().await?)
+            .await?;
 
-        let rows = sqlx::query(
+        let mut groups: BTreeMap<String, Vec<Address>> = BTreeMap::new();
+        for row in rows {
+            groups
+                .entry(row.get("name"))
+                .or_default()
+                .push(Address::new(row.get("address")));
+        }
+        Ok(groups)
+    }
+
+    /// Create a competition or replace its definition and users.
+    ///
+    /// # Errors
+    /// Returns an error if the write fails.
+    pub async fn upsert_competition(&self, competition: &Competition) -> Result<(), sqlx::Error> {
+        let prizes = serde_json::Value::from(competition.prizes.clone()).to_string();
+        let mut tx = self.writer.begin().await?;
+
+        sqlx::query(
             r#"
-            SELECT DISTINCT coin
-            FROM raw_fills
-            WHERE user = ? AND time_ms >= ? AND time_ms <= ?
-            ORDER BY coin ASC
+            INSERT INTO competitions (name, from_ms, to_ms, metric, builder_only, prizes)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                from_ms = excluded.from_ms,
+                to_ms = excluded.to_ms,
+                metric = excluded.metric,
+                builder_only = excluded.builder_only,
+                prizes = excluded.prizes
             "#,
         )
-        .bind(user.as_str())
-        .bind(from_ms)
-        .bind(to_ms)
-        .fetch_all(&self.pool)
+        .bind(&competition.name)
+        .bind(competition.from_ms.as_i64())
+        .bind(competition.to_ms.as_i64())
+        .bind(&competition.metric)
+        .bind(competition.builder_only)
+        .bind(prizes)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| Coin::new(row.get::<String, _>("coin")))
-            .collect())
+        sqlx::query("DELETE FROM competition_users WHERE name = ?")
+            .bind(&competition.name)
+            .execute(&mut *tx)
+            .await?;
+        for user in &competition.users {
+            sqlx::query("INSERT OR IGNORE INTO competition_users (name, address) VALUES (?, ?)")
+                .bind(&competition.name)
+                .bind(user.as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
     }
 
-    /// Query position snapshots for a user with optional coin and time window.
+    /// Delete a competition, its users, and its frozen results. Returns whether it existed.
     ///
-    /// Joins lifecycles to expose lifecycle-level taint flags for builder-only filtering.
+    /// # Errors
+    /// Returns an error if the delete fails.
+    pub async fn delete_competition(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        for table in ["competition_users", "competition_results"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE name = ?", table))
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let result = sqlx::query("DELETE FROM competitions WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// One competition, or `None` when it does not exist.
     ///
     /// # Errors
     /// Returns an error if the query fails.
-    pub async fn query_position_snapshots(
-        &self,
-        user: &Address,
-        coin: Option<&Coin>,
-        from_ms: Option<TimeMs>,
-        to_ms: Option<TimeMs>,
-    ) -> Result<Vec<PositionSnapshotRow>, sqlx::Error> {
-        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
-        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+    pub async fn query_competition(&self, name: &str) -> Result<Option<Competition>, sqlx::Error> {
+        Ok(self
+            .query_competitions_where(Some(name))
+            .await?
+            .into_iter()
+            .next())
+    }
 
-        let (sql, binds_coin) = if coin.is_some() {
-            (
-                r#"
-                SELECT ps.time_ms, ps.seq, ps.coin, ps.net_size, ps.avg_entry_px, ps.lifecycle_id, pl.is_tainted
-                FROM position_snapshots ps
-                JOIN position_lifecycles pl ON ps.lifecycle_id = pl.id
-                WHERE ps.user = ? AND ps.coin = ? AND ps.time_ms >= ? AND ps.time_ms <= ?
-                ORDER BY ps.time_ms ASC, ps.seq ASC, ps.coin ASC, ps.lifecycle_id ASC
-                "#,
-                true,
-            )
-        } else {
-            (
-                r#"
-                SELECT ps.time_ms, ps.seq, ps.coin, ps.net_size, ps.avg_entry_px, ps.lifecycle_id, pl.is_tainted
-                FROM position_snapshots ps
-                JOIN position_lifecycles pl ON ps.lifecycle_id = pl.id
-                WHERE ps.user = ? AND ps.time_ms >= ? AND ps.time_ms <= ?
-                ORDER BY ps.time_ms ASC, ps.seq ASC, ps.coin ASC, ps.lifecycle_id ASC
-                "#,
-                false,
-            )
-        };
+    /// Every competition, by name.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_competitions(&self) -> Result<Vec<Competition>, sqlx::Error> {
+        self.query_competitions_where(None).await
+    }
 
-        let mut query = sqlx::query(sql).bind(user.as_str());
-        if binds_coin {
-            query = query.bind(coin.expect("binds_coin implies coin is Some").as_str());
-        }
-        query = query.bind(from_ms).bind(to_ms);
+    async fn query_competitions_where(
+        &self,
+        name: Option<&str>,
+    ) -> Result<Vec<Competition>, sqlx::Error> {
+        let mut conn = self.reader.acquire().await?;
+        let rows = sqlx::query(
+            r#"
+            SELECT name, from_ms, to_ms, metric, builder_only, prizes
+            FROM competitions
+            WHERE ? IS NULL OR name = ?
+            ORDER BY name
+            "#,
+        )
+        .bind(name)
+        .bind(name)
+        .fetch_all(&mut *conn)
+        .await?;
+        let users = sqlx::query(
+            r#"
+            SELECT name, address
+            FROM competition_users
+            WHERE ? IS NULL OR name = ?
+            ORDER BY name, address
+            "#,
+        )
+        .bind(name)
+        .bind(name)
+        .fetch_all(&mut *conn)
+        .await?;
 
-        let rows = query.fetch_all(&self.pool).await?;
-        Ok(rows
-            .into_iter()
-            .map(|row| PositionSnapshotRow {
-                time_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
-                seq: row.get::<i32, _>("seq"),
-                coin: Coin::new(row.get::<String, _>("coin")),
-                net_size: row.get::<String, _>("net_size"),
-                avg_entry_px: row.get::<String, _>("avg_entry_px"),
-                lifecycle_id: row.get::<i64, _>("lifecycle_id"),
-                lifecycle_tainted: row.get::<i32, _>("is_tainted") != 0,
+        let mut users_by_name: BTreeMap<String, Vec<Address>> = BTreeMap::new();
+        for row in users {
+            users_by_name
+                .entry(row.get("name"))
+                .or_default()
+                .push(Address::new(row.get("address")));
+        }
+
+        rows.into_iter()
+            .map(|row| {
+                let name: String = row.get("name");
+                let prizes: String = row.get("prizes");
+                Ok(Competition {
+                    users: users_by_name.remove(&name).unwrap_or_default(),
+                    from_ms: TimeMs::new(row.get("from_ms")),
+                    to_ms: TimeMs::new(row.get("to_ms")),
+                    metric: row.get("metric"),
+                    builder_only: row.get("builder_only"),
+                    prizes: serde_json::from_str(&prizes)
+                        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                    name,
+                })
             })
-            .collect())
+            .collect()
     }
 
-    /// Get a raw fill by its fill_key.
+    /// Every strategy rule, in the order they are tried.
     ///
     /// # Errors
-    /// Returns an error if the query fails.
-    pub async fn get_raw_fill_by_key(&self, fill_key: &str) -> Result<Option<Fill>, sqlx::Error> {
-        let row = sqlx::query(
+    /// Returns an error if the query fails or a stored rule does not parse.
+    pub async fn query_strategy_rules(&self) -> Result<Vec<StrategyRule>, sqlx::Error> {
+        read_strategy_rules(&mut *self.reader.acquire().await?).await
+    }
+
+    /// Create a strategy rule or replace it. Stored lifecycles keep their labels until
+    /// [`Self::relabel_lifecycle_strategies`] passes over them.
+    ///
+    /// # Errors
+    /// Returns an error if the write fails.
+    pub async fn upsert_strategy_rule(&self, rule: &StrategyRule) -> Result<(), sqlx::Error> {
+        let coins: Vec<&str> = rule.coins.iter().map(|c| c.as_str()).collect();
+        let tags: Vec<&str> = rule.tags.iter().map(|t| t.as_str()).collect();
+        sqlx::query(
             r#"
-            SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                   builder_fee, tid, oid, fill_key
-            FROM raw_fills
-            WHERE fill_key = ?
+            INSERT INTO strategy_rules (name, priority, coins, from_ms, to_ms, tags)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                priority = excluded.priority,
+                coins = excluded.coins,
+                from_ms = excluded.from_ms,
+                to_ms = excluded.to_ms,
+                tags = excluded.tags
             "#,
         )
-        .bind(fill_key)
-        .fetch_optional(&self.pool)
+        .bind(&rule.name)
+        .bind(rule.priority)
+        .bind(serde_json::Value::from(coins).to_string())
+        .bind(rule.from_ms.map(|t| t.as_i64()))
+        .bind(rule.to_ms.map(|t| t.as_i64()))
+        .bind(serde_json::Value::from(tags).to_string())
+        .execute(&mut *self.writer.acquire().await?)
         .await?;
+        Ok(())
+    }
 
-        Ok(row.map(|r| {
-            let side_str: String = r.get("side");
-            let side = match side_str.as_str() {
-                "buy" => Side::Buy,
-                "sell" => Side::Sell,
-                _ => Side::Buy,
-            };
+    /// Delete a strategy rule; returns whether it existed. As with
+    /// [`Self::upsert_strategy_rule`], stored labels change once relabelled.
+    ///
+    /// # Errors
+    /// Returns an error if the delete fails.
+    pub async fn delete_strategy_rule(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM strategy_rules WHERE name = ?")
+            .bind(name)
+            .execute(&mut *self.writer.acquire().await?)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Label up to `limit` lifecycles with ids above `after_id`, in id order, with the
+    /// current strategy rules, in one transaction.
+    ///
+    /// Returns the users with a lifecycle whose label changed, and the last id labelled,
+    /// which is `None` once no lifecycle is left. Passing that id back walks every
+    /// lifecycle in short transactions.
+    ///
+    /// # Errors
+    /// Returns an error if the query or an update fails.
+    pub async fn relabel_lifecycle_strategies(
+        &self,
+        after_id: i64,
+        limit: usize,
+    ) -> Result<(Vec<Address>, Option<i64>), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        let relabelled =
+            write_lifecycle_strategies(&mut tx, LifecycleScope::After { after_id, limit }).await?;
+        tx.commit().await?;
+        Ok(relabelled)
+    }
 
-            let px_str: String = r.get("px");
-            let sz_str: String = r.get("sz");
-            let fee_str: String = r.get("fee");
-            let closed_pnl_str: String = r.get("closed_pnl");
-            let builder_fee_opt: Option<String> = r.get("builder_fee");
-            let fill_key: String = r.get("fill_key");
-
-            let mut fill = Fill::new(
-                TimeMs::new(r.get("time_ms")),
-                Address::new(r.get("user")),
-                Coin::new(r.get("coin")),
-                side,
-                Decimal::from_str(&px_str).unwrap_or_default(),
-                Decimal::from_str(&sz_str).unwrap_or_default(),
-                Decimal::from_str(&fee_str).unwrap_or_default(),
-                Decimal::from_str(&closed_pnl_str).unwrap_or_default(),
-                builder_fee_opt.and_then(|s| Decimal::from_str(&s).ok()),
-                r.get("tid"),
-                r.get("oid"),
+    /// Strategies of the given lifecycles; unlabelled lifecycles are left out.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_lifecycle_strategies(
+        &self,
+        lifecycle_ids: &[i64],
+    ) -> Result<HashMap<i64, String>, sqlx::Error> {
+        let mut conn = self.reader.acquire().await?;
+        let mut strategies = HashMap::new();
+        for chunk in lifecycle_ids.chunks(SQLITE_MAX_BIND_PARAMS) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let sql = format!(
+                "SELECT id, strategy FROM position_lifecycles \
+                 WHERE id IN ({}) AND strategy IS NOT NULL",
+                placeholders
             );
-            fill.fill_key = fill_key;
-            fill
-        }))
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(id);
+            }
+            for row in query.fetch_all(&mut *conn).await? {
+                strategies.insert(row.get("id"), row.get("strategy"));
+            }
+        }
+        Ok(strategies)
     }
 
-    /// Store compile state for a user and coin.
+    /// Store the final standings of a competition unless they were already stored.
+    ///
+    /// Returns whether these rows were written; a competition's results are never
+    /// replaced.
     ///
     /// # Errors
-    /// Returns an error if the insert fails.
-    pub async fn store_compile_state(
+    /// Returns an error if the write fails.
+    pub async fn insert_competition_results(
         &self,
-        user: &Address,
-        coin: &Coin,
-        last_compiled_time_ms: Option<i64>,
-        last_compiled_fill_key: Option<&str>,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            INSERT INTO compile_state (user, coin, last_compiled_time_ms, last_compiled_fill_key, compile_version)
-            VALUES (?, ?, ?, ?, 1)
-            ON CONFLICT(user, coin) DO UPDATE SET
-                last_compiled_time_ms = excluded.last_compiled_time_ms,
-                last_compiled_fill_key = excluded.last_compiled_fill_key,
-                compile_version = compile_version + 1
-            "#,
-        )
-        .bind(user.as_str())
-        .bind(coin.as_str())
-        .bind(last_compiled_time_ms)
-        .bind(last_compiled_fill_key)
-        .execute(&self.pool)
-        .await?;
+        name: &str,
+        rows: &[CompetitionResultRow],
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        let existing = sqlx::query("SELECT 1 FROM competition_results WHERE name = ? LIMIT 1")
+            .bind(name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if existing.is_some() || rows.is_empty() {
+            return Ok(false);
+        }
 
-        Ok(())
+        for chunk in rows.chunks(rows_per_insert(6)) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO competition_results (name, user, rank, value, trade_count, tainted) ",
+            );
+            query.push_values(chunk, |mut row, result| {
+                row.push_bind(name)
+                    .push_bind(result.user.as_str())
+                    .push_bind(result.rank)
+                    .push_bind(result.value.to_canonical_string())
+                    .push_bind(result.trade_count)
+                    .push_bind(result.tainted);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
     }
 
-    /// Get compile state for a user and coin.
+    /// Frozen standings of a competition, best first; empty until it has been frozen.
     ///
     /// # Errors
     /// Returns an error if the query fails.
-    pub async fn get_compile_state(
+    pub async fn query_competition_results(
         &self,
-        user: &Address,
-        coin: &Coin,
-    ) -> Result<Option<(Option<i64>, Option<String>)>, sqlx::Error> {
-        let row = sqlx::query(
-            "SELECT last_compiled_time_ms, last_compiled_fill_key FROM compile_state WHERE user = ? AND coin = ?",
+        name: &str,
+    ) -> Result<Vec<CompetitionResultRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user, rank, value, trade_count, tainted
+            FROM competition_results
+            WHERE name = ?
+            ORDER BY rank
+            "#,
         )
-        .bind(user.as_str())
-        .bind(coin.as_str())
-        .fetch_optional(&self.pool)
+        .bind(name)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        Ok(row.map(|r| {
-            (
-                r.get("last_compiled_time_ms"),
-                r.get("last_compiled_fill_key"),
-            )
-        }))
+        rows
+            .into_iter()
+            .map(|row| {
+                Ok(CompetitionResultRow {
+                    user: Address::new(row.get("user")),
+                    rank: row.get("rank"),
+                    value: decimal_column(&row, "value")?,
+                    trade_count: row.get("trade_count"),
+                    tainted: row.get("tainted"),
+                })
+            })
+            .collect()
     }
 
-    /// Query fills after a watermark fill_key for incremental compilation.
+    /// Query fills after a watermark fill for incremental compilation, in
+    /// [`FillOrderingKey`] order.
     ///
     /// # Arguments
     /// * `user` - User address
     /// * `coin` - Coin/asset symbol
-    /// * `after_fill_key` - Only return fills with fill_key > this value (None for all)
+    /// * `after_fill_key` - Only return fills that order after the stored fill with this
+    ///   key (None for all)
     ///
     /// # Errors
     /// Returns an error if the query fails.
@@ -572,92 +3294,45 @@ impl Repository {
         coin: &Coin,
         after_fill_key: Option<&str>,
     ) -> Result<Vec<Fill>, sqlx::Error> {
+        self.stream_fills_after_watermark(user, coin, after_fill_key)
+            .try_collect()
+            .await
+    }
+
+    /// Streaming form of [`Self::query_fills_after_watermark`], used by the compiler.
+    pub fn stream_fills_after_watermark<'a>(
+        &'a self,
+        user: &'a Address,
+        coin: &'a Coin,
+        after_fill_key: Option<&'a str>,
+    ) -> BoxStream<'a, Result<Fill, sqlx::Error>> {
         let sql = if after_fill_key.is_some() {
             r#"
             SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                   builder_fee, tid, oid, fill_key
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
             FROM raw_fills
-            WHERE user = ? AND coin = ? AND fill_key > ?
-            ORDER BY fill_key ASC
+            WHERE user = ? AND coin = ?
+              AND ordering_key > (SELECT ordering_key FROM raw_fills WHERE fill_key = ?)
+            ORDER BY ordering_key ASC
             "#
         } else {
             r#"
             SELECT user, coin, time_ms, side, px, sz, fee, closed_pnl,
-                   builder_fee, tid, oid, fill_key
+                   builder_fee, tid, oid, crossed, fill_type, twap_id, fill_key
             FROM raw_fills
             WHERE user = ? AND coin = ?
-            ORDER BY fill_key ASC
+            ORDER BY ordering_key ASC
             "#
         };
 
         let mut query = sqlx::query(sql).bind(user.as_str()).bind(coin.as_str());
-
         if let Some(key) = after_fill_key {
             query = query.bind(key);
         }
-
-        let rows = query.fetch_all(&self.pool).await?;
-
-        let fills = rows
-            .iter()
-            .map(|row| {
-                let side_str: String = row.get("side");
-                let side = match side_str.as_str() {
-                    "buy" => Side::Buy,
-                    "sell" => Side::Sell,
-                    _ => Side::Buy,
-                };
-
-                let px_str: String = row.get("px");
-                let sz_str: String = row.get("sz");
-                let fee_str: String = row.get("fee");
-                let closed_pnl_str: String = row.get("closed_pnl");
-                let builder_fee_opt: Option<String> = row.get("builder_fee");
-                let fill_key: String = row.get("fill_key");
-
-                // Parse decimals with warning on failure
-                let px = Decimal::from_str(&px_str).unwrap_or_else(|e| {
-                    warn!(fill_key = %fill_key, px = %px_str, error = %e, "Failed to parse px decimal, using default");
-                    Decimal::default()
-                });
-                let sz = Decimal::from_str(&sz_str).unwrap_or_else(|e| {
-                    warn!(fill_key = %fill_key, sz = %sz_str, error = %e, "Failed to parse sz decimal, using default");
-                    Decimal::default()
-                });
-                let fee = Decimal::from_str(&fee_str).unwrap_or_else(|e| {
-                    warn!(fill_key = %fill_key, fee = %fee_str, error = %e, "Failed to parse fee decimal, using default");
-                    Decimal::default()
-                });
-                let closed_pnl = Decimal::from_str(&closed_pnl_str).unwrap_or_else(|e| {
-                    warn!(fill_key = %fill_key, closed_pnl = %closed_pnl_str, error = %e, "Failed to parse closed_pnl decimal, using default");
-                    Decimal::default()
-                });
-                let builder_fee = builder_fee_opt.and_then(|s| {
-                    Decimal::from_str(&s).map_err(|e| {
-                        warn!(fill_key = %fill_key, builder_fee = %s, error = %e, "Failed to parse builder_fee decimal, ignoring");
-                        e
-                    }).ok()
-                });
-
-                let mut fill = Fill::new(
-                    TimeMs::new(row.get("time_ms")),
-                    Address::new(row.get("user")),
-                    Coin::new(row.get("coin")),
-                    side,
-                    px,
-                    sz,
-                    fee,
-                    closed_pnl,
-                    builder_fee,
-                    row.get("tid"),
-                    row.get("oid"),
-                );
-                fill.fill_key = fill_key;
-                fill
-            })
-            .collect();
-
-        Ok(fills)
+        query
+            .fetch(self.reader.pool())
+            .and_then(|row| async move { fill_from_row(&row) })
+            .boxed()
     }
 
     /// Insert all derived tables (lifecycles, snapshots, effects) atomically in a single transaction.
@@ -682,74 +3357,42 @@ impl Repository {
         snapshots: &[Snapshot],
         effects: &[Effect],
     ) -> Result<(), sqlx::Error> {
-        let mut tx = self.pool.begin().await?;
-
-        // Insert lifecycles with explicit IDs from the tracker
-        for lifecycle in lifecycles {
-            sqlx::query(
-                r#"
-                INSERT OR REPLACE INTO position_lifecycles
-                (id, user, coin, start_time_ms, end_time_ms, is_tainted, taint_reason)
-                VALUES (?, ?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(lifecycle.id)
-            .bind(lifecycle.user.as_str())
-            .bind(lifecycle.coin.as_str())
-            .bind(lifecycle.start_time_ms.as_i64())
-            .bind(lifecycle.end_time_ms.map(|t| t.as_i64()))
-            .bind(0) // is_tainted - will be updated after taint computation
-            .bind::<Option<String>>(None) // taint_reason
-            .execute(&mut *tx)
-            .await?;
-        }
-
-        // Insert snapshots
-        for snapshot in snapshots {
-            sqlx::query(
-                r#"
-                INSERT OR REPLACE INTO position_snapshots
-                (user, coin, time_ms, seq, net_size, avg_entry_px, lifecycle_id, is_tainted)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(user.as_str())
-            .bind(coin.as_str())
-            .bind(snapshot.time_ms.as_i64())
-            .bind(snapshot.seq)
-            .bind(snapshot.net_size.to_canonical_string())
-            .bind(snapshot.avg_entry_px.to_canonical_string())
-            .bind(snapshot.lifecycle_id)
-            .bind(0) // is_tainted
-            .execute(&mut *tx)
-            .await?;
-        }
-
-        // Insert effects
-        for effect in effects {
-            let effect_type_str = match effect.effect_type {
-                EffectType::Open => "open",
-                EffectType::Close => "close",
-            };
+        let mut tx = self.writer.begin().await?;
+        write_derived_tables(&mut tx, user, coin, lifecycles, snapshots, effects).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-            sqlx::query(
-                r#"
-                INSERT OR REPLACE INTO fill_effects
-                (fill_key, lifecycle_id, effect_type, qty, notional, fee, closed_pnl)
-                VALUES (?, ?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(&effect.fill_key)
-            .bind(effect.lifecycle_id)
-            .bind(effect_type_str)
-            .bind(effect.qty.to_canonical_string())
-            .bind(effect.notional.to_canonical_string())
-            .bind(effect.fee.to_canonical_string())
-            .bind(effect.closed_pnl.to_canonical_string())
-            .execute(&mut *tx)
+    /// Store a compile's output and advance its watermark in one transaction.
+    ///
+    /// Writes the derived tables, applies `taint_updates` (as in
+    /// [`Self::update_lifecycle_taints`]), records the invariant `violations` found in the
+    /// compiled fills and stores `watermark` as the last compiled `(time_ms, fill_key)`. A
+    /// failure at any step leaves the data, the violations and the watermark as they were,
+    /// so the next compile redoes the same fills without recording their violations twice.
+    ///
+    /// # Errors
+    /// Returns an error if any database operation fails.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn commit_compile(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        lifecycles: &[Lifecycle],
+        snapshots: &[Snapshot],
+        effects: &[Effect],
+        taint_updates: &[(i64, bool, Option<String>)],
+        violations: &[InvariantViolation],
+        watermark: (i64, &str),
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        write_derived_tables(&mut tx, user, coin, lifecycles, snapshots, effects).await?;
+        write_lifecycle_taints(&mut tx, taint_updates).await?;
+        write_invariant_violations(&mut tx, user, coin, violations, TimeMs::now().as_ms())
             .await?;
-        }
-
+        let ids: Vec<i64> = lifecycles.iter().map(|l| l.id).collect();
+        write_lifecycle_strategies(&mut tx, LifecycleScope::Ids(&ids)).await?;
+        write_compile_state(&mut tx, user, coin, Some(watermark.0), Some(watermark.1)).await?;
         tx.commit().await?;
         Ok(())
     }
@@ -763,14 +3406,14 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         for lifecycle in lifecycles {
             sqlx::query(
                 r#"
                 INSERT OR REPLACE INTO position_lifecycles
-                (id, user, coin, start_time_ms, end_time_ms, is_tainted, taint_reason)
-                VALUES (?, ?, ?, ?, ?, ?, ?)
+                (id, user, coin, start_time_ms, end_time_ms, is_tainted, taint_reason, closed_by)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
                 "#,
             )
             .bind(lifecycle.id)
@@ -780,6 +3423,7 @@ impl Repository {
             .bind(lifecycle.end_time_ms.map(|t| t.as_i64()))
             .bind(0) // is_tainted - will be updated in Phase 4
             .bind::<Option<String>>(None) // taint_reason
+            .bind(lifecycle.closed_by.map(|t| t.as_str()))
             .execute(&mut *tx)
             .await?;
         }
@@ -807,7 +3451,7 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         for snapshot in snapshots {
             sqlx::query(
@@ -842,7 +3486,7 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         for effect in effects {
             let effect_type_str = match effect.effect_type {
@@ -853,8 +3497,9 @@ impl Repository {
             sqlx::query(
                 r#"
                 INSERT OR REPLACE INTO fill_effects
-                (fill_key, lifecycle_id, effect_type, qty, notional, fee, closed_pnl)
-                VALUES (?, ?, ?, ?, ?, ?, ?)
+                (fill_key, lifecycle_id, effect_type, qty, notional, fee, builder_fee, closed_pnl,
+                 closed_pnl_e8)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
                 "#,
             )
             .bind(&effect.fill_key)
@@ -863,7 +3508,9 @@ impl Repository {
             .bind(effect.qty.to_canonical_string())
             .bind(effect.notional.to_canonical_string())
             .bind(effect.fee.to_canonical_string())
+            .bind(effect.builder_fee.to_canonical_string())
             .bind(effect.closed_pnl.to_canonical_string())
+            .bind(effect.closed_pnl.to_scaled_units())
             .execute(&mut *tx)
             .await?;
         }
@@ -880,10 +3527,10 @@ impl Repository {
         &self,
         user: &Address,
         coin: &Coin,
-    ) -> Result<Vec<(i64, Address, Coin, i64, Option<i64>)>, sqlx::Error> {
+    ) -> Result<Vec<LifecycleRow>, sqlx::Error> {
         let rows = sqlx::query(
             r#"
-            SELECT id, user, coin, start_time_ms, end_time_ms
+            SELECT id, user, coin, start_time_ms, end_time_ms, is_tainted, taint_reason, closed_by
             FROM position_lifecycles
             WHERE user = ? AND coin = ?
             ORDER BY start_time_ms ASC
@@ -891,19 +3538,22 @@ impl Repository {
         )
         .bind(user.as_str())
         .bind(coin.as_str())
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
         Ok(rows
             .iter()
-            .map(|row| {
-                (
-                    row.get::<i64, _>("id"),
-                    Address::new(row.get::<String, _>("user")),
-                    Coin::new(row.get::<String, _>("coin")),
-                    row.get::<i64, _>("start_time_ms"),
-                    row.get::<Option<i64>, _>("end_time_ms"),
-                )
+            .map(|row| LifecycleRow {
+                id: row.get("id"),
+                user: Address::new(row.get::<String, _>("user")),
+                coin: Coin::new(row.get::<String, _>("coin")),
+                start_time_ms: TimeMs::new(row.get("start_time_ms")),
+                end_time_ms: row.get::<Option<i64>, _>("end_time_ms").map(TimeMs::new),
+                is_tainted: row.get::<i64, _>("is_tainted") != 0,
+                taint_reason: row.get("taint_reason"),
+                closed_by: row
+                    .get::<Option<String>, _>("closed_by")
+                    .and_then(|s| s.parse().ok()),
             })
             .collect())
     }
@@ -916,10 +3566,10 @@ impl Repository {
         &self,
         user: &Address,
         coin: &Coin,
-    ) -> Result<Vec<(i64, i64, i64, i32, String, String)>, sqlx::Error> {
+    ) -> Result<Vec<SnapshotRowFull>, sqlx::Error> {
         let rows = sqlx::query(
             r#"
-            SELECT id, time_ms, lifecycle_id, seq, net_size, avg_entry_px
+            SELECT id, time_ms, lifecycle_id, seq, net_size, avg_entry_px, is_tainted
             FROM position_snapshots
             WHERE user = ? AND coin = ?
             ORDER BY time_ms ASC, seq ASC
@@ -927,22 +3577,24 @@ impl Repository {
         )
         .bind(user.as_str())
         .bind(coin.as_str())
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        Ok(rows
+        rows
             .iter()
             .map(|row| {
-                (
-                    row.get::<i64, _>("id"),
-                    row.get::<i64, _>("time_ms"),
-                    row.get::<i64, _>("lifecycle_id"),
-                    row.get::<i32, _>("seq"),
-                    row.get::<String, _>("net_size"),
-                    row.get::<String, _>("avg_entry_px"),
-                )
+                let lifecycle_id: i64 = row.get("lifecycle_id");
+                Ok(SnapshotRowFull {
+                    id: row.get("id"),
+                    time_ms: TimeMs::new(row.get("time_ms")),
+                    seq: row.get("seq"),
+                    lifecycle_id,
+                    net_size: decimal_column(row, "net_size")?,
+                    avg_entry_px: decimal_column(row, "avg_entry_px")?,
+                    is_tainted: row.get::<i64, _>("is_tainted") != 0,
+                })
             })
-            .collect())
+            .collect()
     }
 
     /// Update taint flags for lifecycles.
@@ -960,23 +3612,8 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
-
-        for (lifecycle_id, is_tainted, taint_reason) in taint_updates {
-            sqlx::query(
-                r#"
-                UPDATE position_lifecycles
-                SET is_tainted = ?, taint_reason = ?
-                WHERE id = ?
-                "#,
-            )
-            .bind(if *is_tainted { 1 } else { 0 })
-            .bind(taint_reason)
-            .bind(lifecycle_id)
-            .execute(&mut *tx)
-            .await?;
-        }
-
+        let mut tx = self.writer.begin().await?;
+        write_lifecycle_taints(&mut tx, taint_updates).await?;
         tx.commit().await?;
         Ok(())
     }
@@ -996,14 +3633,19 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         for (fill_key, attributed, mode, confidence, builder) in attributions {
+            let reason = AttributionReason::infer(
+                AttributionMode::parse(mode).unwrap_or(AttributionMode::Heuristic),
+                AttributionConfidence::parse(confidence).unwrap_or(AttributionConfidence::Low),
+                *attributed,
+            );
             sqlx::query(
                 r#"
                 INSERT OR REPLACE INTO fill_attributions
-                (fill_key, attributed, mode, confidence, builder)
-                VALUES (?, ?, ?, ?, ?)
+                (fill_key, attributed, mode, confidence, builder, score, reason)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
                 "#,
             )
             .bind(fill_key)
@@ -1011,6 +3653,8 @@ impl Repository {
             .bind(mode)
             .bind(confidence)
             .bind(builder)
+            .bind(i64::from(reason.score(*attributed, None, 0)))
+            .bind(reason.as_str())
             .execute(&mut *tx)
             .await?;
         }
@@ -1049,7 +3693,7 @@ impl Repository {
             query = query.bind(key);
         }
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
 
         Ok(rows
             .iter()
@@ -1089,53 +3733,98 @@ impl Repository {
             let placeholders = vec!["?"; chunk.len()].join(",");
             let sql = format!(
                 r#"
-                SELECT fill_key, attributed, mode, confidence, builder
-                FROM fill_attributions
-                WHERE fill_key IN ({})
+                SELECT fill_key, attributed, mode, confidence, builder, tolerance_tier,
+                       matched_log_key, score, reason, match_time_delta_ms
+                FROM fill_attributions
+                WHERE fill_key IN ({})
+                "#,
+                placeholders
+            );
+
+            let mut query = sqlx::query(&sql);
+            for key in chunk {
+                query = query.bind(key);
+            }
+
+            let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
+
+            for row in rows {
+                out.insert(row.get::<String, _>("fill_key"), attribution_from_row(&row));
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Query fills in a window joined with their stored attributions, for auditing.
+    ///
+    /// Fills without an attribution row are returned with `attribution: None`.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_attribution_audit(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<AttributionAuditRow>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let (sql, binds_coin) = if coin.is_some() {
+            (
+                r#"
+                SELECT rf.fill_key, rf.time_ms, rf.coin, rf.side, rf.px, rf.sz, rf.builder_fee,
+                       rf.tid, fa.fill_key AS attribution_key, fa.attributed, fa.mode,
+                       fa.confidence, fa.builder, fa.tolerance_tier, fa.matched_log_key,
+                       fa.score, fa.reason, fa.match_time_delta_ms
+                FROM raw_fills rf
+                LEFT JOIN fill_attributions fa ON fa.fill_key = rf.fill_key
+                WHERE rf.user = ? AND rf.coin = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC
+                "#,
+                true,
+            )
+        } else {
+            (
+                r#"
+                SELECT rf.fill_key, rf.time_ms, rf.coin, rf.side, rf.px, rf.sz, rf.builder_fee,
+                       rf.tid, fa.fill_key AS attribution_key, fa.attributed, fa.mode,
+                       fa.confidence, fa.builder, fa.tolerance_tier, fa.matched_log_key,
+                       fa.score, fa.reason, fa.match_time_delta_ms
+                FROM raw_fills rf
+                LEFT JOIN fill_attributions fa ON fa.fill_key = rf.fill_key
+                WHERE rf.user = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC
                 "#,
-                placeholders
-            );
-
-            let mut query = sqlx::query(&sql);
-            for key in chunk {
-                query = query.bind(key);
-            }
-
-            let rows = query.fetch_all(&self.pool).await?;
-
-            for row in rows {
-                let fill_key = row.get::<String, _>("fill_key");
-                let attributed = row.get::<i32, _>("attributed") != 0;
-                let mode_str = row.get::<String, _>("mode");
-                let confidence_str = row.get::<String, _>("confidence");
-                let builder_opt = row.get::<Option<String>, _>("builder");
-
-                let mode = match mode_str.as_str() {
-                    "heuristic" => AttributionMode::Heuristic,
-                    "logs" => AttributionMode::Logs,
-                    _ => AttributionMode::Heuristic,
-                };
-                let confidence = match confidence_str.as_str() {
-                    "exact" => AttributionConfidence::Exact,
-                    "fuzzy" => AttributionConfidence::Fuzzy,
-                    "low" => AttributionConfidence::Low,
-                    _ => AttributionConfidence::Low,
-                };
-                let builder = builder_opt.map(Address::new);
+                false,
+            )
+        };
 
-                out.insert(
-                    fill_key,
-                    Attribution {
-                        attributed,
-                        mode,
-                        confidence,
-                        builder,
-                    },
-                );
-            }
+        let mut query = sqlx::query(sql).bind(user.as_str());
+        if binds_coin {
+            query = query.bind(coin.expect("binds_coin implies coin is Some").as_str());
         }
+        query = query.bind(from_ms).bind(to_ms);
 
-        Ok(out)
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
+        Ok(rows
+            .iter()
+            .map(|row| AttributionAuditRow {
+                fill_key: row.get::<String, _>("fill_key"),
+                time_ms: TimeMs::new(row.get::<i64, _>("time_ms")),
+                coin: Coin::new(row.get::<String, _>("coin")),
+                side: row.get::<String, _>("side"),
+                px: row.get::<String, _>("px"),
+                sz: row.get::<String, _>("sz"),
+                builder_fee: row.get::<Option<String>, _>("builder_fee"),
+                tid: row.get::<Option<i64>, _>("tid"),
+                attribution: row
+                    .get::<Option<String>, _>("attribution_key")
+                    .map(|_| attribution_from_row(row)),
+            })
+            .collect())
     }
 
     /// Upsert full attribution records (including optional builder address).
@@ -1150,31 +3839,31 @@ impl Repository {
             return Ok(());
         }
 
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         for (fill_key, attribution) in attributions {
-            let mode = match attribution.mode {
-                AttributionMode::Heuristic => "heuristic",
-                AttributionMode::Logs => "logs",
-            };
-            let confidence = match attribution.confidence {
-                AttributionConfidence::Exact => "exact",
-                AttributionConfidence::Fuzzy => "fuzzy",
-                AttributionConfidence::Low => "low",
+            let time_delta_ms = match attribution.reason {
+                AttributionReason::Fields { time_delta_ms } => time_delta_ms,
+                _ => None,
             };
-
             sqlx::query(
                 r#"
                 INSERT OR REPLACE INTO fill_attributions
-                (fill_key, attributed, mode, confidence, builder)
-                VALUES (?, ?, ?, ?, ?)
+                (fill_key, attributed, mode, confidence, builder, tolerance_tier, matched_log_key,
+                 score, reason, match_time_delta_ms)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 "#,
             )
             .bind(fill_key)
             .bind(if attribution.attributed { 1 } else { 0 })
-            .bind(mode)
-            .bind(confidence)
+            .bind(attribution.mode.as_str())
+            .bind(attribution.confidence.as_str())
             .bind(attribution.builder.as_ref().map(|b| b.as_str()))
+            .bind(attribution.tolerance_tier.map(|t| t.as_str()))
+            .bind(attribution.matched_log_key.as_deref())
+            .bind(i64::from(attribution.score))
+            .bind(attribution.reason.as_str())
+            .bind(time_delta_ms)
             .execute(&mut *tx)
             .await?;
         }
@@ -1191,7 +3880,7 @@ impl Repository {
         &self,
         user: &Address,
         coin: &Coin,
-    ) -> Result<Vec<(i64, String, i64, String, String, String, String, String)>, sqlx::Error> {
+    ) -> Result<Vec<EffectRow>, sqlx::Error> {
         let rows = sqlx::query(
             r#"
             SELECT fe.id, fe.fill_key, fe.lifecycle_id, fe.effect_type, fe.qty, fe.notional, fe.fee, fe.closed_pnl
@@ -1203,24 +3892,29 @@ impl Repository {
         )
         .bind(user.as_str())
         .bind(coin.as_str())
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        Ok(rows
+        rows
             .iter()
             .map(|row| {
-                (
-                    row.get::<i64, _>("id"),
-                    row.get::<String, _>("fill_key"),
-                    row.get::<i64, _>("lifecycle_id"),
-                    row.get::<String, _>("effect_type"),
-                    row.get::<String, _>("qty"),
-                    row.get::<String, _>("notional"),
-                    row.get::<String, _>("fee"),
-                    row.get::<String, _>("closed_pnl"),
-                )
+                let lifecycle_id: i64 = row.get("lifecycle_id");
+                let effect_type = match row.get::<String, _>("effect_type").as_str() {
+                    "close" => EffectType::Close,
+                    _ => EffectType::Open,
+                };
+                Ok(EffectRow {
+                    id: row.get("id"),
+                    fill_key: row.get("fill_key"),
+                    lifecycle_id,
+                    effect_type,
+                    qty: decimal_column(row, "qty")?,
+                    notional: decimal_column(row, "notional")?,
+                    fee: decimal_column(row, "fee")?,
+                    closed_pnl: decimal_column(row, "closed_pnl")?,
+                })
             })
-            .collect())
+            .collect()
     }
 
     /// Query fill effects for PnL aggregation for a user with optional coin/time window.
@@ -1239,24 +3933,24 @@ impl Repository {
         let (sql, binds_coin) = if coin.is_some() {
             (
                 r#"
-                SELECT fe.lifecycle_id, fe.fee, fe.closed_pnl
+                SELECT fe.fill_key, fe.lifecycle_id, rf.time_ms, fe.fee, fe.closed_pnl, fe.builder_fee
                 FROM fill_effects fe
                 JOIN raw_fills rf ON rf.fill_key = fe.fill_key
                 JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
                 WHERE pl.user = ? AND pl.coin = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
-                ORDER BY fe.id ASC
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
                 "#,
                 true,
             )
         } else {
             (
                 r#"
-                SELECT fe.lifecycle_id, fe.fee, fe.closed_pnl
+                SELECT fe.fill_key, fe.lifecycle_id, rf.time_ms, fe.fee, fe.closed_pnl, fe.builder_fee
                 FROM fill_effects fe
                 JOIN raw_fills rf ON rf.fill_key = fe.fill_key
                 JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
                 WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
-                ORDER BY fe.id ASC
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
                 "#,
                 false,
             )
@@ -1268,31 +3962,26 @@ impl Repository {
         }
         query = query.bind(from_ms).bind(to_ms);
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
 
-        Ok(rows
+        rows
             .iter()
             .map(|row| {
-                let fee_str: String = row.get("fee");
-                let closed_pnl_str: String = row.get("closed_pnl");
                 let lifecycle_id: i64 = row.get("lifecycle_id");
 
-                let fee = Decimal::from_str(&fee_str).unwrap_or_else(|e| {
-                    warn!(lifecycle_id, fee = %fee_str, error = %e, "Failed to parse fee decimal, using default");
-                    Decimal::default()
-                });
-                let closed_pnl = Decimal::from_str(&closed_pnl_str).unwrap_or_else(|e| {
-                    warn!(lifecycle_id, closed_pnl = %closed_pnl_str, error = %e, "Failed to parse closed_pnl decimal, using default");
-                    Decimal::default()
-                });
+                let fee = decimal_column(row, "fee")?;
+                let closed_pnl = decimal_column(row, "closed_pnl")?;
 
-                PnlFillEffect {
+                Ok(PnlFillEffect {
+                    fill_key: row.get("fill_key"),
                     lifecycle_id,
+                    time_ms: TimeMs::new(row.get("time_ms")),
                     fee,
+                    builder_fee: decimal_column(row, "builder_fee")?,
                     closed_pnl,
-                }
+                })
             })
-            .collect())
+            .collect()
     }
 
     /// Query fill effects for leaderboard aggregation for a user with optional coin/time window.
@@ -1311,24 +4000,24 @@ impl Repository {
         let (sql, binds_coin) = if coin.is_some() {
             (
                 r#"
-                SELECT fe.fill_key, fe.lifecycle_id, fe.notional, fe.fee, fe.closed_pnl
+                SELECT fe.fill_key, fe.lifecycle_id, fe.notional, fe.fee, fe.closed_pnl, fe.builder_fee
                 FROM fill_effects fe
                 JOIN raw_fills rf ON rf.fill_key = fe.fill_key
                 JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
                 WHERE pl.user = ? AND pl.coin = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
-                ORDER BY fe.id ASC
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
                 "#,
                 true,
             )
         } else {
             (
                 r#"
-                SELECT fe.fill_key, fe.lifecycle_id, fe.notional, fe.fee, fe.closed_pnl
+                SELECT fe.fill_key, fe.lifecycle_id, fe.notional, fe.fee, fe.closed_pnl, fe.builder_fee
                 FROM fill_effects fe
                 JOIN raw_fills rf ON rf.fill_key = fe.fill_key
                 JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
                 WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
-                ORDER BY fe.id ASC
+                ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
                 "#,
                 false,
             )
@@ -1340,39 +4029,241 @@ impl Repository {
         }
         query = query.bind(from_ms).bind(to_ms);
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
 
-        Ok(rows
+        rows
             .iter()
             .map(|row| {
                 let fill_key: String = row.get("fill_key");
                 let lifecycle_id: i64 = row.get("lifecycle_id");
-                let notional_str: String = row.get("notional");
-                let fee_str: String = row.get("fee");
-                let closed_pnl_str: String = row.get("closed_pnl");
 
-                let notional = Decimal::from_str(&notional_str).unwrap_or_else(|e| {
-                    warn!(lifecycle_id, notional = %notional_str, error = %e, "Failed to parse notional decimal, using default");
-                    Decimal::default()
-                });
-                let fee = Decimal::from_str(&fee_str).unwrap_or_else(|e| {
-                    warn!(lifecycle_id, fee = %fee_str, error = %e, "Failed to parse fee decimal, using default");
-                    Decimal::default()
-                });
-                let closed_pnl = Decimal::from_str(&closed_pnl_str).unwrap_or_else(|e| {
-                    warn!(lifecycle_id, closed_pnl = %closed_pnl_str, error = %e, "Failed to parse closed_pnl decimal, using default");
-                    Decimal::default()
-                });
+                let notional = decimal_column(row, "notional")?;
+                let fee = decimal_column(row, "fee")?;
+                let closed_pnl = decimal_column(row, "closed_pnl")?;
 
-                LeaderboardFillEffect {
+                Ok(LeaderboardFillEffect {
                     fill_key,
                     lifecycle_id,
                     notional,
                     fee,
+                    builder_fee: decimal_column(row, "builder_fee")?,
                     closed_pnl,
-                }
+                })
             })
-            .collect())
+            .collect()
+    }
+
+    /// Query the effects of a user's lifecycles, ordered by lifecycle start and then in
+    /// compile order within each lifecycle.
+    pub async fn query_lifecycle_effects(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+    ) -> Result<Vec<LifecycleEffectRow>, sqlx::Error> {
+        let sql = if coin.is_some() {
+            r#"
+            SELECT pl.id AS lifecycle_id, pl.coin, pl.start_time_ms, pl.end_time_ms,
+                   pl.is_tainted, pl.taint_reason, pl.closed_by, pl.strategy, fe.fill_key,
+                   rf.side,
+                   fe.effect_type, fe.qty, fe.notional, fe.fee, fe.closed_pnl, fe.builder_fee
+            FROM position_lifecycles pl
+            JOIN fill_effects fe ON fe.lifecycle_id = pl.id
+            JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+            WHERE pl.user = ? AND pl.coin = ?
+            ORDER BY pl.start_time_ms ASC, pl.coin ASC, pl.id ASC, fe.id ASC
+            "#
+        } else {
+            r#"
+            SELECT pl.id AS lifecycle_id, pl.coin, pl.start_time_ms, pl.end_time_ms,
+                   pl.is_tainted, pl.taint_reason, pl.closed_by, pl.strategy, fe.fill_key,
+                   rf.side,
+                   fe.effect_type, fe.qty, fe.notional, fe.fee, fe.closed_pnl, fe.builder_fee
+            FROM position_lifecycles pl
+            JOIN fill_effects fe ON fe.lifecycle_id = pl.id
+            JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+            WHERE pl.user = ?
+            ORDER BY pl.start_time_ms ASC, pl.coin ASC, pl.id ASC, fe.id ASC
+            "#
+        };
+
+        let mut query = sqlx::query(sql).bind(user.as_str());
+        if let Some(coin) = coin {
+            query = query.bind(coin.as_str());
+        }
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
+
+        rows
+            .iter()
+            .map(|row| {
+                let lifecycle_id: i64 = row.get("lifecycle_id");
+                let decimal = |column: &str| decimal_column(row, column);
+                let side = side_column(row, "side")?;
+                let effect_type = match row.get::<String, _>("effect_type").as_str() {
+                    "close" => EffectType::Close,
+                    _ => EffectType::Open,
+                };
+
+                Ok(LifecycleEffectRow {
+                    lifecycle_id,
+                    coin: Coin::new(row.get("coin")),
+                    start_time_ms: TimeMs::new(row.get("start_time_ms")),
+                    end_time_ms: row.get::<Option<i64>, _>("end_time_ms").map(TimeMs::new),
+                    is_tainted: row.get::<i64, _>("is_tainted") != 0,
+                    taint_reason: row.get("taint_reason"),
+                    closed_by: row
+                        .get::<Option<String>, _>("closed_by")
+                        .and_then(|s| s.parse().ok()),
+                    strategy: row.get("strategy"),
+                    fill_key: row.get("fill_key"),
+                    side,
+                    effect_type,
+                    qty: decimal("qty")?,
+                    notional: decimal("notional")?,
+                    fee: decimal("fee")?,
+                    builder_fee: decimal("builder_fee")?,
+                    closed_pnl: decimal("closed_pnl")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Query fills of `users` attributed to `builder`, grouped into time buckets and ordered
+    /// by bucket.
+    ///
+    /// With `include_unnamed`, attributed fills that carry no builder (heuristic
+    /// attributions) also count. Buckets are aligned as in [`Self::query_volume_effects`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn query_builder_fee_fills(
+        &self,
+        users: &[Address],
+        builder: &Address,
+        include_unnamed: bool,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        bucket_ms: i64,
+        origin_ms: i64,
+    ) -> Result<Vec<BuilderFeeFill>, sqlx::Error> {
+        if users.is_empty() {
+            return Ok(Vec::new());
+        }
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let placeholders = vec!["?"; users.len()].join(",");
+        let sql = format!(
+            r#"
+            SELECT ((rf.time_ms - ?) / ?) * ? + ? AS bucket_start_ms,
+                   rf.user, rf.fill_key, rf.px, rf.sz, rf.builder_fee
+            FROM raw_fills rf
+            JOIN fill_attributions fa ON fa.fill_key = rf.fill_key
+            WHERE fa.attributed = 1 AND (fa.builder = ? OR (? AND fa.builder IS NULL))
+              AND rf.time_ms >= ? AND rf.time_ms <= ? AND rf.user IN ({})
+            ORDER BY bucket_start_ms ASC, rf.time_ms ASC, rf.id ASC
+            "#,
+            placeholders
+        );
+
+        let mut query = sqlx::query(&sql)
+            .bind(origin_ms)
+            .bind(bucket_ms)
+            .bind(bucket_ms)
+            .bind(origin_ms)
+            .bind(builder.as_str())
+            .bind(include_unnamed)
+            .bind(from_ms)
+            .bind(to_ms);
+        for user in users {
+            query = query.bind(user.as_str());
+        }
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
+
+        rows
+            .iter()
+            .map(|row| {
+                let fill_key: String = row.get("fill_key");
+                let notional = decimal_column(row, "px")? * decimal_column(row, "sz")?;
+                let builder_fee = optional_decimal_column(row, "builder_fee")?;
+
+                Ok(BuilderFeeFill {
+                    bucket_start_ms: TimeMs::new(row.get("bucket_start_ms")),
+                    user: Address::new(row.get("user")),
+                    fill_key,
+                    notional,
+                    builder_fee,
+                })
+            })
+            .collect()
+    }
+
+    /// Query fill effects for a user grouped into time buckets, ordered by bucket.
+    ///
+    /// Buckets are `bucket_ms` wide and aligned to `origin_ms`, which must not be after the
+    /// first fill. Effects are returned individually rather than summed in SQL so decimal
+    /// totals stay exact; effects of one fill are adjacent.
+    pub async fn query_volume_effects(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        bucket_ms: i64,
+        origin_ms: i64,
+    ) -> Result<Vec<VolumeFillEffect>, sqlx::Error> {
+        let from_ms = from_ms.unwrap_or(TimeMs::new(0)).as_ms();
+        let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX)).as_ms();
+
+        let sql = if coin.is_some() {
+            r#"
+            SELECT ((rf.time_ms - ?) / ?) * ? + ? AS bucket_start_ms,
+                   fe.fill_key, fe.notional, fe.fee, fe.builder_fee, rf.crossed
+            FROM fill_effects fe
+            JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+            JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
+            WHERE pl.user = ? AND pl.coin = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
+            ORDER BY bucket_start_ms ASC, rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
+            "#
+        } else {
+            r#"
+            SELECT ((rf.time_ms - ?) / ?) * ? + ? AS bucket_start_ms,
+                   fe.fill_key, fe.notional, fe.fee, fe.builder_fee, rf.crossed
+            FROM fill_effects fe
+            JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+            JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
+            WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms <= ?
+            ORDER BY bucket_start_ms ASC, rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
+            "#
+        };
+
+        let mut query = sqlx::query(sql)
+            .bind(origin_ms)
+            .bind(bucket_ms)
+            .bind(bucket_ms)
+            .bind(origin_ms)
+            .bind(user.as_str());
+        if let Some(coin) = coin {
+            query = query.bind(coin.as_str());
+        }
+        let rows = query.bind(from_ms).bind(to_ms).fetch_all(&mut *self.reader.acquire().await?).await?;
+
+        rows
+            .iter()
+            .map(|row| {
+                let fill_key: String = row.get("fill_key");
+
+                let notional = decimal_column(row, "notional")?;
+                let fee = decimal_column(row, "fee")?;
+                let builder_fee = decimal_column(row, "builder_fee")?;
+
+                Ok(VolumeFillEffect {
+                    bucket_start_ms: TimeMs::new(row.get("bucket_start_ms")),
+                    fill_key,
+                    notional,
+                    fee,
+                    builder_fee,
+                    crossed: row.get("crossed"),
+                })
+            })
+            .collect()
     }
 
     /// Return the set of tainted lifecycle IDs from a provided list.
@@ -1399,43 +4290,68 @@ impl Repository {
             query = query.bind(id);
         }
 
-        let rows = query.fetch_all(&self.pool).await?;
+        let rows = query.fetch_all(&mut *self.reader.acquire().await?).await?;
         Ok(rows.iter().map(|row| row.get::<i64, _>("id")).collect())
     }
 
     /// Sum deposits up to and including `at_ms`.
     ///
+    /// Withdrawals and outgoing transfers are stored with negative amounts, so this is the
+    /// net flow into the account.
+    ///
     /// # Implementation Note
     ///
-    /// We iterate in Rust to preserve decimal precision. SQLite's SUM aggregate
-    /// function returns REAL (float), which would lose precision for financial
-    /// calculations. By fetching rows and summing with our Decimal type, we
-    /// maintain lossless arithmetic.
+    /// SQLite's SUM over TEXT returns REAL, which would lose precision. The exact sum is
+    /// taken over the `amount_e8` integer column in SQL when every matching row has one and
+    /// the total fits in an `i64`; otherwise rows are fetched and summed as decimals.
     pub async fn sum_deposits_up_to(
         &self,
         user: &Address,
         at_ms: TimeMs,
     ) -> Result<Decimal, sqlx::Error> {
+        self.sum_deposits_between(user, TimeMs::new(i64::MIN), at_ms)
+            .await
+    }
+
+    /// Sum deposits after `after_ms` up to and including `up_to_ms`.
+    pub async fn sum_deposits_between(
+        &self,
+        user: &Address,
+        after_ms: TimeMs,
+        up_to_ms: TimeMs,
+    ) -> Result<Decimal, sqlx::Error> {
+        let scaled = self
+            .sum_scaled_column(
+                r#"
+                SELECT COUNT(*), COUNT(amount_e8), SUM(amount_e8)
+                FROM deposits
+                WHERE user = ? AND time_ms > ? AND time_ms <= ?
+                "#,
+                user,
+                &[after_ms, up_to_ms],
+            )
+            .await?;
+        if let Some(sum) = scaled {
+            return Ok(sum);
+        }
+
         let rows = sqlx::query(
             r#"
             SELECT amount
             FROM deposits
-            WHERE user = ? AND time_ms <= ?
+            WHERE user = ? AND time_ms > ? AND time_ms <= ?
             ORDER BY time_ms ASC, id ASC
             "#,
         )
         .bind(user.as_str())
-        .bind(at_ms.as_i64())
-        .fetch_all(&self.pool)
+        .bind(after_ms.as_i64())
+        .bind(up_to_ms.as_i64())
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
         let mut sum = Decimal::zero();
         for row in rows {
-            let amount_str: String = row.get("amount");
-            let amount = Decimal::from_str(&amount_str).unwrap_or_else(|e| {
-                warn!(user = %user, amount = %amount_str, error = %e, "Failed to parse deposit amount decimal, using default");
-                Decimal::default()
-            });
+            let amount = decimal_column(&row, "amount")?;
             sum = sum + amount;
         }
 
@@ -1444,95 +4360,287 @@ impl Repository {
 
     /// Sum realized PnL strictly before `at_ms` from fill effects (excludes funding).
     ///
-    /// # Implementation Note
-    ///
-    /// We iterate in Rust to preserve decimal precision. SQLite's SUM aggregate
-    /// function returns REAL (float), which would lose precision for financial
-    /// calculations. By fetching rows and summing with our Decimal type, we
-    /// maintain lossless arithmetic.
+    /// Uses the `closed_pnl_e8` column like [`Self::sum_deposits_up_to`], falling back to
+    /// decimal summation when it cannot be summed exactly in SQL.
     pub async fn sum_realized_pnl_before(
         &self,
         user: &Address,
         at_ms: TimeMs,
     ) -> Result<Decimal, sqlx::Error> {
+        self.sum_realized_pnl_between(user, TimeMs::new(i64::MIN), at_ms)
+            .await
+    }
+
+    /// Sum realized PnL of fills at or after `from_ms` and strictly before `before_ms`.
+    pub async fn sum_realized_pnl_between(
+        &self,
+        user: &Address,
+        from_ms: TimeMs,
+        before_ms: TimeMs,
+    ) -> Result<Decimal, sqlx::Error> {
+        let scaled = self
+            .sum_scaled_column(
+                r#"
+                SELECT COUNT(*), COUNT(fe.closed_pnl_e8), SUM(fe.closed_pnl_e8)
+                FROM fill_effects fe
+                JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+                JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
+                WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms < ?
+                "#,
+                user,
+                &[from_ms, before_ms],
+            )
+            .await?;
+        if let Some(sum) = scaled {
+            return Ok(sum);
+        }
+
         let rows = sqlx::query(
             r#"
             SELECT fe.closed_pnl
             FROM fill_effects fe
             JOIN raw_fills rf ON rf.fill_key = fe.fill_key
             JOIN position_lifecycles pl ON pl.id = fe.lifecycle_id
-            WHERE pl.user = ? AND rf.time_ms < ?
-            ORDER BY rf.time_ms ASC, fe.id ASC
+            WHERE pl.user = ? AND rf.time_ms >= ? AND rf.time_ms < ?
+            ORDER BY rf.time_ms ASC, rf.tid ASC, rf.oid ASC, rf.fill_key ASC, fe.id ASC
             "#,
         )
         .bind(user.as_str())
-        .bind(at_ms.as_i64())
-        .fetch_all(&self.pool)
+        .bind(from_ms.as_i64())
+        .bind(before_ms.as_i64())
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
         let mut sum = Decimal::zero();
         for row in rows {
-            let pnl_str: String = row.get("closed_pnl");
-            let pnl = Decimal::from_str(&pnl_str).unwrap_or_else(|e| {
-                warn!(user = %user, closed_pnl = %pnl_str, error = %e, "Failed to parse closed_pnl decimal, using default");
-                Decimal::default()
-            });
+            let pnl = decimal_column(&row, "closed_pnl")?;
             sum = sum + pnl;
         }
 
-        Ok(sum)
+        Ok(sum)
+    }
+
+    /// Run a `SELECT COUNT(*), COUNT(scaled), SUM(scaled)` query bound to `user` followed
+    /// by `bounds`.
+    ///
+    /// Returns `None` when some row has no scaled value or SQLite reports integer overflow,
+    /// in which case the caller must sum the decimal column instead.
+    async fn sum_scaled_column(
+        &self,
+        sql: &str,
+        user: &Address,
+        bounds: &[TimeMs],
+    ) -> Result<Option<Decimal>, sqlx::Error> {
+        let mut query = sqlx::query_as(sql).bind(user.as_str());
+        for bound in bounds {
+            query = query.bind(bound.as_i64());
+        }
+        let result: Result<(i64, i64, Option<i64>), sqlx::Error> =
+            query.fetch_one(&mut *self.reader.acquire().await?).await;
+
+        match result {
+            Ok((rows, scaled, sum)) if rows == scaled => {
+                Ok(Some(sum.map_or_else(Decimal::zero, Decimal::from_scaled_units)))
+            }
+            Ok(_) => Ok(None),
+            Err(sqlx::Error::Database(e)) if e.message().contains("integer overflow") => {
+                debug!(user = %user, "Scaled sum overflowed, summing decimals");
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the latest equity snapshot at or before `at_ms`.
+    pub async fn get_equity_snapshot_at_or_before(
+        &self,
+        user: &Address,
+        at_ms: TimeMs,
+    ) -> Result<Option<(TimeMs, Decimal)>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT time_ms, equity
+            FROM equity_snapshots
+            WHERE user = ? AND time_ms <= ?
+            ORDER BY time_ms DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(at_ms.as_i64())
+        .fetch_optional(&mut *self.reader.acquire().await?)
+        .await?;
+
+        row.map(|r| Ok((TimeMs::new(r.try_get("time_ms")?), decimal_column(&r, "equity")?)))
+            .transpose()
+    }
+
+    /// Get the time of the most recent stored fill for a user, across all coins.
+    pub async fn get_latest_fill_time(&self, user: &Address) -> Result<Option<TimeMs>, sqlx::Error> {
+        let row = sqlx::query("SELECT MAX(time_ms) as max_time FROM raw_fills WHERE user = ?")
+            .bind(user.as_str())
+            .fetch_one(&mut *self.reader.acquire().await?)
+            .await?;
+
+        Ok(row.get::<Option<i64>, _>("max_time").map(TimeMs::new))
+    }
+
+    /// Get the earliest deposit timestamp for a user.
+    ///
+    /// Returns None if the user has no deposits.
+    pub async fn get_earliest_deposit_timestamp(
+        &self,
+        user: &Address,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT MIN(time_ms) as min_time
+            FROM deposits
+            WHERE user = ?
+            "#,
+        )
+        .bind(user.as_str())
+        .fetch_one(&mut *self.reader.acquire().await?)
+        .await?;
+
+        Ok(row.get::<Option<i64>, _>("min_time"))
+    }
+
+    /// Store the latest metadata of each asset in `coins`.
+    ///
+    /// Assets missing from `coins` are kept: the `meta` universe never drops an asset, it
+    /// marks it delisted.
+    pub async fn upsert_coins(&self, coins: &[CoinMeta], now: TimeMs) -> Result<(), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        for meta in coins {
+            sqlx::query(
+                r#"
+                INSERT INTO coins
+                    (name, asset_index, sz_decimals, max_leverage, delisted, updated_at_ms)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT(name) DO UPDATE SET
+                    asset_index = excluded.asset_index,
+                    sz_decimals = excluded.sz_decimals,
+                    max_leverage = excluded.max_leverage,
+                    delisted = excluded.delisted,
+                    updated_at_ms = excluded.updated_at_ms
+                "#,
+            )
+            .bind(meta.coin.as_str())
+            .bind(meta.asset_index as i64)
+            .bind(meta.sz_decimals as i64)
+            .bind(meta.max_leverage as i64)
+            .bind(meta.delisted)
+            .bind(now.as_ms())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    /// Store marks fetched for `coin` and record the buckets `covered` as fetched, merged
+    /// with the span already covered, which it must touch.
+    pub async fn store_mark_prices(
+        &self,
+        coin: &Coin,
+        marks: &[MarkPrice],
+        covered: Option<(TimeMs, TimeMs)>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        for mark in marks {
+            sqlx::query(
+                "INSERT OR REPLACE INTO mark_prices (coin, time_ms, px) VALUES (?, ?, ?)",
+            )
+            .bind(coin.as_str())
+            .bind(mark.time_ms.as_ms())
+            .bind(mark.px.to_canonical_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some((from_ms, to_ms)) = covered {
+            sqlx::query(
+                r#"
+                INSERT INTO mark_price_coverage (coin, from_ms, to_ms) VALUES (?, ?, ?)
+                ON CONFLICT(coin) DO UPDATE SET
+                    from_ms = MIN(from_ms, excluded.from_ms),
+                    to_ms = MAX(to_ms, excluded.to_ms)
+                "#,
+            )
+            .bind(coin.as_str())
+            .bind(from_ms.as_ms())
+            .bind(to_ms.as_ms())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    /// First and last bucket whose marks have been fetched for `coin`, if any.
+    pub async fn mark_price_coverage(
+        &self,
+        coin: &Coin,
+    ) -> Result<Option<(TimeMs, TimeMs)>, sqlx::Error> {
+        let row = sqlx::query("SELECT from_ms, to_ms FROM mark_price_coverage WHERE coin = ?")
+            .bind(coin.as_str())
+            .fetch_optional(&mut *self.reader.acquire().await?)
+            .await?;
+        Ok(row.map(|row| {
+            (
+                TimeMs::new(row.get("from_ms")),
+                TimeMs::new(row.get("to_ms")),
+            )
+        }))
     }
 
-    /// Get the latest equity snapshot at or before `at_ms`.
-    pub async fn get_equity_snapshot_at_or_before(
+    /// Stored marks of `coin` in `from_ms..=to_ms`, in time order.
+    pub async fn query_mark_prices(
         &self,
-        user: &Address,
-        at_ms: TimeMs,
-    ) -> Result<Option<(TimeMs, Decimal)>, sqlx::Error> {
-        let row = sqlx::query(
+        coin: &Coin,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> Result<MarkSeries, sqlx::Error> {
+        let rows = sqlx::query(
             r#"
-            SELECT time_ms, equity
-            FROM equity_snapshots
-            WHERE user = ? AND time_ms <= ?
-            ORDER BY time_ms DESC, id DESC
-            LIMIT 1
+            SELECT time_ms, px FROM mark_prices
+            WHERE coin = ? AND time_ms >= ? AND time_ms <= ?
+            ORDER BY time_ms
             "#,
         )
-        .bind(user.as_str())
-        .bind(at_ms.as_i64())
-        .fetch_optional(&self.pool)
+        .bind(coin.as_str())
+        .bind(from_ms.as_ms())
+        .bind(to_ms.as_ms())
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        Ok(row.map(|r| {
-            let time_ms: i64 = r.get("time_ms");
-            let equity_str: String = r.get("equity");
-            let equity = Decimal::from_str(&equity_str).unwrap_or_else(|e| {
-                warn!(user = %user, equity = %equity_str, error = %e, "Failed to parse equity snapshot decimal, using default");
-                Decimal::default()
-            });
-            (TimeMs::new(time_ms), equity)
-        }))
+        let marks = rows
+            .iter()
+            .map(|row| Ok((TimeMs::new(row.get("time_ms")), decimal_column(row, "px")?)))
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        Ok(MarkSeries::new(marks))
     }
 
-    /// Get the earliest deposit timestamp for a user.
-    ///
-    /// Returns None if the user has no deposits.
-    pub async fn get_earliest_deposit_timestamp(
-        &self,
-        user: &Address,
-    ) -> Result<Option<i64>, sqlx::Error> {
-        let row = sqlx::query(
+    /// Every stored asset, in asset id order.
+    pub async fn query_coins(&self) -> Result<Vec<CoinMeta>, sqlx::Error> {
+        let rows = sqlx::query(
             r#"
-            SELECT MIN(time_ms) as min_time
-            FROM deposits
-            WHERE user = ?
+            SELECT name, asset_index, sz_decimals, max_leverage, delisted
+            FROM coins
+            ORDER BY asset_index, name
             "#,
         )
-        .bind(user.as_str())
-        .fetch_one(&self.pool)
+        .fetch_all(&mut *self.reader.acquire().await?)
         .await?;
 
-        Ok(row.get::<Option<i64>, _>("min_time"))
+        Ok(rows
+            .iter()
+            .map(|row| CoinMeta {
+                coin: Coin::new(row.get("name")),
+                asset_index: row.get::<i64, _>("asset_index") as u32,
+                sz_decimals: row.get::<i64, _>("sz_decimals") as u32,
+                max_leverage: row.get::<i64, _>("max_leverage") as u32,
+                delisted: row.get("delisted"),
+            })
+            .collect())
     }
 
     /// Upsert an equity snapshot for an exact (user, time_ms) key.
@@ -1542,7 +4650,7 @@ impl Repository {
         time_ms: TimeMs,
         equity: Decimal,
     ) -> Result<(), sqlx::Error> {
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.writer.begin().await?;
 
         sqlx::query(
             r#"
@@ -1570,6 +4678,572 @@ impl Repository {
         tx.commit().await?;
         Ok(())
     }
+
+    /// Whether standings were already stored for `snapshot_ms`.
+    pub async fn has_leaderboard_snapshot(&self, snapshot_ms: TimeMs) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT 1 FROM leaderboard_snapshots WHERE snapshot_ms = ? LIMIT 1")
+            .bind(snapshot_ms.as_i64())
+            .fetch_optional(&mut *self.reader.acquire().await?)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Store the standings of one snapshot, unless one was already stored at the same time.
+    ///
+    /// Snapshots are never rewritten, so a second run for the same `snapshot_ms` leaves
+    /// the first one in place and returns `0`.
+    ///
+    /// # Errors
+    /// Returns an error if the insert fails.
+    pub async fn insert_leaderboard_snapshot(
+        &self,
+        snapshot_ms: TimeMs,
+        rows: &[LeaderboardSnapshotRow],
+    ) -> Result<usize, sqlx::Error> {
+        let mut tx = self.writer.begin().await?;
+        let existing = sqlx::query("SELECT 1 FROM leaderboard_snapshots WHERE snapshot_ms = ? LIMIT 1")
+            .bind(snapshot_ms.as_i64())
+            .fetch_optional(&mut *tx)
+            .await?;
+        if existing.is_some() || rows.is_empty() {
+            return Ok(0);
+        }
+
+        let mut inserted = 0usize;
+        for chunk in rows.chunks(rows_per_insert(7)) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT OR IGNORE INTO leaderboard_snapshots \
+                 (snapshot_ms, metric, user, rank, value, trade_count, entrants) ",
+            );
+            query.push_values(chunk, |mut row, standing| {
+                row.push_bind(snapshot_ms.as_i64())
+                    .push_bind(standing.metric.as_str())
+                    .push_bind(standing.user.as_str())
+                    .push_bind(standing.rank)
+                    .push_bind(standing.value.to_canonical_string())
+                    .push_bind(standing.trade_count)
+                    .push_bind(standing.entrants);
+            });
+            inserted += query.build().execute(&mut *tx).await?.rows_affected() as usize;
+        }
+
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    /// A user's stored standings, oldest snapshot first, optionally for one metric.
+    ///
+    /// # Errors
+    /// Returns an error if the query fails.
+    pub async fn query_leaderboard_history(
+        &self,
+        user: &Address,
+        metric: Option<&str>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Vec<LeaderboardSnapshotRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT snapshot_ms, metric, user, rank, value, trade_count, entrants
+            FROM leaderboard_snapshots
+            WHERE user = ?
+              AND (? IS NULL OR metric = ?)
+              AND snapshot_ms >= ?
+              AND snapshot_ms <= ?
+            ORDER BY snapshot_ms ASC, metric ASC
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(metric)
+        .bind(metric)
+        .bind(from_ms.map_or(i64::MIN, |t| t.as_i64()))
+        .bind(to_ms.map_or(i64::MAX, |t| t.as_i64()))
+        .fetch_all(&mut *self.reader.acquire().await?)
+        .await?;
+
+        rows
+            .into_iter()
+            .map(|row| {
+                Ok(LeaderboardSnapshotRow {
+                    snapshot_ms: TimeMs::new(row.get("snapshot_ms")),
+                    metric: row.get("metric"),
+                    user: Address::new(row.get("user")),
+                    rank: row.get("rank"),
+                    value: decimal_column(&row, "value")?,
+                    trade_count: row.get("trade_count"),
+                    entrants: row.get("entrants"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Delete the derived rows and compile watermark of a user and coin on `conn`; see
+/// [`Repository::reset_derived_state`].
+async fn reset_derived_rows(
+    conn: &mut SqliteConnection,
+    user: &Address,
+    coin: &Coin,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM fill_effects WHERE lifecycle_id IN (
+            SELECT id FROM position_lifecycles WHERE user = ? AND coin = ?
+        )
+        "#,
+    )
+    .bind(user.as_str())
+    .bind(coin.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    for sql in [
+        "DELETE FROM position_snapshots WHERE user = ? AND coin = ?",
+        "DELETE FROM position_lifecycles WHERE user = ? AND coin = ?",
+        "DELETE FROM compile_state WHERE user = ? AND coin = ?",
+        "DELETE FROM reconciliation_issues WHERE user = ? AND coin = ?",
+        "DELETE FROM invariant_violations WHERE user = ? AND coin = ?",
+    ] {
+        sqlx::query(sql)
+            .bind(user.as_str())
+            .bind(coin.as_str())
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Insert the derived tables of a compile on `conn`; see
+/// [`Repository::insert_derived_tables_atomic`].
+async fn write_derived_tables(
+    conn: &mut SqliteConnection,
+    user: &Address,
+    coin: &Coin,
+    lifecycles: &[Lifecycle],
+    snapshots: &[Snapshot],
+    effects: &[Effect],
+) -> Result<(), sqlx::Error> {
+    // Insert lifecycles with explicit IDs from the tracker
+    for chunk in lifecycles.chunks(rows_per_insert(8)) {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "INSERT OR REPLACE INTO position_lifecycles \
+             (id, user, coin, start_time_ms, end_time_ms, is_tainted, taint_reason, \
+             closed_by) ",
+        );
+        query.push_values(chunk, |mut row, lifecycle| {
+            row.push_bind(lifecycle.id)
+                .push_bind(lifecycle.user.as_str())
+                .push_bind(lifecycle.coin.as_str())
+                .push_bind(lifecycle.start_time_ms.as_i64())
+                .push_bind(lifecycle.end_time_ms.map(|t| t.as_i64()))
+                .push_bind(0) // is_tainted - will be updated after taint computation
+                .push_bind(None::<String>) // taint_reason
+                .push_bind(lifecycle.closed_by.map(|t| t.as_str()));
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+
+    // Insert snapshots
+    for chunk in snapshots.chunks(rows_per_insert(8)) {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "INSERT OR REPLACE INTO position_snapshots \
+             (user, coin, time_ms, seq, net_size, avg_entry_px, lifecycle_id, is_tainted) ",
+        );
+        query.push_values(chunk, |mut row, snapshot| {
+            row.push_bind(user.as_str())
+                .push_bind(coin.as_str())
+                .push_bind(snapshot.time_ms.as_i64())
+                .push_bind(snapshot.seq)
+                .push_bind(snapshot.net_size.to_canonical_string())
+                .push_bind(snapshot.avg_entry_px.to_canonical_string())
+                .push_bind(snapshot.lifecycle_id)
+                .push_bind(0); // is_tainted
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+
+    // Insert effects
+    for chunk in effects.chunks(rows_per_insert(9)) {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "INSERT OR REPLACE INTO fill_effects \
+             (fill_key, lifecycle_id, effect_type, qty, notional, fee, builder_fee, closed_pnl, \
+             closed_pnl_e8) ",
+        );
+        query.push_values(chunk, |mut row, effect| {
+            let effect_type_str = match effect.effect_type {
+                EffectType::Open => "open",
+                EffectType::Close => "close",
+            };
+            row.push_bind(effect.fill_key.as_str())
+                .push_bind(effect.lifecycle_id)
+                .push_bind(effect_type_str)
+                .push_bind(effect.qty.to_canonical_string())
+                .push_bind(effect.notional.to_canonical_string())
+                .push_bind(effect.fee.to_canonical_string())
+                .push_bind(effect.builder_fee.to_canonical_string())
+                .push_bind(effect.closed_pnl.to_canonical_string())
+                .push_bind(effect.closed_pnl.to_scaled_units());
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+async fn write_lifecycle_taints(
+    conn: &mut SqliteConnection,
+    taint_updates: &[(i64, bool, Option<String>)],
+) -> Result<(), sqlx::Error> {
+    for (lifecycle_id, is_tainted, taint_reason) in taint_updates {
+        sqlx::query(
+            r#"
+            UPDATE position_lifecycles
+            SET is_tainted = ?, taint_reason = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(if *is_tainted { 1 } else { 0 })
+        .bind(taint_reason)
+        .bind(lifecycle_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn read_strategy_rules(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<StrategyRule>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT name, priority, coins, from_ms, to_ms, tags
+        FROM strategy_rules
+        ORDER BY priority, name
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            let list = |column: &str| -> Result<Vec<String>, sqlx::Error> {
+                let value: String = row.get(column);
+                serde_json::from_str(&value).map_err(|e| corrupt(column, &value, e))
+            };
+            let tags = list("tags")?
+                .iter()
+                .map(|tag| tag.parse().map_err(|_| corrupt("tags", tag, "unknown tag")))
+                .collect::<Result<_, _>>()?;
+            Ok(StrategyRule {
+                name: row.get("name"),
+                priority: row.get("priority"),
+                coins: list("coins")?.into_iter().map(Coin::new).collect(),
+                from_ms: row.get::<Option<i64>, _>("from_ms").map(TimeMs::new),
+                to_ms: row.get::<Option<i64>, _>("to_ms").map(TimeMs::new),
+                tags,
+            })
+        })
+        .collect()
+}
+
+/// Lifecycles [`write_lifecycle_strategies`] labels.
+enum LifecycleScope<'a> {
+    Ids(&'a [i64]),
+    /// Up to `limit` lifecycles with ids above `after_id`.
+    After { after_id: i64, limit: usize },
+}
+
+/// Label the lifecycles in `scope` with the first strategy rule they match. Returns the
+/// users with a lifecycle whose label changed, and the largest id in `scope`.
+async fn write_lifecycle_strategies(
+    conn: &mut SqliteConnection,
+    scope: LifecycleScope<'_>,
+) -> Result<(Vec<Address>, Option<i64>), sqlx::Error> {
+    let rules = read_strategy_rules(conn).await?;
+    let sql = |filter: &str| {
+        format!(
+            r#"
+            SELECT pl.id, pl.user, pl.coin, pl.start_time_ms, pl.is_tainted, pl.closed_by,
+                   pl.strategy,
+                   EXISTS (
+                       SELECT 1 FROM fill_effects fe
+                       JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+                       WHERE fe.lifecycle_id = pl.id AND rf.twap_id IS NOT NULL
+                   ) AS twap,
+                   (
+                       SELECT rf.side FROM fill_effects fe
+                       JOIN raw_fills rf ON rf.fill_key = fe.fill_key
+                       WHERE fe.lifecycle_id = pl.id
+                       ORDER BY fe.id
+                       LIMIT 1
+                   ) AS side
+            FROM position_lifecycles pl
+            {}
+            "#,
+            filter
+        )
+    };
+    let rows = match scope {
+        LifecycleScope::After { after_id, limit } => {
+            sqlx::query(&sql("WHERE pl.id > ? ORDER BY pl.id LIMIT ?"))
+                .bind(after_id)
+                .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                .fetch_all(&mut *conn)
+                .await?
+        }
+        LifecycleScope::Ids(ids) => {
+            let mut rows = Vec::new();
+            for chunk in ids.chunks(SQLITE_MAX_BIND_PARAMS) {
+                let placeholders = vec!["?"; chunk.len()].join(",");
+                let sql = sql(&format!("WHERE pl.id IN ({})", placeholders));
+                let mut query = sqlx::query(&sql);
+                for id in chunk {
+                    query = query.bind(id);
+                }
+                rows.extend(query.fetch_all(&mut *conn).await?);
+            }
+            rows
+        }
+    };
+
+    let mut changed = BTreeSet::new();
+    let mut last_id = None;
+    for row in &rows {
+        let id: i64 = row.get("id");
+        last_id = last_id.max(Some(id));
+        let facts = LifecycleFacts {
+            coin: Coin::new(row.get("coin")),
+            start_time_ms: TimeMs::new(row.get("start_time_ms")),
+            tainted: row.get::<i64, _>("is_tainted") != 0,
+            twap: row.get("twap"),
+            liquidated: row.get::<Option<String>, _>("closed_by").is_some(),
+            side: match row.get::<Option<String>, _>("side") {
+                Some(_) => Some(side_column(row, "side")?),
+                None => None,
+            },
+        };
+        let strategy = assign_strategy(&rules, &facts);
+        if strategy == row.get::<Option<&str>, _>("strategy") {
+            continue;
+        }
+        sqlx::query("UPDATE position_lifecycles SET strategy = ? WHERE id = ?")
+            .bind(strategy)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        changed.insert(row.get::<String, _>("user"));
+    }
+    Ok((changed.into_iter().map(Address::new).collect(), last_id))
+}
+
+async fn write_invariant_violations(
+    conn: &mut SqliteConnection,
+    user: &Address,
+    coin: &Coin,
+    violations: &[InvariantViolation],
+    recorded_at_ms: i64,
+) -> Result<(), sqlx::Error> {
+    for violation in violations {
+        sqlx::query(
+            r#"
+            INSERT INTO invariant_violations
+            (user, coin, invariant, fill_key, lifecycle_id, detail, recorded_at_ms)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(user.as_str())
+        .bind(coin.as_str())
+        .bind(violation.invariant.as_str())
+        .bind(&violation.fill_key)
+        .bind(violation.lifecycle_id)
+        .bind(&violation.detail)
+        .bind(recorded_at_ms)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn write_compile_state(
+    conn: &mut SqliteConnection,
+    user: &Address,
+    coin: &Coin,
+    last_compiled_time_ms: Option<i64>,
+    last_compiled_fill_key: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO compile_state (user, coin, last_compiled_time_ms, last_compiled_fill_key, compile_version, compile_schema_version)
+        VALUES (?, ?, ?, ?, 1, ?)
+        ON CONFLICT(user, coin) DO UPDATE SET
+            last_compiled_time_ms = excluded.last_compiled_time_ms,
+            last_compiled_fill_key = excluded.last_compiled_fill_key,
+            compile_version = compile_version + 1,
+            compile_schema_version = excluded.compile_schema_version
+        "#,
+    )
+    .bind(user.as_str())
+    .bind(coin.as_str())
+    .bind(last_compiled_time_ms)
+    .bind(last_compiled_fill_key)
+    .bind(COMPILE_SCHEMA_VERSION)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Restrict a `position_snapshots ps` query to coins of perp dex `dex` (`""` for the
+/// default dex, whose coins are unqualified).
+fn push_dex_filter<'a>(query: &mut QueryBuilder<'a, Sqlite>, dex: &str) {
+    if dex.is_empty() {
+        query.push(" AND instr(ps.coin, ':') = 0");
+    } else {
+        let prefix = format!("{}{}", dex, Coin::DEX_SEPARATOR);
+        query
+            .push(" AND substr(ps.coin, 1, ")
+            .push_bind(prefix.chars().count() as i64)
+            .push(") = ")
+            .push_bind(prefix);
+    }
+}
+
+/// Width of equal time buckets that split `first_ms..=last_ms` into at most `max_points`.
+fn bucket_width_ms(first_ms: i64, last_ms: i64, max_points: usize) -> i64 {
+    let span = last_ms.saturating_sub(first_ms).max(0);
+    span / (max_points.max(1) as i64) + 1
+}
+
+/// Keep the last of `points` (in time order) in each of at most `max_points` equal time
+/// buckets spanning them.
+pub(crate) fn last_in_bucket<T>(points: Vec<(TimeMs, T)>, max_points: usize) -> Vec<(TimeMs, T)> {
+    let (Some((first_ms, _)), Some((last_ms, _))) = (points.first(), points.last()) else {
+        return points;
+    };
+    let first_ms = first_ms.as_ms();
+    let width_ms = bucket_width_ms(first_ms, last_ms.as_ms(), max_points);
+    let bucket = |t: TimeMs| (t.as_ms() - first_ms) / width_ms;
+
+    let mut kept: Vec<(TimeMs, T)> = Vec::new();
+    for point in points {
+        match kept.last_mut() {
+            Some(last) if bucket(last.0) == bucket(point.0) => *last = point,
+            _ => kept.push(point),
+        }
+    }
+    kept
+}
+
+/// Decode the `fill_attributions` columns of a row into an [`Attribution`].
+fn attribution_from_row(row: &sqlx::sqlite::SqliteRow) -> Attribution {
+    let mode = AttributionMode::parse(&row.get::<String, _>("mode"))
+        .unwrap_or(AttributionMode::Heuristic);
+    let confidence = AttributionConfidence::parse(&row.get::<String, _>("confidence"))
+        .unwrap_or(AttributionConfidence::Low);
+    let attributed = row.get::<i32, _>("attributed") != 0;
+    let tolerance_tier = row
+        .get::<Option<String>, _>("tolerance_tier")
+        .and_then(|t| ToleranceTier::parse(&t));
+    // Rows written before scoring, e.g. by an older build against a restored database.
+    let reason = row
+        .get::<Option<String>, _>("reason")
+        .and_then(|r| AttributionReason::parse(&r, row.get("match_time_delta_ms")))
+        .unwrap_or_else(|| AttributionReason::infer(mode, confidence, attributed));
+    let score = row
+        .get::<Option<i64>, _>("score")
+        .and_then(|s| u8::try_from(s).ok())
+        .unwrap_or_else(|| reason.score(attributed, tolerance_tier, 0));
+
+    Attribution {
+        attributed,
+        mode,
+        confidence,
+        builder: row.get::<Option<String>, _>("builder").map(Address::new),
+        tolerance_tier,
+        matched_log_key: row.get::<Option<String>, _>("matched_log_key"),
+        score,
+        reason,
+    }
+}
+
+/// A stored value that does not decode: the row was damaged or written by something other
+/// than this repository. Surfaces as [`sqlx::Error::ColumnDecode`] naming the column.
+#[derive(Debug, Error)]
+#[error("corrupt value {value:?}: {reason}")]
+pub struct CorruptValue {
+    pub value: String,
+    pub reason: String,
+}
+
+/// Whether `err` reports stored data that does not decode, as opposed to a failed query.
+pub fn is_data_corruption(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_))
+}
+
+fn corrupt(column: &str, value: &str, reason: impl fmt::Display) -> sqlx::Error {
+    sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: Box::new(CorruptValue {
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }),
+    }
+}
+
+/// Read a decimal TEXT column.
+fn decimal_column(row: &SqliteRow, column: &str) -> Result<Decimal, sqlx::Error> {
+    let value: String = row.try_get(column)?;
+    Decimal::from_str(&value).map_err(|e| corrupt(column, &value, e))
+}
+
+/// Read a nullable decimal TEXT column.
+fn optional_decimal_column(row: &SqliteRow, column: &str) -> Result<Option<Decimal>, sqlx::Error> {
+    let value: Option<String> = row.try_get(column)?;
+    value
+        .map(|value| Decimal::from_str(&value).map_err(|e| corrupt(column, &value, e)))
+        .transpose()
+}
+
+/// Read a `buy`/`sell` column.
+fn side_column(row: &SqliteRow, column: &str) -> Result<Side, sqlx::Error> {
+    let value: String = row.try_get(column)?;
+    match value.as_str() {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err(corrupt(column, &value, "expected buy or sell")),
+    }
+}
+
+/// Value stored in `raw_fills.ordering_key` for `fill`.
+fn ordering_key(fill: &Fill) -> String {
+    FillOrderingKey::from_fill(fill).to_sortable_string()
+}
+
+/// Decode a `raw_fills` row selected with the standard fill column list.
+///
+/// # Errors
+/// Returns [`sqlx::Error::ColumnDecode`] if a stored value does not parse; see
+/// [`is_data_corruption`].
+pub(crate) fn fill_from_row(row: &SqliteRow) -> Result<Fill, sqlx::Error> {
+    let mut fill = Fill::new(
+        TimeMs::new(row.try_get("time_ms")?),
+        Address::new(row.try_get("user")?),
+        Coin::new(row.try_get("coin")?),
+        side_column(row, "side")?,
+        decimal_column(row, "px")?,
+        decimal_column(row, "sz")?,
+        decimal_column(row, "fee")?,
+        decimal_column(row, "closed_pnl")?,
+        optional_decimal_column(row, "builder_fee")?,
+        row.try_get("tid")?,
+        row.try_get("oid")?,
+    );
+    fill.fill_key = row.try_get("fill_key")?;
+    fill.crossed = row.try_get("crossed")?;
+    let fill_type: String = row.try_get("fill_type")?;
+    fill.fill_type = fill_type
+        .parse()
+        .map_err(|_| corrupt("fill_type", &fill_type, "expected trade, liquidation, or adl"))?;
+    fill.twap_id = row.try_get("twap_id")?;
+    Ok(fill)
 }
 
 #[cfg(test)]
@@ -1577,6 +5251,7 @@ mod tests {
     use super::*;
     use crate::db::migrations::init_db;
     use crate::domain::Deposit;
+    use crate::engine::Invariant;
     use tempfile::TempDir;
 
     async fn setup_test_db() -> (Repository, TempDir) {
@@ -1620,6 +5295,43 @@ mod tests {
         assert_eq!(fills[0].time_ms.as_i64(), 1000);
     }
 
+    #[tokio::test]
+    async fn test_attribution_tolerance_tier_roundtrip() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let fill = Fill::new(
+            TimeMs::new(1000),
+            Address::new("0x123".to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            Decimal::from_str("50000").unwrap(),
+            Decimal::from_str("1").unwrap(),
+            Decimal::from_str("0").unwrap(),
+            Decimal::from_str("0").unwrap(),
+            None,
+            Some(1),
+            None,
+        );
+        repo.insert_fill(&fill).await.unwrap();
+
+        let attribution = Attribution::from_logs_match(
+            true,
+            Some(Address::new("0xbuilder".to_string())),
+            AttributionConfidence::Fuzzy,
+        )
+        .with_tolerance_tier(Some(ToleranceTier::Bps))
+        .with_matched_log_key("tid:1".to_string());
+        repo.upsert_attributions_full(&[(fill.fill_key.clone(), attribution.clone())])
+            .await
+            .unwrap();
+
+        let stored = repo
+            .query_attributions_full(std::slice::from_ref(&fill.fill_key))
+            .await
+            .unwrap();
+        assert_eq!(stored[&fill.fill_key], attribution);
+    }
+
     #[tokio::test]
     async fn test_get_raw_fill_by_key() {
         let (repo, _temp) = setup_test_db().await;
@@ -1640,14 +5352,119 @@ mod tests {
 
         repo.insert_fill(&fill).await.expect("insert failed");
 
-        let retrieved = repo
-            .get_raw_fill_by_key(fill.fill_key())
+        let retrieved = repo
+            .get_raw_fill_by_key(fill.fill_key())
+            .await
+            .expect("query failed");
+
+        assert!(retrieved.is_some());
+        let retrieved_fill = retrieved.unwrap();
+        assert_eq!(retrieved_fill.time_ms.as_i64(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_decimal_is_reported_as_corruption() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let fill = Fill::new(
+            TimeMs::new(1000),
+            Address::new("0x123".to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            Decimal::from_str("50000").unwrap(),
+            Decimal::from_str("1").unwrap(),
+            Decimal::from_str("0").unwrap(),
+            Decimal::from_str("0").unwrap(),
+            None,
+            Some(1),
+            None,
+        );
+        repo.insert_fill(&fill).await.unwrap();
+        sqlx::query("UPDATE raw_fills SET px = 'not-a-number'")
+            .execute(repo.writer.pool())
+            .await
+            .unwrap();
+
+        let err = repo
+            .query_fills(&fill.user, None, None, None)
+            .await
+            .expect_err("corrupt px must not decode");
+        assert!(is_data_corruption(&err));
+        assert!(err.to_string().contains("px"));
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_moves_corrupt_fill_and_resets_pair() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+
+        for tid in [1, 2] {
+            let fill = Fill::new(
+                TimeMs::new(1000 * tid),
+                user.clone(),
+                coin.clone(),
+                Side::Buy,
+                Decimal::from_str("50000").unwrap(),
+                Decimal::from_str("1").unwrap(),
+                Decimal::from_str("0").unwrap(),
+                Decimal::from_str("0").unwrap(),
+                None,
+                Some(tid),
+                None,
+            );
+            repo.insert_fill(&fill).await.unwrap();
+        }
+        repo.insert_attributions(&[(
+            "0x123:BTC:tid:2".to_string(),
+            true,
+            "heuristic".to_string(),
+            "fuzzy".to_string(),
+            None,
+        )])
+        .await
+        .unwrap();
+        repo.store_compile_state(&user, &coin, Some(2000), Some("0x123:BTC:tid:2"))
+            .await
+            .unwrap();
+        sqlx::query("UPDATE raw_fills SET sz = '1..0' WHERE tid = 2")
+            .execute(repo.writer.pool())
+            .await
+            .unwrap();
+
+        assert_eq!(repo.quarantine_swept_version().await.unwrap(), None);
+        let report = repo.quarantine_corrupt_rows().await.unwrap();
+        assert_eq!(
+            repo.quarantine_swept_version().await.unwrap().as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(report.moved.get("raw_fills"), Some(&1));
+        assert_eq!(report.total(), 1);
+        assert_eq!(report.reset_pairs, vec![(user.clone(), coin.clone())]);
+
+        // The remaining fill reads again, and the pair recompiles from scratch.
+        let fills = repo.query_fills(&user, None, None, None).await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].tid, Some(1));
+        assert!(repo.get_compile_state(&user, &coin).await.unwrap().is_none());
+        assert!(repo
+            .query_attributions(&["0x123:BTC:tid:2".to_string()])
             .await
-            .expect("query failed");
-
-        assert!(retrieved.is_some());
-        let retrieved_fill = retrieved.unwrap();
-        assert_eq!(retrieved_fill.time_ms.as_i64(), 1000);
+            .unwrap()
+            .is_empty());
+
+        let rows = repo.query_quarantined_rows(Some(&user), 10).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].source_table, "raw_fills");
+        assert_eq!(rows[0].column, "sz");
+        assert_eq!(rows[0].value.as_deref(), Some("1..0"));
+        let row: serde_json::Value = serde_json::from_str(&rows[0].row).unwrap();
+        assert_eq!(row["fill_key"], "0x123:BTC:tid:2");
+
+        // A second sweep finds nothing new.
+        assert_eq!(repo.quarantine_corrupt_rows().await.unwrap().total(), 0);
+        assert!(repo.delete_quarantined_row(rows[0].id).await.unwrap());
+        assert!(repo.count_quarantined_rows(None).await.unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -1672,6 +5489,141 @@ mod tests {
         assert_eq!(fill_key, Some("tid:999".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_builder_log_file_roundtrip_and_replace() {
+        let (repo, _temp) = setup_test_db().await;
+        let builder = Address::new("0xABC".to_string());
+
+        assert!(repo.get_builder_log_file(&builder, "20240101").await.unwrap().is_none());
+
+        let mut file = BuilderLogFileRow {
+            builder: "0xABC".to_string(),
+            yyyymmdd: "20240101".to_string(),
+            sha256: "aa".to_string(),
+            etag: Some("\"v1\"".to_string()),
+            row_count: 3,
+            fetched_at_ms: 1000,
+            content: vec![1, 2, 3],
+        };
+        repo.upsert_builder_log_file(&file).await.unwrap();
+
+        file.sha256 = "bb".to_string();
+        file.etag = None;
+        file.content = vec![4, 5];
+        repo.upsert_builder_log_file(&file).await.unwrap();
+
+        let cached = repo
+            .get_builder_log_file(&builder, "20240101")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.builder, "0xabc");
+        assert_eq!(cached.sha256, "bb");
+        assert_eq!(cached.etag, None);
+        assert_eq!(cached.content, vec![4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_query_stale_compile_states() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0xabc".to_string());
+        let btc = Coin::new("BTC".to_string());
+        let eth = Coin::new("ETH".to_string());
+
+        repo.store_compile_state(&user, &btc, Some(1000), Some("a"))
+            .await
+            .unwrap();
+        repo.store_compile_state(&user, &eth, Some(1000), Some("b"))
+            .await
+            .unwrap();
+        assert!(repo.query_stale_compile_states().await.unwrap().is_empty());
+
+        sqlx::query("UPDATE compile_state SET compile_schema_version = 0 WHERE coin = 'ETH'")
+            .execute(repo.writer.pool())
+            .await
+            .unwrap();
+
+        let stale = repo.query_stale_compile_states().await.unwrap();
+        assert_eq!(stale, vec![(user, eth)]);
+    }
+
+    #[tokio::test]
+    async fn test_query_compile_progress_counts_uncompiled_fills() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0xabc".to_string());
+        let btc = Coin::new("BTC".to_string());
+        let eth = Coin::new("ETH".to_string());
+        let fill = |coin: &Coin, time_ms: i64, tid: i64| {
+            Fill::new(
+                TimeMs::new(time_ms),
+                user.clone(),
+                coin.clone(),
+                Side::Buy,
+                Decimal::from_str("100").unwrap(),
+                Decimal::from_str("1").unwrap(),
+                Decimal::zero(),
+                Decimal::zero(),
+                None,
+                Some(tid),
+                None,
+            )
+        };
+        repo.insert_fill(&fill(&btc, 1000, 1)).await.unwrap();
+        crate::compile::Compiler::compile_incremental(&repo, &user, &btc)
+            .await
+            .unwrap();
+        repo.insert_fill(&fill(&btc, 2000, 2)).await.unwrap();
+        repo.insert_fill(&fill(&eth, 3000, 3)).await.unwrap();
+
+        let progress = repo.query_compile_progress(&user).await.unwrap();
+        assert_eq!(progress.len(), 2);
+
+        assert_eq!(progress[0].coin, btc);
+        assert_eq!(progress[0].raw_fills, 2);
+        assert_eq!(progress[0].compiled_fills, 1);
+        assert_eq!(progress[0].last_compiled_time_ms, Some(1000));
+        assert_eq!(progress[0].compile_version, Some(1));
+        assert_eq!(progress[0].latest_raw_time_ms, Some(2000));
+
+        assert_eq!(progress[1].coin, eth);
+        assert_eq!(progress[1].raw_fills, 1);
+        assert_eq!(progress[1].compiled_fills, 0);
+        assert_eq!(progress[1].last_compiled_time_ms, None);
+        assert_eq!(progress[1].compile_version, None);
+    }
+
+    #[tokio::test]
+    async fn test_reset_derived_state_keeps_raw_fills() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0xabc".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let fill = Fill::new(
+            TimeMs::new(1000),
+            user.clone(),
+            coin.clone(),
+            Side::Buy,
+            Decimal::from_str("50000").unwrap(),
+            Decimal::from_str("1").unwrap(),
+            Decimal::zero(),
+            Decimal::zero(),
+            None,
+            Some(1),
+            None,
+        );
+        repo.insert_fill(&fill).await.unwrap();
+        crate::compile::Compiler::compile_incremental(&repo, &user, &coin)
+            .await
+            .unwrap();
+        assert!(!repo.query_lifecycles(&user, &coin).await.unwrap().is_empty());
+
+        repo.reset_derived_state(&user, &coin).await.unwrap();
+
+        assert!(repo.query_lifecycles(&user, &coin).await.unwrap().is_empty());
+        assert!(repo.query_snapshots(&user, &coin).await.unwrap().is_empty());
+        assert!(repo.get_compile_state(&user, &coin).await.unwrap().is_none());
+        assert_eq!(repo.query_fills(&user, Some(&coin), None, None).await.unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_insert_duplicate_fill_ignored() {
         let (repo, _temp) = setup_test_db().await;
@@ -1868,6 +5820,390 @@ mod tests {
         assert_eq!(stored.len(), 4);
     }
 
+    #[tokio::test]
+    async fn test_insert_fills_batch_spans_multiple_statements() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        // Full batches bind as many parameters as SQLite allows, so a miscounted column
+        // list fails the insert below.
+        let batch_rows = rows_per_insert(RAW_FILL_INSERT_COLUMNS);
+        assert!(batch_rows * RAW_FILL_INSERT_COLUMNS <= SQLITE_MAX_BIND_PARAMS);
+        assert!((batch_rows + 1) * RAW_FILL_INSERT_COLUMNS > SQLITE_MAX_BIND_PARAMS);
+        let n = batch_rows as i64 * 2 + 7;
+        let fills: Vec<Fill> = (1..=n)
+            .map(|i| {
+                Fill::new(
+                    TimeMs::new(i),
+                    user.clone(),
+                    coin.clone(),
+                    Side::Buy,
+                    Decimal::from_str("50000").unwrap(),
+                    Decimal::from_str("1").unwrap(),
+                    Decimal::from_str("10").unwrap(),
+                    Decimal::from_str("0").unwrap(),
+                    None,
+                    Some(i),
+                    None,
+                )
+            })
+            .collect();
+
+        // Duplicates inside the batch and across chunk boundaries are counted once
+        let mut batch = fills.clone();
+        batch.extend_from_slice(&fills[..10]);
+        assert_eq!(repo.insert_fills_batch(&batch).await.unwrap(), n as usize);
+        assert_eq!(repo.insert_fills_batch(&fills).await.unwrap(), 0);
+
+        let stored = repo.query_fills(&user, None, None, None).await.unwrap();
+        assert_eq!(stored.len(), n as usize);
+        assert_eq!(stored.last().unwrap().tid, Some(n));
+    }
+
+    #[tokio::test]
+    async fn test_insert_deposits_batch_spans_multiple_statements() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let user = Address::new("0x123".to_string());
+        let n = rows_per_insert(7) as i64 + 3;
+        let deposits: Vec<Deposit> = (1..=n)
+            .map(|i| {
+                Deposit::new(
+                    user.clone(),
+                    TimeMs::new(i),
+                    Decimal::from_str("1").unwrap(),
+                    Some(format!("0x{:x}", i)),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            repo.insert_deposits_batch(&deposits).await.unwrap(),
+            n as usize
+        );
+        assert_eq!(repo.insert_deposits_batch(&deposits).await.unwrap(), 0);
+        assert_eq!(
+            repo.query_deposits(&user, 0, n).await.unwrap().len(),
+            n as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relabel_walks_lifecycles_in_batches() {
+        let (repo, _temp) = setup_test_db().await;
+        let coin = Coin::new("BTC".to_string());
+        let lifecycles: Vec<Lifecycle> = (1..=5)
+            .map(|i| Lifecycle {
+                id: i,
+                user: Address::new(format!("0x{}", i % 2)),
+                coin: coin.clone(),
+                start_time_ms: TimeMs::new(i * 1000),
+                end_time_ms: None,
+                closed_by: None,
+            })
+            .collect();
+        repo.insert_lifecycles(&lifecycles).await.unwrap();
+        repo.upsert_strategy_rule(&StrategyRule {
+            name: "late".to_string(),
+            priority: 0,
+            coins: Vec::new(),
+            from_ms: Some(TimeMs::new(3000)),
+            to_ms: None,
+            tags: Vec::new(),
+        })
+        .await
+        .unwrap();
+
+        // Storing the rule relabels nothing by itself.
+        let labels = repo.query_lifecycle_strategies(&[1, 2, 3, 4, 5]).await.unwrap();
+        assert!(labels.is_empty());
+
+        let (changed, last) = repo.relabel_lifecycle_strategies(0, 3).await.unwrap();
+        assert_eq!(changed, vec![Address::new("0x1".to_string())]);
+        assert_eq!(last, Some(3));
+        let (changed, last) = repo.relabel_lifecycle_strategies(3, 3).await.unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(last, Some(5));
+        assert_eq!(repo.relabel_lifecycle_strategies(5, 3).await.unwrap(), (vec![], None));
+
+        let labels = repo.query_lifecycle_strategies(&[1, 2, 3, 4, 5]).await.unwrap();
+        assert_eq!(labels.len(), 3);
+        assert!(labels.values().all(|name| name == "late"));
+
+        assert!(repo.delete_strategy_rule("late").await.unwrap());
+        assert!(!repo.delete_strategy_rule("late").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_insert_derived_tables_spans_multiple_statements() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let user = Address::new("0xabc".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let n = rows_per_insert(8) as i64 + 5;
+        let lifecycles: Vec<Lifecycle> = (1..=n)
+            .map(|i| Lifecycle {
+                id: i,
+                user: user.clone(),
+                coin: coin.clone(),
+                start_time_ms: TimeMs::new(i),
+                end_time_ms: Some(TimeMs::new(i)),
+                closed_by: None,
+            })
+            .collect();
+        let snapshots: Vec<Snapshot> = (1..=n)
+            .map(|i| Snapshot {
+                time_ms: TimeMs::new(i),
+                seq: 0,
+                net_size: Decimal::zero(),
+                avg_entry_px: Decimal::zero(),
+                lifecycle_id: i,
+            })
+            .collect();
+        let fills: Vec<Fill> = (1..=n)
+            .map(|i| {
+                Fill::new(
+                    TimeMs::new(i),
+                    user.clone(),
+                    coin.clone(),
+                    Side::Buy,
+                    Decimal::from_str("1").unwrap(),
+                    Decimal::from_str("1").unwrap(),
+                    Decimal::zero(),
+                    Decimal::zero(),
+                    None,
+                    Some(i),
+                    None,
+                )
+            })
+            .collect();
+        repo.insert_fills_batch(&fills).await.unwrap();
+        let effects: Vec<Effect> = fills
+            .iter()
+            .zip(1..)
+            .map(|(fill, i)| Effect {
+                fill_key: fill.fill_key.clone(),
+                lifecycle_id: i,
+                ..Default::default()
+            })
+            .collect();
+
+        repo.insert_derived_tables_atomic(&user, &coin, &lifecycles, &snapshots, &effects)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.query_lifecycles(&user, &coin).await.unwrap().len(), n as usize);
+        assert_eq!(repo.query_snapshots(&user, &coin).await.unwrap().len(), n as usize);
+        let effect_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM fill_effects")
+            .fetch_one(repo.writer.pool())
+            .await
+            .unwrap();
+        assert_eq!(effect_count, n);
+    }
+
+    #[tokio::test]
+    async fn test_commit_compile_writes_taints_and_watermark_atomically() {
+        let (repo, _temp) = setup_test_db().await;
+
+        let user = Address::new("0xabc".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let lifecycles = vec![Lifecycle {
+            id: 1,
+            user: user.clone(),
+            coin: coin.clone(),
+            start_time_ms: TimeMs::new(1000),
+            end_time_ms: None,
+            closed_by: None,
+        }];
+        let snapshots = vec![Snapshot {
+            time_ms: TimeMs::new(1000),
+            seq: 0,
+            net_size: Decimal::from_str("1").unwrap(),
+            avg_entry_px: Decimal::from_str("100").unwrap(),
+            lifecycle_id: 1,
+        }];
+        let taints = vec![(1, true, Some("non-builder fill".to_string()))];
+
+        repo.commit_compile(&user, &coin, &lifecycles, &snapshots, &[], &taints, &[], (1000, "a"))
+            .await
+            .unwrap();
+
+        let stored = repo.query_lifecycles(&user, &coin).await.unwrap();
+        assert!(stored[0].is_tainted);
+        assert_eq!(stored[0].taint_reason.as_deref(), Some("non-builder fill"));
+        assert_eq!(
+            repo.get_compile_state(&user, &coin).await.unwrap(),
+            Some((Some(1000), Some("a".to_string())))
+        );
+
+        // A failing watermark write rolls back the derived rows written before it.
+        let other = Coin::new("ETH".to_string());
+        sqlx::query("DROP TABLE compile_state")
+            .execute(repo.writer.pool())
+            .await
+            .unwrap();
+        let eth_lifecycles = vec![Lifecycle {
+            id: 2,
+            coin: other.clone(),
+            ..lifecycles[0].clone()
+        }];
+        assert!(repo
+            .commit_compile(&user, &other, &eth_lifecycles, &[], &[], &[], &[], (2000, "b"))
+            .await
+            .is_err());
+        assert!(repo.query_lifecycles(&user, &other).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_records_invariant_violations_once() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0xabc".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let violations = vec![InvariantViolation {
+            invariant: Invariant::FillHasEffect,
+            fill_key: Some("a".to_string()),
+            lifecycle_id: None,
+            detail: "fill produced no effects".to_string(),
+        }];
+
+        for _ in 0..2 {
+            repo.reset_derived_state(&user, &coin).await.unwrap();
+            repo.commit_compile(&user, &coin, &[], &[], &[], &[], &violations, (1000, "a"))
+                .await
+                .unwrap();
+        }
+
+        let counts = repo
+            .count_invariant_violations(Some(&user), Some(&coin))
+            .await
+            .unwrap();
+        assert_eq!(counts.get("fill_has_effect"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_prune_position_snapshots_keeps_latest_and_archives() {
+        let (repo, temp) = setup_test_db().await;
+        let user = Address::new("0xabc".to_string());
+        let lifecycle = |coin: &Coin| Lifecycle {
+            id: if coin.as_str() == "BTC" { 1 } else { 2 },
+            user: user.clone(),
+            coin: coin.clone(),
+            start_time_ms: TimeMs::new(100),
+            end_time_ms: None,
+            closed_by: None,
+        };
+        let snapshot = |time_ms: i64, seq: i32, lifecycle_id: i64| Snapshot {
+            time_ms: TimeMs::new(time_ms),
+            seq,
+            net_size: Decimal::from_str(&seq.to_string()).unwrap(),
+            avg_entry_px: Decimal::zero(),
+            lifecycle_id,
+        };
+        let btc = Coin::new("BTC".to_string());
+        let eth = Coin::new("ETH".to_string());
+        let btc_snapshots = [
+            snapshot(100, 0, 1),
+            snapshot(200, 0, 1),
+            snapshot(200, 1, 1),
+            snapshot(900, 0, 1),
+        ];
+        repo.insert_derived_tables_atomic(&user, &btc, &[lifecycle(&btc)], &btc_snapshots, &[])
+            .await
+            .unwrap();
+        let eth_snapshots = [snapshot(100, 0, 2)];
+        repo.insert_derived_tables_atomic(&user, &eth, &[lifecycle(&eth)], &eth_snapshots, &[])
+            .await
+            .unwrap();
+
+        let archive = temp.path().join("archive.db").to_string_lossy().to_string();
+        let deleted = repo
+            .prune_position_snapshots(TimeMs::new(500), Some(&archive))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+
+        // BTC keeps its last snapshot before the cutoff; ETH's only snapshot stays.
+        let kept: Vec<(i64, i32)> = repo
+            .query_snapshots(&user, &btc)
+            .await
+            .unwrap()
+            .iter()
+            .map(|s| (s.time_ms.as_i64(), s.seq))
+            .collect();
+        assert_eq!(kept, vec![(200, 1), (900, 0)]);
+        assert_eq!(repo.query_snapshots(&user, &eth).await.unwrap().len(), 1);
+
+        let archived = SqlitePool::connect(&format!("sqlite:{}?mode=ro", archive))
+            .await
+            .unwrap();
+        let rows: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT time_ms, seq FROM position_snapshots ORDER BY time_ms, seq")
+                .fetch_all(&archived)
+                .await
+                .unwrap();
+        assert_eq!(rows, vec![(100, 0), (200, 0)]);
+
+        // A second run finds nothing new and the archive is detached again.
+        assert_eq!(
+            repo.prune_position_snapshots(TimeMs::new(500), Some(&archive))
+                .await
+                .unwrap(),
+            0
+        );
+
+        // A rebuild stores the same snapshots under new ids; they are archived once.
+        repo.reset_derived_state(&user, &btc).await.unwrap();
+        repo.insert_derived_tables_atomic(&user, &btc, &[lifecycle(&btc)], &btc_snapshots, &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.prune_position_snapshots(TimeMs::new(500), Some(&archive))
+                .await
+                .unwrap(),
+            2
+        );
+        let rows: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT time_ms, seq FROM position_snapshots ORDER BY time_ms, seq")
+                .fetch_all(&archived)
+                .await
+                .unwrap();
+        assert_eq!(rows, vec![(100, 0), (200, 0)]);
+        assert_eq!(
+            repo.prune_equity_snapshots(TimeMs::new(500), None).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_dedupes_archives_written_without_identity_index() {
+        let (repo, temp) = setup_test_db().await;
+        let archive = temp.path().join("archive.db").to_string_lossy().to_string();
+        let legacy = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", archive))
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE equity_snapshots (id, user, time_ms, equity)")
+            .execute(&legacy)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO equity_snapshots VALUES (1, '0xabc', 100, '5'), (7, '0xabc', 100, '5')",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+
+        repo.prune_equity_snapshots(TimeMs::new(500), Some(&archive))
+            .await
+            .unwrap();
+
+        let ids: Vec<(i64,)> = sqlx::query_as("SELECT id FROM equity_snapshots")
+            .fetch_all(&legacy)
+            .await
+            .unwrap();
+        assert_eq!(ids, vec![(1,)]);
+    }
+
     #[tokio::test]
     async fn test_insert_fills_batch_empty() {
         let (repo, _temp) = setup_test_db().await;
@@ -1919,4 +6255,137 @@ mod tests {
         assert!(inserted1);
         assert!(!inserted2);
     }
+
+    fn deposit(user: &Address, time_ms: i64, amount: &str) -> Deposit {
+        Deposit::new(
+            user.clone(),
+            TimeMs::new(time_ms),
+            Decimal::from_str(amount).unwrap(),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sum_deposits_uses_scaled_column() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0x123".to_string());
+        repo.insert_deposits_batch(&[
+            deposit(&user, 1000, "10.12345678"),
+            deposit(&user, 2000, "-0.00000001"),
+            deposit(&user, 3000, "5"),
+        ])
+        .await
+        .unwrap();
+        repo.insert_deposit(&deposit(&user, 1500, "0.5"))
+            .await
+            .unwrap();
+
+        let scaled = repo
+            .sum_scaled_column(
+                "SELECT COUNT(*), COUNT(amount_e8), SUM(amount_e8) FROM deposits \
+                 WHERE user = ? AND time_ms <= ?",
+                &user,
+                &[TimeMs::new(2000)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(scaled, Some(Decimal::from_str("10.62345677").unwrap()));
+        assert_eq!(
+            repo.sum_deposits_up_to(&user, TimeMs::new(2000)).await.unwrap(),
+            Decimal::from_str("10.62345677").unwrap()
+        );
+        assert_eq!(
+            repo.sum_deposits_up_to(&user, TimeMs::new(0)).await.unwrap(),
+            Decimal::zero()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sum_deposits_falls_back_for_unscaled_rows() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0x123".to_string());
+        repo.insert_deposits_batch(&[
+            deposit(&user, 1000, "1.5"),
+            // More than 8 decimal places: stored without a scaled value.
+            deposit(&user, 2000, "0.000000001"),
+        ])
+        .await
+        .unwrap();
+
+        let sum = repo.sum_deposits_up_to(&user, TimeMs::new(2000)).await.unwrap();
+        assert_eq!(sum, Decimal::from_str("1.500000001").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sum_deposits_falls_back_on_overflow() {
+        let (repo, _temp) = setup_test_db().await;
+        let user = Address::new("0x123".to_string());
+        // Each fits in i64 scaled units (9e18) but the total does not.
+        repo.insert_deposits_batch(&[
+            deposit(&user, 1000, "90000000000"),
+            deposit(&user, 2000, "90000000000"),
+        ])
+        .await
+        .unwrap();
+
+        let sum = repo.sum_deposits_up_to(&user, TimeMs::new(2000)).await.unwrap();
+        assert_eq!(sum, Decimal::from_str("180000000000").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_close_checkpoints_wal() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let pool = init_db(&db_path.to_string_lossy()).await.unwrap();
+        let repo = Repository::new(pool);
+        let user = Address::new("0x123".to_string());
+        repo.insert_deposits_batch(&[deposit(&user, 1000, "10")])
+            .await
+            .unwrap();
+
+        repo.close().await.expect("close failed");
+        assert!(repo.get_earliest_deposit_timestamp(&user).await.is_err());
+
+        let wal = temp_dir.path().join("test.db-wal");
+        assert!(std::fs::metadata(&wal).map(|m| m.len() == 0).unwrap_or(true));
+
+        let reopened = Repository::new(init_db(&db_path.to_string_lossy()).await.unwrap());
+        assert_eq!(
+            reopened.get_earliest_deposit_timestamp(&user).await.unwrap(),
+            Some(1000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upsert_coins_updates_in_place() {
+        let (repo, _temp_dir) = setup_test_db().await;
+        let meta = |name: &str, asset_index, delisted| CoinMeta {
+            coin: Coin::new(name.to_string()),
+            asset_index,
+            sz_decimals: 2,
+            max_leverage: 10,
+            delisted,
+        };
+        repo.upsert_coins(&[meta("ETH", 1, false), meta("BTC", 0, false)], TimeMs::new(1))
+            .await
+            .unwrap();
+        repo.upsert_coins(&[meta("ETH", 1, true)], TimeMs::new(2))
+            .await
+            .unwrap();
+
+        let coins = repo.query_coins().await.unwrap();
+        assert_eq!(coins, vec![meta("BTC", 0, false), meta("ETH", 1, true)]);
+    }
+
+    #[test]
+    fn test_last_in_bucket_keeps_at_most_max_points() {
+        let points: Vec<(TimeMs, i64)> = (0..=100).map(|i| (TimeMs::new(i * 10), i)).collect();
+        let kept = last_in_bucket(points, 4);
+        assert_eq!(
+            kept.iter().map(|(_, i)| *i).collect::<Vec<_>>(),
+            vec![25, 50, 75, 100]
+        );
+        assert!(last_in_bucket(Vec::<(TimeMs, i64)>::new(), 4).is_empty());
+        assert_eq!(last_in_bucket(vec![(TimeMs::new(5), 1)], 1), vec![(TimeMs::new(5), 1)]);
+    }
 }
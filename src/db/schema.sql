@@ -12,8 +12,17 @@ CREATE TABLE IF NOT EXISTS raw_fills (
     builder_fee TEXT,
     tid INTEGER,
     oid INTEGER,
+    -- 1 = taker, 0 = maker, NULL when the source did not report it
+    crossed INTEGER,
+    -- trade, liquidation or adl
+    fill_type TEXT NOT NULL DEFAULT 'trade',
+    -- id of the TWAP order the fill executed, NULL for regular orders
+    twap_id INTEGER,
     fill_key TEXT NOT NULL UNIQUE,
-    created_at INTEGER NOT NULL
+    created_at INTEGER NOT NULL,
+    -- FillOrderingKey encoded so byte order is compile order (time, tid, oid, fill_key).
+    -- Indexed with (user, coin) by the migrations once older databases have the column
+    ordering_key TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_raw_fills_user_coin_time ON raw_fills(user, coin, time_ms);
@@ -26,6 +35,14 @@ CREATE TABLE IF NOT EXISTS fill_attributions (
     mode TEXT NOT NULL,
     confidence TEXT NOT NULL,
     builder TEXT,
+    tolerance_tier TEXT,
+    matched_log_key TEXT,
+    -- 0 to 100, how likely the fill is to be builder flow
+    score INTEGER,
+    -- tid, fields, no_log_match, or builder_fee
+    reason TEXT,
+    -- fill time minus matched log row time, for field matches
+    match_time_delta_ms INTEGER,
     FOREIGN KEY(fill_key) REFERENCES raw_fills(fill_key)
 );
 
@@ -37,7 +54,11 @@ CREATE TABLE IF NOT EXISTS position_lifecycles (
     start_time_ms INTEGER NOT NULL,
     end_time_ms INTEGER,
     is_tainted INTEGER NOT NULL,
-    taint_reason TEXT
+    taint_reason TEXT,
+    -- fill type of the closing fill when it was a liquidation or adl, NULL otherwise
+    closed_by TEXT,
+    -- name of the first strategy rule the lifecycle matches, NULL when none does
+    strategy TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_lifecycles_user_coin ON position_lifecycles(user, coin);
@@ -56,7 +77,9 @@ CREATE TABLE IF NOT EXISTS position_snapshots (
     FOREIGN KEY(lifecycle_id) REFERENCES position_lifecycles(id)
 );
 
-CREATE INDEX IF NOT EXISTS idx_snapshots_user_coin_time ON position_snapshots(user, coin, time_ms);
+-- Ends with seq so the latest snapshot at or before a time is a single index seek
+CREATE INDEX IF NOT EXISTS idx_snapshots_user_coin_time_seq
+    ON position_snapshots(user, coin, time_ms, seq);
 
 -- Fill effects (flip decomposition)
 CREATE TABLE IF NOT EXISTS fill_effects (
@@ -67,12 +90,17 @@ CREATE TABLE IF NOT EXISTS fill_effects (
     qty TEXT NOT NULL,
     notional TEXT NOT NULL,
     fee TEXT NOT NULL,
+    -- share of the fill's builder fee, split like fee on flips
+    builder_fee TEXT NOT NULL DEFAULT '0',
     closed_pnl TEXT NOT NULL,
+    -- closed_pnl in 1e-8 units for exact SQL SUM (NULL when not representable)
+    closed_pnl_e8 INTEGER,
     FOREIGN KEY(fill_key) REFERENCES raw_fills(fill_key),
     FOREIGN KEY(lifecycle_id) REFERENCES position_lifecycles(id)
 );
 
 CREATE INDEX IF NOT EXISTS idx_effects_fill_key ON fill_effects(fill_key);
+CREATE INDEX IF NOT EXISTS idx_effects_lifecycle ON fill_effects(lifecycle_id);
 
 -- Deposits
 CREATE TABLE IF NOT EXISTS deposits (
@@ -80,7 +108,11 @@ CREATE TABLE IF NOT EXISTS deposits (
     user TEXT NOT NULL,
     time_ms INTEGER NOT NULL,
     amount TEXT NOT NULL,
+    -- amount in 1e-8 units for exact SQL SUM (NULL when not representable)
+    amount_e8 INTEGER,
     tx_hash TEXT,
+    -- ledger update type, NULL for rows stored before it was recorded
+    kind TEXT,
     event_key TEXT NOT NULL UNIQUE
 );
 
@@ -107,6 +139,50 @@ CREATE TABLE IF NOT EXISTS builder_logs_cache (
     UNIQUE(builder, yyyymmdd)
 );
 
+-- Builder log files (cached daily downloads, compressed as served)
+CREATE TABLE IF NOT EXISTS builder_log_files (
+    builder TEXT NOT NULL,
+    yyyymmdd TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    etag TEXT,
+    row_count INTEGER NOT NULL,
+    fetched_at_ms INTEGER NOT NULL,
+    content BLOB NOT NULL,
+    PRIMARY KEY(builder, yyyymmdd)
+);
+
+-- Parsed builder log rows, replaced whenever a day is backfilled
+CREATE TABLE IF NOT EXISTS builder_log_fills (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    builder TEXT NOT NULL,
+    yyyymmdd TEXT NOT NULL,
+    time_ms INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    side TEXT NOT NULL,
+    px TEXT NOT NULL,
+    sz TEXT NOT NULL,
+    tid INTEGER,
+    oid INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_builder_log_fills_day ON builder_log_fills(builder, yyyymmdd);
+CREATE INDEX IF NOT EXISTS idx_builder_log_fills_user ON builder_log_fills(user, time_ms);
+
+-- Builder log CSV rows that failed to parse (dead letters), replaced whenever a day is parsed
+CREATE TABLE IF NOT EXISTS builder_log_rejects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    builder TEXT NOT NULL,
+    yyyymmdd TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    byte_offset INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    raw TEXT NOT NULL,
+    recorded_at_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_builder_log_rejects_day ON builder_log_rejects(builder, yyyymmdd);
+
 -- Compile state (watermark tracking)
 CREATE TABLE IF NOT EXISTS compile_state (
     user TEXT NOT NULL,
@@ -114,6 +190,190 @@ CREATE TABLE IF NOT EXISTS compile_state (
     last_compiled_time_ms INTEGER,
     last_compiled_fill_key TEXT,
     compile_version INTEGER NOT NULL DEFAULT 1,
+    compile_schema_version INTEGER NOT NULL DEFAULT 0,
     PRIMARY KEY(user, coin)
 );
 
+-- Compile invariant violations (see engine::invariants), recorded with each compile run and
+-- cleared with the rest of a pair's derived rows on rebuild
+CREATE TABLE IF NOT EXISTS invariant_violations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    invariant TEXT NOT NULL,
+    fill_key TEXT,
+    lifecycle_id INTEGER,
+    detail TEXT NOT NULL,
+    recorded_at_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_invariant_violations_user_coin ON invariant_violations(user, coin);
+
+-- Lifecycles whose reported closed_pnl disagrees with avg-entry PnL (see engine::reconcile),
+-- cleared with the rest of a pair's derived rows on rebuild
+CREATE TABLE IF NOT EXISTS reconciliation_issues (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    lifecycle_id INTEGER NOT NULL,
+    start_time_ms INTEGER NOT NULL,
+    reported_pnl TEXT NOT NULL,
+    recomputed_pnl TEXT NOT NULL,
+    difference TEXT NOT NULL,
+    recorded_at_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_issues_user_coin ON reconciliation_issues(user, coin);
+
+-- Per-coin compile adjustments such as contract size rebases (see engine::compile_hooks)
+CREATE TABLE IF NOT EXISTS coin_adjustments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    coin TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    effective_time_ms INTEGER NOT NULL,
+    factor TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_coin_adjustments_coin ON coin_adjustments(coin, effective_time_ms);
+
+-- Named accounts grouping several wallet addresses (see api::accounts)
+CREATE TABLE IF NOT EXISTS account_groups (
+    name TEXT NOT NULL,
+    address TEXT NOT NULL,
+    PRIMARY KEY(name, address)
+);
+
+-- Info API responses as received, lz4-compressed, kept when RECORD_RAW_PAYLOADS is on so
+-- fills can be re-derived without refetching (identical responses are stored once)
+CREATE TABLE IF NOT EXISTS raw_payloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_type TEXT NOT NULL,
+    user TEXT,
+    request TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    fetched_at_ms INTEGER NOT NULL,
+    content BLOB NOT NULL,
+    UNIQUE(request, sha256)
+);
+
+CREATE INDEX IF NOT EXISTS idx_raw_payloads_type_user ON raw_payloads(request_type, user, fetched_at_ms);
+
+-- Responses of admin and ingest POSTs sent with an Idempotency-Key (see api::idempotency).
+-- caller is the hex SHA-256 of the API key, empty when authentication is disabled.
+-- status is NULL while the first request is still running
+CREATE TABLE IF NOT EXISTS idempotency_requests (
+    caller TEXT NOT NULL,
+    key TEXT NOT NULL,
+    path TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    created_at_ms INTEGER NOT NULL,
+    status INTEGER,
+    content_type TEXT,
+    body BLOB,
+    PRIMARY KEY(caller, key, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_requests_created ON idempotency_requests(created_at_ms);
+
+-- Leaderboard standings as of snapshot_ms, one row per metric and user. Rows are only
+-- ever inserted, so past rankings stay as they were published
+CREATE TABLE IF NOT EXISTS leaderboard_snapshots (
+    snapshot_ms INTEGER NOT NULL,
+    metric TEXT NOT NULL,
+    user TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    trade_count INTEGER NOT NULL,
+    -- users ranked in this snapshot
+    entrants INTEGER NOT NULL,
+    PRIMARY KEY(snapshot_ms, metric, user)
+);
+
+CREATE INDEX IF NOT EXISTS idx_leaderboard_snapshots_user ON leaderboard_snapshots(user, snapshot_ms);
+
+-- Competitions: a leaderboard over a fixed window and set of users (see api::competitions).
+-- prizes is a JSON array of prize descriptions by rank
+CREATE TABLE IF NOT EXISTS competitions (
+    name TEXT PRIMARY KEY,
+    from_ms INTEGER NOT NULL,
+    to_ms INTEGER NOT NULL,
+    metric TEXT NOT NULL,
+    builder_only INTEGER NOT NULL,
+    prizes TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS competition_users (
+    name TEXT NOT NULL,
+    address TEXT NOT NULL,
+    PRIMARY KEY(name, address)
+);
+
+-- Final standings of an ended competition, written once
+CREATE TABLE IF NOT EXISTS competition_results (
+    name TEXT NOT NULL,
+    user TEXT NOT NULL,
+    rank INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    trade_count INTEGER NOT NULL,
+    tainted INTEGER,
+    PRIMARY KEY(name, user)
+);
+
+-- Rows moved out of their table because a stored value did not decode (see
+-- Repository::quarantine_corrupt_rows), with the original row as a JSON object
+CREATE TABLE IF NOT EXISTS quarantined_rows (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_table TEXT NOT NULL,
+    source_id INTEGER NOT NULL,
+    user TEXT,
+    coin TEXT,
+    column_name TEXT NOT NULL,
+    value TEXT,
+    reason TEXT NOT NULL,
+    row_json TEXT NOT NULL,
+    quarantined_at_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_quarantined_rows_user ON quarantined_rows(user);
+
+-- Perp asset metadata from the Hyperliquid meta endpoint, refreshed periodically
+CREATE TABLE IF NOT EXISTS coins (
+    name TEXT PRIMARY KEY,
+    asset_index INTEGER NOT NULL,
+    sz_decimals INTEGER NOT NULL,
+    max_leverage INTEGER NOT NULL,
+    delisted INTEGER NOT NULL DEFAULT 0,
+    updated_at_ms INTEGER NOT NULL
+);
+
+-- Historical mark prices, the price of a coin at the start of each hourly bucket
+CREATE TABLE IF NOT EXISTS mark_prices (
+    coin TEXT NOT NULL,
+    time_ms INTEGER NOT NULL,
+    px TEXT NOT NULL,
+    PRIMARY KEY (coin, time_ms)
+);
+
+-- Span of buckets whose mark prices have been fetched, per coin
+CREATE TABLE IF NOT EXISTS mark_price_coverage (
+    coin TEXT PRIMARY KEY,
+    from_ms INTEGER NOT NULL,
+    to_ms INTEGER NOT NULL
+);
+
+-- Rules labelling lifecycles with a strategy, tried by priority then name
+-- coins and tags are JSON arrays, empty matching any coin and requiring no tag
+CREATE TABLE IF NOT EXISTS strategy_rules (
+    name TEXT PRIMARY KEY,
+    priority INTEGER NOT NULL,
+    coins TEXT NOT NULL,
+    from_ms INTEGER,
+    to_ms INTEGER,
+    tags TEXT NOT NULL
+);
+
+-- Versions of the build that last migrated this database (see db::compat)
+CREATE TABLE IF NOT EXISTS instance_metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,352 @@
+//! Startup self-check of the database file.
+//!
+//! Migrations only add: they create missing tables, indexes, and columns, then record the
+//! running build's versions in `instance_metadata`. An older binary opening a database
+//! written by a newer one would therefore overwrite the newer versions and run against a
+//! schema it does not understand. [`check_manifest`] refuses that before anything is
+//! migrated; [`verify_database`] then checks the migrated file itself.
+
+use crate::db::compat::{CompatError, CompatManifest};
+use crate::db::migrations::{run_migrations, COMPILE_SCHEMA_VERSION, SCHEMA_VERSION};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How thoroughly [`verify_database`] checks the file's pages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntegrityCheck {
+    /// `PRAGMA integrity_check`: every page, index entry, and constraint.
+    Full,
+    /// `PRAGMA quick_check`: skips matching indexes against their tables, so it runs in
+    /// roughly linear time on large databases.
+    #[default]
+    Quick,
+    /// Skip the page check; schema and version checks still run.
+    Off,
+}
+
+impl IntegrityCheck {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntegrityCheck::Full => "full",
+            IntegrityCheck::Quick => "quick",
+            IntegrityCheck::Off => "off",
+        }
+    }
+}
+
+impl FromStr for IntegrityCheck {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(IntegrityCheck::Full),
+            "quick" => Ok(IntegrityCheck::Quick),
+            "off" => Ok(IntegrityCheck::Off),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SelfCheckError {
+    #[error(
+        "database was last opened by hypesilico {crate_version} with schema version \
+         {recorded}, newer than this build's {supported}; run hypesilico {crate_version} or \
+         later, or restore a backup taken before the upgrade"
+    )]
+    SchemaTooNew {
+        recorded: i64,
+        supported: i64,
+        crate_version: String,
+    },
+    #[error(
+        "database has schema version {recorded} but this build serves {supported} read-only; \
+         start it once without READ_ONLY to migrate it"
+    )]
+    SchemaMismatch { recorded: i64, supported: i64 },
+    #[error(
+        "derived tables were compiled by engine version {recorded}, newer than this build's \
+         {supported}; run a build with engine version {recorded} or later"
+    )]
+    EngineTooNew { recorded: i64, supported: i64 },
+    #[error("database is incompatible with this build: {0}")]
+    Incompatible(CompatError),
+    #[error("integrity check failed:\n{}", list(.0))]
+    Integrity(Vec<String>),
+    #[error("schema does not match this build's:\n{}", list(.0))]
+    SchemaDrift(Vec<String>),
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+fn list(problems: &[String]) -> String {
+    problems
+        .iter()
+        .map(|p| format!("  - {}", p))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Refuse a database whose recorded versions this build cannot run against, before it is
+/// migrated. A missing file or one without a manifest passes: migrations set it up.
+pub async fn check_manifest(db_path: &str) -> Result<(), SelfCheckError> {
+    if !Path::new(db_path).exists() {
+        return Ok(());
+    }
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=ro", db_path))
+        .await?;
+    let manifest = CompatManifest::read(&pool).await;
+    pool.close().await;
+
+    let manifest = match manifest {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => return Ok(()),
+        Err(CompatError::Db(e)) => return Err(e.into()),
+        Err(e) => return Err(SelfCheckError::Incompatible(e)),
+    };
+    match manifest.check_against_current() {
+        Err(CompatError::SchemaTooNew { .. }) => Err(SelfCheckError::SchemaTooNew {
+            recorded: manifest.schema_version,
+            supported: SCHEMA_VERSION,
+            crate_version: manifest.crate_version,
+        }),
+        Err(e) => Err(SelfCheckError::Incompatible(e)),
+        Ok(_) if manifest.engine_version > COMPILE_SCHEMA_VERSION => {
+            Err(SelfCheckError::EngineTooNew {
+                recorded: manifest.engine_version,
+                supported: COMPILE_SCHEMA_VERSION,
+            })
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Like [`check_manifest`], for a database served with `READ_ONLY`, which is never
+/// migrated: the file must exist and have been migrated to exactly this build's schema.
+pub async fn check_read_only_manifest(db_path: &str) -> Result<(), SelfCheckError> {
+    check_manifest(db_path).await?;
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=ro", db_path))
+        .await?;
+    let manifest = CompatManifest::read(&pool).await;
+    pool.close().await;
+
+    match manifest {
+        Ok(Some(manifest)) if manifest.schema_version == SCHEMA_VERSION => Ok(()),
+        Ok(Some(manifest)) => Err(SelfCheckError::SchemaMismatch {
+            recorded: manifest.schema_version,
+            supported: SCHEMA_VERSION,
+        }),
+        Ok(None) => Err(SelfCheckError::Incompatible(CompatError::MissingManifest)),
+        Err(CompatError::Db(e)) => Err(e.into()),
+        Err(e) => Err(SelfCheckError::Incompatible(e)),
+    }
+}
+
+/// Check a migrated database: its pages (per `integrity`), that every table, column, and
+/// index a fresh migration creates exists, and that no pair was compiled by a newer engine.
+pub async fn verify_database(
+    pool: &SqlitePool,
+    integrity: IntegrityCheck,
+) -> Result<(), SelfCheckError> {
+    let pragma = match integrity {
+        IntegrityCheck::Full => Some("PRAGMA integrity_check"),
+        IntegrityCheck::Quick => Some("PRAGMA quick_check"),
+        IntegrityCheck::Off => None,
+    };
+    if let Some(pragma) = pragma {
+        let rows: Vec<(String,)> = sqlx::query_as(pragma).fetch_all(pool).await?;
+        let problems: Vec<String> = rows
+            .into_iter()
+            .map(|(row,)| row)
+            .filter(|row| row != "ok")
+            .collect();
+        if !problems.is_empty() {
+            return Err(SelfCheckError::Integrity(problems));
+        }
+    }
+
+    let drift = schema_drift(&expected_schema().await?, &Schema::read(pool).await?);
+    if !drift.is_empty() {
+        return Err(SelfCheckError::SchemaDrift(drift));
+    }
+
+    let (newest,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(compile_schema_version) FROM compile_state")
+            .fetch_one(pool)
+            .await?;
+    match newest {
+        Some(recorded) if recorded > COMPILE_SCHEMA_VERSION => Err(SelfCheckError::EngineTooNew {
+            recorded,
+            supported: COMPILE_SCHEMA_VERSION,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Tables with their columns, and index names, as found in `sqlite_master`.
+#[derive(Debug, Default)]
+struct Schema {
+    tables: BTreeMap<String, BTreeSet<String>>,
+    indexes: BTreeSet<String>,
+}
+
+impl Schema {
+    async fn read(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let mut schema = Schema::default();
+        let tables: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )
+        .fetch_all(pool)
+        .await?;
+        for (table,) in tables {
+            let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+                .bind(&table)
+                .fetch_all(pool)
+                .await?;
+            schema
+                .tables
+                .insert(table, columns.into_iter().map(|(c,)| c).collect());
+        }
+
+        // Automatic indexes back PRIMARY KEY and UNIQUE constraints; their names are
+        // internal, and the tables' columns already cover them.
+        let indexes: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND name NOT LIKE 'sqlite_%'",
+        )
+        .fetch_all(pool)
+        .await?;
+        schema.indexes = indexes.into_iter().map(|(i,)| i).collect();
+        Ok(schema)
+    }
+}
+
+/// The schema this build's migrations produce, from a scratch in-memory database.
+async fn expected_schema() -> Result<Schema, sqlx::Error> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    run_migrations(&pool).await?;
+    let schema = Schema::read(&pool).await;
+    pool.close().await;
+    schema
+}
+
+/// What `actual` lacks of `expected`. Extra tables, columns, and indexes are tolerated.
+fn schema_drift(expected: &Schema, actual: &Schema) -> Vec<String> {
+    let mut drift = Vec::new();
+    for (table, columns) in &expected.tables {
+        match actual.tables.get(table) {
+            None => drift.push(format!("missing table {}", table)),
+            Some(found) => drift.extend(
+                columns
+                    .difference(found)
+                    .map(|column| format!("missing column {}.{}", table, column)),
+            ),
+        }
+    }
+    drift.extend(
+        expected
+            .indexes
+            .difference(&actual.indexes)
+            .map(|index| format!("missing index {}", index)),
+    );
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::init_db;
+    use tempfile::TempDir;
+
+    async fn setup_db(temp_dir: &TempDir) -> (SqlitePool, String) {
+        let db_path = temp_dir.path().join("test.db").to_string_lossy().to_string();
+        let pool = init_db(&db_path).await.expect("init_db failed");
+        (pool, db_path)
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database_passes() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing.db").to_string_lossy().to_string();
+        check_manifest(&missing).await.unwrap();
+
+        let (pool, db_path) = setup_db(&temp_dir).await;
+        check_manifest(&db_path).await.unwrap();
+        for integrity in [IntegrityCheck::Full, IntegrityCheck::Quick, IntegrityCheck::Off] {
+            verify_database(&pool, integrity).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_newer_manifest_is_refused_before_migrating() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, db_path) = setup_db(&temp_dir).await;
+        sqlx::query("UPDATE instance_metadata SET value = ? WHERE key = 'schema_version'")
+            .bind((SCHEMA_VERSION + 1).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        match check_manifest(&db_path).await.unwrap_err() {
+            SelfCheckError::SchemaTooNew {
+                recorded,
+                supported,
+                ..
+            } => assert_eq!((recorded, supported), (SCHEMA_VERSION + 1, SCHEMA_VERSION)),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_index_and_column_are_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir).await;
+        sqlx::query("DROP INDEX idx_snapshots_user_coin_time_seq")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE raw_fills DROP COLUMN twap_id")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        match verify_database(&pool, IntegrityCheck::Off).await.unwrap_err() {
+            SelfCheckError::SchemaDrift(drift) => assert_eq!(
+                drift,
+                vec![
+                    "missing column raw_fills.twap_id",
+                    "missing index idx_snapshots_user_coin_time_seq",
+                ]
+            ),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pairs_compiled_by_newer_engine_are_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let (pool, _) = setup_db(&temp_dir).await;
+        sqlx::query(
+            "INSERT INTO compile_state (user, coin, compile_schema_version) VALUES ('0xabc', 'BTC', ?)",
+        )
+        .bind(COMPILE_SCHEMA_VERSION + 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            verify_database(&pool, IntegrityCheck::Quick).await.unwrap_err(),
+            SelfCheckError::EngineTooNew { .. }
+        ));
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::domain::{Address, Decimal};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// How a fill was attributed to a builder.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,10 +18,24 @@ pub struct Attribution {
 
     /// Which builder was matched (only set if mode=Logs and matched).
     pub builder: Option<Address>,
+
+    /// Which tolerance tier produced a fuzzy log match (only set if confidence=Fuzzy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance_tier: Option<ToleranceTier>,
+
+    /// Key of the builder log row that matched (only set if mode=Logs and matched).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_log_key: Option<String>,
+
+    /// How likely the fill is to be builder flow, from 0 to 100; see [`AttributionReason::score`].
+    pub score: u8,
+
+    /// What the decision was based on.
+    pub reason: AttributionReason,
 }
 
 /// How attribution was determined.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum AttributionMode {
     /// builder_fee > 0 implies builder-attributed.
@@ -31,7 +46,7 @@ pub enum AttributionMode {
 }
 
 /// Confidence level of attribution.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum AttributionConfidence {
     /// Exact match (e.g., tid matched in logs).
@@ -44,6 +59,177 @@ pub enum AttributionConfidence {
     Low,
 }
 
+impl AttributionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributionMode::Heuristic => "heuristic",
+            AttributionMode::Logs => "logs",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "heuristic" => Some(AttributionMode::Heuristic),
+            "logs" => Some(AttributionMode::Logs),
+            _ => None,
+        }
+    }
+}
+
+impl AttributionConfidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributionConfidence::Exact => "exact",
+            AttributionConfidence::Fuzzy => "fuzzy",
+            AttributionConfidence::Low => "low",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "exact" => Some(AttributionConfidence::Exact),
+            "fuzzy" => Some(AttributionConfidence::Fuzzy),
+            "low" => Some(AttributionConfidence::Low),
+            _ => None,
+        }
+    }
+}
+
+/// What an attribution decision was based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AttributionReason {
+    /// The fill's trade id appears in the builder logs.
+    Tid,
+
+    /// A log row for the same user, coin, and side matched on time, price, and size.
+    Fields {
+        /// Time between the fill and the log row; unknown for matches stored before it
+        /// was recorded.
+        #[serde(
+            default,
+            rename = "timeDeltaMs",
+            skip_serializing_if = "Option::is_none"
+        )]
+        time_delta_ms: Option<i64>,
+    },
+
+    /// No builder log row matched.
+    NoLogMatch,
+
+    /// Decided from the fill's builder fee alone.
+    BuilderFee,
+}
+
+/// Score of a trade id match.
+pub const SCORE_TID: u8 = 100;
+/// Score of a field match at zero time difference with the absolute price tolerance.
+pub const SCORE_FIELDS_ABSOLUTE: u8 = 90;
+/// Score of a field match at zero time difference with the basis point price tolerance.
+pub const SCORE_FIELDS_BPS: u8 = 70;
+/// Most a field match loses as its time difference reaches the time tolerance.
+pub const SCORE_FIELDS_TIME_PENALTY: u8 = 20;
+/// Score of a fill with a builder fee when no logs were consulted or matched.
+pub const SCORE_BUILDER_FEE: u8 = 30;
+
+impl AttributionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributionReason::Tid => "tid",
+            AttributionReason::Fields { .. } => "fields",
+            AttributionReason::NoLogMatch => "no_log_match",
+            AttributionReason::BuilderFee => "builder_fee",
+        }
+    }
+
+    /// Rebuild a reason from its stored code and time difference.
+    pub fn parse(s: &str, time_delta_ms: Option<i64>) -> Option<Self> {
+        match s {
+            "tid" => Some(AttributionReason::Tid),
+            "fields" => Some(AttributionReason::Fields { time_delta_ms }),
+            "no_log_match" => Some(AttributionReason::NoLogMatch),
+            "builder_fee" => Some(AttributionReason::BuilderFee),
+            _ => None,
+        }
+    }
+
+    /// The reason behind an attribution recorded only by mode and confidence.
+    pub fn infer(mode: AttributionMode, confidence: AttributionConfidence, attributed: bool) -> Self {
+        match (mode, attributed, confidence) {
+            (AttributionMode::Heuristic, _, _) => AttributionReason::BuilderFee,
+            (AttributionMode::Logs, false, _) => AttributionReason::NoLogMatch,
+            (AttributionMode::Logs, true, AttributionConfidence::Exact) => AttributionReason::Tid,
+            (AttributionMode::Logs, true, _) => AttributionReason::Fields {
+                time_delta_ms: None,
+            },
+        }
+    }
+
+    /// How likely a fill with this reason is to be builder flow, from 0 to 100.
+    ///
+    /// A field match starts at [`SCORE_FIELDS_ABSOLUTE`] or [`SCORE_FIELDS_BPS`] by price
+    /// tier (the lower one when the tier is unknown) and loses up to
+    /// [`SCORE_FIELDS_TIME_PENALTY`] in proportion to its time difference over
+    /// `time_tolerance_ms`; an unknown difference loses all of it.
+    pub fn score(
+        &self,
+        attributed: bool,
+        tier: Option<ToleranceTier>,
+        time_tolerance_ms: i64,
+    ) -> u8 {
+        match self {
+            AttributionReason::Tid => SCORE_TID,
+            AttributionReason::Fields { time_delta_ms } => {
+                let base = match tier {
+                    Some(ToleranceTier::Absolute) => SCORE_FIELDS_ABSOLUTE,
+                    _ => SCORE_FIELDS_BPS,
+                };
+                let penalty = match time_delta_ms {
+                    Some(dt) if time_tolerance_ms > 0 => {
+                        let ratio = dt.unsigned_abs().min(time_tolerance_ms as u64);
+                        (ratio * u64::from(SCORE_FIELDS_TIME_PENALTY) / time_tolerance_ms as u64)
+                            as u8
+                    }
+                    Some(_) => 0,
+                    None => SCORE_FIELDS_TIME_PENALTY,
+                };
+                base - penalty
+            }
+            AttributionReason::NoLogMatch => 0,
+            AttributionReason::BuilderFee if attributed => SCORE_BUILDER_FEE,
+            AttributionReason::BuilderFee => 0,
+        }
+    }
+}
+
+/// Tolerance tier that admitted a fuzzy log match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ToleranceTier {
+    /// Price and size both within the absolute tolerances.
+    Absolute,
+
+    /// Price only within the relative (basis point) tolerance.
+    Bps,
+}
+
+impl ToleranceTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToleranceTier::Absolute => "absolute",
+            ToleranceTier::Bps => "bps",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "absolute" => Some(ToleranceTier::Absolute),
+            "bps" => Some(ToleranceTier::Bps),
+            _ => None,
+        }
+    }
+}
+
 // Backward-compatible alias from PR-002 naming.
 pub type Confidence = AttributionConfidence;
 
@@ -57,12 +243,7 @@ impl Attribution {
             })
             .unwrap_or(false);
 
-        Self {
-            attributed,
-            mode: AttributionMode::Heuristic,
-            confidence: AttributionConfidence::Low,
-            builder: None,
-        }
+        Self::heuristic(attributed)
     }
 
     /// Create attribution from log match.
@@ -71,24 +252,53 @@ impl Attribution {
         builder: Option<Address>,
         confidence: AttributionConfidence,
     ) -> Self {
+        let reason = AttributionReason::infer(AttributionMode::Logs, confidence, matched);
         Self {
             attributed: matched,
             mode: AttributionMode::Logs,
             confidence,
             builder,
+            tolerance_tier: None,
+            matched_log_key: None,
+            score: reason.score(matched, None, 0),
+            reason,
         }
     }
 
+    /// Record the tolerance tier that produced a fuzzy match.
+    pub fn with_tolerance_tier(mut self, tier: Option<ToleranceTier>) -> Self {
+        self.tolerance_tier = tier;
+        self
+    }
+
+    /// Record what the decision was based on and its score.
+    pub fn with_reason(mut self, reason: AttributionReason, score: u8) -> Self {
+        self.reason = reason;
+        self.score = score;
+        self
+    }
+
+    /// Record which builder log row produced the match.
+    pub fn with_matched_log_key(mut self, log_key: String) -> Self {
+        self.matched_log_key = Some(log_key);
+        self
+    }
+
     /// Convenience wrapper for legacy callsites.
     ///
     /// Uses Low confidence because builder_fee > 0 doesn't verify
     /// the builder address matches TARGET_BUILDER.
     pub fn heuristic(attributed: bool) -> Self {
+        let reason = AttributionReason::BuilderFee;
         Self {
             attributed,
             mode: AttributionMode::Heuristic,
             confidence: AttributionConfidence::Low,
             builder: None,
+            tolerance_tier: None,
+            matched_log_key: None,
+            score: reason.score(attributed, None, 0),
+            reason,
         }
     }
 
@@ -112,6 +322,8 @@ mod tests {
         assert_eq!(attr.mode, AttributionMode::Heuristic);
         assert_eq!(attr.confidence, AttributionConfidence::Low);
         assert!(attr.builder.is_none());
+        assert_eq!(attr.reason, AttributionReason::BuilderFee);
+        assert_eq!(attr.score, SCORE_BUILDER_FEE);
     }
 
     #[test]
@@ -139,6 +351,52 @@ mod tests {
         assert_eq!(attr.builder, Some(builder));
     }
 
+    #[test]
+    fn test_fields_score_falls_with_time_delta() {
+        let score = |tier, dt| AttributionReason::Fields { time_delta_ms: dt }.score(true, tier, 1_000);
+        assert_eq!(score(Some(ToleranceTier::Absolute), Some(0)), 90);
+        assert_eq!(score(Some(ToleranceTier::Absolute), Some(-500)), 80);
+        assert_eq!(score(Some(ToleranceTier::Absolute), Some(1_000)), 70);
+        assert_eq!(score(Some(ToleranceTier::Bps), Some(250)), 65);
+        // Unknown tier and time difference score as the worst match they could be.
+        assert_eq!(score(None, None), 50);
+        assert_eq!(AttributionReason::Tid.score(true, None, 1_000), SCORE_TID);
+        assert_eq!(AttributionReason::NoLogMatch.score(false, None, 1_000), 0);
+    }
+
+    #[test]
+    fn test_reason_roundtrip() {
+        for reason in [
+            AttributionReason::Tid,
+            AttributionReason::Fields {
+                time_delta_ms: Some(12),
+            },
+            AttributionReason::NoLogMatch,
+            AttributionReason::BuilderFee,
+        ] {
+            let dt = match reason {
+                AttributionReason::Fields { time_delta_ms } => time_delta_ms,
+                _ => None,
+            };
+            assert_eq!(AttributionReason::parse(reason.as_str(), dt), Some(reason));
+        }
+        assert_eq!(
+            serde_json::to_value(AttributionReason::Fields {
+                time_delta_ms: Some(12)
+            })
+            .unwrap(),
+            serde_json::json!({"kind": "fields", "timeDeltaMs": 12})
+        );
+    }
+
+    #[test]
+    fn test_tolerance_tier_roundtrip() {
+        for tier in [ToleranceTier::Absolute, ToleranceTier::Bps] {
+            assert_eq!(ToleranceTier::parse(tier.as_str()), Some(tier));
+        }
+        assert_eq!(ToleranceTier::parse("loose"), None);
+    }
+
     #[test]
     fn test_attribution_serialization_roundtrip() {
         let fee = Decimal::from_str("1").unwrap();
@@ -17,3 +17,24 @@ pub struct BuilderLogFill {
     pub oid: Option<i64>,
 }
 
+impl BuilderLogFill {
+    /// Stable reference to this log row, stored as `fill_attributions.matched_log_key`.
+    ///
+    /// Rows carrying a `tid` are keyed by it; otherwise the key spells out the matched fields
+    /// so an auditor can find the row in the day's CSV.
+    pub fn log_key(&self) -> String {
+        match self.tid {
+            Some(tid) => format!("tid:{}", tid),
+            None => format!(
+                "{}:{}:{}:{}:{}:{}",
+                self.time_ms.as_ms(),
+                self.user,
+                self.coin.as_str().to_ascii_uppercase(),
+                self.side,
+                self.px.to_canonical_string(),
+                self.sz.to_canonical_string()
+            ),
+        }
+    }
+}
+
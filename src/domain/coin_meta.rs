@@ -0,0 +1,185 @@
+//! Perp asset metadata from Hyperliquid's `meta` endpoint, and symbol lookup over it.
+
+use crate::domain::{Coin, Decimal};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+/// Asset id of the first asset of builder-deployed perp dex `n` (counting the default dex
+/// as `0`) is `BUILDER_DEX_ASSET_OFFSET + n * BUILDER_DEX_ASSET_STRIDE`.
+pub const BUILDER_DEX_ASSET_OFFSET: u32 = 100_000;
+pub const BUILDER_DEX_ASSET_STRIDE: u32 = 10_000;
+
+/// One asset of the perp universe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinMeta {
+    pub coin: Coin,
+    /// Asset id orders are placed with: the position in the `meta` universe, offset for a
+    /// builder-deployed dex (see [`BUILDER_DEX_ASSET_OFFSET`]).
+    pub asset_index: u32,
+    /// Decimal places sizes are quoted in.
+    pub sz_decimals: u32,
+    pub max_leverage: u32,
+    /// Still in the universe, so its history resolves, but no longer tradable.
+    pub delisted: bool,
+}
+
+/// The stored [`CoinMeta`] of every known asset.
+///
+/// An empty registry, before the first refresh, knows nothing, so it accepts every symbol
+/// as given and renders sizes canonically.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoinRegistry {
+    coins: BTreeMap<Coin, CoinMeta>,
+    /// Uppercased name to name, for case-insensitive lookups.
+    folded: HashMap<String, Coin>,
+    /// Dexes with at least one listed asset; `""` is the default dex.
+    dexes: HashSet<String>,
+}
+
+impl CoinRegistry {
+    pub fn new(coins: Vec<CoinMeta>) -> Self {
+        let folded = coins
+            .iter()
+            .map(|meta| (meta.coin.as_str().to_uppercase(), meta.coin.clone()))
+            .collect();
+        let dexes = coins
+            .iter()
+            .map(|meta| meta.coin.dex().unwrap_or_default().to_string())
+            .collect();
+        let coins = coins
+            .into_iter()
+            .map(|meta| (meta.coin.clone(), meta))
+            .collect();
+        Self {
+            coins,
+            folded,
+            dexes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn get(&self, coin: &Coin) -> Option<&CoinMeta> {
+        self.coins.get(coin)
+    }
+
+    /// Every asset, in asset id order.
+    pub fn coins(&self) -> Vec<&CoinMeta> {
+        let mut coins: Vec<_> = self.coins.values().collect();
+        coins.sort_by_key(|meta| meta.asset_index);
+        coins
+    }
+
+    /// The asset `symbol` names, spelled the way Hyperliquid spells it.
+    ///
+    /// Matches regardless of case, and resolves the `1000`-prefixed name of an asset quoted
+    /// per thousand units, so `1000PEPE` is `kPEPE`; the unscaled `PEPE` is not, since it
+    /// could name a different asset. The same applies to the asset of a dex-qualified
+    /// symbol, so `XYZ:tsla` is `xyz:TSLA`. Returns
+    /// `None` for a symbol the registry does not list. A symbol of a dex without listed
+    /// assets, and spot symbols (`@107`, `PURR/USDC`), are returned as given, as is every
+    /// symbol while the registry is empty.
+    pub fn resolve(&self, symbol: &str) -> Option<Coin> {
+        let coin = Coin::from_str(symbol).ok()?;
+        let dex = coin.dex().unwrap_or_default();
+        if !self.dexes.contains(dex) || coin.as_str().contains(['@', '/']) {
+            return Some(coin);
+        }
+        let prefix = match coin.dex() {
+            Some(dex) => format!("{}{}", dex.to_uppercase(), Coin::DEX_SEPARATOR),
+            None => String::new(),
+        };
+        let name = coin.symbol().to_uppercase();
+        self.folded
+            .get(&format!("{}{}", prefix, name))
+            .or_else(|| {
+                let unscaled = name.strip_prefix("1000")?;
+                self.folded.get(&format!("{}K{}", prefix, unscaled))
+            })
+            .cloned()
+    }
+
+    /// `sz` with the asset's size decimals, e.g. `0.10000` for BTC; canonical for an
+    /// unknown asset.
+    pub fn format_sz(&self, coin: &Coin, sz: &Decimal) -> String {
+        match self.get(coin) {
+            Some(meta) => format!("{:.*}", meta.sz_decimals as usize, sz.inner()),
+            None => sz.to_canonical_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(name: &str, asset_index: u32, sz_decimals: u32) -> CoinMeta {
+        CoinMeta {
+            coin: Coin::new(name.to_string()),
+            asset_index,
+            sz_decimals,
+            max_leverage: 20,
+            delisted: false,
+        }
+    }
+
+    fn registry() -> CoinRegistry {
+        CoinRegistry::new(vec![
+            meta("BTC", 0, 5),
+            meta("ETH", 1, 4),
+            meta("kPEPE", 2, 0),
+            meta("xyz:TSLA", 110_000, 3),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_normalizes_case_and_aliases() {
+        let coins = registry();
+        let resolve = |s: &str| coins.resolve(s).map(|c| c.as_str().to_string());
+        assert_eq!(resolve("BTC").as_deref(), Some("BTC"));
+        assert_eq!(resolve(" eth ").as_deref(), Some("ETH"));
+        assert_eq!(resolve("kPEPE").as_deref(), Some("kPEPE"));
+        assert_eq!(resolve("KPEPE").as_deref(), Some("kPEPE"));
+        assert_eq!(resolve("pepe"), None);
+        assert_eq!(resolve("1000PEPE").as_deref(), Some("kPEPE"));
+        assert_eq!(resolve("DOGE"), None);
+        assert_eq!(resolve(""), None);
+        assert_eq!(resolve("xyz:TSLA").as_deref(), Some("xyz:TSLA"));
+        assert_eq!(resolve("XYZ:tsla").as_deref(), Some("xyz:TSLA"));
+        assert_eq!(resolve("xyz:BTC"), None);
+        assert_eq!(resolve("xyz:"), None);
+        // Dexes without listed assets, and spot, pass through.
+        assert_eq!(resolve("FLX:Gold").as_deref(), Some("flx:Gold"));
+        assert_eq!(resolve("@107").as_deref(), Some("@107"));
+    }
+
+    #[test]
+    fn test_empty_registry_accepts_any_symbol() {
+        let coins = CoinRegistry::default();
+        assert_eq!(coins.resolve("DOGE"), Some(Coin::new("DOGE".to_string())));
+        assert_eq!(coins.resolve("  "), None);
+    }
+
+    #[test]
+    fn test_format_sz_uses_size_decimals() {
+        let coins = registry();
+        let sz = Decimal::from_str_canonical("0.1").unwrap();
+        assert_eq!(coins.format_sz(&Coin::new("BTC".to_string()), &sz), "0.10000");
+        let sz = Decimal::from_str_canonical("1200").unwrap();
+        assert_eq!(coins.format_sz(&Coin::new("kPEPE".to_string()), &sz), "1200");
+        let sz = Decimal::from_str_canonical("1.50").unwrap();
+        assert_eq!(coins.format_sz(&Coin::new("DOGE".to_string()), &sz), "1.5");
+    }
+
+    #[test]
+    fn test_coins_in_asset_order() {
+        let names: Vec<_> = registry().coins().iter().map(|m| m.coin.to_string()).collect();
+        assert_eq!(names, vec!["BTC", "ETH", "kPEPE", "xyz:TSLA"]);
+    }
+}
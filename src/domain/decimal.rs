@@ -6,6 +6,28 @@ use rust_decimal::Decimal as RustDecimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
+use thiserror::Error;
+
+/// Version of the canonical string encoding produced by [`Decimal::to_canonical_string`].
+///
+/// Decimal columns are stored as TEXT in this form; bump when the encoding changes.
+pub const DECIMAL_FORMAT_VERSION: i64 = 1;
+
+/// Decimal places of the scaled integer columns stored next to some decimal columns.
+///
+/// A value is stored as an integer count of `10^-SCALED_DECIMAL_PLACES` units so SQLite can
+/// `SUM` it exactly.
+pub const SCALED_DECIMAL_PLACES: u32 = 8;
+
+/// Why checked arithmetic on a [`Decimal`] has no result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DecimalError {
+    /// The result does not fit in 96 bits of mantissa.
+    #[error("decimal overflow in {0}")]
+    Overflow(&'static str),
+    #[error("decimal division by zero")]
+    DivisionByZero,
+}
 
 /// Lossless decimal numeric type for financial calculations.
 ///
@@ -47,6 +69,11 @@ impl Decimal {
         Decimal(RustDecimal::ZERO)
     }
 
+    /// The multiplicative identity (1).
+    pub fn one() -> Self {
+        Decimal(RustDecimal::ONE)
+    }
+
     /// Returns true if the value is exactly zero.
     pub fn is_zero(&self) -> bool {
         self.0.is_zero()
@@ -71,6 +98,62 @@ impl Decimal {
     pub fn hundred() -> Self {
         Decimal(RustDecimal::ONE_HUNDRED)
     }
+
+    /// The value as a count of `10^-SCALED_DECIMAL_PLACES` units.
+    ///
+    /// Returns `None` when the value has more decimal places than that or the count does not
+    /// fit in an `i64`; such values must be summed as decimals.
+    pub fn to_scaled_units(&self) -> Option<i64> {
+        let normalized = self.0.normalize();
+        let scale = normalized.scale();
+        if scale > SCALED_DECIMAL_PLACES {
+            return None;
+        }
+        let units = normalized
+            .mantissa()
+            .checked_mul(10i128.pow(SCALED_DECIMAL_PLACES - scale))?;
+        i64::try_from(units).ok()
+    }
+
+    /// `self + rhs`, or an error where `+` would panic.
+    pub fn checked_add(self, rhs: Decimal) -> Result<Decimal, DecimalError> {
+        self.0
+            .checked_add(rhs.0)
+            .map(Decimal)
+            .ok_or(DecimalError::Overflow("add"))
+    }
+
+    /// `self - rhs`, or an error where `-` would panic.
+    pub fn checked_sub(self, rhs: Decimal) -> Result<Decimal, DecimalError> {
+        self.0
+            .checked_sub(rhs.0)
+            .map(Decimal)
+            .ok_or(DecimalError::Overflow("sub"))
+    }
+
+    /// `self * rhs`, or an error where `*` would panic.
+    pub fn checked_mul(self, rhs: Decimal) -> Result<Decimal, DecimalError> {
+        self.0
+            .checked_mul(rhs.0)
+            .map(Decimal)
+            .ok_or(DecimalError::Overflow("mul"))
+    }
+
+    /// `self / rhs`, or an error where `/` would panic.
+    pub fn checked_div(self, rhs: Decimal) -> Result<Decimal, DecimalError> {
+        if rhs.is_zero() {
+            return Err(DecimalError::DivisionByZero);
+        }
+        self.0
+            .checked_div(rhs.0)
+            .map(Decimal)
+            .ok_or(DecimalError::Overflow("div"))
+    }
+
+    /// Inverse of [`Decimal::to_scaled_units`].
+    pub fn from_scaled_units(units: i64) -> Self {
+        Decimal(RustDecimal::new(units, SCALED_DECIMAL_PLACES))
+    }
 }
 
 impl fmt::Display for Decimal {
@@ -99,7 +182,8 @@ impl From<Decimal> for RustDecimal {
     }
 }
 
-// Arithmetic operations
+// Arithmetic operations. These panic on overflow; use the `checked_*` methods where inputs
+// are not bounded.
 impl std::ops::Add for Decimal {
     type Output = Decimal;
 
@@ -144,6 +228,46 @@ impl std::ops::Neg for Decimal {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_checked_arithmetic() {
+        let d = |s: &str| Decimal::from_str_canonical(s).unwrap();
+        assert_eq!(d("1.5").checked_add(d("2")), Ok(d("3.5")));
+        assert_eq!(d("1.5").checked_sub(d("2")), Ok(d("-0.5")));
+        assert_eq!(d("1.5").checked_mul(d("2")), Ok(d("3")));
+        assert_eq!(d("3").checked_div(d("2")), Ok(d("1.5")));
+
+        let max = Decimal::new(RustDecimal::MAX);
+        assert_eq!(max.checked_add(d("1")), Err(DecimalError::Overflow("add")));
+        assert_eq!((-max).checked_sub(d("1")), Err(DecimalError::Overflow("sub")));
+        assert_eq!(max.checked_mul(d("2")), Err(DecimalError::Overflow("mul")));
+        assert_eq!(max.checked_div(d("0.1")), Err(DecimalError::Overflow("div")));
+        assert_eq!(d("1").checked_div(Decimal::zero()), Err(DecimalError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_scaled_units_roundtrip() {
+        for s in ["0", "1", "-1", "123.45678901", "-0.00000001", "92233720368.54775807"] {
+            let decimal = Decimal::from_str_canonical(s).unwrap();
+            let units = decimal.to_scaled_units().expect(s);
+            assert_eq!(Decimal::from_scaled_units(units), decimal, "{}", s);
+        }
+        assert_eq!(
+            Decimal::from_str_canonical("1.5").unwrap().to_scaled_units(),
+            Some(150_000_000)
+        );
+    }
+
+    #[test]
+    fn test_scaled_units_reject_precision_and_range() {
+        let too_precise = Decimal::from_str_canonical("0.000000001").unwrap();
+        assert_eq!(too_precise.to_scaled_units(), None);
+        // Trailing zeros past the 8th place do not count as precision.
+        let padded = Decimal::from_str_canonical("0.1000000000").unwrap();
+        assert_eq!(padded.to_scaled_units(), Some(10_000_000));
+        let too_large = Decimal::from_str_canonical("92233720368.54775808").unwrap();
+        assert_eq!(too_large.to_scaled_units(), None);
+    }
+
     #[test]
     fn test_decimal_parse_roundtrip() {
         let test_cases = vec![
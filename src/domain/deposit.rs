@@ -3,6 +3,71 @@
 use crate::domain::{Address, Decimal, TimeMs};
 use serde::{Deserialize, Serialize};
 
+/// The ledger update a [`Deposit`] was recorded from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LedgerKind {
+    #[default]
+    Deposit,
+    Withdraw,
+    /// USDC sent to or received from another account.
+    InternalTransfer,
+    /// USDC moved between a master account and one of its sub-accounts.
+    SubAccountTransfer,
+    /// A spot token sent to or received from another account, valued in USDC.
+    SpotTransfer,
+}
+
+impl LedgerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerKind::Deposit => "deposit",
+            LedgerKind::Withdraw => "withdraw",
+            LedgerKind::InternalTransfer => "internalTransfer",
+            LedgerKind::SubAccountTransfer => "subAccountTransfer",
+            LedgerKind::SpotTransfer => "spotTransfer",
+        }
+    }
+
+    /// Transfers appear in the ledgers of both accounts under the same hash.
+    pub fn is_transfer(&self) -> bool {
+        matches!(
+            self,
+            LedgerKind::InternalTransfer | LedgerKind::SubAccountTransfer | LedgerKind::SpotTransfer
+        )
+    }
+
+    /// `Deposit` for inflows and `Withdraw` for outflows.
+    pub fn from_sign(amount: &Decimal) -> Self {
+        if amount.is_negative() {
+            LedgerKind::Withdraw
+        } else {
+            LedgerKind::Deposit
+        }
+    }
+}
+
+impl std::fmt::Display for LedgerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for LedgerKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deposit" => Ok(LedgerKind::Deposit),
+            "withdraw" => Ok(LedgerKind::Withdraw),
+            "internalTransfer" => Ok(LedgerKind::InternalTransfer),
+            "subAccountTransfer" => Ok(LedgerKind::SubAccountTransfer),
+            "spotTransfer" => Ok(LedgerKind::SpotTransfer),
+            _ => Err(()),
+        }
+    }
+}
+
 /// A deposit/withdrawal ledger event.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Deposit {
@@ -19,6 +84,9 @@ pub struct Deposit {
     /// Transaction hash when available.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tx_hash: Option<String>,
+    /// Ledger update type; inferred from the sign of `amount` when not given.
+    #[serde(default)]
+    pub kind: LedgerKind,
 }
 
 impl Deposit {
@@ -30,11 +98,30 @@ impl Deposit {
             event_key,
             user,
             time_ms,
+            kind: LedgerKind::from_sign(&amount),
             amount,
             tx_hash,
         }
     }
 
+    /// Set the ledger update type.
+    ///
+    /// Transfers get an `event_key` scoped to the user, so that the sending and receiving
+    /// sides of one transfer, which share a hash, are both kept.
+    pub fn with_kind(mut self, kind: LedgerKind) -> Self {
+        self.kind = kind;
+        if kind.is_transfer() {
+            self.event_key = Self::compute_transfer_event_key(
+                &self.user,
+                self.time_ms,
+                &self.amount,
+                self.tx_hash.as_deref(),
+                kind,
+            );
+        }
+        self
+    }
+
     /// Compute a stable unique key for this event.
     ///
     /// Priority: `tx_hash` (if present) > hash of deterministic fields (user, time_ms, amount).
@@ -70,6 +157,29 @@ impl Deposit {
         let hash = hasher.finalize();
         format!("hash:{}", hex::encode(&hash[..16]))
     }
+
+    /// Key for one user's side of a transfer: `tx_hash:user` when the hash is known,
+    /// otherwise a hash of the same fields as [`Self::compute_event_key`] plus the kind.
+    pub fn compute_transfer_event_key(
+        user: &Address,
+        time_ms: TimeMs,
+        amount: &Decimal,
+        tx_hash: Option<&str>,
+        kind: LedgerKind,
+    ) -> String {
+        if let Some(tx) = tx_hash.filter(|s| !s.trim().is_empty()) {
+            return format!("{}:{}", tx.trim().to_lowercase(), user.as_str().to_lowercase());
+        }
+
+        use sha2::{Digest, Sha256};
+
+        let base = Self::compute_event_key(user, time_ms, amount, None);
+        let mut hasher = Sha256::new();
+        hasher.update(base.as_bytes());
+        hasher.update(kind.as_str().as_bytes());
+        let hash = hasher.finalize();
+        format!("hash:{}", hex::encode(&hash[..16]))
+    }
 }
 
 fn normalize_tx_hash(tx_hash: Option<String>) -> Option<String> {
@@ -113,4 +223,35 @@ mod tests {
         assert_eq!(d1.event_key, d2.event_key);
         assert!(d1.event_key.starts_with("hash:"));
     }
+
+    #[test]
+    fn transfer_event_key_is_scoped_to_user() {
+        let transfer = |user: &str, amount: &str| {
+            Deposit::new(
+                Address::new(user.to_string()),
+                TimeMs::new(1000),
+                Decimal::from_str(amount).unwrap(),
+                Some("0xBEEF".to_string()),
+            )
+            .with_kind(LedgerKind::InternalTransfer)
+        };
+
+        let sent = transfer("0xAbc", "-10");
+        let received = transfer("0xdef", "10");
+        assert_eq!(sent.event_key, "0xbeef:0xabc");
+        assert_eq!(received.event_key, "0xbeef:0xdef");
+        assert_eq!(sent.kind, LedgerKind::InternalTransfer);
+
+        let unhashed = Deposit::new(
+            Address::new("0xabc".to_string()),
+            TimeMs::new(1000),
+            Decimal::from_str("-10").unwrap(),
+            None,
+        );
+        assert_eq!(unhashed.kind, LedgerKind::Withdraw);
+        assert_ne!(
+            unhashed.clone().with_kind(LedgerKind::SpotTransfer).event_key,
+            unhashed.event_key
+        );
+    }
 }
@@ -3,6 +3,58 @@
 use crate::domain::{Address, Attribution, Coin, Decimal, Side, TimeMs};
 use serde::{Deserialize, Serialize};
 
+/// Version of the [`Fill::compute_fill_key`] scheme.
+///
+/// Bump whenever the key derivation changes. Keys from a different version never dedupe
+/// against ours, so data carrying them must be re-ingested rather than imported.
+pub const FILL_KEY_VERSION: i64 = 1;
+
+/// How a fill came about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillType {
+    /// A regular order execution.
+    #[default]
+    Trade,
+    /// The exchange closed the position because margin ran out.
+    Liquidation,
+    /// The position was auto-deleveraged against a liquidation the insurance could not absorb.
+    Adl,
+}
+
+impl FillType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FillType::Trade => "trade",
+            FillType::Liquidation => "liquidation",
+            FillType::Adl => "adl",
+        }
+    }
+
+    pub fn is_trade(&self) -> bool {
+        *self == FillType::Trade
+    }
+}
+
+impl std::fmt::Display for FillType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for FillType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trade" => Ok(FillType::Trade),
+            "liquidation" => Ok(FillType::Liquidation),
+            "adl" => Ok(FillType::Adl),
+            _ => Err(()),
+        }
+    }
+}
+
 /// A single trade fill/execution.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Fill {
@@ -34,6 +86,21 @@ pub struct Fill {
     pub tid: Option<i64>,
     /// Order ID.
     pub oid: Option<i64>,
+    /// Whether the fill took liquidity (taker); `None` when the source did not report it.
+    ///
+    /// Not part of the fill key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crossed: Option<bool>,
+    /// How the fill came about: a regular trade, a liquidation or auto-deleveraging.
+    ///
+    /// Not part of the fill key.
+    #[serde(default, skip_serializing_if = "FillType::is_trade")]
+    pub fill_type: FillType,
+    /// Id of the TWAP order the fill executed, when it was one of its slices.
+    ///
+    /// Not part of the fill key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twap_id: Option<i64>,
     /// Attribution information (optional).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attribution: Option<Attribution>,
@@ -81,10 +148,31 @@ impl Fill {
             builder_fee,
             tid,
             oid,
+            crossed: None,
+            fill_type: FillType::Trade,
+            twap_id: None,
             attribution: None,
         }
     }
 
+    /// Set whether the fill took liquidity.
+    pub fn with_crossed(mut self, crossed: bool) -> Self {
+        self.crossed = Some(crossed);
+        self
+    }
+
+    /// Set how the fill came about.
+    pub fn with_fill_type(mut self, fill_type: FillType) -> Self {
+        self.fill_type = fill_type;
+        self
+    }
+
+    /// Set the TWAP order the fill belongs to.
+    pub fn with_twap_id(mut self, twap_id: i64) -> Self {
+        self.twap_id = Some(twap_id);
+        self
+    }
+
     /// Set the attribution for this fill.
     pub fn with_attribution(mut self, attribution: Attribution) -> Self {
         self.attribution = Some(attribution);
@@ -0,0 +1,81 @@
+//! Historical mark prices, one per coin and time bucket, for valuing past positions.
+
+use crate::domain::{Coin, Decimal, TimeMs};
+
+/// Width of the epoch-aligned buckets marks are stored for (one hour).
+pub const MARK_PRICE_BUCKET_MS: i64 = 3_600_000;
+
+/// Price of `coin` at `time_ms`, the start of its bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkPrice {
+    pub coin: Coin,
+    pub time_ms: TimeMs,
+    pub px: Decimal,
+}
+
+/// Marks of one coin in time order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkSeries(Vec<(TimeMs, Decimal)>);
+
+impl MarkSeries {
+    /// `marks` must be in time order.
+    pub fn new(marks: Vec<(TimeMs, Decimal)>) -> Self {
+        Self(marks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The mark of the bucket containing `time_ms`, or `None` when that bucket has none.
+    pub fn at(&self, time_ms: TimeMs) -> Option<Decimal> {
+        let bucket = bucket_start(time_ms);
+        let i = self.0.partition_point(|(t, _)| *t <= time_ms);
+        let (t, px) = self.0.get(i.checked_sub(1)?)?;
+        (*t >= bucket).then_some(*px)
+    }
+
+    /// Bucket starts with a mark in `from_ms..=to_ms`, in order.
+    pub fn times_between(
+        &self,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> impl Iterator<Item = TimeMs> + '_ {
+        self.0
+            .iter()
+            .map(|(t, _)| *t)
+            .filter(move |t| *t >= from_ms && *t <= to_ms)
+    }
+}
+
+/// Start of the mark bucket containing `time_ms`.
+pub fn bucket_start(time_ms: TimeMs) -> TimeMs {
+    let t = time_ms.as_ms();
+    TimeMs::new(t - t.rem_euclid(MARK_PRICE_BUCKET_MS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(s: &str) -> Decimal {
+        Decimal::from_str_canonical(s).unwrap()
+    }
+
+    #[test]
+    fn test_mark_at_uses_own_bucket_only() {
+        let hour = MARK_PRICE_BUCKET_MS;
+        let series = MarkSeries::new(vec![
+            (TimeMs::new(0), px("100")),
+            (TimeMs::new(hour), px("110")),
+            (TimeMs::new(3 * hour), px("90")),
+        ]);
+        assert_eq!(series.at(TimeMs::new(0)), Some(px("100")));
+        assert_eq!(series.at(TimeMs::new(hour - 1)), Some(px("100")));
+        assert_eq!(series.at(TimeMs::new(hour + 5)), Some(px("110")));
+        // No mark stored for the third bucket.
+        assert_eq!(series.at(TimeMs::new(2 * hour + 5)), None);
+        assert_eq!(series.at(TimeMs::new(3 * hour)), Some(px("90")));
+        assert_eq!(series.at(TimeMs::new(-1)), None);
+    }
+}
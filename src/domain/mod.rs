@@ -8,16 +8,23 @@
 
 pub mod attribution;
 pub mod builder_logs;
+pub mod coin_meta;
 pub mod decimal;
 pub mod deposit;
 pub mod fill;
+pub mod mark_price;
 pub mod ordering;
 pub mod primitives;
 
-pub use attribution::{Attribution, AttributionConfidence, AttributionMode, Confidence};
+pub use attribution::{
+    Attribution, AttributionConfidence, AttributionMode, AttributionReason, Confidence,
+    ToleranceTier,
+};
 pub use builder_logs::BuilderLogFill;
-pub use decimal::Decimal;
-pub use deposit::Deposit;
-pub use fill::Fill;
+pub use coin_meta::{CoinMeta, CoinRegistry};
+pub use decimal::{Decimal, DecimalError, DECIMAL_FORMAT_VERSION, SCALED_DECIMAL_PLACES};
+pub use deposit::{Deposit, LedgerKind};
+pub use fill::{Fill, FillType, FILL_KEY_VERSION};
+pub use mark_price::{MarkPrice, MarkSeries, MARK_PRICE_BUCKET_MS};
 pub use ordering::FillOrderingKey;
 pub use primitives::{Address, AddressParseError, Coin, Side, TimeMs};
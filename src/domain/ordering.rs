@@ -29,6 +29,28 @@ impl FillOrderingKey {
         }
     }
 
+    /// Encode the key as a string whose byte order matches the key's [`Ord`].
+    ///
+    /// Stored in `raw_fills.ordering_key` so SQL can sort and resume from a watermark in
+    /// the same order the compiler uses. Integers are offset into unsigned fixed-width hex,
+    /// and a missing id sorts before any present one.
+    pub fn to_sortable_string(&self) -> String {
+        fn int(value: i64) -> String {
+            format!("{:016x}", (value as u64) ^ (1 << 63))
+        }
+        fn optional(value: Option<i64>) -> String {
+            value.map_or_else(|| "0".to_string(), |v| format!("1{}", int(v)))
+        }
+
+        format!(
+            "{}:{}:{}:{}",
+            int(self.time_ms),
+            optional(self.tid),
+            optional(self.oid),
+            self.fill_key
+        )
+    }
+
     /// Compare two fills for deterministic ordering.
     ///
     /// Returns true if fill_a should come before fill_b.
@@ -114,6 +136,27 @@ mod tests {
         assert_eq!(fills[2].tid, Some(2));
     }
 
+    #[test]
+    fn test_sortable_string_preserves_order() {
+        let mut fills = vec![
+            make_fill(-5, Some(1), None),
+            make_fill(1000, None, None),
+            make_fill(1000, None, Some(-1)),
+            make_fill(1000, Some(2), Some(7)),
+            make_fill(1000, Some(10), None),
+            make_fill(2000, Some(1), None),
+        ];
+        sort_fills_deterministic(&mut fills);
+
+        let encoded: Vec<String> = fills
+            .iter()
+            .map(|f| FillOrderingKey::from_fill(f).to_sortable_string())
+            .collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+    }
+
     #[test]
     fn test_fill_ordering_key_determinism() {
         let fill = make_fill(1000, Some(123), Some(456));
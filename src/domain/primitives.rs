@@ -31,20 +31,112 @@ impl TimeMs {
     }
 }
 
-/// Wallet address (hex string).
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct Address(pub String);
+/// Wallet address (hex string), stored lowercase.
+///
+/// Hex addresses are case-insensitive, but the database keys users and fills by the address
+/// string, so every constructor lowercases: a checksummed and a lowercase spelling of the
+/// same wallet are one identity. [`Address::to_checksummed`] gives the EIP-55 spelling for
+/// output.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Address(String);
 
 impl Address {
-    /// Create an Address from a string.
+    /// Create an Address from a string, lowercasing it.
     pub fn new(addr: String) -> Self {
-        Address(addr)
+        Address(addr.to_ascii_lowercase())
     }
 
     /// Get the address as a string reference.
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Parse an address, ignoring surrounding whitespace.
+    ///
+    /// Used for configured builder addresses. The EIP-55 checksum of mixed-case input is
+    /// not verified.
+    pub fn parse_normalized(s: &str) -> Result<Self, AddressParseError> {
+        s.trim().parse()
+    }
+
+    /// Parse an address and verify its EIP-55 checksum.
+    ///
+    /// Only mixed-case input carries a checksum; all-lowercase and all-uppercase hex are
+    /// accepted as is. A mixed-case address with a wrong checksum is almost always a typo.
+    pub fn parse_checksummed(s: &str) -> Result<Self, AddressParseError> {
+        let addr: Address = s.parse()?;
+        let hex = &s[2..];
+        let mixed_case = hex != &addr.0[2..] && hex != hex.to_ascii_uppercase();
+        if mixed_case && eip55_checksum(&addr.0[2..]) != hex {
+            return Err(AddressParseError::ChecksumMismatch);
+        }
+        Ok(addr)
+    }
+
+    /// The EIP-55 mixed-case spelling of a well-formed address; anything else (such as the
+    /// short placeholders used in tests) is returned as stored.
+    pub fn to_checksummed(&self) -> String {
+        match self.0.strip_prefix("0x") {
+            Some(hex) if hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                format!("0x{}", eip55_checksum(hex))
+            }
+            _ => self.0.clone(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Address::new)
+    }
+}
+
+/// The EIP-55 spelling of 40 lowercase hex digits: a letter is uppercased when the matching
+/// nibble of the Keccak-256 hash of the lowercase digits is 8 or more.
+fn eip55_checksum(lower_hex: &str) -> String {
+    let hash = keccak256(lower_hex.as_bytes());
+    lower_hex
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0x0f };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Keccak-256 as used by Ethereum (the original Keccak padding, not SHA3-256's).
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+
+    fn absorb(state: &mut [u64; 25], block: &[u8]) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        keccak::f1600(state);
+    }
+
+    let mut state = [0u64; 25];
+    let mut blocks = data.chunks_exact(RATE);
+    for block in &mut blocks {
+        absorb(&mut state, block);
+    }
+    let remainder = blocks.remainder();
+    let mut last = [0u8; RATE];
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[remainder.len()] ^= 0x01;
+    last[RATE - 1] ^= 0x80;
+    absorb(&mut state, &last);
+
+    let mut out = [0u8; 32];
+    for (bytes, lane) in out.chunks_exact_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    out
 }
 
 impl std::fmt::Display for Address {
@@ -58,7 +150,7 @@ impl std::str::FromStr for Address {
 
     /// Parse an address from a string.
     ///
-    /// Requires exactly 42 characters: "0x" prefix + 40 hex digits.
+    /// Requires exactly 42 characters: "0x" prefix + 40 hex digits. The result is lowercase.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() != 42 {
             return Err(AddressParseError::InvalidLength(s.len()));
@@ -70,7 +162,7 @@ impl std::str::FromStr for Address {
         if !hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
             return Err(AddressParseError::InvalidHex);
         }
-        Ok(Address(s.to_string()))
+        Ok(Address(s.to_ascii_lowercase()))
     }
 }
 
@@ -83,6 +175,8 @@ pub enum AddressParseError {
     MissingPrefix,
     /// Address must contain only hex digits after the "0x" prefix.
     InvalidHex,
+    /// Mixed-case address whose letters do not match its EIP-55 checksum.
+    ChecksumMismatch,
 }
 
 impl std::fmt::Display for AddressParseError {
@@ -97,6 +191,9 @@ impl std::fmt::Display for AddressParseError {
             AddressParseError::InvalidHex => {
                 write!(f, "address must contain only hex digits")
             }
+            AddressParseError::ChecksumMismatch => {
+                write!(f, "address does not match its EIP-55 checksum")
+            }
         }
     }
 }
@@ -104,10 +201,16 @@ impl std::fmt::Display for AddressParseError {
 impl std::error::Error for AddressParseError {}
 
 /// Coin/asset symbol (e.g., "BTC", "ETH").
+///
+/// Assets of builder-deployed perp dexes are qualified with their dex, as in `xyz:TSLA`,
+/// so they stay distinct from the default dex's asset of the same name.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Coin(pub String);
 
 impl Coin {
+    /// Separator between a perp dex and its asset, as in `xyz:TSLA`.
+    pub const DEX_SEPARATOR: char = ':';
+
     /// Create a Coin from a string.
     pub fn new(coin: String) -> Self {
         Coin(coin)
@@ -117,8 +220,27 @@ impl Coin {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The builder-deployed perp dex the coin trades on; `None` for the default dex and spot.
+    pub fn dex(&self) -> Option<&str> {
+        self.0.split_once(Self::DEX_SEPARATOR).map(|(dex, _)| dex)
+    }
+
+    /// The asset name without its dex, e.g. `TSLA` for `xyz:TSLA`.
+    pub fn symbol(&self) -> &str {
+        self.0
+            .split_once(Self::DEX_SEPARATOR)
+            .map_or(self.as_str(), |(_, symbol)| symbol)
+    }
+
+    /// Whether the coin trades on `dex`, where the empty string is the default dex.
+    pub fn is_on_dex(&self, dex: &str) -> bool {
+        self.dex().unwrap_or_default() == dex
+    }
 }
 
+/// Parses a symbol given by a caller: surrounding whitespace is dropped and a dex
+/// qualifier is lowercased, as Hyperliquid names dexes, so ` XYZ:TSLA` is `xyz:TSLA`.
 impl FromStr for Coin {
     type Err = &'static str;
 
@@ -127,7 +249,21 @@ impl FromStr for Coin {
         if s.is_empty() {
             return Err("coin cannot be empty");
         }
-        Ok(Coin::new(s.to_string()))
+        match s.split_once(Self::DEX_SEPARATOR) {
+            None => Ok(Coin::new(s.to_string())),
+            Some((dex, symbol)) => {
+                let (dex, symbol) = (dex.trim(), symbol.trim());
+                if dex.is_empty() || symbol.is_empty() || symbol.contains(Self::DEX_SEPARATOR) {
+                    return Err("coin must be a symbol or dex:symbol");
+                }
+                Ok(Coin::new(format!(
+                    "{}{}{}",
+                    dex.to_lowercase(),
+                    Self::DEX_SEPARATOR,
+                    symbol
+                )))
+            }
+        }
     }
 }
 
@@ -193,12 +329,71 @@ mod tests {
         assert_eq!(addr.to_string(), "0x123abc");
     }
 
+    #[test]
+    fn test_address_parse_checksummed() {
+        // Test vectors from EIP-55.
+        for addr in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let parsed = Address::parse_checksummed(addr).unwrap();
+            assert_eq!(parsed.as_str(), addr.to_ascii_lowercase());
+        }
+
+        let lower = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+        assert_eq!(Address::parse_checksummed(lower).unwrap().as_str(), lower);
+        let upper = "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED";
+        assert_eq!(Address::parse_checksummed(upper).unwrap().as_str(), lower);
+        assert_eq!(
+            Address::parse_checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err(AddressParseError::ChecksumMismatch)
+        );
+        assert_eq!(
+            Address::parse_checksummed("0x123"),
+            Err(AddressParseError::InvalidLength(5))
+        );
+    }
+
+    #[test]
+    fn test_address_is_stored_lowercase() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lower = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+        assert_eq!(Address::new(checksummed.to_string()).as_str(), lower);
+        assert_eq!(checksummed.parse::<Address>().unwrap().as_str(), lower);
+        let json: Address = serde_json::from_str(&format!("\"{}\"", checksummed)).unwrap();
+        assert_eq!(serde_json::to_string(&json).unwrap(), format!("\"{}\"", lower));
+        assert_eq!(json.to_checksummed(), checksummed);
+        assert_eq!(Address::new("0xAbC".to_string()).to_checksummed(), "0xabc");
+    }
+
     #[test]
     fn test_coin_display() {
         let coin = Coin::new("BTC".to_string());
         assert_eq!(coin.to_string(), "BTC");
     }
 
+    #[test]
+    fn test_coin_dex_qualified() {
+        let coin: Coin = " XYZ:TSLA ".parse().unwrap();
+        assert_eq!(coin.as_str(), "xyz:TSLA");
+        assert_eq!(coin.dex(), Some("xyz"));
+        assert_eq!(coin.symbol(), "TSLA");
+        assert!(coin.is_on_dex("xyz"));
+        assert!(!coin.is_on_dex(""));
+
+        let btc: Coin = "BTC".parse().unwrap();
+        assert_eq!(btc.dex(), None);
+        assert_eq!(btc.symbol(), "BTC");
+        assert!(btc.is_on_dex(""));
+        assert_ne!(btc, Coin::new("xyz:BTC".to_string()));
+
+        for invalid in ["", ":BTC", "xyz:", "a:b:c"] {
+            assert!(invalid.parse::<Coin>().is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn test_timems_ordering() {
         let t1 = TimeMs::new(1000);
@@ -1,13 +1,24 @@
 //! Matching raw fills against Hyperliquid builder logs for attribution.
 
-use crate::domain::{AttributionConfidence, BuilderLogFill, Decimal, Fill, Side};
+use super::stats::median;
+use crate::domain::{
+    Address, AttributionConfidence, AttributionReason, BuilderLogFill, Coin, Decimal, Fill, Side,
+    TimeMs, ToleranceTier,
+};
+use rust_decimal::Decimal as RustDecimal;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Tolerances for fuzzy matching a fill against builder log rows.
+///
+/// A candidate matches when it is within `time_ms` and `sz_abs`, and its price is within
+/// `px_abs` ([`ToleranceTier::Absolute`]) or, failing that, within `px_bps` basis points of
+/// the logged price ([`ToleranceTier::Bps`]). A zero `px_bps` disables the relative tier.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MatchTolerances {
     pub time_ms: i64,
     pub px_abs: Decimal,
+    pub px_bps: Decimal,
     pub sz_abs: Decimal,
 }
 
@@ -16,21 +27,56 @@ impl Default for MatchTolerances {
         Self {
             time_ms: 1_000,
             px_abs: Decimal::from_str("0.000001").expect("valid decimal"),
+            px_bps: Decimal::zero(),
             sz_abs: Decimal::from_str("0.000001").expect("valid decimal"),
         }
     }
 }
 
+impl MatchTolerances {
+    /// Tier that admits a price difference of `dpx` against `log_px`, if any.
+    fn price_tier(&self, dpx: Decimal, log_px: Decimal) -> Option<ToleranceTier> {
+        if dpx <= self.px_abs {
+            return Some(ToleranceTier::Absolute);
+        }
+        if self.px_bps.is_zero() {
+            return None;
+        }
+        let allowed =
+            Decimal::new(log_px.abs().inner() * self.px_bps.inner() / RustDecimal::from(10_000));
+        (dpx <= allowed).then_some(ToleranceTier::Bps)
+    }
+}
+
+/// Candidate ranking for fuzzy matches: (time delta, price delta, size delta, tid). Lower wins.
+type MatchScore = (i64, Decimal, Decimal, i64);
+
+/// Result of matching a fill against builder logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogsMatch {
+    pub confidence: AttributionConfidence,
+    /// Tier that admitted a fuzzy match; `None` for tid matches.
+    pub tolerance_tier: Option<ToleranceTier>,
+    /// [`BuilderLogFill::log_key`] of the matched row.
+    pub log_key: String,
+    pub reason: AttributionReason,
+    /// See [`AttributionReason::score`].
+    pub score: u8,
+}
+
 #[derive(Debug)]
 pub struct BuilderLogsIndex<'a> {
     by_tid: HashMap<i64, &'a BuilderLogFill>,
     fuzzy: HashMap<(String, String, Side), Vec<&'a BuilderLogFill>>,
+    /// Rows per upper-cased coin, in time order.
+    by_coin: HashMap<String, Vec<&'a BuilderLogFill>>,
 }
 
 impl<'a> BuilderLogsIndex<'a> {
     pub fn new(logs: &'a [BuilderLogFill]) -> Self {
         let mut by_tid = HashMap::new();
         let mut fuzzy: HashMap<(String, String, Side), Vec<&BuilderLogFill>> = HashMap::new();
+        let mut by_coin: HashMap<String, Vec<&BuilderLogFill>> = HashMap::new();
 
         for row in logs {
             if let Some(tid) = row.tid {
@@ -38,14 +84,52 @@ impl<'a> BuilderLogsIndex<'a> {
             }
 
             let key = (
-                row.user.as_str().to_ascii_lowercase(),
+                row.user.as_str().to_string(),
                 row.coin.as_str().to_ascii_uppercase(),
                 row.side,
             );
             fuzzy.entry(key).or_default().push(row);
+            by_coin
+                .entry(row.coin.as_str().to_ascii_uppercase())
+                .or_default()
+                .push(row);
+        }
+        for rows in by_coin.values_mut() {
+            rows.sort_by_key(|row| row.time_ms);
+        }
+
+        Self {
+            by_tid,
+            fuzzy,
+            by_coin,
         }
+    }
 
-        Self { by_tid, fuzzy }
+    /// Median price of the coin's log rows within `window_ms` of `time_ms`, leaving out
+    /// rows of `exclude_user` so a user's own fills do not become their benchmark.
+    ///
+    /// Returns the median and the number of rows it was taken over, or `None` when no row
+    /// is in the window.
+    pub fn median_px(
+        &self,
+        coin: &Coin,
+        time_ms: TimeMs,
+        window_ms: i64,
+        exclude_user: Option<&Address>,
+    ) -> Option<(Decimal, usize)> {
+        let rows = self.by_coin.get(&coin.as_str().to_ascii_uppercase())?;
+        let from = TimeMs::new(time_ms.as_ms().saturating_sub(window_ms));
+        let to = TimeMs::new(time_ms.as_ms().saturating_add(window_ms));
+        let start = rows.partition_point(|row| row.time_ms < from);
+        let prices: Vec<Decimal> = rows[start..]
+            .iter()
+            .take_while(|row| row.time_ms <= to)
+            .filter(|row| {
+                exclude_user.is_none_or(|user| !row.user.as_str().eq_ignore_ascii_case(user.as_str()))
+            })
+            .map(|row| row.px)
+            .collect();
+        median(&prices).map(|px| (px, prices.len()))
     }
 
     pub fn match_fill(
@@ -53,28 +137,56 @@ impl<'a> BuilderLogsIndex<'a> {
         fill: &Fill,
         tolerances: &MatchTolerances,
     ) -> Option<AttributionConfidence> {
-        if let Some(tid) = fill.tid {
-            if self.by_tid.contains_key(&tid) {
-                return Some(AttributionConfidence::Exact);
-            }
+        self.match_fill_detailed(fill, tolerances)
+            .map(|m| m.confidence)
+    }
+
+    /// Like [`match_fill`](Self::match_fill), but also reports the tolerance tier used and
+    /// which log row matched.
+    pub fn match_fill_detailed(
+        &self,
+        fill: &Fill,
+        tolerances: &MatchTolerances,
+    ) -> Option<LogsMatch> {
+        if let Some(row) = fill.tid.and_then(|tid| self.by_tid.get(&tid)) {
+            let reason = AttributionReason::Tid;
+            return Some(LogsMatch {
+                confidence: AttributionConfidence::Exact,
+                tolerance_tier: None,
+                log_key: row.log_key(),
+                score: reason.score(true, None, tolerances.time_ms),
+                reason,
+            });
         }
 
-        self.fuzzy_match(fill, tolerances)
-            .then_some(AttributionConfidence::Fuzzy)
+        self.fuzzy_match(fill, tolerances).map(|(tier, row)| {
+            let reason = AttributionReason::Fields {
+                time_delta_ms: Some(fill.time_ms.as_ms() - row.time_ms.as_ms()),
+            };
+            LogsMatch {
+                confidence: AttributionConfidence::Fuzzy,
+                tolerance_tier: Some(tier),
+                log_key: row.log_key(),
+                score: reason.score(true, Some(tier), tolerances.time_ms),
+                reason,
+            }
+        })
     }
 
-    fn fuzzy_match(&self, fill: &Fill, tolerances: &MatchTolerances) -> bool {
+    fn fuzzy_match(
+        &self,
+        fill: &Fill,
+        tolerances: &MatchTolerances,
+    ) -> Option<(ToleranceTier, &'a BuilderLogFill)> {
         let key = (
-            fill.user.as_str().to_ascii_lowercase(),
+            fill.user.as_str().to_string(),
             fill.coin.as_str().to_ascii_uppercase(),
             fill.side,
         );
 
-        let Some(candidates) = self.fuzzy.get(&key) else {
-            return false;
-        };
+        let candidates = self.fuzzy.get(&key)?;
 
-        let mut best: Option<(i64, Decimal, Decimal, i64)> = None;
+        let mut best: Option<(MatchScore, ToleranceTier, &BuilderLogFill)> = None;
 
         for row in candidates {
             let dt = (fill.time_ms.as_ms() - row.time_ms.as_ms()).abs();
@@ -83,9 +195,9 @@ impl<'a> BuilderLogsIndex<'a> {
             }
 
             let dpx = (fill.px - row.px).abs();
-            if dpx > tolerances.px_abs {
+            let Some(tier) = tolerances.price_tier(dpx, row.px) else {
                 continue;
-            }
+            };
 
             let dsz = (fill.sz - row.sz).abs();
             if dsz > tolerances.sz_abs {
@@ -94,12 +206,12 @@ impl<'a> BuilderLogsIndex<'a> {
 
             let tid = row.tid.unwrap_or(-1);
             let score = (dt, dpx, dsz, tid);
-            if best.as_ref().map(|b| score < *b).unwrap_or(true) {
-                best = Some(score);
+            if best.as_ref().map(|(b, _, _)| score < *b).unwrap_or(true) {
+                best = Some((score, tier, *row));
             }
         }
 
-        best.is_some()
+        best.map(|(_, tier, row)| (tier, row))
     }
 }
 
@@ -108,13 +220,7 @@ mod tests {
     use super::*;
     use crate::domain::{Address, Coin, TimeMs};
 
-    fn fill(
-        time_ms: i64,
-        tid: Option<i64>,
-        px: &str,
-        sz: &str,
-        side: Side,
-    ) -> Fill {
+    fn fill(time_ms: i64, tid: Option<i64>, px: &str, sz: &str, side: Side) -> Fill {
         Fill::new(
             TimeMs::new(time_ms),
             Address::new("0xabc".to_string()),
@@ -164,6 +270,7 @@ mod tests {
         let tolerances = MatchTolerances {
             time_ms: 1_000,
             px_abs: Decimal::from_str("0.000001").unwrap(),
+            px_bps: Decimal::zero(),
             sz_abs: Decimal::from_str("0.000001").unwrap(),
         };
         assert_eq!(
@@ -172,6 +279,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fuzzy_match_reports_tolerance_tier() {
+        let logs = vec![log(1000, None, "100", "1", Side::Buy)];
+        let index = BuilderLogsIndex::new(&logs);
+        let tolerances = MatchTolerances {
+            px_bps: Decimal::from_str("5").unwrap(),
+            ..MatchTolerances::default()
+        };
+
+        let near = fill(1200, None, "100", "1", Side::Buy);
+        assert_eq!(
+            index.match_fill_detailed(&near, &tolerances),
+            Some(LogsMatch {
+                confidence: AttributionConfidence::Fuzzy,
+                tolerance_tier: Some(ToleranceTier::Absolute),
+                log_key: "1000:0xabc:BTC:buy:100:1".to_string(),
+                reason: AttributionReason::Fields {
+                    time_delta_ms: Some(200),
+                },
+                score: 86,
+            })
+        );
+
+        // 4 bps away: outside the absolute tolerance, inside the 5 bps tier.
+        let drifted = fill(1200, None, "100.04", "1", Side::Buy);
+        assert_eq!(
+            index
+                .match_fill_detailed(&drifted, &tolerances)
+                .and_then(|m| m.tolerance_tier),
+            Some(ToleranceTier::Bps)
+        );
+
+        // 6 bps away: rejected, and the bps tier is off by default.
+        let far = fill(1200, None, "100.06", "1", Side::Buy);
+        assert_eq!(index.match_fill_detailed(&far, &tolerances), None);
+        assert_eq!(
+            index.match_fill_detailed(&drifted, &MatchTolerances::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn tid_match_has_no_tolerance_tier() {
+        let logs = vec![log(1000, Some(7), "100", "1", Side::Buy)];
+        let index = BuilderLogsIndex::new(&logs);
+        let m = index
+            .match_fill_detailed(
+                &fill(1000, Some(7), "100", "1", Side::Buy),
+                &MatchTolerances::default(),
+            )
+            .unwrap();
+        assert_eq!(m.confidence, AttributionConfidence::Exact);
+        assert_eq!(m.tolerance_tier, None);
+        assert_eq!(m.log_key, "tid:7");
+        assert_eq!(m.reason, AttributionReason::Tid);
+        assert_eq!(m.score, 100);
+    }
+
     #[test]
     fn no_match_returns_none() {
         let logs = vec![log(1000, Some(1), "100", "1", Side::Buy)];
@@ -180,4 +345,3 @@ mod tests {
         assert_eq!(index.match_fill(&fill, &MatchTolerances::default()), None);
     }
 }
-
@@ -0,0 +1,272 @@
+//! Per-coin compile hooks for instruments the generic tracker cannot handle as-is.
+//!
+//! Pre-launch markets and index perps occasionally rebase their contract size (splits,
+//! redenominations). Fills on either side of such an event are in different units, so
+//! feeding them straight into the [`PositionTracker`](super::PositionTracker) would
+//! produce phantom flips and wrong entry prices.
+//!
+//! Hooks rewrite a coin's fills into a single unit system before tracking. They only touch
+//! price and size, never the fill key, and run in a fixed order so recompiles reproduce the
+//! same derived rows.
+
+use crate::domain::{Coin, Decimal, DecimalError, Fill, TimeMs};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A deterministic adjustment applied to a coin's fills before position tracking.
+pub trait CompileHook: Send + Sync {
+    /// Short identifier used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Adjust a fill in place. Must be a pure function of the fill, and leave it unchanged
+    /// when it fails.
+    fn apply(&self, fill: &mut Fill) -> Result<(), DecimalError>;
+}
+
+/// Contract size rebase (e.g. a 1:1000 split) taking effect at `effective_time_ms`.
+///
+/// Fills before the event are restated in post-event units: size is multiplied by `factor`
+/// and price by its inverse, so notional and closed PnL are unchanged. Only factors whose
+/// inverse is an exact decimal (such as 1000 or 0.25) are accepted, so restating never
+/// rounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeRebase {
+    pub effective_time_ms: TimeMs,
+    pub factor: Decimal,
+    /// `1 / factor`, exactly.
+    inverse: Decimal,
+}
+
+impl SizeRebase {
+    /// Returns `None` unless `factor` is strictly positive with an exact decimal inverse.
+    pub fn new(effective_time_ms: TimeMs, factor: Decimal) -> Option<Self> {
+        let inverse = Decimal::one().checked_div(factor).ok()?;
+        (factor.is_positive() && inverse.checked_mul(factor) == Ok(Decimal::one())).then_some(Self {
+            effective_time_ms,
+            factor,
+            inverse,
+        })
+    }
+}
+
+impl CompileHook for SizeRebase {
+    fn name(&self) -> &'static str {
+        "size_rebase"
+    }
+
+    fn apply(&self, fill: &mut Fill) -> Result<(), DecimalError> {
+        if fill.time_ms < self.effective_time_ms {
+            let sz = fill.sz.checked_mul(self.factor)?;
+            fill.px = fill.px.checked_mul(self.inverse)?;
+            fill.sz = sz;
+        }
+        Ok(())
+    }
+}
+
+/// A configured coin adjustment, as stored in the `coin_adjustments` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoinAdjustment {
+    SizeRebase(SizeRebase),
+}
+
+impl CoinAdjustment {
+    /// Kind stored in `coin_adjustments.kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CoinAdjustment::SizeRebase(_) => "size_rebase",
+        }
+    }
+
+    pub fn effective_time_ms(&self) -> TimeMs {
+        match self {
+            CoinAdjustment::SizeRebase(r) => r.effective_time_ms,
+        }
+    }
+
+    /// Build from stored columns; `None` for unknown kinds or invalid parameters.
+    pub fn from_parts(kind: &str, effective_time_ms: TimeMs, factor: Decimal) -> Option<Self> {
+        match kind {
+            "size_rebase" => SizeRebase::new(effective_time_ms, factor).map(Self::SizeRebase),
+            _ => None,
+        }
+    }
+
+    fn into_hook(self) -> Arc<dyn CompileHook> {
+        match self {
+            CoinAdjustment::SizeRebase(r) => Arc::new(r),
+        }
+    }
+}
+
+/// Hooks registered per coin, applied in registration order.
+#[derive(Default, Clone)]
+pub struct CompileHookRegistry {
+    hooks: HashMap<Coin, Vec<Arc<dyn CompileHook>>>,
+}
+
+impl CompileHookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a registry from configured adjustments.
+    ///
+    /// Adjustments are registered in `(effective_time_ms, input order)` order, so the result
+    /// does not depend on how the caller happened to list them.
+    pub fn from_adjustments(adjustments: Vec<(Coin, CoinAdjustment)>) -> Self {
+        let mut adjustments = adjustments;
+        adjustments.sort_by_key(|(_, a)| a.effective_time_ms());
+
+        let mut registry = Self::new();
+        for (coin, adjustment) in adjustments {
+            registry.register(coin, adjustment.into_hook());
+        }
+        registry
+    }
+
+    pub fn register(&mut self, coin: Coin, hook: Arc<dyn CompileHook>) {
+        self.hooks.entry(coin).or_default().push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Apply every hook registered for `coin` to `fill`.
+    ///
+    /// On overflow the fill is left as it was, so it is never tracked half restated.
+    pub fn apply(&self, coin: &Coin, fill: &mut Fill) -> Result<(), DecimalError> {
+        let Some(hooks) = self.hooks.get(coin) else {
+            return Ok(());
+        };
+        let mut adjusted = fill.clone();
+        for hook in hooks {
+            hook.apply(&mut adjusted)?;
+        }
+        *fill = adjusted;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Side};
+    use crate::engine::PositionTracker;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, side: Side, px: &str, sz: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("kPRE".to_string()),
+            side,
+            d(px),
+            d(sz),
+            d("0"),
+            d("0"),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_size_rebase_requires_positive_factor() {
+        assert!(SizeRebase::new(TimeMs::new(0), d("0")).is_none());
+        assert!(SizeRebase::new(TimeMs::new(0), d("-2")).is_none());
+        // 1/3 has no exact decimal form, so prices could not be restated without rounding.
+        assert!(SizeRebase::new(TimeMs::new(0), d("3")).is_none());
+        assert!(SizeRebase::new(TimeMs::new(0), d("0.25")).is_some());
+        assert!(CoinAdjustment::from_parts("split_v2", TimeMs::new(0), d("2")).is_none());
+    }
+
+    #[test]
+    fn test_size_rebase_restates_pre_event_fills() {
+        let coin = Coin::new("kPRE".to_string());
+        let registry = CompileHookRegistry::from_adjustments(vec![(
+            coin.clone(),
+            CoinAdjustment::from_parts("size_rebase", TimeMs::new(2000), d("1000")).unwrap(),
+        )]);
+
+        let mut fills = vec![
+            fill(1000, Side::Buy, "5", "10", 1),
+            fill(3000, Side::Sell, "0.005", "10000", 2),
+        ];
+        let keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+        for f in &mut fills {
+            registry.apply(&coin, f).unwrap();
+        }
+
+        assert_eq!(fills[0].sz, d("10000"));
+        assert_eq!(fills[0].px, d("0.005"));
+        assert_eq!(fills[1].sz, d("10000"));
+        assert_eq!(
+            fills.iter().map(|f| f.fill_key.clone()).collect::<Vec<_>>(),
+            keys
+        );
+
+        // Without the rebase the sell would flip the position short.
+        let mut tracker = PositionTracker::new();
+        for f in &fills {
+            tracker.process_fill(f);
+        }
+        assert!(tracker.state.is_flat());
+        assert_eq!(tracker.get_lifecycles().len(), 1);
+    }
+
+    #[test]
+    fn test_hooks_only_apply_to_their_coin() {
+        let registry = CompileHookRegistry::from_adjustments(vec![(
+            Coin::new("kPRE".to_string()),
+            CoinAdjustment::from_parts("size_rebase", TimeMs::new(2000), d("10")).unwrap(),
+        )]);
+
+        let mut f = fill(1000, Side::Buy, "5", "10", 1);
+        registry.apply(&Coin::new("BTC".to_string()), &mut f).unwrap();
+        assert_eq!(f.sz, d("10"));
+    }
+
+    #[test]
+    fn test_registration_order_is_by_effective_time() {
+        let coin = Coin::new("kPRE".to_string());
+        let later = CoinAdjustment::from_parts("size_rebase", TimeMs::new(5000), d("10")).unwrap();
+        let earlier = CoinAdjustment::from_parts("size_rebase", TimeMs::new(2000), d("2")).unwrap();
+
+        let a = CompileHookRegistry::from_adjustments(vec![
+            (coin.clone(), later.clone()),
+            (coin.clone(), earlier.clone()),
+        ]);
+        let b = CompileHookRegistry::from_adjustments(vec![
+            (coin.clone(), earlier),
+            (coin.clone(), later),
+        ]);
+
+        let mut fa = fill(1000, Side::Buy, "100", "1", 1);
+        let mut fb = fa.clone();
+        a.apply(&coin, &mut fa).unwrap();
+        b.apply(&coin, &mut fb).unwrap();
+        assert_eq!(fa, fb);
+        assert_eq!(fa.sz, d("20"));
+        assert_eq!(fa.px, d("5"));
+    }
+
+    #[test]
+    fn test_overflowing_rebase_leaves_fill_unchanged() {
+        let coin = Coin::new("kPRE".to_string());
+        let registry = CompileHookRegistry::from_adjustments(vec![(
+            coin.clone(),
+            CoinAdjustment::from_parts("size_rebase", TimeMs::new(2000), d("1000000000")).unwrap(),
+        )]);
+
+        let mut f = fill(1000, Side::Buy, "5", "79228162514264337593543950", 1);
+        let original = f.clone();
+        assert!(registry.apply(&coin, &mut f).is_err());
+        assert_eq!(f, original);
+    }
+}
@@ -1,8 +1,197 @@
 use crate::db::Repository;
-use crate::domain::{Address, Decimal, TimeMs};
+use crate::domain::{Address, Coin, Decimal, MarkSeries, TimeMs};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal as RustDecimal;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// How a return over a window is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReturnMethod {
+    /// Realized PnL over the capital base, see [`EquityResolver::capital_base`].
+    #[default]
+    Simple,
+    /// Time-weighted: the equity curve is cut at every ledger flow and the returns of the
+    /// pieces are compounded, so the size and timing of deposits do not matter.
+    Twr,
+    /// Money-weighted: the rate that discounts the starting equity, the flows and the ending
+    /// equity to zero, compounding once over the whole window.
+    Irr,
+}
+
+impl ReturnMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReturnMethod::Simple => "simple",
+            ReturnMethod::Twr => "twr",
+            ReturnMethod::Irr => "irr",
+        }
+    }
+}
+
+impl FromStr for ReturnMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "simple" => Ok(ReturnMethod::Simple),
+            "twr" => Ok(ReturnMethod::Twr),
+            "irr" => Ok(ReturnMethod::Irr),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Equity at the start of a window and what moved it afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquityCurve {
+    pub start_ms: TimeMs,
+    pub start_equity: Decimal,
+    /// Ledger flows after `start_ms`, in time order.
+    pub flows: Vec<(TimeMs, Decimal)>,
+    /// Realized PnL, in time order.
+    pub pnl: Vec<(TimeMs, Decimal)>,
+}
+
+impl EquityCurve {
+    /// See [`EquityResolver::capital_base`].
+    pub fn capital(&self) -> Decimal {
+        self.start_equity + peak_net_flow(&self.flows)
+    }
+
+    /// Combine two accounts' curves into one. The later start becomes a flow of its
+    /// starting equity into the combined account.
+    pub fn merge(&mut self, mut other: EquityCurve) {
+        if other.start_ms < self.start_ms {
+            std::mem::swap(self, &mut other);
+        }
+        if other.start_ms > self.start_ms {
+            other.flows.insert(0, (other.start_ms, other.start_equity));
+        } else {
+            self.start_equity = self.start_equity + other.start_equity;
+        }
+        self.flows = merge_by_time(std::mem::take(&mut self.flows), other.flows);
+        self.pnl = merge_by_time(std::mem::take(&mut self.pnl), other.pnl);
+    }
+
+    /// Equity after every flow and PnL.
+    pub fn end_equity(&self) -> Decimal {
+        self.flows
+            .iter()
+            .chain(&self.pnl)
+            .fold(self.start_equity, |equity, (_, amount)| equity + *amount)
+    }
+
+    /// Time-weighted return in percent, or `None` when the account never had capital.
+    ///
+    /// Each flow closes a segment; PnL at the same millisecond as a flow lands in the
+    /// segment the flow opens. Segments that start without capital are left out.
+    pub fn time_weighted_return(&self) -> Option<Decimal> {
+        let one = Decimal::new(RustDecimal::ONE);
+        let mut growth = one;
+        let mut measured = false;
+        let mut equity = self.start_equity;
+        let mut segment_start = equity;
+        let mut pnl = self.pnl.iter().peekable();
+
+        for (time_ms, flow) in &self.flows {
+            while let Some((_, amount)) = pnl.next_if(|(t, _)| t < time_ms) {
+                equity = equity + *amount;
+            }
+            if segment_start.is_positive() {
+                growth = growth * (equity / segment_start);
+                measured = true;
+            }
+            equity = equity + *flow;
+            segment_start = equity;
+        }
+        for (_, amount) in pnl {
+            equity = equity + *amount;
+        }
+        if segment_start.is_positive() {
+            growth = growth * (equity / segment_start);
+            measured = true;
+        }
+
+        measured.then(|| (growth - one) * Decimal::hundred())
+    }
+
+    /// Money-weighted return in percent over `start_ms..=end_ms`, or `None` when the window
+    /// is empty or no rate balances the cash flows.
+    pub fn money_weighted_return(&self, end_ms: TimeMs) -> Option<Decimal> {
+        let period_ms = end_ms.as_ms() - self.start_ms.as_ms();
+        if period_ms <= 0 {
+            return None;
+        }
+        // From the investor's side: equity and deposits go in, the ending equity comes out.
+        let mut cash_flows = vec![(self.start_ms, -self.start_equity)];
+        cash_flows.extend(self.flows.iter().map(|(t, amount)| (*t, -*amount)));
+        cash_flows.push((end_ms, self.end_equity()));
+
+        let rate = xirr(&cash_flows, period_ms)?;
+        RustDecimal::from_f64(rate * 100.0).map(|d| Decimal::new(d.round_dp(8)))
+    }
+}
+
+/// Rate per `period_ms` at which `cash_flows` have a net present value of zero, as of the
+/// first flow. Solved by bisection, so the same flows always give the same rate.
+///
+/// With a `period_ms` of a year this is the spreadsheet XIRR.
+pub fn xirr(cash_flows: &[(TimeMs, Decimal)], period_ms: i64) -> Option<f64> {
+    let t0 = cash_flows.first()?.0.as_ms();
+    let flows: Vec<(f64, f64)> = cash_flows
+        .iter()
+        .map(|(t, amount)| {
+            let periods = (t.as_ms() - t0) as f64 / period_ms as f64;
+            Some((periods, amount.inner().to_f64()?))
+        })
+        .collect::<Option<_>>()?;
+    let npv = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|(periods, amount)| amount / (1.0 + rate).powf(*periods))
+            .sum()
+    };
+
+    let mut lo = -1.0 + 1e-9;
+    let mut hi = 1.0;
+    let npv_lo = npv(lo);
+    while npv(hi).signum() == npv_lo.signum() {
+        hi *= 10.0;
+        if hi > 1e12 {
+            return None;
+        }
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.0;
+        if npv(mid).signum() == npv_lo.signum() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let rate = (lo + hi) / 2.0;
+    rate.is_finite().then_some(rate)
+}
+
+fn merge_by_time(
+    a: Vec<(TimeMs, Decimal)>,
+    b: Vec<(TimeMs, Decimal)>,
+) -> Vec<(TimeMs, Decimal)> {
+    let mut merged = a;
+    merged.extend(b);
+    // Stable, so entries in the same millisecond keep their order.
+    merged.sort_by_key(|(t, _)| *t);
+    merged
+}
+
 /// Resolves account equity at a timestamp using best-effort cached snapshots.
+///
+/// Equity at `t` is the net of every ledger flow at or before `t` (deposits positive,
+/// withdrawals and outgoing transfers negative) plus the realized PnL of fills strictly
+/// before `t`. A flow and a fill in the same millisecond are therefore ordered flow first,
+/// whatever order they were ingested in.
 #[derive(Clone)]
 pub struct EquityResolver {
     repo: Arc<Repository>,
@@ -14,13 +203,20 @@ impl EquityResolver {
     }
 
     pub async fn resolve_equity(&self, user: &Address, at_ms: TimeMs) -> Result<Decimal, sqlx::Error> {
-        if let Some((_t, equity)) = self.repo.get_equity_snapshot_at_or_before(user, at_ms).await? {
-            return Ok(equity);
-        }
-
-        let deposits_sum = self.repo.sum_deposits_up_to(user, at_ms).await?;
-        let realized_pnl_before = self.repo.sum_realized_pnl_before(user, at_ms).await?;
-        let derived_equity = deposits_sum + realized_pnl_before;
+        let derived_equity = match self.repo.get_equity_snapshot_at_or_before(user, at_ms).await? {
+            Some((t, equity)) if t == at_ms => return Ok(equity),
+            // Roll an earlier snapshot forward over what happened since, so a withdrawal
+            // between the two does not leave it stale.
+            Some((t, equity)) => {
+                equity
+                    + self.repo.sum_deposits_between(user, t, at_ms).await?
+                    + self.repo.sum_realized_pnl_between(user, t, at_ms).await?
+            }
+            None => {
+                self.repo.sum_deposits_up_to(user, at_ms).await?
+                    + self.repo.sum_realized_pnl_before(user, at_ms).await?
+            }
+        };
 
         self.repo
             .upsert_equity_snapshot(user, at_ms, derived_equity)
@@ -28,5 +224,310 @@ impl EquityResolver {
 
         Ok(derived_equity)
     }
+
+    /// Capital a return over `(from_ms, to_ms]` is measured against: equity at `from_ms`
+    /// plus the highest running total of ledger flows inside the window.
+    ///
+    /// A deposit mid-window adds to the capital; a withdrawal lowers the running total, so
+    /// withdrawing and depositing the same funds again does not count them twice.
+    pub async fn capital_base(
+        &self,
+        user: &Address,
+        from_ms: TimeMs,
+        to_ms: Option<TimeMs>,
+    ) -> Result<Decimal, sqlx::Error> {
+        Ok(self.equity_curve(user, from_ms, to_ms).await?.capital())
+    }
+
+    /// Equity at `from_ms` and the ledger flows in `(from_ms, to_ms]`; the caller adds the
+    /// PnL it counts.
+    pub async fn equity_curve(
+        &self,
+        user: &Address,
+        from_ms: TimeMs,
+        to_ms: Option<TimeMs>,
+    ) -> Result<EquityCurve, sqlx::Error> {
+        let start_equity = self.resolve_equity(user, from_ms).await?;
+        let flows = self
+            .repo
+            .query_deposits(
+                user,
+                from_ms.as_ms().saturating_add(1),
+                to_ms.map_or(i64::MAX, |t| t.as_ms()),
+            )
+            .await?
+            .into_iter()
+            .map(|d| (d.time_ms, d.amount))
+            .collect();
+
+        Ok(EquityCurve {
+            start_ms: from_ms,
+            start_equity,
+            flows,
+            pnl: Vec::new(),
+        })
+    }
 }
 
+/// Highest running total of `flows` (in time order), never below zero.
+///
+/// Flows in the same millisecond are netted before the running total is compared, so the
+/// result does not depend on how they are ordered among themselves.
+pub fn peak_net_flow(flows: &[(TimeMs, Decimal)]) -> Decimal {
+    let mut running = Decimal::zero();
+    let mut peak = Decimal::zero();
+    let mut flows = flows.iter().peekable();
+    while let Some((time_ms, amount)) = flows.next() {
+        running = running + *amount;
+        while let Some((_, amount)) = flows.next_if(|(t, _)| t == time_ms) {
+            running = running + *amount;
+        }
+        if running > peak {
+            peak = running;
+        }
+    }
+    peak
+}
+
+/// A coin's position from `time_ms` until its next snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldPosition {
+    pub time_ms: TimeMs,
+    pub coin: Coin,
+    pub net_size: Decimal,
+    pub avg_entry_px: Decimal,
+}
+
+/// Equity at one time, with the open positions valued at mark prices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkedEquityPoint {
+    pub time_ms: TimeMs,
+    /// Net ledger flows plus realized PnL.
+    pub equity: Decimal,
+    /// PnL of the positions open at `time_ms` at the marks of its bucket; `None` when a
+    /// held coin has no mark there.
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+/// Mark the realized equity curve `points` (in time order) to market.
+///
+/// `positions` are the snapshots of every coin in time order, including those before the
+/// first point so the positions held then are known. Besides every point, the curve gets
+/// a point at each mark bucket up to `to_ms` in which a position is open and marked, so it
+/// moves with prices between fills and flows.
+pub fn mark_to_market(
+    points: &[(TimeMs, Decimal)],
+    positions: &[HeldPosition],
+    marks: &HashMap<Coin, MarkSeries>,
+    to_ms: TimeMs,
+) -> Vec<MarkedEquityPoint> {
+    let Some(&(start_ms, mut equity)) = points.first() else {
+        return Vec::new();
+    };
+    let mut times: BTreeSet<TimeMs> = points.iter().map(|(t, _)| *t).collect();
+    for series in marks.values() {
+        times.extend(series.times_between(start_ms, to_ms));
+    }
+
+    let mut positions = positions.iter().peekable();
+    let mut points = points.iter().peekable();
+    let mut held: BTreeMap<&Coin, (Decimal, Decimal)> = BTreeMap::new();
+    let mut marked = Vec::new();
+    for time_ms in times {
+        while let Some(p) = positions.next_if(|p| p.time_ms <= time_ms) {
+            if p.net_size.is_zero() {
+                held.remove(&p.coin);
+            } else {
+                held.insert(&p.coin, (p.net_size, p.avg_entry_px));
+            }
+        }
+        let mut is_point = false;
+        while let Some((_, e)) = points.next_if(|(t, _)| *t <= time_ms) {
+            equity = *e;
+            is_point = true;
+        }
+
+        let unrealized_pnl = held
+            .iter()
+            .try_fold(Decimal::zero(), |total, (coin, (net_size, avg_entry_px))| {
+                let mark = marks.get(*coin)?.at(time_ms)?;
+                Some(total + (mark - *avg_entry_px) * *net_size)
+            });
+        if is_point || (!held.is_empty() && unrealized_pnl.is_some()) {
+            marked.push(MarkedEquityPoint {
+                time_ms,
+                equity,
+                unrealized_pnl,
+            });
+        }
+    }
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow(time_ms: i64, amount: &str) -> (TimeMs, Decimal) {
+        (TimeMs::new(time_ms), Decimal::from_str(amount).unwrap())
+    }
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    /// 1000 at the start, +100 PnL, a 1000 deposit, then +50 PnL.
+    fn curve_with_midway_deposit() -> EquityCurve {
+        EquityCurve {
+            start_ms: TimeMs::new(0),
+            start_equity: d("1000"),
+            flows: vec![flow(500, "1000")],
+            pnl: vec![flow(250, "100"), flow(750, "50")],
+        }
+    }
+
+    #[test]
+    fn test_return_method_parses_case_insensitively() {
+        assert_eq!(ReturnMethod::from_str("TWR"), Ok(ReturnMethod::Twr));
+        assert_eq!(ReturnMethod::from_str(" irr "), Ok(ReturnMethod::Irr));
+        assert_eq!(ReturnMethod::from_str("dietz"), Err(()));
+    }
+
+    #[test]
+    fn test_time_weighted_return_ignores_deposit_size() {
+        let curve = curve_with_midway_deposit();
+        // 10% on 1000, then 50 on 2100.
+        let expected = (d("1.1") * (d("2150") / d("2100")) - d("1")) * Decimal::hundred();
+        assert_eq!(curve.time_weighted_return(), Some(expected));
+        assert_eq!(curve.capital(), d("2000"));
+
+        let without_capital = EquityCurve {
+            start_equity: Decimal::zero(),
+            flows: Vec::new(),
+            ..curve
+        };
+        assert_eq!(without_capital.time_weighted_return(), None);
+    }
+
+    #[test]
+    fn test_time_weighted_return_applies_flows_before_same_ms_pnl() {
+        let curve = EquityCurve {
+            start_ms: TimeMs::new(0),
+            start_equity: d("100"),
+            flows: vec![flow(500, "100")],
+            pnl: vec![flow(500, "20")],
+        };
+        // The PnL is earned on 200, not on 100.
+        assert_eq!(curve.time_weighted_return(), Some(d("10")));
+    }
+
+    #[test]
+    fn test_money_weighted_return_without_flows_matches_simple() {
+        let curve = EquityCurve {
+            start_ms: TimeMs::new(0),
+            start_equity: d("1000"),
+            flows: Vec::new(),
+            pnl: vec![flow(400, "100")],
+        };
+        assert_eq!(curve.money_weighted_return(TimeMs::new(1000)), Some(d("10")));
+        assert_eq!(curve.money_weighted_return(TimeMs::new(0)), None);
+    }
+
+    #[test]
+    fn test_money_weighted_return_weights_late_deposit_less() {
+        let mwr = curve_with_midway_deposit()
+            .money_weighted_return(TimeMs::new(1000))
+            .unwrap();
+        // Between the simple return on the peak capital and on the starting equity.
+        assert!(mwr > d("7.5") && mwr < d("15"), "{}", mwr);
+    }
+
+    #[test]
+    fn test_xirr_over_a_year() {
+        let year_ms = 365 * 86_400_000;
+        let rate = xirr(&[flow(0, "-100"), flow(year_ms, "110")], year_ms).unwrap();
+        assert!((rate - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merge_turns_later_start_into_flow() {
+        let mut curve = curve_with_midway_deposit();
+        curve.merge(EquityCurve {
+            start_ms: TimeMs::new(300),
+            start_equity: d("400"),
+            flows: Vec::new(),
+            pnl: vec![flow(600, "-10")],
+        });
+        assert_eq!(curve.start_equity, d("1000"));
+        assert_eq!(curve.flows, vec![flow(300, "400"), flow(500, "1000")]);
+        assert_eq!(curve.end_equity(), d("2540"));
+    }
+
+    #[test]
+    fn test_peak_net_flow_does_not_double_count_redeposits() {
+        let flows = vec![flow(1000, "500"), flow(2000, "-800"), flow(3000, "600")];
+        assert_eq!(peak_net_flow(&flows), Decimal::from_str("500").unwrap());
+        assert_eq!(peak_net_flow(&[flow(1000, "-100")]), Decimal::zero());
+    }
+
+    #[test]
+    fn test_peak_net_flow_nets_same_millisecond() {
+        let withdraw_first = vec![flow(1000, "-300"), flow(1000, "300")];
+        let deposit_first = vec![flow(1000, "300"), flow(1000, "-300")];
+        assert_eq!(peak_net_flow(&withdraw_first), Decimal::zero());
+        assert_eq!(peak_net_flow(&deposit_first), Decimal::zero());
+    }
+
+    #[test]
+    fn test_mark_to_market_values_open_positions_between_points() {
+        use crate::domain::MARK_PRICE_BUCKET_MS as HOUR;
+
+        let btc = Coin::new("BTC".to_string());
+        // Long 2 BTC at 100 from t=10, closed at 2h+10 for 30 realized.
+        let positions = vec![
+            HeldPosition {
+                time_ms: TimeMs::new(10),
+                coin: btc.clone(),
+                net_size: d("2"),
+                avg_entry_px: d("100"),
+            },
+            HeldPosition {
+                time_ms: TimeMs::new(2 * HOUR + 10),
+                coin: btc.clone(),
+                net_size: d("0"),
+                avg_entry_px: d("0"),
+            },
+        ];
+        let points = vec![flow(0, "1000"), flow(10, "1000"), flow(2 * HOUR + 10, "1030")];
+        let marks = HashMap::from([(
+            btc,
+            MarkSeries::new(vec![
+                (TimeMs::new(0), d("100")),
+                (TimeMs::new(HOUR), d("110")),
+                (TimeMs::new(2 * HOUR), d("115")),
+                (TimeMs::new(3 * HOUR), d("120")),
+            ]),
+        )]);
+
+        let marked = mark_to_market(&points, &positions, &marks, TimeMs::new(4 * HOUR));
+        let summary: Vec<_> = marked
+            .iter()
+            .map(|p| {
+                let unrealized = p.unrealized_pnl.map(|u| u.to_canonical_string());
+                (p.time_ms.as_ms(), p.equity.to_canonical_string(), unrealized)
+            })
+            .collect();
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            summary,
+            vec![
+                (0, "1000".to_string(), some("0")),
+                (10, "1000".to_string(), some("0")),
+                (HOUR, "1000".to_string(), some("20")),
+                (2 * HOUR, "1000".to_string(), some("30")),
+                // Flat afterwards, so the 3h mark adds no point.
+                (2 * HOUR + 10, "1030".to_string(), some("0")),
+            ]
+        );
+    }
+}
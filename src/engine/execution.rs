@@ -0,0 +1,226 @@
+//! Execution quality: how far each fill's price was from a reference price at the time.
+
+use super::BuilderLogsIndex;
+use crate::domain::{Coin, Decimal, Fill, Side, TimeMs};
+use rust_decimal::Decimal as RustDecimal;
+use std::collections::HashMap;
+
+/// Where a fill's reference price comes from.
+pub trait ReferencePrice {
+    /// Reference price for `fill` and the number of observations behind it, or `None` when
+    /// there is no data close enough.
+    fn reference_px(&self, fill: &Fill) -> Option<(Decimal, usize)>;
+}
+
+/// Median price of the builder's other fills in the same coin around the fill's time.
+#[derive(Debug)]
+pub struct LogsMedianPrice<'a> {
+    pub index: &'a BuilderLogsIndex<'a>,
+    /// Rows within this many milliseconds either side of the fill count.
+    pub window_ms: i64,
+}
+
+impl ReferencePrice for LogsMedianPrice<'_> {
+    fn reference_px(&self, fill: &Fill) -> Option<(Decimal, usize)> {
+        self.index
+            .median_px(&fill.coin, fill.time_ms, self.window_ms, Some(&fill.user))
+    }
+}
+
+/// Mark prices supplied by the caller.
+#[derive(Debug, Clone, Default)]
+pub struct MarkPriceFeed {
+    by_coin: HashMap<Coin, Vec<(TimeMs, Decimal)>>,
+    /// Marks older than this are not used.
+    max_age_ms: i64,
+}
+
+impl MarkPriceFeed {
+    pub fn new(marks: impl IntoIterator<Item = (Coin, TimeMs, Decimal)>, max_age_ms: i64) -> Self {
+        let mut by_coin: HashMap<Coin, Vec<(TimeMs, Decimal)>> = HashMap::new();
+        for (coin, time_ms, px) in marks {
+            by_coin.entry(coin).or_default().push((time_ms, px));
+        }
+        for marks in by_coin.values_mut() {
+            marks.sort_by_key(|(time_ms, _)| *time_ms);
+        }
+        Self {
+            by_coin,
+            max_age_ms,
+        }
+    }
+}
+
+impl ReferencePrice for MarkPriceFeed {
+    /// The latest mark at or before the fill.
+    fn reference_px(&self, fill: &Fill) -> Option<(Decimal, usize)> {
+        let marks = self.by_coin.get(&fill.coin)?;
+        let after = marks.partition_point(|(time_ms, _)| *time_ms <= fill.time_ms);
+        let (time_ms, px) = marks[..after].last()?;
+        (fill.time_ms.as_ms() - time_ms.as_ms() <= self.max_age_ms).then_some((*px, 1))
+    }
+}
+
+/// Slippage of one fill against its reference price.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillSlippage {
+    pub fill_key: String,
+    pub time_ms: TimeMs,
+    pub coin: Coin,
+    pub side: Side,
+    pub px: Decimal,
+    pub sz: Decimal,
+    /// `None` when no reference price was available.
+    pub reference_px: Option<Decimal>,
+    /// Observations the reference price was taken from.
+    pub reference_count: usize,
+    pub slippage_bps: Option<Decimal>,
+}
+
+/// Per-fill slippage and its notional-weighted average.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionQuality {
+    pub fills: Vec<FillSlippage>,
+    /// Fills with a reference price.
+    pub measured_fills: usize,
+    /// Average of `slippage_bps` weighted by notional, over measured fills.
+    pub avg_slippage_bps: Option<Decimal>,
+}
+
+/// Slippage in basis points of `px` against `reference_px`.
+///
+/// Positive means the fill was worse than the reference: bought above it or sold below it.
+pub fn slippage_bps(side: Side, px: Decimal, reference_px: Decimal) -> Decimal {
+    let diff = match side {
+        Side::Buy => px - reference_px,
+        Side::Sell => reference_px - px,
+    };
+    diff / reference_px * Decimal::new(RustDecimal::from(10_000))
+}
+
+pub fn execution_quality(fills: &[Fill], reference: &impl ReferencePrice) -> ExecutionQuality {
+    let mut quality = ExecutionQuality::default();
+    let mut weighted = Decimal::zero();
+    let mut notional = Decimal::zero();
+
+    for fill in fills {
+        let found = reference
+            .reference_px(fill)
+            .filter(|(px, _)| px.is_positive());
+        let slippage = found.map(|(reference_px, _)| slippage_bps(fill.side, fill.px, reference_px));
+        if let Some(bps) = slippage {
+            let fill_notional = fill.px * fill.sz;
+            weighted = weighted + bps * fill_notional;
+            notional = notional + fill_notional;
+            quality.measured_fills += 1;
+        }
+        quality.fills.push(FillSlippage {
+            fill_key: fill.fill_key.clone(),
+            time_ms: fill.time_ms,
+            coin: fill.coin.clone(),
+            side: fill.side,
+            px: fill.px,
+            sz: fill.sz,
+            reference_px: found.map(|(px, _)| px),
+            reference_count: found.map_or(0, |(_, count)| count),
+            slippage_bps: slippage,
+        });
+    }
+
+    quality.avg_slippage_bps = (!notional.is_zero()).then(|| weighted / notional);
+    quality
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, BuilderLogFill};
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, side: Side, px: &str, sz: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            side,
+            d(px),
+            d(sz),
+            Decimal::zero(),
+            Decimal::zero(),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    fn log(time_ms: i64, user: &str, px: &str) -> BuilderLogFill {
+        BuilderLogFill {
+            time_ms: TimeMs::new(time_ms),
+            user: Address::new(user.to_string()),
+            coin: Coin::new("BTC".to_string()),
+            side: Side::Buy,
+            px: d(px),
+            sz: d("1"),
+            tid: None,
+            oid: None,
+        }
+    }
+
+    #[test]
+    fn test_slippage_sign_follows_side() {
+        assert_eq!(slippage_bps(Side::Buy, d("101"), d("100")), d("100"));
+        assert_eq!(slippage_bps(Side::Sell, d("101"), d("100")), d("-100"));
+    }
+
+    #[test]
+    fn test_execution_quality_against_logs_median() {
+        let logs = vec![
+            log(900, "0xdef", "99"),
+            log(1000, "0xdef", "100"),
+            log(1100, "0xdef", "104"),
+            // The user's own fill and a row outside the window are ignored.
+            log(1000, "0xABC", "150"),
+            log(5000, "0xdef", "1"),
+        ];
+        let index = BuilderLogsIndex::new(&logs);
+        let reference = LogsMedianPrice {
+            index: &index,
+            window_ms: 500,
+        };
+        let fills = vec![
+            fill(1000, Side::Buy, "101", "1", 1),
+            fill(1000, Side::Sell, "99", "3", 2),
+            fill(9000, Side::Buy, "50", "1", 3),
+        ];
+
+        let quality = execution_quality(&fills, &reference);
+        assert_eq!(quality.fills[0].reference_px, Some(d("100")));
+        assert_eq!(quality.fills[0].reference_count, 3);
+        assert_eq!(quality.fills[0].slippage_bps, Some(d("100")));
+        assert_eq!(quality.fills[1].slippage_bps, Some(d("100")));
+        assert_eq!(quality.fills[2].reference_px, None);
+        assert_eq!(quality.measured_fills, 2);
+        assert_eq!(quality.avg_slippage_bps, Some(d("100")));
+    }
+
+    #[test]
+    fn test_mark_price_feed_uses_latest_recent_mark() {
+        let btc = Coin::new("BTC".to_string());
+        let feed = MarkPriceFeed::new(
+            vec![
+                (btc.clone(), TimeMs::new(2000), d("200")),
+                (btc.clone(), TimeMs::new(1000), d("100")),
+            ],
+            1_000,
+        );
+
+        assert_eq!(feed.reference_px(&fill(1500, Side::Buy, "1", "1", 1)), Some((d("100"), 1)));
+        assert_eq!(feed.reference_px(&fill(2500, Side::Buy, "1", "1", 2)), Some((d("200"), 1)));
+        assert_eq!(feed.reference_px(&fill(500, Side::Buy, "1", "1", 3)), None);
+        assert_eq!(feed.reference_px(&fill(3500, Side::Buy, "1", "1", 4)), None);
+    }
+}
@@ -0,0 +1,228 @@
+//! Fee aggregation that keeps maker rebates (negative fees) visible.
+//!
+//! Hyperliquid reports rebates as negative `fee` values. Summing them into `feesPaid` is
+//! correct for net PnL, but hides how much of the total was rebated, so aggregations also
+//! track the rebated amount on its own.
+
+use super::Effect;
+use crate::domain::{Decimal, Fill};
+use std::collections::HashMap;
+
+/// Fee totals over a set of fills or effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeeTotals {
+    /// Net fees: charges minus rebates. Negative when rebates exceed charges.
+    pub fees_paid: Decimal,
+    /// Sum of rebates as a non-negative amount.
+    pub rebates_received: Decimal,
+}
+
+impl FeeTotals {
+    pub fn add(&mut self, fee: Decimal) {
+        self.fees_paid = self.fees_paid + fee;
+        if fee.is_negative() {
+            self.rebates_received = self.rebates_received - fee;
+        }
+    }
+
+    pub fn from_fees(fees: impl IntoIterator<Item = Decimal>) -> Self {
+        let mut totals = Self::default();
+        for fee in fees {
+            totals.add(fee);
+        }
+        totals
+    }
+
+    /// Gross charges before rebates.
+    pub fn charged(&self) -> Decimal {
+        self.fees_paid + self.rebates_received
+    }
+}
+
+/// A fill whose fee was not carried over exactly to its effects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeAuditIssue {
+    pub fill_key: String,
+    pub reason: String,
+}
+
+/// Check that each fill's fee is fully allocated to its effects without changing sign.
+///
+/// Flips split one fill's fee between the closing and opening effects; a rebate must stay a
+/// rebate on both sides and the parts must add back up to the original fee exactly. The
+/// builder fee is split the same way and must add back up too. The
+/// compiler records failures as [`Invariant::FeeAllocation`] violations.
+///
+/// [`Invariant::FeeAllocation`]: super::Invariant::FeeAllocation
+pub fn audit_fee_allocation(fills: &[Fill], effects: &[Effect]) -> Vec<FeeAuditIssue> {
+    let mut allocated: HashMap<&str, Vec<&Effect>> = HashMap::new();
+    for effect in effects {
+        allocated
+            .entry(effect.fill_key.as_str())
+            .or_default()
+            .push(effect);
+    }
+
+    let mut issues = Vec::new();
+    for fill in fills {
+        let effects = allocated.remove(fill.fill_key()).unwrap_or_default();
+        let parts: Vec<Decimal> = effects.iter().map(|e| e.fee).collect();
+        let total = parts.iter().fold(Decimal::zero(), |acc, fee| acc + *fee);
+        if total != fill.fee {
+            issues.push(FeeAuditIssue {
+                fill_key: fill.fill_key.clone(),
+                reason: format!(
+                    "effects carry fee {} but the fill fee is {}",
+                    total.to_canonical_string(),
+                    fill.fee.to_canonical_string()
+                ),
+            });
+        }
+        let sign_flipped = parts.iter().any(|fee| {
+            (fill.fee.is_negative() && fee.is_positive())
+                || (!fill.fee.is_negative() && fee.is_negative())
+        });
+        if sign_flipped {
+            issues.push(FeeAuditIssue {
+                fill_key: fill.fill_key.clone(),
+                reason: format!(
+                    "effect fee has the opposite sign of fill fee {}",
+                    fill.fee.to_canonical_string()
+                ),
+            });
+        }
+        let builder_fee = fill.builder_fee.unwrap_or_else(Decimal::zero);
+        let builder_total = effects
+            .iter()
+            .fold(Decimal::zero(), |acc, e| acc + e.builder_fee);
+        if builder_total != builder_fee {
+            issues.push(FeeAuditIssue {
+                fill_key: fill.fill_key.clone(),
+                reason: format!(
+                    "effects carry builder fee {} but the fill builder fee is {}",
+                    builder_total.to_canonical_string(),
+                    builder_fee.to_canonical_string()
+                ),
+            });
+        }
+    }
+
+    issues.extend(allocated.into_keys().map(|fill_key| FeeAuditIssue {
+        fill_key: fill_key.to_string(),
+        reason: "effect has no matching fill".to_string(),
+    }));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Coin, Side, TimeMs};
+    use crate::engine::PositionTracker;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, side: Side, sz: &str, fee: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            side,
+            d("100"),
+            d(sz),
+            d(fee),
+            d("0"),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_fee_totals_separate_rebates() {
+        let totals = FeeTotals::from_fees([d("5"), d("-2"), d("0"), d("-0.5")]);
+        assert_eq!(totals.fees_paid, d("2.5"));
+        assert_eq!(totals.rebates_received, d("2.5"));
+        assert_eq!(totals.charged(), d("5"));
+    }
+
+    #[test]
+    fn test_fee_totals_all_rebates_go_negative() {
+        let totals = FeeTotals::from_fees([d("-1"), d("-3")]);
+        assert_eq!(totals.fees_paid, d("-4"));
+        assert_eq!(totals.rebates_received, d("4"));
+        assert!(totals.charged().is_zero());
+    }
+
+    #[test]
+    fn test_flip_keeps_rebate_sign_on_both_sides() {
+        let fills = vec![
+            fill(1000, Side::Buy, "1", "-0.3", 1),
+            // Flip: closes 1 and opens 3, so the rebate is split 1/4 and 3/4.
+            fill(2000, Side::Sell, "4", "-0.8", 2),
+            fill(3000, Side::Buy, "3", "0", 3),
+        ];
+        let mut tracker = PositionTracker::new();
+        for f in &fills {
+            tracker.process_fill(f);
+        }
+
+        let effects = tracker.get_effects();
+        assert!(audit_fee_allocation(&fills, effects).is_empty());
+
+        let flip_fees: Vec<Decimal> = effects
+            .iter()
+            .filter(|e| e.fill_key == fills[1].fill_key)
+            .map(|e| e.fee)
+            .collect();
+        assert_eq!(flip_fees, vec![d("-0.2"), d("-0.6")]);
+    }
+
+    #[test]
+    fn test_flip_splits_builder_fee_like_fee() {
+        let mut flip = fill(2000, Side::Sell, "4", "0.8", 2);
+        flip.builder_fee = Some(d("0.4"));
+        let fills = vec![fill(1000, Side::Buy, "1", "0.2", 1), flip];
+        let mut tracker = PositionTracker::new();
+        for f in &fills {
+            tracker.process_fill(f);
+        }
+
+        let effects = tracker.get_effects();
+        assert!(audit_fee_allocation(&fills, effects).is_empty());
+        assert!(effects[0].builder_fee.is_zero());
+        assert_eq!(effects[1].builder_fee, d("0.1"));
+        assert_eq!(effects[2].builder_fee, d("0.3"));
+
+        let mut lost = effects.to_vec();
+        lost[2].builder_fee = Decimal::zero();
+        let issues = audit_fee_allocation(&fills, &lost);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].reason.contains("fill builder fee is 0.4"));
+    }
+
+    #[test]
+    fn test_audit_flags_lost_and_sign_flipped_fees() {
+        let fills = vec![fill(1000, Side::Buy, "1", "-1", 1)];
+        let effects = vec![
+            Effect {
+                fill_key: fills[0].fill_key.clone(),
+                fee: d("-2"),
+                ..Default::default()
+            },
+            Effect {
+                fill_key: fills[0].fill_key.clone(),
+                fee: d("0.5"),
+                ..Default::default()
+            },
+        ];
+
+        let issues = audit_fee_allocation(&fills, &effects);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].reason.contains("fill fee is -1"));
+        assert!(issues[1].reason.contains("opposite sign"));
+    }
+}
@@ -0,0 +1,117 @@
+//! Builder attribution from the builder fee a fill paid.
+//!
+//! Used for every fill in `heuristic` mode, and in `auto` mode for fills no builder log row
+//! matched (including days whose logs could not be fetched). Attributions made here are
+//! recorded with mode `heuristic` and `low` confidence: a builder fee shows that *some*
+//! builder routed the fill, not which one.
+
+use crate::domain::{Attribution, Decimal, Fill};
+use rust_decimal::Decimal as RustDecimal;
+
+/// How far a fill's builder fee rate may be from a configured tier, in basis points.
+///
+/// Builder fees are set in tenths of a basis point, so half of one absorbs rounding of the
+/// reported fee.
+const FEE_TIER_TOLERANCE_BPS: RustDecimal = RustDecimal::from_parts(5, 0, 0, false, 2);
+
+/// Attributes fills by their builder fee.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeuristicAttributor {
+    /// Builder fee rates, in basis points of notional, charged by the target builder.
+    ///
+    /// When set, a positive builder fee only counts at one of these rates, so fills routed
+    /// by builders charging other rates are left out. Empty accepts any positive fee.
+    pub fee_tiers_bps: Vec<Decimal>,
+}
+
+impl HeuristicAttributor {
+    pub fn new(fee_tiers_bps: Vec<Decimal>) -> Self {
+        Self { fee_tiers_bps }
+    }
+
+    pub fn attribute(&self, fill: &Fill) -> Attribution {
+        let paid = fill
+            .builder_fee
+            .is_some_and(|fee| !fee.is_zero() && fee.inner().is_sign_positive());
+        Attribution::heuristic(paid && self.in_fee_tier(fill))
+    }
+
+    /// Whether the fill's builder fee rate is one of [`Self::fee_tiers_bps`].
+    fn in_fee_tier(&self, fill: &Fill) -> bool {
+        if self.fee_tiers_bps.is_empty() {
+            return true;
+        }
+        let Some(rate) = fee_rate_bps(fill) else {
+            return false;
+        };
+        self.fee_tiers_bps
+            .iter()
+            .any(|tier| (rate - tier.inner()).abs() <= FEE_TIER_TOLERANCE_BPS)
+    }
+}
+
+/// Builder fee as basis points of the fill's notional.
+fn fee_rate_bps(fill: &Fill) -> Option<RustDecimal> {
+    let fee = fill.builder_fee?.inner();
+    let notional = fill.px.inner().checked_mul(fill.sz.inner())?.abs();
+    if notional.is_zero() {
+        return None;
+    }
+    fee.checked_mul(RustDecimal::from(10_000))?
+        .checked_div(notional)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, AttributionMode, AttributionReason, Coin, Side, TimeMs};
+    use std::str::FromStr;
+
+    fn fill(builder_fee: Option<&str>) -> Fill {
+        // 100 * 10 = 1000 notional, so a fee of 0.1 is 1 bps.
+        Fill::new(
+            TimeMs::new(1_000),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            Decimal::from_str("100").unwrap(),
+            Decimal::from_str("10").unwrap(),
+            Decimal::zero(),
+            Decimal::zero(),
+            builder_fee.map(|f| Decimal::from_str(f).unwrap()),
+            Some(1),
+            None,
+        )
+    }
+
+    fn bps(values: &[&str]) -> HeuristicAttributor {
+        HeuristicAttributor::new(values.iter().map(|v| Decimal::from_str(v).unwrap()).collect())
+    }
+
+    #[test]
+    fn test_any_positive_fee_without_tiers() {
+        let heuristic = HeuristicAttributor::default();
+        let attr = heuristic.attribute(&fill(Some("0.1")));
+        assert!(attr.attributed);
+        assert_eq!(attr.mode, AttributionMode::Heuristic);
+        assert_eq!(attr.reason, AttributionReason::BuilderFee);
+
+        assert!(!heuristic.attribute(&fill(None)).attributed);
+        assert!(!heuristic.attribute(&fill(Some("0"))).attributed);
+        assert!(!heuristic.attribute(&fill(Some("-0.1"))).attributed);
+    }
+
+    #[test]
+    fn test_fee_tiers_limit_attribution() {
+        let heuristic = bps(&["1", "2.5"]);
+        assert!(heuristic.attribute(&fill(Some("0.1"))).attributed);
+        assert!(heuristic.attribute(&fill(Some("0.25"))).attributed);
+        // Within half a tenth of a basis point of 1 bps.
+        assert!(heuristic.attribute(&fill(Some("0.1004"))).attributed);
+
+        let other = heuristic.attribute(&fill(Some("0.5")));
+        assert!(!other.attributed);
+        assert_eq!(other.score, 0);
+        assert!(!heuristic.attribute(&fill(None)).attributed);
+    }
+}
@@ -0,0 +1,293 @@
+//! Integrity invariants checked on compiler output.
+//!
+//! The position tracker must decompose every fill into effects that add back up to the
+//! fill, and lifecycles must span the fills that touch them. The compiler checks these
+//! after every run and records violations instead of only relying on tests to catch
+//! engine regressions.
+
+use super::{audit_fee_allocation, Effect, Lifecycle};
+use crate::domain::{Decimal, Fill, TimeMs};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// A compile invariant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Invariant {
+    /// Effect quantities of a fill sum to the fill size.
+    EffectQty,
+    /// Effect notionals of a fill sum to `px * sz`.
+    EffectNotional,
+    /// Every fill has at least one effect.
+    FillHasEffect,
+    /// A lifecycle starts no later than its first fill and ends no earlier than its last.
+    LifecycleBracket,
+    /// Each fill's fee and builder fee are fully allocated to its effects without changing
+    /// sign; see [`audit_fee_allocation`].
+    FeeAllocation,
+}
+
+impl Invariant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Invariant::EffectQty => "effect_qty",
+            Invariant::EffectNotional => "effect_notional",
+            Invariant::FillHasEffect => "fill_has_effect",
+            Invariant::LifecycleBracket => "lifecycle_bracket",
+            Invariant::FeeAllocation => "fee_allocation",
+        }
+    }
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Invariant {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "effect_qty" => Ok(Invariant::EffectQty),
+            "effect_notional" => Ok(Invariant::EffectNotional),
+            "fill_has_effect" => Ok(Invariant::FillHasEffect),
+            "lifecycle_bracket" => Ok(Invariant::LifecycleBracket),
+            "fee_allocation" => Ok(Invariant::FeeAllocation),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A single invariant violation found after a compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub invariant: Invariant,
+    pub fill_key: Option<String>,
+    pub lifecycle_id: Option<i64>,
+    pub detail: String,
+}
+
+/// Checks compile invariants over fills processed in chunks.
+///
+/// Per-fill invariants are checked as each chunk is processed; lifecycle bracketing only
+/// keeps the earliest and latest fill time per lifecycle, so memory does not grow with the
+/// number of fills.
+#[derive(Debug, Default)]
+pub struct InvariantChecker {
+    spans: HashMap<i64, (TimeMs, TimeMs)>,
+    violations: Vec<InvariantViolation>,
+}
+
+impl InvariantChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a chunk of fills against the effects the tracker emitted for them.
+    pub fn check_fills(&mut self, fills: &[Fill], effects: &[Effect]) {
+        self.violations.extend(
+            audit_fee_allocation(fills, effects)
+                .into_iter()
+                .map(|issue| InvariantViolation {
+                    invariant: Invariant::FeeAllocation,
+                    fill_key: Some(issue.fill_key),
+                    lifecycle_id: None,
+                    detail: issue.reason,
+                }),
+        );
+
+        let mut by_fill: HashMap<&str, Vec<&Effect>> = HashMap::new();
+        for effect in effects {
+            by_fill
+                .entry(effect.fill_key.as_str())
+                .or_default()
+                .push(effect);
+        }
+
+        for fill in fills {
+            let Some(fill_effects) = by_fill.get(fill.fill_key()) else {
+                self.violations.push(InvariantViolation {
+                    invariant: Invariant::FillHasEffect,
+                    fill_key: Some(fill.fill_key.clone()),
+                    lifecycle_id: None,
+                    detail: "fill produced no effects".to_string(),
+                });
+                continue;
+            };
+
+            let qty = fill_effects
+                .iter()
+                .fold(Decimal::zero(), |acc, e| acc + e.qty);
+            if qty != fill.sz {
+                self.violations.push(InvariantViolation {
+                    invariant: Invariant::EffectQty,
+                    fill_key: Some(fill.fill_key.clone()),
+                    lifecycle_id: None,
+                    detail: format!(
+                        "effects carry qty {} but the fill size is {}",
+                        qty.to_canonical_string(),
+                        fill.sz.to_canonical_string()
+                    ),
+                });
+            }
+
+            let notional = fill_effects
+                .iter()
+                .fold(Decimal::zero(), |acc, e| acc + e.notional);
+            let expected = fill.px * fill.sz;
+            if notional != expected {
+                self.violations.push(InvariantViolation {
+                    invariant: Invariant::EffectNotional,
+                    fill_key: Some(fill.fill_key.clone()),
+                    lifecycle_id: None,
+                    detail: format!(
+                        "effects carry notional {} but px * sz is {}",
+                        notional.to_canonical_string(),
+                        expected.to_canonical_string()
+                    ),
+                });
+            }
+
+            for effect in fill_effects {
+                self.spans
+                    .entry(effect.lifecycle_id)
+                    .and_modify(|(first, last)| {
+                        *first = (*first).min(fill.time_ms);
+                        *last = (*last).max(fill.time_ms);
+                    })
+                    .or_insert((fill.time_ms, fill.time_ms));
+            }
+        }
+    }
+
+    /// Check lifecycle bracketing and return all violations found.
+    pub fn finish(self, lifecycles: &[Lifecycle]) -> Vec<InvariantViolation> {
+        let mut violations = self.violations;
+        let lifecycles: HashMap<i64, &Lifecycle> = lifecycles.iter().map(|l| (l.id, l)).collect();
+
+        // Ordered by id so recorded violations are deterministic.
+        let spans: BTreeMap<i64, (TimeMs, TimeMs)> = self.spans.into_iter().collect();
+        for (lifecycle_id, (first, last)) in spans {
+            let Some(lifecycle) = lifecycles.get(&lifecycle_id) else {
+                violations.push(InvariantViolation {
+                    invariant: Invariant::LifecycleBracket,
+                    fill_key: None,
+                    lifecycle_id: Some(lifecycle_id),
+                    detail: "effects reference a lifecycle that was not emitted".to_string(),
+                });
+                continue;
+            };
+
+            if lifecycle.start_time_ms > first {
+                violations.push(InvariantViolation {
+                    invariant: Invariant::LifecycleBracket,
+                    fill_key: None,
+                    lifecycle_id: Some(lifecycle_id),
+                    detail: format!(
+                        "lifecycle starts at {} after its first fill at {}",
+                        lifecycle.start_time_ms.as_ms(),
+                        first.as_ms()
+                    ),
+                });
+            }
+            if let Some(end) = lifecycle.end_time_ms.filter(|end| *end < last) {
+                violations.push(InvariantViolation {
+                    invariant: Invariant::LifecycleBracket,
+                    fill_key: None,
+                    lifecycle_id: Some(lifecycle_id),
+                    detail: format!(
+                        "lifecycle ends at {} before its last fill at {}",
+                        end.as_ms(),
+                        last.as_ms()
+                    ),
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Coin, Side};
+    use crate::engine::PositionTracker;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, side: Side, sz: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            side,
+            d("100.5"),
+            d(sz),
+            d("0.1"),
+            d("0"),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_tracker_output_satisfies_invariants() {
+        let fills = [
+            fill(1000, Side::Buy, "1", 1),
+            fill(2000, Side::Buy, "0.5", 2),
+            // Flip: closes 1.5 and opens 2.
+            fill(3000, Side::Sell, "3.5", 3),
+            fill(4000, Side::Buy, "2", 4),
+        ];
+        let mut tracker = PositionTracker::new();
+        let mut checker = InvariantChecker::new();
+        // Two chunks, as the compiler streams them.
+        for chunk in fills.chunks(3) {
+            let before = tracker.get_effects().len();
+            for f in chunk {
+                tracker.process_fill(f);
+            }
+            checker.check_fills(chunk, &tracker.get_effects()[before..]);
+        }
+
+        assert!(checker.finish(tracker.get_lifecycles()).is_empty());
+    }
+
+    #[test]
+    fn test_detects_broken_effects_and_brackets() {
+        let fills = vec![fill(1000, Side::Buy, "1", 1), fill(2000, Side::Sell, "1", 2)];
+        let mut tracker = PositionTracker::new();
+        for f in &fills {
+            tracker.process_fill(f);
+        }
+        let (mut lifecycles, _, mut effects) = tracker.into_outputs();
+        effects[0].qty = d("0.9");
+        effects.pop();
+        lifecycles[0].start_time_ms = TimeMs::new(1500);
+
+        let mut checker = InvariantChecker::new();
+        checker.check_fills(&fills, &effects);
+        let invariants: Vec<Invariant> = checker
+            .finish(&lifecycles)
+            .into_iter()
+            .map(|v| v.invariant)
+            .collect();
+
+        assert_eq!(
+            invariants,
+            vec![
+                // The dropped effect took the second fill's fee with it.
+                Invariant::FeeAllocation,
+                Invariant::EffectQty,
+                Invariant::FillHasEffect,
+                Invariant::LifecycleBracket,
+            ]
+        );
+    }
+}
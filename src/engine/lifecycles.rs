@@ -0,0 +1,191 @@
+//! Per-lifecycle trade reports: one row per position from open to close.
+
+use super::{EffectType, FeeTotals};
+use crate::db::repo::LifecycleEffectRow;
+use crate::domain::{Coin, Decimal, FillType, Side, TimeMs};
+
+/// Summary of one lifecycle, aggregated from its fill effects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleReport {
+    pub lifecycle_id: i64,
+    pub coin: Coin,
+    /// Side of the opening fill: `Buy` for a long, `Sell` for a short.
+    pub side: Side,
+    pub start_ms: TimeMs,
+    /// `None` while the position is open.
+    pub end_ms: Option<TimeMs>,
+    /// Set when a liquidation or ADL fill closed the position.
+    pub closed_by: Option<FillType>,
+    /// Name of the strategy rule the lifecycle matched.
+    pub strategy: Option<String>,
+    /// Largest absolute size reached.
+    pub max_size: Decimal,
+    /// Volume-weighted price of the fills that opened or added to the position.
+    pub entry_px: Decimal,
+    /// Volume-weighted price of the fills that reduced or closed it; `None` until one does.
+    pub exit_px: Option<Decimal>,
+    pub realized_pnl: Decimal,
+    pub fees: FeeTotals,
+    /// Builder fees of the lifecycle's fills; a flip's fee counts in the lifecycle it closed.
+    pub builder_fees: Decimal,
+    /// Fills with an effect on this lifecycle; a flip counts in both lifecycles.
+    pub trade_count: usize,
+    pub tainted: bool,
+    pub taint_reason: Option<String>,
+}
+
+#[derive(Default)]
+struct Leg {
+    qty: Decimal,
+    notional: Decimal,
+}
+
+impl Leg {
+    fn add(&mut self, qty: Decimal, notional: Decimal) {
+        self.qty = self.qty + qty;
+        self.notional = self.notional + notional;
+    }
+
+    fn vwap(&self) -> Option<Decimal> {
+        (!self.qty.is_zero()).then(|| self.notional / self.qty)
+    }
+}
+
+/// Build reports from effect rows grouped by lifecycle and in compile order within each,
+/// as [`Repository::query_lifecycle_effects`] returns them.
+///
+/// [`Repository::query_lifecycle_effects`]: crate::db::Repository::query_lifecycle_effects
+pub fn lifecycle_reports(rows: &[LifecycleEffectRow]) -> Vec<LifecycleReport> {
+    let mut reports = Vec::new();
+    let mut start = 0;
+    while start < rows.len() {
+        let id = rows[start].lifecycle_id;
+        let end = rows[start..]
+            .iter()
+            .position(|r| r.lifecycle_id != id)
+            .map_or(rows.len(), |n| start + n);
+        reports.push(report(&rows[start..end]));
+        start = end;
+    }
+    reports
+}
+
+fn report(rows: &[LifecycleEffectRow]) -> LifecycleReport {
+    // Lifecycles begin with the effect that opened them.
+    let first = &rows[0];
+    let mut opened = Leg::default();
+    let mut closed = Leg::default();
+    let mut size = Decimal::zero();
+    let mut max_size = Decimal::zero();
+    let mut realized_pnl = Decimal::zero();
+    let mut fees = FeeTotals::default();
+    let mut builder_fees = Decimal::zero();
+    let mut trade_count = 0;
+
+    for (i, row) in rows.iter().enumerate() {
+        match row.effect_type {
+            EffectType::Open => {
+                opened.add(row.qty, row.notional);
+                size = size + row.qty;
+                if size > max_size {
+                    max_size = size;
+                }
+            }
+            EffectType::Close => {
+                closed.add(row.qty, row.notional);
+                size = size - row.qty;
+            }
+        }
+        realized_pnl = realized_pnl + row.closed_pnl;
+        fees.add(row.fee);
+        builder_fees = builder_fees + row.builder_fee;
+        if i == 0 || rows[i - 1].fill_key != row.fill_key {
+            trade_count += 1;
+        }
+    }
+
+    LifecycleReport {
+        lifecycle_id: first.lifecycle_id,
+        coin: first.coin.clone(),
+        side: first.side,
+        start_ms: first.start_time_ms,
+        end_ms: first.end_time_ms,
+        closed_by: first.closed_by,
+        strategy: first.strategy.clone(),
+        max_size,
+        entry_px: opened.vwap().unwrap_or_default(),
+        exit_px: closed.vwap(),
+        realized_pnl,
+        fees,
+        builder_fees,
+        trade_count,
+        tainted: first.is_tainted,
+        taint_reason: first.taint_reason.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn row(
+        lifecycle_id: i64,
+        fill_key: &str,
+        side: Side,
+        effect_type: EffectType,
+        qty: &str,
+        px: &str,
+        closed_pnl: &str,
+    ) -> LifecycleEffectRow {
+        LifecycleEffectRow {
+            lifecycle_id,
+            coin: Coin::new("BTC".to_string()),
+            start_time_ms: TimeMs::new(1_000),
+            end_time_ms: Some(TimeMs::new(5_000)),
+            is_tainted: false,
+            taint_reason: None,
+            closed_by: None,
+            strategy: None,
+            fill_key: fill_key.to_string(),
+            side,
+            effect_type,
+            qty: d(qty),
+            notional: d(qty) * d(px),
+            fee: d("0.1"),
+            builder_fee: Decimal::zero(),
+            closed_pnl: d(closed_pnl),
+        }
+    }
+
+    #[test]
+    fn test_reports_aggregate_each_lifecycle() {
+        let rows = vec![
+            row(1, "a", Side::Buy, EffectType::Open, "1", "100", "0"),
+            row(1, "b", Side::Buy, EffectType::Open, "1", "110", "0"),
+            row(1, "c", Side::Sell, EffectType::Close, "2", "120", "30"),
+            row(2, "d", Side::Sell, EffectType::Open, "3", "120", "0"),
+        ];
+
+        let reports = lifecycle_reports(&rows);
+        assert_eq!(reports.len(), 2);
+
+        let long = &reports[0];
+        assert_eq!(long.side, Side::Buy);
+        assert_eq!(long.max_size, d("2"));
+        assert_eq!(long.entry_px, d("105"));
+        assert_eq!(long.exit_px, Some(d("120")));
+        assert_eq!(long.realized_pnl, d("30"));
+        assert_eq!(long.fees.fees_paid, d("0.3"));
+        assert_eq!(long.trade_count, 3);
+
+        let short = &reports[1];
+        assert_eq!(short.side, Side::Sell);
+        assert_eq!(short.max_size, d("3"));
+        assert_eq!(short.exit_px, None);
+    }
+}
@@ -1,16 +1,55 @@
 //! Pure computation engine(s) for deterministic ledger logic.
 
-use crate::domain::{Address, Coin, Decimal, TimeMs};
+use crate::domain::{Address, Coin, Decimal, FillType, TimeMs};
 
 pub mod builder_logs_matcher;
+pub mod compile_hooks;
 pub mod equity;
+pub mod execution;
+pub mod fees;
+#[cfg(test)]
+pub mod generators;
+pub mod heuristic;
+pub mod invariants;
+pub mod lifecycles;
+pub mod orders;
+pub mod performance;
 pub mod position_tracker;
+#[cfg(test)]
+mod properties;
+pub mod reconcile;
+pub mod risk;
+pub mod series;
+pub mod stats;
+pub mod strategy;
 pub mod taint;
 
-pub use builder_logs_matcher::{BuilderLogsIndex, MatchTolerances};
-pub use equity::EquityResolver;
+pub use builder_logs_matcher::{BuilderLogsIndex, LogsMatch, MatchTolerances};
+pub use compile_hooks::{CoinAdjustment, CompileHook, CompileHookRegistry, SizeRebase};
+pub use equity::{
+    mark_to_market, xirr, EquityCurve, EquityResolver, HeldPosition, MarkedEquityPoint,
+    ReturnMethod,
+};
+pub use heuristic::HeuristicAttributor;
+pub use execution::{
+    execution_quality, slippage_bps, ExecutionQuality, FillSlippage, LogsMedianPrice, MarkPriceFeed,
+    ReferencePrice,
+};
+pub use fees::{audit_fee_allocation, FeeAuditIssue, FeeTotals};
+pub use invariants::{Invariant, InvariantChecker, InvariantViolation};
+pub use lifecycles::{lifecycle_reports, LifecycleReport};
+pub use orders::{order_reports, OrderKey, OrderReport};
+pub use performance::{ClosedTrade, PerformanceStats};
 pub use position_tracker::{PositionState, PositionTracker};
-pub use taint::{BuilderOnlyFilter, TaintComputer, TaintInfo};
+pub use reconcile::{PnlReconciler, ReconciliationIssue};
+pub use risk::{risk_by_day, RiskDay};
+pub use series::{bucket_net_size, SizeBucket};
+pub use strategy::{assign_strategy, LifecycleFacts, LifecycleTag, StrategyRule};
+pub use stats::{revenue_buckets, volume_buckets, FillStats, RevenueBucket, StatsInterval, VolumeBucket};
+pub use taint::{
+    filter_attributed_fills, BuilderOnlyFilter, FillLevelFiltered, TaintComputer, TaintInfo,
+    TaintMode,
+};
 
 /// A lifecycle from position open to close.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -20,6 +59,8 @@ pub struct Lifecycle {
     pub coin: Coin,
     pub start_time_ms: TimeMs,
     pub end_time_ms: Option<TimeMs>, // None if still open
+    /// Set when a liquidation or ADL fill closed the position.
+    pub closed_by: Option<FillType>,
 }
 
 /// A snapshot of position state after a fill.
@@ -41,6 +82,8 @@ pub struct Effect {
     pub qty: Decimal,      // Absolute quantity
     pub notional: Decimal, // px * qty
     pub fee: Decimal,      // Allocated fee
+    /// Allocated builder fee; zero for fills without one.
+    pub builder_fee: Decimal,
     pub closed_pnl: Decimal,
 }
 
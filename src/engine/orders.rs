@@ -0,0 +1,152 @@
+//! Grouping fills into the orders that produced them.
+//!
+//! Hyperliquid reports executions, not orders: one resting order can fill many times, and a
+//! TWAP sends a new child order (with its own `oid`) for every slice. Fills of a TWAP are
+//! grouped by its id, other fills by `oid`. A fill with neither is an order of its own.
+
+use super::FeeTotals;
+use crate::domain::{Coin, Decimal, Fill, Side, TimeMs};
+use std::collections::HashMap;
+
+/// What a group of fills has in common.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OrderKey {
+    Twap(i64),
+    Oid(i64),
+    /// A fill without `oid` or TWAP id, by fill key.
+    Fill(String),
+}
+
+impl OrderKey {
+    pub fn of(fill: &Fill) -> Self {
+        match (fill.twap_id, fill.oid) {
+            (Some(twap_id), _) => OrderKey::Twap(twap_id),
+            (None, Some(oid)) => OrderKey::Oid(oid),
+            (None, None) => OrderKey::Fill(fill.fill_key.clone()),
+        }
+    }
+}
+
+/// Aggregate execution of one logical order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderReport {
+    pub key: OrderKey,
+    pub coin: Coin,
+    /// Side of the first fill. Orders only trade one way.
+    pub side: Side,
+    pub first_fill_ms: TimeMs,
+    pub last_fill_ms: TimeMs,
+    pub size: Decimal,
+    pub notional: Decimal,
+    /// Volume-weighted price of the fills.
+    pub avg_px: Decimal,
+    pub fees: FeeTotals,
+    pub closed_pnl: Decimal,
+    pub fill_count: usize,
+}
+
+impl OrderReport {
+    fn new(key: OrderKey, fill: &Fill) -> Self {
+        Self {
+            key,
+            coin: fill.coin.clone(),
+            side: fill.side,
+            first_fill_ms: fill.time_ms,
+            last_fill_ms: fill.time_ms,
+            size: Decimal::zero(),
+            notional: Decimal::zero(),
+            avg_px: Decimal::zero(),
+            fees: FeeTotals::default(),
+            closed_pnl: Decimal::zero(),
+            fill_count: 0,
+        }
+    }
+
+    fn add(&mut self, fill: &Fill) {
+        self.first_fill_ms = self.first_fill_ms.min(fill.time_ms);
+        self.last_fill_ms = self.last_fill_ms.max(fill.time_ms);
+        self.size = self.size + fill.sz;
+        self.notional = self.notional + fill.px * fill.sz;
+        self.avg_px = self.notional / self.size;
+        self.fees.add(fill.fee);
+        self.closed_pnl = self.closed_pnl + fill.closed_pnl;
+        self.fill_count += 1;
+    }
+}
+
+/// Group fills into orders, ordered by first fill time.
+///
+/// `oid` and TWAP ids are only unique per coin, so the coin is part of the grouping.
+pub fn order_reports(fills: &[Fill]) -> Vec<OrderReport> {
+    let mut index: HashMap<(Coin, OrderKey), usize> = HashMap::new();
+    let mut reports: Vec<OrderReport> = Vec::new();
+    for fill in fills {
+        let key = OrderKey::of(fill);
+        let i = *index
+            .entry((fill.coin.clone(), key.clone()))
+            .or_insert_with(|| {
+                reports.push(OrderReport::new(key, fill));
+                reports.len() - 1
+            });
+        reports[i].add(fill);
+    }
+    // Stable, so orders starting in the same millisecond keep fill order.
+    reports.sort_by_key(|r| r.first_fill_ms);
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Address;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, sz: &str, px: &str, tid: i64, oid: Option<i64>) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            d(px),
+            d(sz),
+            d("0.1"),
+            Decimal::zero(),
+            None,
+            Some(tid),
+            oid,
+        )
+    }
+
+    #[test]
+    fn test_order_reports_group_by_oid_and_twap() {
+        let fills = vec![
+            fill(1000, "1", "100", 1, Some(7)),
+            fill(1500, "1", "200", 2, Some(8)).with_twap_id(3),
+            fill(2000, "3", "104", 3, Some(7)),
+            fill(2500, "1", "210", 4, Some(9)).with_twap_id(3),
+            fill(3000, "1", "50", 5, None),
+        ];
+
+        let reports = order_reports(&fills);
+        assert_eq!(reports.len(), 3);
+
+        assert_eq!(reports[0].key, OrderKey::Oid(7));
+        assert_eq!(reports[0].size, d("4"));
+        assert_eq!(reports[0].avg_px, d("103"));
+        assert_eq!(reports[0].fees.fees_paid, d("0.2"));
+        assert_eq!(reports[0].fill_count, 2);
+        assert_eq!(reports[0].last_fill_ms, TimeMs::new(2000));
+
+        // Slices of a TWAP have their own oids but make up one order.
+        assert_eq!(reports[1].key, OrderKey::Twap(3));
+        assert_eq!(reports[1].avg_px, d("205"));
+        assert_eq!(reports[1].fill_count, 2);
+
+        assert_eq!(reports[2].key, OrderKey::Fill(fills[4].fill_key.clone()));
+        assert_eq!(reports[2].fill_count, 1);
+    }
+}
@@ -0,0 +1,252 @@
+//! Win rate, payoff, drawdown, and risk-adjusted return over closed lifecycles.
+//!
+//! Everything is computed in exact decimal arithmetic from inputs in a fixed order, so the
+//! same history always yields byte-identical results.
+
+use crate::domain::{Decimal, TimeMs};
+use rust_decimal::Decimal as RustDecimal;
+
+/// Newton iterations for [`sqrt`]; convergence takes far fewer at 28 digits.
+const SQRT_MAX_ITERATIONS: usize = 100;
+
+/// Realized result of one closed lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosedTrade {
+    pub close_ms: TimeMs,
+    pub pnl: Decimal,
+}
+
+/// Performance over a set of closed lifecycles.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PerformanceStats {
+    pub closed_count: usize,
+    /// Lifecycles with positive PnL; breakeven ones are neither wins nor losses.
+    pub win_count: usize,
+    pub loss_count: usize,
+    /// Wins as a percentage of closed lifecycles.
+    pub win_rate_pct: Option<Decimal>,
+    pub avg_win: Option<Decimal>,
+    /// Mean PnL of losing lifecycles (negative).
+    pub avg_loss: Option<Decimal>,
+    /// Gross wins over gross losses; `None` without losses.
+    pub profit_factor: Option<Decimal>,
+    /// Largest peak-to-trough fall of cumulative realized PnL, as a positive amount.
+    pub max_drawdown: Decimal,
+    /// `max_drawdown` as a percentage of equity at the peak it fell from; `None` when that
+    /// equity is not positive.
+    pub max_drawdown_pct: Option<Decimal>,
+    /// Mean per-lifecycle PnL over its population standard deviation. Not annualized;
+    /// `None` for fewer than two lifecycles or no dispersion.
+    pub sharpe_ratio: Option<Decimal>,
+}
+
+impl PerformanceStats {
+    /// Compute statistics for `trades`, which must be in close order.
+    ///
+    /// The equity series starts at `starting_equity` and moves with each deposit and each
+    /// trade's PnL; deposits at the same time as a close are applied first. Deposits only
+    /// affect `max_drawdown_pct`.
+    pub fn compute(
+        trades: &[ClosedTrade],
+        starting_equity: Decimal,
+        deposits: &[(TimeMs, Decimal)],
+    ) -> Self {
+        let mut stats = PerformanceStats {
+            closed_count: trades.len(),
+            ..Default::default()
+        };
+        if trades.is_empty() {
+            return stats;
+        }
+
+        let mut gross_win = Decimal::zero();
+        let mut gross_loss = Decimal::zero();
+        for trade in trades {
+            if trade.pnl.is_positive() {
+                stats.win_count += 1;
+                gross_win = gross_win + trade.pnl;
+            } else if trade.pnl.is_negative() {
+                stats.loss_count += 1;
+                gross_loss = gross_loss + trade.pnl;
+            }
+        }
+
+        stats.win_rate_pct =
+            Some(count(stats.win_count) / count(trades.len()) * Decimal::hundred());
+        stats.avg_win = (stats.win_count > 0).then(|| gross_win / count(stats.win_count));
+        stats.avg_loss = (stats.loss_count > 0).then(|| gross_loss / count(stats.loss_count));
+        stats.profit_factor = (stats.loss_count > 0).then(|| gross_win / gross_loss.abs());
+
+        let (max_drawdown, max_drawdown_pct) = drawdown(trades, starting_equity, deposits);
+        stats.max_drawdown = max_drawdown;
+        stats.max_drawdown_pct = max_drawdown_pct;
+        stats.sharpe_ratio = sharpe_ratio(trades);
+        stats
+    }
+}
+
+fn count(n: usize) -> Decimal {
+    Decimal::new(RustDecimal::from(n))
+}
+
+fn drawdown(
+    trades: &[ClosedTrade],
+    starting_equity: Decimal,
+    deposits: &[(TimeMs, Decimal)],
+) -> (Decimal, Option<Decimal>) {
+    let mut deposits = deposits.iter().peekable();
+    let mut equity = starting_equity;
+    let mut cumulative = Decimal::zero();
+    let mut peak = Decimal::zero();
+    let mut peak_equity = starting_equity;
+    let mut max_drawdown = Decimal::zero();
+    let mut max_drawdown_pct = None;
+
+    for trade in trades {
+        while let Some((_, amount)) = deposits.next_if(|(t, _)| *t <= trade.close_ms) {
+            equity = equity + *amount;
+        }
+        equity = equity + trade.pnl;
+        cumulative = cumulative + trade.pnl;
+
+        if cumulative > peak {
+            peak = cumulative;
+            peak_equity = equity;
+        }
+        let drawdown = peak - cumulative;
+        if drawdown > max_drawdown {
+            max_drawdown = drawdown;
+            max_drawdown_pct = peak_equity
+                .is_positive()
+                .then(|| drawdown / peak_equity * Decimal::hundred());
+        }
+    }
+
+    (max_drawdown, max_drawdown_pct)
+}
+
+fn sharpe_ratio(trades: &[ClosedTrade]) -> Option<Decimal> {
+    if trades.len() < 2 {
+        return None;
+    }
+    let n = count(trades.len());
+    let mean = trades
+        .iter()
+        .fold(Decimal::zero(), |acc, t| acc + t.pnl)
+        / n;
+    let variance = trades.iter().fold(Decimal::zero(), |acc, t| {
+        let diff = t.pnl - mean;
+        acc + diff * diff
+    }) / n;
+    let std_dev = sqrt(variance)?;
+    (!std_dev.is_zero()).then(|| mean / std_dev)
+}
+
+/// Square root by Newton's method; `None` for negative input.
+fn sqrt(value: Decimal) -> Option<Decimal> {
+    let x = value.inner();
+    if x.is_sign_negative() && !x.is_zero() {
+        return None;
+    }
+    if x.is_zero() {
+        return Some(Decimal::zero());
+    }
+
+    let two = RustDecimal::TWO;
+    let mut guess = if x > RustDecimal::ONE { x / two } else { RustDecimal::ONE };
+    for _ in 0..SQRT_MAX_ITERATIONS {
+        let next = (guess + x / guess) / two;
+        if next == guess {
+            break;
+        }
+        guess = next;
+    }
+    Some(Decimal::new(guess))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn trades(pnls: &[&str]) -> Vec<ClosedTrade> {
+        pnls.iter()
+            .enumerate()
+            .map(|(i, pnl)| ClosedTrade {
+                close_ms: TimeMs::new(1_000 * (i as i64 + 1)),
+                pnl: d(pnl),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_history() {
+        let stats = PerformanceStats::compute(&[], d("1000"), &[]);
+        assert_eq!(stats.closed_count, 0);
+        assert_eq!(stats.win_rate_pct, None);
+        assert_eq!(stats.max_drawdown, Decimal::zero());
+        assert_eq!(stats.sharpe_ratio, None);
+    }
+
+    #[test]
+    fn test_win_rate_and_payoff() {
+        let stats = PerformanceStats::compute(&trades(&["100", "-50", "0", "300"]), d("1000"), &[]);
+        assert_eq!(stats.win_count, 2);
+        assert_eq!(stats.loss_count, 1);
+        assert_eq!(stats.win_rate_pct, Some(d("50")));
+        assert_eq!(stats.avg_win, Some(d("200")));
+        assert_eq!(stats.avg_loss, Some(d("-50")));
+        assert_eq!(stats.profit_factor, Some(d("8")));
+    }
+
+    #[test]
+    fn test_no_losses_has_no_profit_factor() {
+        let stats = PerformanceStats::compute(&trades(&["10", "20"]), d("0"), &[]);
+        assert_eq!(stats.profit_factor, None);
+        assert_eq!(stats.avg_loss, None);
+    }
+
+    #[test]
+    fn test_max_drawdown_from_peak() {
+        // Cumulative: 100, 50, 250, 100, 150 -> worst fall 250 -> 100.
+        let stats = PerformanceStats::compute(
+            &trades(&["100", "-50", "200", "-150", "50"]),
+            d("1000"),
+            &[],
+        );
+        assert_eq!(stats.max_drawdown, d("150"));
+        // Equity at the peak: 1000 + 250.
+        assert_eq!(stats.max_drawdown_pct, Some(d("12")));
+    }
+
+    #[test]
+    fn test_deposits_only_move_drawdown_pct() {
+        let deposits = vec![(TimeMs::new(1_500), d("750"))];
+        let stats =
+            PerformanceStats::compute(&trades(&["100", "150", "-200"]), d("1000"), &deposits);
+        assert_eq!(stats.max_drawdown, d("200"));
+        // Peak equity: 1000 + 100 + 750 + 150.
+        assert_eq!(stats.max_drawdown_pct, Some(d("10")));
+    }
+
+    #[test]
+    fn test_sharpe_ratio() {
+        // Mean 2, population std dev 1.
+        let stats = PerformanceStats::compute(&trades(&["1", "3"]), d("0"), &[]);
+        assert_eq!(stats.sharpe_ratio, Some(d("2")));
+
+        let flat = PerformanceStats::compute(&trades(&["5", "5"]), d("0"), &[]);
+        assert_eq!(flat.sharpe_ratio, None);
+    }
+
+    #[test]
+    fn test_sqrt_is_exact_for_squares() {
+        assert_eq!(sqrt(d("2.25")), Some(d("1.5")));
+        assert_eq!(sqrt(d("1000000")), Some(d("1000")));
+        assert_eq!(sqrt(d("-1")), None);
+    }
+}
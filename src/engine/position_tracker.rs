@@ -1,4 +1,4 @@
-use crate::domain::{Decimal, Fill, Side};
+use crate::domain::{Decimal, DecimalError, Fill, FillType, Side};
 
 use super::{Effect, EffectType, Lifecycle, Snapshot};
 use sha2::{Digest, Sha256};
@@ -78,6 +78,35 @@ impl PositionTracker {
         }
     }
 
+    /// Process `fill` unless one of the values it derives would overflow, in which case the
+    /// tracker is left untouched.
+    pub fn try_process_fill(&mut self, fill: &Fill) -> Result<(), DecimalError> {
+        self.check_fill(fill)?;
+        self.process_fill(fill);
+        Ok(())
+    }
+
+    /// Evaluate, with checked arithmetic, every value [`process_fill`](Self::process_fill)
+    /// would derive from `fill`.
+    pub fn check_fill(&self, fill: &Fill) -> Result<(), DecimalError> {
+        let old_size = self.state.net_size;
+        let new_size = old_size.checked_add(self.compute_signed_qty(fill))?;
+        fill.px.checked_mul(fill.sz)?;
+
+        if self.is_flip(old_size, new_size) {
+            let close_ratio = old_size.abs().checked_div(fill.sz)?;
+            fill.fee.checked_mul(close_ratio)?;
+            fill.builder_fee
+                .unwrap_or_else(Decimal::zero)
+                .checked_mul(close_ratio)?;
+        } else if !old_size.is_zero() && new_size.abs() > old_size.abs() {
+            let old_value = old_size.abs().checked_mul(self.state.avg_entry_px)?;
+            let new_value = (new_size - old_size).abs().checked_mul(fill.px)?;
+            old_value.checked_add(new_value)?.checked_div(new_size.abs())?;
+        }
+        Ok(())
+    }
+
     /// Compute signed quantity: Buy = +sz, Sell = -sz.
     fn compute_signed_qty(&self, fill: &Fill) -> Decimal {
         match fill.side {
@@ -104,6 +133,7 @@ impl PositionTracker {
             coin: fill.coin.clone(),
             start_time_ms: fill.time_ms,
             end_time_ms: None,
+            closed_by: None,
         });
 
         self.state.net_size = new_size;
@@ -117,6 +147,7 @@ impl PositionTracker {
             qty: fill.sz,
             notional: fill.px * fill.sz,
             fee: fill.fee,
+            builder_fee: fill.builder_fee.unwrap_or_else(Decimal::zero),
             closed_pnl: fill.closed_pnl,
         });
 
@@ -138,6 +169,7 @@ impl PositionTracker {
 
         if let Some(lifecycle) = self.lifecycles.iter_mut().find(|l| l.id == lifecycle_id) {
             lifecycle.end_time_ms = Some(fill.time_ms);
+            lifecycle.closed_by = forced_close(fill);
         }
 
         self.effects.push(Effect {
@@ -147,6 +179,7 @@ impl PositionTracker {
             qty: fill.sz,
             notional: fill.px * fill.sz,
             fee: fill.fee,
+            builder_fee: fill.builder_fee.unwrap_or_else(Decimal::zero),
             closed_pnl: fill.closed_pnl,
         });
 
@@ -176,6 +209,9 @@ impl PositionTracker {
         let close_fee = fill.fee * close_ratio;
         let close_pnl = fill.closed_pnl;
         let open_fee = fill.fee - close_fee;
+        let builder_fee = fill.builder_fee.unwrap_or_else(Decimal::zero);
+        let close_builder_fee = builder_fee * close_ratio;
+        let open_builder_fee = builder_fee - close_builder_fee;
 
         if let Some(lifecycle) = self
             .lifecycles
@@ -183,6 +219,7 @@ impl PositionTracker {
             .find(|lifecycle| lifecycle.id == old_lifecycle_id)
         {
             lifecycle.end_time_ms = Some(fill.time_ms);
+            lifecycle.closed_by = forced_close(fill);
         }
 
         self.effects.push(Effect {
@@ -192,6 +229,7 @@ impl PositionTracker {
             qty: close_qty,
             notional: fill.px * close_qty,
             fee: close_fee,
+            builder_fee: close_builder_fee,
             closed_pnl: close_pnl,
         });
 
@@ -211,6 +249,7 @@ impl PositionTracker {
             coin: fill.coin.clone(),
             start_time_ms: fill.time_ms,
             end_time_ms: None,
+            closed_by: None,
         });
 
         self.effects.push(Effect {
@@ -220,6 +259,7 @@ impl PositionTracker {
             qty: open_qty,
             notional: fill.px * open_qty,
             fee: open_fee,
+            builder_fee: open_builder_fee,
             closed_pnl: Decimal::zero(),
         });
 
@@ -258,6 +298,7 @@ impl PositionTracker {
                 qty: fill.sz,
                 notional: fill.px * fill.sz,
                 fee: fill.fee,
+                builder_fee: fill.builder_fee.unwrap_or_else(Decimal::zero),
                 closed_pnl: fill.closed_pnl,
             });
         } else {
@@ -268,6 +309,7 @@ impl PositionTracker {
                 qty: fill.sz,
                 notional: fill.px * fill.sz,
                 fee: fill.fee,
+                builder_fee: fill.builder_fee.unwrap_or_else(Decimal::zero),
                 closed_pnl: fill.closed_pnl,
             });
         }
@@ -310,6 +352,11 @@ impl Default for PositionTracker {
     }
 }
 
+/// The fill type to record on a lifecycle the fill closed; `None` for regular trades.
+fn forced_close(fill: &Fill) -> Option<FillType> {
+    (!fill.fill_type.is_trade()).then_some(fill.fill_type)
+}
+
 /// Generate a deterministic lifecycle ID from a fill key using SHA256.
 ///
 /// # Design Notes
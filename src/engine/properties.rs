@@ -0,0 +1,154 @@
+//! Property tests running random fill sequences through the [`PositionTracker`].
+
+use super::generators::fill_sequence;
+use super::{Effect, EffectType, InvariantChecker, PositionTracker};
+use crate::domain::{Decimal, Fill, Side};
+use proptest::prelude::*;
+
+fn signed(side: Side, qty: Decimal) -> Decimal {
+    match side {
+        Side::Buy => qty,
+        Side::Sell => -qty,
+    }
+}
+
+/// Effects grouped by the fill that produced them, in fill order.
+fn effects_by_fill<'a>(fills: &'a [Fill], effects: &'a [Effect]) -> Vec<(&'a Fill, Vec<&'a Effect>)> {
+    fills
+        .iter()
+        .map(|fill| {
+            let mine = effects.iter().filter(|e| e.fill_key == fill.fill_key).collect();
+            (fill, mine)
+        })
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    /// Every effect is followed by a snapshot, and each snapshot's net size is the previous
+    /// one moved by that effect, ending at the sum of the signed fill sizes.
+    #[test]
+    fn prop_net_size_continuous_across_snapshots(fills in fill_sequence(60)) {
+        let mut tracker = PositionTracker::new();
+        for fill in &fills {
+            tracker.process_fill(fill);
+        }
+        let final_net = tracker.state.net_size;
+        let (_, snapshots, effects) = tracker.into_outputs();
+        prop_assert_eq!(snapshots.len(), effects.len());
+
+        let sides: std::collections::HashMap<&str, Side> =
+            fills.iter().map(|f| (f.fill_key.as_str(), f.side)).collect();
+        let mut net = Decimal::zero();
+        for (effect, snapshot) in effects.iter().zip(&snapshots) {
+            net = net + signed(sides[effect.fill_key.as_str()], effect.qty);
+            prop_assert_eq!(snapshot.net_size, net);
+            prop_assert_eq!(snapshot.lifecycle_id, effect.lifecycle_id);
+            if net.is_zero() {
+                prop_assert_eq!(snapshot.avg_entry_px, Decimal::zero());
+            }
+        }
+
+        let expected = fills.iter().fold(Decimal::zero(), |acc, f| acc + signed(f.side, f.sz));
+        prop_assert_eq!(net, expected);
+        prop_assert_eq!(final_net, expected);
+    }
+
+    /// Effects of each fill add up to its size, notional and fee.
+    #[test]
+    fn prop_effects_sum_to_fill(fills in fill_sequence(60)) {
+        let mut tracker = PositionTracker::new();
+        let mut checker = InvariantChecker::new();
+        for fill in &fills {
+            tracker.process_fill(fill);
+        }
+        checker.check_fills(&fills, tracker.get_effects());
+        let violations = checker.finish(tracker.get_lifecycles());
+        prop_assert!(violations.is_empty(), "{:?}", violations);
+
+        for (fill, effects) in effects_by_fill(&fills, tracker.get_effects()) {
+            let qty = effects.iter().fold(Decimal::zero(), |acc, e| acc + e.qty);
+            let fee = effects.iter().fold(Decimal::zero(), |acc, e| acc + e.fee);
+            prop_assert_eq!(qty, fill.sz);
+            prop_assert_eq!(fee, fill.fee);
+            prop_assert!(effects.iter().all(|e| e.qty.is_positive()));
+        }
+    }
+
+    /// A fill crossing zero closes the old lifecycle with the old size and opens a new one
+    /// with the remainder; any other fill has a single effect.
+    #[test]
+    fn prop_flips_decompose(fills in fill_sequence(60)) {
+        let mut tracker = PositionTracker::new();
+        for fill in &fills {
+            tracker.process_fill(fill);
+        }
+        let snapshots = tracker.get_snapshots();
+
+        let mut net = Decimal::zero();
+        let mut snapshot_idx = 0;
+        for (fill, effects) in effects_by_fill(&fills, tracker.get_effects()) {
+            let new_net = net + signed(fill.side, fill.sz);
+            let flip = !net.is_zero() && !new_net.is_zero() && net.is_positive() != new_net.is_positive();
+            if flip {
+                prop_assert_eq!(effects.len(), 2);
+                prop_assert_eq!(effects[0].effect_type, EffectType::Close);
+                prop_assert_eq!(effects[0].qty, net.abs());
+                prop_assert_eq!(effects[1].effect_type, EffectType::Open);
+                prop_assert_eq!(effects[1].qty, new_net.abs());
+                prop_assert_ne!(effects[0].lifecycle_id, effects[1].lifecycle_id);
+                prop_assert_eq!(effects[1].closed_pnl, Decimal::zero());
+                prop_assert_eq!(snapshots[snapshot_idx].seq, 0);
+                prop_assert_eq!(snapshots[snapshot_idx].net_size, Decimal::zero());
+                prop_assert_eq!(snapshots[snapshot_idx + 1].seq, 1);
+                prop_assert_eq!(snapshots[snapshot_idx + 1].avg_entry_px, fill.px);
+            } else {
+                prop_assert_eq!(effects.len(), 1);
+                let expected = if new_net.abs() > net.abs() { EffectType::Open } else { EffectType::Close };
+                prop_assert_eq!(effects[0].effect_type, expected);
+            }
+            snapshot_idx += effects.len();
+            net = new_net;
+        }
+    }
+
+    /// Lifecycles follow each other without overlap: each starts with an open, every one
+    /// but the last is closed at the fill that flattened it, and only a non-flat position
+    /// leaves a lifecycle open.
+    #[test]
+    fn prop_lifecycle_boundaries(fills in fill_sequence(60)) {
+        let mut tracker = PositionTracker::new();
+        for fill in &fills {
+            tracker.process_fill(fill);
+        }
+        let final_net = tracker.state.net_size;
+        let (lifecycles, snapshots, effects) = tracker.into_outputs();
+
+        for pair in lifecycles.windows(2) {
+            let end = pair[0].end_time_ms;
+            prop_assert!(end.is_some(), "lifecycle {} followed by another is still open", pair[0].id);
+            prop_assert!(end.unwrap() <= pair[1].start_time_ms);
+        }
+        match lifecycles.last() {
+            Some(last) => prop_assert_eq!(last.end_time_ms.is_none(), !final_net.is_zero()),
+            None => prop_assert!(final_net.is_zero()),
+        }
+
+        for lifecycle in &lifecycles {
+            let mine: Vec<&Effect> = effects.iter().filter(|e| e.lifecycle_id == lifecycle.id).collect();
+            prop_assert!(!mine.is_empty());
+            prop_assert_eq!(mine[0].effect_type, EffectType::Open);
+
+            let last_snapshot = snapshots.iter().rfind(|s| s.lifecycle_id == lifecycle.id).unwrap();
+            match lifecycle.end_time_ms {
+                Some(end) => {
+                    prop_assert_eq!(mine.last().unwrap().effect_type, EffectType::Close);
+                    prop_assert_eq!(last_snapshot.net_size, Decimal::zero());
+                    prop_assert_eq!(last_snapshot.time_ms, end);
+                }
+                None => prop_assert!(!last_snapshot.net_size.is_zero()),
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Cross-check of exchange-reported `closed_pnl` against PnL recomputed by the tracker.
+//!
+//! Every close is priced against the position's average entry before the fill, the same
+//! accounting Hyperliquid uses, and summed per lifecycle. A lifecycle whose reported and
+//! recomputed PnL disagree by more than the tolerance points at either bad upstream data
+//! (missing or reordered fills) or a bug in our own compile.
+
+use super::{Effect, Lifecycle, PositionState};
+use crate::domain::{Decimal, Fill, Side, TimeMs};
+use std::collections::{BTreeMap, HashMap};
+
+/// Largest per-lifecycle difference accepted as rounding: `0.01`.
+const DEFAULT_TOLERANCE_UNITS: i64 = 1_000_000;
+
+/// A lifecycle whose reported realized PnL differs from the recomputed one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciliationIssue {
+    pub lifecycle_id: i64,
+    pub start_time_ms: TimeMs,
+    /// Sum of the exchange's `closed_pnl` over the lifecycle's effects.
+    pub reported_pnl: Decimal,
+    /// Sum of `(px - avgEntryPx) * qty` over the lifecycle's closes, sign-adjusted for shorts.
+    pub recomputed_pnl: Decimal,
+    /// `reported_pnl - recomputed_pnl`.
+    pub difference: Decimal,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct LifecyclePnl {
+    reported: Decimal,
+    recomputed: Decimal,
+}
+
+/// Accumulates reported and recomputed PnL per lifecycle over fills processed in chunks.
+#[derive(Debug)]
+pub struct PnlReconciler {
+    tolerance: Decimal,
+    lifecycles: HashMap<i64, LifecyclePnl>,
+}
+
+impl Default for PnlReconciler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PnlReconciler {
+    pub fn new() -> Self {
+        Self {
+            tolerance: Decimal::from_scaled_units(DEFAULT_TOLERANCE_UNITS),
+            lifecycles: HashMap::new(),
+        }
+    }
+
+    /// Flag only differences larger than `tolerance` in absolute value.
+    pub fn with_tolerance(mut self, tolerance: Decimal) -> Self {
+        self.tolerance = tolerance.abs();
+        self
+    }
+
+    /// Recompute the PnL `fill` realizes against `before`, the position just before the
+    /// tracker processes it.
+    pub fn record_fill(&mut self, before: &PositionState, fill: &Fill) {
+        let reduces = match fill.side {
+            Side::Buy => before.is_short(),
+            Side::Sell => before.is_long(),
+        };
+        let Some(lifecycle_id) = before.lifecycle_id.filter(|_| reduces) else {
+            return;
+        };
+
+        // A flip only realizes PnL on the part that closes the old position.
+        let qty = fill.sz.min(before.net_size.abs());
+        let pnl = if before.is_long() {
+            (fill.px - before.avg_entry_px) * qty
+        } else {
+            (before.avg_entry_px - fill.px) * qty
+        };
+        let entry = self.lifecycles.entry(lifecycle_id).or_default();
+        entry.recomputed = entry.recomputed + pnl;
+    }
+
+    /// Add the exchange-reported PnL carried by the tracker's effects.
+    pub fn record_effects(&mut self, effects: &[Effect]) {
+        for effect in effects {
+            let entry = self.lifecycles.entry(effect.lifecycle_id).or_default();
+            entry.reported = entry.reported + effect.closed_pnl;
+        }
+    }
+
+    /// Lifecycles outside the tolerance, ordered by id.
+    pub fn finish(self, lifecycles: &[Lifecycle]) -> Vec<ReconciliationIssue> {
+        let starts: HashMap<i64, TimeMs> =
+            lifecycles.iter().map(|l| (l.id, l.start_time_ms)).collect();
+        let ordered: BTreeMap<i64, LifecyclePnl> = self.lifecycles.into_iter().collect();
+
+        ordered
+            .into_iter()
+            .filter_map(|(lifecycle_id, pnl)| {
+                let difference = pnl.reported - pnl.recomputed;
+                (difference.abs() > self.tolerance).then(|| ReconciliationIssue {
+                    lifecycle_id,
+                    start_time_ms: starts.get(&lifecycle_id).copied().unwrap_or_default(),
+                    reported_pnl: pnl.reported,
+                    recomputed_pnl: pnl.recomputed,
+                    difference,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Coin};
+    use crate::engine::PositionTracker;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn fill(time_ms: i64, side: Side, px: &str, sz: &str, closed_pnl: &str, tid: i64) -> Fill {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            side,
+            d(px),
+            d(sz),
+            d("0.1"),
+            d(closed_pnl),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    fn reconcile(fills: &[Fill], tolerance: &str) -> Vec<ReconciliationIssue> {
+        let mut tracker = PositionTracker::new();
+        let mut reconciler = PnlReconciler::new().with_tolerance(d(tolerance));
+        for f in fills {
+            let before = tracker.get_effects().len();
+            reconciler.record_fill(&tracker.state, f);
+            tracker.process_fill(f);
+            reconciler.record_effects(&tracker.get_effects()[before..]);
+        }
+        reconciler.finish(tracker.get_lifecycles())
+    }
+
+    #[test]
+    fn test_matching_pnl_has_no_issues() {
+        let fills = [
+            fill(1000, Side::Buy, "100", "1", "0", 1),
+            fill(2000, Side::Buy, "110", "1", "0", 2),
+            // Avg entry 105: closing 1 at 120 realizes 15.
+            fill(3000, Side::Sell, "120", "1", "15", 3),
+            // Flip: closes 1 at 90 for -15 and opens a short of 2.
+            fill(4000, Side::Sell, "90", "3", "-15", 4),
+            // Short from 90 covered at 80 realizes 20.
+            fill(5000, Side::Buy, "80", "2", "20", 5),
+        ];
+        assert!(reconcile(&fills, "0.01").is_empty());
+    }
+
+    #[test]
+    fn test_flags_lifecycles_outside_tolerance() {
+        let fills = [
+            fill(1000, Side::Buy, "100", "2", "0", 1),
+            // Recomputed 10 + 20; reported 29.995 is within 0.01.
+            fill(2000, Side::Sell, "110", "1", "10", 2),
+            fill(3000, Side::Sell, "120", "1", "19.995", 3),
+            fill(4000, Side::Sell, "100", "1", "0", 4),
+            // Covering the short at 90 realizes 10, reported as 12.
+            fill(5000, Side::Buy, "90", "1", "12", 5),
+        ];
+        let issues = reconcile(&fills, "0.01");
+
+        assert_eq!(issues.len(), 1);
+        let issue = &issues[0];
+        assert_eq!(issue.start_time_ms, TimeMs::new(4000));
+        assert_eq!(issue.reported_pnl, d("12"));
+        assert_eq!(issue.recomputed_pnl, d("10"));
+        assert_eq!(issue.difference, d("2"));
+    }
+}
@@ -0,0 +1,187 @@
+//! Historical exposure and drawdown, per UTC day, from the marked equity curve.
+//!
+//! Exposure at a point is the notional of the positions open then, each valued at the mark
+//! of that hour or, without one, at its average entry price. Drawdown is measured from the
+//! highest marked equity seen since the start of the window, so a day that never recovers
+//! the previous high reports how far below it the account went.
+
+use super::equity::{HeldPosition, MarkedEquityPoint};
+use super::series::bucket_start;
+use super::stats::StatsInterval;
+use crate::domain::{Coin, Decimal, MarkSeries, TimeMs};
+use std::collections::{BTreeMap, HashMap};
+
+/// Exposure and drawdown over one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskDay {
+    pub start_ms: TimeMs,
+    /// Sum of `|size * price|` over open positions at the day's last point.
+    pub gross_exposure: Decimal,
+    /// Sum of `size * price` (shorts negative) at the day's last point.
+    pub net_exposure: Decimal,
+    /// Highest gross exposure at any point of the day.
+    pub peak_gross_exposure: Decimal,
+    /// Marked equity at the day's last valued point.
+    pub equity: Decimal,
+    /// Largest fall of marked equity below its running high during the day.
+    pub max_drawdown: Decimal,
+    /// `max_drawdown` as a percentage of the high it fell from; `None` while that high is
+    /// not positive.
+    pub max_drawdown_pct: Option<Decimal>,
+}
+
+/// Exposure and drawdown of every day `points` (a marked equity curve, in time order) has
+/// a point in; see the module docs.
+///
+/// `positions` are the snapshots of every coin in time order, including those before the
+/// first point. Points whose unrealized PnL is unknown count towards exposure but not
+/// drawdown.
+pub fn risk_by_day(
+    points: &[MarkedEquityPoint],
+    positions: &[HeldPosition],
+    marks: &HashMap<Coin, MarkSeries>,
+) -> Vec<RiskDay> {
+    let day_ms = StatsInterval::Day.width_ms();
+    let mut positions = positions.iter().peekable();
+    let mut held: BTreeMap<&Coin, (Decimal, Decimal)> = BTreeMap::new();
+    let mut high: Option<Decimal> = None;
+    let mut days: Vec<RiskDay> = Vec::new();
+
+    for point in points {
+        while let Some(p) = positions.next_if(|p| p.time_ms <= point.time_ms) {
+            if p.net_size.is_zero() {
+                held.remove(&p.coin);
+            } else {
+                held.insert(&p.coin, (p.net_size, p.avg_entry_px));
+            }
+        }
+
+        let (mut gross, mut net) = (Decimal::zero(), Decimal::zero());
+        for (coin, (net_size, avg_entry_px)) in &held {
+            let px = marks
+                .get(*coin)
+                .and_then(|m| m.at(point.time_ms))
+                .unwrap_or(*avg_entry_px);
+            let notional = *net_size * px;
+            gross = gross + notional.abs();
+            net = net + notional;
+        }
+
+        let start_ms = bucket_start(point.time_ms, day_ms);
+        if days.last().map(|d| d.start_ms) != Some(start_ms) {
+            let equity = days.last().map(|d| d.equity).unwrap_or(point.equity);
+            days.push(RiskDay {
+                start_ms,
+                gross_exposure: gross,
+                net_exposure: net,
+                peak_gross_exposure: gross,
+                equity,
+                max_drawdown: Decimal::zero(),
+                max_drawdown_pct: None,
+            });
+        }
+        let day = days.last_mut().expect("pushed above");
+        day.gross_exposure = gross;
+        day.net_exposure = net;
+        if gross > day.peak_gross_exposure {
+            day.peak_gross_exposure = gross;
+        }
+
+        let Some(unrealized_pnl) = point.unrealized_pnl else {
+            continue;
+        };
+        let equity = point.equity + unrealized_pnl;
+        day.equity = equity;
+        let high = high.get_or_insert(equity);
+        if equity > *high {
+            *high = equity;
+        }
+        let drawdown = *high - equity;
+        if drawdown > day.max_drawdown {
+            day.max_drawdown = drawdown;
+            day.max_drawdown_pct = (*high > Decimal::zero())
+                .then(|| drawdown / *high * Decimal::hundred());
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::MARK_PRICE_BUCKET_MS as HOUR;
+    use std::str::FromStr;
+
+    const DAY: i64 = 24 * HOUR;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn point(time_ms: i64, equity: &str, unrealized_pnl: Option<&str>) -> MarkedEquityPoint {
+        MarkedEquityPoint {
+            time_ms: TimeMs::new(time_ms),
+            equity: d(equity),
+            unrealized_pnl: unrealized_pnl.map(d),
+        }
+    }
+
+    #[test]
+    fn test_risk_by_day_tracks_exposure_and_drawdown_from_running_high() {
+        let btc = Coin::new("BTC".to_string());
+        let positions = vec![
+            HeldPosition {
+                time_ms: TimeMs::new(0),
+                coin: btc.clone(),
+                net_size: d("-2"),
+                avg_entry_px: d("100"),
+            },
+            HeldPosition {
+                time_ms: TimeMs::new(DAY + 2 * HOUR),
+                coin: btc.clone(),
+                net_size: d("0"),
+                avg_entry_px: d("0"),
+            },
+        ];
+        let marks = HashMap::from([(
+            btc,
+            MarkSeries::new(vec![
+                (TimeMs::new(0), d("100")),
+                (TimeMs::new(HOUR), d("90")),
+                (TimeMs::new(DAY), d("110")),
+                // No mark for DAY + HOUR: exposure falls back to the entry price.
+            ]),
+        )]);
+        let points = vec![
+            point(0, "1000", Some("0")),
+            point(HOUR, "1000", Some("20")),
+            point(DAY, "1000", Some("-20")),
+            point(DAY + HOUR, "1000", None),
+            point(DAY + 2 * HOUR, "1010", Some("0")),
+        ];
+
+        let days = risk_by_day(&points, &positions, &marks);
+        assert_eq!(days.len(), 2);
+
+        assert_eq!(days[0].start_ms, TimeMs::new(0));
+        assert_eq!(days[0].peak_gross_exposure, d("200"));
+        assert_eq!(days[0].gross_exposure, d("180"));
+        assert_eq!(days[0].net_exposure, d("-180"));
+        assert_eq!(days[0].equity, d("1020"));
+        assert_eq!(days[0].max_drawdown, Decimal::zero());
+        assert_eq!(days[0].max_drawdown_pct, None);
+
+        assert_eq!(days[1].start_ms, TimeMs::new(DAY));
+        assert_eq!(days[1].peak_gross_exposure, d("220"));
+        assert_eq!(days[1].gross_exposure, Decimal::zero());
+        assert_eq!(days[1].equity, d("1010"));
+        // From the 1020 high of the first day down to 980.
+        assert_eq!(days[1].max_drawdown, d("40"));
+        assert_eq!(days[1].max_drawdown_pct, Some(d("40") / d("1020") * Decimal::hundred()));
+    }
+
+    #[test]
+    fn test_risk_by_day_without_points_is_empty() {
+        assert!(risk_by_day(&[], &[], &HashMap::new()).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Interval-bucketed position size series for charting.
+//!
+//! Snapshots are grouped into fixed buckets aligned to the Unix epoch, so the same history
+//! always produces the same buckets regardless of the requested window. Each bucket keeps
+//! open/high/low/close of net size, which shrinks long histories without hiding spikes.
+
+use crate::domain::{Decimal, TimeMs};
+
+/// Net size statistics for one bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBucket {
+    /// Inclusive bucket start, a multiple of the interval.
+    pub start_ms: TimeMs,
+    /// Net size entering the bucket (the previous close, or the carried-in size).
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    /// Net size after the last snapshot in the bucket.
+    pub close: Decimal,
+    pub snapshot_count: usize,
+}
+
+/// Start of the epoch-aligned bucket containing `time_ms`.
+pub fn bucket_start(time_ms: TimeMs, interval_ms: i64) -> TimeMs {
+    let t = time_ms.as_ms();
+    TimeMs::new(t - t.rem_euclid(interval_ms))
+}
+
+/// Bucket `(time, net_size)` points, which must be in snapshot order.
+///
+/// `carried_in` is the net size before the first point (zero when flat). Buckets without
+/// snapshots are omitted; the position is unchanged through them, so clients can
+/// forward-fill from the previous close.
+///
+/// # Panics
+/// Panics if `interval_ms` is not positive.
+pub fn bucket_net_size(
+    points: &[(TimeMs, Decimal)],
+    interval_ms: i64,
+    carried_in: Decimal,
+) -> Vec<SizeBucket> {
+    assert!(interval_ms > 0, "interval_ms must be positive");
+
+    let mut buckets: Vec<SizeBucket> = Vec::new();
+    let mut current = carried_in;
+    for &(time_ms, net_size) in points {
+        let start_ms = bucket_start(time_ms, interval_ms);
+        match buckets.last_mut() {
+            Some(bucket) if bucket.start_ms == start_ms => {
+                bucket.high = bucket.high.max(net_size);
+                bucket.low = bucket.low.min(net_size);
+                bucket.close = net_size;
+                bucket.snapshot_count += 1;
+            }
+            _ => buckets.push(SizeBucket {
+                start_ms,
+                open: current,
+                high: current.max(net_size),
+                low: current.min(net_size),
+                close: net_size,
+                snapshot_count: 1,
+            }),
+        }
+        current = net_size;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn points(values: &[(i64, &str)]) -> Vec<(TimeMs, Decimal)> {
+        values
+            .iter()
+            .map(|&(t, v)| (TimeMs::new(t), d(v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_bucket_start_is_epoch_aligned() {
+        assert_eq!(bucket_start(TimeMs::new(0), 1000), TimeMs::new(0));
+        assert_eq!(bucket_start(TimeMs::new(1999), 1000), TimeMs::new(1000));
+        assert_eq!(bucket_start(TimeMs::new(2000), 1000), TimeMs::new(2000));
+        assert_eq!(bucket_start(TimeMs::new(-1), 1000), TimeMs::new(-1000));
+    }
+
+    #[test]
+    fn test_buckets_keep_extremes() {
+        // A flip through zero inside one bucket: 1 -> 3 -> 0 -> -2 -> -1.
+        let series = bucket_net_size(
+            &points(&[(100, "1"), (200, "3"), (300, "0"), (300, "-2"), (900, "-1")]),
+            1000,
+            Decimal::zero(),
+        );
+
+        assert_eq!(series.len(), 1);
+        let bucket = &series[0];
+        assert_eq!(bucket.start_ms, TimeMs::new(0));
+        assert_eq!(bucket.open, d("0"));
+        assert_eq!(bucket.high, d("3"));
+        assert_eq!(bucket.low, d("-2"));
+        assert_eq!(bucket.close, d("-1"));
+        assert_eq!(bucket.snapshot_count, 5);
+    }
+
+    #[test]
+    fn test_open_carries_previous_close_across_gaps() {
+        let series = bucket_net_size(
+            &points(&[(1500, "2"), (1600, "4"), (5200, "1")]),
+            1000,
+            d("5"),
+        );
+
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].start_ms, TimeMs::new(1000));
+        assert_eq!(
+            (series[0].open, series[0].high, series[0].low),
+            (d("5"), d("5"), d("2"))
+        );
+        assert_eq!(series[0].close, d("4"));
+
+        // Empty buckets 2000..5000 are skipped; the next open is the last close.
+        assert_eq!(series[1].start_ms, TimeMs::new(5000));
+        assert_eq!(
+            (series[1].open, series[1].high, series[1].low),
+            (d("4"), d("4"), d("1"))
+        );
+        assert_eq!(series[1].close, d("1"));
+    }
+
+    #[test]
+    fn test_empty_points_produce_no_buckets() {
+        assert!(bucket_net_size(&[], 60_000, d("1")).is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+//! Order statistics over fills, computed in exact decimal arithmetic.
+
+use super::FeeTotals;
+use crate::db::repo::{BuilderFeeFill, VolumeFillEffect};
+use crate::domain::{Address, Decimal, Fill, TimeMs};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal as RustDecimal;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Percentile of `values` using linear interpolation between closest ranks.
+///
+/// `pct` is on a 0-100 scale. For `n` sorted values the rank is `(n - 1) * pct / 100`; a
+/// fractional rank interpolates between its neighbours, so the 50th percentile of an
+/// even-sized set is the mean of the two middle values. Values are sorted with a stable
+/// sort and no float conversion happens anywhere, so the result is exact and deterministic.
+///
+/// Returns `None` for an empty input or a `pct` outside `[0, 100]`.
+pub fn percentile(values: &[Decimal], pct: Decimal) -> Option<Decimal> {
+    if values.is_empty() || pct.is_negative() || pct > Decimal::hundred() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort();
+
+    let last_index = RustDecimal::from(sorted.len() - 1);
+    let rank = last_index * pct.inner() / RustDecimal::ONE_HUNDRED;
+    let lower = rank.floor();
+    let lower_index = lower.to_usize()?;
+    let fraction = Decimal::new(rank - lower);
+
+    let low = sorted[lower_index];
+    match sorted.get(lower_index + 1) {
+        Some(&high) if !fraction.is_zero() => Some(low + (high - low) * fraction),
+        _ => Some(low),
+    }
+}
+
+/// Median of `values` (the 50th percentile).
+pub fn median(values: &[Decimal]) -> Option<Decimal> {
+    percentile(values, Decimal::new(RustDecimal::from(50)))
+}
+
+/// Aggregate fill statistics for a window.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FillStats {
+    pub trade_count: usize,
+    /// Sum of `px * sz` over all fills.
+    pub volume: Decimal,
+    /// Net fees and rebates, see [`FeeTotals`].
+    pub fees: FeeTotals,
+    /// Median of `sz` across fills.
+    pub median_trade_size: Option<Decimal>,
+    /// 95th percentile of per-fill notional (`px * sz`).
+    pub p95_notional: Option<Decimal>,
+}
+
+impl FillStats {
+    pub fn from_fills(fills: &[Fill]) -> Self {
+        let sizes: Vec<Decimal> = fills.iter().map(|f| f.sz.abs()).collect();
+        let notionals: Vec<Decimal> = fills.iter().map(|f| (f.px * f.sz).abs()).collect();
+
+        Self {
+            trade_count: fills.len(),
+            volume: notionals.iter().fold(Decimal::zero(), |acc, n| acc + *n),
+            fees: FeeTotals::from_fees(fills.iter().map(|f| f.fee)),
+            median_trade_size: median(&sizes),
+            p95_notional: percentile(&notionals, Decimal::new(RustDecimal::from(95))),
+        }
+    }
+}
+
+/// Calendar interval for [`volume_buckets`], in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsInterval {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+}
+
+impl StatsInterval {
+    pub fn width_ms(self) -> i64 {
+        match self {
+            StatsInterval::Day => DAY_MS,
+            StatsInterval::Week => 7 * DAY_MS,
+        }
+    }
+
+    /// A bucket boundary at or before the epoch: midnight, on a Monday for weeks
+    /// (1969-12-29).
+    pub fn origin_ms(self) -> i64 {
+        match self {
+            StatsInterval::Day => 0,
+            StatsInterval::Week => -3 * DAY_MS,
+        }
+    }
+}
+
+impl FromStr for StatsInterval {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "day" => Ok(StatsInterval::Day),
+            "week" => Ok(StatsInterval::Week),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Volume and fees of the fills in one interval bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeBucket {
+    pub start_ms: TimeMs,
+    pub trade_count: usize,
+    pub volume: Decimal,
+    /// Volume of fills that added liquidity.
+    pub maker_volume: Decimal,
+    /// Volume of fills that took liquidity. Fills whose side of the book is unknown count
+    /// in `volume` only.
+    pub taker_volume: Decimal,
+    pub fees: FeeTotals,
+    pub builder_fees: Decimal,
+}
+
+impl VolumeBucket {
+    fn new(start_ms: TimeMs) -> Self {
+        Self {
+            start_ms,
+            trade_count: 0,
+            volume: Decimal::zero(),
+            maker_volume: Decimal::zero(),
+            taker_volume: Decimal::zero(),
+            fees: FeeTotals::default(),
+            builder_fees: Decimal::zero(),
+        }
+    }
+}
+
+/// Sum effects into buckets. `effects` must be ordered by bucket with the effects of each
+/// fill adjacent, as [`Repository::query_volume_effects`] returns them.
+///
+/// A fill split across lifecycles by a flip has one effect per side; its notional, fee, and
+/// builder fee are split between them, while it counts as one trade.
+///
+/// [`Repository::query_volume_effects`]: crate::db::Repository::query_volume_effects
+pub fn volume_buckets(effects: &[VolumeFillEffect]) -> Vec<VolumeBucket> {
+    let mut buckets: Vec<VolumeBucket> = Vec::new();
+    let mut last_fill_key: Option<&str> = None;
+
+    for effect in effects {
+        if buckets.last().map(|b| b.start_ms) != Some(effect.bucket_start_ms) {
+            buckets.push(VolumeBucket::new(effect.bucket_start_ms));
+            last_fill_key = None;
+        }
+        let bucket = buckets.last_mut().expect("bucket pushed above");
+
+        let notional = effect.notional.abs();
+        bucket.volume = bucket.volume + notional;
+        match effect.crossed {
+            Some(true) => bucket.taker_volume = bucket.taker_volume + notional,
+            Some(false) => bucket.maker_volume = bucket.maker_volume + notional,
+            None => {}
+        }
+        bucket.fees.add(effect.fee);
+        bucket.builder_fees = bucket.builder_fees + effect.builder_fee;
+
+        if last_fill_key != Some(effect.fill_key.as_str()) {
+            bucket.trade_count += 1;
+            last_fill_key = Some(&effect.fill_key);
+        }
+    }
+
+    buckets
+}
+
+/// Builder fee revenue from the fills in one interval bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevenueBucket {
+    pub start_ms: TimeMs,
+    pub fill_count: usize,
+    /// Distinct users with a fill in the bucket.
+    pub user_count: usize,
+    pub volume: Decimal,
+    pub builder_fees: Decimal,
+}
+
+/// Sum builder-attributed fills into buckets. `fills` must be ordered by bucket, as
+/// [`Repository::query_builder_fee_fills`] returns them.
+///
+/// [`Repository::query_builder_fee_fills`]: crate::db::Repository::query_builder_fee_fills
+pub fn revenue_buckets(fills: &[BuilderFeeFill]) -> Vec<RevenueBucket> {
+    let mut buckets: Vec<RevenueBucket> = Vec::new();
+    let mut users: HashSet<&Address> = HashSet::new();
+
+    for fill in fills {
+        if buckets.last().map(|b| b.start_ms) != Some(fill.bucket_start_ms) {
+            users.clear();
+            buckets.push(RevenueBucket {
+                start_ms: fill.bucket_start_ms,
+                fill_count: 0,
+                user_count: 0,
+                volume: Decimal::zero(),
+                builder_fees: Decimal::zero(),
+            });
+        }
+        let bucket = buckets.last_mut().expect("bucket pushed above");
+
+        bucket.fill_count += 1;
+        bucket.volume = bucket.volume + fill.notional.abs();
+        if let Some(builder_fee) = fill.builder_fee {
+            bucket.builder_fees = bucket.builder_fees + builder_fee;
+        }
+        if users.insert(&fill.user) {
+            bucket.user_count += 1;
+        }
+    }
+
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn ds(values: &[&str]) -> Vec<Decimal> {
+        values.iter().map(|v| d(v)).collect()
+    }
+
+    #[test]
+    fn test_percentile_empty_is_none() {
+        assert_eq!(percentile(&[], d("50")), None);
+        assert_eq!(median(&[]), None);
+    }
+
+    #[test]
+    fn test_percentile_rejects_out_of_range() {
+        let values = ds(&["1", "2"]);
+        assert_eq!(percentile(&values, d("-1")), None);
+        assert_eq!(percentile(&values, d("100.1")), None);
+    }
+
+    #[test]
+    fn test_median_odd_and_even() {
+        assert_eq!(median(&ds(&["3", "1", "2"])), Some(d("2")));
+        assert_eq!(median(&ds(&["4", "1", "3", "2"])), Some(d("2.5")));
+    }
+
+    #[test]
+    fn test_percentile_interpolates_exactly() {
+        // rank = 9 * 0.95 = 8.55 -> 9 + 0.55 * (10 - 9)
+        let values = ds(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]);
+        assert_eq!(percentile(&values, d("95")), Some(d("9.55")));
+        assert_eq!(percentile(&values, d("0")), Some(d("1")));
+        assert_eq!(percentile(&values, d("100")), Some(d("10")));
+    }
+
+    #[test]
+    fn test_percentile_keeps_decimal_precision() {
+        let values = ds(&["0.1", "0.2"]);
+        assert_eq!(median(&values).unwrap().to_canonical_string(), "0.15");
+    }
+
+    #[test]
+    fn test_percentile_is_order_independent() {
+        let a = ds(&["5", "1", "1", "3"]);
+        let b = ds(&["1", "3", "5", "1"]);
+        assert_eq!(percentile(&a, d("95")), percentile(&b, d("95")));
+        assert_eq!(median(&a), Some(d("2")));
+    }
+
+    fn effect(bucket: i64, key: &str, notional: &str, fee: &str, crossed: Option<bool>) -> VolumeFillEffect {
+        VolumeFillEffect {
+            bucket_start_ms: TimeMs::new(bucket),
+            fill_key: key.to_string(),
+            notional: d(notional),
+            fee: d(fee),
+            builder_fee: d("0.1"),
+            crossed,
+        }
+    }
+
+    #[test]
+    fn test_volume_buckets_split_liquidity_and_count_fills_once() {
+        let effects = vec![
+            effect(0, "a", "100", "0.5", Some(true)),
+            effect(0, "b", "60", "-0.1", Some(false)),
+            // A flip: two effects of one fill, each with its share of the builder fee.
+            effect(0, "b", "40", "0", Some(false)),
+            effect(DAY_MS, "c", "10", "0.01", None),
+        ];
+
+        let buckets = volume_buckets(&effects);
+        assert_eq!(buckets.len(), 2);
+
+        let first = &buckets[0];
+        assert_eq!(first.trade_count, 2);
+        assert_eq!(first.volume, d("200"));
+        assert_eq!(first.taker_volume, d("100"));
+        assert_eq!(first.maker_volume, d("100"));
+        assert_eq!(first.fees.fees_paid, d("0.4"));
+        assert_eq!(first.fees.rebates_received, d("0.1"));
+        assert_eq!(first.builder_fees, d("0.3"));
+
+        let second = &buckets[1];
+        assert_eq!(second.start_ms, TimeMs::new(DAY_MS));
+        assert_eq!(second.volume, d("10"));
+        assert!(second.maker_volume.is_zero() && second.taker_volume.is_zero());
+    }
+
+    #[test]
+    fn test_week_buckets_start_on_monday() {
+        let week = StatsInterval::Week;
+        // 2024-01-03 (Wednesday) falls in the week of Monday 2024-01-01.
+        let t: i64 = 1_704_240_000_000;
+        let start = (t - week.origin_ms()) / week.width_ms() * week.width_ms() + week.origin_ms();
+        assert_eq!(start, 1_704_067_200_000);
+    }
+
+    fn revenue_fill(bucket: i64, user: &str, notional: &str, builder_fee: Option<&str>) -> BuilderFeeFill {
+        BuilderFeeFill {
+            bucket_start_ms: TimeMs::new(bucket),
+            user: Address::new(user.to_string()),
+            fill_key: format!("{}:{}", user, notional),
+            notional: d(notional),
+            builder_fee: builder_fee.map(d),
+        }
+    }
+
+    #[test]
+    fn test_revenue_buckets_count_users_per_bucket() {
+        let fills = vec![
+            revenue_fill(0, "0xa", "100", Some("0.1")),
+            revenue_fill(0, "0xb", "50", None),
+            revenue_fill(0, "0xa", "20", Some("0.02")),
+            revenue_fill(DAY_MS, "0xa", "10", Some("0.01")),
+        ];
+
+        let buckets = revenue_buckets(&fills);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].fill_count, 3);
+        assert_eq!(buckets[0].user_count, 2);
+        assert_eq!(buckets[0].volume, d("170"));
+        assert_eq!(buckets[0].builder_fees, d("0.12"));
+        assert_eq!(buckets[1].user_count, 1);
+        assert_eq!(buckets[1].builder_fees, d("0.01"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Strategy rules: label each lifecycle with the first rule it matches.
+
+use crate::domain::{Coin, Side, TimeMs};
+use std::str::FromStr;
+
+/// Property of a lifecycle a rule can require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LifecycleTag {
+    /// Every fill is builder-attributed, so the lifecycle is not tainted.
+    Builder,
+    /// At least one fill was a slice of a TWAP order.
+    Twap,
+    /// A liquidation or ADL fill closed the position.
+    Liquidated,
+    /// The opening fill bought.
+    Long,
+    /// The opening fill sold.
+    Short,
+}
+
+impl LifecycleTag {
+    pub const ALL: [LifecycleTag; 5] = [
+        LifecycleTag::Builder,
+        LifecycleTag::Twap,
+        LifecycleTag::Liquidated,
+        LifecycleTag::Long,
+        LifecycleTag::Short,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LifecycleTag::Builder => "builder",
+            LifecycleTag::Twap => "twap",
+            LifecycleTag::Liquidated => "liquidated",
+            LifecycleTag::Long => "long",
+            LifecycleTag::Short => "short",
+        }
+    }
+}
+
+impl FromStr for LifecycleTag {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LifecycleTag::ALL
+            .into_iter()
+            .find(|tag| tag.as_str() == s.trim().to_ascii_lowercase())
+            .ok_or(())
+    }
+}
+
+/// What rules are matched against, for one lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleFacts {
+    pub coin: Coin,
+    pub start_time_ms: TimeMs,
+    pub tainted: bool,
+    pub twap: bool,
+    pub liquidated: bool,
+    /// Side of the opening fill; `None` for a lifecycle without fills.
+    pub side: Option<Side>,
+}
+
+impl LifecycleFacts {
+    pub fn has(&self, tag: LifecycleTag) -> bool {
+        match tag {
+            LifecycleTag::Builder => !self.tainted,
+            LifecycleTag::Twap => self.twap,
+            LifecycleTag::Liquidated => self.liquidated,
+            LifecycleTag::Long => self.side == Some(Side::Buy),
+            LifecycleTag::Short => self.side == Some(Side::Sell),
+        }
+    }
+}
+
+/// A stored rule naming the strategy of the lifecycles it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyRule {
+    /// Strategy label given to matching lifecycles.
+    pub name: String,
+    /// Rules are tried lowest first; ties go by name.
+    pub priority: i64,
+    /// Coins the lifecycle must be in; empty matches any coin.
+    pub coins: Vec<Coin>,
+    /// Window, inclusive at both ends, the lifecycle must have opened in.
+    pub from_ms: Option<TimeMs>,
+    pub to_ms: Option<TimeMs>,
+    /// Tags the lifecycle must all have.
+    pub tags: Vec<LifecycleTag>,
+}
+
+impl StrategyRule {
+    pub fn matches(&self, facts: &LifecycleFacts) -> bool {
+        (self.coins.is_empty() || self.coins.contains(&facts.coin))
+            && self.from_ms.is_none_or(|from| facts.start_time_ms >= from)
+            && self.to_ms.is_none_or(|to| facts.start_time_ms <= to)
+            && self.tags.iter().all(|&tag| facts.has(tag))
+    }
+}
+
+/// Name of the first of `rules` that `facts` matches; `rules` must be in the order they are
+/// tried, as [`Repository::query_strategy_rules`] returns them.
+///
+/// [`Repository::query_strategy_rules`]: crate::db::Repository::query_strategy_rules
+pub fn assign_strategy<'a>(rules: &'a [StrategyRule], facts: &LifecycleFacts) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(facts))
+        .map(|rule| rule.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts(coin: &str, start_ms: i64, side: Side) -> LifecycleFacts {
+        LifecycleFacts {
+            coin: Coin::new(coin.to_string()),
+            start_time_ms: TimeMs::new(start_ms),
+            tainted: false,
+            twap: false,
+            liquidated: false,
+            side: Some(side),
+        }
+    }
+
+    fn rule(name: &str, coins: &[&str], tags: &[LifecycleTag]) -> StrategyRule {
+        StrategyRule {
+            name: name.to_string(),
+            priority: 0,
+            coins: coins.iter().map(|c| Coin::new(c.to_string())).collect(),
+            from_ms: None,
+            to_ms: None,
+            tags: tags.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = vec![
+            rule("twap-btc", &["BTC"], &[LifecycleTag::Twap]),
+            rule("shorts", &[], &[LifecycleTag::Short]),
+            rule("majors", &["BTC", "ETH"], &[]),
+        ];
+
+        let mut btc = facts("BTC", 1_000, Side::Sell);
+        assert_eq!(assign_strategy(&rules, &btc), Some("shorts"));
+        btc.twap = true;
+        assert_eq!(assign_strategy(&rules, &btc), Some("twap-btc"));
+        assert_eq!(assign_strategy(&rules, &facts("ETH", 1_000, Side::Buy)), Some("majors"));
+        assert_eq!(assign_strategy(&rules, &facts("SOL", 1_000, Side::Buy)), None);
+    }
+
+    #[test]
+    fn test_window_and_builder_tag() {
+        let mut window = rule("window", &[], &[LifecycleTag::Builder]);
+        window.from_ms = Some(TimeMs::new(1_000));
+        window.to_ms = Some(TimeMs::new(2_000));
+
+        assert!(window.matches(&facts("BTC", 1_000, Side::Buy)));
+        assert!(window.matches(&facts("BTC", 2_000, Side::Buy)));
+        assert!(!window.matches(&facts("BTC", 2_001, Side::Buy)));
+        assert!(!window.matches(&facts("BTC", 999, Side::Buy)));
+
+        let mut tainted = facts("BTC", 1_500, Side::Buy);
+        tainted.tainted = true;
+        assert!(!window.matches(&tainted));
+    }
+
+    #[test]
+    fn test_tag_from_str() {
+        assert_eq!(" TWAP ".parse(), Ok(LifecycleTag::Twap));
+        for tag in LifecycleTag::ALL {
+            assert_eq!(tag.as_str().parse(), Ok(tag));
+        }
+        assert_eq!("spot".parse::<LifecycleTag>(), Err(()));
+    }
+}
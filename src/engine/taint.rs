@@ -1,8 +1,82 @@
 //! Builder attribution taint logic for lifecycles.
 
 use super::{Effect, Snapshot};
-use crate::domain::Attribution;
-use std::collections::{BTreeSet, HashMap};
+use crate::domain::{Attribution, FillType};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
+
+/// Granularity at which builder-only queries drop unattributed activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TaintMode {
+    /// Drop a whole lifecycle if any of its fills is unattributed.
+    #[default]
+    Lifecycle,
+    /// Drop only the unattributed fills; the rest of the lifecycle still counts.
+    Fill,
+}
+
+impl TaintMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaintMode::Lifecycle => "lifecycle",
+            TaintMode::Fill => "fill",
+        }
+    }
+}
+
+impl FromStr for TaintMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lifecycle" => Ok(TaintMode::Lifecycle),
+            "fill" => Ok(TaintMode::Fill),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Result of fill-level builder-only filtering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillLevelFiltered<T> {
+    pub included: Vec<T>,
+    pub had_exclusions: bool,
+    /// Lifecycles that kept some fill effects and lost others.
+    pub partial_lifecycles: usize,
+}
+
+/// Keep only items whose own fill is builder-attributed.
+///
+/// `key` returns the `(fill_key, lifecycle_id)` of an item. Fills without attribution data
+/// are excluded, matching the lifecycle rule in [`TaintComputer::compute_taint`].
+pub fn filter_attributed_fills<T>(
+    items: Vec<T>,
+    attributions: &HashMap<String, Attribution>,
+    key: impl Fn(&T) -> (&str, i64),
+) -> FillLevelFiltered<T> {
+    let mut kept_lifecycles = HashSet::new();
+    let mut dropped_lifecycles = HashSet::new();
+
+    let included: Vec<T> = items
+        .into_iter()
+        .filter(|item| {
+            let (fill_key, lifecycle_id) = key(item);
+            let keep = attributions.get(fill_key).is_some_and(|a| a.attributed);
+            if keep {
+                kept_lifecycles.insert(lifecycle_id);
+            } else {
+                dropped_lifecycles.insert(lifecycle_id);
+            }
+            keep
+        })
+        .collect();
+
+    FillLevelFiltered {
+        included,
+        had_exclusions: !dropped_lifecycles.is_empty(),
+        partial_lifecycles: kept_lifecycles.intersection(&dropped_lifecycles).count(),
+    }
+}
 
 /// Taint information for a lifecycle.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +93,9 @@ pub struct TaintComputer {
 
     /// Map from fill_key to attribution.
     fill_attributions: HashMap<String, Attribution>,
+
+    /// Liquidation and ADL fills, named as such in taint reasons.
+    forced_fills: HashMap<String, FillType>,
 }
 
 impl TaintComputer {
@@ -26,6 +103,7 @@ impl TaintComputer {
         Self {
             lifecycle_fills: HashMap::new(),
             fill_attributions: HashMap::new(),
+            forced_fills: HashMap::new(),
         }
     }
 
@@ -42,35 +120,51 @@ impl TaintComputer {
         self.fill_attributions.insert(fill_key, attribution);
     }
 
+    /// Record how a fill came about; regular trades need not be registered.
+    pub fn set_fill_type(&mut self, fill_key: String, fill_type: FillType) {
+        if !fill_type.is_trade() {
+            self.forced_fills.insert(fill_key, fill_type);
+        }
+    }
+
+    /// How taint reasons refer to a fill.
+    fn describe_fill(&self, fill_key: &str) -> String {
+        match self.forced_fills.get(fill_key) {
+            Some(FillType::Liquidation) => format!("Liquidation fill {}", fill_key),
+            Some(FillType::Adl) => format!("ADL fill {}", fill_key),
+            _ => format!("Fill {}", fill_key),
+        }
+    }
+
     /// Compute taint for a specific lifecycle.
+    ///
+    /// The reason names the first offending fill, preferring a liquidation or ADL fill so
+    /// forced closes are visible.
     pub fn compute_taint(&self, lifecycle_id: i64) -> TaintInfo {
         let empty = BTreeSet::new();
         let fill_keys = self.lifecycle_fills.get(&lifecycle_id).unwrap_or(&empty);
 
+        let mut reason = None;
         for fill_key in fill_keys {
-            match self.fill_attributions.get(fill_key) {
-                Some(attr) if !attr.attributed => {
-                    return TaintInfo {
-                        is_tainted: true,
-                        reason: Some(format!(
-                            "Fill {} not attributed to builder (mode={:?})",
-                            fill_key, attr.mode
-                        )),
-                    };
-                }
-                None => {
-                    return TaintInfo {
-                        is_tainted: true,
-                        reason: Some(format!("Fill {} has no attribution data", fill_key)),
-                    };
-                }
-                Some(_) => {}
+            let problem = match self.fill_attributions.get(fill_key) {
+                Some(attr) if !attr.attributed => format!(
+                    "{} not attributed to builder (mode={:?})",
+                    self.describe_fill(fill_key),
+                    attr.mode
+                ),
+                None => format!("{} has no attribution data", self.describe_fill(fill_key)),
+                Some(_) => continue,
+            };
+            if self.forced_fills.contains_key(fill_key) {
+                reason = Some(problem);
+                break;
             }
+            reason.get_or_insert(problem);
         }
 
         TaintInfo {
-            is_tainted: false,
-            reason: None,
+            is_tainted: reason.is_some(),
+            reason,
         }
     }
 
@@ -267,4 +361,57 @@ mod tests {
         assert!(filter.had_exclusions(&[1, 2]));
         assert!(!filter.had_exclusions(&[1]));
     }
+
+    #[test]
+    fn test_parse_taint_mode() {
+        assert_eq!(TaintMode::from_str("lifecycle"), Ok(TaintMode::Lifecycle));
+        assert_eq!(TaintMode::from_str("Fill"), Ok(TaintMode::Fill));
+        assert!(TaintMode::from_str("trade").is_err());
+        assert_eq!(TaintMode::default(), TaintMode::Lifecycle);
+    }
+
+    #[test]
+    fn test_fill_level_filter_keeps_attributed_fills_of_tainted_lifecycle() {
+        let attributions = HashMap::from([
+            ("fill_a".to_string(), attributed()),
+            ("fill_b".to_string(), not_attributed()),
+            ("fill_c".to_string(), attributed()),
+        ]);
+        // fill_d has no attribution data at all.
+        let effects = vec![("fill_a", 1), ("fill_b", 1), ("fill_c", 2), ("fill_d", 3)];
+
+        let filtered = filter_attributed_fills(effects, &attributions, |&(k, id)| (k, id));
+        assert_eq!(filtered.included, vec![("fill_a", 1), ("fill_c", 2)]);
+        assert!(filtered.had_exclusions);
+        assert_eq!(filtered.partial_lifecycles, 1);
+    }
+
+    #[test]
+    fn test_fill_level_filter_without_exclusions() {
+        let attributions = HashMap::from([("fill_a".to_string(), attributed())]);
+        let filtered =
+            filter_attributed_fills(vec![("fill_a", 1)], &attributions, |&(k, id)| (k, id));
+        assert_eq!(filtered.included.len(), 1);
+        assert!(!filtered.had_exclusions);
+        assert_eq!(filtered.partial_lifecycles, 0);
+    }
+
+    #[test]
+    fn test_taint_reason_names_liquidation_fill() {
+        let mut computer = TaintComputer::new();
+
+        computer.add_fill_to_lifecycle(1, "fill_a".into());
+        computer.add_fill_to_lifecycle(1, "fill_b".into());
+        computer.set_attribution("fill_a".into(), not_attributed());
+        computer.set_attribution("fill_b".into(), not_attributed());
+        computer.set_fill_type("fill_a".into(), FillType::Trade);
+        computer.set_fill_type("fill_b".into(), FillType::Liquidation);
+
+        let taint = computer.compute_taint(1);
+        assert!(taint.is_tainted);
+        assert_eq!(
+            taint.reason.as_deref(),
+            Some("Liquidation fill fill_b not attributed to builder (mode=Heuristic)")
+        );
+    }
 }
@@ -1,10 +1,103 @@
+use crate::datasource::{BuilderLogsError, DataSourceError};
+use crate::db::repo::is_data_corruption;
+use crate::orchestration::attribution::AttributionIngestionError;
+use crate::orchestration::ensure::IngestionError;
+use crate::orchestration::orchestrator::OrchestrationError;
 use axum::{
+    extract::rejection::JsonRejection,
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+/// Stable, machine-readable error code returned as `code` in every error body.
+///
+/// Codes are part of the API contract: new ones may be added, existing ones are never
+/// renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    RateLimited,
+    InvalidAddress,
+    InvalidTimeRange,
+    NotFound,
+    Conflict,
+    PayloadTooLarge,
+    ReadOnly,
+    ConfigError,
+    Internal,
+    CompileFailed,
+    DataCorruption,
+    DataIntegrity,
+    UpstreamRateLimited,
+    UpstreamUnavailable,
+    ShuttingDown,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::InvalidAddress => "INVALID_ADDRESS",
+            ErrorCode::InvalidTimeRange => "INVALID_TIME_RANGE",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::ReadOnly => "READ_ONLY",
+            ErrorCode::ConfigError => "CONFIG_ERROR",
+            ErrorCode::Internal => "INTERNAL",
+            ErrorCode::CompileFailed => "COMPILE_FAILED",
+            ErrorCode::DataCorruption => "DATA_CORRUPTION",
+            ErrorCode::DataIntegrity => "DATA_INTEGRITY",
+            ErrorCode::UpstreamRateLimited => "UPSTREAM_RATE_LIMITED",
+            ErrorCode::UpstreamUnavailable => "UPSTREAM_UNAVAILABLE",
+            ErrorCode::ShuttingDown => "SHUTTING_DOWN",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::BadRequest | ErrorCode::InvalidAddress | ErrorCode::InvalidTimeRange => {
+                StatusCode::BAD_REQUEST
+            }
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden | ErrorCode::ReadOnly => StatusCode::FORBIDDEN,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::ConfigError
+            | ErrorCode::Internal
+            | ErrorCode::CompileFailed
+            | ErrorCode::DataCorruption
+            | ErrorCode::DataIntegrity => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::UpstreamUnavailable => StatusCode::BAD_GATEWAY,
+            ErrorCode::UpstreamRateLimited | ErrorCode::ShuttingDown => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }
+    }
+
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited
+                | ErrorCode::UpstreamRateLimited
+                | ErrorCode::UpstreamUnavailable
+                | ErrorCode::ShuttingDown
+        )
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -14,35 +107,250 @@ pub enum AppError {
     Internal(String),
     #[error("Not found: {0}")]
     NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Read-only: {0}")]
+    ReadOnly(String),
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid time range: {0}")]
+    InvalidTimeRange(String),
+    #[error("Compile failed: {0}")]
+    CompileFailed(String),
+    #[error("Data corruption: {0}")]
+    DataCorruption(String),
+    #[error("Data integrity: {0}")]
+    DataIntegrity(String),
+    #[error("Upstream rate limited: {0}")]
+    UpstreamRateLimited(String),
+    #[error("Upstream unavailable: {0}")]
+    UpstreamUnavailable(String),
+    #[error("Shutting down: {0}")]
+    ShuttingDown(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Config(_) => ErrorCode::ConfigError,
+            AppError::Internal(_) => ErrorCode::Internal,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            AppError::ReadOnly(_) => ErrorCode::ReadOnly,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::RateLimited(_) => ErrorCode::RateLimited,
+            AppError::InvalidAddress(_) => ErrorCode::InvalidAddress,
+            AppError::InvalidTimeRange(_) => ErrorCode::InvalidTimeRange,
+            AppError::CompileFailed(_) => ErrorCode::CompileFailed,
+            AppError::DataCorruption(_) => ErrorCode::DataCorruption,
+            AppError::DataIntegrity(_) => ErrorCode::DataIntegrity,
+            AppError::UpstreamRateLimited(_) => ErrorCode::UpstreamRateLimited,
+            AppError::UpstreamUnavailable(_) => ErrorCode::UpstreamUnavailable,
+            AppError::ShuttingDown(_) => ErrorCode::ShuttingDown,
+        }
+    }
+
+    /// Classify an orchestration failure, prefixing `context` to its message.
+    pub fn orchestration(context: &str, err: OrchestrationError) -> Self {
+        let message = format!("{}: {}", context, err);
+        match err {
+            OrchestrationError::Ingestion(e) => ingestion_code(&e).with_message(message),
+            OrchestrationError::Db(ref e) if is_data_corruption(e) => {
+                AppError::DataCorruption(message)
+            }
+            OrchestrationError::Db(_) => AppError::Internal(message),
+            OrchestrationError::Compile(_) => AppError::CompileFailed(message),
+            OrchestrationError::ShuttingDown(_) => AppError::ShuttingDown(message),
+            OrchestrationError::ReadOnly(_) => AppError::ReadOnly(message),
+        }
+    }
+}
+
+impl ErrorCode {
+    fn with_message(self, message: String) -> AppError {
+        match self {
+            ErrorCode::UpstreamRateLimited => AppError::UpstreamRateLimited(message),
+            ErrorCode::UpstreamUnavailable => AppError::UpstreamUnavailable(message),
+            _ => AppError::Internal(message),
+        }
+    }
+}
+
+fn ingestion_code(err: &IngestionError) -> ErrorCode {
+    match err {
+        IngestionError::DataSource(DataSourceError::RateLimited)
+        | IngestionError::DataSource(DataSourceError::HttpError { status: 429, .. }) => {
+            ErrorCode::UpstreamRateLimited
+        }
+        IngestionError::DataSource(_) => ErrorCode::UpstreamUnavailable,
+        IngestionError::Attribution(AttributionIngestionError::Logs(e)) => match e {
+            BuilderLogsError::HttpStatus(429) => ErrorCode::UpstreamRateLimited,
+            BuilderLogsError::Cache(_) => ErrorCode::Internal,
+            _ => ErrorCode::UpstreamUnavailable,
+        },
+        IngestionError::Attribution(_) | IngestionError::Db(_) => ErrorCode::Internal,
+    }
 }
 
+/// Stored values that no longer decode are reported apart from failed queries, so they
+/// are not mistaken for a transient outage.
 impl From<sqlx::Error> for AppError {
     fn from(err: sqlx::Error) -> Self {
-        AppError::Internal(err.to_string())
+        if is_data_corruption(&err) {
+            AppError::DataCorruption(err.to_string())
+        } else {
+            AppError::Internal(err.to_string())
+        }
+    }
+}
+
+/// A JSON body is malformed, except when reading it ran past the body limit.
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            AppError::PayloadTooLarge(rejection.body_text())
+        } else {
+            AppError::BadRequest(rejection.body_text())
+        }
+    }
+}
+
+impl From<IngestionError> for AppError {
+    fn from(err: IngestionError) -> Self {
+        ingestion_code(&err).with_message(err.to_string())
+    }
+}
+
+/// Orchestration failures surface from handlers that compile before answering.
+impl From<OrchestrationError> for AppError {
+    fn from(err: OrchestrationError) -> Self {
+        AppError::orchestration("Compilation failed", err)
+    }
+}
+
+impl From<crate::client::LedgerError> for AppError {
+    fn from(err: crate::client::LedgerError) -> Self {
+        use crate::client::LedgerError;
+        match err {
+            LedgerError::InvalidTimeRange => AppError::InvalidTimeRange(err.to_string()),
+            LedgerError::Config(msg) => AppError::Internal(msg),
+            LedgerError::Orchestration(e) => e.into(),
+            LedgerError::Deposits(e) => AppError::orchestration("Deposit ingestion failed", e),
+            LedgerError::Db(e) => e.into(),
+        }
     }
 }
 
-impl From<crate::orchestration::ensure::IngestionError> for AppError {
-    fn from(err: crate::orchestration::ensure::IngestionError) -> Self {
-        AppError::Internal(err.to_string())
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    /// Human-readable message; wording may change between releases.
+    pub error: String,
+}
+
+impl From<AppError> for ErrorBody {
+    fn from(err: AppError) -> Self {
+        let code = err.code();
+        let message = match err {
+            AppError::Config(msg)
+            | AppError::Internal(msg)
+            | AppError::NotFound(msg)
+            | AppError::Conflict(msg)
+            | AppError::PayloadTooLarge(msg)
+            | AppError::ReadOnly(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::RateLimited(msg)
+            | AppError::InvalidAddress(msg)
+            | AppError::InvalidTimeRange(msg)
+            | AppError::CompileFailed(msg)
+            | AppError::DataCorruption(msg)
+            | AppError::DataIntegrity(msg)
+            | AppError::UpstreamRateLimited(msg)
+            | AppError::UpstreamUnavailable(msg)
+            | AppError::ShuttingDown(msg) => msg,
+        };
+
+        ErrorBody {
+            code,
+            error: message,
+        }
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Config(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-        };
+        let status = self.code().status();
+        (status, Json(ErrorBody::from(self))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestration::jobs::JobQueueClosed;
+
+    #[test]
+    fn test_rate_limited_datasource_maps_to_503() {
+        let err = AppError::from(OrchestrationError::Ingestion(IngestionError::DataSource(
+            DataSourceError::HttpError {
+                status: 429,
+                message: "Too many requests".to_string(),
+            },
+        )));
+        assert_eq!(err.code(), ErrorCode::UpstreamRateLimited);
+        assert_eq!(err.code().status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(err.code().is_retryable());
+    }
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+    #[test]
+    fn test_network_failure_maps_to_502() {
+        let err = AppError::from(IngestionError::DataSource(DataSourceError::NetworkError(
+            "timeout".to_string(),
+        )));
+        assert_eq!(err.code(), ErrorCode::UpstreamUnavailable);
+        assert_eq!(err.code().status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_shutdown_keeps_context_in_message() {
+        let err = AppError::orchestration("Deposit ingestion failed", JobQueueClosed.into());
+        assert_eq!(err.code(), ErrorCode::ShuttingDown);
+        assert!(matches!(err, AppError::ShuttingDown(msg) if msg.starts_with("Deposit ingestion failed: ")));
+    }
+
+    #[test]
+    fn test_undecodable_column_maps_to_data_corruption() {
+        let err = AppError::from(sqlx::Error::ColumnDecode {
+            index: "px".to_string(),
+            source: "invalid decimal".into(),
+        });
+        assert_eq!(err.code(), ErrorCode::DataCorruption);
+        assert!(!err.code().is_retryable());
+
+        let err = AppError::from(sqlx::Error::RowNotFound);
+        assert_eq!(err.code(), ErrorCode::Internal);
+    }
 
-        (status, body).into_response()
+    #[test]
+    fn test_codes_serialize_as_screaming_snake_case() {
+        for code in [ErrorCode::InvalidAddress, ErrorCode::UpstreamRateLimited] {
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        }
     }
 }
@@ -1,4 +1,5 @@
 pub mod api;
+pub mod client;
 pub mod compile;
 pub mod config;
 pub mod datasource;
@@ -7,13 +8,15 @@ pub mod domain;
 pub mod engine;
 pub mod error;
 pub mod orchestration;
+pub mod telemetry;
 
+pub use client::LedgerClient;
 pub use compile::CompileState;
 pub use config::Config;
 pub use datasource::{DataSource, DataSourceError, HyperliquidDataSource, MockDataSource};
 pub use db::{init_db, Repository};
 pub use domain::{
     Address, Attribution, AttributionConfidence, AttributionMode, Coin, Confidence, Decimal,
-    Deposit, Fill, Side, TimeMs,
+    Deposit, Fill, FillType, LedgerKind, Side, TimeMs,
 };
 pub use error::AppError;
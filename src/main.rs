@@ -1,23 +1,35 @@
 use hypesilico::api::{self, AppState};
-use hypesilico::config::Config;
-use hypesilico::datasource::HyperliquidDataSource;
-use hypesilico::db::init_db;
+use hypesilico::config::{Config, ConfigHandle};
+use hypesilico::datasource::{
+    BuilderLogsFetcher, CachedBuilderLogsFetcher, HyperliquidDataSource, HyperliquidWsDataSource,
+};
+use hypesilico::db::{
+    check_manifest, check_read_only_manifest, init_db_with_pools, open_db_read_only,
+    restore_backup, verify_database, Maintenance,
+};
+use hypesilico::domain::Address;
 use hypesilico::engine::EquityResolver;
 use hypesilico::orchestration::ensure::Ingestor;
 use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::telemetry;
 use hypesilico::Repository;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// How long running ingest/compile jobs get to finish once the server stops.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing_subscriber::filter::LevelFilter::INFO.into()),
-        )
-        .init();
+    // Initialize tracing with a filter the admin API can change at runtime
+    let (subscriber, log_filter) = telemetry::reloadable_subscriber(telemetry::default_filter());
+    if let Err(e) = subscriber.try_init() {
+        eprintln!("Failed to install tracing subscriber: {}", e);
+        std::process::exit(1);
+    }
 
     // Load configuration
     let config = match Config::from_env() {
@@ -28,43 +40,325 @@ async fn main() {
         }
     };
 
-    let port = config.port;
+    let addr = SocketAddr::new(config.bind_addr, config.port);
+    let admin_addr = config
+        .admin_port
+        .map(|port| SocketAddr::new(config.admin_bind_addr, port));
+
+    // Put a snapshot in place before anything opens the database file.
+    if let Some(backup) = &config.maintenance.restore_from {
+        match restore_backup(&backup.to_string_lossy(), &config.database_path).await {
+            Ok(report) => tracing::info!(
+                "Restored {} (written by hypesilico {}) to {}",
+                backup.display(),
+                report.source.crate_version,
+                config.database_path
+            ),
+            Err(e) => {
+                eprintln!("Failed to restore {}: {}", backup.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Migrating stamps the file with this build's versions, so a file written by a newer
+    // build has to be refused before that. Read-only serving never migrates, so it also
+    // needs the file at exactly this build's schema.
+    let read_only = config.read_only;
+    let manifest_check = if read_only {
+        check_read_only_manifest(&config.database_path).await
+    } else {
+        check_manifest(&config.database_path).await
+    };
+    if let Err(e) = manifest_check {
+        eprintln!("Refusing to open {}: {}", config.database_path, e);
+        std::process::exit(1);
+    }
 
     // Initialize database and app state
-    let pool = match init_db(&config.database_path).await {
-        Ok(p) => p,
+    let opened = if read_only {
+        open_db_read_only(&config.database_path, &config.db_pool).await
+    } else {
+        init_db_with_pools(&config.database_path, &config.db_pool).await
+    };
+    let (pool, read_pool) = match opened {
+        Ok(pools) => pools,
         Err(e) => {
             eprintln!("Failed to initialize database: {}", e);
             std::process::exit(1);
         }
     };
+    if let Err(e) = verify_database(&pool, config.startup_integrity_check).await {
+        eprintln!("Refusing to open {}: {}", config.database_path, e);
+        std::process::exit(1);
+    }
+    tracing::info!(
+        "Self-check of {} passed (integrity check: {})",
+        config.database_path,
+        config.startup_integrity_check.as_str()
+    );
+
+    // Analytical reads get their own connections so they never queue behind ingestion.
+    let mut repo = Repository::new(pool);
+    if let Some(read_pool) = read_pool {
+        repo = repo.with_read_pool(read_pool);
+    }
+    let repo = Arc::new(repo);
+    let network_check = if read_only {
+        repo.check_network(config.network).await
+    } else {
+        repo.claim_network(config.network).await
+    };
+    if let Err(e) = network_check {
+        eprintln!("Refusing to open {}: {}", config.database_path, e);
+        std::process::exit(1);
+    }
+    let mut datasource = HyperliquidDataSource::new(config.hyperliquid_api_url.clone());
+    if config.record_raw_payloads {
+        datasource = datasource.with_payload_recording(repo.clone());
+    }
+    let datasource = Arc::new(datasource);
+
+    // Everything above is fixed for the life of the process; the rest reads the handle,
+    // which a SIGHUP or the admin API can reload.
+    let config = ConfigHandle::new(config);
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone())
+        .with_builder_logs(Arc::new(CachedBuilderLogsFetcher::new(
+            BuilderLogsFetcher::default()
+                .with_network(config.load().network)
+                .with_max_concurrent_downloads(config.load().builder_logs_max_concurrent_downloads),
+            repo.clone(),
+        )));
 
-    let repo = Arc::new(Repository::new(pool));
-    let datasource = Arc::new(HyperliquidDataSource::new(config.hyperliquid_api_url.clone()));
-    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
-    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let orchestrator = Arc::new(Orchestrator::new(ingestor.clone(), repo.clone()));
+
+    // Stream leaderboard users' fills so their data does not wait for the next request.
+    // The subscriptions are made once; a reloaded user list applies to queries only.
+    let mut ws_task = None;
+    if read_only {
+        tracing::info!("READ_ONLY is set; serving stored data without ingesting or compiling");
+    } else if let Some(ws_url) = config.load().hyperliquid_ws_url.clone() {
+        let users: Vec<Address> = config
+            .load()
+            .leaderboard_users
+            .iter()
+            .filter_map(|u| match Address::from_str(u) {
+                Ok(addr) => Some(addr),
+                Err(_) => {
+                    tracing::warn!("Not streaming fills for invalid address {}", u);
+                    None
+                }
+            })
+            .collect();
+        if users.is_empty() {
+            tracing::warn!("HYPERLIQUID_WS_URL is set but there are no leaderboard users to stream");
+        } else {
+            let stream = HyperliquidWsDataSource::new(ws_url, users)
+                .with_job_queue(orchestrator.job_queue().clone());
+            ws_task = Some(tokio::spawn(async move { stream.run(ingestor).await }));
+        }
+    }
+
+    if read_only {
+        // Quarantining and recompiling both write; serve the file as it is.
+        match repo.query_stale_compile_states().await {
+            Ok(stale) if !stale.is_empty() => tracing::warn!(
+                "{} (user, coin) pairs were compiled by an older engine and are served as stored",
+                stale.len()
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to count stale derived tables: {}", e),
+        }
+    } else {
+        // Undecodable rows would fail every query touching them; set them aside first so the
+        // recompile below rebuilds the affected pairs without them. The sweep reads every
+        // decimal column, so it runs once per version; POST /v1/admin/quarantine reruns it.
+        let swept = match repo.quarantine_swept_version().await {
+            Ok(version) => version.as_deref() == Some(env!("CARGO_PKG_VERSION")),
+            Err(e) => {
+                eprintln!("Failed to read quarantine sweep version: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if !swept {
+            match repo.quarantine_corrupt_rows().await {
+                Ok(report) if report.total() > 0 => tracing::warn!(
+                    moved = ?report.moved,
+                    reset_pairs = report.reset_pairs.len(),
+                    "Quarantined corrupt rows; see /v1/admin/quarantine"
+                ),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Failed to quarantine corrupt rows: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        // Derived tables written by an older compiler must not be mixed with new rows.
+        match orchestrator.recompile_stale().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Recompiled {} stale (user, coin) pairs", n),
+            Err(e) => {
+                eprintln!("Failed to recompile stale derived tables: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Validate coins against the last known asset list until the first refresh lands.
+    if let Err(e) = orchestrator.load_coins().await {
+        tracing::warn!("Failed to load stored coin metadata: {}", e);
+    }
     let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
 
-    let state = AppState::new(repo, config.clone(), orchestrator, equity_resolver);
+    // Keep the WAL from growing without bound between restarts.
+    let maintenance_tasks = Maintenance::spawn_scheduled(repo.clone(), config.clone());
+
+    let state = AppState::new(repo.clone(), config.clone(), orchestrator.clone(), equity_resolver)
+        .with_log_filter(log_filter);
+
+    // Competitions rank on stored end-of-interval standings, not live recomputation.
+    let snapshot_task = state.ledger.spawn_leaderboard_snapshots();
+    let equity_task = state.ledger.spawn_equity_sampler();
+    let coins_task = state.ledger.spawn_coin_refresh();
+
+    #[cfg(unix)]
+    let reload_task = tokio::spawn(reload_on_sighup(state.clone()));
 
     // Create router
+    let streams = state.clone();
+    let admin_app = admin_addr.map(|_| api::create_admin_router(state.clone()));
     let app = api::create_router(state);
 
     // Bind to address
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    let listener = match tokio::net::TcpListener::bind(&addr).await {
+    let listener = bind(addr).await;
+    tracing::info!("Server listening on {}", addr);
+
+    // Operational endpoints on their own listener, stopped with the public one.
+    let admin_server = match (admin_addr, admin_app) {
+        (Some(admin_addr), Some(admin_app)) => {
+            let admin_listener = bind(admin_addr).await;
+            tracing::info!("Admin server listening on {}", admin_addr);
+            let mut closing = streams.closing.subscribe();
+            Some(tokio::spawn(async move {
+                let shutdown = async move {
+                    let _ = closing.wait_for(|closing| *closing).await;
+                };
+                if let Err(e) = axum::serve(admin_listener, admin_app)
+                    .with_graceful_shutdown(shutdown)
+                    .await
+                {
+                    tracing::error!("Admin server error: {}", e);
+                }
+            }))
+        }
+        _ => None,
+    };
+
+    // Run server until SIGINT/SIGTERM, then let in-flight requests finish
+    if let Err(e) = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            streams.close_streams();
+        })
+        .await
+    {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
+
+    if let Some(admin_server) = admin_server {
+        let _ = admin_server.await;
+    }
+
+    tracing::info!("Stopped accepting requests, draining background jobs");
+    let drained = orchestrator.shutdown(SHUTDOWN_GRACE).await;
+    if let Some(ws_task) = ws_task {
+        ws_task.abort();
+    }
+    for task in maintenance_tasks {
+        task.abort();
+    }
+    snapshot_task.abort();
+    equity_task.abort();
+    coins_task.abort();
+    #[cfg(unix)]
+    reload_task.abort();
+    if !drained {
+        // Compiles commit atomically, so unfinished ones are simply redone on next start.
+        tracing::warn!(
+            "Jobs still running after {:?}; exiting without closing the database",
+            SHUTDOWN_GRACE
+        );
+        return;
+    }
+
+    // A read-only process wrote nothing and cannot checkpoint the WAL.
+    if !read_only {
+        if let Err(e) = repo.close().await {
+            tracing::warn!("WAL checkpoint on shutdown failed: {}", e);
+        }
+    }
+    tracing::info!("Shutdown complete");
+}
+
+/// Bind a listener on `addr`, exiting when that fails.
+async fn bind(addr: SocketAddr) -> tokio::net::TcpListener {
+    match tokio::net::TcpListener::bind(&addr).await {
         Ok(l) => l,
         Err(e) => {
             eprintln!("Failed to bind to {}: {}", addr, e);
             std::process::exit(1);
         }
+    }
+}
+
+/// Reload the operational settings from the config file and environment on every SIGHUP.
+#[cfg(unix)]
+async fn reload_on_sighup(state: AppState) {
+    let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
     };
+    while hangups.recv().await.is_some() {
+        match Config::from_env() {
+            Ok(next) => {
+                state.reload_config(next);
+            }
+            Err(e) => tracing::error!("Keeping the running configuration: {}", e),
+        }
+    }
+}
 
-    tracing::info!("Server listening on {}", addr);
+/// Resolve on SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
 
-    // Run server
-    if let Err(e) = axum::serve(listener, app).await {
-        eprintln!("Server error: {}", e);
-        std::process::exit(1);
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
     }
 }
@@ -3,8 +3,8 @@
 use crate::config::{BuilderAttributionMode, Config};
 use crate::datasource::{BuilderLogsError, BuilderLogsSource};
 use crate::db::Repository;
-use crate::domain::{Address, Attribution, AttributionConfidence, AttributionMode, Coin, Fill, TimeMs};
-use crate::engine::{BuilderLogsIndex, MatchTolerances};
+use crate::domain::{Address, Attribution, AttributionConfidence, Coin, Fill, TimeMs};
+use crate::engine::{BuilderLogsIndex, HeuristicAttributor, LogsMatch, MatchTolerances};
 use chrono::TimeZone;
 use thiserror::Error;
 
@@ -14,8 +14,8 @@ pub enum AttributionIngestionError {
     Db(#[from] sqlx::Error),
     #[error(transparent)]
     Logs(#[from] BuilderLogsError),
-    #[error("invalid target builder address")]
-    InvalidTargetBuilder,
+    #[error("invalid target builder address {0}")]
+    InvalidTargetBuilder(String),
     #[error("invalid timestamp: {0}")]
     InvalidTimestamp(i64),
 }
@@ -43,6 +43,9 @@ impl DayContext {
 #[derive(Debug, Clone, Default)]
 pub struct AttributionIngestor {
     pub tolerances: MatchTolerances,
+    /// Decides fills no builder log row matched, in `auto` mode, and every fill in
+    /// `heuristic` mode.
+    pub heuristic: HeuristicAttributor,
 }
 
 impl AttributionIngestor {
@@ -54,30 +57,36 @@ impl AttributionIngestor {
         target_builder: &Address,
     ) -> Attribution {
         match mode {
-            BuilderAttributionMode::Heuristic => {
-                Attribution::from_heuristic(fill.builder_fee.as_ref())
-            }
-            BuilderAttributionMode::Logs => {
-                let confidence = logs_index.and_then(|idx| idx.match_fill(fill, &self.tolerances));
-                match confidence {
-                    Some(confidence) => {
-                        Attribution::from_logs_match(true, Some(target_builder.clone()), confidence)
-                    }
-                    None => Attribution::from_logs_match(false, None, AttributionConfidence::Exact),
+            BuilderAttributionMode::Heuristic => self.heuristic.attribute(fill),
+            BuilderAttributionMode::Logs => match self.match_logs(fill, logs_index) {
+                Some(m) => {
+                    Attribution::from_logs_match(true, Some(target_builder.clone()), m.confidence)
+                        .with_tolerance_tier(m.tolerance_tier)
+                        .with_matched_log_key(m.log_key)
+                        .with_reason(m.reason, m.score)
                 }
-            }
-            BuilderAttributionMode::Auto => {
-                let confidence = logs_index.and_then(|idx| idx.match_fill(fill, &self.tolerances));
-                match confidence {
-                    Some(confidence) => {
-                        Attribution::from_logs_match(true, Some(target_builder.clone()), confidence)
-                    }
-                    None => Attribution::from_heuristic(fill.builder_fee.as_ref()),
+                None => Attribution::from_logs_match(false, None, AttributionConfidence::Exact),
+            },
+            BuilderAttributionMode::Auto => match self.match_logs(fill, logs_index) {
+                Some(m) => {
+                    Attribution::from_logs_match(true, Some(target_builder.clone()), m.confidence)
+                        .with_tolerance_tier(m.tolerance_tier)
+                        .with_matched_log_key(m.log_key)
+                        .with_reason(m.reason, m.score)
                 }
-            }
+                None => self.heuristic.attribute(fill),
+            },
         }
     }
 
+    fn match_logs(
+        &self,
+        fill: &Fill,
+        logs_index: Option<&BuilderLogsIndex<'_>>,
+    ) -> Option<LogsMatch> {
+        logs_index.and_then(|idx| idx.match_fill_detailed(fill, &self.tolerances))
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn ingest_window(
         &self,
@@ -92,10 +101,18 @@ impl AttributionIngestor {
         let from_ms = from_ms.unwrap_or(TimeMs::new(0));
         let to_ms = to_ms.unwrap_or(TimeMs::new(i64::MAX));
 
-        let target_builder = Address::new(config.target_builder.clone());
-        if target_builder.as_str().is_empty() {
-            return Err(AttributionIngestionError::InvalidTargetBuilder);
-        }
+        // Configs built in code skip `Config::from_env` validation, so check again per job.
+        let target_builder = Address::parse_normalized(&config.target_builder).map_err(|e| {
+            tracing::error!(
+                target_builder = %config.target_builder,
+                error = %e,
+                "Refusing to attribute fills with an invalid target builder"
+            );
+            AttributionIngestionError::InvalidTargetBuilder(format!(
+                "{:?}: {}",
+                config.target_builder, e
+            ))
+        })?;
 
         let fills = repo.query_fills(user, coin, Some(from_ms), Some(to_ms)).await?;
         if fills.is_empty() {
@@ -149,27 +166,81 @@ impl AttributionIngestor {
                 &target_builder,
             );
 
-            staged.push((
-                fill.fill_key.clone(),
-                attribution.attributed,
-                match attribution.mode {
-                    AttributionMode::Heuristic => "heuristic".to_string(),
-                    AttributionMode::Logs => "logs".to_string(),
-                },
-                match attribution.confidence {
-                    AttributionConfidence::Exact => "exact".to_string(),
-                    AttributionConfidence::Fuzzy => "fuzzy".to_string(),
-                    AttributionConfidence::Low => "low".to_string(),
-                },
-                attribution.builder.map(|b| b.as_str().to_string()),
-            ));
+            staged.push((fill.fill_key.clone(), attribution));
         }
 
-        repo.insert_attributions(&staged).await?;
+        repo.upsert_attributions_full(&staged).await?;
         Ok(staged.len())
     }
 }
 
+const DAY_MS: i64 = 86_400_000;
+
+/// How much of a query window has final builder-log attribution.
+///
+/// Logs are only stored once a UTC day has completed, so fills on other days were
+/// attributed without them (or against a partial day) and may still change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributionCoverage {
+    /// `YYYYMMDD` days with fills whose builder logs are not stored yet, ascending.
+    pub pending_days: Vec<String>,
+    /// Last millisecond before the first pending day, clipped to the window.
+    ///
+    /// Results up to here are final. `None` when nothing is pending, or when the first day
+    /// with fills is pending.
+    pub covered_to_ms: Option<TimeMs>,
+    /// Start of the first pending day, clipped to the window; `None` when nothing is pending.
+    pub pending_from_ms: Option<TimeMs>,
+}
+
+/// Check which days with fills in a window have the target builder's logs stored.
+pub async fn attribution_coverage(
+    repo: &Repository,
+    target_builder: &Address,
+    user: &Address,
+    coin: Option<&Coin>,
+    from_ms: Option<TimeMs>,
+    to_ms: Option<TimeMs>,
+) -> Result<AttributionCoverage, AttributionIngestionError> {
+    let days = repo.query_fill_days(user, coin, from_ms, to_ms).await?;
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Ok(AttributionCoverage {
+            pending_days: Vec::new(),
+            covered_to_ms: None,
+            pending_from_ms: None,
+        });
+    };
+
+    let cached = repo
+        .query_cached_builder_log_days(
+            target_builder,
+            &yyyymmdd_utc(first * DAY_MS)?,
+            &yyyymmdd_utc(last * DAY_MS)?,
+        )
+        .await?;
+
+    let mut pending = Vec::new();
+    for day in &days {
+        let yyyymmdd = yyyymmdd_utc(day * DAY_MS)?;
+        if !cached.contains(&yyyymmdd) {
+            pending.push((*day, yyyymmdd));
+        }
+    }
+
+    let window_start = from_ms.map_or(first * DAY_MS, |t| t.as_ms());
+    let pending_start = pending.first().map(|(day, _)| day * DAY_MS);
+    let covered_to_ms = pending_start
+        .map(|start| start - 1)
+        .filter(|end| *end >= window_start)
+        .map(|end| TimeMs::new(to_ms.map_or(end, |to| end.min(to.as_ms()))));
+
+    Ok(AttributionCoverage {
+        pending_days: pending.into_iter().map(|(_, yyyymmdd)| yyyymmdd).collect(),
+        covered_to_ms,
+        pending_from_ms: pending_start.map(|start| TimeMs::new(start.max(window_start))),
+    })
+}
+
 fn yyyymmdd_utc(time_ms: i64) -> Result<String, AttributionIngestionError> {
     let Some(dt) = chrono::Utc.timestamp_millis_opt(time_ms).single() else {
         return Err(AttributionIngestionError::InvalidTimestamp(time_ms));
@@ -180,7 +251,7 @@ fn yyyymmdd_utc(time_ms: i64) -> Result<String, AttributionIngestionError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::{BuilderLogFill, Decimal, Side};
+    use crate::domain::{AttributionMode, BuilderLogFill, Decimal, Side, ToleranceTier};
     use std::str::FromStr;
 
     fn fill_with_builder_fee(builder_fee: Option<&str>, tid: i64) -> Fill {
@@ -227,6 +298,29 @@ mod tests {
         assert_eq!(attr.mode, AttributionMode::Logs);
         assert_eq!(attr.confidence, AttributionConfidence::Exact);
         assert_eq!(attr.builder, Some(target_builder));
+        assert_eq!(attr.matched_log_key.as_deref(), Some("tid:42"));
+    }
+
+    #[test]
+    fn auto_falls_back_to_fee_tiers_without_a_log_match() {
+        let target_builder = Address::new("0xbuilder".to_string());
+        let logs = vec![];
+        let index = BuilderLogsIndex::new(&logs);
+        // Notional is 100, so a builder fee of 0.01 is 1 bps and 0.03 is 3 bps.
+        let ingestor = AttributionIngestor {
+            heuristic: HeuristicAttributor::new(vec![Decimal::from_str("1").unwrap()]),
+            ..AttributionIngestor::default()
+        };
+
+        for (builder_fee, attributed) in [("0.01", true), ("0.03", false)] {
+            let fill = fill_with_builder_fee(Some(builder_fee), 7);
+            for mode in [BuilderAttributionMode::Auto, BuilderAttributionMode::Heuristic] {
+                let attr = ingestor.attribute_fill(mode, &fill, Some(&index), &target_builder);
+                assert_eq!(attr.attributed, attributed, "{} in {:?}", builder_fee, mode);
+                assert_eq!(attr.mode, AttributionMode::Heuristic);
+                assert_eq!(attr.confidence, AttributionConfidence::Low);
+            }
+        }
     }
 
     #[test]
@@ -250,6 +344,42 @@ mod tests {
         assert!(attr.builder.is_none());
     }
 
+    #[test]
+    fn fuzzy_match_records_tolerance_tier() {
+        let target_builder = Address::new("0xbuilder".to_string());
+        let fill = fill_with_builder_fee(None, 42);
+
+        let logs = vec![BuilderLogFill {
+            time_ms: fill.time_ms,
+            user: fill.user.clone(),
+            coin: fill.coin.clone(),
+            side: fill.side,
+            px: Decimal::from_str("100.02").unwrap(),
+            sz: fill.sz,
+            tid: None,
+            oid: None,
+        }];
+        let index = BuilderLogsIndex::new(&logs);
+        let ingestor = AttributionIngestor {
+            tolerances: MatchTolerances {
+                px_bps: Decimal::from_str("3").unwrap(),
+                ..MatchTolerances::default()
+            },
+            ..AttributionIngestor::default()
+        };
+
+        let attr = ingestor.attribute_fill(
+            BuilderAttributionMode::Logs,
+            &fill,
+            Some(&index),
+            &target_builder,
+        );
+
+        assert!(attr.attributed);
+        assert_eq!(attr.confidence, AttributionConfidence::Fuzzy);
+        assert_eq!(attr.tolerance_tier, Some(ToleranceTier::Bps));
+    }
+
     #[test]
     fn yyyymmdd_is_utc_day() {
         let day = yyyymmdd_utc(0).unwrap();
@@ -0,0 +1,112 @@
+//! Managed builder log backfills over a date range.
+//!
+//! [`Orchestrator::backfill_builder_logs`](super::orchestrator::Orchestrator::backfill_builder_logs)
+//! downloads every day of the range for each builder, stores the parsed rows in
+//! `builder_log_fills`, and then re-attributes the affected users once, instead of each
+//! query fetching the days it happens to touch.
+
+use crate::domain::{Address, TimeMs};
+use chrono::{Duration, NaiveDate};
+
+/// Longest range one backfill accepts, in days.
+pub const MAX_BACKFILL_DAYS: usize = 366;
+
+/// Most builders one backfill request through the API accepts; each adds a download per
+/// day to a request that runs before responding.
+pub const MAX_BACKFILL_BUILDERS: usize = 8;
+
+/// What happened to one builder's log file for one day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderLogsDayOutcome {
+    pub builder: Address,
+    pub yyyymmdd: String,
+    /// Rows stored for the day; `0` when the download failed.
+    pub fills: usize,
+    /// Why the day could not be fetched, e.g. an HTTP `404` for a day without a file.
+    pub error: Option<String>,
+}
+
+/// A user re-attributed after the logs were stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderLogsUserOutcome {
+    pub user: Address,
+    pub attributed: usize,
+    pub error: Option<String>,
+}
+
+/// Outcome of a builder log backfill.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuilderLogsBackfillReport {
+    /// One entry per builder and day, in builder then day order.
+    pub days: Vec<BuilderLogsDayOutcome>,
+    pub fills_stored: usize,
+    /// Users in the stored logs who have fills in the range.
+    pub users: Vec<BuilderLogsUserOutcome>,
+}
+
+impl BuilderLogsBackfillReport {
+    pub fn failed_days(&self) -> usize {
+        self.days.iter().filter(|d| d.error.is_some()).count()
+    }
+
+    pub fn fills_attributed(&self) -> usize {
+        self.users.iter().map(|u| u.attributed).sum()
+    }
+}
+
+/// Every `YYYYMMDD` day from `from_day` to `to_day` inclusive.
+///
+/// Returns `None` if either day is not a valid date, or `to_day` is before `from_day`.
+pub fn days_in_range(from_day: &str, to_day: &str) -> Option<Vec<String>> {
+    let from = NaiveDate::parse_from_str(from_day, "%Y%m%d").ok()?;
+    let to = NaiveDate::parse_from_str(to_day, "%Y%m%d").ok()?;
+    if to < from {
+        return None;
+    }
+    Some(
+        from.iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| day.format("%Y%m%d").to_string())
+            .collect(),
+    )
+}
+
+/// First and last millisecond of an inclusive `YYYYMMDD` range, in UTC.
+pub fn range_bounds(from_day: &str, to_day: &str) -> Option<(TimeMs, TimeMs)> {
+    let start = NaiveDate::parse_from_str(from_day, "%Y%m%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_utc();
+    let end = NaiveDate::parse_from_str(to_day, "%Y%m%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_utc()
+        + Duration::days(1);
+    Some((
+        TimeMs::new(start.timestamp_millis()),
+        TimeMs::new(end.timestamp_millis() - 1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_days_in_range_crosses_month_end() {
+        assert_eq!(
+            days_in_range("20240228", "20240302").unwrap(),
+            vec!["20240228", "20240229", "20240301", "20240302"]
+        );
+        assert_eq!(days_in_range("20240101", "20240101").unwrap(), vec!["20240101"]);
+        assert!(days_in_range("20240102", "20240101").is_none());
+        assert!(days_in_range("20241301", "20241302").is_none());
+    }
+
+    #[test]
+    fn test_range_bounds_cover_whole_days() {
+        let (from, to) = range_bounds("20231114", "20231115").unwrap();
+        assert_eq!(from.as_ms(), 1_699_920_000_000);
+        assert_eq!(to.as_ms(), 1_700_092_800_000 - 1);
+    }
+}
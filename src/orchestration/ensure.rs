@@ -1,7 +1,11 @@
-use crate::config::Config;
-use crate::datasource::{DataSource, DataSourceError};
+use crate::config::{BuilderAttributionMode, Config, ConfigHandle};
+use crate::datasource::{BuilderLogsSource, DataSource, DataSourceError, FillsPage};
 use crate::db::Repository;
-use crate::domain::{Address, Coin, TimeMs};
+use crate::domain::mark_price::bucket_start;
+use crate::domain::{Address, Coin, CoinMeta, Decimal, Fill, TimeMs, MARK_PRICE_BUCKET_MS};
+use crate::orchestration::attribution::{AttributionIngestionError, AttributionIngestor};
+use crate::orchestration::webhooks::{WebhookEvent, Webhooks};
+use futures::stream::{self, StreamExt};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -9,21 +13,60 @@ use thiserror::Error;
 pub struct Ingestor {
     datasource: Arc<dyn DataSource>,
     repo: Arc<Repository>,
-    config: Config,
+    config: ConfigHandle,
+    builder_logs: Option<Arc<dyn BuilderLogsSource>>,
+    webhooks: Webhooks,
 }
 
 impl Ingestor {
-    pub fn new(datasource: Arc<dyn DataSource>, repo: Arc<Repository>, config: Config) -> Self {
+    /// Settings are read from `config` on every call, so a reload applies to the next window.
+    pub fn new(
+        datasource: Arc<dyn DataSource>,
+        repo: Arc<Repository>,
+        config: impl Into<ConfigHandle>,
+    ) -> Self {
+        let config = config.into();
         Self {
             datasource,
             repo,
+            webhooks: Webhooks::new(config.clone()),
             config,
+            builder_logs: None,
         }
     }
 
+    /// Attribute newly ingested fills against the target builder's daily logs.
+    ///
+    /// Without a logs source, fills only get the heuristic attribution assigned at compile time.
+    pub fn with_builder_logs(mut self, builder_logs: Arc<dyn BuilderLogsSource>) -> Self {
+        self.builder_logs = Some(builder_logs);
+        self
+    }
+
+    pub fn repo(&self) -> &Arc<Repository> {
+        &self.repo
+    }
+
+    pub fn builder_logs(&self) -> Option<&Arc<dyn BuilderLogsSource>> {
+        self.builder_logs.as_ref()
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load()
+    }
+
+    pub fn webhooks(&self) -> &Webhooks {
+        &self.webhooks
+    }
+
     /// Ensure fills are ingested for the given user/coin/time range.
     ///
-    /// Implements window correctness via `LOOKBACK_MS`.
+    /// Implements window correctness via `LOOKBACK_MS`. Windows longer than
+    /// `INGEST_CHUNK_MS` are fetched in at most [`MAX_INGEST_CHUNKS`] chunks (see
+    /// [`chunk_window`]), up to `INGEST_MAX_CONCURRENT_CHUNKS` at a time, and each chunk
+    /// is stored and attributed as soon as it arrives, so a failure keeps the chunks
+    /// already fetched.
+    #[tracing::instrument(level = "debug", skip_all, fields(user = %user))]
     pub async fn ensure_ingested(
         &self,
         user: &Address,
@@ -34,29 +77,182 @@ impl Ingestor {
         let fetch_from = self.compute_fetch_start(user, coin, from_ms).await?;
         let fetch_to = to_ms.unwrap_or_else(TimeMs::now);
 
+        let config = self.config.load();
+        let chunks = chunk_window(fetch_from, fetch_to, config.ingest_chunk_ms);
+        let chunk_count = chunks.len();
+        let mut result = IngestionResult {
+            fills_fetched: 0,
+            fills_new: 0,
+            fills_attributed: 0,
+            chunks: chunk_count,
+            fetch_from,
+            fetch_to,
+        };
+
+        let mut fetches = stream::iter(chunks)
+            .map(|(from, to)| self.ingest_chunk(user, coin, from, to))
+            .buffer_unordered(config.ingest_max_concurrent_chunks.max(1));
+        let mut done = 0;
+        while let Some(chunk) = fetches.next().await {
+            let chunk = chunk?;
+            done += 1;
+            result.fills_fetched += chunk.fills_fetched;
+            result.fills_new += chunk.fills_new;
+            result.fills_attributed += chunk.fills_attributed;
+            if chunk_count > 1 {
+                tracing::debug!(
+                    chunk = done,
+                    chunks = chunk_count,
+                    fills_fetched = chunk.fills_fetched,
+                    fills_new = chunk.fills_new,
+                    "Ingested chunk"
+                );
+            }
+        }
+
+        tracing::debug!(
+            fills_fetched = result.fills_fetched,
+            fills_new = result.fills_new,
+            fills_attributed = result.fills_attributed,
+            chunks = chunk_count,
+            "Ingested fills"
+        );
+        if result.fills_new > 0 {
+            self.webhooks.emit(WebhookEvent::FillsIngested {
+                user: user.clone(),
+                fills_new: result.fills_new,
+                from_ms: fetch_from.as_ms(),
+                to_ms: fetch_to.as_ms(),
+                source: "poll",
+            });
+        }
+        Ok(result)
+    }
+
+    /// Fetch, store, and attribute the fills of one chunk of an ingestion window.
+    ///
+    /// Follows the source's continuation tokens until the chunk is exhausted, storing each
+    /// page as it arrives.
+    async fn ingest_chunk(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from: TimeMs,
+        to: TimeMs,
+    ) -> Result<IngestionResult, IngestionError> {
         // Convert to DataSource signature (string-based)
         let coin_str = coin.map(|c| c.as_str()).unwrap_or("");
-        let fills = self
-            .datasource
-            .fetch_fills(
-                user.as_str(),
-                coin_str,
-                fetch_from.as_ms(),
-                fetch_to.as_ms(),
-            )
-            .await?;
+        let mut fills_fetched = 0;
+        let mut fills_new = 0;
+        let mut page = None;
+        loop {
+            let FillsPage { fills, next } = self
+                .datasource
+                .fetch_fills_page(user.as_str(), coin_str, from.as_ms(), to.as_ms(), page.as_ref())
+                .await?;
+            fills_fetched += fills.len();
+            fills_new += self.repo.insert_fills_batch(&fills).await?;
+            match next {
+                Some(token) => page = Some(token),
+                None => break,
+            }
+        }
 
-        let fills_fetched = fills.len();
-        let fills_new = self.repo.insert_fills_batch(&fills).await?;
+        let fills_attributed = if fills_new > 0 {
+            self.attribute_window(user, coin, from, to).await?
+        } else {
+            0
+        };
 
         Ok(IngestionResult {
             fills_fetched,
             fills_new,
-            fetch_from,
-            fetch_to,
+            fills_attributed,
+            chunks: 1,
+            fetch_from: from,
+            fetch_to: to,
         })
     }
 
+    /// Fetch `user`'s live equity and store it as the equity snapshot at the time of the
+    /// request. Returns `None`, storing nothing, when the source does not report one.
+    pub async fn sample_equity(&self, user: &Address) -> Result<Option<Decimal>, IngestionError> {
+        let at_ms = TimeMs::now();
+        let equity = self
+            .datasource
+            .fetch_equity(user.as_str(), at_ms.as_ms())
+            .await?;
+        if let Some(equity) = equity {
+            self.repo.upsert_equity_snapshot(user, at_ms, equity).await?;
+        }
+        Ok(equity)
+    }
+
+    /// Store fills pushed by a streaming source and attribute the window they cover.
+    ///
+    /// Returns the number of fills that were not already stored.
+    pub async fn ingest_streamed_fills(
+        &self,
+        user: &Address,
+        fills: &[Fill],
+    ) -> Result<usize, IngestionError> {
+        let fills_new = self.repo.insert_fills_batch(fills).await?;
+        if fills_new > 0 {
+            let from = fills.iter().map(|f| f.time_ms).min();
+            let to = fills.iter().map(|f| f.time_ms).max();
+            if let (Some(from), Some(to)) = (from, to) {
+                self.attribute_window(user, None, from, to).await?;
+                self.webhooks.emit(WebhookEvent::FillsIngested {
+                    user: user.clone(),
+                    fills_new,
+                    from_ms: from.as_ms(),
+                    to_ms: to.as_ms(),
+                    source: "stream",
+                });
+            }
+        }
+        Ok(fills_new)
+    }
+
+    /// Match fills in the fetched window against builder logs and upsert their attributions.
+    ///
+    /// Runs before compilation so that taint is computed from log-based attributions rather
+    /// than the heuristic defaults the compiler fills in for unattributed fills. Also used to
+    /// re-attribute already stored fills once their builder logs become available.
+    pub async fn attribute_window(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        fetch_from: TimeMs,
+        fetch_to: TimeMs,
+    ) -> Result<usize, IngestionError> {
+        let Some(builder_logs) = self.builder_logs.as_ref() else {
+            return Ok(0);
+        };
+        let config = self.config.load();
+        if config.builder_attribution_mode == BuilderAttributionMode::Heuristic {
+            return Ok(0);
+        }
+
+        let attribution = AttributionIngestor {
+            tolerances: config.match_tolerances.clone(),
+            heuristic: config.heuristic_attribution.clone(),
+        };
+        let attributed = attribution
+            .ingest_window(
+                &self.repo,
+                builder_logs.as_ref(),
+                &config,
+                user,
+                coin,
+                Some(fetch_from),
+                Some(fetch_to),
+            )
+            .await?;
+
+        Ok(attributed)
+    }
+
     /// Ensure deposits are ingested for the given user/time range.
     ///
     /// Implements window correctness via `LOOKBACK_MS`.
@@ -85,6 +281,69 @@ impl Ingestor {
         })
     }
 
+    /// Addresses of `user`'s sub-accounts, as reported by the data source.
+    pub async fn sub_accounts(&self, user: &Address) -> Result<Vec<Address>, IngestionError> {
+        Ok(self.datasource.fetch_sub_accounts(user.as_str()).await?)
+    }
+
+    /// Fetch the perp asset metadata and store it in `coins`.
+    ///
+    /// Returns what the data source listed; nothing is stored when it listed nothing.
+    pub async fn refresh_coin_meta(&self) -> Result<Vec<CoinMeta>, IngestionError> {
+        let coins = self.datasource.fetch_coin_meta().await?;
+        if !coins.is_empty() {
+            self.repo.upsert_coins(&coins, TimeMs::now()).await?;
+        }
+        Ok(coins)
+    }
+
+    /// Ensure the marks of `coin` are stored for every bucket from the one containing
+    /// `from_ms` to the one containing `to_ms`.
+    ///
+    /// Only buckets outside the span fetched before are requested. The current bucket is
+    /// fetched but not recorded as fetched, since the source may not have its price yet.
+    pub async fn ensure_mark_prices(
+        &self,
+        coin: &Coin,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> Result<(), IngestionError> {
+        let now = TimeMs::now();
+        let from = bucket_start(from_ms);
+        let to = bucket_start(to_ms.min(now));
+        if from > to {
+            return Ok(());
+        }
+        let shift =
+            |t: TimeMs, buckets: i64| TimeMs::new(t.as_ms() + buckets * MARK_PRICE_BUCKET_MS);
+        let last_finished = shift(bucket_start(now), -1);
+
+        // Gaps start or end next to the covered span, so it stays one span.
+        let gaps = match self.repo.mark_price_coverage(coin).await? {
+            None => vec![(from, to)],
+            Some((covered_from, covered_to)) => {
+                let mut gaps = Vec::new();
+                if from < covered_from {
+                    gaps.push((from, shift(covered_from, -1)));
+                }
+                if to > covered_to {
+                    gaps.push((shift(covered_to, 1), to));
+                }
+                gaps
+            }
+        };
+        for (gap_from, gap_to) in gaps {
+            let marks = self
+                .datasource
+                .fetch_mark_prices(coin.as_str(), gap_from.as_ms(), gap_to.as_ms())
+                .await?;
+            let covered =
+                (gap_from <= last_finished).then(|| (gap_from, gap_to.min(last_finished)));
+            self.repo.store_mark_prices(coin, &marks, covered).await?;
+        }
+        Ok(())
+    }
+
     async fn compute_fetch_start(
         &self,
         _user: &Address,      // TODO(PR-XXX): Use for per-user watermark lookups
@@ -92,7 +351,7 @@ impl Ingestor {
         requested_from: Option<TimeMs>,
     ) -> Result<TimeMs, IngestionError> {
         let requested = requested_from.unwrap_or(TimeMs::new(0));
-        let lookback = self.config.lookback_ms;
+        let lookback = self.config.load().lookback_ms;
         // Clamp to 0 to avoid negative timestamps (Hyperliquid API rejects them)
         let fetch_from = TimeMs::new((requested.as_ms() - lookback).max(0));
 
@@ -107,10 +366,48 @@ impl Ingestor {
     }
 }
 
+/// Most chunks one ingestion window is fetched in; longer windows get wider chunks.
+const MAX_INGEST_CHUNKS: i64 = 64;
+
+/// Split the inclusive window `[from, to]` into inclusive chunks whose boundaries fall on
+/// multiples of `chunk_ms` (UTC midnight for daily chunks).
+///
+/// A window that would take more than [`MAX_INGEST_CHUNKS`] chunks, such as an
+/// open-ended one starting at the epoch, is split at multiples of a wider chunk instead,
+/// itself a multiple of `chunk_ms`. Any window is fetched whole when `chunk_ms` is not
+/// positive.
+fn chunk_window(from: TimeMs, to: TimeMs, chunk_ms: i64) -> Vec<(TimeMs, TimeMs)> {
+    let (from, to) = (from.as_ms(), to.as_ms());
+    if chunk_ms <= 0 || to - from < chunk_ms {
+        return vec![(TimeMs::new(from), TimeMs::new(to))];
+    }
+
+    // Aligned chunks of width w number at most span / w + 1, so w > span / (MAX - 1).
+    let span = to.saturating_sub(from).saturating_add(1);
+    let narrowest = span / (MAX_INGEST_CHUNKS - 1) + 1;
+    let chunk_ms = chunk_ms.saturating_mul((narrowest - 1) / chunk_ms + 1);
+
+    let mut chunks = Vec::new();
+    let mut start = from;
+    // A boundary past `to`, or past i64::MAX for an unbounded window, closes the last chunk.
+    while let Some(boundary) = (start.div_euclid(chunk_ms) + 1)
+        .checked_mul(chunk_ms)
+        .filter(|&boundary| boundary <= to)
+    {
+        chunks.push((TimeMs::new(start), TimeMs::new(boundary - 1)));
+        start = boundary;
+    }
+    chunks.push((TimeMs::new(start), TimeMs::new(to)));
+    chunks
+}
+
 #[derive(Debug)]
 pub struct IngestionResult {
     pub fills_fetched: usize,
     pub fills_new: usize,
+    pub fills_attributed: usize,
+    /// Number of chunks the window was fetched in.
+    pub chunks: usize,
     pub fetch_from: TimeMs,
     pub fetch_to: TimeMs,
 }
@@ -129,17 +426,48 @@ pub enum IngestionError {
     DataSource(#[from] DataSourceError),
     #[error(transparent)]
     Db(#[from] sqlx::Error),
+    #[error(transparent)]
+    Attribution(#[from] AttributionIngestionError),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::datasource::MockDataSource;
+    use crate::datasource::{BuilderLogsError, MockDataSource};
     use crate::db::migrations::init_db;
-    use crate::domain::{Address, Coin, Decimal, Deposit, Fill, Side, TimeMs};
+    use crate::domain::{
+        Address, BuilderLogFill, Coin, Decimal, Deposit, Fill, MarkPrice, Side, TimeMs,
+    };
     use std::str::FromStr;
     use tempfile::TempDir;
 
+    /// Serves the same builder log rows for every requested day.
+    struct StaticLogs(Vec<BuilderLogFill>);
+
+    #[async_trait::async_trait]
+    impl BuilderLogsSource for StaticLogs {
+        async fn fetch_and_parse_day(
+            &self,
+            _builder: &Address,
+            _yyyymmdd: &str,
+        ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn log_for(fill: &Fill) -> BuilderLogFill {
+        BuilderLogFill {
+            time_ms: fill.time_ms,
+            user: fill.user.clone(),
+            coin: fill.coin.clone(),
+            side: fill.side,
+            px: fill.px,
+            sz: fill.sz,
+            tid: fill.tid,
+            oid: fill.oid,
+        }
+    }
+
     async fn setup_repo() -> (Arc<Repository>, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db_path = temp_dir
@@ -154,13 +482,41 @@ mod tests {
     fn test_config(lookback_ms: i64) -> Config {
         Config {
             port: 0,
+            bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+            admin_port: None,
+            admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
             database_path: ":memory:".to_string(),
+            network: crate::config::Network::Mainnet,
             hyperliquid_api_url: "http://example.invalid".to_string(),
-            target_builder: "0x0".to_string(),
+            hyperliquid_ws_url: None,
+            target_builder: "0x0000000000000000000000000000000000000000".to_string(),
             builder_attribution_mode: crate::config::BuilderAttributionMode::Auto,
             pnl_mode: crate::config::PnlMode::Gross,
             lookback_ms,
             leaderboard_users: vec![],
+            match_tolerances: crate::engine::MatchTolerances::default(),
+            heuristic_attribution: crate::engine::HeuristicAttributor::default(),
+            job_limits: crate::orchestration::jobs::JobLimits::default(),
+            builder_logs_auto_backfill: false,
+            builder_logs_max_concurrent_downloads: 4,
+            api_keys: Vec::new(),
+            response_cache_ttl_ms: 0,
+            sub_account_rollup: false,
+            ingest_chunk_ms: 86_400_000,
+            ingest_max_concurrent_chunks: 4,
+            record_raw_payloads: false,
+            maintenance: Default::default(),
+            db_pool: Default::default(),
+            idempotency_ttl_ms: 0,
+            leaderboard_snapshot_interval_ms: 0,
+            equity_sample_interval_ms: 0,
+            response_compression: true,
+            max_request_body_bytes: 2 * 1024 * 1024,
+            startup_integrity_check: Default::default(),
+            read_only: false,
+            coin_meta_refresh_interval_ms: 0,
+            overflow_policy: Default::default(),
+            webhooks: Default::default(),
         }
     }
 
@@ -234,6 +590,60 @@ mod tests {
         assert_eq!(deposits.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_sample_equity_stores_live_account_value() {
+        let user = Address::new("0x123".to_string());
+        let equity = Decimal::from_str("1234.5").unwrap();
+        let ds = Arc::new(MockDataSource::new().with_equity(equity));
+
+        let (repo, _temp) = setup_repo().await;
+        let ingestor = Ingestor::new(ds, repo.clone(), test_config(0));
+
+        assert_eq!(ingestor.sample_equity(&user).await.unwrap(), Some(equity));
+        let (_, stored) = repo
+            .get_equity_snapshot_at_or_before(&user, TimeMs::now())
+            .await
+            .unwrap()
+            .expect("snapshot stored");
+        assert_eq!(stored, equity);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_mark_prices_fetches_only_uncovered_buckets() {
+        const HOUR: i64 = MARK_PRICE_BUCKET_MS;
+        let btc = Coin::new("BTC".to_string());
+        let marks = (0..10)
+            .map(|h| MarkPrice {
+                coin: btc.clone(),
+                time_ms: TimeMs::new(h * HOUR),
+                px: Decimal::from_str(&format!("{}", 100 + h)).unwrap(),
+            })
+            .collect();
+        let ds = Arc::new(MockDataSource::new().with_mark_prices(marks));
+        let (repo, _temp) = setup_repo().await;
+        let ingestor = Ingestor::new(ds.clone(), repo.clone(), test_config(0));
+
+        let at = |h: i64| TimeMs::new(h * HOUR);
+        let mid_fifth_hour = TimeMs::new(5 * HOUR + 10);
+        ingestor.ensure_mark_prices(&btc, at(3), mid_fifth_hour).await.unwrap();
+        assert_eq!(ds.mark_price_fetches(), 1);
+        assert_eq!(repo.mark_price_coverage(&btc).await.unwrap(), Some((at(3), at(5))));
+
+        // Inside the covered span: nothing to fetch.
+        ingestor.ensure_mark_prices(&btc, at(4), at(5)).await.unwrap();
+        assert_eq!(ds.mark_price_fetches(), 1);
+
+        // Overlapping both ends: one fetch per side.
+        ingestor.ensure_mark_prices(&btc, at(1), at(8)).await.unwrap();
+        assert_eq!(ds.mark_price_fetches(), 3);
+        assert_eq!(repo.mark_price_coverage(&btc).await.unwrap(), Some((at(1), at(8))));
+
+        let series = repo.query_mark_prices(&btc, at(0), at(9)).await.unwrap();
+        assert_eq!(series.at(at(0)), None);
+        assert_eq!(series.at(TimeMs::new(2 * HOUR + 1)), Some(Decimal::from_str("102").unwrap()));
+        assert_eq!(series.at(at(9)), None);
+    }
+
     #[tokio::test]
     async fn test_ensure_ingested_is_idempotent() {
         let user = Address::new("0x123".to_string());
@@ -318,4 +728,231 @@ mod tests {
 
         assert_eq!(result.fetch_from.as_ms(), 900);
     }
+
+    #[tokio::test]
+    async fn test_ensure_ingested_attributes_new_fills_from_builder_logs() {
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let matched = make_test_fill(&user, &coin, 1000, 1);
+        let unmatched = make_test_fill(&user, &coin, 60_000, 2);
+        let ds = Arc::new(
+            MockDataSource::new()
+                .with_fill(matched.clone())
+                .with_fill(unmatched.clone()),
+        );
+        let logs = Arc::new(StaticLogs(vec![log_for(&matched)]));
+
+        let (repo, _temp) = setup_repo().await;
+        let ingestor =
+            Ingestor::new(ds, repo.clone(), test_config(0)).with_builder_logs(logs);
+
+        let result = ingestor
+            .ensure_ingested(&user, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.fills_attributed, 2);
+
+        let attributions = repo
+            .query_attributions_full(&[matched.fill_key.clone(), unmatched.fill_key.clone()])
+            .await
+            .unwrap();
+        assert!(attributions[&matched.fill_key].attributed);
+        assert_eq!(
+            attributions[&matched.fill_key].mode,
+            crate::domain::AttributionMode::Logs
+        );
+        // Auto mode falls back to the heuristic for fills missing from the logs.
+        assert!(!attributions[&unmatched.fill_key].attributed);
+
+        let rerun = ingestor
+            .ensure_ingested(&user, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(rerun.fills_attributed, 0, "No new fills, no log fetch");
+    }
+
+    #[tokio::test]
+    async fn test_ensure_ingested_skips_logs_in_heuristic_mode() {
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let fill = make_test_fill(&user, &coin, 1000, 1);
+        let ds = Arc::new(MockDataSource::new().with_fill(fill.clone()));
+        let logs = Arc::new(StaticLogs(vec![log_for(&fill)]));
+
+        let mut config = test_config(0);
+        config.builder_attribution_mode = crate::config::BuilderAttributionMode::Heuristic;
+        let (repo, _temp) = setup_repo().await;
+        let ingestor = Ingestor::new(ds, repo.clone(), config).with_builder_logs(logs);
+
+        let result = ingestor
+            .ensure_ingested(&user, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.fills_attributed, 0);
+        assert!(repo
+            .query_attributions(std::slice::from_ref(&fill.fill_key))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    const DAY_MS: i64 = 86_400_000;
+
+    /// Serves a fixed set of fills, failing any request whose window contains `fail_at`.
+    #[derive(Debug)]
+    struct FlakySource {
+        inner: MockDataSource,
+        fail_at: Option<i64>,
+    }
+
+    #[async_trait::async_trait]
+    impl DataSource for FlakySource {
+        async fn fetch_fills(
+            &self,
+            user: &str,
+            coin: &str,
+            from_ms: i64,
+            to_ms: i64,
+        ) -> Result<Vec<Fill>, DataSourceError> {
+            if self.fail_at.is_some_and(|t| (from_ms..=to_ms).contains(&t)) {
+                return Err(DataSourceError::RateLimited);
+            }
+            self.inner.fetch_fills(user, coin, from_ms, to_ms).await
+        }
+
+        async fn fetch_deposits(
+            &self,
+            user: &str,
+            from_ms: i64,
+            to_ms: i64,
+        ) -> Result<Vec<Deposit>, DataSourceError> {
+            self.inner.fetch_deposits(user, from_ms, to_ms).await
+        }
+
+        async fn fetch_equity(
+            &self,
+            user: &str,
+            at_ms: i64,
+        ) -> Result<Option<Decimal>, DataSourceError> {
+            self.inner.fetch_equity(user, at_ms).await
+        }
+    }
+
+    #[test]
+    fn test_chunk_window_aligns_to_chunk_boundaries() {
+        let chunks = chunk_window(TimeMs::new(DAY_MS + 5), TimeMs::new(3 * DAY_MS + 10), DAY_MS);
+        let chunks: Vec<(i64, i64)> = chunks
+            .into_iter()
+            .map(|(from, to)| (from.as_ms(), to.as_ms()))
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![
+                (DAY_MS + 5, 2 * DAY_MS - 1),
+                (2 * DAY_MS, 3 * DAY_MS - 1),
+                (3 * DAY_MS, 3 * DAY_MS + 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunk_window_keeps_short_windows_whole() {
+        assert_eq!(
+            chunk_window(TimeMs::new(DAY_MS), TimeMs::new(DAY_MS + 1000), DAY_MS),
+            vec![(TimeMs::new(DAY_MS), TimeMs::new(DAY_MS + 1000))]
+        );
+    }
+
+    #[test]
+    fn test_chunk_window_caps_long_and_open_windows() {
+        // Open-ended (from the epoch or unbounded) and just past a long lookback.
+        for (from, to) in [
+            (0, 20_000 * DAY_MS + 7),
+            (0, i64::MAX),
+            (DAY_MS + 5, 3_000 * DAY_MS),
+        ] {
+            let chunks = chunk_window(TimeMs::new(from), TimeMs::new(to), DAY_MS);
+            assert!(chunks.len() > 1);
+            assert!(chunks.len() <= MAX_INGEST_CHUNKS as usize, "{}", chunks.len());
+            assert_eq!(chunks.first().unwrap().0.as_ms(), from);
+            assert_eq!(chunks.last().unwrap().1.as_ms(), to);
+            for pair in chunks.windows(2) {
+                assert_eq!(pair[0].1.as_ms() + 1, pair[1].0.as_ms());
+                assert_eq!(pair[1].0.as_ms() % DAY_MS, 0);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ensure_ingested_fetches_long_window_in_chunks() {
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let fills = (1..=5)
+            .map(|day| make_test_fill(&user, &coin, day * DAY_MS + 1000, day))
+            .collect();
+        let ds = Arc::new(MockDataSource::new().with_fills(fills));
+
+        let (repo, _temp) = setup_repo().await;
+        let ingestor = Ingestor::new(ds, repo.clone(), test_config(0));
+
+        let result = ingestor
+            .ensure_ingested(&user, None, Some(TimeMs::new(DAY_MS)), Some(TimeMs::new(6 * DAY_MS)))
+            .await
+            .unwrap();
+        assert_eq!(result.chunks, 6);
+        assert_eq!(result.fills_fetched, 5);
+        assert_eq!(result.fills_new, 5);
+        assert_eq!(result.fetch_from.as_ms(), DAY_MS);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_ingested_follows_page_tokens() {
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let fills = (1..=5)
+            .map(|tid| make_test_fill(&user, &coin, 1000 + tid, tid))
+            .collect();
+        let ds = Arc::new(MockDataSource::new().with_fills(fills).with_page_size(2));
+
+        let (repo, _temp) = setup_repo().await;
+        let ingestor = Ingestor::new(ds, repo.clone(), test_config(0));
+
+        let result = ingestor
+            .ensure_ingested(&user, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.fills_fetched, 5);
+        assert_eq!(result.fills_new, 5);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_ingested_keeps_chunks_stored_before_a_failure() {
+        let user = Address::new("0x123".to_string());
+        let coin = Coin::new("BTC".to_string());
+        let fills = (1..=5)
+            .map(|day| make_test_fill(&user, &coin, day * DAY_MS + 1000, day))
+            .collect();
+        let ds = Arc::new(FlakySource {
+            inner: MockDataSource::new().with_fills(fills),
+            fail_at: Some(4 * DAY_MS + 1000),
+        });
+
+        let (repo, _temp) = setup_repo().await;
+        let mut config = test_config(0);
+        config.ingest_max_concurrent_chunks = 1;
+        let ingestor = Ingestor::new(ds, repo.clone(), config);
+
+        let result = ingestor
+            .ensure_ingested(&user, None, Some(TimeMs::new(DAY_MS)), Some(TimeMs::new(6 * DAY_MS)))
+            .await;
+        assert!(matches!(
+            result,
+            Err(IngestionError::DataSource(DataSourceError::RateLimited))
+        ));
+
+        // Days 1-3 were fetched and stored before day 4 failed.
+        let stored = repo.query_fills(&user, None, None, None).await.unwrap();
+        assert_eq!(stored.len(), 3);
+    }
 }
+
@@ -0,0 +1,429 @@
+//! Fair admission of ingest and compile jobs across users.
+//!
+//! Every ingest/compile run takes a [`JobPermit`] first. At most `max_workers` permits are
+//! out at once and each user holds at most `max_per_user` of them, so one user's large
+//! backfill cannot take every worker. Waiting jobs are granted interactive (API-triggered)
+//! before background, and round-robin across users within a priority.
+//!
+//! On shutdown the queue is closed: waiting and new jobs are turned away, and
+//! [`JobQueue::wait_idle`] lets the caller wait for running jobs to finish.
+
+use crate::domain::Address;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+use tokio::sync::{oneshot, watch};
+
+/// Concurrency limits for the job queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobLimits {
+    /// Jobs running at once across all users.
+    pub max_workers: usize,
+    /// Jobs running at once for a single user.
+    pub max_per_user: usize,
+    /// Coins compiled at once within a single job.
+    pub coin_parallelism: usize,
+}
+
+impl Default for JobLimits {
+    fn default() -> Self {
+        Self {
+            max_workers: 8,
+            max_per_user: 2,
+            coin_parallelism: 4,
+        }
+    }
+}
+
+/// Scheduling class of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JobPriority {
+    /// Triggered by an API request that is waiting on it.
+    Interactive,
+    /// Backfills and maintenance nobody is waiting on.
+    Background,
+}
+
+impl JobPriority {
+    fn lane(self) -> usize {
+        match self {
+            JobPriority::Interactive => 0,
+            JobPriority::Background => 1,
+        }
+    }
+}
+
+/// Point-in-time queue counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JobQueueStats {
+    pub running: usize,
+    pub waiting_interactive: usize,
+    pub waiting_background: usize,
+}
+
+/// Returned by [`JobQueue::acquire`] once the queue has been closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("job queue is closed for shutdown")]
+pub struct JobQueueClosed;
+
+/// Shared, cloneable handle to the job queue.
+#[derive(Clone)]
+pub struct JobQueue {
+    shared: Arc<Mutex<QueueState>>,
+}
+
+impl JobQueue {
+    pub fn new(limits: JobLimits) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(QueueState {
+                limits: JobLimits {
+                    max_workers: limits.max_workers.max(1),
+                    max_per_user: limits.max_per_user.max(1),
+                    ..limits
+                },
+                running: 0,
+                running_tx: watch::Sender::new(0),
+                closed: false,
+                in_flight: HashMap::new(),
+                lanes: [Lane::default(), Lane::default()],
+            })),
+        }
+    }
+
+    /// Wait until a job for `user` may run.
+    ///
+    /// The job holds its worker until the returned permit is dropped. Dropping the future
+    /// before it resolves gives up the place in the queue.
+    ///
+    /// # Errors
+    /// Fails if the queue is closed before the job gets a worker.
+    pub async fn acquire(
+        &self,
+        user: &Address,
+        priority: JobPriority,
+    ) -> Result<JobPermit, JobQueueClosed> {
+        let (tx, rx) = oneshot::channel();
+        let grants = {
+            let mut state = lock(&self.shared);
+            if state.closed {
+                return Err(JobQueueClosed);
+            }
+            state.lanes[priority.lane()].push(user.clone(), tx);
+            state.dispatch(&self.shared)
+        };
+        send_grants(grants);
+
+        // Closing the queue drops the waiting senders.
+        rx.await.map_err(|_| JobQueueClosed)
+    }
+
+    /// Stop admitting jobs and turn away every waiting one. Running jobs keep their workers.
+    pub fn close(&self) {
+        let waiting = {
+            let mut state = lock(&self.shared);
+            state.closed = true;
+            std::mem::take(&mut state.lanes)
+        };
+        // Dropped outside the lock: waiters wake up as their senders go away.
+        drop(waiting);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        lock(&self.shared).closed
+    }
+
+    /// Wait until no job holds a worker.
+    pub async fn wait_idle(&self) {
+        let mut running = lock(&self.shared).running_tx.subscribe();
+        // The sender lives in the queue state, which outlives this handle.
+        let _ = running.wait_for(|n| *n == 0).await;
+    }
+
+    pub fn stats(&self) -> JobQueueStats {
+        let state = lock(&self.shared);
+        JobQueueStats {
+            running: state.running,
+            waiting_interactive: state.lanes[JobPriority::Interactive.lane()].len(),
+            waiting_background: state.lanes[JobPriority::Background.lane()].len(),
+        }
+    }
+}
+
+impl fmt::Debug for JobQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobQueue")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(JobLimits::default())
+    }
+}
+
+/// A running job's worker slot; released on drop.
+pub struct JobPermit {
+    shared: Arc<Mutex<QueueState>>,
+    user: Address,
+}
+
+impl Drop for JobPermit {
+    fn drop(&mut self) {
+        let grants = {
+            let mut state = lock(&self.shared);
+            state.running -= 1;
+            state.running_tx.send_replace(state.running);
+            if let Some(count) = state.in_flight.get_mut(&self.user) {
+                *count -= 1;
+                if *count == 0 {
+                    state.in_flight.remove(&self.user);
+                }
+            }
+            state.dispatch(&self.shared)
+        };
+        send_grants(grants);
+    }
+}
+
+type Grant = (oneshot::Sender<JobPermit>, JobPermit);
+
+struct QueueState {
+    limits: JobLimits,
+    running: usize,
+    /// Publishes `running` for [`JobQueue::wait_idle`].
+    running_tx: watch::Sender<usize>,
+    closed: bool,
+    in_flight: HashMap<Address, usize>,
+    /// Waiting jobs, indexed by [`JobPriority::lane`].
+    lanes: [Lane; 2],
+}
+
+impl QueueState {
+    /// Hand out permits while workers are free.
+    ///
+    /// Permits are returned rather than sent so the caller can deliver them after releasing
+    /// the lock: a permit whose waiter is gone is dropped on delivery, which locks again.
+    fn dispatch(&mut self, shared: &Arc<Mutex<QueueState>>) -> Vec<Grant> {
+        let mut grants = Vec::new();
+        while self.running < self.limits.max_workers {
+            let Some((user, tx)) = self.next_waiter() else {
+                break;
+            };
+            self.running += 1;
+            *self.in_flight.entry(user.clone()).or_default() += 1;
+            grants.push((
+                tx,
+                JobPermit {
+                    shared: shared.clone(),
+                    user,
+                },
+            ));
+        }
+        self.running_tx.send_replace(self.running);
+        grants
+    }
+
+    /// Oldest waiting job of the next user in round-robin order that is under its limit,
+    /// checking interactive jobs first.
+    fn next_waiter(&mut self) -> Option<(Address, oneshot::Sender<JobPermit>)> {
+        let max_per_user = self.limits.max_per_user;
+        for lane in &mut self.lanes {
+            for _ in 0..lane.users.len() {
+                let Some(user) = lane.users.pop_front() else {
+                    break;
+                };
+                let Some(jobs) = lane.waiting.get_mut(&user) else {
+                    continue;
+                };
+                // Waiters whose request was dropped just leave the queue.
+                while jobs.front().is_some_and(|tx| tx.is_closed()) {
+                    jobs.pop_front();
+                }
+                if jobs.is_empty() {
+                    lane.waiting.remove(&user);
+                    continue;
+                }
+                if self.in_flight.get(&user).copied().unwrap_or(0) >= max_per_user {
+                    lane.users.push_back(user);
+                    continue;
+                }
+
+                let tx = jobs.pop_front()?;
+                if jobs.is_empty() {
+                    lane.waiting.remove(&user);
+                } else {
+                    lane.users.push_back(user.clone());
+                }
+                return Some((user, tx));
+            }
+        }
+        None
+    }
+}
+
+/// Waiting jobs of one priority.
+#[derive(Default)]
+struct Lane {
+    /// Users with waiting jobs, in the order they get their next turn.
+    users: VecDeque<Address>,
+    waiting: HashMap<Address, VecDeque<oneshot::Sender<JobPermit>>>,
+}
+
+impl Lane {
+    fn push(&mut self, user: Address, tx: oneshot::Sender<JobPermit>) {
+        let jobs = self.waiting.entry(user.clone()).or_default();
+        if jobs.is_empty() {
+            self.users.push_back(user);
+        }
+        jobs.push_back(tx);
+    }
+
+    fn len(&self) -> usize {
+        self.waiting.values().map(VecDeque::len).sum()
+    }
+}
+
+fn lock(shared: &Mutex<QueueState>) -> MutexGuard<'_, QueueState> {
+    // Queue state is only mutated in small non-panicking sections; keep going if a holder
+    // panicked anyway.
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn send_grants(grants: Vec<Grant>) {
+    for (tx, permit) in grants {
+        // A waiter that went away hands the permit back, and dropping it frees the worker.
+        let _ = tx.send(permit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn user(name: &str) -> Address {
+        Address::new(name.to_string())
+    }
+
+    fn limits(max_workers: usize, max_per_user: usize) -> JobLimits {
+        JobLimits {
+            max_workers,
+            max_per_user,
+            ..JobLimits::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_per_user_limit_leaves_room_for_others() {
+        let queue = JobQueue::new(limits(4, 2));
+        let whale = user("0xwhale");
+
+        let _w1 = queue.acquire(&whale, JobPriority::Background).await.unwrap();
+        let _w2 = queue.acquire(&whale, JobPriority::Background).await.unwrap();
+        let mut w3 = Box::pin(queue.acquire(&whale, JobPriority::Background));
+        assert!((&mut w3).now_or_never().is_none());
+
+        let small = queue
+            .acquire(&user("0xsmall"), JobPriority::Background)
+            .now_or_never();
+        assert!(small.is_some());
+        assert_eq!(
+            queue.stats(),
+            JobQueueStats {
+                running: 3,
+                waiting_interactive: 0,
+                waiting_background: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_across_users() {
+        let queue = JobQueue::new(limits(1, 1));
+        let (a, b) = (user("0xa"), user("0xb"));
+
+        let running = queue.acquire(&a, JobPriority::Background).await.unwrap();
+        let mut a2 = Box::pin(queue.acquire(&a, JobPriority::Background));
+        let mut a3 = Box::pin(queue.acquire(&a, JobPriority::Background));
+        let mut b1 = Box::pin(queue.acquire(&b, JobPriority::Background));
+        assert!((&mut a2).now_or_never().is_none());
+        assert!((&mut a3).now_or_never().is_none());
+        assert!((&mut b1).now_or_never().is_none());
+
+        drop(running);
+        let a2 = (&mut a2).now_or_never().expect("a's next job runs first");
+        assert!((&mut b1).now_or_never().is_none());
+
+        drop(a2);
+        let b1 = (&mut b1).now_or_never().expect("b gets a turn before a again");
+        assert!((&mut a3).now_or_never().is_none());
+
+        drop(b1);
+        assert!((&mut a3).now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_interactive_jobs_go_first() {
+        let queue = JobQueue::new(limits(1, 1));
+        let (a, b, c) = (user("0xa"), user("0xb"), user("0xc"));
+
+        let running = queue.acquire(&a, JobPriority::Background).await.unwrap();
+        let mut backfill = Box::pin(queue.acquire(&b, JobPriority::Background));
+        let mut request = Box::pin(queue.acquire(&c, JobPriority::Interactive));
+        assert!((&mut backfill).now_or_never().is_none());
+        assert!((&mut request).now_or_never().is_none());
+
+        drop(running);
+        let request = (&mut request).now_or_never().expect("interactive job runs");
+        assert!((&mut backfill).now_or_never().is_none());
+
+        drop(request);
+        assert!((&mut backfill).now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_hold_a_worker() {
+        let queue = JobQueue::new(limits(1, 1));
+        let (a, b) = (user("0xa"), user("0xb"));
+
+        let running = queue.acquire(&a, JobPriority::Interactive).await.unwrap();
+        let mut abandoned = Box::pin(queue.acquire(&b, JobPriority::Interactive));
+        assert!((&mut abandoned).now_or_never().is_none());
+        drop(abandoned);
+        drop(running);
+
+        assert!(queue
+            .acquire(&user("0xc"), JobPriority::Background)
+            .now_or_never()
+            .is_some());
+        assert_eq!(queue.stats().waiting_interactive, 0);
+    }
+
+    #[tokio::test]
+    async fn test_close_turns_away_waiters_and_drains_running_jobs() {
+        let queue = JobQueue::new(limits(1, 1));
+        let (a, b) = (user("0xa"), user("0xb"));
+
+        let running = queue.acquire(&a, JobPriority::Interactive).await.unwrap();
+        let mut waiting = Box::pin(queue.acquire(&b, JobPriority::Background));
+        assert!((&mut waiting).now_or_never().is_none());
+
+        queue.close();
+        assert!(matches!(
+            (&mut waiting).now_or_never(),
+            Some(Err(JobQueueClosed))
+        ));
+        assert!(matches!(
+            queue.acquire(&b, JobPriority::Interactive).now_or_never(),
+            Some(Err(JobQueueClosed))
+        ));
+
+        let mut idle = Box::pin(queue.wait_idle());
+        assert!((&mut idle).now_or_never().is_none());
+        drop(running);
+        assert!((&mut idle).now_or_never().is_some());
+    }
+}
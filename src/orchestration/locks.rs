@@ -0,0 +1,99 @@
+//! In-process async locks keyed by scope.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// One async mutex per key, created on first use.
+///
+/// Holders of the same key run one at a time while different keys proceed in parallel.
+/// Entries only live as long as someone holds or waits on them.
+pub struct KeyedLocks<K> {
+    locks: Arc<Mutex<HashMap<K, Weak<AsyncMutex<()>>>>>,
+}
+
+impl<K: Eq + Hash + Clone> KeyedLocks<K> {
+    pub fn new() -> Self {
+        Self {
+            locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for exclusive access to `key`; released when the guard is dropped.
+    pub async fn lock(&self, key: &K) -> OwnedMutexGuard<()> {
+        let mutex = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            match locks.get(key).and_then(Weak::upgrade) {
+                Some(mutex) => mutex,
+                None => {
+                    // Drop entries nobody holds any more before adding a new one.
+                    locks.retain(|_, lock| lock.strong_count() > 0);
+                    let mutex = Arc::new(AsyncMutex::new(()));
+                    locks.insert(key.clone(), Arc::downgrade(&mutex));
+                    mutex
+                }
+            }
+        };
+        mutex.lock_owned().await
+    }
+
+    /// Number of keys currently held or waited on.
+    pub fn active(&self) -> usize {
+        let locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        locks.values().filter(|lock| lock.strong_count() > 0).count()
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for KeyedLocks<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> Clone for KeyedLocks<K> {
+    fn clone(&self) -> Self {
+        Self {
+            locks: self.locks.clone(),
+        }
+    }
+}
+
+impl<K> fmt::Debug for KeyedLocks<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedLocks").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[tokio::test]
+    async fn test_same_key_is_exclusive() {
+        let locks = KeyedLocks::new();
+
+        let held = locks.lock(&"a").await;
+        let mut waiting = Box::pin(locks.lock(&"a"));
+        assert!((&mut waiting).now_or_never().is_none());
+        assert!(locks.lock(&"b").now_or_never().is_some());
+
+        drop(held);
+        assert!((&mut waiting).now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_released_keys_are_forgotten() {
+        let locks = KeyedLocks::new();
+
+        let a = locks.lock(&"a").await;
+        let b = locks.lock(&"b").await;
+        assert_eq!(locks.active(), 2);
+
+        drop(a);
+        drop(b);
+        assert_eq!(locks.active(), 0);
+    }
+}
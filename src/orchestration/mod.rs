@@ -1,5 +1,10 @@
 //! Orchestration layer for coordinating ingestion and compilation workflows.
 
 pub mod attribution;
+pub mod builder_logs_backfill;
 pub mod ensure;
+pub mod jobs;
+pub mod locks;
 pub mod orchestrator;
+pub mod replay;
+pub mod webhooks;
@@ -1,35 +1,288 @@
-use crate::compile::Compiler;
+use crate::compile::{CompileError, CompileOptions, Compiler};
+use crate::datasource::hyperliquid::replay_fills;
+use crate::datasource::BuilderLogsDownloadStats;
 use crate::db::Repository;
-use crate::domain::{Address, Coin, TimeMs};
-use crate::orchestration::ensure::{Ingestor, IngestionError};
-use std::sync::Arc;
+use crate::domain::mark_price::bucket_start;
+use crate::domain::{
+    Address, Coin, CoinRegistry, Decimal, Fill, FillOrderingKey, MarkSeries, TimeMs,
+};
+use crate::orchestration::builder_logs_backfill::{
+    range_bounds, BuilderLogsBackfillReport, BuilderLogsDayOutcome, BuilderLogsUserOutcome,
+};
+use crate::orchestration::ensure::{IngestionError, IngestionResult, Ingestor};
+use crate::orchestration::jobs::{JobPriority, JobQueue, JobQueueClosed};
+use crate::orchestration::locks::KeyedLocks;
+use crate::orchestration::replay::{diff_fills, ReplayReport};
+use crate::orchestration::webhooks::{LifecycleTaint, WebhookEvent, WebhookEventKind, Webhooks};
+use arc_swap::ArcSwap;
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::sync::OwnedMutexGuard;
+use tokio::task::JoinSet;
+use tracing::{info, warn, Instrument};
 
 #[derive(Clone)]
 pub struct Orchestrator {
     ingestor: Ingestor,
     repo: Arc<Repository>,
+    jobs: JobQueue,
+    /// Serializes compiles of the same (user, coin); lifecycle ids and the watermark are
+    /// derived from the previous compile's output.
+    compile_locks: KeyedLocks<(Address, Coin)>,
+    coin_parallelism: usize,
+    /// Users with an attribution backfill queued or running.
+    backfills: Arc<Mutex<HashSet<Address>>>,
+    /// Bumped whenever a user's derived tables change; see [`Self::compile_generation`].
+    generations: Arc<Mutex<HashMap<Address, u64>>>,
+    /// Perp asset metadata, replaced by [`Self::refresh_coins`].
+    coins: Arc<ArcSwap<CoinRegistry>>,
+    /// State of the background relabel; see [`Self::spawn_strategy_relabel`].
+    relabel: Arc<Mutex<RelabelState>>,
+    /// Sub-account lists by parent, with when each was fetched; see [`Self::sub_accounts`].
+    sub_accounts: Arc<Mutex<HashMap<Address, SubAccounts>>>,
+}
+
+/// Lifecycles relabelled per transaction by [`Orchestrator::spawn_strategy_relabel`].
+const RELABEL_BATCH: usize = 1_000;
+
+/// A parent's sub-accounts and when they were fetched.
+type SubAccounts = (Instant, Vec<Address>);
+
+/// How long a fetched sub-account list is reused by [`Orchestrator::sub_accounts`].
+const SUB_ACCOUNTS_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+struct RelabelState {
+    running: bool,
+    /// Rules changed while a pass was running, so another pass follows it.
+    rerun: bool,
 }
 
 impl Orchestrator {
     pub fn new(ingestor: Ingestor, repo: Arc<Repository>) -> Self {
-        Self { ingestor, repo }
+        let limits = ingestor.config().job_limits;
+        Self {
+            ingestor,
+            repo,
+            jobs: JobQueue::new(limits),
+            compile_locks: KeyedLocks::new(),
+            coin_parallelism: limits.coin_parallelism.max(1),
+            backfills: Arc::new(Mutex::new(HashSet::new())),
+            generations: Arc::new(Mutex::new(HashMap::new())),
+            coins: Arc::new(ArcSwap::from_pointee(CoinRegistry::default())),
+            relabel: Arc::new(Mutex::new(RelabelState::default())),
+            sub_accounts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The current asset metadata; empty until [`Self::load_coins`] or
+    /// [`Self::refresh_coins`] finds some.
+    pub fn coins(&self) -> Arc<CoinRegistry> {
+        self.coins.load_full()
+    }
+
+    /// Use the asset metadata stored by an earlier refresh. Returns how many assets it has.
+    pub async fn load_coins(&self) -> Result<usize, OrchestrationError> {
+        let coins = self.repo.query_coins().await?;
+        let count = coins.len();
+        if count > 0 {
+            self.coins.store(Arc::new(CoinRegistry::new(coins)));
+        }
+        Ok(count)
+    }
+
+    /// Fetch and store the asset metadata, then use it. Returns how many assets it has.
+    ///
+    /// A data source without metadata leaves the current registry in place.
+    pub async fn refresh_coins(&self) -> Result<usize, OrchestrationError> {
+        self.check_writable("coin metadata refresh")?;
+        if self.ingestor.refresh_coin_meta().await?.is_empty() {
+            return Ok(self.coins.load().len());
+        }
+        self.load_coins().await
+    }
+
+    /// Queue that admits ingest and compile jobs; share it with other job sources.
+    pub fn job_queue(&self) -> &JobQueue {
+        &self.jobs
+    }
+
+    /// Download counters of the builder logs source, if it downloads.
+    pub fn builder_logs_download_stats(&self) -> Option<BuilderLogsDownloadStats> {
+        self.ingestor
+            .builder_logs()
+            .and_then(|logs| logs.download_stats())
+    }
+
+    /// Counter that changes whenever `user`'s derived tables change in this process.
+    ///
+    /// Results computed from the derived tables are current as long as the generation
+    /// read before computing them is unchanged.
+    pub fn compile_generation(&self, user: &Address) -> u64 {
+        self.generations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(user)
+            .copied()
+            .unwrap_or(0)
     }
 
-    /// Ensure deposits are ingested for the given user/time range.
+    /// Record that `users`' derived tables changed other than by compiling, such as when
+    /// strategy rules relabel their lifecycles.
+    pub fn derived_tables_changed(&self, users: &[Address]) {
+        let mut generations = self.generations.lock().unwrap_or_else(|e| e.into_inner());
+        for user in users {
+            *generations.entry(user.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Record that compiling changed `user`'s derived tables for `coins`.
+    fn compiled(&self, user: &Address, coins: Vec<Coin>) {
+        self.derived_tables_changed(std::slice::from_ref(user));
+        self.webhooks().emit(WebhookEvent::CompileCompleted {
+            user: user.clone(),
+            coins,
+        });
+    }
+
+    pub fn webhooks(&self) -> &Webhooks {
+        self.ingestor.webhooks()
+    }
+
+    /// Whether `READ_ONLY` is set: queries are answered from what is stored, and anything
+    /// that would write fails with [`OrchestrationError::ReadOnly`].
+    pub fn is_read_only(&self) -> bool {
+        self.ingestor.config().read_only
+    }
+
+    fn check_writable(&self, what: &'static str) -> Result<(), OrchestrationError> {
+        if self.is_read_only() {
+            return Err(OrchestrationError::ReadOnly(what));
+        }
+        Ok(())
+    }
+
+    /// Stop admitting jobs and wait up to `grace` for running ones to finish.
+    ///
+    /// Queued jobs fail with [`OrchestrationError::ShuttingDown`]. A compile cut off after
+    /// the grace period loses nothing: derived rows and the watermark are committed in one
+    /// transaction, so the next start resumes from the last committed watermark. Returns
+    /// whether every running job finished in time.
+    pub async fn shutdown(&self, grace: Duration) -> bool {
+        self.jobs.close();
+        tokio::time::timeout(grace, self.jobs.wait_idle())
+            .await
+            .is_ok()
+    }
+
+    /// Ensure deposits are ingested for the given user/time range; a no-op when read-only.
     pub async fn ensure_deposits_ingested(
         &self,
         user: &Address,
         from_ms: Option<TimeMs>,
         to_ms: Option<TimeMs>,
     ) -> Result<(), OrchestrationError> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         self.ingestor
             .ensure_deposits_ingested(user, from_ms, to_ms)
             .await?;
         Ok(())
     }
 
+    /// Store `user`'s live equity as an equity snapshot; see [`Ingestor::sample_equity`].
+    pub async fn sample_equity(&self, user: &Address) -> Result<Option<Decimal>, OrchestrationError> {
+        self.check_writable("equity sampling")?;
+        Ok(self.ingestor.sample_equity(user).await?)
+    }
+
+    /// Marks of each of `coins` over `from_ms..=to_ms`, fetching buckets not stored yet.
+    ///
+    /// Best-effort: a coin whose marks cannot be fetched gets the ones already stored, as
+    /// does every coin when read-only.
+    pub async fn mark_prices<'a>(
+        &self,
+        coins: impl IntoIterator<Item = &'a Coin>,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> Result<HashMap<Coin, MarkSeries>, OrchestrationError> {
+        let mut marks = HashMap::new();
+        let fetch = !self.is_read_only();
+        for coin in coins {
+            if fetch {
+                if let Err(e) = self.ingestor.ensure_mark_prices(coin, from_ms, to_ms).await {
+                    warn!(coin = %coin, "Failed to fetch mark prices: {}", e);
+                }
+            }
+            let series = self
+                .repo
+                .query_mark_prices(coin, bucket_start(from_ms), to_ms)
+                .await?;
+            marks.insert(coin.clone(), series);
+        }
+        Ok(marks)
+    }
+
+    /// Addresses of `user`'s sub-accounts, whose fills are ingested under their own address.
+    ///
+    /// A fetched list is reused for [`SUB_ACCOUNTS_TTL`], and served past it when the refetch
+    /// fails.
+    pub async fn sub_accounts(&self, user: &Address) -> Result<Vec<Address>, OrchestrationError> {
+        let known = self
+            .sub_accounts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(user)
+            .cloned();
+        if let Some((fetched_at, sub_accounts)) = &known {
+            if fetched_at.elapsed() < SUB_ACCOUNTS_TTL {
+                return Ok(sub_accounts.clone());
+            }
+        }
+
+        match self.ingestor.sub_accounts(user).await {
+            Ok(sub_accounts) => {
+                self.sub_accounts
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(user.clone(), (Instant::now(), sub_accounts.clone()));
+                Ok(sub_accounts)
+            }
+            Err(e) => match known {
+                Some((_, sub_accounts)) => {
+                    warn!(user = %user, "Failed to refresh sub-accounts, keeping the last: {}", e);
+                    Ok(sub_accounts)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    /// Ensure fills are ingested for the given window, without compiling them.
+    ///
+    /// Runs as an interactive job.
+    pub async fn ensure_ingested(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+    ) -> Result<IngestionResult, OrchestrationError> {
+        self.check_writable("ingestion")?;
+        let _permit = self.jobs.acquire(user, JobPriority::Interactive).await?;
+        Ok(self
+            .ingestor
+            .ensure_ingested(user, coin, from_ms, to_ms)
+            .await?)
+    }
+
     /// Ensure fills are ingested and compiled for the given query window.
+    ///
+    /// Runs as an interactive job; see [`Self::ensure_compiled_with_priority`].
     pub async fn ensure_compiled(
         &self,
         user: &Address,
@@ -37,6 +290,27 @@ impl Orchestrator {
         from_ms: Option<TimeMs>,
         to_ms: Option<TimeMs>,
     ) -> Result<(), OrchestrationError> {
+        self.ensure_compiled_with_priority(user, coin, from_ms, to_ms, JobPriority::Interactive)
+            .await
+    }
+
+    /// Ensure fills are ingested and compiled, waiting for a job queue slot first.
+    ///
+    /// A no-op when read-only, so queries see the derived tables as stored.
+    #[tracing::instrument(level = "debug", skip_all, fields(user = %user, ?priority))]
+    pub async fn ensure_compiled_with_priority(
+        &self,
+        user: &Address,
+        coin: Option<&Coin>,
+        from_ms: Option<TimeMs>,
+        to_ms: Option<TimeMs>,
+        priority: JobPriority,
+    ) -> Result<(), OrchestrationError> {
+        if self.is_read_only() {
+            return Ok(());
+        }
+        let _permit = self.jobs.acquire(user, priority).await?;
+
         self.ingestor
             .ensure_ingested(user, coin, from_ms, to_ms)
             .await?;
@@ -46,12 +320,570 @@ impl Orchestrator {
             None => self.repo.query_distinct_coins(user, from_ms, to_ms).await?,
         };
 
-        for coin in coins_to_compile {
-            Compiler::compile_incremental(&self.repo, user, &coin).await?;
+        self.compile_coins(user, coins_to_compile).await
+    }
+
+    /// Compile `coins` for `user` concurrently, at most `coin_parallelism` at a time.
+    ///
+    /// Every coin is attempted even if another fails; failures are reported together.
+    async fn compile_coins(
+        &self,
+        user: &Address,
+        coins: Vec<Coin>,
+    ) -> Result<(), OrchestrationError> {
+        let mut pending = coins.into_iter();
+        let mut tasks = JoinSet::new();
+        let mut failures = Vec::new();
+        let mut changed = Vec::new();
+
+        loop {
+            while tasks.len() < self.coin_parallelism {
+                let Some(coin) = pending.next() else {
+                    break;
+                };
+                let repo = self.repo.clone();
+                let webhooks = self.webhooks().clone();
+                let options = self.compile_options();
+                let locks = self.compile_locks.clone();
+                let user = user.clone();
+                // Keep the caller's span so compile logs stay tied to the request.
+                tasks.spawn(
+                    async move {
+                        let _lock = locks.lock(&(user.clone(), coin.clone())).await;
+                        let result =
+                            compile_coin(&repo, &webhooks, options, &user, &coin, false).await;
+                        (coin, result)
+                    }
+                    .in_current_span(),
+                );
+            }
+
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            match joined {
+                Ok((coin, Ok(processed))) => {
+                    if processed > 0 {
+                        changed.push(coin);
+                    }
+                }
+                Ok((coin, Err(error))) => failures.push(CoinCompileError { coin, error }),
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                Err(e) => panic!("coin compile task did not finish: {}", e),
+            }
+        }
+
+        if !changed.is_empty() {
+            changed.sort();
+            self.compiled(user, changed);
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        failures.sort_by(|a, b| a.coin.cmp(&b.coin));
+        Err(OrchestrationError::Compile(failures))
+    }
+
+    /// Compile a (user, coin) pair after `fills` were pushed into `raw_fills` directly.
+    ///
+    /// Unlike [`Self::ensure_compiled`] this never pulls from the data source. Incremental
+    /// compilation only picks up fills whose [`FillOrderingKey`] sorts after the watermark
+    /// fill's, so if any pushed fill lands at or before it, the pair's derived tables are
+    /// rebuilt from scratch.
+    pub async fn compile_pushed(
+        &self,
+        user: &Address,
+        coin: &Coin,
+        fills: &[Fill],
+    ) -> Result<(), OrchestrationError> {
+        self.check_writable("pushing fills")?;
+        let _permit = self.jobs.acquire(user, JobPriority::Interactive).await?;
+        // Hold the lock across the watermark check so a concurrent compile cannot move it.
+        let _lock = self.lock_compile(user, coin).await;
+        let watermark = match self.repo.get_compile_state(user, coin).await? {
+            Some((_, Some(key))) => self.repo.get_raw_fill_by_key(&key).await?,
+            _ => None,
+        };
+        let late = watermark.is_some_and(|last| {
+            let last = FillOrderingKey::from_fill(&last);
+            fills.iter().any(|f| FillOrderingKey::from_fill(f) <= last)
+        });
+        if late {
+            info!(user = %user, coin = %coin, "Pushed fills precede watermark, rebuilding");
         }
 
+        compile_coin(&self.repo, self.webhooks(), self.compile_options(), user, coin, late).await?;
+        self.compiled(user, vec![coin.clone()]);
         Ok(())
     }
+
+    /// Rebuild every (user, coin) pair compiled under a different `COMPILE_SCHEMA_VERSION`.
+    ///
+    /// Meant to run once on startup, before serving requests. Returns the number of pairs
+    /// that were recompiled.
+    pub async fn recompile_stale(&self) -> Result<usize, OrchestrationError> {
+        self.check_writable("recompiling")?;
+        let stale = self.repo.query_stale_compile_states().await?;
+
+        for (user, coin) in &stale {
+            let _permit = self.jobs.acquire(user, JobPriority::Background).await?;
+            let _lock = self.lock_compile(user, coin).await;
+            info!(user = %user, coin = %coin, "Recompiling stale derived tables");
+            compile_coin(
+                &self.repo,
+                self.webhooks(),
+                self.compile_options(),
+                user,
+                coin,
+                true,
+            )
+            .await?;
+            self.compiled(user, vec![coin.clone()]);
+        }
+
+        Ok(stale.len())
+    }
+
+    /// Re-normalize `user`'s fills from the payloads recorded in `raw_payloads` with the
+    /// current parser and diff them against `raw_fills`.
+    ///
+    /// With `apply`, missing fills are inserted, changed fills are overwritten, and the
+    /// affected coins are rebuilt. Runs as a background job.
+    pub async fn replay_raw_payloads(
+        &self,
+        user: &Address,
+        apply: bool,
+    ) -> Result<ReplayReport, OrchestrationError> {
+        if apply {
+            self.check_writable("applying a replay")?;
+        }
+        let _permit = self.jobs.acquire(user, JobPriority::Background).await?;
+
+        let payloads = self
+            .repo
+            .query_raw_payloads("userFillsByTime", Some(user))
+            .await?;
+        let mut replayed = Vec::new();
+        for payload in &payloads {
+            let fills = replay_fills(&payload.request, &payload.content)
+                .map_err(|e| OrchestrationError::Ingestion(IngestionError::DataSource(e)))?;
+            replayed.extend(fills);
+        }
+
+        let stored: HashMap<String, Fill> = self
+            .repo
+            .query_fills(user, None, None, None)
+            .await?
+            .into_iter()
+            .map(|fill| (fill.fill_key.clone(), fill))
+            .collect();
+        let mut report = diff_fills(&stored, replayed);
+        report.payloads = payloads.len();
+        info!(
+            user = %user,
+            payloads = report.payloads,
+            missing = report.missing.len(),
+            changed = report.changed.len(),
+            apply,
+            "Replayed recorded fill payloads"
+        );
+        if !apply || (report.missing.is_empty() && report.changed.is_empty()) {
+            return Ok(report);
+        }
+
+        let coins = report.affected_coins(&stored);
+        let _locks = {
+            let mut locks = Vec::with_capacity(coins.len());
+            for coin in &coins {
+                locks.push(self.lock_compile(user, coin).await);
+            }
+            locks
+        };
+        self.repo.insert_fills_batch(&report.missing).await?;
+        let from = report.missing.iter().map(|f| f.time_ms).min();
+        let to = report.missing.iter().map(|f| f.time_ms).max();
+        if let (Some(from), Some(to)) = (from, to) {
+            self.ingestor.attribute_window(user, None, from, to).await?;
+        }
+        let corrected: Vec<Fill> = report.changed.iter().map(|c| c.replayed.clone()).collect();
+        self.repo.update_fills(&corrected).await?;
+        for coin in &coins {
+            compile_coin(
+                &self.repo,
+                self.webhooks(),
+                self.compile_options(),
+                user,
+                coin,
+                true,
+            )
+            .await?;
+        }
+        report.rebuilt_coins = coins.into_iter().collect();
+        self.compiled(user, report.rebuilt_coins.clone());
+
+        report.applied = true;
+        Ok(report)
+    }
+
+    /// Re-attribute a user's fills in a window against builder logs, then rebuild the coins
+    /// traded in it so lifecycle taint reflects the new attributions.
+    ///
+    /// Runs as a background job. Returns the number of fills attributed.
+    pub async fn backfill_attributions(
+        &self,
+        user: &Address,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> Result<usize, OrchestrationError> {
+        self.check_writable("attribution backfill")?;
+        let _permit = self.jobs.acquire(user, JobPriority::Background).await?;
+        let attributed = self
+            .ingestor
+            .attribute_window(user, None, from_ms, to_ms)
+            .await?;
+        if attributed == 0 {
+            return Ok(0);
+        }
+
+        let coins = self
+            .repo
+            .query_distinct_coins(user, Some(from_ms), Some(to_ms))
+            .await?;
+        for coin in &coins {
+            let _lock = self.lock_compile(user, coin).await;
+            compile_coin(
+                &self.repo,
+                self.webhooks(),
+                self.compile_options(),
+                user,
+                coin,
+                true,
+            )
+            .await?;
+        }
+        self.compiled(user, coins);
+
+        Ok(attributed)
+    }
+
+    /// Run [`Self::backfill_attributions`] in the background unless one is already queued
+    /// or running for `user`, or read-only. Returns whether a backfill was started.
+    pub fn spawn_attribution_backfill(
+        self: &Arc<Self>,
+        user: &Address,
+        from_ms: TimeMs,
+        to_ms: TimeMs,
+    ) -> bool {
+        if self.is_read_only()
+            || !self
+                .backfills
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(user.clone())
+        {
+            return false;
+        }
+
+        let orchestrator = self.clone();
+        let user = user.clone();
+        tokio::spawn(async move {
+            // Run the backfill as its own task so the user is released even if it panics.
+            let backfill = {
+                let orchestrator = orchestrator.clone();
+                let user = user.clone();
+                tokio::spawn(
+                    async move {
+                        orchestrator
+                            .backfill_attributions(&user, from_ms, to_ms)
+                            .await
+                    }
+                    .in_current_span(),
+                )
+            };
+            let (attributed, error) = match backfill.await {
+                Ok(Ok(attributed)) => {
+                    info!(user = %user, attributed, "Builder log attribution backfill finished");
+                    (attributed, None)
+                }
+                Ok(Err(e)) => {
+                    warn!(user = %user, error = %e, "Builder log attribution backfill failed");
+                    (0, Some(e.to_string()))
+                }
+                Err(e) => {
+                    warn!(user = %user, error = %e, "Builder log attribution backfill panicked");
+                    (0, Some(e.to_string()))
+                }
+            };
+            orchestrator.webhooks().emit(WebhookEvent::BackfillFinished {
+                user: user.clone(),
+                from_ms: from_ms.as_ms(),
+                to_ms: to_ms.as_ms(),
+                attributed,
+                error,
+            });
+            orchestrator
+                .backfills
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&user);
+        }
+        // Logs stay attributed to the request that queued the backfill.
+        .in_current_span());
+        true
+    }
+
+    /// Relabel every stored lifecycle with the current strategy rules in the background, in
+    /// batches of [`RELABEL_BATCH`] so no transaction holds the writer for long. Called
+    /// while a relabel runs, it queues one more pass, which starts over once the running
+    /// one finishes.
+    pub fn spawn_strategy_relabel(self: &Arc<Self>) {
+        {
+            let mut relabel = self.relabel.lock().unwrap_or_else(|e| e.into_inner());
+            if relabel.running {
+                relabel.rerun = true;
+                return;
+            }
+            relabel.running = true;
+        }
+
+        let orchestrator = self.clone();
+        tokio::spawn(
+            async move {
+                loop {
+                    // Run each pass as its own task so the state is reset even if it panics.
+                    let pass = {
+                        let orchestrator = orchestrator.clone();
+                        tokio::spawn(
+                            async move { orchestrator.relabel_strategies().await }
+                                .in_current_span(),
+                        )
+                    };
+                    match pass.await {
+                        Ok(Ok(users)) => info!(users, "Strategy relabel finished"),
+                        Ok(Err(e)) => warn!(error = %e, "Strategy relabel failed"),
+                        Err(e) => warn!(error = %e, "Strategy relabel panicked"),
+                    }
+                    let mut relabel =
+                        orchestrator.relabel.lock().unwrap_or_else(|e| e.into_inner());
+                    if !std::mem::take(&mut relabel.rerun) {
+                        relabel.running = false;
+                        break;
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    /// Whether a relabel started by [`Self::spawn_strategy_relabel`] is queued or running.
+    pub fn strategy_relabel_pending(&self) -> bool {
+        self.relabel
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .running
+    }
+
+    /// One pass over every lifecycle. Returns how many users had a label changed.
+    async fn relabel_strategies(&self) -> Result<usize, OrchestrationError> {
+        let mut after_id = 0;
+        let mut users = HashSet::new();
+        loop {
+            let (changed, last_id) = self
+                .repo
+                .relabel_lifecycle_strategies(after_id, RELABEL_BATCH)
+                .await?;
+            self.derived_tables_changed(&changed);
+            users.extend(changed);
+            match last_id {
+                Some(id) => after_id = id,
+                None => return Ok(users.len()),
+            }
+        }
+    }
+
+    /// Download each builder's logs for every day in `days`, store the parsed rows in
+    /// `builder_log_fills`, then re-attribute every user in them who has stored fills.
+    ///
+    /// `days` are consecutive `YYYYMMDD` days (see
+    /// [`days_in_range`](crate::orchestration::builder_logs_backfill::days_in_range)). A day
+    /// that cannot be fetched is recorded in the report and skipped, as is a user whose
+    /// attribution fails; only database errors while storing abort the backfill.
+    pub async fn backfill_builder_logs(
+        &self,
+        builders: &[Address],
+        days: &[String],
+    ) -> Result<BuilderLogsBackfillReport, OrchestrationError> {
+        self.check_writable("builder log backfill")?;
+        let mut report = BuilderLogsBackfillReport::default();
+        let (Some(from_day), Some(to_day)) = (days.first(), days.last()) else {
+            return Ok(report);
+        };
+
+        // Downloads run concurrently, up to the configured limit; results come back in
+        // builder then day order and are stored one day at a time.
+        let total = builders.len() * days.len();
+        let parallelism = self.ingestor.config().builder_logs_max_concurrent_downloads;
+        let requests: Vec<(Address, String)> = builders
+            .iter()
+            .flat_map(|builder| days.iter().map(|day| (builder.clone(), day.clone())))
+            .collect();
+        let logs = self.ingestor.builder_logs().cloned();
+        let mut fetches = stream::iter(requests)
+            .map(move |(builder, day)| {
+                let logs = logs.clone();
+                async move {
+                    let fetched = match logs {
+                        Some(logs) => logs
+                            .fetch_and_parse_day(&builder, &day)
+                            .await
+                            .map_err(|e| e.to_string()),
+                        None => Err("no builder logs source is configured".to_string()),
+                    };
+                    (builder, day, fetched)
+                }
+            })
+            .buffered(parallelism.max(1));
+
+        while let Some((builder, day, fetched)) = fetches.next().await {
+            let (fills, error) = match fetched {
+                Ok(fills) => {
+                    let stored = self
+                        .repo
+                        .replace_builder_log_fills(&builder, &day, &fills)
+                        .await?;
+                    report.fills_stored += stored;
+                    (stored, None)
+                }
+                Err(error) => {
+                    warn!(builder = %builder, yyyymmdd = %day, error = %error, "Builder log day not backfilled");
+                    (0, Some(error))
+                }
+            };
+            info!(
+                builder = %builder,
+                yyyymmdd = %day,
+                fills,
+                done = report.days.len() + 1,
+                total,
+                "Builder log backfill progress"
+            );
+            report.days.push(BuilderLogsDayOutcome {
+                builder,
+                yyyymmdd: day,
+                fills,
+                error,
+            });
+        }
+
+        let Some((from_ms, to_ms)) = range_bounds(from_day, to_day) else {
+            return Ok(report);
+        };
+        let users = self
+            .repo
+            .query_builder_log_users_with_fills(builders, from_day, to_day, from_ms, to_ms)
+            .await?;
+        for user in users {
+            let outcome = match self.backfill_attributions(&user, from_ms, to_ms).await {
+                Ok(attributed) => BuilderLogsUserOutcome {
+                    user,
+                    attributed,
+                    error: None,
+                },
+                Err(OrchestrationError::ShuttingDown(e)) => {
+                    return Err(OrchestrationError::ShuttingDown(e))
+                }
+                Err(e) => {
+                    warn!(user = %user, error = %e, "Builder log attribution backfill failed");
+                    BuilderLogsUserOutcome {
+                        user,
+                        attributed: 0,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            report.users.push(outcome);
+        }
+
+        info!(
+            builders = builders.len(),
+            days = days.len(),
+            failed_days = report.failed_days(),
+            fills_stored = report.fills_stored,
+            users = report.users.len(),
+            fills_attributed = report.fills_attributed(),
+            "Builder log backfill finished"
+        );
+        Ok(report)
+    }
+
+    /// How compiles started now treat fills that overflow and attribute new fills.
+    fn compile_options(&self) -> CompileOptions {
+        let config = self.ingestor.config();
+        CompileOptions {
+            overflow: config.overflow_policy,
+            heuristic: config.heuristic_attribution.clone(),
+        }
+    }
+
+    /// Wait until no other compile of `(user, coin)` runs in this process.
+    async fn lock_compile(&self, user: &Address, coin: &Coin) -> OwnedMutexGuard<()> {
+        self.compile_locks.lock(&(user.clone(), coin.clone())).await
+    }
+}
+
+/// Compile `(user, coin)`, from scratch with `rebuild`, and report lifecycles whose taint
+/// changed. Returns the number of fills processed.
+///
+/// Lifecycle ids are reassigned by a rebuild, so lifecycles are matched by start time; one
+/// that did not exist before counts as untainted.
+async fn compile_coin(
+    repo: &Repository,
+    webhooks: &Webhooks,
+    options: CompileOptions,
+    user: &Address,
+    coin: &Coin,
+    rebuild: bool,
+) -> Result<usize, CompileError> {
+    let before: Option<HashMap<i64, bool>> = if webhooks.wants(WebhookEventKind::TaintChanged) {
+        let lifecycles = repo.query_lifecycles(user, coin).await?;
+        Some(
+            lifecycles
+                .iter()
+                .map(|l| (l.start_time_ms.as_ms(), l.is_tainted))
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    if rebuild {
+        repo.reset_derived_state(user, coin).await?;
+    }
+    let processed = Compiler::compile_incremental_with(repo, user, coin, options).await?;
+
+    if let Some(before) = before {
+        let changed: Vec<LifecycleTaint> = repo
+            .query_lifecycles(user, coin)
+            .await?
+            .into_iter()
+            .filter(|l| {
+                before.get(&l.start_time_ms.as_ms()).copied().unwrap_or(false) != l.is_tainted
+            })
+            .map(|l| LifecycleTaint {
+                lifecycle_id: l.id,
+                start_time_ms: l.start_time_ms.as_ms(),
+                tainted: l.is_tainted,
+            })
+            .collect();
+        if !changed.is_empty() {
+            webhooks.emit(WebhookEvent::TaintChanged {
+                user: user.clone(),
+                coin: coin.clone(),
+                lifecycles: changed,
+            });
+        }
+    }
+    Ok(processed)
 }
 
 #[derive(Debug, Error)]
@@ -60,4 +892,39 @@ pub enum OrchestrationError {
     Ingestion(#[from] IngestionError),
     #[error(transparent)]
     Db(#[from] sqlx::Error),
+    #[error("{}", describe_compile_failures(.0))]
+    Compile(Vec<CoinCompileError>),
+    #[error(transparent)]
+    ShuttingDown(#[from] JobQueueClosed),
+    #[error("{0} is disabled while READ_ONLY is set")]
+    ReadOnly(&'static str),
+}
+
+/// A coin whose compile failed while other coins of the same job were compiled.
+#[derive(Debug)]
+pub struct CoinCompileError {
+    pub coin: Coin,
+    pub error: CompileError,
+}
+
+impl From<CompileError> for OrchestrationError {
+    fn from(err: CompileError) -> Self {
+        match err {
+            CompileError::Db(e) => OrchestrationError::Db(e),
+            CompileError::Arithmetic { ref coin, .. } => {
+                OrchestrationError::Compile(vec![CoinCompileError {
+                    coin: coin.clone(),
+                    error: err,
+                }])
+            }
+        }
+    }
+}
+
+fn describe_compile_failures(failures: &[CoinCompileError]) -> String {
+    failures
+        .iter()
+        .map(|f| format!("{}: {}", f.coin, f.error))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
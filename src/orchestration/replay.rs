@@ -0,0 +1,192 @@
+//! Re-deriving fills from recorded upstream payloads.
+//!
+//! With `RECORD_RAW_PAYLOADS` on, every `userFillsByTime` response is archived in
+//! `raw_payloads`. Replaying them through the current parser and diffing the result against
+//! `raw_fills` shows what a parser or upstream schema change would alter, and
+//! [`Orchestrator::replay_raw_payloads`](super::orchestrator::Orchestrator::replay_raw_payloads)
+//! can apply the corrections.
+
+use crate::domain::{Coin, Fill};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// One field whose replayed value differs from the stored one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub stored: Option<String>,
+    pub replayed: Option<String>,
+}
+
+/// A stored fill whose replayed version differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillCorrection {
+    /// The fill as the current parser reads it; same `fill_key` as the stored one.
+    pub replayed: Fill,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Outcome of replaying a user's recorded fill payloads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub payloads: usize,
+    /// Distinct fills across all payloads.
+    pub replayed_fills: usize,
+    pub unchanged: usize,
+    /// Replayed fills with no stored fill of the same key.
+    pub missing: Vec<Fill>,
+    pub changed: Vec<FillCorrection>,
+    /// Whether the corrections were written to `raw_fills`.
+    pub applied: bool,
+    /// Coins whose derived tables were rebuilt after applying.
+    pub rebuilt_coins: Vec<Coin>,
+}
+
+impl ReplayReport {
+    /// Coins touched by a missing or changed fill, including a changed fill's old coin.
+    pub fn affected_coins(&self, stored: &HashMap<String, Fill>) -> BTreeSet<Coin> {
+        let mut coins: BTreeSet<Coin> = self.missing.iter().map(|f| f.coin.clone()).collect();
+        for correction in &self.changed {
+            coins.insert(correction.replayed.coin.clone());
+            if let Some(old) = stored.get(&correction.replayed.fill_key) {
+                coins.insert(old.coin.clone());
+            }
+        }
+        coins
+    }
+}
+
+/// Diff replayed fills against stored ones by `fill_key`.
+///
+/// When several payloads contain the same fill, the last one wins, so pass `replayed` in
+/// recording order. Stored fills absent from every payload (streamed or pushed fills, or
+/// windows fetched before recording was enabled) are not reported. Results are ordered by
+/// `fill_key`.
+pub fn diff_fills(stored: &HashMap<String, Fill>, replayed: Vec<Fill>) -> ReplayReport {
+    let replayed: BTreeMap<String, Fill> = replayed
+        .into_iter()
+        .map(|fill| (fill.fill_key.clone(), fill))
+        .collect();
+
+    let mut report = ReplayReport {
+        replayed_fills: replayed.len(),
+        ..ReplayReport::default()
+    };
+    for (key, fill) in replayed {
+        match stored.get(&key) {
+            None => report.missing.push(fill),
+            Some(old) => {
+                let changes = field_changes(old, &fill);
+                if changes.is_empty() {
+                    report.unchanged += 1;
+                } else {
+                    report.changed.push(FillCorrection {
+                        replayed: fill,
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    report
+}
+
+fn field_changes(stored: &Fill, replayed: &Fill) -> Vec<FieldChange> {
+    let fields: [(&'static str, Option<String>, Option<String>); 13] = [
+        ("coin", Some(stored.coin.to_string()), Some(replayed.coin.to_string())),
+        ("timeMs", Some(stored.time_ms.as_ms().to_string()), Some(replayed.time_ms.as_ms().to_string())),
+        ("side", Some(stored.side.to_string()), Some(replayed.side.to_string())),
+        ("px", Some(stored.px.to_canonical_string()), Some(replayed.px.to_canonical_string())),
+        ("sz", Some(stored.sz.to_canonical_string()), Some(replayed.sz.to_canonical_string())),
+        ("fee", Some(stored.fee.to_canonical_string()), Some(replayed.fee.to_canonical_string())),
+        (
+            "closedPnl",
+            Some(stored.closed_pnl.to_canonical_string()),
+            Some(replayed.closed_pnl.to_canonical_string()),
+        ),
+        (
+            "builderFee",
+            stored.builder_fee.map(|d| d.to_canonical_string()),
+            replayed.builder_fee.map(|d| d.to_canonical_string()),
+        ),
+        ("tid", stored.tid.map(|v| v.to_string()), replayed.tid.map(|v| v.to_string())),
+        ("oid", stored.oid.map(|v| v.to_string()), replayed.oid.map(|v| v.to_string())),
+        (
+            "crossed",
+            stored.crossed.map(|v| v.to_string()),
+            replayed.crossed.map(|v| v.to_string()),
+        ),
+        (
+            "fillType",
+            Some(stored.fill_type.to_string()),
+            Some(replayed.fill_type.to_string()),
+        ),
+        (
+            "twapId",
+            stored.twap_id.map(|v| v.to_string()),
+            replayed.twap_id.map(|v| v.to_string()),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, stored, replayed)| FieldChange {
+            field,
+            stored,
+            replayed,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Address, Decimal, Side, TimeMs};
+    use std::str::FromStr;
+
+    fn fill(tid: i64, px: &str) -> Fill {
+        Fill::new(
+            TimeMs::new(1000 + tid),
+            Address::new("0xabc".to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            Decimal::from_str(px).unwrap(),
+            Decimal::from_str("1").unwrap(),
+            Decimal::zero(),
+            Decimal::zero(),
+            None,
+            Some(tid),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_diff_fills_classifies_replayed_fills() {
+        let stored: HashMap<String, Fill> = [fill(1, "100"), fill(2, "100"), fill(9, "100")]
+            .into_iter()
+            .map(|f| (f.fill_key.clone(), f))
+            .collect();
+
+        // tid 2 appears twice; the later payload's version wins.
+        let report = diff_fills(
+            &stored,
+            vec![fill(1, "100"), fill(2, "100"), fill(2, "101"), fill(3, "100")],
+        );
+        assert_eq!(report.replayed_fills, 3);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.missing, vec![fill(3, "100")]);
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(
+            report.changed[0].changes,
+            vec![FieldChange {
+                field: "px",
+                stored: Some("100".to_string()),
+                replayed: Some("101".to_string()),
+            }]
+        );
+        assert_eq!(
+            report.affected_coins(&stored),
+            BTreeSet::from([Coin::new("BTC".to_string())])
+        );
+    }
+}
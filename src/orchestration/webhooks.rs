@@ -0,0 +1,315 @@
+//! Outbound webhooks: signed JSON `POST`s to the configured URLs when a user's fills or
+//! derived tables change, so downstream services need not poll `/v1/trades`.
+//!
+//! Every delivery carries `X-Hypesilico-Signature: t=<unix ms>,v1=<hex>`, where the hex is
+//! HMAC-SHA256 of `<unix ms>.<body>` under `WEBHOOK_SECRET`. Deliveries are best effort:
+//! each URL is tried a few times with backoff and failures are only logged.
+//!
+//! Every event is also published in-process, whether or not a webhook wants it, for live
+//! streams to [`subscribe`](Webhooks::subscribe) to.
+
+use crate::config::ConfigHandle;
+use crate::domain::{Address, Coin, TimeMs};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+const DELIVERY_ATTEMPTS: u32 = 3;
+const DELIVERY_BACKOFF: Duration = Duration::from_secs(1);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Events a slow subscriber may fall behind by before it misses some.
+const SUBSCRIBER_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEventKind {
+    FillsIngested,
+    CompileCompleted,
+    TaintChanged,
+    BackfillFinished,
+}
+
+impl WebhookEventKind {
+    pub const ALL: [WebhookEventKind; 4] = [
+        WebhookEventKind::FillsIngested,
+        WebhookEventKind::CompileCompleted,
+        WebhookEventKind::TaintChanged,
+        WebhookEventKind::BackfillFinished,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEventKind::FillsIngested => "fills.ingested",
+            WebhookEventKind::CompileCompleted => "compile.completed",
+            WebhookEventKind::TaintChanged => "taint.changed",
+            WebhookEventKind::BackfillFinished => "backfill.finished",
+        }
+    }
+}
+
+impl FromStr for WebhookEventKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s.trim())
+            .ok_or(())
+    }
+}
+
+/// Where webhooks go and which events are sent; nothing is sent without a URL.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    /// Key deliveries are signed with; unsigned when unset.
+    pub secret: Option<String>,
+    /// Events to send; empty sends every event.
+    pub events: Vec<WebhookEventKind>,
+}
+
+impl WebhookConfig {
+    pub fn wants(&self, kind: WebhookEventKind) -> bool {
+        !self.urls.is_empty() && (self.events.is_empty() || self.events.contains(&kind))
+    }
+}
+
+impl fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("urls", &self.urls)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+/// Taint of one lifecycle after a compile, reported when it differs from before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleTaint {
+    pub lifecycle_id: i64,
+    pub start_time_ms: i64,
+    pub tainted: bool,
+}
+
+/// Body of a webhook delivery, without the envelope.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data", rename_all_fields = "camelCase")]
+pub enum WebhookEvent {
+    /// New fills were stored; `source` is `poll`, `stream` or `push`.
+    #[serde(rename = "fills.ingested")]
+    FillsIngested {
+        user: Address,
+        fills_new: usize,
+        from_ms: i64,
+        to_ms: i64,
+        source: &'static str,
+    },
+    /// Compiling changed the derived tables of `coins`.
+    #[serde(rename = "compile.completed")]
+    CompileCompleted { user: Address, coins: Vec<Coin> },
+    /// A compile changed the taint of some of a coin's lifecycles.
+    #[serde(rename = "taint.changed")]
+    TaintChanged {
+        user: Address,
+        coin: Coin,
+        lifecycles: Vec<LifecycleTaint>,
+    },
+    /// A background builder log attribution backfill finished or failed.
+    #[serde(rename = "backfill.finished")]
+    BackfillFinished {
+        user: Address,
+        from_ms: i64,
+        to_ms: i64,
+        attributed: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            WebhookEvent::FillsIngested { .. } => WebhookEventKind::FillsIngested,
+            WebhookEvent::CompileCompleted { .. } => WebhookEventKind::CompileCompleted,
+            WebhookEvent::TaintChanged { .. } => WebhookEventKind::TaintChanged,
+            WebhookEvent::BackfillFinished { .. } => WebhookEventKind::BackfillFinished,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope<'a> {
+    id: &'a str,
+    time_ms: i64,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+/// Sends events to the URLs configured at the time of each event, and to subscribers.
+#[derive(Clone)]
+pub struct Webhooks {
+    config: ConfigHandle,
+    client: reqwest::Client,
+    bus: broadcast::Sender<Arc<WebhookEvent>>,
+}
+
+impl Webhooks {
+    pub fn new(config: impl Into<ConfigHandle>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            config: config.into(),
+            client,
+            bus: broadcast::channel(SUBSCRIBER_BUFFER).0,
+        }
+    }
+
+    /// Receive every event emitted from now on. A subscriber more than a buffer behind gets
+    /// [`broadcast::error::RecvError::Lagged`] and should resynchronize from storage.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<WebhookEvent>> {
+        self.bus.subscribe()
+    }
+
+    /// Whether an event of `kind` would be sent; lets callers skip work only a webhook needs.
+    pub fn wants(&self, kind: WebhookEventKind) -> bool {
+        self.config.load().webhooks.wants(kind)
+    }
+
+    /// Publish `event` to subscribers and deliver it to every configured URL in the
+    /// background.
+    pub fn emit(&self, event: WebhookEvent) {
+        let event = Arc::new(event);
+        // Fails only without subscribers.
+        let _ = self.bus.send(event.clone());
+
+        let config = self.config.load();
+        let webhooks = &config.webhooks;
+        if !webhooks.wants(event.kind()) {
+            return;
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let time_ms = TimeMs::now().as_ms();
+        let body = match serde_json::to_vec(&Envelope {
+            id: &id,
+            time_ms,
+            event: event.as_ref(),
+        }) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(error = %e, "Failed to serialize webhook event");
+                return;
+            }
+        };
+        let signature = webhooks
+            .secret
+            .as_deref()
+            .map(|secret| signature_header(secret, time_ms, &body));
+
+        for url in &webhooks.urls {
+            let request = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Hypesilico-Event", event.kind().as_str())
+                .header("X-Hypesilico-Delivery", &id);
+            let request = match &signature {
+                Some(signature) => request.header("X-Hypesilico-Signature", signature),
+                None => request,
+            };
+            tokio::spawn(deliver(request.body(body.clone()), url.clone(), event.kind()));
+        }
+    }
+}
+
+async fn deliver(request: reqwest::RequestBuilder, url: String, kind: WebhookEventKind) {
+    let mut backoff = DELIVERY_BACKOFF;
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        let Some(request) = request.try_clone() else {
+            return;
+        };
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!(url, event = kind.as_str(), "Delivered webhook");
+                return;
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt == DELIVERY_ATTEMPTS {
+            warn!(url, event = kind.as_str(), error, "Webhook delivery failed");
+            return;
+        }
+        debug!(url, event = kind.as_str(), attempt, error, "Retrying webhook delivery");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+}
+
+/// Hex HMAC-SHA256 of `message` under `secret`.
+pub fn sign(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// `X-Hypesilico-Signature` value for a body sent at `time_ms`.
+pub fn signature_header(secret: &str, time_ms: i64, body: &[u8]) -> String {
+    let mut message = format!("{}.", time_ms).into_bytes();
+    message.extend_from_slice(body);
+    format!("t={},v1={}", time_ms, sign(secret, &message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_matches_hmac_sha256() {
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(
+            signature_header("key", 5, b"{}"),
+            format!("t=5,v1={}", sign("key", b"5.{}"))
+        );
+    }
+
+    #[test]
+    fn test_envelope_has_type_and_camel_case_data() {
+        let event = WebhookEvent::FillsIngested {
+            user: Address::new("0xabc".to_string()),
+            fills_new: 2,
+            from_ms: 1,
+            to_ms: 9,
+            source: "push",
+        };
+        let json = serde_json::to_value(Envelope {
+            id: "d1",
+            time_ms: 10,
+            event: &event,
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": "d1",
+                "timeMs": 10,
+                "type": "fills.ingested",
+                "data": {"user": "0xabc", "fillsNew": 2, "fromMs": 1, "toMs": 9, "source": "push"}
+            })
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Tracing setup with a filter that can be changed while the server runs.
+//!
+//! Restarting to raise a log level also throws away in-memory caches and stream state, so
+//! the subscriber is built around a reloadable `EnvFilter` that the admin API can swap.
+
+use std::sync::Arc;
+use tracing::Subscriber;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{reload, Registry};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogFilterError {
+    #[error("invalid filter directives: {0}")]
+    Invalid(String),
+    #[error("failed to reload filter: {0}")]
+    Reload(String),
+}
+
+/// Handle to the active tracing filter.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives in effect at startup, restored by [`LogFilterHandle::reset`].
+    startup: Arc<str>,
+}
+
+impl LogFilterHandle {
+    /// The directives currently applied, e.g. `info,hypesilico::compile=debug`.
+    pub fn current(&self) -> Result<String, LogFilterError> {
+        self.handle
+            .with_current(|filter| filter.to_string())
+            .map_err(|e| LogFilterError::Reload(e.to_string()))
+    }
+
+    /// Replace the filter with `directives` (`RUST_LOG` syntax) and return the applied form.
+    pub fn set(&self, directives: &str) -> Result<String, LogFilterError> {
+        let filter =
+            EnvFilter::try_new(directives).map_err(|e| LogFilterError::Invalid(e.to_string()))?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| LogFilterError::Reload(e.to_string()))?;
+        self.current()
+    }
+
+    /// Restore the filter the process started with.
+    pub fn reset(&self) -> Result<String, LogFilterError> {
+        let startup = self.startup.clone();
+        self.set(&startup)
+    }
+}
+
+/// Build the default filter: `RUST_LOG` directives plus `info` for everything else.
+pub fn default_filter() -> EnvFilter {
+    EnvFilter::from_default_env().add_directive(LevelFilter::INFO.into())
+}
+
+/// Build a formatting subscriber whose filter can be replaced through the returned handle.
+///
+/// The handle stops working once the subscriber is dropped, so callers install it globally
+/// (or keep it alive for the duration of a test).
+pub fn reloadable_subscriber(
+    filter: EnvFilter,
+) -> (impl Subscriber + Send + Sync + 'static, LogFilterHandle) {
+    let startup: Arc<str> = filter.to_string().into();
+    let (filter_layer, handle) = reload::Layer::new(filter);
+    let subscriber = Registry::default()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer());
+    (subscriber, LogFilterHandle { handle, startup })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_reset_filter() {
+        let (_subscriber, handle) = reloadable_subscriber(EnvFilter::new("info"));
+        assert_eq!(handle.current().unwrap(), "info");
+
+        let applied = handle.set("info,hypesilico::compile=debug").unwrap();
+        assert!(applied.contains("hypesilico::compile=debug"));
+        assert_eq!(handle.current().unwrap(), applied);
+
+        assert_eq!(handle.reset().unwrap(), "info");
+    }
+
+    #[test]
+    fn test_invalid_directives_keep_current_filter() {
+        let (_subscriber, handle) = reloadable_subscriber(EnvFilter::new("warn"));
+        let err = handle.set("hypesilico=notalevel").unwrap_err();
+        assert!(matches!(err, LogFilterError::Invalid(_)));
+        assert_eq!(handle.current().unwrap(), "warn");
+    }
+
+    #[test]
+    fn test_handle_fails_after_subscriber_dropped() {
+        let (subscriber, handle) = reloadable_subscriber(EnvFilter::new("info"));
+        drop(subscriber);
+        assert!(matches!(
+            handle.set("debug"),
+            Err(LogFilterError::Reload(_))
+        ));
+    }
+}
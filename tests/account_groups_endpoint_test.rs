@@ -0,0 +1,263 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x00000000000000000000000000000000000000a1";
+const BOB: &str = "0x00000000000000000000000000000000000000b2";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(user: &str, time_ms: i64, side: Side, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn send(
+    app: axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => builder
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(axum::body::Body::empty()).unwrap(),
+    };
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn seed(repo: &Repository) {
+    for f in [
+        fill(ALICE, 1000, Side::Buy, "100", "0", 1),
+        fill(ALICE, 2000, Side::Sell, "110", "10", 2),
+        fill(BOB, 1000, Side::Buy, "100", "0", 3),
+        fill(BOB, 3000, Side::Sell, "105", "5", 4),
+    ] {
+        repo.insert_fill(&f).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_account_pnl_and_history_aggregate_members() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+
+    let (status, body) = send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/accounts/desk",
+        Some(serde_json::json!({ "addresses": [BOB, ALICE, BOB] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["addresses"], serde_json::json!([ALICE, BOB]));
+
+    let (status, body) = send(app.clone(), "GET", "/v1/pnl?account=desk", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["realizedPnl"], "15");
+    assert_eq!(body["feesPaid"], "0.4");
+    assert_eq!(body["tradeCount"], 4);
+
+    let (status, body) = send(app.clone(), "GET", "/v1/positions/history?account=desk", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let order: Vec<(i64, String)> = body["snapshots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["timeMs"].as_i64().unwrap(), s["user"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            (1000, ALICE.to_string()),
+            (1000, BOB.to_string()),
+            (2000, ALICE.to_string()),
+            (3000, BOB.to_string()),
+        ]
+    );
+
+    // Single-user history does not label snapshots.
+    let (_, body) = send(
+        app,
+        "GET",
+        &format!("/v1/positions/history?user={}", ALICE),
+        None,
+    )
+    .await;
+    assert!(body["snapshots"][0].get("user").is_none());
+}
+
+#[tokio::test]
+async fn test_account_admin_lifecycle_and_errors() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = send(app.clone(), "GET", "/v1/pnl?account=desk", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        app.clone(),
+        "GET",
+        &format!("/v1/pnl?user={}&account=desk", ALICE),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/accounts/desk",
+        Some(serde_json::json!({ "addresses": ["nope"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/accounts/desk",
+        Some(serde_json::json!({ "addresses": [ALICE] })),
+    )
+    .await;
+    let (_, body) = send(app.clone(), "GET", "/v1/admin/accounts", None).await;
+    assert_eq!(
+        body["accounts"],
+        serde_json::json!([{ "name": "desk", "addresses": [ALICE] }])
+    );
+
+    let (status, _) = send(app.clone(), "DELETE", "/v1/admin/accounts/desk", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(app, "DELETE", "/v1/admin/accounts/desk", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_account_history_pages_across_members() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+    send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/accounts/desk",
+        Some(serde_json::json!({ "addresses": [ALICE, BOB] })),
+    )
+    .await;
+
+    let (_, full) = send(app.clone(), "GET", "/v1/positions/history?account=desk", None).await;
+
+    let mut snapshots = Vec::new();
+    let mut uri = "/v1/positions/history?account=desk&limit=1".to_string();
+    loop {
+        let (status, page) = send(app.clone(), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        snapshots.extend(page["snapshots"].as_array().unwrap().iter().cloned());
+        match page["nextCursor"].as_str() {
+            Some(cursor) => {
+                uri = format!("/v1/positions/history?account=desk&limit=1&cursor={}", cursor)
+            }
+            None => break,
+        }
+    }
+    assert_eq!(serde_json::Value::Array(snapshots), full["snapshots"]);
+}
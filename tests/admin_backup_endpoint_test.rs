@@ -0,0 +1,168 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::{init_db, restore_backup, MaintenanceConfig},
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(backups: bool) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let backup_dir = temp_dir.path().join("backups");
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: MaintenanceConfig {
+            backup_dir: backups.then(|| backup_dir.clone()),
+            ..MaintenanceConfig::default()
+        },
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_backup(app: axum::Router) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/admin/backup")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(1000 + tid),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_backup_during_ingestion_restores_consistently() {
+    let TestApp { app, repo, _temp: temp } = setup_test_app(true).await;
+    for tid in 0..50 {
+        repo.insert_fill(&fill(tid)).await.unwrap();
+    }
+
+    // Keep writing while the snapshot is taken.
+    let writer = {
+        let repo = repo.clone();
+        tokio::spawn(async move {
+            for tid in 50..250 {
+                repo.insert_fill(&fill(tid)).await.unwrap();
+            }
+        })
+    };
+    let (status, body) = post_backup(app).await;
+    writer.await.unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["bytes"].as_u64().unwrap() > 0);
+    assert_eq!(body["removed"], serde_json::json!([]));
+
+    let target = temp.path().join("restored.db").to_string_lossy().to_string();
+    restore_backup(body["path"].as_str().unwrap(), &target)
+        .await
+        .unwrap();
+    let restored = Repository::new(init_db(&target).await.unwrap());
+    let fills = restored
+        .query_fills(&Address::new(ALICE.to_string()), None, None, None)
+        .await
+        .unwrap();
+    // Every fill committed before the snapshot, and none torn.
+    assert!(fills.len() >= 50 && fills.len() <= 250);
+    let tids: Vec<i64> = fills.iter().filter_map(|f| f.tid).collect();
+    assert_eq!(tids, (0..fills.len() as i64).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_backup_requires_backup_dir() {
+    let TestApp { app, _temp, .. } = setup_test_app(false).await;
+
+    let (status, body) = post_backup(app).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("DB_BACKUP_DIR"));
+}
@@ -0,0 +1,223 @@
+use axum::http::StatusCode;
+use hypesilico::api;
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::{BuilderLogsError, BuilderLogsSource, MockDataSource};
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, AttributionMode, BuilderLogFill, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const BUILDER: &str = "0x2868fc0d9786a740b491577a43502259efa78a39";
+const USER: &str = "0x1111111111111111111111111111111111111111";
+/// A trader with builder log rows but no stored fills.
+const STRANGER: &str = "0x2222222222222222222222222222222222222222";
+/// 2024-01-11T19:06:40Z
+const DAY1_MS: i64 = 1_705_000_000_000;
+/// 2024-01-12T01:00:00Z
+const DAY2_MS: i64 = 1_705_021_200_000;
+
+/// Serves the rows it holds for a day and `404` for any other day.
+struct DailyLogsSource {
+    days: HashMap<String, Vec<BuilderLogFill>>,
+}
+
+#[async_trait::async_trait]
+impl BuilderLogsSource for DailyLogsSource {
+    async fn fetch_and_parse_day(
+        &self,
+        _builder: &Address,
+        yyyymmdd: &str,
+    ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
+        self.days
+            .get(yyyymmdd)
+            .cloned()
+            .ok_or(BuilderLogsError::HttpStatus(404))
+    }
+}
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<hypesilico::Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(days: HashMap<String, Vec<BuilderLogFill>>) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(hypesilico::Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Logs,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone())
+        .with_builder_logs(Arc::new(DailyLogsSource { days }));
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(user: &str, time_ms: i64, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("50000").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("5").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        Some(tid),
+    )
+}
+
+fn log_row(fill: &Fill) -> BuilderLogFill {
+    BuilderLogFill {
+        time_ms: fill.time_ms,
+        user: fill.user.clone(),
+        coin: fill.coin.clone(),
+        side: fill.side,
+        px: fill.px,
+        sz: fill.sz,
+        tid: fill.tid,
+        oid: None,
+    }
+}
+
+async fn post_json(
+    app: axum::Router,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/v1/admin/builder-logs/backfill")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_backfill_stores_days_and_attributes_stored_users() {
+    let fills = [fill(USER, DAY1_MS, 1), fill(USER, DAY2_MS, 2)];
+    let days = HashMap::from([
+        (
+            "20240111".to_string(),
+            vec![log_row(&fills[0]), log_row(&fill(STRANGER, DAY1_MS, 3))],
+        ),
+        ("20240112".to_string(), vec![log_row(&fills[1])]),
+    ]);
+    let test_app = setup_test_app(days).await;
+    test_app.repo.insert_fills_batch(&fills).await.unwrap();
+
+    let request = serde_json::json!({ "fromDay": "20240111", "toDay": "20240113" });
+    let (status, json) = post_json(test_app.app.clone(), request.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+    assert_eq!(json["builders"], serde_json::json!([BUILDER]));
+    assert_eq!(json["fillsStored"], 3);
+    assert_eq!(json["failedDays"], 1);
+    let days = json["days"].as_array().unwrap();
+    assert_eq!(days.len(), 3);
+    assert_eq!(days[0]["fills"], 2);
+    assert_eq!(days[2]["day"], "20240113");
+    assert!(days[2]["error"].as_str().unwrap().contains("404"));
+    // Only users with stored fills are re-attributed.
+    assert_eq!(
+        json["users"],
+        serde_json::json!([{ "user": USER, "attributed": 2 }])
+    );
+
+    let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+    let attributions = test_app.repo.query_attributions_full(&fill_keys).await.unwrap();
+    assert!(attributions
+        .values()
+        .all(|a| a.mode == AttributionMode::Logs && a.attributed));
+
+    // Backfilling again replaces the days rather than duplicating them.
+    let (status, json) = post_json(test_app.app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["fillsStored"], 3);
+}
+
+#[tokio::test]
+async fn test_backfill_rejects_bad_ranges() {
+    let test_app = setup_test_app(HashMap::new()).await;
+
+    for body in [
+        serde_json::json!({ "fromDay": "20240112", "toDay": "20240111" }),
+        serde_json::json!({ "fromDay": "2024-01-11", "toDay": "20240111" }),
+        serde_json::json!({ "fromDay": "20200101", "toDay": "20240101" }),
+        serde_json::json!({ "fromDay": "20240111", "toDay": "20240111", "builders": ["0x12"] }),
+        serde_json::json!({
+            "fromDay": "20240111",
+            "toDay": "20240111",
+            "builders": (1..=9).map(|i| format!("0x{:040x}", i)).collect::<Vec<_>>(),
+        }),
+    ] {
+        let (status, json) = post_json(test_app.app.clone(), body.clone()).await;
+        assert!(status.is_client_error(), "{} -> {} {}", body, status, json);
+    }
+}
@@ -0,0 +1,151 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(target_builder: &str) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: target_builder.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_builder_diagnostics_normalizes_valid_builder() {
+    let TestApp { app, _temp } = setup_test_app("0x2868FC0D9786A740B491577A43502259EFA78A39").await;
+
+    let (status, body) = get_json(app, "/v1/admin/builders?day=20250117&probe=false").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["day"], "20250117");
+
+    let builder = &body["builders"][0];
+    assert_eq!(builder["source"], "TARGET_BUILDER");
+    assert_eq!(
+        builder["configured"],
+        "0x2868FC0D9786A740B491577A43502259EFA78A39"
+    );
+    assert_eq!(
+        builder["address"],
+        "0x2868fc0d9786a740b491577a43502259efa78a39"
+    );
+    assert_eq!(
+        builder["url"],
+        "https://stats-data.hyperliquid.xyz/Mainnet/builder_fills/0x2868fc0d9786a740b491577a43502259efa78a39/20250117.csv.lz4"
+    );
+    assert!(builder.get("error").is_none());
+    // Not probed, so no reachability fields.
+    assert!(builder.get("reachable").is_none());
+}
+
+#[tokio::test]
+async fn test_builder_diagnostics_reports_invalid_builder() {
+    let TestApp { app, _temp } = setup_test_app("0xbuilder").await;
+
+    let (status, body) = get_json(app, "/v1/admin/builders").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let builder = &body["builders"][0];
+    assert_eq!(builder["configured"], "0xbuilder");
+    assert_eq!(builder["error"], "address must be 42 characters, got 9");
+    assert!(builder.get("address").is_none());
+    assert!(builder.get("url").is_none());
+    assert!(builder.get("reachable").is_none());
+}
+
+#[tokio::test]
+async fn test_builder_diagnostics_rejects_bad_day() {
+    let TestApp { app, _temp } = setup_test_app("0x2868fc0d9786a740b491577a43502259efa78a39").await;
+
+    let (status, _) = get_json(app, "/v1/admin/builders?day=2025-01-17").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
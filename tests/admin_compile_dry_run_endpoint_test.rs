@@ -0,0 +1,214 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::compile::Compiler;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, CoinMeta, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    orchestrator: Arc<Orchestrator>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator.clone(), equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        orchestrator,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(side: Side, time_ms: i64, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_compile_dry_run_reports_uncompiled_rows() {
+    let TestApp { app, repo, _temp, .. } = setup_test_app().await;
+    repo.insert_fills_batch(&[fill(Side::Buy, 1000, 1), fill(Side::Sell, 2000, 2)])
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        &format!("/v1/admin/compile-dry-run?user={}&coin=BTC&includeRows=true", ALICE),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["coin"], "BTC");
+    assert_eq!(body["fills"], 2);
+    assert_eq!(body["lifecycles"], 1);
+    assert_eq!(body["effects"], 2);
+    assert_eq!(body["matchesStored"], false);
+    assert_eq!(body["diff"]["lifecycles"]["added"].as_array().unwrap().len(), 1);
+    assert_eq!(body["diff"]["snapshots"]["added"].as_array().unwrap().len(), 2);
+    assert_eq!(body["rows"]["effects"][0]["effectType"], "open");
+    assert_eq!(body["rows"]["effects"][1]["effectType"], "close");
+    assert_eq!(body["rows"]["lifecycles"][0]["endTimeMs"], 2000);
+
+    // Nothing was written.
+    let alice = Address::new(ALICE.to_string());
+    let btc = Coin::new("BTC".to_string());
+    assert!(repo.query_lifecycles(&alice, &btc).await.unwrap().is_empty());
+    assert!(repo.get_compile_state(&alice, &btc).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_compile_dry_run_matches_compiled_coin() {
+    let TestApp { app, repo, _temp, .. } = setup_test_app().await;
+    let alice = Address::new(ALICE.to_string());
+    repo.insert_fills_batch(&[fill(Side::Buy, 1000, 1), fill(Side::Sell, 2000, 2)])
+        .await
+        .unwrap();
+    Compiler::compile_incremental(&repo, &alice, &Coin::new("BTC".to_string()))
+        .await
+        .unwrap();
+
+    let (status, body) =
+        get_json(app, &format!("/v1/admin/compile-dry-run?user={}&coin=BTC", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["matchesStored"], true);
+    assert_eq!(body["diff"]["effects"]["unchanged"], 2);
+    assert!(body.get("rows").is_none());
+}
+
+#[tokio::test]
+async fn test_compile_dry_run_requires_user_and_coin() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app.clone(), "/v1/admin/compile-dry-run?user=nope&coin=BTC").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) =
+        get_json(app, &format!("/v1/admin/compile-dry-run?user={}&coin=", ALICE)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_compile_dry_run_resolves_coin_against_registry() {
+    let TestApp {
+        app,
+        repo,
+        orchestrator,
+        _temp,
+    } = setup_test_app().await;
+    let btc = CoinMeta {
+        coin: Coin::new("BTC".to_string()),
+        asset_index: 0,
+        sz_decimals: 5,
+        max_leverage: 50,
+        delisted: false,
+    };
+    repo.upsert_coins(&[btc], TimeMs::new(1)).await.unwrap();
+    orchestrator.load_coins().await.unwrap();
+    repo.insert_fills_batch(&[fill(Side::Buy, 1000, 1)]).await.unwrap();
+
+    let (status, body) =
+        get_json(app.clone(), &format!("/v1/admin/compile-dry-run?user={}&coin=btc", ALICE))
+            .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["coin"], "BTC");
+    assert_eq!(body["fills"], 1);
+
+    let (status, body) =
+        get_json(app, &format!("/v1/admin/compile-dry-run?user={}&coin=DOGEE", ALICE)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Unknown coin 'DOGEE'");
+}
@@ -0,0 +1,190 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::compile::Compiler;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(coin: &str, time_ms: i64, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new(coin.to_string()),
+        Side::Buy,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_compile_state_shows_fills_behind_watermark() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    let alice = Address::new(ALICE.to_string());
+    repo.insert_fills_batch(&[fill("BTC", 1000, 1), fill("BTC", 2000, 2)])
+        .await
+        .unwrap();
+    Compiler::compile_incremental(&repo, &alice, &Coin::new("BTC".to_string()))
+        .await
+        .unwrap();
+    repo.insert_fills_batch(&[fill("BTC", 3000, 3), fill("ETH", 4000, 4)])
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, &format!("/v1/admin/compile-state?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"], ALICE);
+
+    let coins = body["coins"].as_array().unwrap();
+    assert_eq!(coins.len(), 2);
+    assert_eq!(coins[0]["coin"], "BTC");
+    assert_eq!(coins[0]["lastCompiledTimeMs"], 2000);
+    assert_eq!(coins[0]["compileVersion"], 1);
+    assert_eq!(coins[0]["rawFills"], 3);
+    assert_eq!(coins[0]["compiledFills"], 2);
+    assert_eq!(coins[0]["pendingFills"], 1);
+    assert_eq!(coins[0]["latestRawTimeMs"], 3000);
+    assert_eq!(coins[0]["behind"], true);
+
+    assert_eq!(coins[1]["coin"], "ETH");
+    assert!(coins[1].get("lastCompiledTimeMs").is_none());
+    assert_eq!(coins[1]["pendingFills"], 1);
+    assert_eq!(coins[1]["behind"], true);
+}
+
+#[tokio::test]
+async fn test_compile_state_caught_up_after_compile() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    let alice = Address::new(ALICE.to_string());
+    let btc = Coin::new("BTC".to_string());
+    repo.insert_fills_batch(&[fill("BTC", 1000, 1)]).await.unwrap();
+    Compiler::compile_incremental(&repo, &alice, &btc).await.unwrap();
+    repo.insert_fills_batch(&[fill("BTC", 2000, 2)]).await.unwrap();
+    Compiler::compile_incremental(&repo, &alice, &btc).await.unwrap();
+
+    let (status, body) = get_json(app, &format!("/v1/admin/compile-state?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    let btc_state = &body["coins"][0];
+    assert_eq!(btc_state["compileVersion"], 2);
+    assert_eq!(btc_state["lastCompiledTimeMs"], 2000);
+    assert_eq!(btc_state["pendingFills"], 0);
+    assert_eq!(btc_state["behind"], false);
+}
+
+#[tokio::test]
+async fn test_compile_state_unknown_user_is_empty() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = get_json(app, &format!("/v1/admin/compile-state?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["coins"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_compile_state_rejects_invalid_user() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app, "/v1/admin/compile-state?user=nope").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,88 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::EquityResolver;
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{api, config::Config, db::init_db, DataSource, Repository};
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+fn write_config_file(path: &Path, db_path: &str, extra: &str) {
+    let content = format!(
+        "database_path = \"{}\"\n\
+         hyperliquid_api_url = \"http://example.invalid\"\n\
+         target_builder = \"0x0000000000000000000000000000000000000000\"\n\
+         {}\n",
+        db_path, extra
+    );
+    std::fs::write(path, content).unwrap();
+}
+
+async fn post_reload(app: axum::Router) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/admin/config/reload")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+// The endpoint reads CONFIG_FILE from the process environment, so everything that depends
+// on it stays in this one test.
+#[tokio::test]
+async fn test_config_reload_applies_file_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let config_path = temp_dir.path().join("hypesilico.toml");
+    write_config_file(&config_path, &db_path, "");
+    std::env::set_var("CONFIG_FILE", &config_path);
+
+    let config = Config::from_env().expect("config from file");
+    let repo = Arc::new(Repository::new(init_db(&db_path).await.unwrap()));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+    let app = api::create_router(state.clone());
+
+    let (status, body) = post_reload(app.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["changed"], serde_json::json!([]));
+    assert_eq!(body["restartRequired"], serde_json::json!([]));
+
+    write_config_file(
+        &config_path,
+        &db_path,
+        &format!("port = 9999\nleaderboard_users = [\"{}\"]", ALICE),
+    );
+    let (status, body) = post_reload(app.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["changed"], serde_json::json!(["leaderboard_users"]));
+    assert_eq!(body["restartRequired"], serde_json::json!(["port"]));
+    let config = state.config.load();
+    assert_eq!(config.leaderboard_users, vec![ALICE.to_string()]);
+    assert_eq!(config.port, 8080);
+
+    // A broken file is rejected whole and the running settings are kept.
+    write_config_file(&config_path, &db_path, "lookback_ms = \"soon\"");
+    let (status, body) = post_reload(app).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "CONFIG_ERROR");
+    assert_eq!(state.config.load().leaderboard_users, vec![ALICE.to_string()]);
+
+    std::env::remove_var("CONFIG_FILE");
+}
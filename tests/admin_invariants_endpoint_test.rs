@@ -0,0 +1,213 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::compile::Compiler;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{
+    EquityResolver, HeuristicAttributor, Invariant, InvariantViolation, MatchTolerances,
+};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+const BOB: &str = "0x0000000000000000000000000000000000000b0b";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_clean_compile_records_no_violations() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    repo.insert_fills_batch(&[
+        fill(1000, Side::Buy, "1", 1),
+        fill(2000, Side::Sell, "3", 2),
+        fill(3000, Side::Buy, "2", 3),
+    ])
+    .await
+    .unwrap();
+
+    let compiled = Compiler::compile_incremental(
+        &repo,
+        &Address::new(ALICE.to_string()),
+        &Coin::new("BTC".to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(compiled, 3);
+
+    let (status, body) = get_json(app, "/v1/admin/invariants").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["counts"], serde_json::json!({}));
+    assert_eq!(body["violations"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_reports_recorded_violations_newest_first() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    let btc = Coin::new("BTC".to_string());
+    repo.insert_invariant_violations(
+        &Address::new(ALICE.to_string()),
+        &btc,
+        &[InvariantViolation {
+            invariant: Invariant::EffectQty,
+            fill_key: Some("k1".to_string()),
+            lifecycle_id: None,
+            detail: "effects carry qty 0.9 but the fill size is 1".to_string(),
+        }],
+        10,
+    )
+    .await
+    .unwrap();
+    repo.insert_invariant_violations(
+        &Address::new(BOB.to_string()),
+        &btc,
+        &[InvariantViolation {
+            invariant: Invariant::LifecycleBracket,
+            fill_key: None,
+            lifecycle_id: Some(7),
+            detail: "lifecycle ends at 1 before its last fill at 2".to_string(),
+        }],
+        20,
+    )
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(app.clone(), "/v1/admin/invariants").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["counts"],
+        serde_json::json!({"effect_qty": 1, "lifecycle_bracket": 1})
+    );
+    let violations = body["violations"].as_array().unwrap();
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[0]["invariant"], "lifecycle_bracket");
+    assert_eq!(violations[0]["lifecycleId"], 7);
+    assert!(violations[0].get("fillKey").is_none());
+    assert_eq!(violations[1]["fillKey"], "k1");
+    assert_eq!(violations[1]["recordedAtMs"], 10);
+
+    let (status, body) =
+        get_json(app.clone(), &format!("/v1/admin/invariants?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["counts"], serde_json::json!({"effect_qty": 1}));
+    assert_eq!(body["violations"].as_array().unwrap().len(), 1);
+
+    let (status, body) = get_json(app, "/v1/admin/invariants?limit=1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["violations"].as_array().unwrap().len(), 1);
+    assert_eq!(body["counts"]["effect_qty"], 1);
+}
+
+#[tokio::test]
+async fn test_rejects_bad_limit() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app, "/v1/admin/invariants?limit=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,172 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::telemetry::reloadable_subscriber;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+use tracing_subscriber::EnvFilter;
+
+struct TestApp {
+    app: axum::Router,
+    /// Keeps the reloadable filter alive; the handle errors once this is dropped.
+    _subscriber: Box<dyn tracing::Subscriber + Send + Sync>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(with_log_filter: bool) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let mut state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+
+    let (subscriber, handle) = reloadable_subscriber(EnvFilter::new("info"));
+    if with_log_filter {
+        state = state.with_log_filter(handle);
+    }
+
+    TestApp {
+        app: api::create_router(state),
+        _subscriber: Box::new(subscriber),
+        _temp: temp_dir,
+    }
+}
+
+async fn send(
+    app: axum::Router,
+    method: &str,
+    body: Option<&str>,
+) -> (StatusCode, serde_json::Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri("/v1/admin/log-filter");
+    let body = match body {
+        Some(body) => {
+            request = request.header("content-type", "application/json");
+            axum::body::Body::from(body.to_string())
+        }
+        None => axum::body::Body::empty(),
+    };
+
+    let response = app.oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_log_filter_update_and_reset() {
+    let TestApp { app, .. } = &setup_test_app(true).await;
+
+    let (status, body) = send(app.clone(), "GET", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["filter"], "info");
+
+    let (status, body) = send(
+        app.clone(),
+        "PUT",
+        Some(r#"{"filter":"info,hypesilico::compile=debug"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let applied = body["filter"].as_str().unwrap().to_string();
+    assert!(applied.contains("hypesilico::compile=debug"));
+
+    let (_, body) = send(app.clone(), "GET", None).await;
+    assert_eq!(body["filter"], applied.as_str());
+
+    let (status, body) = send(app.clone(), "DELETE", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["filter"], "info");
+}
+
+#[tokio::test]
+async fn test_log_filter_rejects_invalid_directives() {
+    let TestApp { app, .. } = &setup_test_app(true).await;
+
+    let (status, body) = send(app.clone(), "PUT", Some(r#"{"filter":"hypesilico=loud"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("invalid filter directives"));
+
+    let (status, _) = send(app.clone(), "PUT", Some(r#"{"level":"debug"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(app.clone(), "GET", None).await;
+    assert_eq!(body["filter"], "info");
+}
+
+#[tokio::test]
+async fn test_log_filter_not_found_without_reloadable_subscriber() {
+    let TestApp { app, .. } = &setup_test_app(false).await;
+
+    let (status, _) = send(app.clone(), "GET", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,158 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::{init_db, MaintenanceConfig},
+    DataSource, Repository,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    backup_dir: PathBuf,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(backups: bool) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let backup_dir = temp_dir.path().join("backups");
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: MaintenanceConfig {
+            backup_dir: backups.then(|| backup_dir.clone()),
+            backup_keep: 1,
+            ..MaintenanceConfig::default()
+        },
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        backup_dir,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_maintenance(
+    app: axum::Router,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/admin/maintenance")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_maintenance_runs_requested_tasks_in_order() {
+    let TestApp {
+        app,
+        backup_dir,
+        _temp,
+    } = setup_test_app(true).await;
+
+    let (status, body) = post_maintenance(
+        app.clone(),
+        serde_json::json!({"tasks": ["checkpoint", "analyze", "backup"]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["task"], "checkpoint");
+    assert_eq!(results[0]["busy"], false);
+    assert_eq!(results[1]["task"], "analyze");
+    assert_eq!(results[2]["task"], "backup");
+    let first = PathBuf::from(results[2]["path"].as_str().unwrap());
+    assert!(first.starts_with(&backup_dir) && first.exists());
+
+    // BACKUP_KEEP is 1, so the second backup replaces the first.
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (status, body) = post_maintenance(app, serde_json::json!({"tasks": ["backup"]})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["results"][0]["removed"],
+        serde_json::json!([first.display().to_string()])
+    );
+    assert!(!first.exists());
+}
+
+#[tokio::test]
+async fn test_maintenance_rejects_unknown_or_unconfigured_tasks() {
+    let TestApp { app, _temp, .. } = setup_test_app(false).await;
+
+    let (status, body) = post_maintenance(app.clone(), serde_json::json!({"tasks": ["vacuum"]})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("vacuum"));
+
+    let (status, _) = post_maintenance(app.clone(), serde_json::json!({"tasks": []})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = post_maintenance(app, serde_json::json!({"tasks": ["backup"]})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("DB_BACKUP_DIR"));
+}
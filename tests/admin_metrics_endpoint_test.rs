@@ -0,0 +1,122 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::{init_db_with_pools, DbPoolConfig},
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+async fn setup_test_app(db_pool: DbPoolConfig) -> (axum::Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let (pool, read_pool) = init_db_with_pools(&db_path, &db_pool)
+        .await
+        .expect("init_db_with_pools failed");
+
+    let mut repo = Repository::new(pool);
+    if let Some(read_pool) = read_pool {
+        repo = repo.with_read_pool(read_pool);
+    }
+    let repo = Arc::new(repo);
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool,
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+
+    (api::create_router(state), temp_dir)
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_metrics_report_read_and_write_pools() {
+    let (app, _temp) = setup_test_app(DbPoolConfig::default()).await;
+
+    let (status, _) = get_json(app.clone(), &format!("/v1/trades?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = get_json(app, "/v1/admin/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    let pools = &body["dbPools"];
+    assert_eq!(pools["write"]["maxConnections"], 5);
+    assert_eq!(pools["read"]["maxConnections"], 8);
+    assert!(pools["read"]["acquired"].as_u64().unwrap() > 0);
+    assert_eq!(pools["read"]["failed"], 0);
+    assert!(pools["read"]["waitUsAvg"].is_u64());
+}
+
+#[tokio::test]
+async fn test_metrics_omit_read_pool_when_shared() {
+    let (app, _temp) = setup_test_app(DbPoolConfig {
+        write_connections: 3,
+        read_connections: 0,
+    })
+    .await;
+
+    let (status, body) = get_json(app, "/v1/admin/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["dbPools"]["write"]["maxConnections"], 3);
+    assert!(body["dbPools"].get("read").is_none());
+}
@@ -0,0 +1,213 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    pool: SqlitePool,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool.clone()));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        pool,
+        _temp: temp_dir,
+    }
+}
+
+async fn send(app: axum::Router, method: &str, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(time_ms: i64, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Some(Decimal::from_str("0.01").unwrap()),
+        Some(tid),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_sweep_quarantines_corrupt_fill_and_blocks_builder_only_queries() {
+    let TestApp {
+        app,
+        repo,
+        pool,
+        _temp,
+    } = setup_test_app().await;
+    repo.insert_fills_batch(&[fill(1000, "100", 1), fill(2000, "101", 2)])
+        .await
+        .unwrap();
+    sqlx::query("UPDATE raw_fills SET px = 'garbage' WHERE tid = 2")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Until it is swept, the corrupt value fails every read of the user's fills.
+    let (status, _) = send(app.clone(), "GET", &format!("/v1/trades?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let (status, body) = send(app.clone(), "POST", "/v1/admin/quarantine").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["moved"], serde_json::json!({ "raw_fills": 1 }));
+    assert_eq!(body["resetPairs"], serde_json::json!([format!("{}:BTC", ALICE)]));
+
+    let (status, body) =
+        send(app.clone(), "GET", &format!("/v1/admin/quarantine?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["counts"], serde_json::json!({ "raw_fills": 1 }));
+    let row = &body["rows"][0];
+    assert_eq!(row["table"], "raw_fills");
+    assert_eq!(row["column"], "px");
+    assert_eq!(row["value"], "garbage");
+    assert_eq!(row["row"]["tid"], 2);
+
+    let (_, body) = send(app.clone(), "GET", "/v1/admin/metrics").await;
+    assert_eq!(body["quarantinedRows"], serde_json::json!({ "raw_fills": 1 }));
+
+    // The remaining fill is served; builder-only results would silently miss one.
+    let (status, body) = send(app.clone(), "GET", &format!("/v1/trades?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["trades"].as_array().unwrap().len(), 1);
+
+    let (status, body) = send(
+        app.clone(),
+        "GET",
+        &format!("/v1/trades?user={}&builderOnly=true", ALICE),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "DATA_INTEGRITY");
+
+    let (status, body) = send(
+        app.clone(),
+        "GET",
+        &format!("/v1/lifecycles?user={}&builderOnly=true", ALICE),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "DATA_INTEGRITY");
+
+    // Dismissing the row lifts the block.
+    let id = row["id"].as_i64().unwrap();
+    let (status, _) = send(app.clone(), "DELETE", &format!("/v1/admin/quarantine/{}", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(app.clone(), "DELETE", &format!("/v1/admin/quarantine/{}", id)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        app,
+        "GET",
+        &format!("/v1/trades?user={}&builderOnly=true", ALICE),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_sweep_of_clean_database_moves_nothing() {
+    let TestApp { app, repo, _temp, .. } = setup_test_app().await;
+    repo.insert_fills_batch(&[fill(1000, "100", 1)]).await.unwrap();
+
+    let (status, body) = send(app.clone(), "POST", "/v1/admin/quarantine").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["moved"], serde_json::json!({}));
+
+    let (status, body) = send(app, "GET", "/v1/admin/quarantine").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["counts"], serde_json::json!({}));
+    assert_eq!(body["rows"], serde_json::json!([]));
+}
@@ -0,0 +1,206 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::compile::Compiler;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+const BOB: &str = "0x0000000000000000000000000000000000000b0b";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(user: &str, time_ms: i64, side: Side, px: &str, sz: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn compile(repo: &Repository, user: &str) {
+    Compiler::compile_incremental(
+        repo,
+        &Address::new(user.to_string()),
+        &Coin::new("BTC".to_string()),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_matching_closed_pnl_records_no_issues() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    repo.insert_fills_batch(&[
+        fill(ALICE, 1000, Side::Buy, "100", "1", "0", 1),
+        fill(ALICE, 2000, Side::Buy, "110", "1", "0", 2),
+        fill(ALICE, 3000, Side::Sell, "120", "2", "30", 3),
+    ])
+    .await
+    .unwrap();
+    compile(&repo, ALICE).await;
+
+    let (status, body) = get_json(app, "/v1/admin/reconciliation").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 0);
+    assert_eq!(body["issues"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_reports_lifecycles_with_mismatched_closed_pnl() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    repo.insert_fills_batch(&[
+        // Long from 100 closed at 110 realizes 10, reported as 12.
+        fill(ALICE, 1000, Side::Buy, "100", "1", "0", 1),
+        fill(ALICE, 2000, Side::Sell, "110", "1", "12", 2),
+        fill(BOB, 1000, Side::Sell, "100", "1", "0", 3),
+        fill(BOB, 2000, Side::Buy, "90", "1", "5", 4),
+    ])
+    .await
+    .unwrap();
+    compile(&repo, ALICE).await;
+    compile(&repo, BOB).await;
+
+    let (status, body) = get_json(app.clone(), "/v1/admin/reconciliation").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 2);
+    let issues = body["issues"].as_array().unwrap();
+    assert_eq!(issues[0]["user"], BOB);
+    assert_eq!(issues[0]["difference"], "-5");
+    assert_eq!(issues[1]["user"], ALICE);
+    assert_eq!(issues[1]["coin"], "BTC");
+    assert_eq!(issues[1]["startTimeMs"], 1000);
+    assert_eq!(issues[1]["reportedPnl"], "12");
+    assert_eq!(issues[1]["recomputedPnl"], "10");
+    assert_eq!(issues[1]["difference"], "2");
+
+    let (status, body) =
+        get_json(app.clone(), &format!("/v1/admin/reconciliation?user={}", ALICE)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 1);
+    assert_eq!(body["issues"].as_array().unwrap().len(), 1);
+
+    let (status, body) = get_json(app.clone(), "/v1/admin/reconciliation?limit=1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 2);
+    assert_eq!(body["issues"].as_array().unwrap().len(), 1);
+
+    // A rebuild drops the pair's issues along with its other derived rows.
+    repo.reset_derived_state(
+        &Address::new(ALICE.to_string()),
+        &Coin::new("BTC".to_string()),
+    )
+    .await
+    .unwrap();
+    let (_, body) = get_json(app, &format!("/v1/admin/reconciliation?user={}", ALICE)).await;
+    assert_eq!(body["count"], 0);
+}
+
+#[tokio::test]
+async fn test_rejects_bad_limit() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app, "/v1/admin/reconciliation?limit=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,213 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_replay(app: axum::Router, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/admin/replay")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn stored_fill(tid: i64, time_ms: i64, px: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+/// Record a `userFillsByTime` response holding tid 1 at px 101 and tid 2 at px 102.
+async fn record_payload(repo: &Repository) {
+    let request = serde_json::json!({
+        "type": "userFillsByTime",
+        "user": ALICE,
+        "coin": "BTC",
+        "startTime": 0,
+        "endTime": 10_000,
+        "aggregateByTime": false
+    });
+    let content = serde_json::json!([
+        {"coin": "BTC", "time": 1000, "side": "A", "px": "101", "sz": "1", "fee": "0.1", "closedPnl": "0", "tid": 1},
+        {"coin": "BTC", "time": 2000, "side": "A", "px": "102", "sz": "1", "fee": "0.1", "closedPnl": "0", "tid": 2}
+    ]);
+    repo.insert_raw_payload(
+        "userFillsByTime",
+        Some(ALICE),
+        &request.to_string(),
+        content.to_string().as_bytes(),
+        5000,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_replay_reports_corrections_without_applying() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    repo.insert_fill(&stored_fill(1, 1000, "100")).await.unwrap();
+    record_payload(&repo).await;
+
+    let (status, body) = post_replay(app, serde_json::json!({"user": ALICE})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["payloads"], 1);
+    assert_eq!(body["replayedFills"], 2);
+    assert_eq!(body["unchanged"], 0);
+    assert_eq!(body["applied"], false);
+    assert_eq!(body["missing"][0]["timeMs"], 2000);
+    assert_eq!(
+        body["changed"][0]["changes"],
+        serde_json::json!([{"field": "px", "stored": "100", "replayed": "101"}])
+    );
+
+    let fills = repo
+        .query_fills(&Address::new(ALICE.to_string()), None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].px.to_canonical_string(), "100");
+}
+
+#[tokio::test]
+async fn test_replay_applies_corrections_and_rebuilds() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    repo.insert_fill(&stored_fill(1, 1000, "100")).await.unwrap();
+    record_payload(&repo).await;
+
+    let (status, body) = post_replay(app.clone(), serde_json::json!({"user": ALICE, "apply": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["applied"], true);
+    assert_eq!(body["rebuiltCoins"], serde_json::json!(["BTC"]));
+
+    let fills = repo
+        .query_fills(&Address::new(ALICE.to_string()), None, None, None)
+        .await
+        .unwrap();
+    let prices: Vec<String> = fills.iter().map(|f| f.px.to_canonical_string()).collect();
+    assert_eq!(prices, vec!["101", "102"]);
+
+    // Once applied, a second replay finds nothing to correct.
+    let (_, body) = post_replay(app, serde_json::json!({"user": ALICE})).await;
+    assert_eq!(body["unchanged"], 2);
+    assert_eq!(body["missing"], serde_json::json!([]));
+    assert_eq!(body["changed"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_replay_without_payloads_is_empty() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = post_replay(app, serde_json::json!({"user": ALICE, "apply": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["payloads"], 0);
+    assert_eq!(body["applied"], false);
+}
+
+#[tokio::test]
+async fn test_replay_rejects_invalid_user() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = post_replay(app, serde_json::json!({"user": "nope"})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
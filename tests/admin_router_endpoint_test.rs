@@ -0,0 +1,132 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+/// The public router and the admin one, from a config with `admin_port`.
+struct TestApp {
+    app: axum::Router,
+    admin_app: axum::Router,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(admin_port: Option<u16>) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let admin_app = api::create_admin_router(state.clone());
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        admin_app,
+        _temp: temp_dir,
+    }
+}
+
+async fn status(app: &axum::Router, method: &str, uri: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_admin_port_moves_admin_endpoints_off_public_router() {
+    let TestApp {
+        app,
+        admin_app,
+        _temp,
+    } = setup_test_app(Some(9091)).await;
+
+    let trades = format!("/v1/trades?user={}", USER);
+    assert_eq!(status(&app, "GET", &trades).await, StatusCode::OK);
+    assert_eq!(status(&app, "GET", "/health").await, StatusCode::OK);
+    assert_eq!(status(&app, "GET", "/v1/admin/metrics").await, StatusCode::NOT_FOUND);
+    assert_eq!(
+        status(&app, "POST", "/v1/admin/config/reload").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(status(&app, "POST", "/v1/admin/backup").await, StatusCode::NOT_FOUND);
+
+    assert_eq!(status(&admin_app, "GET", "/v1/admin/metrics").await, StatusCode::OK);
+    assert_eq!(status(&admin_app, "GET", "/health").await, StatusCode::OK);
+    assert_eq!(status(&admin_app, "GET", &trades).await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&admin_app, "GET", "/openapi.json").await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_admin_endpoints_stay_on_public_router_without_admin_port() {
+    let TestApp { app, _temp, .. } = setup_test_app(None).await;
+
+    assert_eq!(status(&app, "GET", "/v1/admin/metrics").await, StatusCode::OK);
+    assert_eq!(
+        status(&app, "GET", &format!("/v1/trades?user={}", USER)).await,
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,296 @@
+use axum::http::StatusCode;
+use hypesilico::api;
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::{BuilderLogsError, BuilderLogsSource, MockDataSource};
+use hypesilico::db::init_db;
+use hypesilico::db::repo::BuilderLogFileRow;
+use hypesilico::domain::{Address, AttributionMode, BuilderLogFill, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const BUILDER: &str = "0x2868fc0d9786a740b491577a43502259efa78a39";
+const USER: &str = "0x1111111111111111111111111111111111111111";
+/// 2024-01-11T19:06:40Z
+const DAY1_MS: i64 = 1_705_000_000_000;
+/// 2024-01-12T00:00:00Z
+const DAY2_START_MS: i64 = 1_705_017_600_000;
+const DAY2_MS: i64 = DAY2_START_MS + 3_600_000;
+
+/// Serves every fill it was built with as a builder log row.
+struct StaticLogsSource {
+    fills: Vec<BuilderLogFill>,
+}
+
+#[async_trait::async_trait]
+impl BuilderLogsSource for StaticLogsSource {
+    async fn fetch_and_parse_day(
+        &self,
+        _builder: &Address,
+        _yyyymmdd: &str,
+    ) -> Result<Vec<BuilderLogFill>, BuilderLogsError> {
+        Ok(self.fills.clone())
+    }
+}
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<hypesilico::Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(
+    mode: BuilderAttributionMode,
+    auto_backfill: bool,
+    logs: Option<Vec<BuilderLogFill>>,
+) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(hypesilico::Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: mode,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: auto_backfill,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let mut ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    if let Some(fills) = logs {
+        ingestor = ingestor.with_builder_logs(Arc::new(StaticLogsSource { fills }));
+    }
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, tid: i64, side: Side) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str("50000").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("5").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        Some(tid),
+    )
+}
+
+fn log_row(fill: &Fill) -> BuilderLogFill {
+    BuilderLogFill {
+        time_ms: fill.time_ms,
+        user: fill.user.clone(),
+        coin: fill.coin.clone(),
+        side: fill.side,
+        px: fill.px,
+        sz: fill.sz,
+        tid: fill.tid,
+        oid: None,
+    }
+}
+
+async fn cache_day(repo: &hypesilico::Repository, yyyymmdd: &str) {
+    repo.upsert_builder_log_file(&BuilderLogFileRow {
+        builder: BUILDER.to_string(),
+        yyyymmdd: yyyymmdd.to_string(),
+        sha256: "00".to_string(),
+        etag: None,
+        row_count: 0,
+        fetched_at_ms: 0,
+        content: Vec::new(),
+    })
+    .await
+    .unwrap();
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> serde_json::Value {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_builder_only_reports_pending_days_and_covered_window() {
+    let test_app = setup_test_app(BuilderAttributionMode::Logs, false, None).await;
+    test_app
+        .repo
+        .insert_fills_batch(&[fill(DAY1_MS, 1, Side::Buy), fill(DAY2_MS, 2, Side::Sell)])
+        .await
+        .unwrap();
+    cache_day(&test_app.repo, "20240111").await;
+
+    for path in ["/v1/trades", "/v1/stats", "/v1/pnl", "/v1/positions/history"] {
+        let json = get_json(
+            test_app.app.clone(),
+            &format!("{}?user={}&builderOnly=true", path, USER),
+        )
+        .await;
+
+        assert_eq!(json["attributionPending"], true, "{}", path);
+        let coverage = &json["attributionCoverage"];
+        assert_eq!(coverage["coveredToMs"], DAY2_START_MS - 1, "{}", path);
+        assert_eq!(coverage["pendingDays"], serde_json::json!(["20240112"]), "{}", path);
+        assert_eq!(coverage["backfillQueued"], false, "{}", path);
+    }
+
+    // The covered window is clipped to the request.
+    let json = get_json(
+        test_app.app.clone(),
+        &format!(
+            "/v1/trades?user={}&builderOnly=true&fromMs={}&toMs={}",
+            USER, DAY1_MS, DAY1_MS + 1000
+        ),
+    )
+    .await;
+    assert_eq!(json["attributionPending"], false);
+    assert!(json.get("attributionCoverage").is_none());
+}
+
+#[tokio::test]
+async fn test_first_pending_day_leaves_nothing_covered() {
+    let test_app = setup_test_app(BuilderAttributionMode::Auto, false, None).await;
+    test_app
+        .repo
+        .insert_fills_batch(&[fill(DAY1_MS, 1, Side::Buy), fill(DAY2_MS, 2, Side::Sell)])
+        .await
+        .unwrap();
+    cache_day(&test_app.repo, "20240112").await;
+
+    let json = get_json(
+        test_app.app,
+        &format!("/v1/trades?user={}&builderOnly=true", USER),
+    )
+    .await;
+
+    assert_eq!(json["attributionPending"], true);
+    assert!(json["attributionCoverage"].get("coveredToMs").is_none());
+    assert_eq!(
+        json["attributionCoverage"]["pendingDays"],
+        serde_json::json!(["20240111"])
+    );
+}
+
+#[tokio::test]
+async fn test_no_coverage_fields_without_builder_only_or_in_heuristic_mode() {
+    let test_app = setup_test_app(BuilderAttributionMode::Logs, false, None).await;
+    test_app
+        .repo
+        .insert_fill(&fill(DAY1_MS, 1, Side::Buy))
+        .await
+        .unwrap();
+    let json = get_json(test_app.app, &format!("/v1/trades?user={}", USER)).await;
+    assert!(json.get("attributionPending").is_none());
+    assert!(json.get("attributionCoverage").is_none());
+
+    let test_app = setup_test_app(BuilderAttributionMode::Heuristic, false, None).await;
+    test_app
+        .repo
+        .insert_fill(&fill(DAY1_MS, 1, Side::Buy))
+        .await
+        .unwrap();
+    let json = get_json(
+        test_app.app,
+        &format!("/v1/trades?user={}&builderOnly=true", USER),
+    )
+    .await;
+    assert!(json.get("attributionPending").is_none());
+}
+
+#[tokio::test]
+async fn test_auto_backfill_attributes_pending_fills() {
+    let fills = [fill(DAY1_MS, 1, Side::Buy), fill(DAY2_MS, 2, Side::Sell)];
+    let logs = fills.iter().map(log_row).collect();
+    let test_app = setup_test_app(BuilderAttributionMode::Logs, true, Some(logs)).await;
+    test_app.repo.insert_fills_batch(&fills).await.unwrap();
+
+    let uri = format!("/v1/trades?user={}&builderOnly=true", USER);
+    let json = get_json(test_app.app.clone(), &uri).await;
+    assert_eq!(json["attributionPending"], true);
+    assert_eq!(json["attributionCoverage"]["backfillQueued"], true);
+    // Compilation gave the fills heuristic attributions; no builder fee means excluded.
+    assert_eq!(json["trades"].as_array().unwrap().len(), 0);
+
+    let fill_keys: Vec<String> = fills.iter().map(|f| f.fill_key.clone()).collect();
+    let mut attributed = false;
+    for _ in 0..100 {
+        let attributions = test_app.repo.query_attributions_full(&fill_keys).await.unwrap();
+        if attributions
+            .values()
+            .filter(|a| a.mode == AttributionMode::Logs && a.attributed)
+            .count()
+            == fills.len()
+        {
+            attributed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(attributed, "backfill did not attribute the pending fills");
+
+    let json = get_json(test_app.app, &uri).await;
+    assert_eq!(json["trades"].as_array().unwrap().len(), 2);
+}
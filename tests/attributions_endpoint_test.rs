@@ -0,0 +1,269 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{
+        Address, Attribution, AttributionConfidence, AttributionReason, Coin, Decimal, Fill, Side,
+        TimeMs, ToleranceTier,
+    },
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, builder_fee: Option<&str>, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        builder_fee.map(|s| Decimal::from_str(s).unwrap()),
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_attributions_explain_heuristic_decisions() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    let with_fee = fill(1000, Some("0.5"), 1);
+    let without_fee = fill(2000, None, 2);
+    repo.insert_fill(&with_fee).await.unwrap();
+    repo.insert_fill(&without_fee).await.unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/attributions?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let rows = body["attributions"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+
+    assert_eq!(rows[0]["fillKey"], with_fee.fill_key.as_str());
+    assert_eq!(rows[0]["attributed"], true);
+    assert_eq!(rows[0]["mode"], "heuristic");
+    assert_eq!(rows[0]["confidence"], "low");
+    assert_eq!(rows[0]["builderFee"], "0.5");
+    assert_eq!(rows[0]["reason"], "builder fee > 0 (heuristic)");
+    assert!(rows[0].get("matchedLogKey").is_none());
+
+    assert_eq!(rows[1]["attributed"], false);
+    assert_eq!(rows[1]["reason"], "no builder fee (heuristic)");
+}
+
+#[tokio::test]
+async fn test_attributions_expose_matched_log_row() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    let matched = fill(1000, None, 1);
+    repo.insert_fill(&matched).await.unwrap();
+    let attribution = Attribution::from_logs_match(
+        true,
+        Some(Address::new(
+            "0x0000000000000000000000000000000000000000".to_string(),
+        )),
+        AttributionConfidence::Fuzzy,
+    )
+    .with_tolerance_tier(Some(ToleranceTier::Bps))
+    .with_matched_log_key("1000:0xabc:BTC:buy:100.01:1".to_string());
+    repo.upsert_attributions_full(&[(matched.fill_key.clone(), attribution)])
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/attributions?user={}&coin=BTC&fromMs=0&toMs=5000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let row = &body["attributions"][0];
+    assert_eq!(row["attributed"], true);
+    assert_eq!(row["mode"], "logs");
+    assert_eq!(row["confidence"], "fuzzy");
+    assert_eq!(row["toleranceTier"], "bps");
+    assert_eq!(row["matchedLogKey"], "1000:0xabc:BTC:buy:100.01:1");
+    assert_eq!(
+        row["reason"],
+        "matched builder log row within bps tolerances"
+    );
+    assert_eq!(row["score"], 50);
+    assert_eq!(row["reasonDetail"], serde_json::json!({"kind": "fields"}));
+}
+
+#[tokio::test]
+async fn test_attributions_filter_by_min_score() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    let matched = fill(1000, None, 1);
+    let heuristic = fill(2000, Some("0.5"), 2);
+    repo.insert_fill(&matched).await.unwrap();
+    repo.insert_fill(&heuristic).await.unwrap();
+    let attribution = Attribution::from_logs_match(
+        true,
+        Some(Address::new(
+            "0x0000000000000000000000000000000000000000".to_string(),
+        )),
+        AttributionConfidence::Fuzzy,
+    )
+    .with_tolerance_tier(Some(ToleranceTier::Absolute))
+    .with_reason(
+        AttributionReason::Fields {
+            time_delta_ms: Some(-200),
+        },
+        86,
+    );
+    repo.upsert_attributions_full(&[
+        (matched.fill_key.clone(), attribution),
+        (heuristic.fill_key.clone(), Attribution::heuristic(true)),
+    ])
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(
+        app.clone(),
+        format!("/v1/attributions?user={}&minScore=60", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let rows = body["attributions"].as_array().unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["fillKey"], matched.fill_key.as_str());
+    assert_eq!(rows[0]["score"], 86);
+    assert_eq!(
+        rows[0]["reasonDetail"],
+        serde_json::json!({"kind": "fields", "timeDeltaMs": -200})
+    );
+    assert_eq!(
+        rows[0]["reason"],
+        "matched builder log row on time, price, and size within 200 ms and absolute tolerances"
+    );
+
+    let (_, body) = get_json(app, format!("/v1/attributions?user={}&minScore=30", USER)).await;
+    let rows = body["attributions"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1]["score"], 30);
+    assert_eq!(rows[1]["reasonDetail"]["kind"], "builderFee");
+}
+
+#[tokio::test]
+async fn test_attributions_rejects_invalid_input() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app.clone(), "/v1/attributions?user=0x123".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get_json(
+        app.clone(),
+        format!("/v1/attributions?user={}&fromMs=2000&toMs=1000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) =
+        get_json(app, format!("/v1/attributions?user={}&minScore=101", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("minScore"));
+}
@@ -0,0 +1,218 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::api::auth::{ApiKeyConfig, ApiScope};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+const READ_KEY: &str = "read-key";
+const ADMIN_KEY: &str = "admin-key";
+
+async fn setup_test_app(api_keys: Vec<ApiKeyConfig>) -> (axum::Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys,
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+    (api::create_router(state), temp_dir)
+}
+
+fn keys(read_rpm: u32) -> Vec<ApiKeyConfig> {
+    vec![
+        ApiKeyConfig {
+            key: READ_KEY.to_string(),
+            scope: ApiScope::Read,
+            requests_per_minute: read_rpm,
+        },
+        ApiKeyConfig {
+            key: ADMIN_KEY.to_string(),
+            scope: ApiScope::Admin,
+            requests_per_minute: 100,
+        },
+    ]
+}
+
+async fn get(
+    app: &axum::Router,
+    uri: &str,
+    header: Option<(&str, &str)>,
+) -> (StatusCode, axum::http::HeaderMap, serde_json::Value) {
+    let mut req = Request::builder().method("GET").uri(uri);
+    if let Some((name, value)) = header {
+        req = req.header(name, value);
+    }
+    let resp = app
+        .clone()
+        .oneshot(req.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    (status, headers, json)
+}
+
+fn bearer(key: &str) -> String {
+    format!("Bearer {}", key)
+}
+
+#[tokio::test]
+async fn test_no_keys_configured_leaves_api_open() {
+    let (app, _temp) = setup_test_app(vec![]).await;
+    let (status, _, _) = get(&app, &format!("/v1/trades?user={}", USER), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_missing_or_unknown_key_is_rejected() {
+    let (app, _temp) = setup_test_app(keys(100)).await;
+    let uri = format!("/v1/trades?user={}", USER);
+
+    let (status, headers, json) = get(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["code"], "UNAUTHORIZED");
+    assert_eq!(headers["www-authenticate"], "Bearer");
+
+    let (status, _, _) = get(&app, &uri, Some(("authorization", &bearer("nope")))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Probes stay public.
+    let (status, _, _) = get(&app, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_scopes_gate_admin_endpoints() {
+    let (app, _temp) = setup_test_app(keys(100)).await;
+
+    let (status, _, _) = get(
+        &app,
+        &format!("/v1/trades?user={}", USER),
+        Some(("x-api-key", READ_KEY)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, json) = get(
+        &app,
+        "/v1/admin/invariants",
+        Some(("authorization", &bearer(READ_KEY))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(json["code"], "FORBIDDEN");
+
+    let (status, _, _) = get(
+        &app,
+        "/v1/admin/invariants",
+        Some(("authorization", &bearer(ADMIN_KEY))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rate_limit_is_per_key() {
+    let (app, _temp) = setup_test_app(keys(2)).await;
+    let uri = format!("/v1/trades?user={}", USER);
+    let read = bearer(READ_KEY);
+
+    for _ in 0..2 {
+        let (status, _, _) = get(&app, &uri, Some(("authorization", &read))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, headers, json) = get(&app, &uri, Some(("authorization", &read))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(json["code"], "RATE_LIMITED");
+    assert_eq!(headers["retry-after"], "30");
+
+    // Another key has its own budget.
+    let (status, _, _) = get(&app, &uri, Some(("authorization", &bearer(ADMIN_KEY)))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_batch_costs_a_token_per_user() {
+    let (app, _temp) = setup_test_app(keys(3)).await;
+    let body = serde_json::json!({
+        "users": [USER, "0x2222222222222222222222222222222222222222"],
+    });
+    let req = Request::builder()
+        .method("POST")
+        .uri("/v1/batch/pnl")
+        .header("authorization", bearer(READ_KEY))
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let uri = format!("/v1/trades?user={}", USER);
+    let read = bearer(READ_KEY);
+    let (status, _, _) = get(&app, &uri, Some(("authorization", &read))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, headers, _) = get(&app, &uri, Some(("authorization", &read))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(headers["retry-after"], "20");
+}
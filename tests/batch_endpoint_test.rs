@@ -0,0 +1,196 @@
+use axum::http::StatusCode;
+use hypesilico::api::{self, AppState};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x1111111111111111111111111111111111111111";
+const BOB: &str = "0x2222222222222222222222222222222222222222";
+
+struct TestApp {
+    app: axum::Router,
+    state: AppState,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = AppState::new(repo, config, orchestrator, equity_resolver);
+    let app = api::create_router(state.clone());
+
+    TestApp {
+        app,
+        state,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(user: &str, tid: i64, side: Side, px: &str, closed_pnl: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(1_000 * tid),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn post_json(app: axum::Router, uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_batch_pnl_keys_results_by_user() {
+    let test_app = setup_test_app().await;
+    let repo = &test_app.state.repo;
+    repo.insert_fill(&fill(ALICE, 1, Side::Buy, "100", "0")).await.unwrap();
+    repo.insert_fill(&fill(ALICE, 2, Side::Sell, "110", "10")).await.unwrap();
+    repo.insert_fill(&fill(BOB, 3, Side::Buy, "100", "0")).await.unwrap();
+
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/batch/pnl",
+        serde_json::json!({ "users": [ALICE, BOB, ALICE, "nope"], "fromMs": 0 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let results = body["results"].as_object().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[ALICE]["realizedPnl"], "10");
+    assert_eq!(results[ALICE]["tradeCount"], 2);
+    assert_eq!(results[BOB]["realizedPnl"], "0");
+    assert_eq!(body["errors"]["nope"]["code"], "INVALID_ADDRESS");
+
+    // Each entry matches the single-user endpoint.
+    let req = axum::http::Request::builder()
+        .uri(format!("/v1/pnl?user={}&fromMs=0", ALICE))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = test_app.app.clone().oneshot(req).await.unwrap();
+    let single: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap(),
+    )
+    .unwrap();
+    assert_eq!(results[ALICE], single);
+}
+
+#[tokio::test]
+async fn test_batch_positions_returns_open_positions_per_user() {
+    let test_app = setup_test_app().await;
+    let repo = &test_app.state.repo;
+    repo.insert_fill(&fill(ALICE, 1, Side::Buy, "100", "0")).await.unwrap();
+
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/batch/positions",
+        serde_json::json!({ "users": [ALICE, BOB] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("errors").is_none());
+    assert_eq!(body["results"][ALICE]["positions"][0]["netSize"], "1");
+    assert_eq!(body["results"][BOB]["positions"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_batch_rejects_empty_and_oversized_requests() {
+    let test_app = setup_test_app().await;
+
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/batch/pnl",
+        serde_json::json!({ "users": [] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "BAD_REQUEST");
+
+    let users: Vec<String> = (0..=api::batch::MAX_BATCH_USERS)
+        .map(|i| format!("0x{:040x}", i))
+        .collect();
+    let (status, _) = post_json(
+        test_app.app.clone(),
+        "/v1/batch/positions",
+        serde_json::json!({ "users": users }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -150,7 +150,8 @@ async fn existing_parser_fails_with_real_data() {
 
     println!("\n=== Testing existing parser with real API data ===");
     match result {
-        Ok(fills) => {
+        Ok(parsed) => {
+            let fills = parsed.fills;
             println!("WARNING: Parser succeeded but data is likely wrong!");
             println!("Fills parsed: {}", fills.len());
             if !fills.is_empty() {
@@ -160,7 +161,6 @@ async fn existing_parser_fails_with_real_data() {
         }
         Err(e) => {
             println!("Parser correctly failed: {}", e);
-            assert!(true, "Parser should fail because schema is different");
         }
     }
 }
@@ -170,6 +170,7 @@ async fn existing_parser_fails_with_real_data() {
 async fn demonstrate_correct_parsing() {
     // This is what the parser SHOULD look like:
     #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
     struct ActualBuilderLogRow {
         time: String,  // ISO8601 format, not milliseconds!
         user: String,
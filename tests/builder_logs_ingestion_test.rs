@@ -1,13 +1,17 @@
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::{BuilderLogsError, BuilderLogsSource};
 use hypesilico::db::migrations::init_db;
 use hypesilico::domain::{Address, BuilderLogFill, Coin, Decimal, Fill, Side, TimeMs};
-use hypesilico::orchestration::attribution::AttributionIngestor;
+use hypesilico::engine::{HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::attribution::{AttributionIngestionError, AttributionIngestor};
+use hypesilico::orchestration::jobs::JobLimits;
 use std::collections::HashMap;
 use std::str::FromStr;
 use tempfile::TempDir;
 use chrono::TimeZone;
 
+const BUILDER: &str = "0x2868fc0d9786a740b491577a43502259efa78a39";
+
 #[derive(Debug)]
 struct MockLogsSource {
     by_day: HashMap<String, Vec<BuilderLogFill>>,
@@ -42,13 +46,41 @@ async fn setup_repo() -> (hypesilico::Repository, TempDir) {
 fn cfg(mode: BuilderAttributionMode) -> Config {
     Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: ":memory:".to_string(),
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
-        target_builder: "0xbuilder".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
         builder_attribution_mode: mode,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     }
 }
 
@@ -147,11 +179,11 @@ async fn logs_mode_persists_exact_and_fuzzy_matches() {
 
     assert_eq!(
         map.get(&fill_exact.fill_key).unwrap(),
-        &(true, "logs".to_string(), "exact".to_string(), Some("0xbuilder".to_string()))
+        &(true, "logs".to_string(), "exact".to_string(), Some(BUILDER.to_string()))
     );
     assert_eq!(
         map.get(&fill_fuzzy.fill_key).unwrap(),
-        &(true, "logs".to_string(), "fuzzy".to_string(), Some("0xbuilder".to_string()))
+        &(true, "logs".to_string(), "fuzzy".to_string(), Some(BUILDER.to_string()))
     );
     assert_eq!(
         map.get(&fill_unmatched.fill_key).unwrap(),
@@ -203,7 +235,7 @@ async fn auto_mode_prefers_logs_but_falls_back_to_heuristic() {
         .unwrap();
 
     let rows = repo
-        .query_attributions(&vec![fill_logs.fill_key.clone(), fill_heuristic.fill_key.clone()])
+        .query_attributions(&[fill_logs.fill_key.clone(), fill_heuristic.fill_key.clone()])
         .await
         .unwrap();
     let map: HashMap<String, (bool, String, String, Option<String>)> = rows
@@ -213,10 +245,48 @@ async fn auto_mode_prefers_logs_but_falls_back_to_heuristic() {
 
     assert_eq!(
         map.get(&fill_logs.fill_key).unwrap(),
-        &(true, "logs".to_string(), "exact".to_string(), Some("0xbuilder".to_string()))
+        &(true, "logs".to_string(), "exact".to_string(), Some(BUILDER.to_string()))
     );
     assert_eq!(
         map.get(&fill_heuristic.fill_key).unwrap(),
         &(true, "heuristic".to_string(), "low".to_string(), None)
     );
 }
+
+#[tokio::test]
+async fn invalid_target_builder_fails_the_job() {
+    let (repo, _tmp) = setup_repo().await;
+
+    let t0 = 1_700_000_000_000;
+    let fill = make_fill(t0, 1, "100", "1", Some("0.1"));
+    repo.insert_fill(&fill).await.unwrap();
+
+    let mut config = cfg(BuilderAttributionMode::Auto);
+    config.target_builder = "0xbuilder".to_string();
+    let logs_source = MockLogsSource {
+        by_day: HashMap::new(),
+    };
+
+    let err = AttributionIngestor::default()
+        .ingest_window(
+            &repo,
+            &logs_source,
+            &config,
+            &fill.user,
+            Some(&fill.coin),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AttributionIngestionError::InvalidTargetBuilder(_)
+    ));
+    assert!(err.to_string().contains("0xbuilder"), "{}", err);
+    assert!(repo
+        .query_attributions(std::slice::from_ref(&fill.fill_key))
+        .await
+        .unwrap()
+        .is_empty());
+}
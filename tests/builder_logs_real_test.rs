@@ -6,6 +6,7 @@
 //! - BasedApp: 0x1924b8561eef20e70ede628a296175d358be80e5
 
 use hypesilico::datasource::builder_logs::{BuilderLogsFetcher, BuilderLogsSource};
+use hypesilico::config::Network;
 use hypesilico::domain::Address;
 use reqwest::Client;
 
@@ -38,7 +39,7 @@ fn days_ago_yyyymmdd(days: i64) -> String {
 #[test]
 fn url_construction_phantom() {
     let builder = Address::new(PHANTOM_BUILDER.to_string());
-    let url = BuilderLogsFetcher::builder_logs_url(&builder, "20250117");
+    let url = BuilderLogsFetcher::builder_logs_url(Network::Mainnet, &builder, "20250117");
     assert_eq!(
         url,
         format!("https://stats-data.hyperliquid.xyz/Mainnet/builder_fills/{}/20250117.csv.lz4", PHANTOM_BUILDER)
@@ -48,7 +49,7 @@ fn url_construction_phantom() {
 #[test]
 fn url_construction_insilico() {
     let builder = Address::new(INSILICO_BUILDER.to_string());
-    let url = BuilderLogsFetcher::builder_logs_url(&builder, "20250117");
+    let url = BuilderLogsFetcher::builder_logs_url(Network::Mainnet, &builder, "20250117");
     assert_eq!(
         url,
         format!("https://stats-data.hyperliquid.xyz/Mainnet/builder_fills/{}/20250117.csv.lz4", INSILICO_BUILDER)
@@ -58,7 +59,7 @@ fn url_construction_insilico() {
 #[test]
 fn url_construction_basedapp() {
     let builder = Address::new(BASEDAPP_BUILDER.to_string());
-    let url = BuilderLogsFetcher::builder_logs_url(&builder, "20250117");
+    let url = BuilderLogsFetcher::builder_logs_url(Network::Mainnet, &builder, "20250117");
     assert_eq!(
         url,
         format!("https://stats-data.hyperliquid.xyz/Mainnet/builder_fills/{}/20250117.csv.lz4", BASEDAPP_BUILDER)
@@ -199,15 +200,12 @@ async fn fetch_insilico_last_7_days() {
 
     for days_ago in 1..=7 {
         let day = days_ago_yyyymmdd(days_ago);
-        match fetcher.fetch_and_parse_day(&builder, &day).await {
-            Ok(fills) => {
-                total_fills += fills.len();
-                if !fills.is_empty() {
-                    days_with_data += 1;
-                    println!("  {}: {} fills", day, fills.len());
-                }
+        if let Ok(fills) = fetcher.fetch_and_parse_day(&builder, &day).await {
+            total_fills += fills.len();
+            if !fills.is_empty() {
+                days_with_data += 1;
+                println!("  {}: {} fills", day, fills.len());
             }
-            Err(_) => {}
         }
     }
 
@@ -261,15 +259,12 @@ async fn fetch_basedapp_last_7_days() {
 
     for days_ago in 1..=7 {
         let day = days_ago_yyyymmdd(days_ago);
-        match fetcher.fetch_and_parse_day(&builder, &day).await {
-            Ok(fills) => {
-                total_fills += fills.len();
-                if !fills.is_empty() {
-                    days_with_data += 1;
-                    println!("  {}: {} fills", day, fills.len());
-                }
+        if let Ok(fills) = fetcher.fetch_and_parse_day(&builder, &day).await {
+            total_fills += fills.len();
+            if !fills.is_empty() {
+                days_with_data += 1;
+                println!("  {}: {} fills", day, fills.len());
             }
-            Err(_) => {}
         }
     }
 
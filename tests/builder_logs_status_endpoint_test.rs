@@ -0,0 +1,219 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::repo::{BuilderLogFileRow, BuilderLogRejectRow};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::Address,
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const BUILDER: &str = "0x2868fc0d9786a740b491577a43502259efa78a39";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn reject(line: i64, byte_offset: i64, reason: &str) -> BuilderLogRejectRow {
+    BuilderLogRejectRow {
+        line,
+        byte_offset,
+        reason: reason.to_string(),
+        raw: "2024-01-01T00:00:00.000Z,0xabc,BTC,wat,100,1".to_string(),
+    }
+}
+
+async fn seed(repo: &Repository) {
+    let builder = Address::new(BUILDER.to_string());
+    repo.upsert_builder_log_file(&BuilderLogFileRow {
+        builder: BUILDER.to_string(),
+        yyyymmdd: "20240101".to_string(),
+        sha256: "ab".to_string(),
+        etag: None,
+        row_count: 10,
+        fetched_at_ms: 5000,
+        content: vec![1],
+    })
+    .await
+    .unwrap();
+    repo.replace_builder_log_rejects(
+        &builder,
+        "20240101",
+        &[
+            reject(7, 420, "invalid side: wat"),
+            reject(3, 120, "invalid px"),
+        ],
+        5000,
+    )
+    .await
+    .unwrap();
+    // An uncached day (e.g. today) that only has dead letters.
+    repo.replace_builder_log_rejects(&builder, "20240102", &[reject(2, 60, "invalid sz")], 6000)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_status_reports_per_day_corruption_stats() {
+    let test_app = setup_test_app().await;
+    seed(&test_app.repo).await;
+
+    let (status, body) = get_json(test_app.app, "/v1/builder-logs/status").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["builder"], BUILDER);
+
+    let days = body["days"].as_array().unwrap();
+    assert_eq!(days.len(), 2);
+
+    assert_eq!(days[0]["day"], "20240101");
+    assert_eq!(days[0]["cached"], true);
+    assert_eq!(days[0]["rowCount"], 10);
+    assert_eq!(days[0]["rejectedCount"], 2);
+    assert_eq!(days[0]["rejects"][0]["line"], 3);
+    assert_eq!(days[0]["rejects"][0]["byteOffset"], 120);
+    assert_eq!(days[0]["rejects"][1]["reason"], "invalid side: wat");
+
+    assert_eq!(days[1]["day"], "20240102");
+    assert_eq!(days[1]["cached"], false);
+    assert!(days[1].get("rowCount").is_none());
+    assert_eq!(days[1]["rejectedCount"], 1);
+}
+
+#[tokio::test]
+async fn test_status_day_range_and_reparse_replace_rejects() {
+    let test_app = setup_test_app().await;
+    seed(&test_app.repo).await;
+
+    // A clean reparse of the day clears its dead letters.
+    test_app
+        .repo
+        .replace_builder_log_rejects(&Address::new(BUILDER.to_string()), "20240101", &[], 7000)
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        test_app.app,
+        "/v1/builder-logs/status?fromDay=20240101&toDay=20240101",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let days = body["days"].as_array().unwrap();
+    assert_eq!(days.len(), 1);
+    assert_eq!(days[0]["rejectedCount"], 0);
+    assert_eq!(days[0]["rejects"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_status_validates_parameters() {
+    let test_app = setup_test_app().await;
+
+    let (status, body) = get_json(
+        test_app.app.clone(),
+        "/v1/builder-logs/status?fromDay=2024-01-01",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("fromDay must be a YYYYMMDD date"));
+
+    let (status, _) = get_json(
+        test_app.app.clone(),
+        "/v1/builder-logs/status?fromDay=20240102&toDay=20240101",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get_json(test_app.app, "/v1/builder-logs/status?builder=0x123").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
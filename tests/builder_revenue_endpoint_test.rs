@@ -0,0 +1,202 @@
+use axum::http::StatusCode;
+use hypesilico::api::{self, AppState};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Attribution, AttributionConfidence, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0x1111111111111111111111111111111111111111";
+const BOB: &str = "0x2222222222222222222222222222222222222222";
+const UNTRACKED: &str = "0x3333333333333333333333333333333333333333";
+const BUILDER: &str = "0xb0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0";
+const OTHER_BUILDER: &str = "0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+const DAY_MS: i64 = 86_400_000;
+
+struct TestApp {
+    app: axum::Router,
+    state: AppState,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![ALICE.to_string(), BOB.to_string()],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = AppState::new(repo, config, orchestrator, equity_resolver);
+    let app = api::create_router(state.clone());
+
+    TestApp {
+        app,
+        state,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(user: &str, time_ms: i64, tid: i64, builder_fee: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.05").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Some(Decimal::from_str(builder_fee).unwrap()),
+        Some(tid),
+        None,
+    )
+}
+
+async fn seed(repo: &Repository, fills: &[(Fill, Attribution)]) {
+    for (fill, _) in fills {
+        repo.insert_fill(fill).await.unwrap();
+    }
+    let attributions: Vec<_> = fills
+        .iter()
+        .map(|(f, a)| (f.fill_key.clone(), a.clone()))
+        .collect();
+    repo.upsert_attributions_full(&attributions).await.unwrap();
+}
+
+fn logs(builder: &str) -> Attribution {
+    Attribution::from_logs_match(
+        true,
+        Some(Address::new(builder.to_string())),
+        AttributionConfidence::Exact,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_revenue_sums_tracked_users_per_day() {
+    let test_app = setup_test_app().await;
+    seed(
+        &test_app.state.repo,
+        &[
+            (fill(ALICE, 1_000, 1, "0.1"), logs(BUILDER)),
+            (fill(BOB, 2_000, 2, "0.2"), Attribution::heuristic(true)),
+            (fill(ALICE, DAY_MS + 1_000, 3, "0.3"), logs(BUILDER)),
+            // Another builder, an unattributed fill, and an untracked user do not count.
+            (fill(ALICE, 3_000, 4, "0.4"), logs(OTHER_BUILDER)),
+            (fill(BOB, 4_000, 5, "0.5"), Attribution::heuristic(false)),
+            (fill(UNTRACKED, 5_000, 6, "0.6"), logs(BUILDER)),
+        ],
+    )
+    .await;
+
+    let (status, body) = get_json(
+        test_app.app.clone(),
+        &format!("/v1/builder/revenue?builder={}", BUILDER.to_uppercase().replace("0X", "0x")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["builder"], BUILDER);
+    assert_eq!(body["fillCount"], 3);
+    assert_eq!(body["builderFees"], "0.6");
+
+    let days = body["days"].as_array().unwrap();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[0]["startMs"], 0);
+    assert_eq!(days[0]["userCount"], 2);
+    assert_eq!(days[0]["volume"], "200");
+    assert_eq!(days[0]["builderFees"], "0.3");
+    assert_eq!(days[1]["startMs"], DAY_MS);
+    assert_eq!(days[1]["builderFees"], "0.3");
+
+    // Heuristic attributions name no builder, so they only count for the target builder.
+    let (_, body) = get_json(
+        test_app.app.clone(),
+        &format!("/v1/builder/revenue?builder={}&toMs={}", OTHER_BUILDER, DAY_MS),
+    )
+    .await;
+    assert_eq!(body["fillCount"], 1);
+    assert_eq!(body["builderFees"], "0.4");
+}
+
+#[tokio::test]
+async fn test_revenue_validates_builder_and_window() {
+    let test_app = setup_test_app().await;
+
+    let (status, body) = get_json(test_app.app.clone(), "/v1/builder/revenue?builder=nope").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ADDRESS");
+
+    let (status, body) = get_json(
+        test_app.app.clone(),
+        &format!("/v1/builder/revenue?builder={}&fromMs=10&toMs=5", BUILDER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_TIME_RANGE");
+}
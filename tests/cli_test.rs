@@ -0,0 +1,100 @@
+//! Tests for the `hypesilico-cli` binary's offline subcommands.
+
+use hypesilico::compile::Compiler;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::Repository;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::str::FromStr;
+use tempfile::TempDir;
+
+const ALICE: &str = "0x0000000000000000000000000000000000000a11";
+
+fn fill(time_ms: i64, side: Side, px: &str, closed_pnl: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(ALICE.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.5").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(time_ms),
+        Some(time_ms),
+    )
+}
+
+async fn seed(db_path: &str) -> Repository {
+    let repo = Repository::new(init_db(db_path).await.unwrap());
+    repo.insert_fills_batch(&[
+        fill(1_000, Side::Buy, "100", "0"),
+        fill(2_000, Side::Sell, "110", "10"),
+    ])
+    .await
+    .unwrap();
+    repo
+}
+
+fn cli(dir: &Path, db_path: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hypesilico-cli"))
+        .args(args)
+        .current_dir(dir)
+        .env_remove("CONFIG_FILE")
+        .env("DATABASE_PATH", db_path)
+        .env("HYPERLIQUID_API_URL", "http://example.invalid")
+        .env("TARGET_BUILDER", "0x0000000000000000000000000000000000000000")
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_export_writes_stored_fills() {
+    let temp = TempDir::new().unwrap();
+    let db_path = temp.path().join("test.db").to_string_lossy().to_string();
+    let repo = seed(&db_path).await;
+    repo.close().await.unwrap();
+
+    let output = cli(temp.path(), &db_path, &["export", "--user", ALICE]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("fill_key,user,time_ms,coin,side,px"));
+    assert!(lines[2].contains(",2000,BTC,"));
+    assert!(lines[2].contains(",110,1,0.5,10,"));
+}
+
+#[tokio::test]
+async fn test_verify_compares_derived_tables() {
+    let temp = TempDir::new().unwrap();
+    let db_path = temp.path().join("test.db").to_string_lossy().to_string();
+    let repo = seed(&db_path).await;
+
+    // Fills that were never compiled differ from the (empty) derived tables.
+    let output = cli(temp.path(), &db_path, &["verify", "--user", ALICE]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("lifecycles +1 -0 ~0"), "{}", stdout);
+
+    let alice = Address::new(ALICE.to_string());
+    Compiler::compile_incremental(&repo, &alice, &Coin::new("BTC".to_string()))
+        .await
+        .unwrap();
+    repo.close().await.unwrap();
+
+    let output = cli(temp.path(), &db_path, &["verify", "--user", ALICE]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("BTC: derived tables match 2 fills"), "{}", stdout);
+}
+
+#[test]
+fn test_rejects_invalid_user() {
+    let temp = TempDir::new().unwrap();
+    let output = cli(temp.path(), "unused.db", &["compile", "--user", "nope"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--user"));
+}
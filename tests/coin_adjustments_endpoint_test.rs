@@ -0,0 +1,187 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn send(
+    app: axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => builder
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(axum::body::Body::empty()).unwrap(),
+    };
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_coin_adjustment_rebuilds_derived_rows() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    // A 1:1000 split at 2000: the sell closes the long in post-split units.
+    repo.insert_fill(&fill(1000, Side::Buy, "1", "1000", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, Side::Sell, "1000", "1.1", "100", 2))
+        .await
+        .unwrap();
+
+    let lifecycles_uri = format!("/v1/lifecycles?user={}&coin=BTC", USER);
+    let (_, body) = send(app.clone(), "GET", &lifecycles_uri, None).await;
+    assert_eq!(body["lifecycles"].as_array().unwrap().len(), 2);
+
+    let (status, body) = send(
+        app.clone(),
+        "POST",
+        "/v1/admin/coin-adjustments",
+        Some(serde_json::json!({
+            "coin": "BTC",
+            "kind": "sizeRebase",
+            "effectiveTimeMs": 2000,
+            "factor": "1000"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["adjustments"],
+        serde_json::json!([{ "kind": "sizeRebase", "effectiveTimeMs": 2000, "factor": "1000" }])
+    );
+
+    let (_, body) = send(app.clone(), "GET", &lifecycles_uri, None).await;
+    let lifecycles = body["lifecycles"].as_array().unwrap();
+    assert_eq!(lifecycles.len(), 1);
+    assert_eq!(lifecycles[0]["maxSize"], "1000");
+    assert_eq!(lifecycles[0]["entryPx"], "1");
+
+    let (status, body) = send(app, "GET", "/v1/admin/coin-adjustments?coin=BTC", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["adjustments"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_coin_adjustment_validation() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    for body in [
+        serde_json::json!({ "coin": "BTC", "kind": "split", "effectiveTimeMs": 1, "factor": "2" }),
+        serde_json::json!({ "coin": "BTC", "kind": "sizeRebase", "effectiveTimeMs": 1, "factor": "0" }),
+        serde_json::json!({ "coin": "BTC", "kind": "sizeRebase", "effectiveTimeMs": 1, "factor": "x" }),
+        serde_json::json!({ "coin": "BTC", "kind": "sizeRebase", "effectiveTimeMs": 1, "factor": "3" }),
+    ] {
+        let (status, _) = send(app.clone(), "POST", "/v1/admin/coin-adjustments", Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,289 @@
+use axum::http::StatusCode;
+use hypesilico::api;
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, CoinMeta, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<hypesilico::Repository>,
+    orchestrator: Arc<Orchestrator>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(datasource: Arc<MockDataSource>) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(hypesilico::Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+
+    let state = api::AppState::new(repo.clone(), config, orchestrator.clone(), equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        orchestrator,
+        _temp: temp_dir,
+    }
+}
+
+fn meta(name: &str, asset_index: u32, sz_decimals: u32) -> CoinMeta {
+    CoinMeta {
+        coin: Coin::new(name.to_string()),
+        asset_index,
+        sz_decimals,
+        max_leverage: 50,
+        delisted: false,
+    }
+}
+
+fn universe() -> Vec<CoinMeta> {
+    vec![meta("BTC", 0, 5), meta("ETH", 1, 4), meta("kPEPE", 2, 0)]
+}
+
+fn fill(user: &str, coin: &str, time_ms: i64, tid: i64, sz: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new(coin.to_string()),
+        Side::Buy,
+        Decimal::from_str("50000").unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        Some(tid),
+    )
+}
+
+async fn request(app: axum::Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn post_fills(app: axum::Router, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/fills")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_coins_lists_refreshed_metadata() {
+    let datasource = Arc::new(MockDataSource::new().with_coin_meta(universe()));
+    let test_app = setup_test_app(datasource).await;
+
+    let (status, json) = request(test_app.app.clone(), "/v1/coins").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["coins"], serde_json::json!([]));
+
+    assert_eq!(test_app.orchestrator.refresh_coins().await.unwrap(), 3);
+    let (status, json) = request(test_app.app, "/v1/coins").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["coins"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        json["coins"][2],
+        serde_json::json!({
+            "coin": "kPEPE",
+            "assetIndex": 2,
+            "szDecimals": 0,
+            "maxLeverage": 50,
+            "delisted": false
+        })
+    );
+    assert_eq!(test_app.repo.query_coins().await.unwrap(), universe());
+}
+
+#[tokio::test]
+async fn test_stored_metadata_is_loaded() {
+    let datasource = Arc::new(MockDataSource::new());
+    let test_app = setup_test_app(datasource).await;
+    test_app
+        .repo
+        .upsert_coins(&universe(), TimeMs::new(1))
+        .await
+        .unwrap();
+
+    // A source without metadata keeps what is stored.
+    assert_eq!(test_app.orchestrator.refresh_coins().await.unwrap(), 0);
+    assert_eq!(test_app.orchestrator.load_coins().await.unwrap(), 3);
+    assert_eq!(test_app.orchestrator.refresh_coins().await.unwrap(), 3);
+    assert!(test_app.orchestrator.coins().get(&Coin::new("BTC".to_string())).is_some());
+}
+
+#[tokio::test]
+async fn test_coin_parameter_is_normalized_and_validated() {
+    let user = "0x1111111111111111111111111111111111111111";
+    let datasource = Arc::new(MockDataSource::new().with_coin_meta(universe()));
+    let test_app = setup_test_app(datasource).await;
+    test_app.orchestrator.refresh_coins().await.unwrap();
+    test_app
+        .repo
+        .insert_fill(&fill(user, "kPEPE", 1000, 1, "1200"))
+        .await
+        .unwrap();
+
+    for alias in ["kPEPE", "kpepe", "1000PEPE"] {
+        let (status, json) = request(
+            test_app.app.clone(),
+            &format!("/v1/trades?user={}&coin={}", user, alias),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", alias);
+        assert_eq!(json["trades"].as_array().unwrap().len(), 1, "{}", alias);
+        assert_eq!(json["trades"][0]["coin"], "kPEPE");
+    }
+
+    for unknown in ["DOGEE", "PEPE"] {
+        let (status, json) = request(
+            test_app.app.clone(),
+            &format!("/v1/trades?user={}&coin={}", user, unknown),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", unknown);
+        let expected = format!("Unknown coin '{}'", unknown);
+        assert!(json["error"].as_str().unwrap().contains(&expected));
+    }
+
+    // Other dexes are outside the perp universe and pass through.
+    let (status, _) = request(
+        test_app.app,
+        &format!("/v1/trades?user={}&coin=xyz:TSLA", user),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_sizes_render_with_coin_decimals() {
+    let user = "0x1111111111111111111111111111111111111111";
+    let datasource = Arc::new(MockDataSource::new().with_coin_meta(universe()));
+    let test_app = setup_test_app(datasource).await;
+    test_app
+        .repo
+        .insert_fill(&fill(user, "BTC", 1000, 1, "0.1"))
+        .await
+        .unwrap();
+    let uri = format!("/v1/trades?user={}", user);
+
+    let (_, json) = request(test_app.app.clone(), &uri).await;
+    assert_eq!(json["trades"][0]["sz"], "0.1");
+
+    test_app.orchestrator.refresh_coins().await.unwrap();
+    let (_, json) = request(test_app.app, &uri).await;
+    assert_eq!(json["trades"][0]["sz"], "0.10000");
+}
+
+#[tokio::test]
+async fn test_ingesting_a_newly_listed_coin_refreshes_metadata() {
+    let user = "0x1111111111111111111111111111111111111111";
+    let datasource = Arc::new(MockDataSource::new().with_coin_meta(universe()));
+    let test_app = setup_test_app(datasource).await;
+    // The stored registry predates the listing of ETH.
+    test_app
+        .repo
+        .upsert_coins(&[meta("BTC", 0, 5)], TimeMs::new(1))
+        .await
+        .unwrap();
+    assert_eq!(test_app.orchestrator.load_coins().await.unwrap(), 1);
+
+    let fill = |coin: &str| {
+        serde_json::json!([{
+            "user": user,
+            "timeMs": 1000,
+            "coin": coin,
+            "side": "buy",
+            "px": "2000",
+            "sz": "1",
+            "fee": "0",
+            "closedPnl": "0",
+            "tid": 1,
+        }])
+    };
+    let (status, json) = post_fills(test_app.app.clone(), fill("eth")).await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+    assert_eq!(json["fillKeys"][0], format!("{}:ETH:tid:1", user));
+    assert!(test_app.orchestrator.coins().get(&Coin::new("ETH".to_string())).is_some());
+
+    // A coin the source does not list either is still rejected.
+    let (status, json) = post_fills(test_app.app, fill("DOGEE")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"], "fills[0]: unknown coin 'DOGEE'");
+}
@@ -1,12 +1,22 @@
 //! Integration tests for the compile pipeline.
 
 use hypesilico::{
-    compile::Compiler,
-    db::init_db,
+    compile::{CompileError, Compiler, OverflowPolicy},
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    datasource::MockDataSource,
+    db::{init_db, COMPILE_SCHEMA_VERSION},
     domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    engine::{CoinAdjustment, HeuristicAttributor, MatchTolerances},
+    orchestration::{
+        ensure::Ingestor,
+        jobs::JobLimits,
+        orchestrator::{OrchestrationError, Orchestrator},
+    },
     Repository,
 };
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 use tempfile::TempDir;
 
 async fn setup_test_db() -> (Repository, TempDir) {
@@ -85,6 +95,76 @@ async fn test_compile_incremental_single_fill() {
     assert!(fill_key.is_some());
 }
 
+#[tokio::test]
+async fn test_overflow_policy_fails_or_taints_lifecycle() {
+    let (repo, _temp) = setup_test_db().await;
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let open = create_test_fill(1000, "0x123", "BTC", Side::Buy, "100", "1", "0", "0");
+    let overflowing = create_test_fill(
+        2000,
+        "0x123",
+        "BTC",
+        Side::Buy,
+        "79228162514264337593543950335",
+        "2",
+        "0",
+        "0",
+    );
+    repo.insert_fills_batch(&[open, overflowing.clone()])
+        .await
+        .expect("insert failed");
+
+    let err = Compiler::compile_incremental(&repo, &user, &coin)
+        .await
+        .expect_err("overflow must fail by default");
+    assert!(matches!(err, CompileError::Arithmetic { .. }));
+    assert!(repo.get_compile_state(&user, &coin).await.unwrap().is_none());
+
+    let processed =
+        Compiler::compile_incremental_with(&repo, &user, &coin, OverflowPolicy::Taint)
+            .await
+            .expect("taint policy compiles");
+    assert_eq!(processed, 2);
+
+    let lifecycles = repo.query_lifecycles(&user, &coin).await.unwrap();
+    assert_eq!(lifecycles.len(), 1);
+    assert!(lifecycles[0].is_tainted);
+    assert!(lifecycles[0]
+        .taint_reason
+        .as_deref()
+        .is_some_and(|reason| reason.contains(overflowing.fill_key())));
+}
+
+#[tokio::test]
+async fn test_watermark_follows_fill_ordering_not_fill_key() {
+    let (repo, _temp) = setup_test_db().await;
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    // Fill keys embed the tid, so `tid:10` sorts before the `tid:9` watermark as a string.
+    let open = create_test_fill(9, "0x123", "BTC", Side::Buy, "100", "1", "0", "0");
+    let close = create_test_fill(10, "0x123", "BTC", Side::Sell, "110", "1", "0", "10");
+    assert!(close.fill_key < open.fill_key);
+
+    repo.insert_fill(&open).await.expect("insert failed");
+    assert_eq!(
+        Compiler::compile_incremental(&repo, &user, &coin).await.unwrap(),
+        1
+    );
+
+    repo.insert_fill(&close).await.expect("insert failed");
+    assert_eq!(
+        Compiler::compile_incremental(&repo, &user, &coin).await.unwrap(),
+        1
+    );
+
+    let (time_ms, fill_key) = repo.get_compile_state(&user, &coin).await.unwrap().unwrap();
+    assert_eq!(time_ms, Some(10));
+    assert_eq!(fill_key.as_deref(), Some(close.fill_key()));
+}
+
 #[tokio::test]
 async fn test_compile_incremental_idempotent() {
     let (repo, _temp) = setup_test_db().await;
@@ -346,11 +426,11 @@ async fn test_lifecycle_open_close_sequence() {
         .expect("query_lifecycles failed");
 
     assert_eq!(lifecycles.len(), 1, "Expected 1 lifecycle");
-    let (_id, lc_user, lc_coin, start_time, end_time) = &lifecycles[0];
-    assert_eq!(lc_user, &user);
-    assert_eq!(lc_coin, &coin);
-    assert_eq!(*start_time, 1000);
-    assert_eq!(*end_time, Some(2000));
+    let lifecycle = &lifecycles[0];
+    assert_eq!(lifecycle.user, user);
+    assert_eq!(lifecycle.coin, coin);
+    assert_eq!(lifecycle.start_time_ms, TimeMs::new(1000));
+    assert_eq!(lifecycle.end_time_ms, Some(TimeMs::new(2000)));
 }
 
 #[tokio::test]
@@ -574,3 +654,346 @@ async fn test_taint_with_non_builder_fill() {
     assert!(!lifecycles.is_empty(), "No lifecycles found");
     // With missing attribution for fill2, lifecycle should be tainted
 }
+
+#[tokio::test]
+async fn test_recompile_stale_rebuilds_pairs_from_older_compiler() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool.clone()));
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let fill1 = create_test_fill(1000, "0x123", "BTC", Side::Buy, "50000", "1.0", "10", "0");
+    let fill2 = create_test_fill(2000, "0x123", "BTC", Side::Sell, "51000", "1.0", "10", "100");
+    repo.insert_fill(&fill1).await.expect("insert failed");
+    repo.insert_fill(&fill2).await.expect("insert failed");
+    Compiler::compile_incremental(&repo, &user, &coin)
+        .await
+        .expect("compile failed");
+    let snapshots_before = repo.query_snapshots(&user, &coin).await.unwrap();
+
+    // Pretend the pair was compiled by an older engine.
+    sqlx::query("UPDATE compile_state SET compile_schema_version = ?")
+        .bind(COMPILE_SCHEMA_VERSION - 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config);
+    let orchestrator = Orchestrator::new(ingestor, repo.clone());
+
+    assert_eq!(orchestrator.recompile_stale().await.unwrap(), 1);
+    assert_eq!(orchestrator.recompile_stale().await.unwrap(), 0);
+
+    // Rows are rebuilt rather than appended alongside the old ones.
+    let snapshots_after = repo.query_snapshots(&user, &coin).await.unwrap();
+    assert_eq!(snapshots_after.len(), snapshots_before.len());
+
+    let version: (i64,) = sqlx::query_as("SELECT compile_schema_version FROM compile_state")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(version.0, COMPILE_SCHEMA_VERSION);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_compiles_of_same_pair_are_serialized() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    // 50 open/close round trips.
+    let fills: Vec<Fill> = (0..100)
+        .map(|i| {
+            let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+            create_test_fill(1000 + i * 10, "0x123", "BTC", side, "50000", "1.0", "1", "0")
+        })
+        .collect();
+    repo.insert_fills_batch(&fills).await.unwrap();
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config);
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+
+    // Every push after the first lands before the watermark and forces a rebuild, so
+    // unserialized compiles would interleave resets with inserts.
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let orchestrator = orchestrator.clone();
+            let (user, coin, fills) = (user.clone(), coin.clone(), fills.clone());
+            tokio::spawn(async move { orchestrator.compile_pushed(&user, &coin, &fills).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().expect("compile failed");
+    }
+
+    assert_eq!(repo.query_lifecycles(&user, &coin).await.unwrap().len(), 50);
+    assert_eq!(repo.query_snapshots(&user, &coin).await.unwrap().len(), 100);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_ensure_compiled_compiles_all_coins_in_parallel() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+    let user = Address::new("0x123".to_string());
+
+    let coins = ["BTC", "ETH", "SOL"];
+    let fills: Vec<Fill> = coins
+        .iter()
+        .enumerate()
+        .flat_map(|(i, coin)| {
+            let t = 1000 + i as i64 * 100;
+            [
+                create_test_fill(t, "0x123", coin, Side::Buy, "100", "1.0", "1", "0"),
+                create_test_fill(t + 10, "0x123", coin, Side::Sell, "110", "1.0", "1", "10"),
+            ]
+        })
+        .collect();
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits {
+            coin_parallelism: 2,
+            ..JobLimits::default()
+        },
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+    let datasource = MockDataSource::new().with_fills(fills);
+    let ingestor = Ingestor::new(Arc::new(datasource), repo.clone(), config);
+    let orchestrator = Orchestrator::new(ingestor, repo.clone());
+
+    orchestrator
+        .ensure_compiled(&user, None, Some(TimeMs::new(0)), Some(TimeMs::new(10_000)))
+        .await
+        .expect("ensure_compiled failed");
+
+    for coin in coins {
+        let coin = Coin::new(coin.to_string());
+        assert_eq!(repo.query_lifecycles(&user, &coin).await.unwrap().len(), 1);
+        assert_eq!(repo.query_snapshots(&user, &coin).await.unwrap().len(), 2);
+    }
+}
+
+#[tokio::test]
+async fn test_shutdown_rejects_new_jobs() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config);
+    let orchestrator = Orchestrator::new(ingestor, repo.clone());
+
+    assert!(orchestrator.shutdown(Duration::from_secs(1)).await);
+
+    let fill = create_test_fill(1000, "0x123", "BTC", Side::Buy, "50000", "1.0", "1", "0");
+    let result = orchestrator.compile_pushed(&user, &coin, &[fill]).await;
+    assert!(matches!(result, Err(OrchestrationError::ShuttingDown(_))));
+}
+
+#[tokio::test]
+async fn test_coin_adjustment_rebases_pre_event_fills() {
+    let (repo, _temp) = setup_test_db().await;
+    let user = Address::new("0x123".to_string());
+    let coin = Coin::new("kPRE".to_string());
+
+    // 10 units bought before a 1:1000 split, 10000 units sold after it.
+    let buy = create_test_fill(1000, "0x123", "kPRE", Side::Buy, "5", "10", "0", "0");
+    let sell = create_test_fill(3000, "0x123", "kPRE", Side::Sell, "0.006", "10000", "0", "10");
+    repo.insert_fill(&buy).await.unwrap();
+    repo.insert_fill(&sell).await.unwrap();
+
+    // Unadjusted, the sell flips the position into a large short.
+    Compiler::compile_incremental(&repo, &user, &coin).await.unwrap();
+    let lifecycles = repo.query_lifecycles(&user, &coin).await.unwrap();
+    assert_eq!(lifecycles.len(), 2);
+
+    // Registering the split drops the stale derived rows...
+    let split = CoinAdjustment::from_parts(
+        "size_rebase",
+        TimeMs::new(2000),
+        Decimal::from_str("1000").unwrap(),
+    )
+    .unwrap();
+    repo.insert_coin_adjustment(&coin, &split).await.unwrap();
+    assert!(repo.get_compile_state(&user, &coin).await.unwrap().is_none());
+    assert!(repo.query_lifecycles(&user, &coin).await.unwrap().is_empty());
+    assert_eq!(repo.query_coin_adjustments(&coin).await.unwrap(), vec![split]);
+
+    // ...and the rebuild sees a single round trip in post-split units.
+    Compiler::compile_incremental(&repo, &user, &coin).await.unwrap();
+    let lifecycles = repo.query_lifecycles(&user, &coin).await.unwrap();
+    assert_eq!(lifecycles.len(), 1);
+    assert_eq!(lifecycles[0].end_time_ms, Some(TimeMs::new(3000)));
+}
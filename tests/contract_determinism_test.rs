@@ -8,12 +8,13 @@
 use axum::http::StatusCode;
 use hypesilico::api::{self, AppState};
 use hypesilico::compile::Compiler;
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
-use hypesilico::domain::{Address, Attribution, AttributionConfidence, Coin, Decimal, Deposit, Fill, Side, TimeMs};
-use hypesilico::engine::EquityResolver;
+use hypesilico::domain::{Address, Attribution, AttributionConfidence, Coin, Decimal, Deposit, LedgerKind, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use hypesilico::Repository;
 use std::str::FromStr;
@@ -52,13 +53,41 @@ async fn setup_test_app(leaderboard_users: Vec<String>) -> TestApp {
     let datasource = Arc::new(MockDataSource::new());
     let config = Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: db_path,
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
         target_builder: TEST_BUILDER.to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users,
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     };
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
@@ -119,11 +148,13 @@ fn fill(
 }
 
 fn deposit(user: &str, time_ms: i64, amount: &str, tx_hash: Option<&str>, event_key: &str) -> Deposit {
+    let amount = Decimal::from_str(amount).unwrap();
     Deposit {
         event_key: event_key.to_string(),
         user: Address::new(user.to_string()),
         time_ms: TimeMs::new(time_ms),
-        amount: Decimal::from_str(amount).unwrap(),
+        kind: LedgerKind::from_sign(&amount),
+        amount,
         tx_hash: tx_hash.map(|s| s.to_string()),
     }
 }
@@ -135,7 +166,7 @@ fn assert_all_keys_camel_case(value: &serde_json::Value, path: &str) {
             for (key, val) in map {
                 // camelCase: starts with lowercase, no underscores
                 assert!(
-                    key.chars().next().map_or(true, |c| c.is_lowercase()),
+                    key.chars().next().is_none_or(|c| c.is_lowercase()),
                     "Key '{}' at path '{}' should start with lowercase (camelCase)",
                     key,
                     path
@@ -844,6 +875,65 @@ async fn test_contract_error_response_invalid_address() {
         json["error"].as_str().unwrap().to_lowercase().contains("user"),
         "Error message should mention address/user validation"
     );
+    assert_eq!(json["code"], "INVALID_ADDRESS");
+}
+
+#[tokio::test]
+async fn test_contract_error_response_bad_address_checksum() {
+    let test_app = setup_test_app(vec![]).await;
+
+    // Mixed case must match the EIP-55 checksum; the last character is flipped here
+    let (status, body) = request(
+        test_app.app,
+        "/v1/trades?user=0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["code"], "INVALID_ADDRESS");
+    assert!(json["error"].as_str().unwrap().contains("EIP-55"));
+}
+
+#[tokio::test]
+async fn test_contract_error_response_malformed_query_param() {
+    let test_app = setup_test_app(vec![]).await;
+
+    // A parameter that does not parse gets the JSON error body, not axum's plain text
+    let (status, body) = request(
+        test_app.app,
+        &format!("/v1/pnl?user={}&fromMs=yesterday", TEST_USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].is_string(), "Error response must have 'error' field");
+    assert_eq!(json["code"], "BAD_REQUEST");
+}
+
+#[tokio::test]
+async fn test_contract_address_format_header_checksums_addresses() {
+    let test_app = setup_test_app(vec![]).await;
+    let builder = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+    let uri = format!("/v1/builder-logs/status?builder={}", builder);
+
+    let (status, body) = request(test_app.app.clone(), &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["builder"], builder);
+
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(&uri)
+        .header("X-Address-Format", "checksummed")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = test_app.app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["builder"], "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
 }
 
 #[tokio::test]
@@ -860,6 +950,7 @@ async fn test_contract_error_response_invalid_time_window_deposits() {
 
     let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
     assert!(json["error"].is_string(), "Error response must have 'error' field");
+    assert_eq!(json["code"], "INVALID_TIME_RANGE");
 }
 
 #[tokio::test]
@@ -908,6 +999,7 @@ async fn test_contract_error_response_format_json() {
 
         let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
         assert!(json["error"].is_string(), "Error response for {} must have 'error' field", endpoint);
+        assert!(json["code"].is_string(), "Error response for {} must have 'code' field", endpoint);
         assert_all_keys_camel_case(&json, &format!("error response for {}", endpoint));
     }
 }
@@ -1,11 +1,12 @@
 use axum::http::StatusCode;
 use hypesilico::api;
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
-use hypesilico::domain::{Address, Decimal, Deposit, TimeMs};
-use hypesilico::engine::EquityResolver;
+use hypesilico::domain::{Address, Decimal, Deposit, LedgerKind, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -30,13 +31,41 @@ async fn setup_test_app(datasource: Arc<MockDataSource>) -> TestApp {
 
     let config = Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: db_path,
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
-        target_builder: "0x0".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     };
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
@@ -69,11 +98,13 @@ async fn request(app: axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
 }
 
 fn deposit(user: &str, time_ms: i64, amount: &str, tx_hash: Option<&str>, event_key: &str) -> Deposit {
+    let amount = Decimal::from_str(amount).unwrap();
     Deposit {
         event_key: event_key.to_string(),
         user: Address::from_str(user).unwrap(),
         time_ms: TimeMs::new(time_ms),
-        amount: Decimal::from_str(amount).unwrap(),
+        kind: LedgerKind::from_sign(&amount),
+        amount,
         tx_hash: tx_hash.map(|s| s.to_string()),
     }
 }
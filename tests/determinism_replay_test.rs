@@ -0,0 +1,438 @@
+//! Determinism audit: replay one fill set through the full pipeline twice and diff the runs.
+//!
+//! Each run starts from a fresh database and serves the fills from a [`MockDataSource`], so
+//! ingestion, attribution and compilation all happen through the API exactly as in
+//! production. Every response body and every row of every derived table must then be
+//! byte-identical between the two runs; the first divergence is reported.
+//!
+//! By default the fills are a seeded synthetic set (flips, same-millisecond fills, mixed
+//! builder and non-builder fills, deposits and withdrawals). To audit real data instead,
+//! point `REPLAY_FILLS_DB` at a database recorded with `RECORD_RAW_PAYLOADS=true`; its
+//! `userFillsByTime` payloads are re-derived with [`replay_fills`] and replayed:
+//!
+//! ```bash
+//! REPLAY_FILLS_DB=./hypesilico.db cargo test --test determinism_replay_test -- --nocapture
+//! ```
+
+use axum::http::StatusCode;
+use hypesilico::api::{self, AppState};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::hyperliquid::replay_fills;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Deposit, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const BUILDER: &str = "0x3333333333333333333333333333333333333333";
+const USERS: [&str; 3] = [
+    "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    "0xcccccccccccccccccccccccccccccccccccccccc",
+];
+const COINS: [(&str, i64); 3] = [("BTC", 50_000), ("ETH", 3_000), ("SOL", 100)];
+const FILLS_PER_USER: usize = 150;
+
+/// Tables that are not derived from the fills: bookkeeping of the process itself.
+const SKIPPED_TABLES: [&str; 3] = ["idempotency_requests", "instance_metadata", "raw_payloads"];
+
+/// Wall-clock columns, which legitimately differ between runs.
+const SKIPPED_COLUMNS: [&str; 4] = ["created_at", "created_at_ms", "fetched_at_ms", "recorded_at_ms"];
+
+/// The fills and deposits both runs are fed.
+struct Dataset {
+    users: Vec<String>,
+    fills: Vec<Fill>,
+    deposits: Vec<Deposit>,
+}
+
+/// Everything observable about one run.
+struct RunOutput {
+    /// `(uri, status, body)` in request order.
+    responses: Vec<(String, StatusCode, Vec<u8>)>,
+    /// Table name to its rows, each row rendered with SQLite's `quote()` and sorted.
+    tables: BTreeMap<String, Vec<String>>,
+}
+
+/// Minimal linear congruential generator, so the synthetic set is the same on every platform.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, n: u64) -> i64 {
+        (self.next() % n) as i64
+    }
+}
+
+fn decimal(s: &str) -> Decimal {
+    Decimal::from_str(s).unwrap()
+}
+
+/// Per user: random walks per coin with flips and reductions, several fills per
+/// millisecond, a third of them without a builder fee, and deposits around the trading.
+fn synthetic_dataset() -> Dataset {
+    let mut rng = Lcg(0x5eed);
+    let mut fills = Vec::new();
+    let mut deposits = Vec::new();
+    let mut tid = 0;
+
+    for (u, user) in USERS.iter().enumerate() {
+        let mut time_ms = 1_000 + u as i64 * 7;
+        let mut positions = [0i64; COINS.len()];
+        deposits.push(Deposit::new(
+            Address::new(user.to_string()),
+            TimeMs::new(time_ms - 500),
+            decimal("100000"),
+            Some(format!("0x{}d0", u)),
+        ));
+
+        for i in 0..FILLS_PER_USER {
+            time_ms += rng.below(3);
+            let c = rng.below(COINS.len() as u64) as usize;
+            let (coin, base_px) = COINS[c];
+            let sz = 1 + rng.below(4);
+            let side = if rng.below(2) == 0 { Side::Buy } else { Side::Sell };
+            let signed = if side == Side::Buy { sz } else { -sz };
+            let reducing = positions[c] != 0 && positions[c].signum() != signed.signum();
+            positions[c] += signed;
+
+            let px = base_px + rng.below(200) - 100;
+            let closed_pnl = if reducing { rng.below(2_000) - 1_000 } else { 0 };
+            let builder_fee = (rng.below(3) != 0).then(|| decimal("0.1"));
+            tid += 1;
+            fills.push(Fill::new(
+                TimeMs::new(time_ms),
+                Address::new(user.to_string()),
+                Coin::new(coin.to_string()),
+                side,
+                decimal(&px.to_string()),
+                decimal(&sz.to_string()),
+                decimal(&format!("{}.{:02}", sz, rng.below(100))),
+                decimal(&closed_pnl.to_string()),
+                builder_fee,
+                Some(tid),
+                Some(tid / 2),
+            ));
+
+            if i % 50 == 49 {
+                let amount = if rng.below(2) == 0 { "2500.5" } else { "-1000" };
+                deposits.push(Deposit::new(
+                    Address::new(user.to_string()),
+                    TimeMs::new(time_ms),
+                    decimal(amount),
+                    Some(format!("0x{}d{}", u, i)),
+                ));
+            }
+        }
+    }
+
+    Dataset {
+        users: USERS.iter().map(|u| u.to_string()).collect(),
+        fills,
+        deposits,
+    }
+}
+
+/// Fills re-derived from the `userFillsByTime` payloads recorded in the database at `path`,
+/// which is opened read-only.
+async fn recorded_dataset(path: &str) -> Dataset {
+    let options = SqliteConnectOptions::from_str(path)
+        .unwrap()
+        .read_only(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path, e));
+    let repo = Repository::new(pool);
+
+    let mut fills = Vec::new();
+    for payload in repo.query_raw_payloads("userFillsByTime", None).await.unwrap() {
+        fills.extend(
+            replay_fills(&payload.request, &payload.content)
+                .unwrap_or_else(|e| panic!("Payload {} does not replay: {}", payload.id, e)),
+        );
+    }
+    assert!(
+        !fills.is_empty(),
+        "{} has no recorded userFillsByTime payloads (record with RECORD_RAW_PAYLOADS=true)",
+        path
+    );
+
+    let users: BTreeSet<String> = fills.iter().map(|f| f.user.as_str().to_string()).collect();
+    Dataset {
+        users: users.into_iter().collect(),
+        fills,
+        deposits: Vec::new(),
+    }
+}
+
+fn requests(users: &[String]) -> Vec<String> {
+    let mut uris = Vec::new();
+    for user in users {
+        uris.push(format!("/v1/trades?user={}", user));
+        uris.push(format!("/v1/positions/history?user={}", user));
+        uris.push(format!("/v1/positions/history?user={}&builderOnly=true", user));
+        uris.push(format!("/v1/positions/history?user={}&maxPoints=16", user));
+        uris.push(format!("/v1/positions/open?user={}", user));
+        uris.push(format!("/v1/positions/aging?user={}&asOfMs=100000", user));
+        uris.push(format!("/v1/pnl?user={}", user));
+        uris.push(format!("/v1/pnl?user={}&builderOnly=true", user));
+        uris.push(format!("/v1/pnl?user={}&builderOnly=true&taintMode=fill", user));
+        uris.push(format!("/v1/pnl?user={}&returnMethod=twr", user));
+        uris.push(format!("/v1/pnl?user={}&returnMethod=irr", user));
+        uris.push(format!("/v1/performance?user={}", user));
+        uris.push(format!("/v1/equity/history?user={}", user));
+        uris.push(format!("/v1/stats?user={}", user));
+        uris.push(format!("/v1/stats?user={}&builderOnly=true", user));
+        uris.push(format!("/v1/attributions?user={}", user));
+        uris.push(format!("/v1/deposits?user={}", user));
+        for (coin, _) in COINS {
+            uris.push(format!(
+                "/v1/positions/series?user={}&coin={}&intervalMs=10",
+                user, coin
+            ));
+        }
+    }
+    for metric in ["volume", "pnl", "returnPct", "fees"] {
+        uris.push(format!("/v1/leaderboard?metric={}", metric));
+        uris.push(format!("/v1/leaderboard?metric={}&builderOnly=true", metric));
+    }
+    uris
+}
+
+async fn run(dataset: &Dataset) -> RunOutput {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("replay.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool.clone()));
+    let datasource = Arc::new(
+        MockDataSource::new()
+            .with_fills(dataset.fills.clone())
+            .with_deposits(dataset.deposits.clone()),
+    );
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: dataset.users.clone(),
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = AppState::new(repo, config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    let mut responses = Vec::new();
+    for uri in requests(&dataset.users) {
+        let (status, body) = request(app.clone(), &uri).await;
+        responses.push((uri, status, body));
+    }
+    let tables = dump_tables(&pool).await;
+    pool.close().await;
+
+    RunOutput { responses, tables }
+}
+
+async fn request(app: axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec();
+    (status, body)
+}
+
+/// Every table except [`SKIPPED_TABLES`], without [`SKIPPED_COLUMNS`], as sorted rows of
+/// quoted SQL literals (so `1`, `'1'` and `X'01'` stay distinct).
+///
+/// `AUTOINCREMENT` row ids are left out too: coins compile concurrently, so effects and
+/// snapshots are numbered in the order the compiles finish. Nothing refers to those ids;
+/// lifecycle ids are content hashes and are still compared through `lifecycle_id`.
+async fn dump_tables(pool: &SqlitePool) -> BTreeMap<String, Vec<String>> {
+    let tables: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+
+    let mut dump = BTreeMap::new();
+    for (table, sql) in tables {
+        if SKIPPED_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+        let row_id = sql
+            .contains("id INTEGER PRIMARY KEY AUTOINCREMENT")
+            .then_some("id");
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+                .bind(&table)
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        let row_expr = columns
+            .iter()
+            .filter(|c| !SKIPPED_COLUMNS.contains(&c.as_str()) && Some(c.as_str()) != row_id)
+            .map(|c| format!("'{}=' || quote(\"{}\")", c, c))
+            .collect::<Vec<_>>()
+            .join(" || ', ' || ");
+        let rows = sqlx::query(&format!("SELECT {} AS row FROM \"{}\"", row_expr, table))
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        let mut rows: Vec<String> = rows.iter().map(|r| r.get("row")).collect();
+        rows.sort();
+        dump.insert(table, rows);
+    }
+    dump
+}
+
+/// The first difference between the runs, described for a human; `None` if they match.
+fn first_divergence(first: &RunOutput, second: &RunOutput) -> Option<String> {
+    for ((uri, status_a, body_a), (_, status_b, body_b)) in
+        first.responses.iter().zip(&second.responses)
+    {
+        if status_a != status_b {
+            return Some(format!("{}: status {} vs {}", uri, status_a, status_b));
+        }
+        if body_a != body_b {
+            let at = body_a
+                .iter()
+                .zip(body_b)
+                .position(|(a, b)| a != b)
+                .unwrap_or(body_a.len().min(body_b.len()));
+            return Some(format!(
+                "{}: bodies differ at byte {} of {}/{}\n  first:  …{}…\n  second: …{}…",
+                uri,
+                at,
+                body_a.len(),
+                body_b.len(),
+                excerpt(body_a, at),
+                excerpt(body_b, at)
+            ));
+        }
+    }
+
+    for (table, rows_a) in &first.tables {
+        let rows_b = &second.tables[table];
+        if rows_a == rows_b {
+            continue;
+        }
+        let at = rows_a
+            .iter()
+            .zip(rows_b)
+            .position(|(a, b)| a != b)
+            .unwrap_or(rows_a.len().min(rows_b.len()));
+        return Some(format!(
+            "table {}: {} vs {} rows, first difference at sorted row {}\n  first:  {}\n  second: {}",
+            table,
+            rows_a.len(),
+            rows_b.len(),
+            at,
+            rows_a.get(at).map_or("<none>", String::as_str),
+            rows_b.get(at).map_or("<none>", String::as_str)
+        ));
+    }
+    None
+}
+
+fn excerpt(body: &[u8], at: usize) -> String {
+    let start = at.saturating_sub(60);
+    let end = (at + 60).min(body.len());
+    String::from_utf8_lossy(&body[start.min(end)..end]).into_owned()
+}
+
+#[tokio::test]
+async fn test_replay_is_deterministic() {
+    let dataset = match std::env::var("REPLAY_FILLS_DB") {
+        Ok(path) => recorded_dataset(&path).await,
+        Err(_) => synthetic_dataset(),
+    };
+
+    let first = run(&dataset).await;
+    let second = run(&dataset).await;
+
+    let failed: Vec<_> = first
+        .responses
+        .iter()
+        .filter(|(_, status, _)| !status.is_success())
+        .map(|(uri, status, body)| format!("{} {}: {}", status, uri, String::from_utf8_lossy(body)))
+        .collect();
+    assert!(failed.is_empty(), "Requests failed:\n{}", failed.join("\n"));
+    for table in ["raw_fills", "position_lifecycles", "position_snapshots", "fill_effects"] {
+        assert!(!first.tables[table].is_empty(), "{} is empty after the replay", table);
+    }
+
+    if let Some(divergence) = first_divergence(&first, &second) {
+        panic!("Runs diverge: {}", divergence);
+    }
+    println!(
+        "{} fills, {} responses and {} table rows identical across both runs",
+        dataset.fills.len(),
+        first.responses.len(),
+        first.tables.values().map(Vec::len).sum::<usize>()
+    );
+}
@@ -5,10 +5,9 @@
 
 use hypesilico::datasource::builder_logs::{BuilderLogsFetcher, BuilderLogsSource};
 use hypesilico::domain::{Address, AttributionConfidence, Side};
-use hypesilico::engine::{BuilderLogsIndex, MatchTolerances};
+use hypesilico::engine::MatchTolerances;
 use reqwest::Client;
 use std::collections::HashMap;
-use std::io::Read;
 
 const INSILICO_BUILDER: &str = "0x2868fc0d9786a740b491577a43502259efa78a39";
 
@@ -92,6 +91,7 @@ struct TestFill {
     side: Side,
     px: hypesilico::domain::Decimal,
     sz: hypesilico::domain::Decimal,
+    #[allow(dead_code)]
     tid: Option<i64>,
 }
 
@@ -112,12 +112,12 @@ fn try_fuzzy_match(
 ) -> Option<AttributionConfidence> {
     for log in logs {
         // Check user matches (case insensitive)
-        if log.user.as_str().to_ascii_lowercase() != user.to_ascii_lowercase() {
+        if !log.user.as_str().eq_ignore_ascii_case(user) {
             continue;
         }
 
         // Check coin matches (case insensitive)
-        if log.coin.as_str().to_ascii_uppercase() != fill.coin.to_ascii_uppercase() {
+        if !log.coin.as_str().eq_ignore_ascii_case(&fill.coin) {
             continue;
         }
 
@@ -180,7 +180,7 @@ async fn e2e_fuzzy_matching_with_real_data() {
 
     // Step 3: Pick top 3 users with most fills for testing
     let mut user_counts: Vec<_> = by_user.iter().map(|(u, l)| (u.clone(), l.len())).collect();
-    user_counts.sort_by(|a, b| b.1.cmp(&a.1));
+    user_counts.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     println!("\n2. Top users by builder fill count:");
     for (user, count) in user_counts.iter().take(5) {
@@ -224,8 +224,10 @@ async fn e2e_fuzzy_matching_with_real_data() {
         println!("   Parsed {} fills", fills.len());
 
         // Test matching
-        let mut stats = MatchStats::default();
-        stats.total_fills = fills.len();
+        let mut stats = MatchStats {
+            total_fills: fills.len(),
+            ..Default::default()
+        };
 
         for fill in &fills {
             if let Some(confidence) = try_fuzzy_match(fill, user, &logs, &tolerances) {
@@ -264,7 +266,7 @@ async fn e2e_fuzzy_matching_with_real_data() {
                     // Find corresponding log entry
                     for log in &logs {
                         if log.user.as_str().to_ascii_lowercase() == *user
-                            && log.coin.as_str().to_ascii_uppercase() == fill.coin.to_ascii_uppercase()
+                            && log.coin.as_str().eq_ignore_ascii_case(&fill.coin)
                             && log.side == fill.side
                         {
                             let dt = (fill.time_ms - log.time_ms.as_ms()).abs();
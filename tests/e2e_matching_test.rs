@@ -6,7 +6,7 @@
 //! 3. Verifying matching works with slight variations
 
 use hypesilico::datasource::builder_logs::{BuilderLogsFetcher, BuilderLogsSource};
-use hypesilico::domain::{Address, AttributionConfidence, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::domain::{Address, AttributionConfidence, Decimal, Fill, TimeMs};
 use hypesilico::engine::{BuilderLogsIndex, MatchTolerances};
 use reqwest::Client;
 use std::str::FromStr;
@@ -0,0 +1,307 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{
+        Address, Coin, Decimal, Deposit, Fill, MarkPrice, Side, TimeMs, MARK_PRICE_BUCKET_MS,
+    },
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    setup_test_app_with(MockDataSource::new()).await
+}
+
+async fn setup_test_app_with(datasource: MockDataSource) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(datasource);
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::zero(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+fn deposit(time_ms: i64, amount: &str, tx_hash: &str) -> Deposit {
+    Deposit::new(
+        Address::new(USER.to_string()),
+        TimeMs::new(time_ms),
+        Decimal::from_str(amount).unwrap(),
+        Some(tx_hash.to_string()),
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn seed(repo: &Repository) {
+    repo.insert_deposits_batch(&[
+        deposit(0, "1000", "0x01"),
+        // Withdrawn in the same millisecond as the close.
+        deposit(2000, "-300", "0x02"),
+    ])
+    .await
+    .unwrap();
+    repo.insert_fill(&fill(1000, Side::Buy, "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, Side::Sell, "200", "100", 2))
+        .await
+        .unwrap();
+}
+
+fn points(body: &serde_json::Value) -> Vec<(i64, String)> {
+    body["points"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["timeMs"].as_i64().unwrap(),
+                p["equity"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_equity_history_follows_flows_and_pnl() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+
+    let (status, body) = get_json(app.clone(), format!("/v1/equity/history?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        points(&body),
+        vec![
+            (0, "1000".to_string()),
+            (1000, "1000".to_string()),
+            (2000, "800".to_string()),
+        ]
+    );
+
+    // Equity at fromMs counts the withdrawal but not the fill in the same millisecond.
+    let (status, body) = get_json(
+        app.clone(),
+        format!("/v1/equity/history?user={}&fromMs=2000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(points(&body), vec![(2000, "800".to_string())]);
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/equity/history?user={}&maxPoints=1", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(points(&body), vec![(2000, "800".to_string())]);
+}
+
+#[tokio::test]
+async fn test_max_points_is_validated() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+
+    let (status, _) = get_json(
+        app.clone(),
+        format!("/v1/equity/history?user={}&maxPoints=0", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = get_json(
+        app.clone(),
+        format!("/v1/positions/history?user={}&maxPoints=10&limit=5", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("maxPoints"));
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/history?user={}&maxPoints=1", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let snapshots = body["snapshots"].as_array().unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0]["timeMs"], 2000);
+}
+
+#[tokio::test]
+async fn test_equity_history_marks_open_positions_to_market() {
+    const HOUR: i64 = MARK_PRICE_BUCKET_MS;
+    let btc = Coin::new("BTC".to_string());
+    let mark = |time_ms: i64, px: &str| MarkPrice {
+        coin: btc.clone(),
+        time_ms: TimeMs::new(time_ms),
+        px: Decimal::from_str(px).unwrap(),
+    };
+    let datasource = MockDataSource::new().with_mark_prices(vec![
+        mark(0, "100"),
+        mark(HOUR, "110"),
+        mark(2 * HOUR, "120"),
+        mark(3 * HOUR, "125"),
+    ]);
+    let TestApp { app, repo, _temp } = setup_test_app_with(datasource).await;
+
+    repo.insert_deposits_batch(&[deposit(0, "1000", "0x01")])
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(1000, Side::Buy, "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3 * HOUR + 5, Side::Sell, "130", "30", 2))
+        .await
+        .unwrap();
+
+    let uri = format!(
+        "/v1/equity/history?user={}&toMs={}&includeUnrealized=true",
+        USER,
+        4 * HOUR
+    );
+    let (status, body) = get_json(app.clone(), uri.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let marked: Vec<_> = body["points"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["timeMs"].as_i64().unwrap(),
+                p["unrealizedPnl"].as_str().unwrap().to_string(),
+                p["markedEquity"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    let point = |t: i64, unrealized: &str, marked: &str| {
+        (t, unrealized.to_string(), marked.to_string())
+    };
+    assert_eq!(
+        marked,
+        vec![
+            point(0, "0", "1000"),
+            point(1000, "0", "1000"),
+            point(HOUR, "10", "1010"),
+            point(2 * HOUR, "20", "1020"),
+            point(3 * HOUR, "25", "1025"),
+            point(3 * HOUR + 5, "0", "1030"),
+        ]
+    );
+
+    // Without the flag the curve is realized only.
+    let (_, body) = get_json(
+        app,
+        format!("/v1/equity/history?user={}&toMs={}", USER, 4 * HOUR),
+    )
+    .await;
+    assert_eq!(
+        points(&body),
+        vec![
+            (0, "1000".to_string()),
+            (1000, "1000".to_string()),
+            (3 * HOUR + 5, "1030".to_string()),
+        ]
+    );
+    assert!(body["points"][0].get("unrealizedPnl").is_none());
+}
@@ -0,0 +1,221 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::repo::BuilderLogFileRow;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+const BUILDER: &str = "0x0000000000000000000000000000000000000000";
+/// 2023-11-14T22:13:20Z
+const T0: i64 = 1_700_000_000_000;
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+fn fill(time_ms: i64, side: Side, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::zero(),
+        Decimal::zero(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+/// Store a builder log file for 2023-11-14 holding `rows` as `(time, user, px)`.
+async fn cache_logs(repo: &hypesilico::Repository, rows: &[(&str, &str, &str)]) {
+    let mut csv = String::from("time,user,coin,side,px,sz\n");
+    for (time, user, px) in rows {
+        csv.push_str(&format!("{},{},BTC,B,{},1\n", time, user, px));
+    }
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder.write_all(csv.as_bytes()).unwrap();
+    let content = encoder.finish().unwrap();
+
+    repo.upsert_builder_log_file(&BuilderLogFileRow {
+        builder: BUILDER.to_string(),
+        yyyymmdd: "20231114".to_string(),
+        sha256: hex::encode(Sha256::digest(&content)),
+        etag: None,
+        row_count: rows.len() as i64,
+        fetched_at_ms: 0,
+        content,
+    })
+    .await
+    .unwrap();
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_execution_quality_against_builder_logs() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    cache_logs(
+        &repo,
+        &[
+            ("2023-11-14T22:13:10.000Z", "0xdef", "99"),
+            ("2023-11-14T22:13:20.000Z", "0xdef", "100"),
+            ("2023-11-14T22:13:30.000Z", "0xdef", "104"),
+            // The user's own execution is not part of the benchmark.
+            ("2023-11-14T22:13:20.000Z", USER, "101"),
+        ],
+    )
+    .await;
+    repo.insert_fills_batch(&[
+        fill(T0, Side::Buy, "101", 1),
+        fill(T0 + 1_000, Side::Sell, "101", 2),
+        fill(T0 + 3_600_000, Side::Sell, "90", 3),
+    ])
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/execution-quality?user={}&windowMs=30000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let fills = body["fills"].as_array().unwrap();
+    assert_eq!(fills.len(), 3);
+
+    assert_eq!(fills[0]["referencePx"], "100");
+    assert_eq!(fills[0]["referenceCount"], 3);
+    assert_eq!(fills[0]["slippageBps"], "100");
+    // Selling above the reference is an improvement.
+    assert_eq!(fills[1]["slippageBps"], "-100");
+    assert!(fills[2].get("referencePx").is_none());
+    assert_eq!(fills[2]["referenceCount"], 0);
+
+    assert_eq!(body["measuredFills"], 2);
+    assert_eq!(body["avgSlippageBps"], "0");
+    assert_eq!(body["pendingDays"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_execution_quality_reports_days_without_logs() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    // Within a minute of midnight, so the next day's logs are needed as well.
+    repo.insert_fills_batch(&[fill(1_700_006_390_000, Side::Buy, "100", 1)])
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/execution-quality?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["measuredFills"], 0);
+    assert!(body.get("avgSlippageBps").is_none());
+    assert_eq!(body["pendingDays"], serde_json::json!(["20231114", "20231115"]));
+}
+
+#[tokio::test]
+async fn test_execution_quality_rejects_oversized_window() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/execution-quality?user={}&windowMs=86400001", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "BAD_REQUEST");
+}
@@ -0,0 +1,568 @@
+//! Golden snapshot suite for the full API surface.
+//!
+//! Every public read endpoint and the batch endpoints are exercised against a fixed demo
+//! dataset and the raw response bytes are compared with the files under `tests/golden/`. Any
+//! byte change fails the test. `/v1/risk` reads live account state, and the stream, ingest,
+//! and admin endpoints have their own tests.
+//!
+//! To accept intentional changes, regenerate the snapshots and review the diff:
+//!
+//! ```bash
+//! UPDATE_GOLDEN=1 cargo test --test golden_snapshot_test
+//! ```
+
+use axum::http::StatusCode;
+use hypesilico::api::{self, AppState};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Deposit, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const ALICE: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+const BOB: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+const CAROL: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+const BUILDER: &str = "0x3333333333333333333333333333333333333333";
+/// `nextCursor` of `positions_history_page1`; it only encodes that page's last snapshot,
+/// so it is as stable as the snapshot itself.
+const HISTORY_PAGE2_CURSOR: &str = "343030303a303a333236353731313731373436303038383038313a3a455448";
+
+/// A single request in the suite and the status it must return. Cases with a body are
+/// POSTed as JSON.
+struct GoldenCase {
+    name: &'static str,
+    uri: String,
+    body: Option<serde_json::Value>,
+    status: StatusCode,
+}
+
+fn golden_cases() -> Vec<GoldenCase> {
+    let case = |name: &'static str, uri: String, status: StatusCode| GoldenCase {
+        name,
+        uri,
+        body: None,
+        status,
+    };
+    let post = |name: &'static str, uri: &str, body: serde_json::Value| GoldenCase {
+        name,
+        uri: uri.to_string(),
+        body: Some(body),
+        status: StatusCode::OK,
+    };
+
+    vec![
+        case("health", "/health".to_string(), StatusCode::OK),
+        case("ready", "/ready".to_string(), StatusCode::OK),
+        case("trades", format!("/v1/trades?user={}", ALICE), StatusCode::OK),
+        case(
+            "trades_coin_window",
+            format!("/v1/trades?user={}&coin=BTC&fromMs=1000&toMs=3000", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "trades_builder_only",
+            format!("/v1/trades?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case("pnl", format!("/v1/pnl?user={}", ALICE), StatusCode::OK),
+        case(
+            "pnl_builder_only",
+            format!("/v1/pnl?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "pnl_builder_only_fill_mode",
+            format!("/v1/pnl?user={}&builderOnly=true&taintMode=fill", BOB),
+            StatusCode::OK,
+        ),
+        case("pnl_flows", format!("/v1/pnl?user={}", CAROL), StatusCode::OK),
+        case(
+            "pnl_flows_from_withdrawal",
+            format!("/v1/pnl?user={}&fromMs=300", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "pnl_flows_after_withdrawal",
+            format!("/v1/pnl?user={}&fromMs=350", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "pnl_flows_twr",
+            format!("/v1/pnl?user={}&returnMethod=twr", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "pnl_flows_irr",
+            format!("/v1/pnl?user={}&returnMethod=irr", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "performance",
+            format!("/v1/performance?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "performance_builder_only",
+            format!("/v1/performance?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_history",
+            format!("/v1/positions/history?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_history_page1",
+            format!("/v1/positions/history?user={}&limit=4", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_history_page2",
+            format!(
+                "/v1/positions/history?user={}&limit=4&cursor={}",
+                ALICE, HISTORY_PAGE2_CURSOR
+            ),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_history_downsampled",
+            format!("/v1/positions/history?user={}&maxPoints=2", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_history_builder_only",
+            format!("/v1/positions/history?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "equity_history",
+            format!("/v1/equity/history?user={}", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "equity_history_downsampled",
+            format!("/v1/equity/history?user={}&fromMs=300&maxPoints=2", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_open",
+            format!("/v1/positions/open?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_open_builder_only",
+            format!("/v1/positions/open?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_aging",
+            format!("/v1/positions/aging?user={}&asOfMs=10000", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_aging_by_size",
+            format!("/v1/positions/aging?user={}&asOfMs=10000&sortBy=size", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_series",
+            format!(
+                "/v1/positions/series?user={}&coin=ETH&intervalMs=2000",
+                ALICE
+            ),
+            StatusCode::OK,
+        ),
+        case("stats", format!("/v1/stats?user={}", ALICE), StatusCode::OK),
+        case(
+            "stats_builder_only",
+            format!("/v1/stats?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "attributions",
+            format!("/v1/attributions?user={}", BOB),
+            StatusCode::OK,
+        ),
+        case(
+            "builder_logs_status",
+            "/v1/builder-logs/status".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "deposits",
+            format!("/v1/deposits?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "deposits_flows",
+            format!("/v1/deposits?user={}", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "leaderboard_volume",
+            "/v1/leaderboard?metric=volume".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "leaderboard_pnl",
+            "/v1/leaderboard?metric=pnl".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "leaderboard_return_pct",
+            "/v1/leaderboard?metric=returnPct".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "leaderboard_builder_only",
+            "/v1/leaderboard?metric=volume&builderOnly=true".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "leaderboard_builder_only_fill_mode",
+            "/v1/leaderboard?metric=pnl&builderOnly=true&taintMode=fill".to_string(),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_at",
+            format!("/v1/positions/at?user={}&atMs=3500", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "positions_at_builder_only",
+            format!("/v1/positions/at?user={}&atMs=2000&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case("lifecycles", format!("/v1/lifecycles?user={}", ALICE), StatusCode::OK),
+        case(
+            "lifecycles_builder_only",
+            format!("/v1/lifecycles?user={}&builderOnly=true", BOB),
+            StatusCode::OK,
+        ),
+        case("orders", format!("/v1/orders?user={}", ALICE), StatusCode::OK),
+        case(
+            "execution_quality",
+            format!("/v1/execution-quality?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case(
+            "risk_history",
+            format!("/v1/risk/history?user={}&fromMs=0&toMs=86400000", CAROL),
+            StatusCode::OK,
+        ),
+        case(
+            "builder_revenue",
+            format!("/v1/builder/revenue?builder={}", BUILDER),
+            StatusCode::OK,
+        ),
+        case("coins", "/v1/coins".to_string(), StatusCode::OK),
+        case(
+            "leaderboard_history",
+            format!("/v1/leaderboard/history?user={}", ALICE),
+            StatusCode::OK,
+        ),
+        case("competitions", "/v1/competitions".to_string(), StatusCode::OK),
+        post(
+            "batch_pnl",
+            "/v1/batch/pnl",
+            serde_json::json!({ "users": [ALICE, BOB] }),
+        ),
+        post(
+            "batch_positions",
+            "/v1/batch/positions",
+            serde_json::json!({ "users": [ALICE, BOB], "builderOnly": true }),
+        ),
+        case(
+            "error_invalid_address",
+            "/v1/trades?user=0x123".to_string(),
+            StatusCode::BAD_REQUEST,
+        ),
+        case(
+            "error_unknown_competition",
+            "/v1/competitions/standings?name=spring".to_string(),
+            StatusCode::NOT_FOUND,
+        ),
+        case(
+            "error_invalid_metric",
+            "/v1/leaderboard?metric=sharpe".to_string(),
+            StatusCode::BAD_REQUEST,
+        ),
+    ]
+}
+
+async fn setup_demo_app() -> (axum::Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: BUILDER.to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![ALICE.to_string(), BOB.to_string()],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    seed_demo_dataset(&repo).await;
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = AppState::new(repo, config, orchestrator, equity_resolver);
+
+    (api::create_router(state), temp_dir)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fill(
+    user: &str,
+    coin: &str,
+    time_ms: i64,
+    tid: i64,
+    side: Side,
+    px: &str,
+    sz: &str,
+    fee: &str,
+    closed_pnl: &str,
+    builder_fee: Option<&str>,
+) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str(fee).unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        builder_fee.map(|s| Decimal::from_str(s).unwrap()),
+        Some(tid),
+        None,
+    )
+}
+
+fn deposit(user: &str, time_ms: i64, amount: &str, tx_hash: &str) -> Deposit {
+    Deposit::new(
+        Address::new(user.to_string()),
+        TimeMs::new(time_ms),
+        Decimal::from_str(amount).unwrap(),
+        Some(tx_hash.to_string()),
+    )
+}
+
+/// Seed the demo dataset shared by every golden case.
+///
+/// - Alice: a closed BTC round trip, an ETH long that flips short, and an open SOL long.
+///   All fills carry a builder fee, so her lifecycles are clean.
+/// - Bob: a BTC lifecycle mixing builder and non-builder fills (tainted) and a clean ETH long.
+/// - Carol: deposit, a BTC round trip, a withdrawal in the same millisecond as its close, a
+///   redeposit and an ETH round trip.
+async fn seed_demo_dataset(repo: &Repository) {
+    let fills = vec![
+        fill(ALICE, "BTC", 1000, 1, Side::Buy, "50000", "1", "5", "0", Some("1")),
+        fill(ALICE, "BTC", 2000, 2, Side::Sell, "51000", "1", "5", "1000", Some("1")),
+        fill(ALICE, "ETH", 3000, 3, Side::Buy, "3000", "2", "1.5", "0", Some("0.5")),
+        fill(ALICE, "ETH", 4000, 4, Side::Sell, "3100", "3", "2.25", "200", Some("0.5")),
+        fill(ALICE, "SOL", 5000, 5, Side::Buy, "100", "10", "0.5", "0", Some("0.1")),
+        fill(BOB, "BTC", 1500, 6, Side::Buy, "50500", "0.5", "2.5", "0", Some("1")),
+        fill(BOB, "BTC", 2500, 7, Side::Sell, "50000", "0.5", "2.5", "-250", None),
+        fill(BOB, "ETH", 3500, 8, Side::Buy, "3050", "1", "0.75", "0", Some("0.25")),
+        fill(CAROL, "BTC", 200, 9, Side::Buy, "50000", "0.02", "0.5", "0", None),
+        fill(CAROL, "BTC", 300, 10, Side::Sell, "55000", "0.02", "0.55", "100", None),
+        fill(CAROL, "ETH", 400, 11, Side::Buy, "3000", "1", "1.5", "0", None),
+        fill(CAROL, "ETH", 500, 12, Side::Sell, "3050", "1", "1.5", "50", None),
+    ];
+    repo.insert_fills_batch(&fills).await.unwrap();
+
+    let deposits = vec![
+        deposit(ALICE, 500, "10000", "0xa1"),
+        deposit(ALICE, 4500, "2500.5", "0xa2"),
+        deposit(BOB, 800, "5000", "0xb1"),
+        deposit(CAROL, 100, "1000", "0xc1"),
+        deposit(CAROL, 300, "-600", "0xc2"),
+        deposit(CAROL, 450, "200", "0xc3"),
+    ];
+    repo.insert_deposits_batch(&deposits).await.unwrap();
+}
+
+async fn request(app: axum::Router, uri: &str) -> (StatusCode, Vec<u8>) {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec();
+    (status, body)
+}
+
+async fn send(app: axum::Router, case: &GoldenCase) -> (StatusCode, Vec<u8>) {
+    let Some(body) = &case.body else {
+        return request(app, &case.uri).await;
+    };
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri(&case.uri)
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec();
+    (status, body)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.json", name))
+}
+
+fn update_mode() -> bool {
+    std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+#[tokio::test]
+async fn test_golden_snapshots() {
+    let (app, _temp) = setup_demo_app().await;
+    let update = update_mode();
+    let mut mismatches = Vec::new();
+
+    for case in golden_cases() {
+        let (status, body) = send(app.clone(), &case).await;
+        assert_eq!(status, case.status, "{} ({}) returned {}", case.name, case.uri, status);
+
+        let path = golden_path(case.name);
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &body).unwrap();
+            continue;
+        }
+
+        match std::fs::read(&path) {
+            Ok(expected) if expected == body => {}
+            Ok(expected) => mismatches.push(format!(
+                "{} ({})\n  expected: {}\n  actual:   {}",
+                case.name,
+                case.uri,
+                String::from_utf8_lossy(&expected),
+                String::from_utf8_lossy(&body)
+            )),
+            Err(_) => mismatches.push(format!(
+                "{} ({}): missing {}",
+                case.name,
+                case.uri,
+                path.display()
+            )),
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Golden snapshots differ (rerun with UPDATE_GOLDEN=1 to accept):\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[tokio::test]
+async fn test_golden_snapshots_are_reproducible() {
+    let (first_app, _first) = setup_demo_app().await;
+    let (second_app, _second) = setup_demo_app().await;
+
+    for case in golden_cases() {
+        let (_, first) = send(first_app.clone(), &case).await;
+        let (_, second) = send(second_app.clone(), &case).await;
+        assert_eq!(first, second, "{} differs between fresh databases", case.name);
+    }
+}
+
+#[tokio::test]
+async fn test_history_pages_concatenate_to_full_history() {
+    let (app, _temp) = setup_demo_app().await;
+    let base = format!("/v1/positions/history?user={}", ALICE);
+    let (_, full) = request(app.clone(), &base).await;
+    let full: serde_json::Value = serde_json::from_slice(&full).unwrap();
+
+    for limit in 1..=7 {
+        let mut snapshots = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let uri = match &cursor {
+                Some(cursor) => format!("{}&limit={}&cursor={}", base, limit, cursor),
+                None => format!("{}&limit={}", base, limit),
+            };
+            let (status, body) = request(app.clone(), &uri).await;
+            assert_eq!(status, StatusCode::OK);
+            let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let rows = page["snapshots"].as_array().unwrap();
+            assert!(rows.len() <= limit);
+            snapshots.extend(rows.iter().cloned());
+            match page["nextCursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+        assert_eq!(
+            serde_json::Value::Array(snapshots),
+            full["snapshots"],
+            "pages of {} differ from the full history",
+            limit
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::{init_db, SCHEMA_VERSION},
+    DataSource, Repository,
+};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    pool: SqlitePool,
+    orchestrator: Arc<Orchestrator>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool.clone()));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator.clone(), equity_resolver);
+
+    TestApp {
+        app: api::create_router(state),
+        pool,
+        orchestrator,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_ready(app: axum::Router) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri("/ready")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_ready_reports_each_check() {
+    let test_app = setup_test_app().await;
+
+    let (status, body) = get_ready(test_app.app).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["checks"]["database"]["status"], "ok");
+    assert_eq!(
+        body["checks"]["migrations"]["detail"],
+        format!("schema version {}", SCHEMA_VERSION)
+    );
+    assert_eq!(body["checks"]["scheduler"]["status"], "ok");
+    // The datasource is reported but an unreachable one does not gate readiness.
+    assert_eq!(body["checks"]["datasource"]["status"], "fail");
+}
+
+#[tokio::test]
+async fn test_ready_fails_until_migrations_apply() {
+    let test_app = setup_test_app().await;
+    sqlx::query("UPDATE instance_metadata SET value = ? WHERE key = 'schema_version'")
+        .bind((SCHEMA_VERSION - 1).to_string())
+        .execute(&test_app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = get_ready(test_app.app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "notReady");
+    assert_eq!(body["checks"]["migrations"]["status"], "fail");
+}
+
+#[tokio::test]
+async fn test_ready_fails_once_scheduler_closes() {
+    let test_app = setup_test_app().await;
+    test_app.orchestrator.job_queue().close();
+
+    let (status, body) = get_ready(test_app.app).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["checks"]["scheduler"]["status"], "fail");
+}
@@ -0,0 +1,182 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+struct TestApp {
+    app: axum::Router,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(response_compression: bool, max_request_body_bytes: usize) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression,
+        max_request_body_bytes,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        _temp: temp_dir,
+    }
+}
+
+async fn get_openapi(app: axum::Router, accept_encoding: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder().method("GET").uri("/openapi.json");
+    if let Some(encoding) = accept_encoding {
+        request = request.header("accept-encoding", encoding);
+    }
+    app.oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn body_bytes(response: axum::response::Response) -> axum::body::Bytes {
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+}
+
+/// POST `len` bytes to the ingest endpoint, declaring the length only when `declare_len`.
+async fn post_body(
+    app: axum::Router,
+    len: usize,
+    declare_len: bool,
+) -> (StatusCode, serde_json::Value) {
+    // A JSON array of spaces parses as an empty array once it fits.
+    let mut body = vec![b' '; len];
+    body[0] = b'[';
+    body[len - 1] = b']';
+
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/fills")
+        .header("content-type", "application/json");
+    if declare_len {
+        request = request.header("content-length", len);
+    }
+    let response = app
+        .oneshot(request.body(axum::body::Body::from(body)).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, serde_json::from_slice(&body_bytes(response).await).unwrap())
+}
+
+#[tokio::test]
+async fn test_responses_compressed_when_accepted() {
+    let TestApp { app, _temp } = setup_test_app(true, 2 * 1024 * 1024).await;
+
+    let plain = get_openapi(app.clone(), None).await;
+    assert_eq!(plain.status(), StatusCode::OK);
+    assert!(plain.headers().get("content-encoding").is_none());
+    let plain = body_bytes(plain).await;
+
+    for encoding in ["gzip", "deflate"] {
+        let response = get_openapi(app.clone(), Some(encoding)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], encoding);
+        let compressed = body_bytes(response).await;
+        assert!(
+            compressed.len() < plain.len() / 2,
+            "{} body of {} bytes is not much smaller than {}",
+            encoding,
+            compressed.len(),
+            plain.len()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_responses_uncompressed_when_disabled() {
+    let TestApp { app, _temp } = setup_test_app(false, 2 * 1024 * 1024).await;
+
+    let response = get_openapi(app, Some("gzip, deflate")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = body_bytes(response).await;
+    serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+}
+
+#[tokio::test]
+async fn test_oversized_body_rejected_with_413() {
+    let TestApp { app, _temp } = setup_test_app(true, 1024).await;
+
+    let (status, body) = post_body(app.clone(), 1024, true).await;
+    assert_eq!(status, StatusCode::OK, "a body at the limit is accepted: {}", body);
+
+    // Refused from the declared length, and while reading a body that declares none.
+    for declare_len in [true, false] {
+        let (status, body) = post_body(app.clone(), 1025, declare_len).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["code"], "PAYLOAD_TOO_LARGE");
+    }
+}
+
+#[tokio::test]
+async fn test_body_limit_above_axum_default() {
+    let TestApp { app, _temp } = setup_test_app(true, 4 * 1024 * 1024).await;
+
+    let (status, body) = post_body(app, 3 * 1024 * 1024, false).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
@@ -0,0 +1,313 @@
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use hypesilico::api::auth::{ApiKeyConfig, ApiScope};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::{init_db, IdempotencyKey},
+    DataSource, Repository,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+const ADMIN_KEY: &str = "admin-key-1";
+const OTHER_ADMIN_KEY: &str = "admin-key-2";
+
+/// How the middleware scopes keys to a request on `/v1/ingest/fills` without API keys.
+fn fills_key(key: &str) -> IdempotencyKey<'_> {
+    IdempotencyKey {
+        caller: "",
+        key,
+        path: "/v1/ingest/fills",
+    }
+}
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    setup_test_app_with(60_000, Vec::new()).await
+}
+
+async fn setup_test_app_with(idempotency_ttl_ms: u64, api_keys: Vec<ApiKeyConfig>) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys,
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_fills(app: axum::Router, key: Option<&str>, body: serde_json::Value) -> Response {
+    post_fills_as(app, None, key, body).await
+}
+
+async fn post_fills_as(
+    app: axum::Router,
+    api_key: Option<&str>,
+    key: Option<&str>,
+    body: serde_json::Value,
+) -> Response {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/fills")
+        .header("content-type", "application/json");
+    if let Some(api_key) = api_key {
+        request = request.header("x-api-key", api_key);
+    }
+    if let Some(key) = key {
+        request = request.header("idempotency-key", key);
+    }
+    app.oneshot(request.body(axum::body::Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn json_body(response: Response) -> serde_json::Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn fill(time_ms: i64, tid: i64) -> serde_json::Value {
+    json!({
+        "user": USER,
+        "timeMs": time_ms,
+        "coin": "BTC",
+        "side": "buy",
+        "px": "50000",
+        "sz": "1",
+        "fee": "1.5",
+        "closedPnl": "0",
+        "tid": tid,
+    })
+}
+
+#[tokio::test]
+async fn test_repeat_replays_original_response() {
+    let test_app = setup_test_app().await;
+    let body = json!([fill(1000, 1), fill(2000, 2)]);
+
+    let first = post_fills(test_app.app.clone(), Some("batch-1"), body.clone()).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first = json_body(first).await;
+    assert_eq!(first["inserted"], 2);
+
+    // A plain retry runs again and finds duplicates; a keyed one gets the first answer.
+    let repeat = post_fills(test_app.app.clone(), Some("batch-1"), body.clone()).await;
+    assert_eq!(repeat.status(), StatusCode::OK);
+    assert_eq!(repeat.headers()["idempotent-replayed"], "true");
+    assert_eq!(repeat.headers()["content-type"], "application/json");
+    assert_eq!(json_body(repeat).await, first);
+
+    let unkeyed = json_body(post_fills(test_app.app.clone(), None, body).await).await;
+    assert_eq!(unkeyed["inserted"], 0);
+    assert_eq!(unkeyed["duplicates"], 2);
+}
+
+#[tokio::test]
+async fn test_key_reused_for_different_request_conflicts() {
+    let test_app = setup_test_app().await;
+
+    let first = post_fills(test_app.app.clone(), Some("batch-1"), json!([fill(1000, 1)])).await;
+    assert_eq!(first.status(), StatusCode::OK);
+
+    let other = post_fills(test_app.app.clone(), Some("batch-1"), json!([fill(2000, 2)])).await;
+    assert_eq!(other.status(), StatusCode::CONFLICT);
+    let body = json_body(other).await;
+    assert_eq!(body["code"], "CONFLICT");
+    assert!(body["error"].as_str().unwrap().contains("different request"));
+}
+
+#[tokio::test]
+async fn test_in_flight_key_conflicts_until_released() {
+    let test_app = setup_test_app().await;
+    let body = json!([fill(1000, 1)]);
+
+    // Claim the key as a concurrent request with the same body would.
+    let hash = {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(b"\0");
+        hasher.update(body.to_string().as_bytes());
+        hex::encode(hasher.finalize())
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    assert!(test_app
+        .repo
+        .claim_idempotency_key(&fills_key("batch-1"), &hash, now, now - 60_000, now - 60_000)
+        .await
+        .unwrap()
+        .is_none());
+
+    let response = post_fills(test_app.app.clone(), Some("batch-1"), body.clone()).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert!(json_body(response).await["error"]
+        .as_str()
+        .unwrap()
+        .contains("still in progress"));
+
+    test_app
+        .repo
+        .release_idempotency_key(&fills_key("batch-1"))
+        .await
+        .unwrap();
+    let response = post_fills(test_app.app.clone(), Some("batch-1"), body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["inserted"], 1);
+}
+
+#[tokio::test]
+async fn test_expired_key_runs_again() {
+    let test_app = setup_test_app().await;
+    let long_ago = chrono::Utc::now().timestamp_millis() - 120_000;
+    assert!(test_app
+        .repo
+        .claim_idempotency_key(&fills_key("batch-1"), "stale", long_ago, 0, 0)
+        .await
+        .unwrap()
+        .is_none());
+
+    let response = post_fills(test_app.app.clone(), Some("batch-1"), json!([fill(1000, 1)])).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["inserted"], 1);
+}
+
+#[tokio::test]
+async fn test_abandoned_claim_is_taken_over_after_lease() {
+    // A day-long TTL, so only the in-flight lease can free the claim.
+    let test_app = setup_test_app_with(86_400_000, Vec::new()).await;
+    let body = json!([fill(1000, 1)]);
+    let hash = {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(b"\0");
+        hasher.update(body.to_string().as_bytes());
+        hex::encode(hasher.finalize())
+    };
+    // Claimed by a request that died with the process an hour ago.
+    let an_hour_ago = chrono::Utc::now().timestamp_millis() - 3_600_000;
+    assert!(test_app
+        .repo
+        .claim_idempotency_key(&fills_key("batch-1"), &hash, an_hour_ago, 0, 0)
+        .await
+        .unwrap()
+        .is_none());
+
+    let response = post_fills(test_app.app.clone(), Some("batch-1"), body.clone()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["inserted"], 1);
+
+    let repeat = post_fills(test_app.app.clone(), Some("batch-1"), body).await;
+    assert_eq!(repeat.headers()["idempotent-replayed"], "true");
+}
+
+#[tokio::test]
+async fn test_keys_are_scoped_to_the_api_key() {
+    let api_keys = [ADMIN_KEY, OTHER_ADMIN_KEY]
+        .into_iter()
+        .map(|key| ApiKeyConfig {
+            key: key.to_string(),
+            scope: ApiScope::Admin,
+            requests_per_minute: 100,
+        })
+        .collect();
+    let test_app = setup_test_app_with(60_000, api_keys).await;
+
+    let first = post_fills_as(
+        test_app.app.clone(),
+        Some(ADMIN_KEY),
+        Some("batch-1"),
+        json!([fill(1000, 1)]),
+    )
+    .await;
+    assert_eq!(first.status(), StatusCode::OK);
+
+    // Another caller reusing the key with a different body neither conflicts nor replays.
+    let other = post_fills_as(
+        test_app.app.clone(),
+        Some(OTHER_ADMIN_KEY),
+        Some("batch-1"),
+        json!([fill(2000, 2)]),
+    )
+    .await;
+    assert_eq!(other.status(), StatusCode::OK);
+    assert!(other.headers().get("idempotent-replayed").is_none());
+    assert_eq!(json_body(other).await["inserted"], 1);
+
+    let repeat = post_fills_as(
+        test_app.app.clone(),
+        Some(ADMIN_KEY),
+        Some("batch-1"),
+        json!([fill(1000, 1)]),
+    )
+    .await;
+    assert_eq!(repeat.headers()["idempotent-replayed"], "true");
+}
@@ -0,0 +1,223 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal},
+    DataSource, Repository,
+};
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_json(
+    app: axum::Router,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn fill(time_ms: i64, side: &str, sz: &str, closed_pnl: &str, tid: i64) -> serde_json::Value {
+    json!({
+        "user": USER,
+        "timeMs": time_ms,
+        "coin": "BTC",
+        "side": side,
+        "px": "50000",
+        "sz": sz,
+        "fee": "1.5",
+        "closedPnl": closed_pnl,
+        "builderFee": "0.1",
+        "tid": tid,
+    })
+}
+
+#[tokio::test]
+async fn test_ingest_is_idempotent() {
+    let test_app = setup_test_app().await;
+    let body = json!([
+        fill(1000, "buy", "1", "0", 1),
+        fill(2000, "sell", "1", "10", 2)
+    ]);
+
+    let (status, first) = post_json(test_app.app.clone(), "/v1/ingest/fills", body.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["received"], 2);
+    assert_eq!(first["inserted"], 2);
+    assert_eq!(first["duplicates"], 0);
+    assert_eq!(first["fillKeys"][0], format!("{}:BTC:tid:1", USER));
+    assert!(first.get("compiledPairs").is_none());
+
+    let (status, second) = post_json(test_app.app, "/v1/ingest/fills", body).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["inserted"], 0);
+    assert_eq!(second["duplicates"], 2);
+    assert_eq!(second["fillKeys"], first["fillKeys"]);
+
+    let fills = test_app
+        .repo
+        .query_fills(&Address::new(USER.to_string()), None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(fills.len(), 2);
+    assert_eq!(fills[0].fee.to_canonical_string(), "1.5");
+}
+
+#[tokio::test]
+async fn test_ingest_compile_rebuilds_on_late_fills() {
+    let test_app = setup_test_app().await;
+    let user = Address::new(USER.to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/ingest/fills?compile=true",
+        json!([fill(2000, "sell", "1", "10", 2)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["compiledPairs"], 1);
+
+    // The opening buy arrives late, before the compiled watermark.
+    let (status, body) = post_json(
+        test_app.app,
+        "/v1/ingest/fills?compile=true",
+        json!([fill(1000, "buy", "1", "0", 1)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["compiledPairs"], 1);
+
+    let lifecycles = test_app.repo.query_lifecycles(&user, &coin).await.unwrap();
+    assert_eq!(lifecycles.len(), 1);
+    let snapshots = test_app.repo.query_snapshots(&user, &coin).await.unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].net_size, Decimal::from_str_canonical("1").unwrap());
+    assert_eq!(snapshots[1].net_size, Decimal::zero());
+}
+
+#[tokio::test]
+async fn test_ingest_rejects_invalid_fills() {
+    let test_app = setup_test_app().await;
+
+    let mut bad_side = fill(1000, "buy", "1", "0", 1);
+    bad_side["side"] = json!("B");
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/ingest/fills",
+        json!([fill(1000, "buy", "1", "0", 1), bad_side]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "fills[1]: side must be one of: buy, sell");
+
+    let (status, body) = post_json(
+        test_app.app.clone(),
+        "/v1/ingest/fills",
+        json!([fill(1000, "buy", "0", "0", 1)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "fills[0]: px and sz must be > 0");
+
+    let (status, _) = post_json(test_app.app, "/v1/ingest/fills", json!({"fills": []})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Nothing from a rejected batch is stored.
+    let fills = test_app
+        .repo
+        .query_fills(&Address::new(USER.to_string()), None, None, None)
+        .await
+        .unwrap();
+    assert!(fills.is_empty());
+}
@@ -1,10 +1,11 @@
 use axum::http::StatusCode;
 use hypesilico::api::{self, AppState};
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
-use hypesilico::engine::EquityResolver;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use hypesilico::Repository;
 use std::sync::Arc;
@@ -25,13 +26,41 @@ async fn setup_test_app() -> (axum::Router, TempDir) {
 
     let config = Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: db_path,
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
         target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     };
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
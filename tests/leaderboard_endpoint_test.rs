@@ -1,11 +1,12 @@
 use axum::http::StatusCode;
 use hypesilico::api::{self, AppState};
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
 use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
-use hypesilico::engine::EquityResolver;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use hypesilico::Repository;
 use std::str::FromStr;
@@ -19,20 +20,52 @@ struct TestApp {
     _temp: TempDir,
 }
 
-fn test_config(users: Vec<String>) -> Config {
+fn test_config(users: Vec<String>, pnl_mode: PnlMode) -> Config {
     Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: ":memory:".to_string(),
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
-        target_builder: "0x0".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
-        pnl_mode: PnlMode::Gross,
+        pnl_mode,
         lookback_ms: 0,
         leaderboard_users: users,
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     }
 }
 
 async fn setup_test_app(users: Vec<String>) -> TestApp {
+    setup_test_app_with_pnl_mode(users, PnlMode::Gross).await
+}
+
+async fn setup_test_app_with_pnl_mode(users: Vec<String>, pnl_mode: PnlMode) -> TestApp {
     let temp_dir = TempDir::new().unwrap();
     let db_path = temp_dir
         .path()
@@ -43,7 +76,7 @@ async fn setup_test_app(users: Vec<String>) -> TestApp {
 
     let repo = Arc::new(Repository::new(pool));
     let datasource = Arc::new(MockDataSource::new());
-    let config = test_config(users);
+    let config = test_config(users, pnl_mode);
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
     let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
@@ -234,6 +267,190 @@ async fn test_leaderboard_metric_correctness_volume_pnl_return_pct() {
     assert_eq!(v[0]["metricValue"], "10");
 }
 
+#[tokio::test]
+async fn test_leaderboard_net_pnl_counts_rebates() {
+    let maker = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let taker = Address::new("0x0000000000000000000000000000000000000456".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let test_app = setup_test_app_with_pnl_mode(
+        vec![maker.as_str().to_string(), taker.as_str().to_string()],
+        PnlMode::NetFees,
+    )
+    .await;
+
+    // Same gross PnL; the maker is rebated on both legs, the taker pays fees.
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&maker, &coin, 1000, 1, Side::Buy, "10", "1", "-0.5", "0", None),
+            fill(&maker, &coin, 2000, 2, Side::Sell, "15", "1", "-0.5", "5", None),
+            fill(&taker, &coin, 1000, 3, Side::Buy, "10", "1", "0.5", "0", None),
+            fill(&taker, &coin, 2000, 4, Side::Sell, "15", "1", "0.5", "5", None),
+        ])
+        .await
+        .unwrap();
+
+    let (status, body) = request(test_app.app.clone(), "/v1/leaderboard?metric=pnl").await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v[0]["user"], maker.as_str());
+    assert_eq!(v[0]["metricValue"], "6");
+    assert_eq!(v[1]["user"], taker.as_str());
+    assert_eq!(v[1]["metricValue"], "4");
+}
+
+#[tokio::test]
+async fn test_leaderboard_fees_metric_includes_builder_fees() {
+    let maker = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let taker = Address::new("0x0000000000000000000000000000000000000456".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let test_app =
+        setup_test_app(vec![maker.as_str().to_string(), taker.as_str().to_string()]).await;
+
+    // The taker's sell flips long to short; its builder fee is split, not counted twice.
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&maker, &coin, 1000, 1, Side::Buy, "10", "1", "-0.5", "0", Some("0.1")),
+            fill(&taker, &coin, 1000, 2, Side::Buy, "10", "1", "0.5", "0", Some("0.2")),
+            fill(&taker, &coin, 2000, 3, Side::Sell, "15", "2", "0.5", "5", Some("0.4")),
+        ])
+        .await
+        .unwrap();
+
+    let (status, body) = request(test_app.app.clone(), "/v1/leaderboard?metric=fees").await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v[0]["user"], taker.as_str());
+    assert_eq!(v[0]["metricValue"], "1.6");
+    assert_eq!(v[1]["user"], maker.as_str());
+    assert_eq!(v[1]["metricValue"], "-0.4");
+}
+
+#[tokio::test]
+async fn test_leaderboard_multiple_metrics_ranked_independently() {
+    let maker = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let taker = Address::new("0x0000000000000000000000000000000000000456".to_string());
+    let whale = Address::new("0x0000000000000000000000000000000000000789".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let test_app = setup_test_app(vec![
+        maker.as_str().to_string(),
+        taker.as_str().to_string(),
+        whale.as_str().to_string(),
+    ])
+    .await;
+
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&maker, &coin, 1000, 1, Side::Buy, "10", "1", "-0.5", "0", Some("0.1")),
+            fill(&taker, &coin, 1000, 2, Side::Buy, "10", "1", "0.5", "0", Some("0.2")),
+            fill(&taker, &coin, 2000, 3, Side::Sell, "15", "2", "0.5", "5", Some("0.4")),
+            fill(&whale, &coin, 1000, 4, Side::Buy, "100", "1", "0", "0", None),
+        ])
+        .await
+        .unwrap();
+
+    let (status, body) = request(
+        test_app.app.clone(),
+        "/v1/leaderboard?metric=feesPaid,volume,tradeCount,feesPaid",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // Rows follow the first metric; each metric carries its own rank.
+    assert_eq!(v[0]["user"], taker.as_str());
+    assert_eq!(v[0]["metricValue"], "1");
+    assert_eq!(v[0]["metrics"]["feesPaid"]["rank"], 1);
+    assert_eq!(v[0]["metrics"]["volume"]["value"], "40");
+    assert_eq!(v[0]["metrics"]["volume"]["rank"], 2);
+    assert_eq!(v[0]["metrics"]["tradeCount"]["value"], "2");
+    assert_eq!(v[0]["metrics"]["tradeCount"]["rank"], 1);
+    assert_eq!(v[1]["user"], whale.as_str());
+    assert_eq!(v[1]["metrics"]["volume"]["rank"], 1);
+    assert_eq!(v[1]["metrics"]["tradeCount"]["rank"], 3);
+    assert_eq!(v[2]["user"], maker.as_str());
+    assert_eq!(v[2]["metrics"]["feesPaid"]["value"], "-0.5");
+    assert_eq!(v[2]["metrics"]["tradeCount"]["rank"], 2);
+    assert_eq!(v[0]["metrics"].as_object().unwrap().len(), 3);
+
+    let (status, body) =
+        request(test_app.app.clone(), "/v1/leaderboard?metric=builderFees").await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v[0]["user"], taker.as_str());
+    assert_eq!(v[0]["metricValue"], "0.6");
+    assert_eq!(v[1]["metricValue"], "0.1");
+    assert_eq!(v[2]["metricValue"], "0");
+    assert!(v[0].get("metrics").is_none());
+}
+
+#[tokio::test]
+async fn test_leaderboard_history_reports_stored_snapshots() {
+    let maker = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let taker = Address::new("0x0000000000000000000000000000000000000456".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let test_app =
+        setup_test_app(vec![maker.as_str().to_string(), taker.as_str().to_string()]).await;
+    let repo = &test_app.state.repo;
+    let ledger = &test_app.state.ledger;
+
+    repo.insert_fills_batch(&[
+        fill(&maker, &coin, 1000, 1, Side::Buy, "10", "1", "0", "0", None),
+        fill(&maker, &coin, 1500, 2, Side::Sell, "12", "1", "0", "2", None),
+        fill(&taker, &coin, 1000, 3, Side::Buy, "10", "1", "0", "0", None),
+        fill(&taker, &coin, 1500, 4, Side::Sell, "11", "1", "0", "1", None),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(ledger.snapshot_leaderboard(TimeMs::new(2000)).await.unwrap(), 14);
+
+    // Later fills move the taker ahead without touching the stored snapshot.
+    repo.insert_fills_batch(&[
+        fill(&taker, &coin, 2500, 5, Side::Buy, "10", "1", "0", "0", None),
+        fill(&taker, &coin, 2600, 6, Side::Sell, "15", "1", "0", "5", None),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(ledger.snapshot_leaderboard(TimeMs::new(2000)).await.unwrap(), 0);
+    assert_eq!(ledger.snapshot_leaderboard(TimeMs::new(3000)).await.unwrap(), 14);
+
+    let uri = format!("/v1/leaderboard/history?user={}&metric=pnl", taker.as_str());
+    let (status, body) = request(test_app.app.clone(), &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["user"], taker.as_str());
+    let standings = v["standings"].as_array().unwrap();
+    assert_eq!(standings.len(), 2);
+    assert_eq!(standings[0]["snapshotMs"], 2000);
+    assert_eq!(standings[0]["metric"], "pnl");
+    assert_eq!(standings[0]["rank"], 2);
+    assert_eq!(standings[0]["metricValue"], "1");
+    assert_eq!(standings[0]["entrants"], 2);
+    assert_eq!(standings[1]["snapshotMs"], 3000);
+    assert_eq!(standings[1]["rank"], 1);
+    assert_eq!(standings[1]["metricValue"], "6");
+    assert_eq!(standings[1]["tradeCount"], 4);
+
+    let uri = format!("/v1/leaderboard/history?user={}&fromMs=2500", taker.as_str());
+    let (status, body) = request(test_app.app.clone(), &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["standings"].as_array().unwrap().len(), 7);
+
+    let uri = format!("/v1/leaderboard/history?user={}&metric=nope", taker.as_str());
+    let (status, _) = request(test_app.app.clone(), &uri).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_leaderboard_builder_only_excludes_tainted_lifecycles() {
     let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
@@ -324,6 +541,35 @@ async fn test_leaderboard_builder_only_excludes_tainted_lifecycles() {
     assert_eq!(v[0]["tainted"], true);
 }
 
+#[tokio::test]
+async fn test_leaderboard_builder_only_fill_taint_mode() {
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let test_app = setup_test_app(vec![user.as_str().to_string()]).await;
+
+    let fills = vec![
+        fill(&user, &coin, 1000, 1, Side::Buy, "10", "1", "0", "0", Some("1")),
+        fill(&user, &coin, 2000, 2, Side::Sell, "10", "1", "0", "0", None),
+        fill(&user, &coin, 3000, 3, Side::Buy, "20", "1", "0", "0", Some("1")),
+        fill(&user, &coin, 4000, 4, Side::Sell, "20", "1", "0", "0", Some("1")),
+    ];
+    test_app.state.repo.insert_fills_batch(&fills).await.unwrap();
+
+    let (status, body) = request(
+        test_app.app.clone(),
+        "/v1/leaderboard?metric=volume&builderOnly=true&taintMode=fill&coin=BTC&fromMs=0&toMs=5000",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v[0]["metricValue"], "50");
+    assert_eq!(v[0]["tradeCount"], 3);
+    assert_eq!(v[0]["tainted"], true);
+    assert_eq!(v[0]["partialLifecycles"], 1);
+}
+
 #[tokio::test]
 async fn test_leaderboard_empty_users_returns_empty_array() {
     // No users configured
@@ -359,7 +605,7 @@ async fn test_leaderboard_invalid_metric_returns_bad_request() {
     assert!(v["error"]
         .as_str()
         .unwrap()
-        .contains("must be one of: volume, pnl, returnPct"));
+        .contains("must be one of: volume, pnl, returnPct, fees, feesPaid, builderFees, tradeCount"));
 }
 
 #[tokio::test]
@@ -0,0 +1,185 @@
+//! Tests for the embeddable `LedgerClient`.
+
+use hypesilico::client::{
+    LeaderboardMetric, LeaderboardRequest, LedgerClient, LedgerError, PnlRequest, StatsRequest,
+    TradesRequest,
+};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{
+    EquityResolver, HeuristicAttributor, MatchTolerances, StatsInterval,
+};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+const OTHER: &str = "0x2222222222222222222222222222222222222222";
+
+fn fill(user: &str, time_ms: i64, side: Side, px: &str, closed_pnl: &str) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(time_ms),
+        Some(time_ms),
+    )
+}
+
+async fn setup_client(datasource: MockDataSource) -> (LedgerClient, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![USER.to_string(), OTHER.to_string()],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(datasource), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let client = LedgerClient::from_parts(repo, config, orchestrator, equity_resolver);
+    (client, temp_dir)
+}
+
+#[tokio::test]
+async fn test_ingest_stores_fills_without_compiling() {
+    let datasource = MockDataSource::new().with_fills(vec![
+        fill(USER, 1_000, Side::Buy, "100", "0"),
+        fill(USER, 2_000, Side::Sell, "110", "10"),
+    ]);
+    let (client, _temp) = setup_client(datasource).await;
+    let user = Address::from_str(USER).unwrap();
+
+    let result = client
+        .ingest(&user, None, Some(TimeMs::new(0)), Some(TimeMs::new(3_000)))
+        .await
+        .unwrap();
+    assert_eq!(result.fills_new, 2);
+
+    let coin = Coin::new("BTC".to_string());
+    assert!(client
+        .repo()
+        .get_compile_state(&user, &coin)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_trades_pnl_and_leaderboard() {
+    let datasource = MockDataSource::new().with_fills(vec![
+        fill(USER, 1_000, Side::Buy, "100", "0"),
+        fill(USER, 2_000, Side::Sell, "110", "10"),
+        fill(OTHER, 1_500, Side::Buy, "100", "0"),
+    ]);
+    let (client, _temp) = setup_client(datasource).await;
+    let user = Address::from_str(USER).unwrap();
+
+    let trades = client.trades(&TradesRequest::new(user.clone())).await.unwrap();
+    assert_eq!(trades.trades.len(), 2);
+    assert_eq!(trades.trades[1].side, Side::Sell);
+    assert_eq!(trades.tainted, None);
+
+    let pnl = client.pnl(&PnlRequest::new(user.clone())).await.unwrap();
+    assert_eq!(pnl.realized_pnl, Decimal::from_str("10").unwrap());
+    assert_eq!(pnl.fees.fees_paid, Decimal::from_str("2").unwrap());
+    assert_eq!(pnl.trade_count, 2);
+
+    let rows = client
+        .leaderboard(&LeaderboardRequest::new(LeaderboardMetric::Pnl))
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].rank, 1);
+    assert_eq!(rows[0].user, user);
+    assert_eq!(rows[1].metric_value, Decimal::zero());
+}
+
+#[tokio::test]
+async fn test_inverted_window_is_rejected() {
+    let (client, _temp) = setup_client(MockDataSource::new()).await;
+    let mut request = PnlRequest::new(Address::from_str(USER).unwrap());
+    request.from_ms = Some(TimeMs::new(2_000));
+    request.to_ms = Some(TimeMs::new(1_000));
+
+    let err = client.pnl(&request).await.unwrap_err();
+    assert!(matches!(err, LedgerError::InvalidTimeRange));
+}
+
+#[tokio::test]
+async fn test_stats_and_equity_history() {
+    let datasource = MockDataSource::new().with_fills(vec![
+        fill(USER, 1_000, Side::Buy, "100", "0"),
+        fill(USER, 2_000, Side::Sell, "110", "10"),
+    ]);
+    let (client, _temp) = setup_client(datasource).await;
+    let user = Address::from_str(USER).unwrap();
+
+    let mut request = StatsRequest::new(user.clone());
+    request.interval = Some(StatsInterval::Day);
+    let stats = client.stats(&request).await.unwrap();
+    assert_eq!(stats.stats.trade_count, 2);
+    assert_eq!(stats.stats.volume, Decimal::from_str("210").unwrap());
+    assert_eq!(stats.tainted, None);
+    let buckets = stats.buckets.unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0].trade_count, 2);
+
+    let points = client.equity_history(&user, None, None, None).await.unwrap();
+    let (last_ms, last_equity) = points.last().cloned().unwrap();
+    assert_eq!(last_ms, TimeMs::new(2_000));
+    assert_eq!(last_equity, Decimal::from_str("10").unwrap());
+}
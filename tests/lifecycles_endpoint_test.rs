@@ -0,0 +1,219 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, FillType, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_lifecycles_report_closed_and_open_trades() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, Side::Buy, "1", "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, Side::Buy, "1", "110", "0", 2))
+        .await
+        .unwrap();
+    // Closes the 2 long and flips into a 1 short.
+    repo.insert_fill(&fill(5000, Side::Sell, "3", "120", "30", 3))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/lifecycles?user={}&coin=BTC", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let lifecycles = body["lifecycles"].as_array().unwrap();
+    assert_eq!(lifecycles.len(), 2);
+
+    let long = &lifecycles[0];
+    assert_eq!(long["direction"], "long");
+    assert_eq!(long["openMs"], 1000);
+    assert_eq!(long["closeMs"], 5000);
+    assert_eq!(long["durationMs"], 4000);
+    assert_eq!(long["maxSize"], "2");
+    assert_eq!(long["entryPx"], "105");
+    assert_eq!(long["exitPx"], "120");
+    assert_eq!(long["realizedPnl"], "30");
+    assert_eq!(long["tradeCount"], 3);
+
+    let short = &lifecycles[1];
+    assert_eq!(short["direction"], "short");
+    assert_eq!(short["openMs"], 5000);
+    assert_eq!(short["maxSize"], "1");
+    assert_eq!(short["entryPx"], "120");
+    assert!(short.get("closeMs").is_none());
+    assert!(short.get("exitPx").is_none());
+    assert_eq!(short["tradeCount"], 1);
+    // The flip fill's fee is split between the two lifecycles.
+    assert_eq!(
+        Decimal::from_str(long["feesPaid"].as_str().unwrap()).unwrap()
+            + Decimal::from_str(short["feesPaid"].as_str().unwrap()).unwrap(),
+        Decimal::from_str("0.3").unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_lifecycles_builder_only_drops_tainted() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, Side::Buy, "1", "100", "0", 1))
+        .await
+        .unwrap();
+
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/lifecycles?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["lifecycles"][0]["tainted"], true);
+    assert!(body.get("tainted").is_none());
+
+    let (_, body) = get_json(app, format!("/v1/lifecycles?user={}&builderOnly=true", USER)).await;
+    assert_eq!(body["lifecycles"].as_array().unwrap().len(), 0);
+    assert_eq!(body["tainted"], true);
+}
+
+#[tokio::test]
+async fn test_lifecycles_rejects_invalid_user() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = get_json(app, "/v1/lifecycles?user=nope".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_ADDRESS");
+}
+
+#[tokio::test]
+async fn test_lifecycles_flag_liquidation_close() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, Side::Buy, "1", "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, Side::Sell, "1", "80", "-20", 2).with_fill_type(FillType::Liquidation))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, Side::Buy, "1", "90", "0", 3))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/lifecycles?user={}&coin=BTC", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let lifecycles = body["lifecycles"].as_array().unwrap();
+    assert_eq!(lifecycles.len(), 2);
+
+    assert_eq!(lifecycles[0]["liquidated"], true);
+    assert_eq!(lifecycles[0]["closedBy"], "liquidation");
+    assert_eq!(lifecycles[1]["liquidated"], false);
+    assert!(lifecycles[1].get("closedBy").is_none());
+}
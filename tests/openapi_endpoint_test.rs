@@ -0,0 +1,205 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::api::auth::{ApiKeyConfig, ApiScope};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{api, Repository};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+const KEY: &str = "read-key";
+
+async fn setup_test_app(api_keys: Vec<ApiKeyConfig>) -> (axum::Router, Arc<Repository>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys,
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    (api::create_router(state), repo, temp_dir)
+}
+
+async fn get(app: &axum::Router, uri: &str) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("x-api-key", KEY)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn spec(app: &axum::Router) -> Value {
+    let (status, body) = get(app, "/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_str(&body).unwrap()
+}
+
+/// Property names of a component schema, following `allOf` (used for flattened fields).
+fn properties(spec: &Value, schema: &str) -> BTreeSet<String> {
+    fn collect(spec: &Value, schema: &Value, out: &mut BTreeSet<String>) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.rsplit('/').next().unwrap();
+            collect(spec, &spec["components"]["schemas"][name], out);
+        }
+        if let Some(props) = schema["properties"].as_object() {
+            out.extend(props.keys().cloned());
+        }
+        for part in schema["allOf"].as_array().into_iter().flatten() {
+            collect(spec, part, out);
+        }
+    }
+    let mut out = BTreeSet::new();
+    collect(spec, &spec["components"]["schemas"][schema], &mut out);
+    out
+}
+
+fn keys(value: &Value) -> BTreeSet<String> {
+    value.as_object().unwrap().keys().cloned().collect()
+}
+
+#[tokio::test]
+async fn test_spec_documents_query_endpoints() {
+    let (app, _repo, _temp) = setup_test_app(vec![]).await;
+    let spec = spec(&app).await;
+
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    for path in [
+        "/v1/trades",
+        "/v1/pnl",
+        "/v1/positions/history",
+        "/v1/positions/open",
+        "/v1/leaderboard",
+        "/v1/risk",
+    ] {
+        assert!(spec["paths"][path]["get"].is_object(), "{} missing", path);
+    }
+
+    let params: Vec<&str> = spec["paths"]["/v1/pnl"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert!(params.contains(&"fromMs") && params.contains(&"taintMode"));
+
+    let error = &spec["paths"]["/v1/trades"]["get"]["responses"]["default"];
+    assert_eq!(
+        error["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/ErrorBody"
+    );
+    assert!(properties(&spec, "RiskResponse").contains("crossMarginSummary"));
+}
+
+#[tokio::test]
+async fn test_responses_match_documented_schemas() {
+    let (app, repo, _temp) = setup_test_app(vec![]).await;
+    let spec = spec(&app).await;
+    repo.insert_fill(&Fill::new(
+        TimeMs::new(1_000),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(1),
+        Some(1),
+    ))
+    .await
+    .unwrap();
+
+    let (_, body) = get(&app, &format!("/v1/trades?user={}", USER)).await;
+    let trades: Value = serde_json::from_str(&body).unwrap();
+    assert!(keys(&trades).is_subset(&properties(&spec, "TradesResponse")));
+    assert!(keys(&trades["trades"][0]).is_subset(&properties(&spec, "TradeDto")));
+
+    let (_, body) = get(&app, &format!("/v1/pnl?user={}&builderOnly=true", USER)).await;
+    let pnl: Value = serde_json::from_str(&body).unwrap();
+    assert!(keys(&pnl).is_subset(&properties(&spec, "PnlResponse")));
+
+    let (_, body) = get(&app, "/v1/trades?user=invalid").await;
+    let error: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(keys(&error), properties(&spec, "ErrorBody"));
+}
+
+#[tokio::test]
+async fn test_docs_are_public_when_keys_are_required() {
+    let (app, _repo, _temp) = setup_test_app(vec![ApiKeyConfig {
+        key: "other-key".to_string(),
+        scope: ApiScope::Read,
+        requests_per_minute: 100,
+    }])
+    .await;
+
+    spec(&app).await;
+    let (status, body) = get(&app, "/docs/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("swagger"), "{}", body);
+
+    let (status, _) = get(&app, &format!("/v1/trades?user={}", USER)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
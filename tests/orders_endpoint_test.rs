@@ -0,0 +1,169 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, px: &str, tid: i64, oid: Option<i64>) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::zero(),
+        None,
+        Some(tid),
+        oid,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_orders_group_fills_by_oid_and_twap() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fills_batch(&[
+        fill(1000, Side::Buy, "1", "100", 1, Some(7)),
+        fill(1500, Side::Sell, "1", "200", 2, Some(8)).with_twap_id(3),
+        fill(2000, Side::Buy, "3", "104", 3, Some(7)),
+        fill(2500, Side::Sell, "1", "210", 4, Some(9)).with_twap_id(3),
+        fill(3000, Side::Buy, "1", "50", 5, None),
+    ])
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/orders?user={}&coin=BTC", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let orders = body["orders"].as_array().unwrap();
+    assert_eq!(orders.len(), 3);
+
+    assert_eq!(orders[0]["kind"], "order");
+    assert_eq!(orders[0]["oid"], 7);
+    assert_eq!(orders[0]["side"], "buy");
+    assert_eq!(orders[0]["firstFillMs"], 1000);
+    assert_eq!(orders[0]["lastFillMs"], 2000);
+    assert_eq!(orders[0]["size"], "4");
+    assert_eq!(orders[0]["avgPx"], "103");
+    assert_eq!(orders[0]["feesPaid"], "0.2");
+    assert_eq!(orders[0]["fillCount"], 2);
+
+    assert_eq!(orders[1]["kind"], "twap");
+    assert_eq!(orders[1]["twapId"], 3);
+    assert!(orders[1].get("oid").is_none());
+    assert_eq!(orders[1]["avgPx"], "205");
+    assert_eq!(orders[1]["fillCount"], 2);
+
+    assert_eq!(orders[2]["kind"], "fill");
+    assert!(orders[2]["fillKey"].is_string());
+    assert_eq!(orders[2]["fillCount"], 1);
+}
+
+#[tokio::test]
+async fn test_orders_rejects_inverted_range() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) =
+        get_json(app, format!("/v1/orders?user={}&fromMs=2000&toMs=1000", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "INVALID_TIME_RANGE");
+}
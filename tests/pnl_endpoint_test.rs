@@ -1,12 +1,13 @@
 use axum::http::StatusCode;
 use hypesilico::api::{self, AppState};
 use hypesilico::compile::Compiler;
-use hypesilico::config::{BuilderAttributionMode, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
-use hypesilico::domain::{Address, Coin, Decimal, Deposit, Fill, Side, TimeMs};
-use hypesilico::engine::EquityResolver;
+use hypesilico::domain::{Address, Coin, Decimal, Deposit, Fill, LedgerKind, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use hypesilico::{Config, Repository};
 use std::str::FromStr;
@@ -23,13 +24,41 @@ struct TestApp {
 fn test_config(pnl_mode: PnlMode) -> Config {
     Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: ":memory:".to_string(),
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
-        target_builder: "0x0".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     }
 }
 
@@ -118,6 +147,7 @@ async fn test_pnl_response_has_required_fields() {
             time_ms: TimeMs::new(0),
             amount: Decimal::from_str("10000").unwrap(),
             tx_hash: None,
+            kind: LedgerKind::Deposit,
         })
         .await
         .unwrap();
@@ -155,6 +185,7 @@ async fn test_pnl_response_has_required_fields() {
     assert!(v["realizedPnl"].is_string());
     assert!(v["returnPct"].is_string());
     assert!(v["feesPaid"].is_string());
+    assert!(v["rebatesReceived"].is_string());
     assert!(v["tradeCount"].is_i64());
 }
 
@@ -219,7 +250,7 @@ async fn test_pnl_calculation_matches_manual_math_gross_mode() {
 
 #[tokio::test]
 async fn test_pnl_net_mode_subtracts_fees() {
-    let test_app = setup_test_app(PnlMode::Net).await;
+    let test_app = setup_test_app(PnlMode::NetFees).await;
 
     let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
     let coin = Coin::new("BTC".to_string());
@@ -257,6 +288,69 @@ async fn test_pnl_net_mode_subtracts_fees() {
     assert_eq!(v["realizedPnl"], "95");
 }
 
+#[tokio::test]
+async fn test_pnl_net_mode_adds_back_rebates() {
+    let test_app = setup_test_app(PnlMode::NetFees).await;
+
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    // Maker entry earns a rebate, taker exit pays a fee.
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&user, &coin, 1000, 1, Side::Buy, "50000", "1", "-2", "0", None),
+            fill(&user, &coin, 2000, 2, Side::Sell, "50100", "1", "3", "100", None),
+        ])
+        .await
+        .unwrap();
+
+    let (status, body) = request(
+        test_app.app.clone(),
+        "/v1/pnl?user=0x0000000000000000000000000000000000000123",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["feesPaid"], "1");
+    assert_eq!(v["rebatesReceived"], "2");
+    assert_eq!(v["realizedPnl"], "99");
+}
+
+#[tokio::test]
+async fn test_pnl_rebates_can_exceed_fees() {
+    let test_app = setup_test_app(PnlMode::NetFees).await;
+
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&user, &coin, 1000, 1, Side::Buy, "50000", "1", "-2", "0", None),
+            fill(&user, &coin, 2000, 2, Side::Sell, "50000", "1", "-2", "0", None),
+            fill(&user, &coin, 3000, 3, Side::Buy, "50000", "1", "0", "0", None),
+        ])
+        .await
+        .unwrap();
+
+    let (status, body) = request(
+        test_app.app.clone(),
+        "/v1/pnl?user=0x0000000000000000000000000000000000000123",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["feesPaid"], "-4");
+    assert_eq!(v["rebatesReceived"], "4");
+    assert_eq!(v["realizedPnl"], "4");
+    assert_eq!(v["tradeCount"], 3);
+}
+
 #[tokio::test]
 async fn test_return_pct_uses_equity_at_from_ms() {
     let test_app = setup_test_app(PnlMode::Gross).await;
@@ -273,6 +367,7 @@ async fn test_return_pct_uses_equity_at_from_ms() {
             time_ms: TimeMs::new(0),
             amount: Decimal::from_str("10000").unwrap(),
             tx_hash: None,
+            kind: LedgerKind::Deposit,
         })
         .await
         .unwrap();
@@ -351,6 +446,7 @@ async fn test_return_pct_respects_max_start_capital() {
             time_ms: TimeMs::new(0),
             amount: Decimal::from_str("100000").unwrap(),
             tx_hash: None,
+            kind: LedgerKind::Deposit,
         })
         .await
         .unwrap();
@@ -464,6 +560,141 @@ async fn test_builder_only_excludes_tainted_lifecycles() {
     assert_eq!(v["tainted"], true);
 }
 
+#[tokio::test]
+async fn test_builder_only_fill_taint_mode_keeps_attributed_fills() {
+    let test_app = setup_test_app(PnlMode::Gross).await;
+
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    let fills = vec![
+        fill(&user, &coin, 1000, 1, Side::Buy, "50000", "2", "1", "0", Some("1")),
+        fill(&user, &coin, 2000, 2, Side::Sell, "50100", "1", "1", "100", Some("1")),
+        fill(&user, &coin, 3000, 3, Side::Sell, "49900", "1", "1", "-100", None),
+    ];
+    test_app.state.repo.insert_fills_batch(&fills).await.unwrap();
+    Compiler::compile_incremental(&test_app.state.repo, &user, &coin)
+        .await
+        .unwrap();
+
+    let (status, body) = request(
+        test_app.app.clone(),
+        "/v1/pnl?user=0x0000000000000000000000000000000000000123&builderOnly=true&taintMode=fill",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["realizedPnl"], "100");
+    assert_eq!(v["feesPaid"], "2");
+    assert_eq!(v["tradeCount"], 2);
+    assert_eq!(v["tainted"], true);
+    assert_eq!(v["partialLifecycles"], 1);
+
+    // The default lifecycle mode drops the whole lifecycle and omits the count.
+    let (_, body) = request(
+        test_app.app.clone(),
+        "/v1/pnl?user=0x0000000000000000000000000000000000000123&builderOnly=true",
+    )
+    .await;
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["tradeCount"], 0);
+    assert!(v.get("partialLifecycles").is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_taint_mode_returns_bad_request() {
+    let test_app = setup_test_app(PnlMode::Gross).await;
+
+    let (status, body) = request(
+        test_app.app,
+        "/v1/pnl?user=0x0000000000000000000000000000000000000123&builderOnly=true&taintMode=trade",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(v["error"]
+        .as_str()
+        .unwrap()
+        .contains("taintMode must be one of: lifecycle, fill"));
+}
+
+#[tokio::test]
+async fn test_return_method_selects_twr_or_irr() {
+    let test_app = setup_test_app(PnlMode::Gross).await;
+
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    // 1000 earns 100, then a 9000 deposit lands before 100 more.
+    for (key, time_ms, amount) in [("dep:1", 0, "1000"), ("dep:2", 1500, "9000")] {
+        test_app
+            .state
+            .repo
+            .insert_deposit(&Deposit {
+                event_key: key.to_string(),
+                user: user.clone(),
+                time_ms: TimeMs::new(time_ms),
+                amount: Decimal::from_str(amount).unwrap(),
+                tx_hash: None,
+                kind: LedgerKind::Deposit,
+            })
+            .await
+            .unwrap();
+    }
+    let fills = [
+        (1000, 1, Side::Buy, "1000", "0"),
+        (1000, 2, Side::Sell, "1100", "100"),
+        (2000, 3, Side::Buy, "1000", "0"),
+        (2000, 4, Side::Sell, "1010", "100"),
+    ];
+    for (time_ms, tid, side, px, closed_pnl) in fills {
+        test_app
+            .state
+            .repo
+            .insert_fill(&fill(&user, &coin, time_ms, tid, side, px, "1", "0", closed_pnl, None))
+            .await
+            .unwrap();
+    }
+    Compiler::compile_incremental(&test_app.state.repo, &user, &coin)
+        .await
+        .unwrap();
+
+    let base = "/v1/pnl?user=0x0000000000000000000000000000000000000123";
+    let return_pct = |body: &[u8]| -> String {
+        let v: serde_json::Value = serde_json::from_slice(body).unwrap();
+        v["returnPct"].as_str().unwrap().to_string()
+    };
+
+    let (status, body) = request(test_app.app.clone(), base).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(return_pct(&body), "2");
+
+    // 10% on 1100 equity compounded with 100 on 10100.
+    let (status, body) = request(test_app.app.clone(), &format!("{}&returnMethod=twr", base)).await;
+    assert_eq!(status, StatusCode::OK);
+    let twr = Decimal::from_str(&return_pct(&body)).unwrap();
+    let expected = (Decimal::from_str("1.1").unwrap()
+        * (Decimal::from_str("10200").unwrap() / Decimal::from_str("10100").unwrap())
+        - Decimal::from_str("1").unwrap())
+        * Decimal::hundred();
+    assert_eq!(twr, expected);
+
+    let (status, body) = request(test_app.app.clone(), &format!("{}&returnMethod=IRR", base)).await;
+    assert_eq!(status, StatusCode::OK);
+    let irr = Decimal::from_str(&return_pct(&body)).unwrap();
+    assert!(irr > Decimal::from_str("2").unwrap() && irr < twr, "irr {}", irr);
+
+    let (status, body) = request(test_app.app, &format!("{}&returnMethod=dietz", base)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(v["error"]
+        .as_str()
+        .unwrap()
+        .contains("returnMethod must be one of: simple, twr, irr"));
+}
+
 #[tokio::test]
 async fn test_pnl_response_deterministic() {
     let test_app = setup_test_app(PnlMode::Gross).await;
@@ -480,6 +711,7 @@ async fn test_pnl_response_deterministic() {
             time_ms: TimeMs::new(0),
             amount: Decimal::from_str("10000").unwrap(),
             tx_hash: None,
+            kind: LedgerKind::Deposit,
         })
         .await
         .unwrap();
@@ -519,3 +751,50 @@ async fn test_pnl_response_deterministic() {
 
     assert_eq!(body1, body2, "Responses must be byte-identical");
 }
+
+#[tokio::test]
+async fn test_pnl_mode_selected_per_request() {
+    let test_app = setup_test_app(PnlMode::Gross).await;
+
+    let user = Address::new("0x0000000000000000000000000000000000000123".to_string());
+    let coin = Coin::new("BTC".to_string());
+
+    // The sell flips the long into a short; its builder fee must count once.
+    test_app
+        .state
+        .repo
+        .insert_fills_batch(&[
+            fill(&user, &coin, 1000, 1, Side::Buy, "100", "1", "0", "0", Some("0.5")),
+            fill(&user, &coin, 2000, 2, Side::Sell, "110", "2", "1", "10", Some("0.5")),
+        ])
+        .await
+        .unwrap();
+
+    let base = "/v1/pnl?user=0x0000000000000000000000000000000000000123";
+    for (mode, realized, formula) in [
+        ("", "10", "realizedPnl = sum(closedPnl)"),
+        ("&pnlMode=netFees", "9", "realizedPnl = sum(closedPnl) - feesPaid"),
+        (
+            "&pnlMode=netAllFees",
+            "8",
+            "realizedPnl = sum(closedPnl) - feesPaid - builderFeesPaid",
+        ),
+    ] {
+        let (status, body) = request(test_app.app.clone(), &format!("{}{}", base, mode)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["realizedPnl"], realized, "{}", mode);
+        assert_eq!(v["builderFeesPaid"], "1");
+        assert_eq!(v["builderFees"], "1");
+        assert_eq!(v["pnlFormula"], formula);
+    }
+
+    let (status, body) = request(test_app.app.clone(), &format!("{}&pnlMode=net", base)).await;
+    assert_eq!(status, StatusCode::OK);
+    let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(v["pnlMode"], "netFees");
+
+    let (status, _) = request(test_app.app.clone(), &format!("{}&pnlMode=bogus", base)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -1,5 +1,5 @@
 use hypesilico::engine::{EffectType, PositionTracker};
-use hypesilico::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::{Address, Coin, Decimal, Fill, FillType, Side, TimeMs};
 
 fn d(s: &str) -> Decimal {
     Decimal::from_str_canonical(s).unwrap()
@@ -173,3 +173,41 @@ fn test_short_open_add_partial_close_then_close() {
     assert_eq!(effects[2].effect_type, EffectType::Close);
     assert_eq!(effects[3].effect_type, EffectType::Close);
 }
+
+#[test]
+fn test_forced_close_marks_lifecycle() {
+    let mut tracker = PositionTracker::new();
+
+    tracker.process_fill(&buy("2", "100", 1000, 1));
+    tracker.process_fill(&sell("1", "90", 2000, 2).with_fill_type(FillType::Adl));
+    // A partial forced close leaves the lifecycle open and unmarked.
+    assert_eq!(tracker.get_lifecycles()[0].closed_by, None);
+
+    // A liquidation that flips only marks the lifecycle it closed.
+    tracker.process_fill(&sell("3", "80", 3000, 3).with_fill_type(FillType::Liquidation));
+    tracker.process_fill(&buy("2", "85", 4000, 4));
+
+    let lifecycles = tracker.get_lifecycles();
+    assert_eq!(lifecycles.len(), 2);
+    assert_eq!(lifecycles[0].closed_by, Some(FillType::Liquidation));
+    assert_eq!(lifecycles[1].end_time_ms, Some(TimeMs::new(4000)));
+    assert_eq!(lifecycles[1].closed_by, None);
+}
+
+#[test]
+fn test_overflowing_fill_is_rejected_without_changing_state() {
+    let mut tracker = PositionTracker::new();
+    tracker.process_fill(&buy("1", "100", 1000, 1));
+
+    // Notional of 2 at the largest representable price does not fit.
+    let overflowing = buy("2", "79228162514264337593543950335", 2000, 2);
+    assert!(matches!(
+        tracker.try_process_fill(&overflowing),
+        Err(hypesilico::domain::DecimalError::Overflow(_))
+    ));
+
+    assert_eq!(tracker.state.net_size, d("1"));
+    assert_eq!(tracker.state.avg_entry_px, d("100"));
+    assert_eq!(tracker.get_effects().len(), 1);
+    assert_eq!(tracker.get_snapshots().len(), 1);
+}
@@ -0,0 +1,196 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        // Unroutable upstream so mark price lookups fail fast.
+        hyperliquid_api_url: "http://127.0.0.1:1".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, coin: &str, side: Side, sz: &str, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_positions_aging_sorts_by_age_by_default() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "0.1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, "ETH", Side::Sell, "5", "3000", 2))
+        .await
+        .unwrap();
+    // SOL is opened and closed, so it never shows up.
+    repo.insert_fill(&fill(2000, "SOL", Side::Buy, "10", "100", 3))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2500, "SOL", Side::Sell, "10", "110", 4))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/aging?user={}&asOfMs=10000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["asOfMs"], 10000);
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[0]["coin"], "BTC");
+    assert_eq!(positions[0]["ageMs"], 9000);
+    assert_eq!(positions[0]["lifecycleStartMs"], 1000);
+    assert_eq!(positions[0]["netSize"], "0.1");
+    assert_eq!(positions[0]["avgEntryPx"], "50000");
+    assert_eq!(positions[1]["coin"], "ETH");
+    assert_eq!(positions[1]["ageMs"], 7000);
+    assert_eq!(positions[1]["netSize"], "-5");
+}
+
+#[tokio::test]
+async fn test_positions_aging_sorts_by_absolute_size() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "0.1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, "ETH", Side::Sell, "5", "3000", 2))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/aging?user={}&asOfMs=10000&sortBy=size", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions[0]["coin"], "ETH");
+    assert_eq!(positions[1]["coin"], "BTC");
+}
+
+#[tokio::test]
+async fn test_positions_aging_rejects_unknown_sort() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) =
+        get_json(app, format!("/v1/positions/aging?user={}&sortBy=pnl", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "sortBy must be one of: age, size");
+}
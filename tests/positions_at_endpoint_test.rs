@@ -0,0 +1,274 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, MarkPrice, Side, TimeMs, MARK_PRICE_BUCKET_MS},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    setup_test_app_with(MockDataSource::new()).await
+}
+
+async fn setup_test_app_with(datasource: MockDataSource) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(datasource);
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://127.0.0.1:1".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, coin: &str, side: Side, sz: &str, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_positions_at_reconstructs_state_at_each_time() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    // BTC: open long, then add to it.
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Buy, "1", "52000", 2))
+        .await
+        .unwrap();
+    // ETH: open and fully close.
+    repo.insert_fill(&fill(1500, "ETH", Side::Buy, "2", "3000", 3))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2500, "ETH", Side::Sell, "2", "3100", 4))
+        .await
+        .unwrap();
+
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/positions/at?user={}&atMs=500", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["atMs"], 500);
+    assert!(body["positions"].as_array().unwrap().is_empty());
+
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/positions/at?user={}&atMs=1800", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("tainted").is_none(), "tainted must be omitted");
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[0]["coin"], "BTC");
+    assert_eq!(positions[0]["netSize"], "1");
+    assert_eq!(positions[0]["avgEntryPx"], "50000");
+    assert_eq!(positions[0]["lifecycleStartMs"], 1000);
+    assert_eq!(positions[0]["lastUpdateMs"], 1000);
+    assert!(positions[0].get("markPx").is_none());
+    assert_eq!(positions[1]["coin"], "ETH");
+    assert_eq!(positions[1]["netSize"], "2");
+
+    // ETH closes exactly at atMs.
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/positions/at?user={}&atMs=2500", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1, "closed ETH lifecycle must be excluded");
+    assert_eq!(positions[0]["coin"], "BTC");
+    assert_eq!(positions[0]["netSize"], "2");
+    assert_eq!(positions[0]["avgEntryPx"], "51000");
+    assert_eq!(positions[0]["lastUpdateMs"], 2000);
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/at?user={}&atMs=1800&coin=ETH", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0]["coin"], "ETH");
+}
+
+#[tokio::test]
+async fn test_positions_at_across_flip_reports_lifecycle_open_then() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Sell, "3", "51000", 2))
+        .await
+        .unwrap();
+
+    let (status, before) =
+        get_json(app.clone(), format!("/v1/positions/at?user={}&atMs=1999", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let before = before["positions"].as_array().unwrap();
+    assert_eq!(before.len(), 1);
+    assert_eq!(before[0]["netSize"], "1");
+
+    let (status, after) = get_json(app, format!("/v1/positions/at?user={}&atMs=2000", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let after = after["positions"].as_array().unwrap();
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0]["netSize"], "-2");
+    assert_eq!(after[0]["avgEntryPx"], "51000");
+    assert_eq!(after[0]["lifecycleStartMs"], 2000);
+    assert_ne!(after[0]["lifecycleId"], before[0]["lifecycleId"]);
+}
+
+#[tokio::test]
+async fn test_positions_at_include_unrealized_uses_historical_mark() {
+    const HOUR: i64 = MARK_PRICE_BUCKET_MS;
+    let mark = |time_ms: i64, px: &str| MarkPrice {
+        coin: Coin::new("BTC".to_string()),
+        time_ms: TimeMs::new(time_ms),
+        px: Decimal::from_str(px).unwrap(),
+    };
+    let datasource = MockDataSource::new().with_mark_prices(vec![
+        mark(0, "50000"),
+        mark(HOUR, "52000"),
+    ]);
+    let TestApp { app, repo, _temp } = setup_test_app_with(datasource).await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "2", "50000", 1))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app.clone(),
+        format!("/v1/positions/at?user={}&atMs={}&includeUnrealized=true", USER, HOUR + 5),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let btc = &body["positions"][0];
+    assert_eq!(btc["markPx"], "52000");
+    assert_eq!(btc["unrealizedPnl"], "4000");
+
+    // No mark stored for the third hour.
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/at?user={}&atMs={}&includeUnrealized=true", USER, 2 * HOUR + 5),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let btc = &body["positions"][0];
+    assert_eq!(btc["netSize"], "2");
+    assert!(btc.get("markPx").is_none());
+    assert!(btc.get("unrealizedPnl").is_none());
+}
+
+#[tokio::test]
+async fn test_positions_at_rejects_invalid_params() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app.clone(), format!("/v1/positions/at?user={}", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "atMs is required");
+
+    let (status, _) =
+        get_json(app.clone(), format!("/v1/positions/at?user={}&atMs=soon", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get_json(app, "/v1/positions/at?user=not-an-address&atMs=1".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
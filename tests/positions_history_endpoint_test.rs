@@ -1,13 +1,14 @@
 use axum::http::{Request, StatusCode};
 use hypesilico::datasource::MockDataSource;
-use hypesilico::engine::EquityResolver;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use hypesilico::{
     api,
-    config::{BuilderAttributionMode, Config, PnlMode},
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
     db::init_db,
-    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    domain::{Address, Coin, Decimal, Fill, MarkPrice, Side, TimeMs, MARK_PRICE_BUCKET_MS},
     DataSource, Repository,
 };
 use std::str::FromStr;
@@ -24,6 +25,10 @@ struct TestApp {
 }
 
 async fn setup_test_app() -> TestApp {
+    setup_test_app_with(MockDataSource::new()).await
+}
+
+async fn setup_test_app_with(datasource: MockDataSource) -> TestApp {
     let temp_dir = TempDir::new().unwrap();
     let db_path = temp_dir
         .path()
@@ -33,16 +38,44 @@ async fn setup_test_app() -> TestApp {
     let pool = init_db(&db_path).await.expect("init_db failed");
 
     let repo = Arc::new(Repository::new(pool));
-    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let datasource: Arc<dyn DataSource> = Arc::new(datasource);
     let config = Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: db_path,
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
         target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     };
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
@@ -183,6 +216,110 @@ async fn test_positions_history_filters_by_coin_and_time() {
     assert_eq!(snapshots[0]["timeMs"], 3000);
 }
 
+#[tokio::test]
+async fn test_positions_history_include_unrealized_marks_each_snapshot() {
+    const HOUR: i64 = MARK_PRICE_BUCKET_MS;
+    let mark = |time_ms: i64, px: &str| MarkPrice {
+        coin: Coin::new("BTC".to_string()),
+        time_ms: TimeMs::new(time_ms),
+        px: Decimal::from_str(px).unwrap(),
+    };
+    // No mark for the third hour.
+    let datasource = MockDataSource::new().with_mark_prices(vec![
+        mark(0, "49000"),
+        mark(HOUR, "52000"),
+        mark(3 * HOUR, "53000"),
+    ]);
+    let TestApp { app, repo, _temp } = setup_test_app_with(datasource).await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(HOUR + 1000, "BTC", Side::Buy, "1", "51000", 2))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2 * HOUR + 1000, "BTC", Side::Sell, "1", "52000", 3))
+        .await
+        .unwrap();
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let request = Request::builder()
+                .method("GET")
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+
+    let body = get(format!(
+        "/v1/positions/history?user={}&includeUnrealized=true",
+        USER
+    ))
+    .await;
+    let snapshots = body["snapshots"].as_array().unwrap();
+    assert_eq!(snapshots.len(), 3);
+    assert_eq!(snapshots[0]["markPx"], "49000");
+    assert_eq!(snapshots[0]["unrealizedPnl"], "-1000");
+    // 2 at an average of 50500.
+    assert_eq!(snapshots[1]["markPx"], "52000");
+    assert_eq!(snapshots[1]["unrealizedPnl"], "3000");
+    assert!(snapshots[2].get("markPx").is_none());
+    assert!(snapshots[2].get("unrealizedPnl").is_none());
+
+    let body = get(format!("/v1/positions/history?user={}", USER)).await;
+    assert!(body["snapshots"][0].get("markPx").is_none());
+}
+
+#[tokio::test]
+async fn test_positions_history_filters_by_dex() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "xyz:BTC", Side::Buy, "2", "50100", 2))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, "xyz:TSLA", Side::Buy, "5", "250", 3))
+        .await
+        .unwrap();
+
+    for (query, expected) in [
+        ("dex=xyz", vec!["xyz:BTC", "xyz:TSLA"]),
+        ("dex=xyz&limit=1", vec!["xyz:BTC"]),
+        ("dex=xyz&maxPoints=1", vec!["xyz:BTC", "xyz:TSLA"]),
+        ("dex=", vec!["BTC"]),
+    ] {
+        let request = Request::builder()
+            .method("GET")
+            .uri(format!("/v1/positions/history?user={}&{}", USER, query))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", query);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let coins: Vec<_> = body["snapshots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["coin"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(coins, expected, "{}", query);
+    }
+}
+
 #[tokio::test]
 async fn test_positions_history_response_deterministic() {
     let TestApp { app, repo, _temp } = setup_test_app().await;
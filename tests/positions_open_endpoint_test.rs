@@ -0,0 +1,292 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        // Unroutable upstream so mark price lookups fail fast.
+        hyperliquid_api_url: "http://127.0.0.1:1".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, coin: &str, side: Side, sz: &str, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_open_positions_returns_latest_snapshot_of_open_lifecycles() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    // BTC: open long, then add to it.
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Buy, "1", "52000", 2))
+        .await
+        .unwrap();
+    // ETH: open and fully close.
+    repo.insert_fill(&fill(1500, "ETH", Side::Buy, "2", "3000", 3))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2500, "ETH", Side::Sell, "2", "3100", 4))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/positions/open?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("tainted").is_none(), "tainted must be omitted");
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1, "closed ETH lifecycle must be excluded");
+
+    let btc = &positions[0];
+    assert_eq!(btc["coin"], "BTC");
+    assert_eq!(btc["netSize"], "2");
+    assert_eq!(btc["avgEntryPx"], "51000");
+    assert_eq!(btc["lifecycleStartMs"], 1000);
+    assert_eq!(btc["lastUpdateMs"], 2000);
+    assert!(btc["lifecycleId"].as_str().is_some());
+    assert!(btc.get("markPx").is_none());
+    assert!(btc.get("unrealizedPnl").is_none());
+    assert!(btc.get("tainted").is_none());
+}
+
+#[tokio::test]
+async fn test_open_positions_filters_by_dex() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "xyz:BTC", Side::Buy, "2", "50100", 2))
+        .await
+        .unwrap();
+
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/positions/open?user={}&dex=xyz", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0]["coin"], "xyz:BTC");
+    assert_eq!(positions[0]["netSize"], "2");
+
+    let (status, _) = get_json(
+        app,
+        format!("/v1/positions/open?user={}&coin=xyz:BTC&dex=", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_open_positions_after_flip_reports_new_lifecycle() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Sell, "3", "51000", 2))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/positions/open?user={}&coin=BTC", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0]["netSize"], "-2");
+    assert_eq!(positions[0]["avgEntryPx"], "51000");
+    assert_eq!(positions[0]["lifecycleStartMs"], 2000);
+}
+
+#[tokio::test]
+async fn test_open_positions_builder_only_excludes_tainted_lifecycles() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    let f1 = fill(1000, "BTC", Side::Buy, "1", "50000", 1);
+    let f2 = fill(2000, "BTC", Side::Buy, "1", "51000", 2);
+    let f3 = fill(1000, "ETH", Side::Buy, "1", "3000", 3);
+    for f in [&f1, &f2, &f3] {
+        repo.insert_fill(f).await.unwrap();
+    }
+
+    // Mixed attribution taints the BTC lifecycle; ETH is fully attributed.
+    repo.insert_attributions(&[
+        (
+            f1.fill_key.clone(),
+            true,
+            "heuristic".to_string(),
+            "low".to_string(),
+            None,
+        ),
+        (
+            f2.fill_key.clone(),
+            false,
+            "heuristic".to_string(),
+            "low".to_string(),
+            None,
+        ),
+        (
+            f3.fill_key.clone(),
+            true,
+            "heuristic".to_string(),
+            "low".to_string(),
+            None,
+        ),
+    ])
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/open?user={}&builderOnly=true", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tainted"], true);
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0]["coin"], "ETH");
+    assert_eq!(positions[0]["tainted"], false);
+}
+
+#[tokio::test]
+async fn test_open_positions_unrealized_omitted_when_marks_unavailable() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "50000", 1))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/open?user={}&includeUnrealized=true", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let positions = body["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert!(positions[0].get("markPx").is_none());
+    assert!(positions[0].get("unrealizedPnl").is_none());
+}
+
+#[tokio::test]
+async fn test_open_positions_rejects_invalid_user_address() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(app, "/v1/positions/open?user=0x123".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
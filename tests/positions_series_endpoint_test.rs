@@ -0,0 +1,222 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+/// BTC net size: +1 @1000, +3 @1500, flip to -2 @2500, -1 @2600, flat @7200.
+async fn seed(repo: &Repository) {
+    repo.insert_fills_batch(&[
+        fill(1000, Side::Buy, "1", 1),
+        fill(1500, Side::Buy, "2", 2),
+        fill(2500, Side::Sell, "5", 3),
+        fill(2600, Side::Buy, "1", 4),
+        fill(7200, Side::Buy, "1", 5),
+    ])
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_positions_series_buckets_ohlc() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+
+    let (status, body) = get_json(
+        app,
+        format!(
+            "/v1/positions/series?user={}&coin=BTC&intervalMs=2000",
+            USER
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["coin"], "BTC");
+    assert_eq!(body["intervalMs"], 2000);
+
+    let buckets = body["buckets"].as_array().unwrap();
+    let ohlc: Vec<(i64, &str, &str, &str, &str, u64)> = buckets
+        .iter()
+        .map(|b| {
+            (
+                b["startMs"].as_i64().unwrap(),
+                b["open"].as_str().unwrap(),
+                b["high"].as_str().unwrap(),
+                b["low"].as_str().unwrap(),
+                b["close"].as_str().unwrap(),
+                b["snapshotCount"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    // The flip writes a flat snapshot and an opening one, so the 2000 bucket has 3.
+    // Buckets 4000 is empty and omitted.
+    assert_eq!(
+        ohlc,
+        vec![
+            (0, "0", "3", "0", "3", 2),
+            (2000, "3", "3", "-2", "-1", 3),
+            (6000, "-1", "0", "-1", "0", 1),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_positions_series_window_carries_in_prior_size() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    seed(&repo).await;
+
+    let (status, body) = get_json(
+        app,
+        format!(
+            "/v1/positions/series?user={}&coin=BTC&intervalMs=1000&fromMs=2000&toMs=3000",
+            USER
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let buckets = body["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0]["startMs"], 2000);
+    assert_eq!(buckets[0]["open"], "3");
+    assert_eq!(buckets[0]["low"], "-2");
+    assert_eq!(buckets[0]["close"], "-1");
+}
+
+#[tokio::test]
+async fn test_positions_series_validates_params() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    for uri in [
+        format!("/v1/positions/series?user={}&intervalMs=1000", USER),
+        format!("/v1/positions/series?user={}&coin=BTC", USER),
+        format!("/v1/positions/series?user={}&coin=BTC&intervalMs=0", USER),
+        format!(
+            "/v1/positions/series?user={}&coin=BTC&intervalMs=1000&fromMs=5&toMs=1",
+            USER
+        ),
+        "/v1/positions/series?user=0x123&coin=BTC&intervalMs=1000".to_string(),
+    ] {
+        let (status, _) = get_json(app.clone(), uri.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+    }
+}
@@ -0,0 +1,257 @@
+use axum::http::{Method, StatusCode};
+use hypesilico::api;
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::{check_read_only_manifest, init_db, open_db_read_only, SelfCheckError};
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<hypesilico::Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(datasource: Arc<MockDataSource>, read_only: bool) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(hypesilico::Repository::new(pool));
+
+    TestApp {
+        app: router(repo.clone(), datasource, db_path, read_only),
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn router(
+    repo: Arc<hypesilico::Repository>,
+    datasource: Arc<MockDataSource>,
+    db_path: String,
+    read_only: bool,
+) -> axum::Router {
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+    api::create_router(state)
+}
+
+fn fill(time_ms: i64, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        Side::Buy,
+        Decimal::from_str("50000").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str("5").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        Some(tid),
+    )
+}
+
+async fn request(
+    app: axum::Router,
+    method: Method,
+    uri: &str,
+    body: &str,
+) -> (StatusCode, serde_json::Value) {
+    let req = axum::http::Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_read_only_serves_stored_fills_without_ingesting() {
+    let datasource = Arc::new(MockDataSource::new().with_fill(fill(2000, 2)));
+    let uri = format!("/v1/trades?user={}", USER);
+
+    let test_app = setup_test_app(datasource.clone(), true).await;
+    test_app.repo.insert_fill(&fill(1000, 1)).await.unwrap();
+    let (status, json) = request(test_app.app, Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["trades"].as_array().unwrap().len(), 1);
+    assert_eq!(json["trades"][0]["timeMs"], 1000);
+
+    let test_app = setup_test_app(datasource, false).await;
+    test_app.repo.insert_fill(&fill(1000, 1)).await.unwrap();
+    let (status, json) = request(test_app.app, Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["trades"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_read_only_rejects_writes() {
+    let test_app = setup_test_app(Arc::new(MockDataSource::new()), true).await;
+
+    for (method, uri, body) in [
+        (Method::POST, "/v1/ingest/fills".to_string(), "[]"),
+        (Method::PUT, "/v1/admin/accounts/desk".to_string(), r#"{"addresses":[]}"#),
+        (Method::POST, "/v1/admin/maintenance".to_string(), "{}"),
+        (
+            Method::POST,
+            "/v1/admin/replay".to_string(),
+            &format!(r#"{{"user":"{}","apply":true}}"#, USER),
+        ),
+    ] {
+        let (status, json) = request(test_app.app.clone(), method, &uri, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_eq!(json["code"], "READ_ONLY", "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_allows_queries_and_dry_runs() {
+    let test_app = setup_test_app(Arc::new(MockDataSource::new()), true).await;
+
+    let (status, _) = request(
+        test_app.app.clone(),
+        Method::POST,
+        "/v1/batch/pnl",
+        &format!(r#"{{"users":["{}"]}}"#, USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, json) = request(
+        test_app.app,
+        Method::POST,
+        "/v1/admin/replay",
+        &format!(r#"{{"user":"{}"}}"#, USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", json);
+}
+
+#[tokio::test]
+async fn test_read_only_database_serves_stored_rows_and_refuses_writes() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let writer = hypesilico::Repository::new(init_db(&db_path).await.unwrap());
+    writer.insert_fill(&fill(1000, 1)).await.unwrap();
+    writer.close().await.unwrap();
+
+    // Opened as startup does with READ_ONLY: no migrations, read-only connections.
+    check_read_only_manifest(&db_path).await.unwrap();
+    let (pool, read_pool) = open_db_read_only(&db_path, &Default::default())
+        .await
+        .unwrap();
+    let mut repo = hypesilico::Repository::new(pool);
+    if let Some(read_pool) = read_pool {
+        repo = repo.with_read_pool(read_pool);
+    }
+    let repo = Arc::new(repo);
+    let app = router(repo.clone(), Arc::new(MockDataSource::new()), db_path, true);
+
+    let uri = format!("/v1/trades?user={}", USER);
+    let (status, json) = request(app.clone(), Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["trades"].as_array().unwrap().len(), 1);
+    let uri = format!("/v1/positions/history?user={}", USER);
+    let (status, _) = request(app.clone(), Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+
+    for (method, uri, body) in [
+        (Method::POST, "/v1/ingest/fills", "[]"),
+        (Method::PUT, "/v1/admin/accounts/desk", r#"{"addresses":[]}"#),
+    ] {
+        let (status, json) = request(app.clone(), method, uri, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_eq!(json["code"], "READ_ONLY", "{}", uri);
+    }
+
+    // Writes that bypass the API fail at the connection instead of reaching the file.
+    assert!(repo.insert_fill(&fill(2000, 2)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_read_only_refuses_other_schema_version() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.unwrap();
+    sqlx::query("UPDATE instance_metadata SET value = '1' WHERE key = 'schema_version'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    let err = check_read_only_manifest(&db_path).await.unwrap_err();
+    assert!(matches!(err, SelfCheckError::SchemaMismatch { recorded: 1, .. }), "{}", err);
+
+    let missing = temp_dir.path().join("missing.db");
+    assert!(check_read_only_manifest(&missing.to_string_lossy()).await.is_err());
+}
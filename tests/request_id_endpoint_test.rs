@@ -0,0 +1,131 @@
+use axum::http::StatusCode;
+use hypesilico::api;
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+
+async fn setup_test_app() -> (axum::Router, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(hypesilico::Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+    (api::create_router(state), temp_dir)
+}
+
+async fn send(app: axum::Router, uri: &str, request_id: Option<&str>) -> axum::response::Response {
+    let mut req = axum::http::Request::builder().method("GET").uri(uri);
+    if let Some(id) = request_id {
+        req = req.header("x-request-id", id);
+    }
+    app.oneshot(req.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn response_id(resp: &axum::response::Response) -> String {
+    resp.headers()
+        .get("x-request-id")
+        .expect("missing X-Request-Id")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_generates_request_id_when_absent() {
+    let (app, _temp) = setup_test_app().await;
+    let uri = format!("/v1/pnl?user={}", USER);
+
+    let first = send(app.clone(), &uri, None).await;
+    let second = send(app, &uri, None).await;
+    assert_eq!(first.status(), StatusCode::OK);
+
+    let first_id = response_id(&first);
+    assert!(uuid::Uuid::parse_str(&first_id).is_ok(), "{}", first_id);
+    assert_ne!(first_id, response_id(&second));
+}
+
+#[tokio::test]
+async fn test_reuses_caller_request_id() {
+    let (app, _temp) = setup_test_app().await;
+
+    let resp = send(app, "/health", Some("lb-7f3a.42")).await;
+    assert_eq!(response_id(&resp), "lb-7f3a.42");
+}
+
+#[tokio::test]
+async fn test_replaces_malformed_request_id() {
+    let (app, _temp) = setup_test_app().await;
+
+    let resp = send(app, "/health", Some("not a valid id")).await;
+    let id = response_id(&resp);
+    assert_ne!(id, "not a valid id");
+    assert!(uuid::Uuid::parse_str(&id).is_ok());
+}
+
+#[tokio::test]
+async fn test_error_responses_carry_request_id() {
+    let (app, _temp) = setup_test_app().await;
+
+    let resp = send(app, "/v1/trades?user=invalid", Some("req-1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response_id(&resp), "req-1");
+}
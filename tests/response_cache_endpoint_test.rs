@@ -0,0 +1,194 @@
+use axum::http::StatusCode;
+use hypesilico::api::{self, AppState};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::db::init_db;
+use hypesilico::domain::{Address, Coin, Decimal, Fill, Side, TimeMs};
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::Repository;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x1111111111111111111111111111111111111111";
+
+struct TestApp {
+    app: axum::Router,
+    state: AppState,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(response_cache_ttl_ms: u64) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+    let repo = Arc::new(Repository::new(pool));
+
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Heuristic,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![USER.to_string()],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(Arc::new(MockDataSource::new()), repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = AppState::new(repo, config, orchestrator, equity_resolver);
+    let app = api::create_router(state.clone());
+
+    TestApp {
+        app,
+        state,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(tid: i64, side: Side) -> Fill {
+    Fill::new(
+        TimeMs::new(1_000 * tid),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str("100").unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        Some(tid),
+    )
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let req = axum::http::Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_cached_until_new_fills_are_compiled() {
+    let test_app = setup_test_app(60_000).await;
+    let repo = &test_app.state.repo;
+    let pnl = format!("/v1/pnl?user={}&fromMs=0", USER);
+    let leaderboard = "/v1/leaderboard?metric=volume";
+
+    repo.insert_fill(&fill(1, Side::Buy)).await.unwrap();
+    assert_eq!(get_json(&test_app.app, &pnl).await["tradeCount"], 1);
+    assert_eq!(get_json(&test_app.app, leaderboard).await[0]["tradeCount"], 1);
+    // The first responses compiled the fill, so they were computed against an older
+    // generation; these populate the cache.
+    get_json(&test_app.app, &pnl).await;
+    get_json(&test_app.app, leaderboard).await;
+
+    // A fill stored without compiling is invisible to cached responses.
+    repo.insert_fill(&fill(2, Side::Sell)).await.unwrap();
+    assert_eq!(get_json(&test_app.app, &pnl).await["tradeCount"], 1);
+    assert_eq!(get_json(&test_app.app, leaderboard).await[0]["tradeCount"], 1);
+
+    // Compiling it through any uncached endpoint invalidates both.
+    get_json(&test_app.app, &format!("/v1/trades?user={}", USER)).await;
+    assert_eq!(get_json(&test_app.app, &pnl).await["tradeCount"], 2);
+    assert_eq!(get_json(&test_app.app, leaderboard).await[0]["tradeCount"], 2);
+}
+
+#[tokio::test]
+async fn test_entries_expire_after_ttl() {
+    let test_app = setup_test_app(500).await;
+    let repo = &test_app.state.repo;
+    let uri = format!("/v1/positions/history?user={}", USER);
+
+    repo.insert_fill(&fill(1, Side::Buy)).await.unwrap();
+    get_json(&test_app.app, &uri).await;
+    let cached = get_json(&test_app.app, &uri).await;
+    assert_eq!(cached["snapshots"].as_array().unwrap().len(), 1);
+
+    // Recompute from scratch so the new fill is picked up without a generation bump.
+    let user = Address::new(USER.to_string());
+    let coin = Coin::new("BTC".to_string());
+    repo.insert_fill(&fill(2, Side::Buy)).await.unwrap();
+    hypesilico::compile::Compiler::compile_incremental(repo, &user, &coin)
+        .await
+        .unwrap();
+    assert_eq!(get_json(&test_app.app, &uri).await, cached);
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let fresh = get_json(&test_app.app, &uri).await;
+    assert_eq!(fresh["snapshots"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_equivalent_queries_share_an_entry() {
+    let test_app = setup_test_app(60_000).await;
+    let repo = &test_app.state.repo;
+    let pnl = format!("/v1/pnl?user={}&fromMs=0", USER);
+    let history = format!("/v1/positions/history?user={}", USER);
+
+    repo.insert_fill(&fill(1, Side::Buy)).await.unwrap();
+    get_json(&test_app.app, &pnl).await;
+    get_json(&test_app.app, &pnl).await;
+    get_json(&test_app.app, &history).await;
+
+    // Stored without compiling, so only a fresh computation would see it.
+    repo.insert_fill(&fill(2, Side::Sell)).await.unwrap();
+    let spelled_out = format!(
+        "/v1/pnl?fromMs=0&builderOnly=false&includeSubAccounts=false&user={}",
+        USER
+    );
+    assert_eq!(get_json(&test_app.app, &spelled_out).await["tradeCount"], 1);
+    let spelled_out = format!(
+        "/v1/positions/history?includeUnrealized=false&user={}&builderOnly=false",
+        USER
+    );
+    let snapshots = get_json(&test_app.app, &spelled_out).await["snapshots"].clone();
+    assert_eq!(snapshots.as_array().unwrap().len(), 1);
+}
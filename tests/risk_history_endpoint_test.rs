@@ -0,0 +1,218 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{
+        Address, Coin, Decimal, Deposit, Fill, MarkPrice, Side, TimeMs, MARK_PRICE_BUCKET_MS,
+    },
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    setup_test_app_with(MockDataSource::new()).await
+}
+
+async fn setup_test_app_with(datasource: MockDataSource) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(datasource);
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::zero(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+fn deposit(time_ms: i64, amount: &str, tx_hash: &str) -> Deposit {
+    Deposit::new(
+        Address::new(USER.to_string()),
+        TimeMs::new(time_ms),
+        Decimal::from_str(amount).unwrap(),
+        Some(tx_hash.to_string()),
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn mark(time_ms: i64, px: &str) -> MarkPrice {
+    MarkPrice {
+        coin: Coin::new("BTC".to_string()),
+        time_ms: TimeMs::new(time_ms),
+        px: Decimal::from_str(px).unwrap(),
+    }
+}
+
+const HOUR: i64 = MARK_PRICE_BUCKET_MS;
+const DAY: i64 = 24 * HOUR;
+
+#[tokio::test]
+async fn test_risk_history_reports_daily_exposure_and_drawdown() {
+    let datasource = MockDataSource::new().with_mark_prices(vec![
+        mark(0, "100"),
+        mark(HOUR, "120"),
+        mark(DAY, "80"),
+        mark(DAY + HOUR, "90"),
+    ]);
+    let TestApp { app, repo, _temp } = setup_test_app_with(datasource).await;
+
+    repo.insert_deposits_batch(&[deposit(0, "1000", "0x01")])
+        .await
+        .unwrap();
+    // Long 1 BTC at 100 through a rally and a slump, closed at 90 on the second day.
+    repo.insert_fill(&fill(1000, Side::Buy, "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(DAY + HOUR + 5, Side::Sell, "90", "-10", 2))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/risk/history?user={}&toMs={}", USER, 2 * DAY),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let days = body["days"].as_array().unwrap();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[0]["dayStartMs"], 0);
+    assert_eq!(days[0]["peakGrossExposure"], "120");
+    assert_eq!(days[0]["grossExposure"], "120");
+    assert_eq!(days[0]["netExposure"], "120");
+    assert_eq!(days[0]["equity"], "1020");
+    assert_eq!(days[0]["maxDrawdown"], "0");
+    assert!(days[0].get("maxDrawdownPct").is_none());
+
+    assert_eq!(days[1]["dayStartMs"], DAY);
+    assert_eq!(days[1]["peakGrossExposure"], "90");
+    assert_eq!(days[1]["grossExposure"], "0");
+    assert_eq!(days[1]["equity"], "990");
+    // From the 1020 high down to 980.
+    assert_eq!(days[1]["maxDrawdown"], "40");
+
+    assert_eq!(body["peakGrossExposure"], "120");
+    assert_eq!(body["maxDrawdown"], "40");
+    assert!(body["maxDrawdownPct"].as_str().unwrap().starts_with("3.92"));
+}
+
+#[tokio::test]
+async fn test_risk_history_empty_without_activity() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = get_json(app, format!("/v1/risk/history?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["days"].as_array().unwrap().len(), 0);
+    assert_eq!(body["peakGrossExposure"], "0");
+    assert_eq!(body["maxDrawdown"], "0");
+}
+
+#[tokio::test]
+async fn test_risk_history_rejects_inverted_window() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(
+        app,
+        format!("/v1/risk/history?user={}&fromMs=2000&toMs=1000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,270 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, coin: &str, side: Side, sz: &str, px: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new(coin.to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0").unwrap(),
+        Decimal::from_str("0").unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_stats_reports_exact_order_statistics() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    // Notionals: 100, 200, 300, 400 -> p95 rank 2.85 -> 300 + 0.85 * 100 = 385
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "100", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Buy, "2", "100", 2))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, "ETH", Side::Sell, "3", "100", 3))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(4000, "ETH", Side::Sell, "4", "100", 4))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/stats?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tradeCount"], 4);
+    assert_eq!(body["volume"], "1000");
+    assert_eq!(body["medianTradeSize"], "2.5");
+    assert_eq!(body["p95Notional"], "385");
+    assert!(body.get("tainted").is_none());
+}
+
+#[tokio::test]
+async fn test_stats_empty_window_omits_order_statistics() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, body) = get_json(app, format!("/v1/stats?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tradeCount"], 0);
+    assert_eq!(body["volume"], "0");
+    assert_eq!(body["rebatesReceived"], "0");
+    assert!(body.get("medianTradeSize").is_none());
+    assert!(body.get("p95Notional").is_none());
+}
+
+#[tokio::test]
+async fn test_stats_filters_by_coin() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "100", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "ETH", Side::Buy, "5", "10", 2))
+        .await
+        .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/stats?user={}&coin=ETH", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tradeCount"], 1);
+    assert_eq!(body["medianTradeSize"], "5");
+    assert_eq!(body["p95Notional"], "50");
+}
+
+#[tokio::test]
+async fn test_stats_reports_rebates_separately() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    let with_fee = |time_ms: i64, fee: &str, tid: i64| {
+        Fill::new(
+            TimeMs::new(time_ms),
+            Address::new(USER.to_string()),
+            Coin::new("BTC".to_string()),
+            Side::Buy,
+            Decimal::from_str("100").unwrap(),
+            Decimal::from_str("1").unwrap(),
+            Decimal::from_str(fee).unwrap(),
+            Decimal::from_str("0").unwrap(),
+            None,
+            Some(tid),
+            None,
+        )
+    };
+    repo.insert_fills_batch(&[
+        with_fee(1000, "3", 1),
+        with_fee(2000, "-1.25", 2),
+        with_fee(3000, "0", 3),
+    ])
+    .await
+    .unwrap();
+
+    let (status, body) = get_json(app, format!("/v1/stats?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["feesPaid"], "1.75");
+    assert_eq!(body["rebatesReceived"], "1.25");
+}
+
+#[tokio::test]
+async fn test_stats_rejects_inverted_window() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    let (status, _) = get_json(
+        app,
+        format!("/v1/stats?user={}&fromMs=2000&toMs=1000", USER),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_stats_buckets_by_day_with_liquidity_split() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    const DAY: i64 = 86_400_000;
+
+    repo.insert_fill(&fill(1000, "BTC", Side::Buy, "1", "100", 1).with_crossed(true))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, "BTC", Side::Sell, "1", "110", 2).with_crossed(false))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(DAY + 1000, "BTC", Side::Buy, "2", "100", 3))
+        .await
+        .unwrap();
+
+    let (status, body) =
+        get_json(app.clone(), format!("/v1/stats?user={}&interval=day", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let buckets = body["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0]["startMs"], 0);
+    assert_eq!(buckets[0]["tradeCount"], 2);
+    assert_eq!(buckets[0]["volume"], "210");
+    assert_eq!(buckets[0]["takerVolume"], "100");
+    assert_eq!(buckets[0]["makerVolume"], "110");
+    assert_eq!(buckets[1]["startMs"], DAY);
+    assert_eq!(buckets[1]["volume"], "200");
+    assert_eq!(buckets[1]["takerVolume"], "0");
+
+    let (_, body) = get_json(app.clone(), format!("/v1/stats?user={}&interval=week", USER)).await;
+    let buckets = body["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0]["startMs"], -3 * DAY);
+    assert_eq!(buckets[0]["tradeCount"], 3);
+
+    let (_, body) = get_json(app.clone(), format!("/v1/stats?user={}", USER)).await;
+    assert!(body.get("buckets").is_none());
+
+    let (status, body) = get_json(app, format!("/v1/stats?user={}&interval=hour", USER)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "BAD_REQUEST");
+}
@@ -0,0 +1,273 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    repo: Arc<Repository>,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        repo,
+        _temp: temp_dir,
+    }
+}
+
+fn fill(time_ms: i64, side: Side, sz: &str, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(USER.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str(sz).unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn send(
+    app: axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let builder = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => builder
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(axum::body::Body::empty()).unwrap(),
+    };
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+/// Poll until the background relabel started by a rule change has finished.
+async fn wait_for_relabel(app: &axum::Router) {
+    for _ in 0..100 {
+        let (_, body) = send(app.clone(), "GET", "/v1/admin/strategies", None).await;
+        if body["relabelPending"] == false {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("strategy relabel did not finish");
+}
+
+/// A long closed for 10 and a short closed for 5.
+async fn insert_long_then_short(repo: &Repository) {
+    repo.insert_fill(&fill(1000, Side::Buy, "1", "100", "0", 1))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(2000, Side::Sell, "1", "110", "10", 2))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(3000, Side::Sell, "1", "110", "0", 3))
+        .await
+        .unwrap();
+    repo.insert_fill(&fill(4000, Side::Buy, "1", "105", "5", 4))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_strategy_rule_labels_compiled_lifecycles() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+    insert_long_then_short(&repo).await;
+
+    // Compile before any rule exists, so the rule has to relabel stored lifecycles.
+    let lifecycles_uri = format!("/v1/lifecycles?user={}&groupBy=strategy", USER);
+    let (status, body) = send(app.clone(), "GET", &lifecycles_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["lifecycles"][1].get("strategy").is_none());
+    assert_eq!(body["groups"].as_array().unwrap().len(), 1);
+
+    let (status, body) = send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/strategies/shorts",
+        Some(serde_json::json!({ "coins": ["BTC"], "tags": ["short"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["tags"], serde_json::json!(["short"]));
+    wait_for_relabel(&app).await;
+
+    let (_, body) = send(app.clone(), "GET", &lifecycles_uri, None).await;
+    let lifecycles = body["lifecycles"].as_array().unwrap();
+    assert!(lifecycles[0].get("strategy").is_none());
+    assert_eq!(lifecycles[1]["strategy"], "shorts");
+    let groups = body["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["strategy"], "shorts");
+    assert_eq!(groups[0]["lifecycleCount"], 1);
+    assert_eq!(groups[0]["realizedPnl"], "5");
+    assert!(groups[1].get("strategy").is_none());
+    assert_eq!(groups[1]["realizedPnl"], "10");
+
+    let (status, body) = send(
+        app.clone(),
+        "GET",
+        &format!("/v1/pnl?user={}&groupBy=strategy", USER),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["realizedPnl"], "15");
+    let groups = body["groups"].as_array().unwrap();
+    assert_eq!(groups[0]["strategy"], "shorts");
+    assert_eq!(groups[0]["realizedPnl"], "5");
+    assert_eq!(groups[0]["tradeCount"], 2);
+    assert_eq!(groups[1]["realizedPnl"], "10");
+
+    let (status, _) = send(app.clone(), "DELETE", "/v1/admin/strategies/shorts", None).await;
+    assert_eq!(status, StatusCode::OK);
+    wait_for_relabel(&app).await;
+    let (_, body) = send(app.clone(), "GET", &lifecycles_uri, None).await;
+    assert!(body["lifecycles"][1].get("strategy").is_none());
+
+    let (status, _) = send(app, "DELETE", "/v1/admin/strategies/shorts", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_strategy_rules_apply_when_compiling() {
+    let TestApp { app, repo, _temp } = setup_test_app().await;
+
+    for (name, priority, body) in [
+        ("late", 1, serde_json::json!({ "priority": 1, "fromMs": 2500 })),
+        ("all", 2, serde_json::json!({ "priority": 2 })),
+    ] {
+        let uri = format!("/v1/admin/strategies/{}", name);
+        let (status, body) = send(app.clone(), "PUT", &uri, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["priority"], priority);
+    }
+    let (_, body) = send(app.clone(), "GET", "/v1/admin/strategies", None).await;
+    assert_eq!(body["strategies"][0]["name"], "late");
+    assert_eq!(body["strategies"][1]["name"], "all");
+
+    insert_long_then_short(&repo).await;
+    let (_, body) = send(
+        app,
+        "GET",
+        &format!("/v1/lifecycles?user={}", USER),
+        None,
+    )
+    .await;
+    assert_eq!(body["lifecycles"][0]["strategy"], "all");
+    assert_eq!(body["lifecycles"][1]["strategy"], "late");
+    assert!(body.get("groups").is_none());
+}
+
+#[tokio::test]
+async fn test_strategy_rule_and_group_by_validation() {
+    let TestApp { app, _temp, .. } = setup_test_app().await;
+
+    for body in [
+        serde_json::json!({ "tags": ["spot"] }),
+        serde_json::json!({ "fromMs": 2000, "toMs": 1000 }),
+    ] {
+        let (status, _) = send(app.clone(), "PUT", "/v1/admin/strategies/bad", Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = send(
+        app.clone(),
+        "PUT",
+        "/v1/admin/strategies/no%20spaces",
+        Some(serde_json::json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    for endpoint in ["pnl", "lifecycles"] {
+        let uri = format!("/v1/{}?user={}&groupBy=coin", endpoint, USER);
+        let (status, body) = send(app.clone(), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "groupBy must be one of: strategy");
+    }
+}
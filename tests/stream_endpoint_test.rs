@@ -0,0 +1,198 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use serde_json::json;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+
+struct TestApp {
+    app: axum::Router,
+    state: api::AppState,
+    _temp: TempDir,
+}
+
+async fn setup_test_app() -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state.clone());
+
+    TestApp {
+        app,
+        state,
+        _temp: temp_dir,
+    }
+}
+
+async fn post_fills(app: axum::Router, body: serde_json::Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/fills?compile=true")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+}
+
+fn fill(time_ms: i64, side: &str, closed_pnl: &str, tid: i64) -> serde_json::Value {
+    json!({
+        "user": USER,
+        "timeMs": time_ms,
+        "coin": "BTC",
+        "side": side,
+        "px": "50000",
+        "sz": "1",
+        "fee": "1.5",
+        "closedPnl": closed_pnl,
+        "tid": tid,
+    })
+}
+
+/// Reads `(event, data)` pairs from an SSE body, skipping keep-alive comments.
+struct SseReader {
+    body: axum::body::BodyDataStream,
+    buffer: String,
+}
+
+impl SseReader {
+    async fn next(&mut self) -> Option<(String, serde_json::Value)> {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                let field = |name: &str| {
+                    frame
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(|v| v.trim_start().to_string())
+                };
+                if let (Some(event), Some(data)) = (field("event:"), field("data:")) {
+                    return Some((event, serde_json::from_str(&data).unwrap()));
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.body.next())
+                .await
+                .expect("no stream event within 5s")?
+                .unwrap();
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+async fn open_stream(app: axum::Router, uri: &str) -> (StatusCode, SseReader) {
+    let request = Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let reader = SseReader {
+        body: response.into_body().into_data_stream(),
+        buffer: String::new(),
+    };
+    (status, reader)
+}
+
+#[tokio::test]
+async fn test_stream_sends_trades_positions_and_pnl() {
+    let test_app = setup_test_app().await;
+    post_fills(test_app.app.clone(), json!([fill(1000, "buy", "0", 1)])).await;
+
+    let (status, mut events) =
+        open_stream(test_app.app.clone(), &format!("/v1/stream?user={}", USER)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (name, snapshot) = events.next().await.unwrap();
+    assert_eq!(name, "snapshot");
+    assert_eq!(snapshot["realizedPnl"], "0");
+    assert_eq!(snapshot["positions"][0]["coin"], "BTC");
+    assert_eq!(snapshot["positions"][0]["netSize"], "1");
+
+    // A push that lands before the watermark rebuilds the coin, closing the position.
+    post_fills(test_app.app.clone(), json!([fill(500, "sell", "10", 2)])).await;
+    let (name, trade) = events.next().await.unwrap();
+    assert_eq!(name, "trade");
+    assert_eq!(trade["timeMs"], 500);
+    assert_eq!(trade["side"], "sell");
+    assert_eq!(trade["closedPnl"], "10");
+
+    let (name, position) = events.next().await.unwrap();
+    assert_eq!(name, "position");
+    assert_eq!(position, json!({"coin": "BTC", "netSize": "0"}));
+
+    let (name, pnl) = events.next().await.unwrap();
+    assert_eq!(name, "pnl");
+    assert_eq!(pnl, json!({"realizedPnl": "10", "delta": "10"}));
+
+    // Shutting down ends the stream.
+    test_app.state.close_streams();
+    assert!(events.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_requires_valid_user() {
+    let test_app = setup_test_app().await;
+    let (status, _) = open_stream(test_app.app.clone(), "/v1/stream?user=0x123").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
@@ -0,0 +1,186 @@
+use axum::http::{Request, StatusCode};
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    domain::{Address, Coin, Decimal, Fill, Side, TimeMs},
+    DataSource, Repository,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tower::util::ServiceExt;
+
+const PARENT: &str = "0x00000000000000000000000000000000000000a1";
+const SUB: &str = "0x00000000000000000000000000000000000000b2";
+
+async fn setup_test_app(sub_account_rollup: bool) -> (axum::Router, MockDataSource, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let mock = MockDataSource::new()
+        .with_fills(vec![
+            fill(PARENT, 1000, Side::Buy, "100", "0", 1),
+            fill(PARENT, 3000, Side::Sell, "110", "10", 2),
+            fill(SUB, 2000, Side::Buy, "100", "0", 3),
+            fill(SUB, 3000, Side::Sell, "104", "4", 4),
+        ])
+        .with_sub_account(
+            Address::new(PARENT.to_string()),
+            Address::new(SUB.to_string()),
+        );
+    let datasource: Arc<dyn DataSource> = Arc::new(mock.clone());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo, config, orchestrator, equity_resolver);
+    (api::create_router(state), mock, temp_dir)
+}
+
+fn fill(user: &str, time_ms: i64, side: Side, px: &str, closed_pnl: &str, tid: i64) -> Fill {
+    Fill::new(
+        TimeMs::new(time_ms),
+        Address::new(user.to_string()),
+        Coin::new("BTC".to_string()),
+        side,
+        Decimal::from_str(px).unwrap(),
+        Decimal::from_str("1").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        Decimal::from_str(closed_pnl).unwrap(),
+        None,
+        Some(tid),
+        None,
+    )
+}
+
+async fn get_json(app: axum::Router, uri: String) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_include_sub_accounts_rolls_up_fills() {
+    let (app, _mock, _temp) = setup_test_app(true).await;
+
+    let (status, body) = get_json(
+        app.clone(),
+        format!("/v1/trades?user={}&includeSubAccounts=true", PARENT),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let origins: Vec<(i64, &str)> = body["trades"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["timeMs"].as_i64().unwrap(), t["user"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        origins,
+        vec![(1000, PARENT), (2000, SUB), (3000, PARENT), (3000, SUB)]
+    );
+
+    let (_, body) = get_json(
+        app.clone(),
+        format!("/v1/pnl?user={}&includeSubAccounts=true", PARENT),
+    )
+    .await;
+    assert_eq!(body["realizedPnl"], "14");
+    assert_eq!(body["tradeCount"], 4);
+
+    // Without the flag only the parent's own flow counts.
+    let (_, body) = get_json(app.clone(), format!("/v1/pnl?user={}", PARENT)).await;
+    assert_eq!(body["realizedPnl"], "10");
+
+    let (_, body) = get_json(app, format!("/v1/trades?user={}", PARENT)).await;
+    assert_eq!(body["trades"].as_array().unwrap().len(), 2);
+    assert!(body["trades"][0].get("user").is_none());
+}
+
+#[tokio::test]
+async fn test_include_sub_accounts_requires_rollup() {
+    let (app, _mock, _temp) = setup_test_app(false).await;
+
+    let (status, body) = get_json(
+        app,
+        format!("/v1/positions/history?user={}&includeSubAccounts=true", PARENT),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("SUB_ACCOUNT_ROLLUP"));
+}
+
+#[tokio::test]
+async fn test_sub_account_lists_are_reused() {
+    let (app, mock, _temp) = setup_test_app(true).await;
+
+    for _ in 0..3 {
+        let (status, body) = get_json(
+            app.clone(),
+            format!("/v1/pnl?user={}&includeSubAccounts=true", PARENT),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["tradeCount"], 4);
+    }
+    assert_eq!(mock.sub_account_fetches(), 1);
+}
@@ -1,11 +1,12 @@
 use axum::http::StatusCode;
 use hypesilico::api;
-use hypesilico::config::{BuilderAttributionMode, Config, PnlMode};
+use hypesilico::config::{BuilderAttributionMode, Config, Network, PnlMode};
 use hypesilico::datasource::MockDataSource;
 use hypesilico::db::init_db;
 use hypesilico::domain::{Address, Attribution, AttributionConfidence, Coin, Decimal, Fill, Side, TimeMs};
-use hypesilico::engine::EquityResolver;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
 use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
 use hypesilico::orchestration::orchestrator::Orchestrator;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -30,13 +31,41 @@ async fn setup_test_app(datasource: Arc<MockDataSource>) -> TestApp {
 
     let config = Config {
         port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
         database_path: db_path,
+        network: Network::Mainnet,
         hyperliquid_api_url: "http://example.invalid".to_string(),
-        target_builder: "0x0".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
         builder_attribution_mode: BuilderAttributionMode::Auto,
         pnl_mode: PnlMode::Gross,
         lookback_ms: 0,
         leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks: Default::default(),
     };
 
     let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
@@ -140,6 +169,35 @@ async fn test_trades_response_deterministic() {
     assert_eq!(b1, b2, "Responses must be byte-identical");
 }
 
+#[tokio::test]
+async fn test_trades_checksummed_and_lowercase_user_are_one_identity() {
+    let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    let lower = checksummed.to_ascii_lowercase();
+    let datasource = Arc::new(MockDataSource::new());
+    let test_app = setup_test_app(datasource).await;
+
+    // The same trade reported under both spellings is stored once.
+    test_app
+        .repo
+        .insert_fill(&fill(checksummed, "BTC", 1000, 1, 1, Side::Buy))
+        .await
+        .unwrap();
+    test_app
+        .repo
+        .insert_fill(&fill(&lower, "BTC", 1000, 1, 1, Side::Buy))
+        .await
+        .unwrap();
+
+    let (s1, b1) = request(test_app.app.clone(), &format!("/v1/trades?user={}", checksummed)).await;
+    let (s2, b2) = request(test_app.app, &format!("/v1/trades?user={}", lower)).await;
+    assert_eq!(s1, StatusCode::OK);
+    assert_eq!(s2, StatusCode::OK);
+    assert_eq!(b1, b2);
+
+    let json: serde_json::Value = serde_json::from_slice(&b1).unwrap();
+    assert_eq!(json["trades"].as_array().unwrap().len(), 1);
+}
+
 #[tokio::test]
 async fn test_builder_only_filters_non_builder_fills_and_sets_tainted() {
     let user = "0x1111111111111111111111111111111111111111";
@@ -222,3 +280,52 @@ async fn test_trades_rejects_invalid_user() {
     let (status, _body) = request(test_app.app, "/v1/trades?user=not-an-address").await;
     assert_eq!(status, StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_trades_filters_by_dex() {
+    let user = "0x1111111111111111111111111111111111111111";
+    let datasource = Arc::new(MockDataSource::new());
+    let test_app = setup_test_app(datasource).await;
+
+    for (i, coin) in ["BTC", "xyz:BTC", "xyz:TSLA"].into_iter().enumerate() {
+        let i = i as i64 + 1;
+        test_app
+            .repo
+            .insert_fill(&fill(user, coin, 1000 * i, i, i, Side::Buy))
+            .await
+            .unwrap();
+    }
+
+    let coins = |uri: String| {
+        let app = test_app.app.clone();
+        async move {
+            let (status, body) = request(app, &uri).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            json["trades"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["coin"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Dex-qualified coins stay distinct from the default dex's coin of the same name.
+    assert_eq!(
+        coins(format!("/v1/trades?user={}", user)).await,
+        vec!["BTC", "xyz:BTC", "xyz:TSLA"]
+    );
+    assert_eq!(coins(format!("/v1/trades?user={}&dex=XYZ", user)).await, vec!["xyz:BTC", "xyz:TSLA"]);
+    assert_eq!(coins(format!("/v1/trades?user={}&dex=", user)).await, vec!["BTC"]);
+    assert_eq!(
+        coins(format!("/v1/trades?user={}&coin=xyz:BTC&dex=xyz", user)).await,
+        vec!["xyz:BTC"]
+    );
+
+    let (status, _) =
+        request(test_app.app.clone(), &format!("/v1/trades?user={}&coin=BTC&dex=xyz", user)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = request(test_app.app, &format!("/v1/trades?user={}&dex=a:b", user)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
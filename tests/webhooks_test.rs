@@ -0,0 +1,192 @@
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::routing::post;
+use hypesilico::datasource::MockDataSource;
+use hypesilico::engine::{EquityResolver, HeuristicAttributor, MatchTolerances};
+use hypesilico::orchestration::ensure::Ingestor;
+use hypesilico::orchestration::jobs::JobLimits;
+use hypesilico::orchestration::orchestrator::Orchestrator;
+use hypesilico::orchestration::webhooks::{signature_header, WebhookConfig, WebhookEventKind};
+use hypesilico::{
+    api,
+    config::{BuilderAttributionMode, Config, Network, PnlMode},
+    db::init_db,
+    DataSource, Repository,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use tower::util::ServiceExt;
+
+const USER: &str = "0x0000000000000000000000000000000000000123";
+const SECRET: &str = "webhook-secret";
+
+struct TestApp {
+    app: axum::Router,
+    _temp: TempDir,
+}
+
+async fn setup_test_app(webhooks: WebhookConfig) -> TestApp {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_string_lossy()
+        .to_string();
+    let pool = init_db(&db_path).await.expect("init_db failed");
+
+    let repo = Arc::new(Repository::new(pool));
+    let datasource: Arc<dyn DataSource> = Arc::new(MockDataSource::new());
+    let config = Config {
+        port: 0,
+        bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        admin_port: None,
+        admin_bind_addr: std::net::Ipv4Addr::LOCALHOST.into(),
+        database_path: db_path,
+        network: Network::Mainnet,
+        hyperliquid_api_url: "http://example.invalid".to_string(),
+        hyperliquid_ws_url: None,
+        target_builder: "0x0000000000000000000000000000000000000000".to_string(),
+        builder_attribution_mode: BuilderAttributionMode::Auto,
+        pnl_mode: PnlMode::Gross,
+        lookback_ms: 0,
+        leaderboard_users: vec![],
+        match_tolerances: MatchTolerances::default(),
+        heuristic_attribution: HeuristicAttributor::default(),
+        job_limits: JobLimits::default(),
+        builder_logs_auto_backfill: false,
+        builder_logs_max_concurrent_downloads: 4,
+        api_keys: Vec::new(),
+        response_cache_ttl_ms: 0,
+        sub_account_rollup: false,
+        ingest_chunk_ms: 86_400_000,
+        ingest_max_concurrent_chunks: 4,
+        record_raw_payloads: false,
+        maintenance: Default::default(),
+        db_pool: Default::default(),
+        idempotency_ttl_ms: 0,
+        leaderboard_snapshot_interval_ms: 0,
+        equity_sample_interval_ms: 0,
+        response_compression: true,
+        max_request_body_bytes: 2 * 1024 * 1024,
+        startup_integrity_check: Default::default(),
+        read_only: false,
+        coin_meta_refresh_interval_ms: 0,
+        overflow_policy: Default::default(),
+        webhooks,
+    };
+
+    let ingestor = Ingestor::new(datasource, repo.clone(), config.clone());
+    let orchestrator = Arc::new(Orchestrator::new(ingestor, repo.clone()));
+    let equity_resolver = Arc::new(EquityResolver::new(repo.clone()));
+    let state = api::AppState::new(repo.clone(), config, orchestrator, equity_resolver);
+    let app = api::create_router(state);
+
+    TestApp {
+        app,
+        _temp: temp_dir,
+    }
+}
+
+/// Start a receiver that forwards each delivery's headers and body, and return its URL.
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<(HeaderMap, String)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: String| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send((headers, body));
+                StatusCode::NO_CONTENT
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{}/hook", addr), rx)
+}
+
+async fn post_fills(app: axum::Router, body: serde_json::Value) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/ingest/fills?compile=true")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    app.oneshot(request).await.unwrap().status()
+}
+
+fn fill(time_ms: i64, side: &str, closed_pnl: &str, tid: i64) -> serde_json::Value {
+    json!({
+        "user": USER,
+        "timeMs": time_ms,
+        "coin": "BTC",
+        "side": side,
+        "px": "50000",
+        "sz": "1",
+        "fee": "1.5",
+        "closedPnl": closed_pnl,
+        "tid": tid,
+    })
+}
+
+#[tokio::test]
+async fn test_pushed_fills_send_signed_webhooks() {
+    let (url, mut deliveries) = spawn_receiver().await;
+    let test_app = setup_test_app(WebhookConfig {
+        urls: vec![url],
+        secret: Some(SECRET.to_string()),
+        events: vec![
+            WebhookEventKind::FillsIngested,
+            WebhookEventKind::CompileCompleted,
+        ],
+    })
+    .await;
+
+    let body = json!([fill(1000, "buy", "0", 1), fill(2000, "sell", "10", 2)]);
+    assert_eq!(post_fills(test_app.app.clone(), body).await, StatusCode::OK);
+
+    let mut events = Vec::new();
+    for _ in 0..2 {
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(5), deliveries.recv())
+            .await
+            .expect("webhook was not delivered")
+            .unwrap();
+        let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+
+        let signature = header("x-hypesilico-signature");
+        let time_ms: i64 = signature
+            .strip_prefix("t=")
+            .and_then(|s| s.split(',').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(signature, signature_header(SECRET, time_ms, body.as_bytes()));
+
+        let event: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(event["type"], header("x-hypesilico-event").as_str());
+        assert_eq!(event["id"], header("x-hypesilico-delivery").as_str());
+        assert_eq!(event["timeMs"], time_ms);
+        events.push(event);
+    }
+    events.sort_by_key(|e| e["type"].as_str().unwrap().to_string());
+
+    assert_eq!(events[0]["type"], "compile.completed");
+    assert_eq!(events[0]["data"], json!({"user": USER, "coins": ["BTC"]}));
+    assert_eq!(events[1]["type"], "fills.ingested");
+    assert_eq!(
+        events[1]["data"],
+        json!({"user": USER, "fillsNew": 2, "fromMs": 1000, "toMs": 2000, "source": "push"})
+    );
+
+    // Already stored fills are not new, so nothing is sent.
+    let body = json!([fill(1000, "buy", "0", 1)]);
+    assert_eq!(post_fills(test_app.app.clone(), body).await, StatusCode::OK);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), deliveries.recv())
+            .await
+            .is_err()
+    );
+}